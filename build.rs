@@ -0,0 +1,13 @@
+// Generate the gRPC service of proto/manifest_producer.proto, with the `grpc` feature. The
+// schema is compiled by protox, so that no protoc is needed.
+fn main() {
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/manifest_producer.proto");
+        let descriptors = protox::compile(["proto/manifest_producer.proto"], ["proto"])
+            .expect("the gRPC schema could not be compiled");
+        tonic_build::configure()
+            .compile_fds(descriptors)
+            .expect("the gRPC service could not be generated");
+    }
+}
@@ -0,0 +1,81 @@
+#ifndef MANIFEST_PRODUCER_H
+#define MANIFEST_PRODUCER_H
+
+/* Generated by cbindgen from ffi/src/lib.rs, do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// The call succeeded.
+#define MP_OK 0
+
+// The error belongs to no other class, or the call panicked.
+#define MP_ERROR 2
+
+// The binary has no debug information.
+#define MP_ERROR_STRIPPED 3
+
+// The binary is of an unsupported or unexpected architecture.
+#define MP_ERROR_UNSUPPORTED_ARCH 4
+
+// None of the APIs of the list was found in the binary.
+#define MP_ERROR_NO_API 5
+
+// A file could not be read or written.
+#define MP_ERROR_IO 6
+
+// An argument, the options, the API list or a manifest is malformed.
+#define MP_ERROR_INVALID_INPUT 7
+
+// The binary is not a well-formed ELF file.
+#define MP_ERROR_INVALID_ELF 8
+
+// The code of the binary looks compressed or encrypted.
+#define MP_ERROR_PACKED 9
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Return the version of the library, as a static NUL-terminated string.
+const char *mp_version(void);
+
+// Analyze an ELF binary and return its manifests.
+//
+// `options` is a JSON document with the keys of the configuration file, e.g.
+// `{"apis": ["writeOnDrive"], "outputs": ["sarif"], "output_dir": "manifests"}`; relative paths
+// are taken from the current directory. The manifests are also written to `output_dir` when it
+// is given. On success, `out_json` receives a document holding each manifest by name
+// (`basic_info`, `flow_call`, `feature_manifest`, ...).
+//
+// # Safety
+//
+// `path` and `options` must be NUL-terminated strings, `options` may be NULL, and `out_json`
+// must point to a writable `char *`.
+int mp_analyze(const char *path, const char *options, char **out_json);
+
+// Validate a manifest against the JSON schema of its kind and declared version.
+//
+// `kind` is the name of the manifest, e.g. `flow_call`. On success, `out_json` receives the
+// array of the violations found, empty when the manifest is valid.
+//
+// # Safety
+//
+// `kind` and `manifest` must be NUL-terminated strings, and `out_json` must point to a
+// writable `char *`.
+int mp_validate(const char *kind, const char *manifest, char **out_json);
+
+// Release a string returned in `out_json`. Does nothing on NULL.
+//
+// # Safety
+//
+// `string` must be NULL or a string returned by this library, released only once.
+void mp_string_free(char *string);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* MANIFEST_PRODUCER_H */
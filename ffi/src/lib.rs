@@ -0,0 +1,295 @@
+//! C-compatible interface of manifest-producer.
+//!
+//! Every function returns a status code, [`MP_OK`] or the exit status of the class of the error
+//! (see `manifest_producer::error`), and writes its result to `out_json` as a JSON string to be
+//! released with [`mp_string_free`]. On error, `out_json` receives the description of the error,
+//! `{"error": ..., "message": ..., "causes": [...], "exit_code": ...}`.
+//!
+//! The C header, `include/manifest_producer.h`, is generated by cbindgen and committed; after
+//! changing the exported functions, regenerate it from this directory with
+//! `cbindgen --config cbindgen.toml --output include/manifest_producer.h`.
+
+use std::{
+    env,
+    ffi::{c_char, c_int, CStr, CString},
+    fs,
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    process, ptr,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use manifest_producer::{
+    analyzer::{Analyzer, Options},
+    api_detection::parse_api_list,
+    config::Config,
+    error::{Error, Result},
+    manifest_creation::collect_manifests,
+    progress::Verbosity,
+    schema::{validate, ManifestKind},
+};
+use serde_json::{json, Value};
+
+/// The call succeeded.
+pub const MP_OK: c_int = 0;
+/// The error belongs to no other class, or the call panicked.
+pub const MP_ERROR: c_int = 2;
+/// The binary has no debug information.
+pub const MP_ERROR_STRIPPED: c_int = 3;
+/// The binary is of an unsupported or unexpected architecture.
+pub const MP_ERROR_UNSUPPORTED_ARCH: c_int = 4;
+/// None of the APIs of the list was found in the binary.
+pub const MP_ERROR_NO_API: c_int = 5;
+/// A file could not be read or written.
+pub const MP_ERROR_IO: c_int = 6;
+/// An argument, the options, the API list or a manifest is malformed.
+pub const MP_ERROR_INVALID_INPUT: c_int = 7;
+/// The binary is not a well-formed ELF file.
+pub const MP_ERROR_INVALID_ELF: c_int = 8;
+/// The code of the binary looks compressed or encrypted.
+pub const MP_ERROR_PACKED: c_int = 9;
+
+// Number of analyses run by the process, to name their scratch directories.
+static ANALYSES: AtomicUsize = AtomicUsize::new(0);
+
+/// Return the version of the library, as a static NUL-terminated string.
+#[no_mangle]
+pub extern "C" fn mp_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast()
+}
+
+/// Analyze an ELF binary and return its manifests.
+///
+/// `options` is a JSON document with the keys of the configuration file, e.g.
+/// `{"apis": ["writeOnDrive"], "outputs": ["sarif"], "output_dir": "manifests"}`; relative paths
+/// are taken from the current directory. The manifests are also written to `output_dir` when it
+/// is given. On success, `out_json` receives a document holding each manifest by name
+/// (`basic_info`, `flow_call`, `feature_manifest`, ...).
+///
+/// # Safety
+///
+/// `path` and `options` must be NUL-terminated strings, `options` may be NULL, and `out_json`
+/// must point to a writable `char *`.
+#[no_mangle]
+pub unsafe extern "C" fn mp_analyze(
+    path: *const c_char,
+    options: *const c_char,
+    out_json: *mut *mut c_char,
+) -> c_int {
+    guarded(out_json, || {
+        let path = read_str(path, "path")?;
+        let options = match options.is_null() {
+            true => json!({}),
+            false => serde_json::from_str(read_str(options, "options")?)?,
+        };
+        analyze(path, &options)
+    })
+}
+
+/// Validate a manifest against the JSON schema of its kind and declared version.
+///
+/// `kind` is the name of the manifest, e.g. `flow_call`. On success, `out_json` receives the
+/// array of the violations found, empty when the manifest is valid.
+///
+/// # Safety
+///
+/// `kind` and `manifest` must be NUL-terminated strings, and `out_json` must point to a
+/// writable `char *`.
+#[no_mangle]
+pub unsafe extern "C" fn mp_validate(
+    kind: *const c_char,
+    manifest: *const c_char,
+    out_json: *mut *mut c_char,
+) -> c_int {
+    guarded(out_json, || {
+        let kind: ManifestKind = read_str(kind, "kind")?.parse()?;
+        let manifest: Value = serde_json::from_str(read_str(manifest, "manifest")?)?;
+        Ok(json!(validate(kind, &manifest)?))
+    })
+}
+
+/// Release a string returned in `out_json`. Does nothing on NULL.
+///
+/// # Safety
+///
+/// `string` must be NULL or a string returned by this library, released only once.
+#[no_mangle]
+pub unsafe extern "C" fn mp_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+// Run a call, writing its result or its error to `out_json`, and return its status.
+unsafe fn guarded(out_json: *mut *mut c_char, call: impl FnOnce() -> Result<Value>) -> c_int {
+    if out_json.is_null() {
+        return MP_ERROR_INVALID_INPUT;
+    }
+    let (status, document) = match panic::catch_unwind(AssertUnwindSafe(call)) {
+        Ok(Ok(document)) => (MP_OK, document),
+        Ok(Err(error)) => (error.exit_code(), error.to_json()),
+        Err(_) => (
+            MP_ERROR,
+            json!({
+                "error": "panic",
+                "message": "the analysis panicked",
+                "causes": [],
+                "exit_code": MP_ERROR,
+            }),
+        ),
+    };
+    // A JSON document holds no NUL byte: they are escaped in its strings.
+    *out_json = CString::new(document.to_string()).map_or(ptr::null_mut(), CString::into_raw);
+    status
+}
+
+// Read a NUL-terminated UTF-8 argument.
+unsafe fn read_str<'a>(string: *const c_char, name: &str) -> Result<&'a str> {
+    if string.is_null() {
+        return Err(Error::InvalidConfig(format!("{} is NULL", name)));
+    }
+    CStr::from_ptr(string)
+        .to_str()
+        .map_err(|_| Error::InvalidConfig(format!("{} is not valid UTF-8", name)))
+}
+
+// Analyze a binary with the options of a JSON configuration, returning its manifests.
+fn analyze(path: &str, options: &Value) -> Result<Value> {
+    let config = Config::from_json(options, Path::new(""))?;
+    let specs = match (&config.api_list, &config.apis) {
+        (Some(api_list), _) => {
+            parse_api_list(&serde_json::from_str(&fs::read_to_string(api_list)?)?)?
+        }
+        (None, Some(apis)) => apis.clone(),
+        (None, None) => {
+            return Err(Error::InvalidApiList(
+                "the options give no apis nor api_list".to_string(),
+            ))
+        }
+    };
+    let options = Options {
+        verbosity: Verbosity::Quiet,
+        ..Options::from_config(&config)?
+    };
+    // Without an output directory, the manifests are written to a scratch one.
+    let (output_dir, scratch) = match &config.output_dir {
+        Some(output_dir) => (output_dir.clone(), false),
+        None => {
+            let dir = env::temp_dir().join(format!(
+                "manifest-ffi-{}-{}",
+                process::id(),
+                ANALYSES.fetch_add(1, Ordering::Relaxed)
+            ));
+            (dir.to_string_lossy().to_string(), true)
+        }
+    };
+    fs::create_dir_all(&output_dir)?;
+    let analyzed = Analyzer::new(path)
+        .with_api_list(specs)
+        .with_options(options)
+        .run(&output_dir)
+        .and_then(|_| collect_manifests(&output_dir));
+    if scratch {
+        fs::remove_dir_all(&output_dir)?;
+    }
+    Ok(json!(analyzed?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use manifest_producer::error::{
+        EXIT_ERROR, EXIT_INVALID_ELF, EXIT_INVALID_INPUT, EXIT_IO, EXIT_NO_API, EXIT_PACKED,
+        EXIT_STRIPPED, EXIT_UNSUPPORTED_ARCH,
+    };
+
+    // Call `mp_analyze` and read back its status and output.
+    fn call(path: &str, options: &str) -> (c_int, Value) {
+        let (path, options) = (CString::new(path).unwrap(), CString::new(options).unwrap());
+        let mut out = ptr::null_mut();
+        let status = unsafe { mp_analyze(path.as_ptr(), options.as_ptr(), &mut out) };
+        let output = unsafe { CStr::from_ptr(out) }.to_str().unwrap().to_string();
+        unsafe { mp_string_free(out) };
+        (status, serde_json::from_str(&output).unwrap())
+    }
+
+    #[test]
+    fn test_mp_analyze() {
+        let binary = "../tests/elf_file/fake-firmware-c-dynamic";
+        let (status, manifests) = call(binary, r#"{"apis": ["writeOnDrive"]}"#);
+        assert_eq!(status, MP_OK);
+        assert_eq!(
+            manifests["flow_call"]["Public APIs flow"][0]["name"],
+            "writeOnDrive"
+        );
+        assert!(manifests.get("basic_info").is_some());
+
+        let (status, error) = call(binary, r#"{"apis": ["notAnApi"]}"#);
+        assert_eq!(status, MP_ERROR_NO_API);
+        assert_eq!(error["error"], "no-api-found");
+        let (status, _) = call(binary, r#"{"api": []}"#);
+        assert_eq!(status, MP_ERROR_INVALID_INPUT);
+        let (status, _) = call("../tests/elf_file/missing", r#"{"apis": ["main"]}"#);
+        assert_eq!(status, MP_ERROR_IO);
+
+        let kind = CString::new("flow_call").unwrap();
+        let manifest = CString::new(manifests["flow_call"].to_string()).unwrap();
+        let mut out = ptr::null_mut();
+        assert_eq!(
+            unsafe { mp_validate(kind.as_ptr(), manifest.as_ptr(), &mut out) },
+            MP_OK
+        );
+        assert_eq!(unsafe { CStr::from_ptr(out) }.to_bytes(), b"[]");
+        unsafe { mp_string_free(out) };
+        assert_eq!(
+            unsafe { mp_validate(kind.as_ptr(), manifest.as_ptr(), ptr::null_mut()) },
+            MP_ERROR_INVALID_INPUT
+        );
+    }
+
+    #[test]
+    fn test_header_up_to_date() {
+        let crate_dir = env!("CARGO_MANIFEST_DIR");
+        let config = cbindgen::Config::from_file(format!("{}/cbindgen.toml", crate_dir)).unwrap();
+        let mut header = Vec::new();
+        cbindgen::Builder::new()
+            .with_crate(crate_dir)
+            .with_config(config)
+            .generate()
+            .unwrap()
+            .write(&mut header);
+        let committed = fs::read(format!("{}/include/manifest_producer.h", crate_dir)).unwrap();
+        assert!(
+            header == committed,
+            "include/manifest_producer.h is out of date, regenerate it with cbindgen"
+        );
+    }
+
+    #[test]
+    fn test_status_codes() {
+        assert_eq!(
+            [
+                MP_ERROR,
+                MP_ERROR_STRIPPED,
+                MP_ERROR_UNSUPPORTED_ARCH,
+                MP_ERROR_NO_API,
+                MP_ERROR_IO,
+                MP_ERROR_INVALID_INPUT,
+                MP_ERROR_INVALID_ELF,
+                MP_ERROR_PACKED
+            ],
+            [
+                EXIT_ERROR,
+                EXIT_STRIPPED,
+                EXIT_UNSUPPORTED_ARCH,
+                EXIT_NO_API,
+                EXIT_IO,
+                EXIT_INVALID_INPUT,
+                EXIT_INVALID_ELF,
+                EXIT_PACKED
+            ]
+        );
+        let version = unsafe { CStr::from_ptr(mp_version()) };
+        assert_eq!(version.to_str().unwrap(), env!("CARGO_PKG_VERSION"));
+    }
+}
@@ -0,0 +1,437 @@
+//! Python bindings of manifest-producer, built into the `manifest_producer` module with maturin.
+//!
+//! ```python
+//! import manifest_producer as mp
+//!
+//! analysis = mp.analyze("firmware.elf", ["writeOnDrive"], max_depth=8)
+//! for api in analysis.apis:
+//!     print(api.name, api.syscalls, [c.capability for c in api.capabilities])
+//! print(mp.diff("manifests/v1", "manifests/v2"))
+//! ```
+
+use std::{
+    env, fs,
+    path::Path,
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use pyo3::{
+    create_exception,
+    exceptions::PyException,
+    prelude::*,
+    types::{PyDict, PyModule},
+};
+use serde_json::Value;
+
+use manifest_producer::{
+    analyzer::{self, Analyzer, Options},
+    api_detection::{parse_api_list, ApiSpec},
+    capabilities::ApiCapability,
+    config::Config,
+    diff::{diff_manifests, ManifestDiff},
+    elf_utils::API,
+    error::{Error, Result},
+    progress::Verbosity,
+};
+
+create_exception!(
+    manifest_producer,
+    ManifestProducerError,
+    PyException,
+    "An analysis failed; `args` holds the message and the kind of the error, e.g. `no-api-found`."
+);
+
+// Number of binaries analyzed by `diff`, to name their scratch directories.
+static ANALYSES: AtomicUsize = AtomicUsize::new(0);
+
+/// A capability of the flow of an API, with the evidence of the rule that matched.
+#[pyclass(module = "manifest_producer", get_all, frozen)]
+#[derive(Clone)]
+pub struct Capability {
+    /// The label of the capability.
+    pub capability: String,
+    /// The functions called and strings used by the API that matched the rule.
+    pub evidence: Vec<String>,
+}
+
+#[pymethods]
+impl Capability {
+    fn __repr__(&self) -> String {
+        format!("Capability({:?})", self.capability)
+    }
+}
+
+impl From<&ApiCapability> for Capability {
+    fn from(capability: &ApiCapability) -> Self {
+        Self {
+            capability: capability.capability.clone(),
+            evidence: capability.evidence.clone(),
+        }
+    }
+}
+
+/// An API found in the binary, with its call flow and capabilities.
+#[pyclass(module = "manifest_producer", get_all, frozen)]
+#[derive(Clone)]
+pub struct Api {
+    /// The name of the API.
+    pub name: String,
+    /// The starting address of the API.
+    pub start_addr: u64,
+    /// The ending address of the API.
+    pub end_addr: u64,
+    /// The functions called by the API, in order.
+    pub syscalls: Vec<String>,
+    /// The functions reachable from the API.
+    pub transitive_calls: Vec<String>,
+    /// The indirect calls reachable from the API whose target could not be determined.
+    pub unresolved_calls: Vec<String>,
+    /// The functions reachable from the API that take part in a recursion cycle.
+    pub recursive_functions: Vec<String>,
+    /// Whether the flow was cut by the maximum traversal depth.
+    pub depth_truncated: bool,
+    /// The functions reachable from the API skipped for going over the resource budget.
+    pub skipped_functions: Vec<String>,
+    /// The strings referenced by the code reachable from the API.
+    pub strings: Vec<String>,
+    /// The cryptographic algorithms used by the code reachable from the API.
+    pub crypto: Vec<String>,
+    /// The request codes of the `ioctl` calls reachable from the API.
+    pub ioctls: Vec<u64>,
+    /// The capabilities of the flow of the API.
+    pub capabilities: Vec<Capability>,
+}
+
+#[pymethods]
+impl Api {
+    fn __repr__(&self) -> String {
+        format!("Api({:?}, start_addr={:#x})", self.name, self.start_addr)
+    }
+}
+
+impl From<&API> for Api {
+    fn from(api: &API) -> Self {
+        Self {
+            name: api.name.clone(),
+            start_addr: api.start_addr,
+            end_addr: api.end_addr,
+            syscalls: api.syscalls.clone(),
+            transitive_calls: api.transitive_calls.clone(),
+            unresolved_calls: api.unresolved_calls.clone(),
+            recursive_functions: api.recursive_functions.clone(),
+            depth_truncated: api.depth_truncated,
+            skipped_functions: api.skipped_functions.clone(),
+            strings: api
+                .strings
+                .iter()
+                .map(|string| string.value.clone())
+                .collect(),
+            crypto: api.crypto.clone(),
+            ioctls: api.ioctls.clone(),
+            capabilities: api.capabilities.iter().map(Capability::from).collect(),
+        }
+    }
+}
+
+/// The results of [`analyze`].
+#[pyclass(module = "manifest_producer", get_all, frozen)]
+pub struct Analysis {
+    /// The path of the binary analyzed.
+    pub path: String,
+    /// The programming language of the binary, e.g. `C99`.
+    pub language: String,
+    /// The APIs found, with their flows and capabilities.
+    pub apis: Vec<Api>,
+    /// The number of functions of the call graph of the binary.
+    pub functions: usize,
+}
+
+#[pymethods]
+impl Analysis {
+    /// Return the API of the given name, `None` when it was not found.
+    fn api(&self, name: &str) -> Option<Api> {
+        self.apis.iter().find(|api| api.name == name).cloned()
+    }
+
+    /// Return the labels of the capabilities of every API, sorted.
+    fn capabilities(&self) -> Vec<String> {
+        let mut capabilities: Vec<String> = self
+            .apis
+            .iter()
+            .flat_map(|api| api.capabilities.iter().map(|c| c.capability.clone()))
+            .collect();
+        capabilities.sort();
+        capabilities.dedup();
+        capabilities
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Analysis({:?}, {} APIs)", self.path, self.apis.len())
+    }
+}
+
+impl From<&analyzer::Analysis> for Analysis {
+    fn from(analysis: &analyzer::Analysis) -> Self {
+        Self {
+            path: analysis.binary.path.clone(),
+            language: analysis.binary.language.clone(),
+            apis: analysis.apis.iter().map(Api::from).collect(),
+            functions: analysis.graph.functions().count(),
+        }
+    }
+}
+
+/// The change of the calls of an API between two versions.
+#[pyclass(module = "manifest_producer", get_all, frozen)]
+#[derive(Clone)]
+pub struct FlowChange {
+    /// The name of the API.
+    pub api: String,
+    /// The functions called or reached only in the new version.
+    pub added_calls: Vec<String>,
+    /// The functions called or reached only in the old version.
+    pub removed_calls: Vec<String>,
+    /// Whether the API calls the same functions in a different order.
+    pub reordered: bool,
+}
+
+impl From<&manifest_producer::diff::FlowChange> for FlowChange {
+    fn from(change: &manifest_producer::diff::FlowChange) -> Self {
+        Self {
+            api: change.api.clone(),
+            added_calls: change.added_calls.clone(),
+            removed_calls: change.removed_calls.clone(),
+            reordered: change.reordered,
+        }
+    }
+}
+
+/// The behavioral differences between two versions, see [`diff`].
+#[pyclass(module = "manifest_producer", frozen)]
+pub struct Diff {
+    /// The APIs found only in the new version.
+    #[pyo3(get)]
+    pub added_apis: Vec<String>,
+    /// The APIs found only in the old version.
+    #[pyo3(get)]
+    pub removed_apis: Vec<String>,
+    /// The APIs whose calls changed.
+    #[pyo3(get)]
+    pub changed_flows: Vec<FlowChange>,
+    /// The functions called by no API of the old version and by some of the new one.
+    #[pyo3(get)]
+    pub new_syscalls: Vec<String>,
+    /// The capabilities gained, as (API, capability).
+    #[pyo3(get)]
+    pub new_capabilities: Vec<(String, String)>,
+    /// The capabilities lost, as (API, capability).
+    #[pyo3(get)]
+    pub removed_capabilities: Vec<(String, String)>,
+    // The description of the differences, as text and as JSON.
+    text: String,
+    json: String,
+}
+
+#[pymethods]
+impl Diff {
+    /// Return whether the two versions behave the same.
+    fn is_empty(&self) -> bool {
+        self.added_apis.is_empty()
+            && self.removed_apis.is_empty()
+            && self.changed_flows.is_empty()
+            && self.new_syscalls.is_empty()
+            && self.new_capabilities.is_empty()
+            && self.removed_capabilities.is_empty()
+    }
+
+    /// Describe the differences as a JSON document, as `manifest-producer diff --json` does.
+    fn to_json(&self) -> String {
+        self.json.clone()
+    }
+
+    fn __str__(&self) -> String {
+        self.text.clone()
+    }
+}
+
+impl From<&ManifestDiff> for Diff {
+    fn from(diff: &ManifestDiff) -> Self {
+        Self {
+            added_apis: diff.added_apis.clone(),
+            removed_apis: diff.removed_apis.clone(),
+            changed_flows: diff.changed_flows.iter().map(FlowChange::from).collect(),
+            new_syscalls: diff.new_syscalls.clone(),
+            new_capabilities: diff.new_capabilities.clone(),
+            removed_capabilities: diff.removed_capabilities.clone(),
+            text: diff.to_string(),
+            json: diff.to_json().to_string(),
+        }
+    }
+}
+
+// Raise an error of the analysis as a `ManifestProducerError`.
+fn py_error(error: Error) -> PyErr {
+    ManifestProducerError::new_err((error.to_string(), error.kind()))
+}
+
+/// Analyze an ELF binary.
+///
+/// The keyword arguments are the keys of the configuration file, e.g. `max_depth=8` or
+/// `capability_rules="rules.toml"`; the manifests are written only when `output_dir` is given.
+#[pyfunction]
+#[pyo3(signature = (path, apis = None, **options))]
+fn analyze(
+    py: Python<'_>,
+    path: &str,
+    apis: Option<Vec<String>>,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<Analysis> {
+    let mut config = read_options(py, options).map_err(py_error)?;
+    if let Some(apis) = apis {
+        config.apis = Some(apis.iter().map(|name| ApiSpec::new(name)).collect());
+    }
+    let specs = api_list(&config).map_err(py_error)?;
+    let options = Options {
+        verbosity: Verbosity::Quiet,
+        ..Options::from_config(&config).map_err(py_error)?
+    };
+    let analyzer = Analyzer::new(path)
+        .with_api_list(specs)
+        .with_options(options);
+    // The GIL is released during the analysis, which may take minutes.
+    let analysis = py
+        .allow_threads(|| match &config.output_dir {
+            Some(output_dir) => {
+                fs::create_dir_all(output_dir)?;
+                analyzer.run(output_dir)
+            }
+            None => analyzer.analyze(),
+        })
+        .map_err(py_error)?;
+    Ok(Analysis::from(&analysis))
+}
+
+/// Compare two versions of a firmware, each given by the directory of its manifests or by its
+/// ELF binary, which is then analyzed with `apis`.
+#[pyfunction]
+#[pyo3(signature = (old, new, apis = None))]
+fn diff(py: Python<'_>, old: &str, new: &str, apis: Option<Vec<String>>) -> PyResult<Diff> {
+    let specs: Vec<ApiSpec> = apis
+        .unwrap_or_default()
+        .iter()
+        .map(|name| ApiSpec::new(name))
+        .collect();
+    let diff = py
+        .allow_threads(|| -> Result<ManifestDiff> {
+            let (mut dirs, mut analyzed) = (Vec::new(), Vec::new());
+            for path in [old, new] {
+                if Path::new(path).is_dir() {
+                    dirs.push(path.to_string());
+                    continue;
+                }
+                if specs.is_empty() {
+                    return Err(Error::InvalidApiList(
+                        "comparing ELF files requires apis".to_string(),
+                    ));
+                }
+                let dir = env::temp_dir().join(format!(
+                    "manifest-python-{}-{}",
+                    process::id(),
+                    ANALYSES.fetch_add(1, Ordering::Relaxed)
+                ));
+                let dir = dir.to_string_lossy().to_string();
+                analyzed.push(dir.clone());
+                fs::create_dir_all(&dir)?;
+                Analyzer::new(path)
+                    .with_api_list(specs.clone())
+                    .with_verbosity(Verbosity::Quiet)
+                    .run(&dir)?;
+                dirs.push(dir);
+            }
+            let diff = diff_manifests(&dirs[0], &dirs[1]);
+            for dir in analyzed {
+                fs::remove_dir_all(dir)?;
+            }
+            diff
+        })
+        .map_err(py_error)?;
+    Ok(Diff::from(&diff))
+}
+
+// Read the keyword arguments of `analyze` as a configuration, through their JSON encoding.
+fn read_options(py: Python<'_>, options: Option<&Bound<'_, PyDict>>) -> Result<Config> {
+    let options = match options {
+        Some(options) => py
+            .import("json")
+            .and_then(|json| json.call_method1("dumps", (options,)))
+            .and_then(|document| document.extract::<String>())
+            .map_err(|error| Error::InvalidConfig(error.to_string()))?,
+        None => "{}".to_string(),
+    };
+    let options: Value = serde_json::from_str(&options)?;
+    Config::from_json(&options, Path::new(""))
+}
+
+// Read the API list of a configuration.
+fn api_list(config: &Config) -> Result<Vec<ApiSpec>> {
+    match (&config.api_list, &config.apis) {
+        (Some(api_list), _) => {
+            parse_api_list(&serde_json::from_str(&fs::read_to_string(api_list)?)?)
+        }
+        (None, Some(apis)) => Ok(apis.clone()),
+        (None, None) => Err(Error::InvalidApiList(
+            "analyze requires apis or api_list".to_string(),
+        )),
+    }
+}
+
+/// The `manifest_producer` Python module.
+#[pymodule]
+#[pyo3(name = "manifest_producer")]
+fn python_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    m.add(
+        "ManifestProducerError",
+        m.py().get_type::<ManifestProducerError>(),
+    )?;
+    m.add_class::<Analysis>()?;
+    m.add_class::<Api>()?;
+    m.add_class::<Capability>()?;
+    m.add_class::<Diff>()?;
+    m.add_class::<FlowChange>()?;
+    m.add_function(wrap_pyfunction!(analyze, m)?)?;
+    m.add_function(wrap_pyfunction!(diff, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use manifest_producer::diff::ManifestSet;
+    use serde_json::json;
+
+    #[test]
+    fn test_python_types() {
+        let analysis = Analyzer::new("../tests/elf_file/fake-firmware-c-dynamic")
+            .with_api_list(vec![ApiSpec::new("writeOnDrive")])
+            .with_verbosity(Verbosity::Quiet)
+            .analyze()
+            .unwrap();
+        let analysis = Analysis::from(&analysis);
+        assert_eq!(analysis.language, "C99");
+        let api = analysis.api("writeOnDrive").unwrap();
+        assert_eq!(api.syscalls, analysis.apis[0].syscalls);
+        assert!(analysis.api("accessWebcam").is_none());
+
+        let diff = Diff::from(&ManifestDiff::compute(
+            &ManifestSet::default(),
+            &ManifestSet::default(),
+        ));
+        assert!(diff.is_empty());
+        assert_eq!(
+            diff.to_json(),
+            json!(ManifestDiff::default().to_json()).to_string()
+        );
+    }
+}
@@ -0,0 +1,2119 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::Path,
+    str::FromStr,
+};
+
+use ed25519_dalek::SigningKey;
+use goblin::elf::Elf;
+use rayon::prelude::*;
+use serde_json::Value;
+use tracing::warn;
+
+#[cfg(feature = "sqlite")]
+use crate::store::ResultStore;
+use crate::{
+    api_detection::{api_search, attach_specs, fuzzy_api_search, ApiSpec},
+    apparmor::apparmor_profile,
+    budget::Budget,
+    cache::{cache_key, function_hashes, AnalysisCache},
+    call_graph::{function_nodes, CallGraph, FunctionNode},
+    capabilities::{api_ioctls, builtin_rules, classify_api, ioctl_requests, load_rules},
+    cfg::ControlFlowGraph,
+    cleanup::{demangle_api_name, syscall_flow, transitive_flow},
+    code_section_handler::find_vulnerability_patterns,
+    config::Config,
+    constructors::{constructors, Constructor, Phase},
+    correlation::{correlate, Correlation},
+    cortex_m::{thumb_call_graph, vector_table, VectorTable},
+    coverage::api_coverage,
+    crypto::{api_crypto, detect_crypto},
+    dangerous_calls::{find_dangerous_calls, DEFAULT_BANNED_FUNCTIONS},
+    dead_code::{unreachable_functions, unreachable_json},
+    diagnostics::collect_diagnostics,
+    dwarf_analysis::{dwarf_language, infer_language},
+    dynamic_analysis::{trace, DynamicOptions, ObservedBehavior},
+    dynamic_loading::{dynamic_loading, resolve_library, DynamicLoading, LibraryFlows},
+    elf_utils::{get_arch, is_stripped, API},
+    entropy::packed_error,
+    environment::environment_variables,
+    error::{Error, ErrorContext, Result},
+    files::file_accesses,
+    go_analysis::{
+        find_gopclntab, go_api_search, go_build_info, parse_pclntab, GoBuildInfo, GoFunction,
+    },
+    incremental::{patch_manifests, read_baseline, IncrementalReport},
+    ioctls::ioctl_inventory,
+    libraries::detect_libraries,
+    licenses::{detect_copyrights, detect_license_strings},
+    listing::Listing,
+    manifest_creation::{
+        api_flow_json, apparmor_manifest, basic_info_document, call_graph_manifest, cfg_manifest,
+        coverage_manifest, cyclonedx_manifest, disassembly_manifest, feature_document,
+        flow_call_document, generator_document, go_manifest, html_manifest, markdown_manifest,
+        mud_manifest, sarif_manifest, seccomp_manifest, spdx_manifest, write_manifest,
+        xrefs_manifest, Findings, Format,
+    },
+    network::{api_endpoints, binary_endpoints, socket_endpoints},
+    plugins::{AnalysisPass, ManifestSection, Plugins},
+    progress::{Progress, ProgressListener, Verbosity},
+    provenance::{group_by_library, library_of},
+    reader::{BinaryData, ReadMode},
+    report::{html_report, markdown_summary},
+    rtos::{detect_rtos, task_creations, Rtos, TaskCreation},
+    rust_crates::{rust_dependencies, RustDependencies},
+    schema::ManifestKind,
+    seccomp::SeccompProfile,
+    secrets::detect_secrets,
+    signals::{signal_registrations, SignalRegistration},
+    signatures::{
+        names_digest, recognize_functions, recognized_api_search, recognized_json,
+        RecognizedFunction, SignatureFile,
+    },
+    signing::sign_manifests,
+    stack_usage::{stack_json, StackFrames},
+    strings::{api_strings, extract_strings, link_references},
+    syscall_arguments::syscall_arguments,
+    threads::{thread_creations, ThreadCreation},
+    unpacking::{detect_upx, unpack_upx, Packing},
+    vulnerabilities::VulnDb,
+    xrefs::XrefDb,
+};
+
+// Minimum number of characters of the strings extracted from the binary.
+const MIN_STRING_LEN: usize = 4;
+
+// Minimum confidence for an API to be matched under a name different from the requested one.
+const FUZZY_CONFIDENCE: f64 = 0.8;
+
+// The pattern matching every function, the APIs of the whole-binary scope.
+const ALL_FUNCTIONS: &str = "^.*$";
+
+/// The kinds of analysis of a binary.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AnalysisMode {
+    /// Analyze the code and data of the binary, without running it.
+    #[default]
+    Static,
+    /// Observe the binary while it runs.
+    Dynamic,
+    /// Perform both analyses.
+    Both,
+}
+
+impl FromStr for AnalysisMode {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name {
+            "static" => Ok(AnalysisMode::Static),
+            "dynamic" => Ok(AnalysisMode::Dynamic),
+            "both" => Ok(AnalysisMode::Both),
+            _ => Err(Error::InvalidAnalysisMode(name.to_string())),
+        }
+    }
+}
+
+/// The functions of a binary analyzed as its APIs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Scope {
+    /// The functions of the API list.
+    #[default]
+    ApiList,
+    /// Every function of the binary, whatever the API list.
+    All,
+}
+
+impl FromStr for Scope {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name {
+            "api-list" => Ok(Scope::ApiList),
+            "all" => Ok(Scope::All),
+            _ => Err(Error::InvalidScope(name.to_string())),
+        }
+    }
+}
+
+/// The optional manifests written next to the basic info, flow call and feature manifests.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Outputs {
+    /// The call graph rooted at each API.
+    pub call_graph: bool,
+    /// The cross-references of each API.
+    pub xrefs: bool,
+    /// The control flow graph of each API.
+    pub cfg: bool,
+    /// A CycloneDX bill of materials.
+    pub cyclonedx: bool,
+    /// An SPDX document.
+    pub spdx: bool,
+    /// A SARIF log of the security findings.
+    pub sarif: bool,
+    /// An HTML report of the manifests.
+    pub html: bool,
+    /// A Markdown summary of the manifests.
+    pub markdown: bool,
+    /// A seccomp allowlist of the syscalls of the binary.
+    pub seccomp: bool,
+    /// A draft AppArmor profile of the binary.
+    pub apparmor: bool,
+    /// The annotated disassembly of each API.
+    pub disassembly: bool,
+}
+
+/// The outputs and limits of an analysis.
+#[derive(Clone, Default)]
+pub struct Options {
+    /// The optional manifests to write.
+    pub outputs: Outputs,
+    /// The URL to serve a MUD file of the network behavior from, none written when `None`.
+    pub mud_url: Option<String>,
+    /// The format of the basic info, flow call and feature manifests.
+    pub format: Format,
+    /// The expected architecture of the binary, checked against its header when given.
+    pub arch: Option<String>,
+    /// The kind of analysis to perform.
+    pub analysis: AnalysisMode,
+    /// The functions analyzed as the APIs of the binary.
+    pub scope: Scope,
+    /// How the binary is run for its dynamic analysis.
+    pub dynamic: DynamicOptions,
+    /// The maximum depth of the transitive flows, unlimited when `None`.
+    pub max_depth: Option<usize>,
+    /// The functions whose calls are reported, [`DEFAULT_BANNED_FUNCTIONS`] when `None`.
+    pub banned_functions: Option<Vec<String>>,
+    /// The path of an OSV snapshot to match the libraries against.
+    pub vuln_db: Option<String>,
+    /// The path of a rules file mapping functions, strings and ioctls to capabilities.
+    pub capability_rules: Option<String>,
+    /// The key signing the manifests, which are left unsigned when `None`.
+    pub signing_key: Option<SigningKey>,
+    /// How much is reported on stderr during the analysis.
+    pub verbosity: Verbosity,
+    /// How the binary is read.
+    pub read_mode: ReadMode,
+    /// The directory of the [`AnalysisCache`] of the call graphs, not cached when `None`.
+    pub cache_dir: Option<String>,
+    /// The resources the disassembly may use before functions are skipped.
+    pub budget: Budget,
+    /// Start from the analysis of the previous build whose manifests are in the output
+    /// directory, re-analyzing only the functions changed and rewriting only the manifests
+    /// changed. Requires the cache.
+    pub incremental: bool,
+    /// Called with each step of the analysis, besides what is reported on stderr.
+    pub listener: Option<ProgressListener>,
+    /// The custom passes and manifest sections.
+    pub plugins: Plugins,
+    /// The program unpacking the binaries packed with UPX, e.g.
+    /// [`crate::unpacking::DEFAULT_UPX`]; such binaries are not analyzed when `None`.
+    pub unpacker: Option<String>,
+    /// The libraries whose functions are left out of the transitive flows, e.g. `libc`, as
+    /// attributed by [`crate::provenance::library_of`].
+    pub exclude_libraries: Vec<String>,
+    /// The FLIRT `.pat` files of the libraries recognized in stripped binaries, which are not
+    /// analyzed when there are none.
+    pub signatures: Vec<String>,
+    /// The root directory of the firmware tree, in which the libraries loaded with `dlopen` are
+    /// looked for to trace the flows of the symbols looked up in them; they are not when `None`.
+    pub library_root: Option<String>,
+    /// Keep going when an optional stage of the analysis, or the flow of one function, fails:
+    /// the error is recorded in [`Analysis::errors`] and the manifests are still written. A
+    /// stripped binary without signatures is analyzed from its dynamic symbols, and a binary
+    /// where no API is found is analyzed without APIs. The errors of the inputs, and of a
+    /// binary that cannot be read, parsed or disassembled, stay fatal.
+    pub best_effort: bool,
+    /// Leave the time of the analysis out of the `generator` block of the manifests, so that
+    /// the same binary and options give byte-identical manifests.
+    pub no_timestamp: bool,
+    /// The stack depth in bytes beyond which a flow is flagged in the `stack usage` of the flow
+    /// call manifest, none being flagged when `None`.
+    pub stack_threshold: Option<u64>,
+    /// How many calls away from each API its callees are also listed in its annotated
+    /// disassembly, `0` for the API only.
+    pub disassembly_depth: usize,
+    /// The SQLite database the results are also written to, with the `sqlite` feature, see
+    /// [`crate::store`].
+    pub store: Option<String>,
+}
+
+impl Options {
+    /// Read the options of a configuration; the API list and the output directory are not part
+    /// of them.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the list of banned functions of the configuration cannot be read.
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let banned_functions = match &config.banned {
+            Some(path) => Some(serde_json::from_str(&fs::read_to_string(path)?)?),
+            None => None,
+        };
+        Ok(Self {
+            outputs: Outputs {
+                call_graph: config.output("call-graph"),
+                xrefs: config.output("xrefs"),
+                cfg: config.output("cfg"),
+                cyclonedx: config.output("cyclonedx"),
+                spdx: config.output("spdx"),
+                sarif: config.output("sarif"),
+                html: config.output("html"),
+                markdown: config.output("markdown"),
+                seccomp: config.output("seccomp"),
+                apparmor: config.output("apparmor"),
+                disassembly: config.output("disassembly"),
+            },
+            format: config.format.unwrap_or_default(),
+            arch: config.arch.clone(),
+            analysis: config.analysis.unwrap_or_default(),
+            scope: config.scope.unwrap_or_default(),
+            dynamic: config.dynamic.clone(),
+            max_depth: config.max_depth,
+            banned_functions,
+            vuln_db: config.vuln_db.clone(),
+            capability_rules: config.capability_rules.clone(),
+            cache_dir: config.cache_dir.clone(),
+            budget: config.budget,
+            unpacker: config.unpacker.clone(),
+            exclude_libraries: config.exclude_libraries.clone(),
+            signatures: config.signatures.clone(),
+            library_root: config.library_root.clone(),
+            best_effort: config.best_effort,
+            no_timestamp: config.no_timestamp,
+            stack_threshold: config.stack_threshold,
+            disassembly_depth: config.disassembly_depth.unwrap_or_default(),
+            store: config.store.clone(),
+            ..Self::default()
+        })
+    }
+
+    /// Describe the options shaping the manifests, for their `generator` block; those only
+    /// changing how the analysis runs, e.g. the cache, the progress or the incremental mode,
+    /// are left out.
+    pub fn to_json(&self) -> Value {
+        let outputs = &self.outputs;
+        let written: Vec<&str> = [
+            ("call-graph", outputs.call_graph),
+            ("xrefs", outputs.xrefs),
+            ("cfg", outputs.cfg),
+            ("cyclonedx", outputs.cyclonedx),
+            ("spdx", outputs.spdx),
+            ("sarif", outputs.sarif),
+            ("html", outputs.html),
+            ("markdown", outputs.markdown),
+            ("seccomp", outputs.seccomp),
+            ("apparmor", outputs.apparmor),
+            ("disassembly", outputs.disassembly),
+        ]
+        .into_iter()
+        .filter_map(|(name, written)| written.then_some(name))
+        .collect();
+        let mut options = serde_json::json!({
+            "analysis": match self.analysis {
+                AnalysisMode::Static => "static",
+                AnalysisMode::Dynamic => "dynamic",
+                AnalysisMode::Both => "both",
+            },
+            "scope": match self.scope {
+                Scope::ApiList => "api-list",
+                Scope::All => "all",
+            },
+            "format": self.format.extension(),
+            "arch": self.arch,
+            "outputs": written,
+            "mud url": self.mud_url,
+            "max depth": self.max_depth,
+            "stack threshold": self.stack_threshold,
+            "disassembly depth": self.disassembly_depth,
+            "banned functions": self.banned_functions,
+            "vulnerability database": self.vuln_db,
+            "capability rules": self.capability_rules,
+            "function timeout": self.budget.function_timeout.map(|limit| limit.as_secs_f64()),
+            "memory limit": self.budget.memory_limit,
+            "unpacker": self.unpacker,
+            "exclude libraries": self.exclude_libraries,
+            "signatures": self.signatures,
+            "library root": self.library_root,
+            "best effort": self.best_effort,
+            "signed": self.signing_key.is_some(),
+        });
+        if let (false, Some(block)) = (
+            self.analysis == AnalysisMode::Static,
+            options.as_object_mut(),
+        ) {
+            let dynamic = &self.dynamic;
+            block.insert(
+                "dynamic".to_string(),
+                serde_json::json!({
+                    "duration": dynamic.duration.as_secs_f64(),
+                    "args": dynamic.args,
+                    "allow network": dynamic.allow_network,
+                    "emulator": dynamic.emulator,
+                    "sysroot": dynamic.sysroot,
+                }),
+            );
+        }
+        options
+    }
+}
+
+/// A binary loaded by [`Analyzer::load`].
+pub struct Binary {
+    /// The path of the binary.
+    pub path: String,
+    /// The content of the binary.
+    pub data: BinaryData,
+    /// The programming language of the binary, from its DWARF information, e.g. `C99`.
+    pub language: String,
+    /// Whether the language was guessed from the symbols and sections of a binary without
+    /// DWARF information, see [`infer_language`].
+    pub language_inferred: bool,
+    /// The functions of the `.gopclntab` of a Go binary.
+    pub go_functions: Option<Vec<GoFunction>>,
+    /// The Go version and the modules of a Go binary, from its `.go.buildinfo`.
+    pub go_build_info: Option<GoBuildInfo>,
+    /// The rustc version and the crates of a Rust binary, from its symbols and source paths.
+    pub rust_dependencies: Option<RustDependencies>,
+    /// How the binary was packed, when it was unpacked before its analysis.
+    pub packing: Option<Packing>,
+    /// The functions of a stripped binary, found from its calls and named by the signatures
+    /// of [`Options::signatures`].
+    pub recognized_functions: Option<Vec<RecognizedFunction>>,
+}
+
+impl Binary {
+    /// Parse the ELF structure of the binary.
+    pub fn elf(&self) -> Result<Elf<'_>> {
+        Ok(Elf::parse(&self.data)?)
+    }
+}
+
+/// The results of [`Analyzer::analyze`].
+pub struct Analysis {
+    /// The binary analyzed.
+    pub binary: Binary,
+    /// The APIs found, with their flows, strings, endpoints and capabilities.
+    pub apis: Vec<API>,
+    /// The call graph of the whole binary.
+    pub graph: CallGraph,
+    /// The cross-references of the functions of the call graph.
+    pub xrefs: XrefDb,
+    /// The findings about the whole binary.
+    pub findings: Findings,
+    /// What was reused and recomputed, for an incremental analysis.
+    pub incremental: Option<IncrementalReport>,
+    /// The result of each custom pass, by name.
+    pub plugins: BTreeMap<String, Value>,
+    /// What the binary did while it ran, for a dynamic analysis.
+    pub observed: Option<ObservedBehavior>,
+    /// The syscalls predicted and observed, when both analyses ran.
+    pub correlation: Option<Correlation>,
+    /// The calls creating threads.
+    pub threads: Vec<ThreadCreation>,
+    /// The flows rooted at the start routine of each thread created.
+    pub thread_flows: Vec<API>,
+    /// The calls registering signal handlers.
+    pub signals: Vec<SignalRegistration>,
+    /// The flows rooted at each signal handler registered.
+    pub handler_flows: Vec<API>,
+    /// The libraries loaded at run time and the symbols looked up in them.
+    pub loading: DynamicLoading,
+    /// The flows of the symbols looked up, in the libraries found in the firmware tree.
+    pub library_flows: Vec<LibraryFlows>,
+    /// The constructors and destructors run around `main`.
+    pub constructors: Vec<Constructor>,
+    /// The flows rooted at each constructor.
+    pub startup_flows: Vec<API>,
+    /// The flows rooted at each destructor.
+    pub shutdown_flows: Vec<API>,
+    /// The interrupt vector table, for a Cortex-M image.
+    pub vector_table: Option<VectorTable>,
+    /// The flows rooted at each exception and interrupt handler of the vector table.
+    pub interrupt_flows: Vec<API>,
+    /// The RTOS the firmware is built on.
+    pub rtos: Option<Rtos>,
+    /// The calls creating tasks of the RTOS.
+    pub tasks: Vec<TaskCreation>,
+    /// The flows rooted at the entry function of each task created.
+    pub task_flows: Vec<API>,
+    /// The errors the analysis recovered from, in best-effort mode, each with where it
+    /// occurred.
+    pub errors: Vec<Error>,
+}
+
+/// The analysis pipeline of a binary: API detection, call flows, findings and manifests.
+///
+/// ```no_run
+/// use manifest_producer::analyzer::{Analyzer, Outputs};
+/// use manifest_producer::api_detection::ApiSpec;
+///
+/// let analysis = Analyzer::new("firmware.elf")
+///     .with_api_list(vec![ApiSpec::new("writeOnDrive")])
+///     .with_outputs(Outputs { sarif: true, ..Outputs::default() })
+///     .run("./manifest-produced")?;
+/// println!("{} APIs found", analysis.apis.len());
+/// # Ok::<(), manifest_producer::error::Error>(())
+/// ```
+///
+/// Each stage can also be run on its own: [`Analyzer::load`], [`Analyzer::detect_apis`],
+/// [`Analyzer::build_graph`], [`Analyzer::trace_flows`], [`Analyzer::collect_findings`] and
+/// [`Analyzer::write_manifests`].
+pub struct Analyzer {
+    file_path: String,
+    bytes: Option<Vec<u8>>,
+    source_url: Option<String>,
+    specs: Vec<ApiSpec>,
+    options: Options,
+}
+
+impl Analyzer {
+    /// Start the analysis of the binary at `file_path`, with the default options.
+    pub fn new(file_path: &str) -> Self {
+        Self {
+            file_path: file_path.to_string(),
+            bytes: None,
+            source_url: None,
+            specs: Vec::new(),
+            options: Options::default(),
+        }
+    }
+
+    /// Start the analysis of a binary already in memory, e.g. uploaded to a web page, with the
+    /// default options. `name` stands for its path in the manifests.
+    pub fn from_bytes(name: &str, bytes: Vec<u8>) -> Self {
+        Self {
+            bytes: Some(bytes),
+            ..Self::new(name)
+        }
+    }
+
+    /// Record in the `generator` block of the manifests the URL the binary was downloaded from,
+    /// the input file being named after the file name of the binary rather than the place it
+    /// was downloaded to.
+    pub fn with_source_url(mut self, url: &str) -> Self {
+        self.source_url = Some(url.to_string());
+        self
+    }
+
+    /// Look for the APIs of a list.
+    pub fn with_api_list(mut self, specs: Vec<ApiSpec>) -> Self {
+        self.specs = specs;
+        self
+    }
+
+    /// Write the given optional manifests.
+    pub fn with_outputs(mut self, outputs: Outputs) -> Self {
+        self.options.outputs = outputs;
+        self
+    }
+
+    /// Write the basic info, flow call and feature manifests in a format.
+    pub fn with_format(mut self, format: Format) -> Self {
+        self.options.format = format;
+        self
+    }
+
+    /// Check that the binary is of an architecture, e.g. `x86-64`.
+    pub fn with_arch(mut self, arch: &str) -> Self {
+        self.options.arch = Some(arch.to_string());
+        self
+    }
+
+    /// Analyze the functions of the API list, or every function of the binary.
+    pub fn with_scope(mut self, scope: Scope) -> Self {
+        self.options.scope = scope;
+        self
+    }
+
+    /// Limit the transitive flows to call chains of at most `max_depth` calls.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.options.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Report the progress of the analysis on stderr.
+    pub fn with_verbosity(mut self, verbosity: Verbosity) -> Self {
+        self.options.verbosity = verbosity;
+        self
+    }
+
+    /// Read the binary rather than mapping it in memory, with [`ReadMode::Read`].
+    pub fn with_read_mode(mut self, read_mode: ReadMode) -> Self {
+        self.options.read_mode = read_mode;
+        self
+    }
+
+    /// Reuse the call graph and cross-references of a binary analyzed before, from the cache in
+    /// the directory `dir`.
+    pub fn with_cache(mut self, dir: &str) -> Self {
+        self.options.cache_dir = Some(dir.to_string());
+        self
+    }
+
+    /// Re-analyze only what changed since the previous build whose manifests are in the output
+    /// directory, see [`Options::incremental`].
+    pub fn with_incremental(mut self) -> Self {
+        self.options.incremental = true;
+        self
+    }
+
+    /// Skip the functions whose disassembly goes over a resource budget, rather than letting
+    /// them hang the analysis.
+    pub fn with_budget(mut self, budget: Budget) -> Self {
+        self.options.budget = budget;
+        self
+    }
+
+    /// Report each step of the analysis to `listener`, e.g. to stream the progress to a client.
+    pub fn with_listener(mut self, listener: ProgressListener) -> Self {
+        self.options.listener = Some(listener);
+        self
+    }
+
+    /// Record the errors of the optional stages and go on with the analysis, rather than
+    /// failing, see [`Options::best_effort`].
+    pub fn with_best_effort(mut self, best_effort: bool) -> Self {
+        self.options.best_effort = best_effort;
+        self
+    }
+
+    /// Write the time of the analysis in the `generator` block of the manifests, or leave it
+    /// out, see [`Options::no_timestamp`].
+    pub fn with_timestamp(mut self, timestamp: bool) -> Self {
+        self.options.no_timestamp = !timestamp;
+        self
+    }
+
+    /// Flag the flows whose worst-case stack depth exceeds `bytes`, see
+    /// [`Options::stack_threshold`].
+    pub fn with_stack_threshold(mut self, bytes: u64) -> Self {
+        self.options.stack_threshold = Some(bytes);
+        self
+    }
+
+    /// List the callees of each API up to `depth` calls away in its annotated disassembly, see
+    /// [`Options::disassembly_depth`].
+    pub fn with_disassembly_depth(mut self, depth: usize) -> Self {
+        self.options.disassembly_depth = depth;
+        self
+    }
+
+    /// Also write the results of [`Analyzer::run`] to the SQLite database `path`, see
+    /// [`Options::store`].
+    pub fn with_store(mut self, path: &str) -> Self {
+        self.options.store = Some(path.to_string());
+        self
+    }
+
+    /// Run a custom pass once the built-in analysis is done, see [`crate::plugins`].
+    pub fn with_pass(mut self, pass: impl AnalysisPass + 'static) -> Self {
+        self.options.plugins.register_pass(pass);
+        self
+    }
+
+    /// Add a custom section to a manifest, see [`crate::plugins`].
+    pub fn with_section(mut self, section: impl ManifestSection + 'static) -> Self {
+        self.options.plugins.register_section(section);
+        self
+    }
+
+    /// Replace all the options of the analysis.
+    pub fn with_options(mut self, options: Options) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// The options of the analysis.
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
+    /// Read the binary, unpack it if packed with UPX, check its architecture and that it can be
+    /// analyzed, and detect its language.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::PackedBinary`] for a binary packed with UPX when there is no unpacker,
+    /// and [`Error::Unpacking`] when the unpacker fails.
+    ///
+    /// Returns [`Error::UnsupportedArch`] for a binary of another architecture than x86-64,
+    /// unless it is a Cortex-M image or only analyzed dynamically, [`Error::ArchMismatch`] for
+    /// a binary of another architecture than expected, [`Error::PackedBinary`] for a stripped
+    /// binary whose code looks compressed or encrypted, and [`Error::DebugInfo`] for another
+    /// stripped binary that is not written in Go, unless in best-effort mode.
+    pub fn load(&self) -> Result<Binary> {
+        let data = match &self.bytes {
+            Some(bytes) => BinaryData::Owned(bytes.clone()),
+            None => BinaryData::open(&self.file_path, self.options.read_mode)?,
+        };
+        let packing = detect_upx(&data);
+        let data = match (&packing, &self.options.unpacker) {
+            (None, _) => data,
+            (Some(_), Some(unpacker)) => BinaryData::Owned(unpack_upx(&data, unpacker)?),
+            (Some(packing), None) => {
+                return Err(Error::PackedBinary(format!(
+                    "packed with UPX {}, unpack it with --unpack",
+                    packing.version.as_deref().unwrap_or("of unknown version")
+                )))
+            }
+        };
+        let elf = Elf::parse(&data)?;
+        let found = get_arch(&elf)?;
+        // Only the architecture of the disassembler, and the Thumb code of the Cortex-M images,
+        // can be analyzed statically; the others are only run under emulation.
+        let bare_metal = found == "arm" && vector_table(&elf, &data).is_some();
+        if found != "x86-64" && !bare_metal && self.options.analysis != AnalysisMode::Dynamic {
+            return Err(Error::UnsupportedArch(
+                goblin::elf::header::machine_to_str(elf.header.e_machine).to_string(),
+            ));
+        }
+        if let Some(arch) = &self.options.arch {
+            if found != arch {
+                return Err(Error::ArchMismatch {
+                    expected: arch.clone(),
+                    found: found.to_string(),
+                });
+            }
+        }
+
+        // Go binaries keep their function table in .gopclntab even when stripped.
+        let go_functions = match find_gopclntab(&elf, &data) {
+            Some(pclntab) => Some(parse_pclntab(pclntab)?),
+            None => None,
+        };
+        let go_build_info = go_build_info(&elf, &data);
+        let rust_dependencies = rust_dependencies(&elf, &data);
+        let stripped = is_stripped(&elf) && go_functions.is_none();
+        let unnamed = self.options.signatures.is_empty();
+        match packed_error(&elf, &data) {
+            Some(error) if stripped => return Err(error),
+            None if stripped && unnamed && !self.options.best_effort => {
+                return Err(Error::DebugInfo)
+            }
+            _ => {}
+        }
+        let recognized_functions = match stripped && !unnamed {
+            true => {
+                let files = self
+                    .options
+                    .signatures
+                    .iter()
+                    .map(|path| SignatureFile::load(path))
+                    .collect::<Result<Vec<_>>>()?;
+                Some(recognize_functions(&elf, &data, &files)?)
+            }
+            false => None,
+        };
+
+        let (language, language_inferred) = match dwarf_language(&data)?.strip_prefix("DW_LANG_") {
+            Some(stripped_lang) => (stripped_lang.to_owned(), false),
+            None if go_functions.is_some() || go_build_info.is_some() => ("Go".to_string(), false),
+            // Without DWARF, the symbols and sections tell the demangling scheme to use.
+            None => match infer_language(&elf, &data) {
+                Some(language) => (language.to_string(), true),
+                None => ("".to_string(), false),
+            },
+        };
+        Ok(Binary {
+            path: self.file_path.clone(),
+            data,
+            language,
+            language_inferred,
+            go_functions,
+            go_build_info,
+            rust_dependencies,
+            packing,
+            recognized_functions,
+        })
+    }
+
+    /// Find the APIs of the list in a binary, by name or by similarity of their names, or every
+    /// function of the binary with [`Scope::All`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::PackedBinary`] if no API is found in a binary whose code looks
+    /// compressed or encrypted, and [`Error::APIListEmpty`] if no API is found in another.
+    pub fn detect_apis(&self, binary: &Binary) -> Result<Vec<API>> {
+        let api_list: Vec<&str> = match self.options.scope {
+            Scope::ApiList => self.specs.iter().flat_map(|spec| spec.names()).collect(),
+            Scope::All => vec![ALL_FUNCTIONS],
+        };
+        let mut api_found = match (&binary.go_functions, &binary.recognized_functions) {
+            (Some(functions), _) => go_api_search(functions, &api_list)?,
+            (None, Some(functions)) => recognized_api_search(functions, &api_list)?,
+            (None, None) => {
+                let elf = binary.elf()?;
+                let mut api_found = api_search(&elf, &api_list)?;
+                let fuzzy_found = fuzzy_api_search(&elf, &api_list, &api_found, FUZZY_CONFIDENCE)?;
+                api_found.extend(fuzzy_found);
+                api_found
+            }
+        };
+        if api_found.is_empty() {
+            return Err(packed_error(&binary.elf()?, &binary.data).unwrap_or(Error::APIListEmpty));
+        }
+        attach_specs(&mut api_found, &self.specs)?;
+        // The order of the symbol table depends on the linker, the manifests list the APIs by name.
+        api_found.sort_by(|a, b| a.name.cmp(&b.name).then(a.start_addr.cmp(&b.start_addr)));
+        Ok(api_found)
+    }
+
+    /// Disassemble every function of a binary into its call graph.
+    pub fn build_graph(&self, binary: &Binary) -> Result<CallGraph> {
+        self.build_graph_observed(binary, &|_, _| {})
+    }
+
+    /// Read the direct and transitive calls of the APIs from the call graph.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error of the flow of an API, with the API it occurred in.
+    pub fn trace_flows(&self, binary: &Binary, graph: &CallGraph, apis: &mut [API]) -> Result<()> {
+        // The APIs are independent, their flows are read in parallel.
+        apis.par_iter_mut().try_for_each(|api| {
+            self.trace_flow(&binary.language, graph, api)
+                .map_err(|error| error.with_context(flow_context(api)))
+        })
+    }
+
+    // Read the flows of the APIs, recording the error of each API whose flow cannot be read in
+    // best-effort mode rather than failing.
+    fn trace_flows_with(
+        &self,
+        binary: &Binary,
+        graph: &CallGraph,
+        apis: &mut [API],
+        errors: &mut Vec<Error>,
+    ) -> Result<()> {
+        if !self.options.best_effort {
+            return self.trace_flows(binary, graph, apis);
+        }
+        let failed: Vec<Error> = apis
+            .par_iter_mut()
+            .filter_map(|api| {
+                let error = self.trace_flow(&binary.language, graph, api).err()?;
+                Some(error.with_context(flow_context(api)))
+            })
+            .collect();
+        errors.extend(failed);
+        Ok(())
+    }
+
+    // Read the direct and transitive calls of an API from the call graph.
+    fn trace_flow(&self, lang: &str, graph: &CallGraph, api: &mut API) -> Result<()> {
+        syscall_flow(api, graph.callee_names(api.start_addr), lang)?;
+        let traversal = graph.traverse(api.start_addr, self.options.max_depth);
+        let calls = traversal
+            .calls
+            .iter()
+            .map(|edge| edge.name.clone())
+            .collect();
+        transitive_flow(api, calls, lang)?;
+        let excluded = &self.options.exclude_libraries;
+        api.transitive_calls.retain(|name| {
+            !excluded
+                .iter()
+                .any(|library| library == library_of(name, lang))
+        });
+        api.libraries = group_by_library(&api.transitive_calls, lang)
+            .into_iter()
+            .map(|(library, names)| (library.to_string(), names))
+            .collect();
+        api.recursive_functions = traversal
+            .recursive
+            .iter()
+            .filter_map(|&addr| graph.function(addr))
+            .map(|func| demangle_api_name(&func.name))
+            .collect();
+        api.depth_truncated = traversal.truncated;
+        // `reachable` only returns the functions of the graph, which all have a node.
+        for addr in graph.reachable(&[api.start_addr]) {
+            if let Some(reason) = graph.skipped(addr) {
+                api.skipped_functions.push(format!(
+                    "{}: {}",
+                    demangle_api_name(&graph.function(addr).unwrap().name),
+                    reason
+                ));
+            }
+            for call in graph.unresolved_calls(addr) {
+                api.unresolved_calls.push(format!(
+                    "{:#x} in {}: call {}",
+                    call.site,
+                    demangle_api_name(&graph.function(addr).unwrap().name),
+                    call.operand
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Analyze the data of a binary: the strings, endpoints, algorithms, ioctls and capabilities
+    /// of each API, and the findings about the whole binary.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the cross-references of the functions and the findings.
+    pub fn collect_findings(
+        &self,
+        binary: &Binary,
+        graph: &CallGraph,
+        apis: &mut [API],
+    ) -> Result<(XrefDb, Findings)> {
+        let xrefs = XrefDb::build(&binary.elf()?, &binary.data, graph);
+        let findings = self.collect_findings_with(binary, graph, &xrefs, apis)?;
+        Ok((xrefs, findings))
+    }
+
+    // Analyze the data of a binary, with the cross-references of its functions.
+    fn collect_findings_with(
+        &self,
+        binary: &Binary,
+        graph: &CallGraph,
+        xrefs: &XrefDb,
+        apis: &mut [API],
+    ) -> Result<Findings> {
+        let vuln_db = self
+            .options
+            .vuln_db
+            .as_deref()
+            .map(VulnDb::load)
+            .transpose()?;
+        let capability_rules = match &self.options.capability_rules {
+            Some(path) => load_rules(path)?,
+            None => builtin_rules(),
+        };
+        let elf = binary.elf()?;
+        let elf_data = &binary.data;
+
+        let mut strings = extract_strings(&elf, elf_data, MIN_STRING_LEN);
+        link_references(&mut strings, xrefs, graph);
+        let sockets = socket_endpoints(&elf, elf_data, graph, &strings)?;
+        let banned = self.options.banned_functions.clone().unwrap_or_else(|| {
+            DEFAULT_BANNED_FUNCTIONS
+                .iter()
+                .map(|name| name.to_string())
+                .collect()
+        });
+        let libraries = detect_libraries(&elf, &strings);
+        let ioctls = ioctl_requests(&elf, elf_data, graph)?;
+        let arguments = syscall_arguments(&elf, elf_data, graph, xrefs)?;
+        let crypto = detect_crypto(&elf, elf_data, graph, xrefs);
+        // The control flow graphs are built from x86-64 code only.
+        let x86 = get_arch(&elf)? == "x86-64";
+        apis.par_iter_mut().try_for_each(|api| -> Result<()> {
+            api.strings = api_strings(&strings, graph, api.start_addr);
+            api.endpoints = api_endpoints(&strings, &sockets, graph, api.start_addr);
+            api.crypto = api_crypto(&crypto, graph, api.start_addr);
+            api.ioctls = api_ioctls(&ioctls, graph, api.start_addr);
+            api.syscall_arguments = arguments.get(&api.start_addr).cloned().unwrap_or_default();
+            api.capabilities = classify_api(api, &capability_rules);
+            if x86 {
+                api.complexity = ControlFlowGraph::build(
+                    &elf,
+                    elf_data,
+                    &api.name,
+                    api.start_addr,
+                    api.end_addr,
+                )?
+                .map(|cfg| cfg.complexity());
+            }
+            Ok(())
+        })?;
+        let findings = Findings {
+            secrets: detect_secrets(&strings, graph),
+            endpoints: binary_endpoints(&strings, &sockets),
+            crypto,
+            dangerous_calls: find_dangerous_calls(graph, &banned, apis),
+            vulnerability_patterns: find_vulnerability_patterns(&elf, elf_data, graph, apis)?,
+            vulnerabilities: vuln_db.map(|db| db.scan(&libraries)),
+            libraries,
+            licenses: detect_license_strings(&strings),
+            copyrights: detect_copyrights(&strings),
+            environment: environment_variables(&elf, elf_data, graph, xrefs, apis)?,
+            files: file_accesses(&elf, elf_data, graph, xrefs, apis)?,
+            ioctls: ioctl_inventory(&ioctls, graph, apis),
+        };
+        Ok(findings)
+    }
+
+    /// Run every stage of the analysis, without writing the manifests.
+    pub fn analyze(&self) -> Result<Analysis> {
+        let mut progress =
+            Progress::new(self.options.verbosity).with_listener(self.options.listener.clone());
+        let analysis = self.analyze_with(&mut progress, None)?;
+        progress.finish();
+        Ok(analysis)
+    }
+
+    /// Write the manifests of an analysis to the directory `path`, then sign them when a
+    /// signing key is given.
+    pub fn write_manifests(&self, analysis: &Analysis, path: &str) -> Result<()> {
+        let manifests = self.manifests(analysis)?;
+        self.write_signed(analysis, &manifests, path)
+    }
+
+    // Write the manifests built from an analysis and its other outputs to the directory `path`,
+    // then sign them when a signing key is given.
+    fn write_signed(
+        &self,
+        analysis: &Analysis,
+        manifests: &BTreeMap<String, Value>,
+        path: &str,
+    ) -> Result<()> {
+        self.write_unsigned(analysis, manifests, path)?;
+        // Last, to cover every manifest written.
+        if let Some(key) = &self.options.signing_key {
+            sign_manifests(path, &analysis.binary.data, key)?;
+        }
+        Ok(())
+    }
+
+    /// Build the basic information, flow call and feature manifests of an analysis, by name,
+    /// with the sections of the plugins, without writing them.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the binary cannot be parsed again.
+    pub fn manifests(&self, analysis: &Analysis) -> Result<BTreeMap<String, Value>> {
+        let binary = &analysis.binary;
+        let mut basic_info = basic_info_document(
+            &binary.elf()?,
+            &binary.data,
+            &binary.path,
+            &analysis.apis,
+            binary.language.clone(),
+        )?;
+        if let (Some(info), Some(manifest)) = (&binary.go_build_info, basic_info.as_object_mut()) {
+            manifest.insert("go build info".to_string(), info.to_json());
+        }
+        if let (Some(rust), Some(manifest)) =
+            (&binary.rust_dependencies, basic_info.as_object_mut())
+        {
+            manifest.insert("rust crates".to_string(), rust.to_json());
+        }
+        if let (Some(packing), Some(manifest)) = (&binary.packing, basic_info.as_object_mut()) {
+            manifest.insert("packing".to_string(), packing.to_json());
+        }
+        if let (Some(rtos), Some(manifest)) = (&analysis.rtos, basic_info.as_object_mut()) {
+            manifest.insert("rtos".to_string(), rtos.to_json());
+        }
+        if let (false, Some(manifest)) = (analysis.errors.is_empty(), basic_info.as_object_mut()) {
+            let errors: Vec<Value> = analysis.errors.iter().map(Error::to_json).collect();
+            manifest.insert("errors".to_string(), Value::Array(errors));
+        }
+        if let (Some(functions), Some(manifest)) =
+            (&binary.recognized_functions, basic_info.as_object_mut())
+        {
+            manifest.insert(
+                "recognized functions".to_string(),
+                recognized_json(functions),
+            );
+        }
+        let mut flow_call = flow_call_document(&analysis.apis);
+        if let (Some(observed), Some(manifest)) = (&analysis.observed, flow_call.as_object_mut()) {
+            manifest.insert("observed".to_string(), observed.to_json());
+        }
+        if let (Some(correlation), Some(manifest)) =
+            (&analysis.correlation, flow_call.as_object_mut())
+        {
+            manifest.insert("correlation".to_string(), correlation.to_json());
+        }
+        if let (false, Some(manifest)) = (analysis.threads.is_empty(), flow_call.as_object_mut()) {
+            let threads: Vec<Value> = analysis.threads.iter().map(|t| t.to_json()).collect();
+            let flows: Vec<Value> = analysis.thread_flows.iter().map(api_flow_json).collect();
+            manifest.insert("threads".to_string(), Value::Array(threads));
+            manifest.insert("Thread flows".to_string(), Value::Array(flows));
+        }
+        if let (false, Some(manifest)) = (analysis.tasks.is_empty(), flow_call.as_object_mut()) {
+            let tasks: Vec<Value> = analysis.tasks.iter().map(|t| t.to_json()).collect();
+            let flows: Vec<Value> = analysis.task_flows.iter().map(api_flow_json).collect();
+            manifest.insert("tasks".to_string(), Value::Array(tasks));
+            manifest.insert("Task flows".to_string(), Value::Array(flows));
+        }
+        if let (false, Some(manifest)) = (analysis.signals.is_empty(), flow_call.as_object_mut()) {
+            let signals: Vec<Value> = analysis.signals.iter().map(|s| s.to_json()).collect();
+            let flows: Vec<Value> = analysis.handler_flows.iter().map(api_flow_json).collect();
+            manifest.insert("signal handlers".to_string(), Value::Array(signals));
+            manifest.insert("Signal handler flows".to_string(), Value::Array(flows));
+        }
+        if let (false, Some(manifest)) =
+            (analysis.constructors.is_empty(), flow_call.as_object_mut())
+        {
+            let entries: Vec<Value> = analysis.constructors.iter().map(|c| c.to_json()).collect();
+            let startup: Vec<Value> = analysis.startup_flows.iter().map(api_flow_json).collect();
+            let shutdown: Vec<Value> = analysis.shutdown_flows.iter().map(api_flow_json).collect();
+            manifest.insert("constructors".to_string(), Value::Array(entries));
+            manifest.insert("Startup flows".to_string(), Value::Array(startup));
+            manifest.insert("Shutdown flows".to_string(), Value::Array(shutdown));
+        }
+        if let (Some(table), Some(manifest)) = (&analysis.vector_table, flow_call.as_object_mut()) {
+            let flows: Vec<Value> = analysis.interrupt_flows.iter().map(api_flow_json).collect();
+            manifest.insert("vector table".to_string(), table.to_json());
+            manifest.insert("Interrupt flows".to_string(), Value::Array(flows));
+        }
+        if let (false, Some(manifest)) = (analysis.loading.is_empty(), flow_call.as_object_mut()) {
+            manifest.insert("dynamic loading".to_string(), analysis.loading.to_json());
+        }
+        if let (false, Some(manifest)) =
+            (analysis.library_flows.is_empty(), flow_call.as_object_mut())
+        {
+            let flows: Vec<Value> = analysis.library_flows.iter().map(|l| l.to_json()).collect();
+            manifest.insert("Loaded library flows".to_string(), Value::Array(flows));
+        }
+        if let (Some(_), Some(manifest)) =
+            (analysis.graph.functions().next(), flow_call.as_object_mut())
+        {
+            let roots: Vec<u64> = [
+                &analysis.apis,
+                &analysis.thread_flows,
+                &analysis.handler_flows,
+                &analysis.startup_flows,
+                &analysis.shutdown_flows,
+                &analysis.interrupt_flows,
+                &analysis.task_flows,
+            ]
+            .into_iter()
+            .flatten()
+            .map(|api| api.start_addr)
+            .collect();
+            let (elf, graph) = (binary.elf()?, &analysis.graph);
+            let unreachable = unreachable_functions(&elf, &binary.data, graph, &roots);
+            manifest.insert(
+                "unreachable functions".to_string(),
+                unreachable_json(&unreachable),
+            );
+            let frames = StackFrames::estimate(&elf, &binary.data, graph);
+            if !frames.is_empty() {
+                let usages = frames.flows(graph, &roots);
+                let section = stack_json(&usages, self.options.stack_threshold);
+                manifest.insert("stack usage".to_string(), section);
+            }
+        }
+        let mut manifests = [
+            (ManifestKind::BasicInfo, basic_info),
+            (ManifestKind::FlowCall, flow_call),
+            (
+                ManifestKind::Feature,
+                feature_document(&analysis.apis, &analysis.findings),
+            ),
+        ];
+        let diagnostics: Vec<Value> = collect_diagnostics(analysis, &self.specs)?
+            .iter()
+            .map(|diagnostic| diagnostic.to_json())
+            .collect();
+        let mut generator = generator_document(
+            &binary.path,
+            &binary.data,
+            self.options_json(),
+            !self.options.no_timestamp,
+        );
+        if let (Some(url), Some(input)) = (&self.source_url, generator["input"].as_object_mut()) {
+            let file = Path::new(&binary.path).file_name().unwrap_or_default();
+            input.insert("file".to_string(), file.to_string_lossy().into());
+            input.insert("url".to_string(), Value::String(url.clone()));
+        }
+        for (kind, manifest) in &mut manifests {
+            if let Some(manifest) = manifest.as_object_mut() {
+                manifest.insert("generator".to_string(), generator.clone());
+            }
+            if let (false, Some(manifest)) = (diagnostics.is_empty(), manifest.as_object_mut()) {
+                let diagnostics = Value::Array(diagnostics.clone());
+                manifest.insert("diagnostics".to_string(), diagnostics);
+            }
+            if let (Some(section), Some(manifest)) = (
+                self.options.plugins.section(analysis, *kind)?,
+                manifest.as_object_mut(),
+            ) {
+                manifest.insert("plugins".to_string(), section);
+            }
+        }
+        Ok(manifests
+            .into_iter()
+            .map(|(kind, manifest)| (kind.as_str().to_string(), manifest))
+            .collect())
+    }
+
+    // The options of the analysis with the names of the API list, sorted as the APIs found are.
+    fn options_json(&self) -> Value {
+        let mut options = self.options.to_json();
+        let names: BTreeSet<&str> = self.specs.iter().flat_map(|spec| spec.names()).collect();
+        if let Some(block) = options.as_object_mut() {
+            block.insert("api list".to_string(), serde_json::json!(names));
+        }
+        options
+    }
+
+    // Write the manifests built from an analysis and its other outputs to the directory `path`.
+    fn write_unsigned(
+        &self,
+        analysis: &Analysis,
+        manifests: &BTreeMap<String, Value>,
+        path: &str,
+    ) -> Result<()> {
+        let options = &self.options;
+        let binary = &analysis.binary;
+        let (elf, elf_data) = (binary.elf()?, &binary.data);
+        let (file_path, lang) = (binary.path.as_str(), &binary.language);
+        let (api_found, findings) = (&analysis.apis, &analysis.findings);
+
+        for (name, manifest) in manifests {
+            write_manifest(manifest, path, name, options.format)?;
+        }
+        coverage_manifest(&api_coverage(binary, &self.specs, api_found)?, path)?;
+        if let Some(functions) = &binary.go_functions {
+            go_manifest(functions, api_found, path)?;
+        }
+        if options.outputs.call_graph {
+            call_graph_manifest(&analysis.graph, api_found, path)?;
+        }
+        if options.outputs.cfg {
+            let mut cfgs = Vec::new();
+            for api in api_found {
+                cfgs.extend(ControlFlowGraph::build(
+                    &elf,
+                    elf_data,
+                    &api.name,
+                    api.start_addr,
+                    api.end_addr,
+                )?);
+            }
+            cfg_manifest(&cfgs, path)?;
+        }
+        if options.outputs.disassembly {
+            let mut listings = Vec::new();
+            for api in api_found {
+                listings.extend(Listing::build(
+                    &elf,
+                    elf_data,
+                    &analysis.graph,
+                    &api.name,
+                    api.start_addr,
+                    api.end_addr,
+                    options.disassembly_depth,
+                )?);
+            }
+            disassembly_manifest(&listings, path)?;
+        }
+        if options.outputs.cyclonedx {
+            let go = binary.go_build_info.as_ref();
+            cyclonedx_manifest(&elf, elf_data, file_path, lang, findings, go, path)?;
+        }
+        if options.outputs.spdx {
+            let go = binary.go_build_info.as_ref();
+            spdx_manifest(&elf, elf_data, file_path, findings, go, path)?;
+        }
+        if options.outputs.sarif {
+            sarif_manifest(&elf, file_path, findings, path)?;
+        }
+        if options.outputs.html {
+            html_manifest(&html_report(manifests, &analysis.graph, api_found), path)?;
+        }
+        if options.outputs.markdown {
+            markdown_manifest(&markdown_summary(manifests), path)?;
+        }
+        if options.outputs.seccomp {
+            let profile = SeccompProfile::new(&elf, &analysis.graph, analysis.observed.as_ref())?;
+            seccomp_manifest(&elf, &profile, path)?;
+        }
+        if options.outputs.apparmor {
+            let (graph, xrefs) = (&analysis.graph, &analysis.xrefs);
+            let (files, endpoints) = (&findings.files, &findings.endpoints);
+            let profile =
+                apparmor_profile(&elf, elf_data, graph, xrefs, file_path, files, endpoints)?;
+            apparmor_manifest(&profile, path)?;
+        }
+        if let Some(mud_url) = &options.mud_url {
+            mud_manifest(file_path, &findings.endpoints, mud_url, path)?;
+        }
+        if options.outputs.xrefs {
+            xrefs_manifest(&analysis.xrefs, &analysis.graph, api_found, path)?;
+        }
+        Ok(())
+    }
+
+    /// Analyze the binary and write its manifests to the directory `path`.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the results of the analysis.
+    pub fn run(&self, path: &str) -> Result<Analysis> {
+        let mut progress =
+            Progress::new(self.options.verbosity).with_listener(self.options.listener.clone());
+        let baseline = match self.options.incremental {
+            true => read_baseline(path),
+            false => None,
+        };
+        let mut analysis = self.analyze_with(&mut progress, baseline)?;
+        progress.phase("manifests");
+        let manifests = self.manifests(&analysis)?;
+        match analysis.incremental.take() {
+            Some(mut report) => {
+                // The manifests are written aside, then only those that changed are replaced.
+                let scratch = Path::new(path).join(".incremental");
+                let scratch = scratch.to_string_lossy();
+                fs::create_dir_all(&*scratch)?;
+                self.write_unsigned(&analysis, &manifests, &scratch)?;
+                (report.manifests_updated, report.manifests_unchanged) =
+                    patch_manifests(&scratch, path)?;
+                fs::remove_dir_all(&*scratch)?;
+                report.write(path)?;
+                if let Some(key) = &self.options.signing_key {
+                    sign_manifests(path, &analysis.binary.data, key)?;
+                }
+                progress.message(&report.summary());
+                analysis.incremental = Some(report);
+            }
+            None => self.write_signed(&analysis, &manifests, path)?,
+        }
+        if let Some(store) = &self.options.store {
+            self.store_results(&analysis, &manifests, store)?;
+        }
+        progress.finish();
+        Ok(analysis)
+    }
+
+    // Write the results of an analysis to the SQLite database `path`.
+    #[cfg(feature = "sqlite")]
+    fn store_results(
+        &self,
+        analysis: &Analysis,
+        manifests: &BTreeMap<String, Value>,
+        path: &str,
+    ) -> Result<()> {
+        ResultStore::open(path)?.insert(analysis, manifests)?;
+        Ok(())
+    }
+
+    // Without the `sqlite` feature, no database can be written.
+    #[cfg(not(feature = "sqlite"))]
+    fn store_results(
+        &self,
+        _analysis: &Analysis,
+        _manifests: &BTreeMap<String, Value>,
+        path: &str,
+    ) -> Result<()> {
+        Err(Error::InvalidConfig(format!(
+            "cannot write the results to {}: built without the `sqlite` feature",
+            path
+        )))
+    }
+
+    // Run the stages of the analysis, reporting each as a phase, starting from the analysis of
+    // the `baseline` build when incremental.
+    fn analyze_with(&self, progress: &mut Progress, baseline: Option<String>) -> Result<Analysis> {
+        progress.phase("parsing");
+        let binary = self.load()?;
+        let mut errors = Vec::new();
+        let elf = binary.elf()?;
+        // Only loaded in best-effort mode, from the dynamic symbols.
+        if is_stripped(&elf)
+            && binary.recognized_functions.is_none()
+            && binary.go_functions.is_none()
+        {
+            errors.push(Error::DebugInfo.with_context(ErrorContext::new("parsing")));
+        }
+        let statically = self.options.analysis != AnalysisMode::Dynamic;
+        if let (true, Some(table)) = (statically, vector_table(&elf, &binary.data)) {
+            return self.analyze_bare_metal(progress, binary, table, errors);
+        }
+        progress.phase("API detection");
+        let mut apis = self.recover(&mut errors, "API detection", self.detect_apis(&binary))?;
+        let key = match (&self.options.cache_dir, statically) {
+            (Some(_), true) => {
+                let key = cache_key(&binary.elf()?, &binary.data);
+                // The functions of a stripped binary depend on the signatures recognizing them.
+                Some(match &binary.recognized_functions {
+                    Some(functions) => format!("{}-{}", key, &names_digest(functions)[..16]),
+                    None => key,
+                })
+            }
+            _ => None,
+        };
+        let (graph, xrefs, reused) = match statically {
+            // Every function is disassembled once, then the flows of all APIs are read from the
+            // graph.
+            true => {
+                progress.phase("disassembly");
+                let (graph, xrefs, reused) = self
+                    .graph_and_xrefs(&binary, key.as_deref(), baseline.as_deref(), progress)
+                    .map_err(|error| error.with_context(ErrorContext::new("disassembly")))?;
+                progress.phase("flows");
+                self.trace_flows_with(&binary, &graph, &mut apis, &mut errors)?;
+                (graph, xrefs, reused)
+            }
+            false => {
+                let graph = CallGraph::default();
+                let xrefs = XrefDb::build(&binary.elf()?, &binary.data, &graph);
+                (graph, xrefs, BTreeSet::new())
+            }
+        };
+        let threads = thread_creations(&elf, &binary.data, &graph, &apis);
+        let threads = self.recover(&mut errors, "threads", threads)?;
+        let mut thread_flows = graph.root_apis(threads.iter().filter_map(|t| t.routine));
+        self.trace_flows_with(&binary, &graph, &mut thread_flows, &mut errors)?;
+        let rtos_tasks = self.rtos_tasks(&binary, &graph, &apis);
+        let (rtos, tasks, task_flows) = self.recover(&mut errors, "tasks", rtos_tasks)?;
+        let signals = signal_registrations(&elf, &binary.data, &graph, &apis);
+        let signals = self.recover(&mut errors, "signal handlers", signals)?;
+        let mut handler_flows = graph.root_apis(signals.iter().filter_map(|s| s.handler));
+        self.trace_flows_with(&binary, &graph, &mut handler_flows, &mut errors)?;
+        let constructors = constructors(&elf, &binary.data, &graph);
+        let rooted = |phase| {
+            let addrs = constructors.iter().filter(move |c| c.phase == phase);
+            graph.root_apis(addrs.map(|c| c.address))
+        };
+        let (mut startup_flows, mut shutdown_flows) =
+            (rooted(Phase::Startup), rooted(Phase::Shutdown));
+        self.trace_flows_with(&binary, &graph, &mut startup_flows, &mut errors)?;
+        self.trace_flows_with(&binary, &graph, &mut shutdown_flows, &mut errors)?;
+        let loading = dynamic_loading(&elf, &binary.data, &graph, &xrefs, &apis);
+        let loading = self.recover(&mut errors, "dynamic loading", loading)?;
+        let library_flows = match &self.options.library_root {
+            Some(root) => self.library_flows(Path::new(root), &loading),
+            None => Vec::new(),
+        };
+        let observed = match self.options.analysis {
+            AnalysisMode::Static => None,
+            AnalysisMode::Dynamic | AnalysisMode::Both => {
+                progress.phase("dynamic analysis");
+                let observed = trace(&binary.path, &binary.data, &self.options.dynamic);
+                self.recover(&mut errors, "dynamic analysis", observed.map(Some))?
+            }
+        };
+        progress.phase("data analysis");
+        let findings = self.collect_findings_with(&binary, &graph, &xrefs, &mut apis);
+        let findings = self.recover(&mut errors, "data analysis", findings)?;
+        let incremental = match (self.options.incremental, key) {
+            (true, Some(key)) => Some(IncrementalReport::new(
+                baseline, key, &graph, &reused, &apis,
+            )),
+            _ => None,
+        };
+        let correlation = match (self.options.analysis, &observed) {
+            (AnalysisMode::Both, Some(observed)) => Some(correlate(&graph, &apis, observed)),
+            _ => None,
+        };
+        let mut analysis = Analysis {
+            binary,
+            apis,
+            graph,
+            xrefs,
+            findings,
+            incremental,
+            plugins: BTreeMap::new(),
+            correlation,
+            observed,
+            threads,
+            thread_flows,
+            signals,
+            handler_flows,
+            loading,
+            library_flows,
+            constructors,
+            startup_flows,
+            shutdown_flows,
+            vector_table: None,
+            interrupt_flows: Vec::new(),
+            rtos,
+            tasks,
+            task_flows,
+            errors: Vec::new(),
+        };
+        self.finish_analysis(progress, &mut analysis, errors)?;
+        Ok(analysis)
+    }
+
+    // Analyze a Cortex-M image: the flows of the APIs and of the handlers of its vector table
+    // are read from the graph of its Thumb code. The other analyses of the code expect x86-64
+    // instructions, they only see its data. The APIs are optional, since the handlers are the
+    // roots of an image without an operating system.
+    fn analyze_bare_metal(
+        &self,
+        progress: &mut Progress,
+        binary: Binary,
+        table: VectorTable,
+        mut errors: Vec<Error>,
+    ) -> Result<Analysis> {
+        progress.phase("API detection");
+        let mut apis = match self.detect_apis(&binary) {
+            Err(Error::APIListEmpty) => Vec::new(),
+            apis => self.recover(&mut errors, "API detection", apis)?,
+        };
+        for api in &mut apis {
+            api.start_addr &= !1;
+            api.end_addr &= !1;
+        }
+        progress.phase("disassembly");
+        let graph = thumb_call_graph(&binary.elf()?, &binary.data);
+        progress.phase("flows");
+        self.trace_flows_with(&binary, &graph, &mut apis, &mut errors)?;
+        let mut interrupt_flows = graph.root_apis(table.handlers());
+        self.trace_flows_with(&binary, &graph, &mut interrupt_flows, &mut errors)?;
+        let rtos_tasks = self.rtos_tasks(&binary, &graph, &apis);
+        let (rtos, tasks, task_flows) = self.recover(&mut errors, "tasks", rtos_tasks)?;
+        progress.phase("data analysis");
+        let data_only = CallGraph::default();
+        let xrefs = XrefDb::build(&binary.elf()?, &binary.data, &data_only);
+        let findings = self.collect_findings_with(&binary, &data_only, &xrefs, &mut apis);
+        let findings = self.recover(&mut errors, "data analysis", findings)?;
+        let mut analysis = Analysis {
+            binary,
+            apis,
+            graph,
+            xrefs,
+            findings,
+            incremental: None,
+            plugins: BTreeMap::new(),
+            correlation: None,
+            observed: None,
+            threads: Vec::new(),
+            thread_flows: Vec::new(),
+            signals: Vec::new(),
+            handler_flows: Vec::new(),
+            loading: DynamicLoading::default(),
+            library_flows: Vec::new(),
+            constructors: Vec::new(),
+            startup_flows: Vec::new(),
+            shutdown_flows: Vec::new(),
+            vector_table: Some(table),
+            interrupt_flows,
+            rtos,
+            tasks,
+            task_flows,
+            errors: Vec::new(),
+        };
+        self.finish_analysis(progress, &mut analysis, errors)?;
+        Ok(analysis)
+    }
+
+    // Run the plugins on an analysis, then keep the errors recovered from and report them.
+    fn finish_analysis(
+        &self,
+        progress: &mut Progress,
+        analysis: &mut Analysis,
+        mut errors: Vec<Error>,
+    ) -> Result<()> {
+        if !self.options.plugins.is_empty() {
+            progress.phase("plugins");
+            let plugins = self.options.plugins.run_passes(analysis);
+            analysis.plugins = self.recover(&mut errors, "plugins", plugins)?;
+        }
+        for error in &errors {
+            progress.message(&format!("{}: {}", analysis.binary.path, error));
+        }
+        analysis.errors = errors;
+        Ok(())
+    }
+
+    // Give the error of an optional stage the phase it occurred in. In best-effort mode, the
+    // error is recorded in `errors` and the analysis goes on without the result of the stage,
+    // unless the inputs are invalid or the binary is packed.
+    fn recover<T: Default>(
+        &self,
+        errors: &mut Vec<Error>,
+        phase: &str,
+        result: Result<T>,
+    ) -> Result<T> {
+        let error = match result {
+            Ok(value) => return Ok(value),
+            Err(error) => error.with_context(ErrorContext::new(phase)),
+        };
+        match (self.options.best_effort, error.kind()) {
+            (false, _) | (true, "invalid-input" | "packed-binary") => Err(error),
+            (true, _) => {
+                errors.push(error);
+                Ok(T::default())
+            }
+        }
+    }
+
+    // Detect the RTOS of the binary, then trace the flow of each task it creates. The tasks are
+    // only looked for in the firmware built on an RTOS.
+    fn rtos_tasks(
+        &self,
+        binary: &Binary,
+        graph: &CallGraph,
+        apis: &[API],
+    ) -> Result<(Option<Rtos>, Vec<TaskCreation>, Vec<API>)> {
+        let elf = binary.elf()?;
+        let Some(rtos) = detect_rtos(&elf, &binary.data) else {
+            return Ok((None, Vec::new(), Vec::new()));
+        };
+        let tasks = task_creations(&elf, &binary.data, graph, apis)?;
+        let mut task_flows = graph.root_apis(tasks.iter().filter_map(|t| t.entry));
+        self.trace_flows(binary, graph, &mut task_flows)?;
+        Ok((Some(rtos), tasks, task_flows))
+    }
+
+    // Trace the flows of the symbols looked up with `dlsym` in each library loaded with a
+    // constant path and found under `root`. A library that cannot be analyzed is kept with the
+    // error.
+    fn library_flows(&self, root: &Path, loading: &DynamicLoading) -> Vec<LibraryFlows> {
+        let symbols: Vec<ApiSpec> = loading
+            .symbol_names()
+            .iter()
+            .map(|name| ApiSpec::new(name))
+            .collect();
+        if symbols.is_empty() {
+            return Vec::new();
+        }
+        let mut libraries: Vec<&str> = loading
+            .libraries
+            .iter()
+            .filter_map(|load| load.library.as_deref())
+            .collect();
+        libraries.sort_unstable();
+        libraries.dedup();
+        let options = Options {
+            verbosity: Verbosity::Quiet,
+            budget: self.options.budget,
+            max_depth: self.options.max_depth,
+            exclude_libraries: self.options.exclude_libraries.clone(),
+            cache_dir: self.options.cache_dir.clone(),
+            ..Options::default()
+        };
+
+        let mut flows = Vec::new();
+        for library in libraries {
+            let Some(path) = resolve_library(root, library) else {
+                continue;
+            };
+            let path = path.to_string_lossy().to_string();
+            let analysis = Analyzer::new(&path)
+                .with_api_list(symbols.clone())
+                .with_options(options.clone())
+                .analyze();
+            let (apis, error) = match analysis {
+                Ok(analysis) => (analysis.apis, None),
+                Err(error) => (Vec::new(), Some(error.to_string())),
+            };
+            flows.push(LibraryFlows {
+                library: library.to_string(),
+                path,
+                flows: apis,
+                error,
+            });
+        }
+        flows
+    }
+
+    // Build the call graph and the cross-references of a binary, from the cache entry `key`
+    // when it was analyzed before, else disassembling only the functions changed since the
+    // `baseline` build and caching the results. Returns them with the functions reused.
+    fn graph_and_xrefs(
+        &self,
+        binary: &Binary,
+        key: Option<&str>,
+        baseline: Option<&str>,
+        progress: &Progress,
+    ) -> Result<(CallGraph, XrefDb, BTreeSet<u64>)> {
+        let observer = |func: &FunctionNode, total| progress.item(&func.name, total);
+        let elf = binary.elf()?;
+        let (Some(dir), Some(key)) = (&self.options.cache_dir, key) else {
+            let graph = self.build_graph_observed(binary, &observer)?;
+            let xrefs = XrefDb::build(&elf, &binary.data, &graph);
+            return Ok((graph, xrefs, BTreeSet::new()));
+        };
+        let cache = AnalysisCache::new(dir);
+        if let Some(cached) = cache.load(key) {
+            let reused = cached
+                .graph
+                .functions()
+                .map(|func| func.start_addr)
+                .collect();
+            return Ok((cached.graph, cached.xrefs, reused));
+        }
+
+        let nodes = self.function_nodes(binary, &elf);
+        let hashes = function_hashes(&elf, &binary.data, &nodes);
+        let rust = binary.language.contains("Rust");
+        let previous = baseline
+            .filter(|baseline| *baseline != key)
+            .and_then(|baseline| cache.load(baseline));
+        let (graph, xrefs, reused) = match previous {
+            Some(previous) => {
+                let unchanged = hashes
+                    .iter()
+                    .filter(|(addr, hash)| previous.hashes.get(addr) == Some(hash))
+                    .map(|(addr, _)| *addr)
+                    .collect();
+                let (graph, reused) = CallGraph::build_incremental(
+                    &elf,
+                    &binary.data,
+                    rust,
+                    nodes,
+                    (&previous.graph, &unchanged),
+                    &self.options.budget,
+                    &observer,
+                )?;
+                let xrefs = XrefDb::build(&elf, &binary.data, &graph);
+                (graph, xrefs, reused)
+            }
+            None => {
+                let graph = CallGraph::build_budgeted(
+                    &elf,
+                    &binary.data,
+                    rust,
+                    nodes,
+                    &self.options.budget,
+                    &observer,
+                )?;
+                let xrefs = XrefDb::build(&elf, &binary.data, &graph);
+                (graph, xrefs, BTreeSet::new())
+            }
+        };
+        // The cache only saves time, the analysis goes on when it cannot be written. The graphs
+        // cut short by the budget are not cached, to be completed by a run with a larger one.
+        if graph.is_complete() {
+            if let Err(error) = cache.store(key, &graph, &xrefs, &hashes) {
+                warn!(%error, "the analysis could not be cached");
+            }
+        }
+        Ok((graph, xrefs, reused))
+    }
+
+    // Build the call graph, calling `observer` before each function is disassembled.
+    fn build_graph_observed(
+        &self,
+        binary: &Binary,
+        observer: &(dyn Fn(&FunctionNode, usize) + Sync),
+    ) -> Result<CallGraph> {
+        let elf = binary.elf()?;
+        let nodes = self.function_nodes(binary, &elf);
+        let rust = binary.language.contains("Rust");
+        CallGraph::build_budgeted(
+            &elf,
+            &binary.data,
+            rust,
+            nodes,
+            &self.options.budget,
+            observer,
+        )
+    }
+
+    // The functions of a binary: those of the `.gopclntab` of Go binaries, those recognized in
+    // other stripped binaries, else those of the symbol table.
+    fn function_nodes(&self, binary: &Binary, elf: &Elf) -> Vec<FunctionNode> {
+        match (&binary.go_functions, &binary.recognized_functions) {
+            (Some(functions), _) => functions
+                .iter()
+                .map(|func| FunctionNode {
+                    name: func.name.clone(),
+                    start_addr: func.start_addr,
+                    end_addr: func.end_addr,
+                })
+                .collect(),
+            (None, Some(functions)) => functions
+                .iter()
+                .map(|func| FunctionNode {
+                    name: func.name.clone(),
+                    start_addr: func.start_addr,
+                    end_addr: func.end_addr,
+                })
+                .collect(),
+            (None, None) => function_nodes(elf).into_values().collect(),
+        }
+    }
+}
+
+// Where the flow of an API failed: the API and its address.
+fn flow_context(api: &API) -> ErrorContext {
+    ErrorContext::new("flows")
+        .with_function(&api.name)
+        .with_offset(api.start_addr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signatures::generate_signatures;
+    use std::{env::temp_dir, fs, path::Path};
+
+    #[test]
+    fn test_analyzer() {
+        let analyzer = Analyzer::new("./tests/elf_file/fake-firmware-c-dynamic")
+            .with_api_list(vec![
+                ApiSpec::new("writeOnDrive"),
+                ApiSpec::new("turnLampOn"),
+            ])
+            .with_outputs(Outputs {
+                sarif: true,
+                seccomp: true,
+                apparmor: true,
+                disassembly: true,
+                ..Outputs::default()
+            })
+            .with_timestamp(false)
+            .with_stack_threshold(64)
+            .with_verbosity(Verbosity::Quiet);
+
+        let binary = analyzer.load().unwrap();
+        assert_eq!(binary.language, "C99");
+        let apis = analyzer.detect_apis(&binary).unwrap();
+        let names: Vec<&str> = apis.iter().map(|api| api.name.as_str()).collect();
+        assert_eq!(names.len(), 2);
+        assert!(names.contains(&"writeOnDrive"));
+
+        let dir = temp_dir().join(format!("manifest-analyzer-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let analysis = analyzer.run(dir.to_str().unwrap()).unwrap();
+        let write = analysis
+            .apis
+            .iter()
+            .find(|api| api.name == "writeOnDrive")
+            .unwrap();
+        assert!(write.syscalls.iter().any(|call| call.starts_with("fopen")));
+        assert_eq!(write.syscall_arguments[0].arguments["path"], "foo.txt");
+        let complexity = write.complexity.unwrap();
+        assert!(complexity.cyclomatic >= 1 && complexity.basic_blocks >= 1);
+        assert!(complexity.instructions >= complexity.basic_blocks);
+        assert!(Path::new(&dir.join("findings.sarif")).is_file());
+        assert!(Path::new(&dir.join("seccomp.json")).is_file());
+        assert!(Path::new(&dir.join("seccomp.bpf")).is_file());
+        assert!(Path::new(&dir.join("apparmor.profile")).is_file());
+        let listing = fs::read_to_string(dir.join("writeOnDrive.disasm.txt")).unwrap();
+        assert!(listing.contains("-> fopen64 [syscalls: openat"));
+        assert!(Path::new(&dir.join("flow_call.json")).is_file());
+
+        // In memory, the same manifests as written.
+        let bytes = fs::read("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let in_memory = Analyzer::from_bytes("./tests/elf_file/fake-firmware-c-dynamic", bytes)
+            .with_api_list(vec![
+                ApiSpec::new("writeOnDrive"),
+                ApiSpec::new("turnLampOn"),
+            ])
+            .with_options(analyzer.options.clone());
+        let manifests = in_memory.manifests(&in_memory.analyze().unwrap()).unwrap();
+        assert_eq!(
+            manifests["feature_manifest"]["complexity"]["writeOnDrive"]["basic blocks"],
+            complexity.basic_blocks
+        );
+        let unreachable = &manifests["flow_call"]["unreachable functions"];
+        let dead: Vec<&str> = unreachable["functions"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|func| func["name"].as_str())
+            .collect();
+        assert!(dead.contains(&"curl_easy_pause") && !dead.contains(&"writeOnDrive"));
+        let stack = &manifests["flow_call"]["stack usage"];
+        assert_eq!(stack["threshold"], 64);
+        assert_eq!(stack["flows"]["writeOnDrive"]["worst case"], 32);
+        assert!(!stack["exceeding"]
+            .as_array()
+            .unwrap()
+            .contains(&Value::from("writeOnDrive")));
+        for (name, manifest) in &manifests {
+            let written = fs::read(dir.join(format!("{}.json", name))).unwrap();
+            assert_eq!(
+                *manifest,
+                serde_json::from_slice::<Value>(&written).unwrap()
+            );
+        }
+
+        let missing = Analyzer::new("./tests/elf_file/fake-firmware-c-dynamic")
+            .with_api_list(vec![ApiSpec::new("notAnApi")]);
+        assert!(matches!(
+            missing.detect_apis(&binary),
+            Err(Error::APIListEmpty)
+        ));
+        assert!(matches!(
+            Analyzer::new("./tests/elf_file/fake-firmware-c-dynamic")
+                .with_arch("aarch64")
+                .load(),
+            Err(Error::ArchMismatch { .. })
+        ));
+        let mut packed = fs::read("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        packed.extend_from_slice(b"UPX!");
+        assert!(matches!(
+            Analyzer::from_bytes("packed", packed).load(),
+            Err(Error::PackedBinary(_))
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_stripped_analysis() {
+        let path = "./tests/elf_file/minimal-fake-firmware-c-static";
+        let bytes = fs::read(path).unwrap();
+        let elf = Elf::parse(&bytes).unwrap();
+        let dir = temp_dir().join(format!("manifest-signatures-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let pat = dir.join("firmware.pat");
+        let signatures = generate_signatures(&elf, &bytes, "firmware").unwrap();
+        fs::write(&pat, signatures.to_pat()).unwrap();
+
+        // The binary stripped of its symbol table.
+        let mut stripped = bytes.clone();
+        let symtab = elf
+            .section_headers
+            .iter()
+            .position(|sh| sh.sh_type == goblin::elf::section_header::SHT_SYMTAB)
+            .unwrap();
+        let sh_type = elf.header.e_shoff as usize + symtab * elf.header.e_shentsize as usize + 4;
+        stripped[sh_type..sh_type + 4].fill(0);
+        let analyzer = |signatures: Vec<String>| {
+            Analyzer::from_bytes(path, stripped.clone())
+                .with_api_list(vec![ApiSpec::new("writeOnDrive")])
+                .with_options(Options {
+                    signatures,
+                    verbosity: Verbosity::Quiet,
+                    ..Options::default()
+                })
+        };
+        assert!(matches!(analyzer(Vec::new()).load(), Err(Error::DebugInfo)));
+        let best_effort = analyzer(Vec::new()).with_best_effort(true);
+        let analysis = best_effort.analyze().unwrap();
+        let kinds: Vec<&str> = analysis.errors.iter().map(Error::kind).collect();
+        assert_eq!(kinds, ["stripped-binary", "no-api-found"]);
+        let manifests = best_effort.manifests(&analysis).unwrap();
+        assert_eq!(
+            manifests["basic_info"]["errors"][1]["context"]["phase"],
+            "API detection"
+        );
+
+        let analyzer = analyzer(vec![pat.to_string_lossy().to_string()]);
+        let analysis = analyzer.analyze().unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(analysis.apis.len(), 1);
+        assert!(analysis.apis[0]
+            .syscalls
+            .iter()
+            .any(|call| call.contains("fopen")));
+        let manifests = analyzer.manifests(&analysis).unwrap();
+        let recognized = &manifests["basic_info"]["recognized functions"];
+        assert!(recognized["libraries"]["firmware"].as_u64().unwrap() > 0);
+    }
+
+    #[test]
+    fn test_library_flows() {
+        // A firmware tree whose plugin, loaded by name, defines the symbol looked up.
+        let root = temp_dir().join(format!("manifest-plugins-{}", std::process::id()));
+        fs::create_dir_all(root.join("usr/lib")).unwrap();
+        fs::copy(
+            "./tests/elf_file/fake-firmware-c-dynamic",
+            root.join("usr/lib/libplugin.so"),
+        )
+        .unwrap();
+        fs::write(root.join("usr/lib/libbroken.so"), b"not an ELF").unwrap();
+        let load = |library: &str| crate::dynamic_loading::LibraryLoad {
+            function: "dlopen".to_string(),
+            site: 0x1000,
+            caller: "load_plugins".to_string(),
+            library: Some(library.to_string()),
+            flags: Some(2),
+            apis: Vec::new(),
+        };
+        let loading = DynamicLoading {
+            libraries: vec![
+                load("libplugin.so"),
+                load("libbroken.so"),
+                load("libnone.so"),
+            ],
+            symbols: vec![crate::dynamic_loading::SymbolLookup {
+                function: "dlsym".to_string(),
+                site: 0x1010,
+                caller: "load_plugins".to_string(),
+                symbol: Some("writeOnDrive".to_string()),
+                handle: None,
+                apis: Vec::new(),
+            }],
+        };
+        let analyzer = Analyzer::new("firmware").with_verbosity(Verbosity::Quiet);
+        let flows = analyzer.library_flows(&root, &loading);
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(flows.len(), 2);
+        assert_eq!(flows[0].library, "libbroken.so");
+        assert!(flows[0].error.is_some() && flows[0].flows.is_empty());
+        assert_eq!(flows[1].library, "libplugin.so");
+        assert_eq!(flows[1].error, None);
+        assert_eq!(flows[1].flows[0].name, "writeOnDrive");
+        assert!(flows[1].flows[0]
+            .syscalls
+            .iter()
+            .any(|call| call.starts_with("fopen")));
+        assert_eq!(flows[1].to_json()["flows"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_bare_metal() {
+        // No API of the list is in the image: the handlers are the roots of its flows.
+        let analyzer = Analyzer::new("./tests/elf_file/minimal-fake-firmware-cortex-m")
+            .with_api_list(vec![ApiSpec::new("writeOnDrive")])
+            .with_verbosity(Verbosity::Quiet);
+        let analysis = analyzer.analyze().unwrap();
+        assert!(analysis.apis.is_empty());
+        let table = analysis.vector_table.as_ref().unwrap();
+        assert_eq!(table.handlers().len(), analysis.interrupt_flows.len());
+        let uart = analysis
+            .interrupt_flows
+            .iter()
+            .find(|flow| flow.name == "UART0_IRQHandler")
+            .unwrap();
+        assert_eq!(uart.transitive_calls, ["uart_read", "ring_push"]);
+
+        let analysis = Analyzer::new("./tests/elf_file/minimal-fake-firmware-cortex-m")
+            .with_api_list(vec![ApiSpec::new("main")])
+            .with_verbosity(Verbosity::Quiet)
+            .analyze()
+            .unwrap();
+        assert_eq!(analysis.apis[0].start_addr & 1, 0);
+        assert!(analysis.apis[0]
+            .transitive_calls
+            .contains(&"write_reg".to_string()));
+        let manifests = analyzer.manifests(&analysis).unwrap();
+        let flow_call = &manifests["flow_call"];
+        assert_eq!(flow_call["vector table"]["reset handler"], "Reset_Handler");
+        assert_eq!(flow_call["Interrupt flows"][0]["name"], "Reset_Handler");
+        assert!(analysis.rtos.is_none() && analysis.tasks.is_empty());
+    }
+
+    #[test]
+    fn test_scope() {
+        assert_eq!("api-list".parse::<Scope>().unwrap(), Scope::ApiList);
+        assert!(matches!(
+            "functions".parse::<Scope>(),
+            Err(Error::InvalidScope(_))
+        ));
+
+        // Every function is an API, none of the list is needed.
+        let analyzer = Analyzer::new("./tests/elf_file/minimal-fake-firmware-cortex-m")
+            .with_scope(Scope::All)
+            .with_verbosity(Verbosity::Quiet);
+        let analysis = analyzer.analyze().unwrap();
+        let names: Vec<&str> = analysis.apis.iter().map(|api| api.name.as_str()).collect();
+        assert_eq!(names.len(), 16);
+        assert!(names.contains(&"main") && names.contains(&"process_command"));
+        let uart = analysis
+            .apis
+            .iter()
+            .find(|api| api.name == "UART0_IRQHandler")
+            .unwrap();
+        assert_eq!(uart.transitive_calls, ["uart_read", "ring_push"]);
+        let manifests = analyzer.manifests(&analysis).unwrap();
+        assert_eq!(
+            manifests["flow_call"]["Public APIs flow"]
+                .as_array()
+                .map(Vec::len),
+            Some(16)
+        );
+    }
+
+    #[test]
+    fn test_best_effort() {
+        let analyzer = Analyzer::new("./tests/elf_file/fake-firmware-c-dynamic")
+            .with_api_list(vec![ApiSpec::new("notAnApi")])
+            .with_verbosity(Verbosity::Quiet);
+        let error = analyzer.analyze().err().unwrap();
+        assert!(matches!(error.root(), Error::APIListEmpty));
+        assert_eq!(error.context().unwrap().phase, "API detection");
+        assert_eq!(error.exit_code(), crate::error::EXIT_NO_API);
+
+        let analyzer = analyzer.with_best_effort(true);
+        let analysis = analyzer.analyze().unwrap();
+        assert!(analysis.apis.is_empty());
+        assert_eq!(analysis.errors.len(), 1);
+        let manifests = analyzer.manifests(&analysis).unwrap();
+        assert_eq!(
+            manifests["basic_info"]["errors"][0]["error"],
+            "no-api-found"
+        );
+        assert!(manifests.contains_key("feature_manifest"));
+    }
+
+    #[test]
+    fn test_rtos_tasks() {
+        let analyzer = Analyzer::new("./tests/elf_file/minimal-fake-firmware-freertos")
+            .with_api_list(vec![ApiSpec::new("main")])
+            .with_verbosity(Verbosity::Quiet);
+        let analysis = analyzer.analyze().unwrap();
+        assert_eq!(analysis.rtos.as_ref().unwrap().name, "FreeRTOS");
+        assert_eq!(analysis.tasks.len(), 2);
+        let names: Vec<&str> = analysis
+            .task_flows
+            .iter()
+            .map(|f| f.name.as_str())
+            .collect();
+        assert_eq!(names, ["vSensorTask", "vNetworkTask"]);
+
+        let manifests = analyzer.manifests(&analysis).unwrap();
+        assert_eq!(manifests["basic_info"]["rtos"]["version"], "10.4.6");
+        assert_eq!(manifests["flow_call"]["tasks"][0]["task"], "sensor");
+        assert_eq!(
+            manifests["flow_call"]["Task flows"]
+                .as_array()
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_reproducible() {
+        // The same binary and options give the same bytes, whatever the order of the API list.
+        let manifests = |specs: Vec<ApiSpec>| {
+            let analyzer = Analyzer::new("./tests/elf_file/fake-firmware-c-dynamic")
+                .with_api_list(specs)
+                .with_timestamp(false)
+                .with_verbosity(Verbosity::Quiet);
+            let analysis = analyzer.analyze().unwrap();
+            serde_json::to_string_pretty(&analyzer.manifests(&analysis).unwrap()).unwrap()
+        };
+        let first = manifests(vec![
+            ApiSpec::new("writeOnDrive"),
+            ApiSpec::new("turnLampOn"),
+        ]);
+        let second = manifests(vec![
+            ApiSpec::new("turnLampOn"),
+            ApiSpec::new("writeOnDrive"),
+        ]);
+        assert_eq!(first, second);
+        assert!(first.find("\"turnLampOn\"") < first.find("\"writeOnDrive\""));
+    }
+
+    #[test]
+    fn test_generator() {
+        let analyzer = Analyzer::new("./tests/elf_file/fake-firmware-c-dynamic")
+            .with_api_list(vec![ApiSpec::new("writeOnDrive")])
+            .with_max_depth(4)
+            .with_verbosity(Verbosity::Quiet);
+        let analysis = analyzer.analyze().unwrap();
+        let manifests = analyzer.manifests(&analysis).unwrap();
+        let generator = &manifests["flow_call"]["generator"];
+        assert_eq!(generator["name"], "manifest-producer");
+        assert_eq!(generator["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(
+            generator["input"]["sha256"],
+            manifests["basic_info"]["sha256"]
+        );
+        assert_eq!(generator["options"]["max depth"], 4);
+        assert_eq!(
+            generator["options"]["api list"],
+            serde_json::json!(["writeOnDrive"])
+        );
+        assert!(generator["timestamp"].is_string());
+        assert!(generator["options"].get("dynamic").is_none());
+        for kind in [ManifestKind::BasicInfo, ManifestKind::Feature] {
+            assert_eq!(manifests[kind.as_str()]["generator"], *generator);
+        }
+
+        let undated = analyzer.with_timestamp(false);
+        let manifests = undated.manifests(&analysis).unwrap();
+        assert!(manifests["basic_info"]["generator"]
+            .get("timestamp")
+            .is_none());
+        assert!(generator["input"].get("url").is_none());
+
+        let url = "https://releases.example.com/fake-firmware-c-dynamic";
+        let downloaded = undated.with_source_url(url);
+        let manifests = downloaded.manifests(&analysis).unwrap();
+        let input = &manifests["basic_info"]["generator"]["input"];
+        assert_eq!(input["url"], url);
+        assert_eq!(input["file"], "fake-firmware-c-dynamic");
+    }
+}
@@ -1,29 +1,232 @@
 use goblin::elf::Elf;
+use regex::Regex;
+use tracing::{debug, instrument};
 
-use crate::{elf_utils, error};
-use elf_utils::API;
-use error::Result;
+use crate::{cleanup::demangle_api_name, elf_utils, error};
+use elf_utils::{FuzzyMatch, API};
+use error::{Error, Result};
+
+/// A single entry of the API list, compiled for matching against symbol names.
+///
+/// Every kind of pattern is anchored, so it has to match the whole symbol name:
+/// * entries starting with `^` or ending with `$` are regular expressions, e.g. `^turnLamp(On|Off)$`;
+/// * entries containing `*`, `?` or `[` are globs, where `*` matches within a single path
+///   segment (no `:`), `**` matches across segments and `?` matches one character, e.g. `net::*::connect`;
+/// * any other entry is compared literally.
+pub enum ApiPattern {
+    /// A literal API name.
+    Exact(String),
+    /// A glob or a regular expression, translated into an anchored regex.
+    Pattern(Regex),
+}
+
+impl ApiPattern {
+    /// Compiles an entry of the API list.
+    pub fn new(entry: &str) -> Result<Self> {
+        let source = if entry.starts_with('^') || entry.ends_with('$') {
+            format!(
+                "^(?:{})$",
+                entry.trim_start_matches('^').trim_end_matches('$')
+            )
+        } else if entry.contains(['*', '?', '[']) {
+            glob_to_regex(entry)
+        } else {
+            return Ok(Self::Exact(entry.to_string()));
+        };
+        Regex::new(&source)
+            .map(Self::Pattern)
+            .map_err(|err| Error::InvalidPattern(format!("{}: {}", entry, err)))
+    }
+
+    /// Checks whether the pattern matches the whole name.
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            Self::Exact(api) => api == name,
+            Self::Pattern(regex) => regex.is_match(name),
+        }
+    }
+}
+
+/// An entry of the API list, with the metadata the vendor declared for it.
+///
+/// The API list is a JSON array whose entries are either plain names (or patterns), or objects:
+///
+/// ```json
+/// [
+///     "turnLampOn",
+///     {
+///         "name": "accessNetwork",
+///         "capability": "network",
+///         "parameters": ["url"],
+///         "aliases": ["access_network", "net_access"]
+///     }
+/// ]
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct ApiSpec {
+    /// The name or pattern of the API.
+    pub name: String,
+    /// The capability the API is expected to exercise, e.g. `network`.
+    pub capability: Option<String>,
+    /// The expected parameters of the API.
+    pub parameters: Vec<String>,
+    /// Alternative names under which the API can be found.
+    pub aliases: Vec<String>,
+}
+
+impl ApiSpec {
+    /// Creates an entry without metadata.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            capability: None,
+            parameters: Vec::new(),
+            aliases: Vec::new(),
+        }
+    }
+
+    /// Checks whether the entry declares any metadata besides its name.
+    pub fn has_metadata(&self) -> bool {
+        self.capability.is_some() || !self.parameters.is_empty() || !self.aliases.is_empty()
+    }
+
+    /// Returns the name and the aliases of the entry.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.name.as_str()).chain(self.aliases.iter().map(String::as_str))
+    }
+}
+
+/// Parse the JSON API list, accepting both plain strings and objects with metadata.
+///
+/// # Arguments
+///
+/// * `json` - The JSON value read from the API list file.
+///
+/// # Returns
+///
+/// Returns a `Result` containing one `ApiSpec` per entry of the list.
+pub fn parse_api_list(json: &serde_json::Value) -> Result<Vec<ApiSpec>> {
+    let entries = json
+        .as_array()
+        .ok_or_else(|| Error::InvalidApiList("expected an array".to_string()))?;
+    entries
+        .iter()
+        .map(|entry| match entry {
+            serde_json::Value::String(name) => Ok(ApiSpec::new(name)),
+            serde_json::Value::Object(object) => {
+                let name = object
+                    .get("name")
+                    .and_then(|name| name.as_str())
+                    .ok_or_else(|| Error::InvalidApiList(format!("missing name in {}", entry)))?;
+                let strings = |key: &str| -> Result<Vec<String>> {
+                    match object.get(key) {
+                        None => Ok(Vec::new()),
+                        Some(serde_json::Value::Array(values)) => values
+                            .iter()
+                            .map(|value| {
+                                value.as_str().map(str::to_string).ok_or_else(|| {
+                                    Error::InvalidApiList(format!(
+                                        "{} of {} must be strings",
+                                        key, name
+                                    ))
+                                })
+                            })
+                            .collect(),
+                        Some(_) => Err(Error::InvalidApiList(format!(
+                            "{} of {} must be an array",
+                            key, name
+                        ))),
+                    }
+                };
+                Ok(ApiSpec {
+                    name: name.to_string(),
+                    capability: object
+                        .get("capability")
+                        .and_then(|capability| capability.as_str())
+                        .map(str::to_string),
+                    parameters: strings("parameters")?,
+                    aliases: strings("aliases")?,
+                })
+            }
+            _ => Err(Error::InvalidApiList(format!("unexpected entry {}", entry))),
+        })
+        .collect()
+}
+
+/// Attach to each API found the entry of the API list it was requested by.
+///
+/// # Arguments
+///
+/// * `api_found` - The APIs found in the binary.
+/// * `specs` - The entries of the API list.
+///
+/// # Returns
+///
+/// Returns a `Result` indicating success or failure.
+pub fn attach_specs(api_found: &mut [API], specs: &[ApiSpec]) -> Result<()> {
+    for spec in specs.iter().filter(|spec| spec.has_metadata()) {
+        let names: Vec<&str> = spec.names().collect();
+        let patterns = compile_patterns(&names)?;
+        for api in api_found.iter_mut().filter(|api| api.spec.is_none()) {
+            let requested = api
+                .fuzzy_match
+                .as_ref()
+                .map(|fuzzy| fuzzy.requested.as_str());
+            if patterns.iter().any(|pattern| {
+                pattern.matches(&api.name) || requested.is_some_and(|name| pattern.matches(name))
+            }) {
+                api.spec = Some(spec.clone());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Compiles every entry of the API list.
+///
+/// # Arguments
+///
+/// * `api_list` - A vector containing the names or patterns of the APIs to search for.
+///
+/// # Returns
+///
+/// Returns a `Result` containing the compiled patterns, or an error for the first invalid entry.
+pub fn compile_patterns(api_list: &[&str]) -> Result<Vec<ApiPattern>> {
+    api_list
+        .iter()
+        .map(|entry| ApiPattern::new(entry))
+        .collect()
+}
 
 /// Do an API lookup in the symbol table.
 ///
-/// This function searches for APIs in the symbol table of the ELF file based on a list of API names provided.
+/// This function searches for APIs in the symbol table of the ELF file based on a list of API names
+/// or patterns provided (see [`ApiPattern`]).
+/// Mangled symbols are also compared in their demangled form, so C++ methods can be requested
+/// as `Camera::open`.
 ///
 /// # Arguments
 ///
 /// * `elf` - The ELF file structure.
-/// * `api_list` - A vector containing the names of the APIs to search for.
+/// * `api_list` - A vector containing the names or patterns of the APIs to search for.
 ///
 /// # Returns
 ///
 /// Returns a `Result` containing a vector of `API` structures representing the APIs found.
+#[instrument(skip_all, fields(apis = api_list.len()))]
 pub fn api_search<'a>(elf: &'a Elf<'a>, api_list: &'a [&'a str]) -> Result<Vec<API>> {
+    let patterns = compile_patterns(api_list)?;
     let mut api_found = Vec::new();
     for symbol in &elf.syms {
         if symbol.st_type() == goblin::elf::sym::STT_FUNC && symbol.st_shndx != 0 {
             if let Some(function_name) = get_name_sym(elf, &symbol.to_owned()) {
-                if api_list.contains(&function_name) {
+                let demangled_name = demangle_api_name(function_name);
+                if patterns.iter().any(|pattern| {
+                    pattern.matches(function_name) || pattern.matches(&demangled_name)
+                }) {
+                    debug!(symbol = function_name, api = %demangled_name, "API found");
                     api_found.push(API::new(
-                        function_name.to_string(),
+                        demangled_name,
                         symbol.st_value,
                         symbol.st_value + symbol.st_size,
                     ));
@@ -31,12 +234,335 @@ pub fn api_search<'a>(elf: &'a Elf<'a>, api_list: &'a [&'a str]) -> Result<Vec<A
             }
         }
     }
+    debug!(found = api_found.len(), "API search done");
     Ok(api_found)
 }
 
+/// A symbol that may correspond to a requested API, with its similarity score.
+pub struct ApiCandidate {
+    /// The (demangled) name of the symbol.
+    pub symbol: String,
+    /// The similarity between the requested name and the symbol, from 0 to 1.
+    pub confidence: f64,
+}
+
+/// Rank the symbols that may correspond to a requested API name.
+///
+/// Names are compared after splitting them into words, so `accessWebcam`, `access_webcam` and
+/// `fw::access_webcam` are considered equal, while vendor prefixes and small spelling differences
+/// lower the score.
+///
+/// # Arguments
+///
+/// * `requested` - The API name as written in the API list.
+/// * `symbols` - The names of the candidate symbols.
+///
+/// # Returns
+///
+/// Returns the candidates sorted by decreasing confidence.
+pub fn rank_candidates(requested: &str, symbols: &[String]) -> Vec<ApiCandidate> {
+    let mut candidates: Vec<ApiCandidate> = symbols
+        .iter()
+        .map(|symbol| ApiCandidate {
+            symbol: symbol.clone(),
+            confidence: similarity(requested, symbol),
+        })
+        .filter(|candidate| candidate.confidence > 0.0)
+        .collect();
+    candidates.sort_by(|a, b| {
+        b.confidence
+            .total_cmp(&a.confidence)
+            .then_with(|| a.symbol.cmp(&b.symbol))
+    });
+    candidates
+}
+
+/// Search the symbol table for the APIs that were not found under their exact name.
+///
+/// For every literal entry of the API list without a match in `api_found`, the best ranked
+/// function symbol is selected when its confidence reaches `min_confidence`.
+///
+/// # Arguments
+///
+/// * `elf` - The ELF file structure.
+/// * `api_list` - A vector containing the names or patterns of the APIs to search for.
+/// * `api_found` - The APIs already found by [`api_search`].
+/// * `min_confidence` - The minimum confidence, from 0 to 1, for a candidate to be accepted.
+///
+/// # Returns
+///
+/// Returns a `Result` containing the additional APIs, each one carrying its `FuzzyMatch`.
+#[instrument(skip(elf, api_list, api_found))]
+pub fn fuzzy_api_search(
+    elf: &Elf,
+    api_list: &[&str],
+    api_found: &[API],
+    min_confidence: f64,
+) -> Result<Vec<API>> {
+    let patterns = compile_patterns(api_list)?;
+    let symbols: Vec<(String, u64, u64)> = elf
+        .syms
+        .iter()
+        .filter(|sym| sym.st_type() == goblin::elf::sym::STT_FUNC && sym.st_shndx != 0)
+        .filter_map(|sym| {
+            let name = get_name_sym(elf, &sym)?;
+            Some((
+                demangle_api_name(name),
+                sym.st_value,
+                sym.st_value + sym.st_size,
+            ))
+        })
+        .collect();
+    let names: Vec<String> = symbols.iter().map(|(name, _, _)| name.clone()).collect();
+
+    let mut fuzzy_found: Vec<API> = Vec::new();
+    for pattern in &patterns {
+        let ApiPattern::Exact(requested) = pattern else {
+            continue;
+        };
+        if api_found.iter().any(|api| pattern.matches(&api.name)) {
+            continue;
+        }
+        let best = rank_candidates(requested, &names)
+            .into_iter()
+            .find(|candidate| {
+                !api_found
+                    .iter()
+                    .chain(&fuzzy_found)
+                    .any(|api| api.name == candidate.symbol)
+            });
+        match &best {
+            Some(candidate) if candidate.confidence >= min_confidence => debug!(
+                requested = %requested,
+                symbol = %candidate.symbol,
+                confidence = candidate.confidence,
+                "API matched by name similarity"
+            ),
+            Some(candidate) => debug!(
+                requested = %requested,
+                symbol = %candidate.symbol,
+                confidence = candidate.confidence,
+                "API not found, best candidate below the confidence threshold"
+            ),
+            None => debug!(requested = %requested, "API not found"),
+        }
+        if let Some(candidate) = best.filter(|c| c.confidence >= min_confidence) {
+            if let Some((name, start, end)) =
+                symbols.iter().find(|(n, _, _)| *n == candidate.symbol)
+            {
+                let mut api = API::new(name.clone(), *start, *end);
+                api.fuzzy_match = Some(FuzzyMatch {
+                    requested: requested.clone(),
+                    confidence: candidate.confidence,
+                });
+                fuzzy_found.push(api);
+            }
+        }
+    }
+    Ok(fuzzy_found)
+}
+
+// Similarity between two API names, from 0 (unrelated) to 1 (same words).
+fn similarity(requested: &str, symbol: &str) -> f64 {
+    let requested = normalize(requested);
+    let symbol = normalize(symbol);
+    if requested.is_empty() || symbol.is_empty() {
+        return 0.0;
+    }
+    if requested == symbol {
+        return 1.0;
+    }
+    let (short, long) = if requested.len() <= symbol.len() {
+        (&requested, &symbol)
+    } else {
+        (&symbol, &requested)
+    };
+    // A prefixed or suffixed name, e.g. `fw_access_webcam` for `accessWebcam`.
+    let containment = if long.starts_with(short.as_str()) || long.ends_with(short.as_str()) {
+        0.8 + 0.2 * short.len() as f64 / long.len() as f64
+    } else {
+        0.0
+    };
+    // Squared, so that a single different word (`turnLampOn`/`turnLampOff`) is not a match,
+    // while a typo in a long name still is.
+    let edit = (1.0 - levenshtein(short, long) as f64 / long.len() as f64).powi(2);
+    containment.max(edit)
+}
+
+// Keep only the last path segment and join its lowercase words.
+fn normalize(name: &str) -> String {
+    let name = name.rsplit("::").next().unwrap_or(name);
+    let name = name.rsplit('.').next().unwrap_or(name);
+    name.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                prev
+            } else {
+                1 + prev.min(row[j]).min(row[j + 1])
+            };
+            prev = current;
+        }
+    }
+    row[b.len()]
+}
+
+// Translate a glob into an anchored regular expression.
+fn glob_to_regex(glob: &str) -> String {
+    let mut source = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                source.push_str(".*");
+            }
+            '*' => source.push_str("[^:]*"),
+            '?' => source.push('.'),
+            '[' => {
+                source.push('[');
+                if chars.next_if_eq(&'!').is_some() {
+                    source.push('^');
+                }
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        break;
+                    }
+                    if c == '\\' || c == '[' {
+                        source.push('\\');
+                    }
+                    source.push(c);
+                }
+                source.push(']');
+            }
+            _ => source.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    source.push('$');
+    source
+}
+
 // This function retrieves the name of a symbol from the ELF symbol table.
 fn get_name_sym<'a>(elf: &'a Elf, symbol: &'a goblin::elf::Sym) -> Option<&'a str> {
     let name_offset = symbol.st_name;
     let name_str: &'a str = elf.strtab.get_at(name_offset)?;
     Some(name_str)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exact_pattern() {
+        let pattern = ApiPattern::new("turnLampOn").unwrap();
+        assert!(pattern.matches("turnLampOn"));
+        assert!(!pattern.matches("turnLampOnce"));
+    }
+
+    #[test]
+    fn test_regex_pattern_is_anchored() {
+        let pattern = ApiPattern::new("^turnLamp(On|Off)$").unwrap();
+        assert!(pattern.matches("turnLampOn"));
+        assert!(pattern.matches("turnLampOff"));
+        assert!(!pattern.matches("turnLampOnce"));
+
+        let pattern = ApiPattern::new("^access").unwrap();
+        assert!(!pattern.matches("accessWebcam"));
+        let pattern = ApiPattern::new("^access.*").unwrap();
+        assert!(pattern.matches("accessWebcam"));
+    }
+
+    #[test]
+    fn test_glob_pattern() {
+        let pattern = ApiPattern::new("net::*::connect").unwrap();
+        assert!(pattern.matches("net::tcp::connect"));
+        assert!(!pattern.matches("net::tcp::stream::connect"));
+        assert!(!pattern.matches("net::tcp::connect_timeout"));
+
+        let pattern = ApiPattern::new("net::**::connect").unwrap();
+        assert!(pattern.matches("net::tcp::stream::connect"));
+
+        let pattern = ApiPattern::new("turnLamp[NO]?*").unwrap();
+        assert!(pattern.matches("turnLampOn"));
+        assert!(pattern.matches("turnLampOff"));
+
+        let pattern = ApiPattern::new("turnLamp[!O]*").unwrap();
+        assert!(!pattern.matches("turnLampOn"));
+    }
+
+    #[test]
+    fn test_similarity() {
+        assert_eq!(similarity("accessWebcam", "access_webcam"), 1.0);
+        assert_eq!(similarity("accessWebcam", "fw::access_webcam"), 1.0);
+        assert!(similarity("accessWebcam", "fw_access_webcam") > 0.9);
+        assert!(similarity("acessWebcam", "accessWebcam") > 0.8);
+        assert!(similarity("turnLampOn", "turnLampOff") < 0.8);
+        assert_eq!(similarity("", "turnLampOn"), 0.0);
+    }
+
+    #[test]
+    fn test_rank_candidates() {
+        let symbols = vec![
+            "turn_lamp_off".to_string(),
+            "vendor_turn_lamp_on".to_string(),
+            "main".to_string(),
+        ];
+        let candidates = rank_candidates("turnLampOn", &symbols);
+        assert_eq!(candidates[0].symbol, "vendor_turn_lamp_on");
+        assert_eq!(candidates[1].symbol, "turn_lamp_off");
+    }
+
+    #[test]
+    fn test_parse_api_list() {
+        let json = serde_json::json!([
+            "turnLampOn",
+            {
+                "name": "accessNetwork",
+                "capability": "network",
+                "parameters": ["url"],
+                "aliases": ["access_network"]
+            }
+        ]);
+        let specs = parse_api_list(&json).unwrap();
+        assert_eq!(specs[0], ApiSpec::new("turnLampOn"));
+        assert!(!specs[0].has_metadata());
+        assert_eq!(specs[1].capability.as_deref(), Some("network"));
+        assert_eq!(specs[1].parameters, vec!["url"]);
+        assert_eq!(
+            specs[1].names().collect::<Vec<_>>(),
+            vec!["accessNetwork", "access_network"]
+        );
+
+        assert!(parse_api_list(&serde_json::json!({"name": "x"})).is_err());
+        assert!(parse_api_list(&serde_json::json!([{"aliases": ["x"]}])).is_err());
+        assert!(parse_api_list(&serde_json::json!([{"name": "x", "aliases": "y"}])).is_err());
+    }
+
+    #[test]
+    fn test_attach_specs() {
+        let spec = parse_api_list(&serde_json::json!([
+            {"name": "accessNetwork", "aliases": ["access_network"]}
+        ]))
+        .unwrap();
+        let mut apis = vec![API::new("access_network".to_string(), 0, 0)];
+        attach_specs(&mut apis, &spec).unwrap();
+        assert_eq!(apis[0].spec.as_ref(), Some(&spec[0]));
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        assert!(compile_patterns(&["^turnLamp(On$"]).is_err());
+    }
+}
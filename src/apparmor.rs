@@ -0,0 +1,428 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+
+use goblin::elf::Elf;
+use serde_json::Value;
+
+use crate::{
+    arguments::{call_arguments, CallArguments},
+    call_graph::CallGraph,
+    cleanup::demangle_api_name,
+    error::Result,
+    files::{unresolved_file_calls, Access, FileAccess},
+    network::Endpoint,
+    syscall_arguments::{syscall_arguments, SyscallArguments},
+    xrefs::XrefDb,
+};
+
+// The functions executing a program, with the index of the argument holding it and whether it
+// is a command run by `/bin/sh` rather than the path of a program.
+const EXEC_FUNCTIONS: [(&str, usize, bool); 11] = [
+    ("execve", 0, false),
+    ("execv", 0, false),
+    ("execvp", 0, false),
+    ("execvpe", 0, false),
+    ("execl", 0, false),
+    ("execlp", 0, false),
+    ("execle", 0, false),
+    ("posix_spawn", 1, false),
+    ("posix_spawnp", 1, false),
+    ("system", 0, true),
+    ("popen", 0, true),
+];
+
+// The functions resolving host names, which read the configuration of the name services.
+const RESOLVER_FUNCTIONS: [&str; 4] = [
+    "getaddrinfo",
+    "gethostbyname",
+    "gethostbyname2",
+    "gethostbyname_r",
+];
+
+// The shell running the commands of `system` and `popen`.
+const SHELL: &str = "/bin/sh";
+
+// The ports below this one are bound with the `CAP_NET_BIND_SERVICE` capability.
+const PRIVILEGED_PORTS: u64 = 1024;
+
+// The rules of the profile, grouped by what they confine, with the comments of the accesses
+// that could not be resolved.
+#[derive(Default)]
+struct Rules {
+    includes: BTreeSet<&'static str>,
+    capabilities: BTreeSet<&'static str>,
+    network: BTreeSet<String>,
+    files: BTreeMap<String, BTreeSet<char>>,
+    programs: BTreeSet<String>,
+    todos: BTreeMap<&'static str, BTreeSet<String>>,
+}
+
+/// Draft an AppArmor profile confining the binary to the files, network and programs it uses.
+///
+/// The rules come from the constant paths given to the file functions (see
+/// [`file_accesses`](crate::files::file_accesses)), the families and types of the sockets
+/// created and the endpoints of the binary, and the programs given to `execve`, `posix_spawn`,
+/// `system` and the like, which inherit the profile. The accesses the static analysis cannot
+/// resolve, e.g. a path built at run time or relative to the working directory, are written as
+/// commented `TODO` rules, to be completed before the profile is enforced. The profile is
+/// attached to any path ending with the name of the binary and starts in complain mode.
+///
+/// # Arguments
+///
+/// * `elf` - The ELF object representing the binary.
+/// * `buffer` - The buffer containing the binary data of the ELF file.
+/// * `graph` - The call graph of the binary.
+/// * `xrefs` - The cross-references of the functions, holding the strings they reference.
+/// * `file_path` - The path of the binary, naming the profile.
+/// * `files` - The filesystem paths used by the binary.
+/// * `endpoints` - The network endpoints of the binary.
+///
+/// # Returns
+///
+/// Returns a `Result` containing the profile, in the syntax read by `apparmor_parser`.
+pub fn apparmor_profile(
+    elf: &Elf,
+    buffer: &[u8],
+    graph: &CallGraph,
+    xrefs: &XrefDb,
+    file_path: &str,
+    files: &[FileAccess],
+    endpoints: &[Endpoint],
+) -> Result<String> {
+    let mut rules = Rules::default();
+    rules.includes.insert("abstractions/base");
+    for file in files {
+        file_rule(&mut rules, file);
+    }
+    for call in unresolved_file_calls(elf, buffer, graph, xrefs)? {
+        let todo = format!(
+            "{} uses a path built at run time.",
+            call_site(graph, call.function, call.site, call.caller)
+        );
+        rules.todos.entry("files").or_default().insert(todo);
+    }
+
+    let mut sockets: Vec<SyscallArguments> = syscall_arguments(elf, buffer, graph, xrefs)?
+        .into_values()
+        .flatten()
+        .collect();
+    sockets.sort_by_key(|call| call.site);
+    for call in &sockets {
+        network_rule(&mut rules, graph, call);
+    }
+    for endpoint in endpoints {
+        endpoint_rule(&mut rules, endpoint);
+    }
+
+    let mut functions: Vec<&str> = EXEC_FUNCTIONS.map(|(function, _, _)| function).to_vec();
+    functions.extend(RESOLVER_FUNCTIONS);
+    for call in call_arguments(elf, buffer, graph, &functions)? {
+        match EXEC_FUNCTIONS
+            .iter()
+            .find(|(function, _, _)| *function == call.function)
+        {
+            Some(&(_, index, shell)) => exec_rule(&mut rules, graph, xrefs, &call, index, shell),
+            None => {
+                rules.includes.insert("abstractions/nameservice");
+            }
+        }
+    }
+
+    let name = Path::new(file_path)
+        .file_name()
+        .map_or(file_path.to_string(), |name| {
+            name.to_string_lossy().into_owned()
+        });
+    Ok(render(&rules, &name))
+}
+
+// Add the rule of a path used by the binary, or a TODO when it is relative.
+fn file_rule(rules: &mut Rules, file: &FileAccess) {
+    let permissions: BTreeSet<char> = file
+        .accesses
+        .iter()
+        .map(|access| match access {
+            Access::Read => 'r',
+            Access::Write | Access::Delete => 'w',
+        })
+        .collect();
+    let path = glob_specifiers(&file.path);
+    if path.starts_with('/') {
+        rules.files.entry(path).or_default().extend(permissions);
+    } else {
+        let todo = format!(
+            "{} ({}) is relative to the working directory, used by {}.",
+            file.path,
+            permissions.iter().collect::<String>(),
+            file.functions.join(", ")
+        );
+        rules.todos.entry("files").or_default().insert(todo);
+    }
+}
+
+// Add the rule of a socket created, or of the capability needed by a port bound.
+fn network_rule(rules: &mut Rules, graph: &CallGraph, call: &SyscallArguments) {
+    match call.function {
+        "socket" => {
+            let (Some(domain), kind) = (
+                call.arguments.get("domain").and_then(Value::as_str),
+                call.arguments.get("type").and_then(Value::as_str),
+            ) else {
+                let todo = format!(
+                    "{} creates a socket of a family not recovered.",
+                    call_site(graph, call.function, call.site, call.caller)
+                );
+                rules.todos.entry("network").or_default().insert(todo);
+                return;
+            };
+            let family = domain.trim_start_matches("AF_").to_lowercase();
+            let kind = kind
+                .and_then(|kind| kind.split('|').next())
+                .filter(|kind| kind.starts_with("SOCK_"))
+                .map(|kind| kind.trim_start_matches("SOCK_").to_lowercase());
+            if kind.as_deref() == Some("raw") || family == "packet" {
+                rules.capabilities.insert("net_raw");
+            }
+            rules.network.insert(match kind {
+                Some(kind) => format!("{} {}", family, kind),
+                None => family,
+            });
+        }
+        "bind" => {
+            let port = call
+                .arguments
+                .get("address")
+                .and_then(|address| address["port"].as_u64());
+            if port.is_some_and(|port| port != 0 && port < PRIVILEGED_PORTS) {
+                rules.capabilities.insert("net_bind_service");
+            }
+        }
+        _ => {}
+    }
+}
+
+// Add the rules of the sockets reaching an endpoint; a domain name may resolve to an IPv4 or
+// an IPv6 address.
+fn endpoint_rule(rules: &mut Rules, endpoint: &Endpoint) {
+    let kind = match endpoint.ip_protocol() {
+        Some(6) => "stream",
+        Some(17) => "dgram",
+        _ => return,
+    };
+    let ipv6 = endpoint
+        .host
+        .as_ref()
+        .is_some_and(|host| host.contains(':'));
+    let families: &[&str] = match (endpoint.is_ip(), ipv6) {
+        (true, true) => &["inet6"],
+        (true, false) => &["inet"],
+        (false, _) => &["inet", "inet6"],
+    };
+    for family in families {
+        rules.network.insert(format!("{} {}", family, kind));
+    }
+    if !endpoint.is_ip() && endpoint.host.is_some() {
+        rules.includes.insert("abstractions/nameservice");
+    }
+}
+
+// Add the rule of a program executed, or a TODO when it is not a constant absolute path.
+fn exec_rule(
+    rules: &mut Rules,
+    graph: &CallGraph,
+    xrefs: &XrefDb,
+    call: &CallArguments,
+    index: usize,
+    shell: bool,
+) {
+    let argument = call.string(index, xrefs);
+    let program = match shell {
+        true => {
+            rules.programs.insert(SHELL.to_string());
+            argument.and_then(|command| command.split_whitespace().next().map(str::to_string))
+        }
+        false => argument,
+    };
+    let site = call_site(graph, call.function, call.site, call.caller);
+    let todo = match program {
+        Some(program) if program.starts_with('/') => {
+            rules.programs.insert(glob_specifiers(&program));
+            return;
+        }
+        Some(program) => format!("{} executes `{}`, looked up in the PATH.", site, program),
+        None if shell => format!("{} runs a command built at run time.", site),
+        None => format!("{} executes a program known at run time.", site),
+    };
+    rules.todos.entry("programs").or_default().insert(todo);
+}
+
+// The function called, the function calling it and the call site, for the TODO comments.
+fn call_site(graph: &CallGraph, function: &str, site: u64, caller: u64) -> String {
+    let caller = graph
+        .function(caller)
+        .map_or(format!("{:#x}", caller), |func| {
+            demangle_api_name(&func.name)
+        });
+    format!("{} in {} ({:#x})", function, caller, site)
+}
+
+// Replace the `printf` conversions of a path, e.g. `/proc/%d/stat`, with an AppArmor glob.
+fn glob_specifiers(path: &str) -> String {
+    let mut glob = String::with_capacity(path.len());
+    let mut chars = path.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            glob.push(c);
+            continue;
+        }
+        if chars.peek() == Some(&'%') {
+            chars.next();
+            glob.push('%');
+            continue;
+        }
+        while chars
+            .peek()
+            .is_some_and(|c| c.is_ascii_digit() || "-+ #.lhzjt".contains(*c))
+        {
+            chars.next();
+        }
+        chars.next();
+        glob.push('*');
+    }
+    glob
+}
+
+// Quote a path holding spaces, as AppArmor requires.
+fn quoted(path: &str) -> String {
+    match path.contains(char::is_whitespace) {
+        true => format!("\"{}\"", path),
+        false => path.to_string(),
+    }
+}
+
+// Write the profile of the binary named `name`.
+fn render(rules: &Rules, name: &str) -> String {
+    let mut profile = format!(
+        "# Draft AppArmor profile of {} by manifest-producer {}.\n\
+         # Review the rules and resolve the TODO comments before enforcing it.\n\
+         \n\
+         abi <abi/3.0>,\n\
+         \n\
+         include <tunables/global>\n\
+         \n\
+         # TODO: attach the profile to the path the binary is installed at.\n\
+         profile {} /**/{} flags=(complain) {{\n",
+        name,
+        env!("CARGO_PKG_VERSION"),
+        quoted(name),
+        quoted(name)
+    );
+    for include in &rules.includes {
+        profile.push_str(&format!("  include <{}>\n", include));
+    }
+
+    let mut section = |title: &str, lines: Vec<String>, todos: Option<&BTreeSet<String>>| {
+        if lines.is_empty() && todos.is_none() {
+            return;
+        }
+        profile.push_str(&format!("\n  # {}\n", title));
+        for line in lines {
+            profile.push_str(&format!("  {},\n", line));
+        }
+        for todo in todos.into_iter().flatten() {
+            profile.push_str(&format!("  # TODO: {}\n", todo));
+        }
+    };
+    section(
+        "Capabilities.",
+        rules
+            .capabilities
+            .iter()
+            .map(|capability| format!("capability {}", capability))
+            .collect(),
+        None,
+    );
+    section(
+        "Network.",
+        rules
+            .network
+            .iter()
+            .map(|rule| format!("network {}", rule))
+            .collect(),
+        rules.todos.get("network"),
+    );
+    let mut files = vec![format!("/**/{} mr", quoted(name))];
+    files.extend(rules.files.iter().map(|(path, permissions)| {
+        format!(
+            "{} {}",
+            quoted(path),
+            permissions.iter().collect::<String>()
+        )
+    }));
+    section("Files.", files, rules.todos.get("files"));
+    section(
+        "Programs executed.",
+        rules
+            .programs
+            .iter()
+            .map(|program| format!("{} ix", quoted(program)))
+            .collect(),
+        rules.todos.get("programs"),
+    );
+    profile.push_str("}\n");
+    profile
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{elf_utils::read_elf_file, files::file_accesses};
+
+    #[test]
+    fn test_apparmor_profile() {
+        let buffer = read_elf_file("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        let graph = CallGraph::build(&elf, &buffer, false).unwrap();
+        let xrefs = XrefDb::build(&elf, &buffer, &graph);
+        let files = file_accesses(&elf, &buffer, &graph, &xrefs, &[]).unwrap();
+        let endpoints = [Endpoint {
+            host: Some("10.0.0.1".to_string()),
+            port: Some(443),
+            protocol: Some("https".to_string()),
+            source: "string",
+        }];
+        let profile = apparmor_profile(
+            &elf,
+            &buffer,
+            &graph,
+            &xrefs,
+            "rootfs/usr/bin/fake-firmware-c-dynamic",
+            &files,
+            &endpoints,
+        )
+        .unwrap();
+
+        assert!(profile.contains(
+            "profile fake-firmware-c-dynamic /**/fake-firmware-c-dynamic flags=(complain) {"
+        ));
+        assert!(profile.contains("  include <abstractions/base>\n"));
+        assert!(profile.contains("  /dev/urandom r,\n"));
+        // The socket of `Curl_ipv6works` and the endpoint.
+        assert!(profile.contains("  network inet6 dgram,\n"));
+        assert!(profile.contains("  network inet stream,\n"));
+        // `writeOnDrive` opens `foo.txt` in the working directory.
+        assert!(profile.contains("  # TODO: foo.txt (w) is relative to the working directory"));
+        assert!(profile.contains("uses a path built at run time."));
+        assert!(profile.ends_with("}\n"));
+    }
+
+    #[test]
+    fn test_glob_specifiers() {
+        assert_eq!(glob_specifiers("/proc/%d/stat"), "/proc/*/stat");
+        assert_eq!(glob_specifiers("/tmp/%08lx.%s"), "/tmp/*.*");
+        assert_eq!(glob_specifiers("/var/100%%"), "/var/100%");
+        assert_eq!(quoted("/mnt/My Files"), "\"/mnt/My Files\"");
+    }
+}
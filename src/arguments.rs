@@ -0,0 +1,140 @@
+use goblin::elf::Elf;
+
+use crate::{
+    call_graph::CallGraph,
+    disassembler::Disassembler,
+    elf_utils::code_bytes,
+    error::Result,
+    indirect_calls::{PointerResolver, RegisterState},
+    xrefs::XrefDb,
+};
+
+/// The registers of the first six integer arguments of a call, in the System V x86-64 ABI.
+pub const ARGUMENT_REGISTERS: [&str; 6] = ["%rdi", "%rsi", "%rdx", "%rcx", "%r8", "%r9"];
+
+/// A call to one of the functions looked for, with the constants passed to it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CallArguments {
+    /// The function called, as named in the list looked for.
+    pub function: &'static str,
+    /// The address of the call instruction.
+    pub site: u64,
+    /// The starting address of the function performing the call.
+    pub caller: u64,
+    /// The constant held by each argument register before the call, when it is known.
+    pub values: [Option<u64>; 6],
+}
+
+impl CallArguments {
+    /// Return the string an argument points to, when it is the address of a string referenced
+    /// by the code of the caller.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index of the argument, 0 for the first one.
+    /// * `xrefs` - The cross-references of the functions, holding the strings they reference.
+    pub fn string(&self, index: usize, xrefs: &XrefDb) -> Option<String> {
+        let addr = self.values.get(index).copied().flatten()?;
+        xrefs
+            .data_refs(self.caller)
+            .iter()
+            .find(|data| data.addr == addr)
+            .and_then(|data| data.string.clone())
+    }
+}
+
+/// Find the calls to some functions and recover the constants of their arguments.
+///
+/// The constants are those of the registers set before each call, as tracked by
+/// [`RegisterState`]: immediates, addresses loaded by `lea`, and pointers read from the binary.
+/// The functions are matched by name, without the symbol version, e.g. `getenv@GLIBC_2.2.5`.
+///
+/// # Arguments
+///
+/// * `elf` - The ELF object representing the binary.
+/// * `buffer` - The buffer containing the binary data of the ELF file.
+/// * `graph` - The call graph of the binary.
+/// * `functions` - The names of the functions whose calls are looked for.
+///
+/// # Returns
+///
+/// Returns a `Result` containing the calls found, sorted by call site.
+pub fn call_arguments(
+    elf: &Elf,
+    buffer: &[u8],
+    graph: &CallGraph,
+    functions: &[&'static str],
+) -> Result<Vec<CallArguments>> {
+    let resolver = PointerResolver::new(elf, buffer);
+    let disassembler = Disassembler::new()?;
+    let matching = |name: &str| {
+        let name = name.split('@').next().unwrap_or(name);
+        functions
+            .iter()
+            .find(|function| **function == name)
+            .copied()
+    };
+    let mut calls = Vec::new();
+
+    for func in graph.functions() {
+        // The functions looked for calling one another, e.g. aliases, are left out.
+        if matching(&func.name).is_some() {
+            continue;
+        }
+        let sites: Vec<(u64, &'static str)> = graph
+            .callees(func.start_addr)
+            .iter()
+            .filter_map(|edge| Some((edge.site, matching(&edge.name)?)))
+            .collect();
+        if sites.is_empty() {
+            continue;
+        }
+        let Some(code) = code_bytes(elf, buffer, func.start_addr, func.end_addr) else {
+            continue;
+        };
+        let Ok(instructions) = disassembler.disassemble(code, func.start_addr) else {
+            continue;
+        };
+
+        let mut state = RegisterState::new();
+        for insn in instructions.iter() {
+            if let Some((site, function)) = sites.iter().find(|(site, _)| *site == insn.address) {
+                calls.push(CallArguments {
+                    function,
+                    site: *site,
+                    caller: func.start_addr,
+                    values: ARGUMENT_REGISTERS.map(|reg| state.constant(reg)),
+                });
+            }
+            state.update(&insn.mnemonic, &insn.op_str, insn.next_addr(), &resolver);
+        }
+    }
+    calls.sort_by_key(|call| call.site);
+    Ok(calls)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf_utils::read_elf_file;
+
+    #[test]
+    fn test_call_arguments() {
+        let buffer = read_elf_file("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        let graph = CallGraph::build(&elf, &buffer, false).unwrap();
+        let xrefs = XrefDb::build(&elf, &buffer, &graph);
+        let calls = call_arguments(&elf, &buffer, &graph, &["getenv", "sigaction"]).unwrap();
+
+        assert!(calls.windows(2).all(|pair| pair[0].site <= pair[1].site));
+        // sigpipe_ignore: sigaction(SIGPIPE, ...).
+        assert!(calls
+            .iter()
+            .any(|call| call.function == "sigaction" && call.values[0] == Some(13)));
+        assert!(calls
+            .iter()
+            .filter(|call| call.function == "getenv")
+            .any(|call| call.string(0, &xrefs).is_some()));
+        assert!(calls.iter().all(|call| call.string(5, &xrefs).is_none()));
+    }
+}
@@ -0,0 +1,188 @@
+use std::{
+    collections::BTreeSet,
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use crate::error::{Error, Result};
+
+// ELF types of the files analyzed in batch: executables and shared objects (`ET_EXEC`, `ET_DYN`).
+const ELF_TYPES: [u16; 2] = [2, 3];
+
+/// A binary found by [`discover_elf_files`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct BatchInput {
+    /// The path of the binary.
+    pub path: PathBuf,
+    /// The name of its output folder, unique among the binaries found.
+    pub output_name: String,
+}
+
+/// The outcome of the analysis of one binary of a batch.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BatchEntry {
+    /// The path of the binary.
+    pub path: String,
+    /// The folder where its manifests were written.
+    pub output_dir: String,
+    /// Why its analysis failed, `None` when it succeeded.
+    pub error: Option<String>,
+}
+
+/// Find the ELF executables and shared objects of files, directories and glob patterns.
+///
+/// Directories are walked recursively, without following symbolic links; files that are not
+/// ELF executables or shared objects (object files, scripts, data) are skipped. Each binary is
+/// given an output folder named after its path relative to the directory it was found in,
+/// e.g. `usr_bin_httpd` for `rootfs/usr/bin/httpd` when `rootfs` is given.
+///
+/// # Arguments
+///
+/// * `paths` - The files, directories and glob patterns (e.g. `rootfs/**/bin/*`) to search.
+/// * `exclude` - The glob patterns of the paths to leave out, matched against the whole path and
+///   the path relative to the directory searched.
+///
+/// # Returns
+///
+/// Returns a `Result` containing the binaries found, in the order of the paths and sorted
+/// within each directory.
+///
+/// # Errors
+///
+/// Returns an error if a path does not exist or a glob pattern is malformed.
+pub fn discover_elf_files(paths: &[String], exclude: &[String]) -> Result<Vec<BatchInput>> {
+    let exclude = exclude
+        .iter()
+        .map(|pattern| {
+            glob::Pattern::new(pattern)
+                .map_err(|error| Error::InvalidPattern(format!("{}: {}", pattern, error)))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut found: Vec<(PathBuf, PathBuf)> = Vec::new();
+    for path in paths {
+        if path.contains(['*', '?', '[']) {
+            let matches = glob::glob(path)
+                .map_err(|error| Error::InvalidPattern(format!("{}: {}", path, error)))?;
+            for entry in matches.filter_map(|entry| entry.ok()) {
+                collect(&entry, &entry, &mut found)?;
+            }
+        } else {
+            let path = Path::new(path);
+            if !path.exists() {
+                return Err(Error::InvalidPath(format!(
+                    "{} does not exist",
+                    path.display()
+                )));
+            }
+            collect(path, path, &mut found)?;
+        }
+    }
+
+    let mut names = BTreeSet::new();
+    let mut inputs = Vec::new();
+    for (root, path) in found {
+        if inputs.iter().any(|input: &BatchInput| input.path == path) {
+            continue;
+        }
+        let relative = match path.strip_prefix(&root) {
+            Ok(relative) if !relative.as_os_str().is_empty() => relative,
+            _ => Path::new(path.file_name().unwrap_or(path.as_os_str())),
+        };
+        if exclude
+            .iter()
+            .any(|pattern| pattern.matches_path(&path) || pattern.matches_path(relative))
+        {
+            continue;
+        }
+        let base: String = relative
+            .to_string_lossy()
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '.' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        let mut output_name = base.clone();
+        for suffix in 2.. {
+            if names.insert(output_name.clone()) {
+                break;
+            }
+            output_name = format!("{}-{}", base, suffix);
+        }
+        inputs.push(BatchInput { path, output_name });
+    }
+    Ok(inputs)
+}
+
+/// Whether a file is an ELF executable or shared object, from its header.
+pub fn is_elf_executable(path: &Path) -> bool {
+    let mut header = [0u8; 18];
+    let Ok(mut file) = File::open(path) else {
+        return false;
+    };
+    if file.read_exact(&mut header).is_err() || &header[..4] != b"\x7fELF" {
+        return false;
+    }
+    let elf_type = match header[5] {
+        2 => u16::from_be_bytes([header[16], header[17]]),
+        _ => u16::from_le_bytes([header[16], header[17]]),
+    };
+    ELF_TYPES.contains(&elf_type)
+}
+
+// Collect the ELF binaries under a path, with the directory they were found from.
+fn collect(root: &Path, path: &Path, found: &mut Vec<(PathBuf, PathBuf)>) -> Result<()> {
+    let metadata = fs::symlink_metadata(path)?;
+    if metadata.is_dir() {
+        let mut entries: Vec<PathBuf> = fs::read_dir(path)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .collect();
+        entries.sort();
+        for entry in entries {
+            collect(root, &entry, found)?;
+        }
+    } else if metadata.is_file() && is_elf_executable(path) {
+        found.push((root.to_path_buf(), path.to_path_buf()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env::temp_dir;
+
+    #[test]
+    fn test_discover_elf_files() {
+        let dir = temp_dir().join("manifest-batch");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("usr/bin")).unwrap();
+        fs::create_dir_all(dir.join("bin")).unwrap();
+        let elf = fs::read("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        fs::write(dir.join("usr/bin/httpd"), &elf).unwrap();
+        fs::write(dir.join("bin/sh"), &elf).unwrap();
+        fs::write(dir.join("bin/script.sh"), "#!/bin/sh\n").unwrap();
+
+        let root = dir.to_str().unwrap().to_string();
+        let inputs = discover_elf_files(std::slice::from_ref(&root), &[]).unwrap();
+        let names: Vec<&str> = inputs.iter().map(|i| i.output_name.as_str()).collect();
+        assert_eq!(names, ["bin_sh", "usr_bin_httpd"]);
+
+        let pattern = format!("{}/**/httpd", root);
+        let file = format!("{}/bin/sh", root);
+        let inputs = discover_elf_files(&[pattern, file.clone(), file], &[]).unwrap();
+        let names: Vec<&str> = inputs.iter().map(|i| i.output_name.as_str()).collect();
+        assert_eq!(names, ["httpd", "sh"]);
+
+        let exclude = ["usr/**".to_string()];
+        let inputs = discover_elf_files(std::slice::from_ref(&root), &exclude).unwrap();
+        assert_eq!(inputs.len(), 1);
+
+        assert!(discover_elf_files(&[format!("{}/missing", root)], &[]).is_err());
+    }
+}
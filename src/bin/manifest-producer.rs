@@ -1,14 +1,71 @@
-use manifest_producer::api_detection::api_search;
-use manifest_producer::cleanup::syscall_flow;
-use manifest_producer::code_section_handler::code_section;
-use manifest_producer::dwarf_analysis::dwarf_analysis;
-use manifest_producer::elf_utils::{is_static, is_stripped, read_elf_file};
-use manifest_producer::error::{Error, Result};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use ed25519_dalek::SigningKey;
+use manifest_producer::analyzer::{AnalysisMode, Analyzer, Options, Outputs, Scope};
+use manifest_producer::api_detection::{parse_api_list, ApiSpec};
+use manifest_producer::batch::{discover_elf_files, BatchEntry};
+use manifest_producer::budget::{parse_duration, parse_size, Budget};
+use manifest_producer::cache::AnalysisCache;
+use manifest_producer::config::Config;
+use manifest_producer::conformance::Declaration;
+use manifest_producer::diff::{diff_manifests, ManifestDiff, ManifestSet};
+use manifest_producer::dwarf_analysis::resolve_addresses;
+use manifest_producer::dynamic_analysis::DynamicOptions;
+use manifest_producer::elf_utils::read_elf_file;
+use manifest_producer::error::{Error, Result, EXIT_FAILURE};
 use manifest_producer::manifest_creation::{
-    basic_info_manifest, feature_manifest, flow_call_manifest,
+    collect_manifests, merge_manifests, read_manifest, summary_manifest, Format,
 };
+use manifest_producer::matrix::FleetMatrix;
+use manifest_producer::plugins::Plugins;
+use manifest_producer::policy::{Policy, Severity};
+use manifest_producer::progress::Verbosity;
+use manifest_producer::reader::ReadMode;
+use manifest_producer::remote::{is_url, RemoteInput};
+use manifest_producer::schema::{json_schema, validate, ManifestKind, SCHEMA_VERSION};
+use manifest_producer::server::{Server, ServerConfig};
+use manifest_producer::signatures::generate_signatures;
+use manifest_producer::signing::{
+    parse_signing_key, parse_verifying_key, public_key_hex, verify_manifests,
+};
+use manifest_producer::unpacking::DEFAULT_UPX;
+use manifest_producer::viewer::run_viewer;
+use manifest_producer::watch::Watcher;
 use serde_json::Value;
-use std::{env, fs};
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    process,
+    time::Duration,
+};
+use tracing_subscriber::EnvFilter;
+
+#[cfg(feature = "remote")]
+use manifest_producer::remote::fetch;
+#[cfg(not(feature = "remote"))]
+use manifest_producer::remote::redact_url;
+#[cfg(feature = "sqlite")]
+use manifest_producer::store::{Query, ResultStore};
+
+/// How the manifests are written to the standard output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum StdoutMode {
+    /// A single JSON document holding every manifest, by name.
+    Json,
+    /// One JSON line per manifest, with its name and content.
+    Ndjson,
+}
+
+// Default directory where the manifests are written.
+const DEFAULT_OUTPUT_DIR: &str = "./manifest-produced";
+
+// How often the binaries are polled for changes with --watch.
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+// Environment variables holding the signing key and the public key, in hexadecimal.
+const SIGNING_KEY_VAR: &str = "MANIFEST_PRODUCER_SIGNING_KEY";
+const PUBLIC_KEY_VAR: &str = "MANIFEST_PRODUCER_PUBLIC_KEY";
 
 /// Perform ELF analysis including API detection, system call flow encapsulation, and manifest generation.
 ///
@@ -17,73 +74,1275 @@ use std::{env, fs};
 /// # Arguments
 ///
 /// * `file_path` - The path to the ELF file to be analyzed.
-/// * `api_list` - A vector containing the names of the APIs to search for.
+/// * `specs` - The entries of the API list, with their names, aliases and metadata.
+/// * `path` - The directory where the manifests are written.
+/// * `options` - The optional outputs and limits of the analysis.
 ///
 /// # Returns
 ///
 /// Returns a `Result` indicating success or failure of the ELF analysis.
-pub fn elf_analysis(file_path: &str, api_list: Vec<&str>, path: &str) -> Result<()> {
-    let elf_data = read_elf_file(file_path)?;
-    let elf = goblin::elf::Elf::parse(&elf_data)?;
+pub fn elf_analysis(
+    file_path: &str,
+    source_url: Option<&str>,
+    specs: &[ApiSpec],
+    path: &str,
+    options: &Options,
+) -> Result<()> {
+    let mut analyzer = Analyzer::new(file_path)
+        .with_api_list(specs.to_vec())
+        .with_options(options.clone());
+    if let Some(url) = source_url {
+        analyzer = analyzer.with_source_url(url);
+    }
+    analyzer.run(path)?;
+    Ok(())
+}
+
+fn read_api_list(json_file_path: &str) -> Result<Vec<ApiSpec>> {
+    let contents = fs::read_to_string(json_file_path)?;
+    let json: Value = serde_json::from_str(&contents)?;
+    parse_api_list(&json)
+}
+
+fn read_banned_list(json_file_path: &str) -> Result<Vec<String>> {
+    let contents = fs::read_to_string(json_file_path)?;
+    Ok(serde_json::from_str(&contents)?)
+}
+
+// Check manifests against the schema of their declared version, telling their kind from the
+// stem of their file name.
+fn validate_manifests(paths: &[String]) -> Result<bool> {
+    let mut valid = true;
+    for path in paths {
+        let kind: ManifestKind = Path::new(path)
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or_default()
+            .parse()?;
+        let violations = validate(kind, &read_manifest(path)?)?;
+        if violations.is_empty() {
+            println!("{}: valid", path);
+        }
+        for violation in &violations {
+            println!("{}: {}", path, violation);
+        }
+        valid &= violations.is_empty();
+    }
+    Ok(valid)
+}
+
+// Parse the --function-timeout of the command line.
+fn parse_duration_arg(duration: &str) -> std::result::Result<Duration, String> {
+    parse_duration(duration).ok_or_else(|| "expected a duration, e.g. 2s or 500ms".to_string())
+}
+
+// Parse the --memory-limit of the command line.
+fn parse_size_arg(size: &str) -> std::result::Result<u64, String> {
+    parse_size(size).ok_or_else(|| "expected a size, e.g. 4G or 512M".to_string())
+}
+
+// Parse an address of the command line, hexadecimal with or without its 0x prefix.
+fn parse_address_arg(address: &str) -> std::result::Result<u64, String> {
+    let digits = address
+        .strip_prefix("0x")
+        .or_else(|| address.strip_prefix("0X"))
+        .unwrap_or(address);
+    u64::from_str_radix(digits, 16)
+        .map_err(|_| "expected a hexadecimal address, e.g. 0x1189".to_string())
+}
 
-    let stripped = is_stripped(&elf);
-    if stripped {
-        return Err(Error::DebugInfo);
+// Read the configuration file given, or the one of the current directory if there is one.
+fn read_config(path: Option<&str>) -> Result<Config> {
+    match path {
+        Some(path) => Config::load(path),
+        None => Ok(Config::discover()?.unwrap_or_default()),
     }
+}
 
-    let lang = match dwarf_analysis(file_path)?.strip_prefix("DW_LANG_") {
-        Some(stripped_lang) => stripped_lang.to_owned(),
-        None => "".to_string(), //return Err(Error::PrefixNotFound),
+// Read a key from a file holding its hexadecimal digits, or from an environment variable.
+fn read_key(file_path: Option<&str>, var: &str) -> Result<Option<String>> {
+    match file_path {
+        Some(file_path) => Ok(Some(fs::read_to_string(file_path)?)),
+        None => Ok(env::var(var).ok()),
+    }
+}
+
+// Analyze an ELF file, or the ELF files of directories and glob patterns, with the options
+// given on the command line, falling back on the configuration file for the options not given.
+// Returns whether every binary was analyzed.
+fn analyze_command(args: &AnalyzeArgs) -> Result<bool> {
+    let config = read_config(args.config.as_deref())?;
+    if let Some(jobs) = args.jobs {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build_global()
+            .map_err(|error| Error::InvalidConfig(format!("--jobs {}: {}", jobs, error)))?;
+    }
+    let scope = args.scope.or(config.scope).unwrap_or_default();
+    let api_list = match (&args.api_list, &config.api_list, &config.apis) {
+        (Some(path), _, _) | (None, Some(path), _) => read_api_list(path)?,
+        (None, None, Some(apis)) => apis.clone(),
+        // Every function is an API, the list only gives the metadata of some.
+        (None, None, None) if scope == Scope::All => Vec::new(),
+        (None, None, None) => {
+            return Err(Error::InvalidApiList(
+                "no API list given with --api-list nor in the configuration file".to_string(),
+            ))
+        }
+    };
+    let signing_key = read_key(args.sign_key.as_deref(), SIGNING_KEY_VAR)?
+        .map(|key| parse_signing_key(&key))
+        .transpose()?;
+    let banned = args.banned.as_ref().or(config.banned.as_ref());
+    let options = Options {
+        outputs: Outputs {
+            call_graph: args.call_graph || config.output("call-graph"),
+            xrefs: args.xrefs || config.output("xrefs"),
+            cfg: args.cfg || config.output("cfg"),
+            cyclonedx: args.cyclonedx || config.output("cyclonedx"),
+            spdx: args.spdx || config.output("spdx"),
+            sarif: args.sarif || config.output("sarif"),
+            html: args.html || config.output("html"),
+            markdown: args.markdown || config.output("markdown"),
+            seccomp: args.seccomp || config.output("seccomp"),
+            apparmor: args.apparmor || config.output("apparmor"),
+            disassembly: args.disassembly || config.output("disassembly"),
+        },
+        mud_url: args.mud.clone(),
+        format: args.format.or(config.format).unwrap_or_default(),
+        arch: args.arch.clone().or(config.arch),
+        analysis: args.analysis.or(config.analysis).unwrap_or_default(),
+        scope,
+        dynamic: DynamicOptions {
+            duration: args.duration.unwrap_or(config.dynamic.duration),
+            args: match args.run_arg.is_empty() {
+                true => config.dynamic.args.clone(),
+                false => args.run_arg.clone(),
+            },
+            allow_network: args.allow_network || config.dynamic.allow_network,
+            emulator: args.emulator.clone().or(config.dynamic.emulator),
+            sysroot: args.sysroot.clone().or(config.dynamic.sysroot),
+        },
+        max_depth: args.max_depth.or(config.max_depth),
+        banned_functions: banned.map(|path| read_banned_list(path)).transpose()?,
+        vuln_db: args.vuln_db.clone().or(config.vuln_db),
+        capability_rules: args.capability_rules.clone().or(config.capability_rules),
+        signing_key,
+        verbosity: Verbosity::from_flags(args.quiet, args.verbose),
+        read_mode: if args.no_mmap {
+            ReadMode::Read
+        } else {
+            ReadMode::Mmap
+        },
+        cache_dir: match args.no_cache {
+            true => None,
+            false => args
+                .cache_dir
+                .clone()
+                .or(config.cache_dir)
+                .or_else(AnalysisCache::default_dir),
+        },
+        incremental: args.incremental || args.watch,
+        listener: None,
+        plugins: load_plugins(args)?,
+        unpacker: args.unpack.clone().or(config.unpacker),
+        exclude_libraries: match args.exclude_library.is_empty() {
+            true => config.exclude_libraries.clone(),
+            false => args.exclude_library.clone(),
+        },
+        signatures: match args.signatures.is_empty() {
+            true => config.signatures.clone(),
+            false => args.signatures.clone(),
+        },
+        library_root: args.library_root.clone().or(config.library_root),
+        best_effort: args.best_effort || config.best_effort,
+        no_timestamp: args.no_timestamp || config.no_timestamp,
+        stack_threshold: args.stack_threshold.or(config.stack_threshold),
+        disassembly_depth: args
+            .disassembly_depth
+            .or(config.disassembly_depth)
+            .unwrap_or_default(),
+        store: store_path(args).or(config.store),
+        budget: Budget {
+            function_timeout: args.function_timeout.or(config.budget.function_timeout),
+            memory_limit: args.memory_limit.or(config.budget.memory_limit),
+        },
     };
+    let output_dir = args
+        .output_dir
+        .clone()
+        .or(config.output_dir)
+        .unwrap_or_else(|| DEFAULT_OUTPUT_DIR.to_string());
+    // The manifests streamed to stdout are written to a scratch directory first.
+    let output_dir = match args.stdout {
+        Some(_) => env::temp_dir()
+            .join(format!("manifest-stdout-{}", process::id()))
+            .to_string_lossy()
+            .to_string(),
+        None => output_dir,
+    };
+    fs::create_dir_all(&output_dir)?;
+    if args.watch && args.paths.iter().any(|path| is_url(path)) {
+        return Err(Error::InvalidConfig(
+            "--watch needs the paths of the binaries, not URLs".to_string(),
+        ));
+    }
+    // The binaries given as URLs are downloaded to a scratch directory first.
+    let scratch = env::temp_dir().join(format!("manifest-remote-{}", process::id()));
+    let inputs = fetch_inputs(&args.paths, &scratch);
+    let analyzed = inputs.and_then(|inputs| {
+        analyze_paths(
+            args,
+            &inputs,
+            &api_list,
+            &config.exclude,
+            &output_dir,
+            &options,
+        )
+    });
+    if scratch.exists() {
+        fs::remove_dir_all(&scratch)?;
+    }
+    if args.watch {
+        if let Err(error) = &analyzed {
+            eprintln!("Elf analysis failed: {}", error);
+        }
+        return watch_paths(args, &api_list, &config.exclude, &output_dir, &options);
+    }
+    if let (Some(mode), Ok(_)) = (args.stdout, &analyzed) {
+        print_manifests(mode, &output_dir, !is_single_file(&args.paths))?;
+    }
+    if args.stdout.is_some() {
+        fs::remove_dir_all(&output_dir)?;
+    }
+    analyzed
+}
 
-    let link = is_static(&elf);
+// The binaries to analyze: the paths given, those given as URLs replaced by the files they
+// were downloaded to.
+struct Inputs {
+    paths: Vec<String>,
+    // The URL of each binary downloaded, by path.
+    sources: BTreeMap<String, String>,
+}
 
-    let mut api_found = api_search(&elf, &api_list)?;
-    if api_found.is_empty() {
-        return Err(Error::APIListEmpty);
+// Download the binaries given as URLs, each to its own folder of a scratch directory.
+fn fetch_inputs(paths: &[String], scratch: &Path) -> Result<Inputs> {
+    let mut inputs = Inputs {
+        paths: Vec::new(),
+        sources: BTreeMap::new(),
+    };
+    for (index, path) in paths.iter().enumerate() {
+        if !is_url(path) {
+            inputs.paths.push(path.clone());
+            continue;
+        }
+        let remote = fetch_input(path, &scratch.join(index.to_string()))?;
+        let local = remote.path.to_string_lossy().to_string();
+        inputs.sources.insert(local.clone(), remote.url);
+        inputs.paths.push(local);
     }
+    Ok(inputs)
+}
 
-    for api in &mut api_found {
-        let sys = code_section(&elf, api, &elf_data, link, lang.contains("Rust"))?;
-        syscall_flow(api, sys, &lang)?;
+// Download a binary given as a URL.
+#[cfg(feature = "remote")]
+fn fetch_input(url: &str, dir: &Path) -> Result<RemoteInput> {
+    fetch(url, dir)
+}
+
+// Without the `remote` feature, no binary can be downloaded.
+#[cfg(not(feature = "remote"))]
+fn fetch_input(url: &str, _dir: &Path) -> Result<RemoteInput> {
+    Err(Error::InvalidPath(format!(
+        "{}: analyzing a URL needs the `remote` feature",
+        redact_url(url)
+    )))
+}
+
+// Load the plugin libraries given with --plugin.
+#[cfg(feature = "plugins")]
+fn load_plugins(args: &AnalyzeArgs) -> Result<Plugins> {
+    let mut plugins = Plugins::default();
+    for path in &args.plugin {
+        // The libraries are trusted as the tool itself is: the user chose to run them.
+        unsafe { plugins.load(path)? };
     }
+    Ok(plugins)
+}
 
-    basic_info_manifest(&elf, file_path, &api_found, lang, path)?;
-    flow_call_manifest(&api_found, path)?;
-    feature_manifest(&api_found, path)?;
+// Without the `plugins` feature, no library can be loaded.
+#[cfg(not(feature = "plugins"))]
+fn load_plugins(_args: &AnalyzeArgs) -> Result<Plugins> {
+    Ok(Plugins::default())
+}
+
+// The database given with --store.
+#[cfg(feature = "sqlite")]
+fn store_path(args: &AnalyzeArgs) -> Option<String> {
+    args.store.clone()
+}
 
+// Without the `sqlite` feature, no database is given on the command line.
+#[cfg(not(feature = "sqlite"))]
+fn store_path(_args: &AnalyzeArgs) -> Option<String> {
+    None
+}
+
+// Serve the analysis over HTTP with the options of the configuration file, until killed.
+fn serve_command(args: &ServeArgs) -> Result<()> {
+    let config = read_config(args.config.as_deref())?;
+    let options = Options {
+        verbosity: Verbosity::Quiet,
+        cache_dir: match args.no_cache {
+            true => None,
+            false => args
+                .cache_dir
+                .clone()
+                .or(config.cache_dir.clone())
+                .or_else(AnalysisCache::default_dir),
+        },
+        budget: Budget {
+            function_timeout: args.function_timeout.or(config.budget.function_timeout),
+            memory_limit: args.memory_limit.or(config.budget.memory_limit),
+        },
+        ..Options::from_config(&config)?
+    };
+    let defaults = ServerConfig::default();
+    let config = ServerConfig {
+        bind: args.bind.clone(),
+        workers: args.workers.unwrap_or(defaults.workers),
+        queue: args.queue,
+        max_upload: args.max_upload,
+        options,
+        ..defaults
+    };
+    #[cfg(feature = "grpc")]
+    if args.grpc {
+        let listener = std::net::TcpListener::bind(&config.bind)?;
+        eprintln!("Listening on grpc://{}", listener.local_addr()?);
+        return manifest_producer::grpc::serve(listener, config);
+    }
+    let server = Server::bind(config)?;
+    eprintln!("Listening on http://{}", server.addr());
+    server.run();
     Ok(())
 }
 
-fn read_api_list(json_file_path: &str) -> Result<Vec<String>> {
-    let contents = fs::read_to_string(json_file_path)?;
-    let json: Value = serde_json::from_str(&contents)?;
-    let api_list: Vec<String> = serde_json::from_value(json)?;
-    Ok(api_list)
+// Whether the paths given to analyze are a single ELF file rather than a batch.
+fn is_single_file(paths: &[String]) -> bool {
+    match paths {
+        [path] => !path.contains(['*', '?', '[']) && !Path::new(path).is_dir(),
+        _ => false,
+    }
 }
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() < 3 {
-        println!("Usage: {} <ELF_file_path> <JSON_file_path>", args[0]);
-        return;
+// Analyze the paths given to analyze into the output directory, leaving out the excluded paths
+// of a batch. Returns whether every binary was analyzed.
+fn analyze_paths(
+    args: &AnalyzeArgs,
+    inputs: &Inputs,
+    api_list: &[ApiSpec],
+    exclude: &[String],
+    output_dir: &str,
+    options: &Options,
+) -> Result<bool> {
+    // The standard output is kept for the manifests when they are streamed.
+    let log = |message: String| match args.stdout {
+        _ if options.verbosity == Verbosity::Quiet => {}
+        Some(_) => eprintln!("{}", message),
+        None => println!("{}", message),
+    };
+    let source = |path: &str| inputs.sources.get(path).map(String::as_str);
+    if is_single_file(&inputs.paths) {
+        let path = &inputs.paths[0];
+        elf_analysis(path, source(path), api_list, output_dir, options)?;
+        log("Analysis performed successfully!".to_string());
+        return Ok(true);
+    }
+
+    // Batch mode: one output folder per binary, going on after the failures.
+    let mut entries = Vec::new();
+    for input in discover_elf_files(&inputs.paths, exclude)? {
+        let path = input.path.to_string_lossy().to_string();
+        let output_dir = Path::new(output_dir)
+            .join(&input.output_name)
+            .to_string_lossy()
+            .to_string();
+        let analysis = fs::create_dir_all(&output_dir)
+            .map_err(|error| error.to_string())
+            .and_then(|()| {
+                panic::catch_unwind(AssertUnwindSafe(|| {
+                    elf_analysis(&path, source(&path), api_list, &output_dir, options)
+                }))
+                .map_err(|_| "the analysis panicked".to_string())?
+                .map_err(|error| error.to_string())
+            });
+        match &analysis {
+            Ok(()) => log(format!("{}: analyzed in {}", path, output_dir)),
+            Err(error) => eprintln!("{}: failed: {}", path, error),
+        }
+        entries.push(BatchEntry {
+            path,
+            output_dir,
+            error: analysis.err(),
+        });
+    }
+    summary_manifest(&entries, output_dir)?;
+    let failed = entries.iter().filter(|entry| entry.error.is_some()).count();
+    log(format!(
+        "{} binaries analyzed, {} failed, summary in {}/summary.json",
+        entries.len() - failed,
+        failed,
+        output_dir
+    ));
+    Ok(failed == 0)
+}
+
+// Analyze the paths again each time a binary changes, then print what changed in the behavior
+// of each binary changed. Runs until interrupted.
+fn watch_paths(
+    args: &AnalyzeArgs,
+    api_list: &[ApiSpec],
+    exclude: &[String],
+    output_dir: &str,
+    options: &Options,
+) -> Result<bool> {
+    let mut watcher = Watcher::new(&args.paths, exclude, WATCH_INTERVAL)?;
+    eprintln!("Watching {} for changes", args.paths.join(", "));
+    loop {
+        let changed = watcher.wait()?;
+        let outputs: Vec<(String, String)> = match is_single_file(&args.paths) {
+            true => vec![(args.paths[0].clone(), output_dir.to_string())],
+            false => discover_elf_files(&args.paths, exclude)?
+                .into_iter()
+                .filter(|input| changed.contains(&input.path))
+                .map(|input| {
+                    let dir = Path::new(output_dir).join(&input.output_name);
+                    (
+                        input.path.to_string_lossy().to_string(),
+                        dir.to_string_lossy().to_string(),
+                    )
+                })
+                .collect(),
+        };
+        let before: Vec<ManifestSet> = outputs
+            .iter()
+            .map(|(_, dir)| ManifestSet::load(dir).unwrap_or_default())
+            .collect();
+        let inputs = Inputs {
+            paths: args.paths.clone(),
+            sources: BTreeMap::new(),
+        };
+        if let Err(error) = analyze_paths(args, &inputs, api_list, exclude, output_dir, options) {
+            eprintln!("Elf analysis failed: {}", error);
+            continue;
+        }
+        for ((path, dir), old) in outputs.iter().zip(&before) {
+            let new = ManifestSet::load(dir).unwrap_or_default();
+            println!("{}: {}", path, ManifestDiff::compute(old, &new).summary());
+        }
+    }
+}
+
+// Print the manifests of an output directory to stdout. The manifests of a batch are grouped
+// by the output folder of their binary, next to the summary.
+fn print_manifests(mode: StdoutMode, output_dir: &str, batch: bool) -> Result<()> {
+    let mut lines = Vec::new();
+    let mut document = serde_json::Map::new();
+    for (name, manifest) in collect_manifests(output_dir)? {
+        lines.push(serde_json::json!({ "manifest": name, "content": manifest }));
+        document.insert(name, manifest);
+    }
+    if batch {
+        let mut binaries: Vec<_> = fs::read_dir(output_dir)?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect();
+        binaries.sort();
+        for binary in binaries {
+            let manifests = collect_manifests(&format!("{}/{}", output_dir, binary))?;
+            for (name, manifest) in &manifests {
+                lines.push(serde_json::json!({
+                    "binary": binary,
+                    "manifest": name,
+                    "content": manifest,
+                }));
+            }
+            document.insert(binary, serde_json::json!(manifests));
+        }
+    }
+    match mode {
+        StdoutMode::Json => println!("{}", serde_json::to_string_pretty(&document)?),
+        StdoutMode::Ndjson => {
+            for line in lines {
+                println!("{}", line);
+            }
+        }
     }
-    let elf_file_path = &args[1];
-    let json_file_path = &args[2];
+    Ok(())
+}
 
-    let api_list = match read_api_list(json_file_path) {
-        Ok(list) => list,
-        Err(error) => {
-            eprintln!("Error reading API list from JSON file: {}", error);
-            return;
+// Compare two manifest directories, or two ELF files analyzed with the same API list.
+fn diff_command(old: &str, new: &str, api_list: Option<&str>, json: bool) -> Result<()> {
+    let (mut dirs, mut analyzed) = (Vec::new(), Vec::new());
+    for (version, path) in [("old", old), ("new", new)] {
+        if Path::new(path).is_dir() {
+            dirs.push(path.to_string());
+            continue;
         }
+        let specs = match api_list {
+            Some(api_list) => read_api_list(api_list)?,
+            None => {
+                let config = read_config(None)?;
+                match (config.api_list, config.apis) {
+                    (Some(path), _) => read_api_list(&path)?,
+                    (None, Some(apis)) => apis,
+                    (None, None) => {
+                        return Err(Error::InvalidApiList(
+                            "comparing ELF files requires --api-list".to_string(),
+                        ))
+                    }
+                }
+            }
+        };
+        let dir = env::temp_dir().join(format!("manifest-diff-{}-{}", process::id(), version));
+        fs::create_dir_all(&dir)?;
+        let dir = dir.to_string_lossy().to_string();
+        elf_analysis(path, None, &specs, &dir, &Options::default())?;
+        analyzed.push(dir.clone());
+        dirs.push(dir);
+    }
+
+    let diff = diff_manifests(&dirs[0], &dirs[1]);
+    for dir in analyzed {
+        fs::remove_dir_all(dir)?;
+    }
+    let diff = diff?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&diff.to_json())?);
+    } else {
+        print!("{}", diff);
+    }
+    Ok(())
+}
+
+// Evaluate a policy against the manifests of a directory, and check them against a
+// declaration, printing the reports. Returns whether the manifests pass both.
+fn check_command(
+    dir: &str,
+    policy: Option<&str>,
+    declared: Option<&str>,
+    fail_on: Severity,
+    json: bool,
+) -> Result<bool> {
+    let policy = match policy {
+        Some(path) => Some(Policy::load(path)?.evaluate_dir(dir)?),
+        None => None,
     };
-    let api_list_refs: Vec<&str> = api_list.iter().map(|s| s.as_str()).collect();
+    let conformance = match declared {
+        Some(path) => Some(Declaration::load(path)?.check_dir(dir)?),
+        None => None,
+    };
+    if json {
+        match (&policy, &conformance) {
+            (Some(policy), None) => println!("{}", policy.to_json()),
+            (None, Some(conformance)) => println!("{}", conformance.to_json()),
+            (policy, conformance) => println!(
+                "{}",
+                serde_json::json!({
+                    "policy": policy.as_ref().map(|report| report.to_json()),
+                    "conformance": conformance.as_ref().map(|report| report.to_json()),
+                })
+            ),
+        }
+    } else {
+        if let Some(report) = &policy {
+            println!("{}", report);
+        }
+        if let Some(report) = &conformance {
+            println!("{}", report);
+        }
+    }
+    Ok(policy.is_none_or(|report| report.passed(fail_on))
+        && conformance.is_none_or(|report| report.conforms()))
+}
 
-    let manifest_path = "./manifest-produced";
+// Print the function, source file and line of addresses of a binary, less its load address.
+fn resolve_command(binary: &str, addresses: &[u64], base: u64, json: bool) -> Result<()> {
+    let buffer = read_elf_file(binary)?;
+    let addresses: Vec<u64> = addresses
+        .iter()
+        .map(|address| address.wrapping_sub(base))
+        .collect();
+    let locations = resolve_addresses(&buffer, &addresses)?;
+    if json {
+        let locations: Vec<Value> = locations.iter().map(|l| l.to_json()).collect();
+        println!("{}", serde_json::to_string_pretty(&locations)?);
+    } else {
+        for location in &locations {
+            println!("{}", location);
+        }
+    }
+    Ok(())
+}
 
-    match elf_analysis(elf_file_path, api_list_refs, manifest_path) {
-        Ok(_) => println!("Analysis performed successfully!"),
-        Err(error) => eprintln!("Elf analysis failed: {}", error),
+// Write the comparison matrix of the binaries of directories to a file, or to stdout.
+fn matrix_command(dirs: &[String], format: MatrixFormat, output: Option<&str>) -> Result<()> {
+    let dirs: Vec<&str> = dirs.iter().map(String::as_str).collect();
+    let matrix = FleetMatrix::load(&dirs)?;
+    let contents = match format {
+        MatrixFormat::Csv => matrix.to_csv(),
+        MatrixFormat::Json => serde_json::to_string_pretty(&matrix.to_json())? + "\n",
     };
+    match output {
+        Some(output) => fs::write(output, contents)?,
+        None => print!("{}", contents),
+    }
+    Ok(())
+}
+
+// Print the binaries of a results store matching a query.
+#[cfg(feature = "sqlite")]
+fn query_command(db: &str, query: &Query, json: bool) -> Result<()> {
+    if !Path::new(db).is_file() {
+        return Err(Error::InvalidPath(format!("{}: no such database", db)));
+    }
+    let matches = ResultStore::open(db)?.query(query)?;
+    if json {
+        let matches: Vec<Value> = matches.iter().map(|m| m.to_json()).collect();
+        println!("{}", serde_json::to_string_pretty(&matches)?);
+    } else {
+        for found in &matches {
+            println!("{}", found);
+        }
+    }
+    Ok(())
+}
+
+// Generate a signing key from the random source of the operating system.
+fn keygen_command() -> Result<()> {
+    let key = SigningKey::generate(&mut rand_core::OsRng);
+    let secret: String = key
+        .to_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    println!("signing key: {}", secret);
+    println!("public key: {}", public_key_hex(&key));
+    Ok(())
+}
+
+// Write the signatures of the functions of a binary to a .pat file, or to stdout.
+fn signatures_command(binary: &str, output: Option<&str>, library: Option<&str>) -> Result<()> {
+    let buffer = read_elf_file(binary)?;
+    let elf = goblin::elf::Elf::parse(&buffer)?;
+    let stem = |path: &str| {
+        Path::new(path)
+            .file_stem()
+            .map_or(path.to_string(), |stem| stem.to_string_lossy().to_string())
+    };
+    let library = match (library, output) {
+        (Some(library), _) => library.to_string(),
+        (None, Some(output)) => stem(output),
+        (None, None) => stem(binary),
+    };
+    let pat = generate_signatures(&elf, &buffer, &library)?.to_pat();
+    match output {
+        Some(output) => fs::write(output, pat)?,
+        None => print!("{}", pat),
+    }
+    Ok(())
+}
+
+/// Analyze ELF binaries to produce manifests of their public APIs, behavior and findings.
+#[derive(Parser)]
+#[command(name = "manifest-producer", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+    /// Report errors on stderr as JSON objects, with their class and exit status.
+    #[arg(long, global = true)]
+    json_errors: bool,
+    /// The format of the logs written on stderr, filtered by $RUST_LOG (e.g.
+    /// manifest_producer::cleanup=debug).
+    #[arg(long, global = true, value_enum, default_value = "text")]
+    log_format: LogFormat,
+}
+
+/// The formats of the fleet comparison matrix.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum MatrixFormat {
+    /// One line per binary, one column per value.
+    Csv,
+    /// The values and the outliers of each binary.
+    Json,
+}
+
+/// The formats of the logs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum LogFormat {
+    /// Human-readable lines.
+    Text,
+    /// One JSON object per event.
+    Json,
+}
+
+// Log the events enabled by $RUST_LOG on stderr, the warnings only when it is not set.
+fn init_logging(format: LogFormat) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn"));
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+    match format {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder.json().init(),
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Analyze an ELF binary and write its manifests.
+    Analyze(Box<AnalyzeArgs>),
+    /// Report the behavioral changes between two versions of a firmware.
+    ///
+    /// The versions are manifest directories, in any format, or ELF files analyzed with the
+    /// API list given by --api-list.
+    Diff {
+        /// The manifest directory or ELF file of the old version.
+        old: String,
+        /// The manifest directory or ELF file of the new version.
+        new: String,
+        /// The JSON list of the APIs to look for, when comparing ELF files, the one of
+        /// ./manifest-producer.toml when not given.
+        #[arg(long, value_name = "JSON_FILE")]
+        api_list: Option<String>,
+        /// Print the differences as JSON rather than as text.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Check manifests against the schema of the version they declare.
+    ///
+    /// The kind of each manifest is told from its file name. Exits with status 1 when a
+    /// manifest is invalid.
+    Validate {
+        /// The manifests to check, in any format.
+        #[arg(required = true)]
+        paths: Vec<String>,
+    },
+    /// Evaluate the rules of a compliance policy against the manifests of an output directory,
+    /// or check them against the behavior declared by the vendor.
+    ///
+    /// Exits with status 1 when a rule of the --fail-on severity or more serious fails, or the
+    /// manifests deviate from the declaration.
+    Check {
+        /// The output directory holding the manifests, in any format.
+        dir: String,
+        /// The TOML or JSON file of the policy.
+        #[arg(
+            short,
+            long,
+            value_name = "POLICY_FILE",
+            required_unless_present = "declared"
+        )]
+        policy: Option<String>,
+        /// The TOML or JSON file of the declared behavior: the intended APIs, with their
+        /// capabilities and calls.
+        #[arg(long, value_name = "DECLARATION_FILE")]
+        declared: Option<String>,
+        /// The least serious failure failing the check: info, low, medium, high or critical.
+        #[arg(long, value_name = "SEVERITY", default_value = "info")]
+        fail_on: Severity,
+        /// Print the results as JSON rather than as text.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Merge the manifests of the binaries of a firmware image into a firmware manifest.
+    Merge {
+        /// The manifest directories of the binaries.
+        #[arg(required = true)]
+        dirs: Vec<String>,
+        /// The directory where firmware_manifest is written.
+        #[arg(short, long, value_name = "DIR", default_value = DEFAULT_OUTPUT_DIR)]
+        output_dir: String,
+        /// The format of the firmware manifest.
+        #[arg(long, default_value = "json")]
+        format: Format,
+    },
+    /// Compare the binaries of a fleet or of a product line: their architecture, language, link,
+    /// hardening checks, capabilities and syscalls, and the columns where each is an outlier.
+    Matrix {
+        /// The manifest directories of the binaries, or directories holding them, e.g. the
+        /// output directory of a batch analysis.
+        #[arg(required = true)]
+        dirs: Vec<String>,
+        /// The format of the matrix.
+        #[arg(long, value_enum, default_value = "csv")]
+        format: MatrixFormat,
+        /// The file where the matrix is written, instead of the standard output.
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<String>,
+    },
+    /// Browse the manifests of an output directory in the terminal.
+    ///
+    /// Lists the flows of the flow call manifest, the call tree of the selected one, expanded
+    /// through call_graph.json when written with --call-graph, and its findings.
+    View {
+        /// The output directory holding the manifests, in any format.
+        dir: String,
+    },
+    /// Map addresses of a binary to their function, source file and line, as addr2line does,
+    /// e.g. to resolve the addresses of a crash report.
+    ///
+    /// The files and lines are read from the DWARF line tables of the binary.
+    Resolve {
+        /// The ELF binary the addresses are in.
+        binary: String,
+        /// The addresses to resolve, in hexadecimal.
+        #[arg(required = true, value_name = "ADDRESS", value_parser = parse_address_arg)]
+        addresses: Vec<u64>,
+        /// The address the binary was loaded at, subtracted from the addresses, e.g. of a
+        /// position-independent binary in the memory map of the crash.
+        #[arg(long, value_name = "ADDRESS", value_parser = parse_address_arg, default_value = "0")]
+        base: u64,
+        /// Print the locations as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Answer a question about the binaries of a results store written with --store, without
+    /// analyzing them again, e.g. which binaries call accessWebcam.
+    #[cfg(feature = "sqlite")]
+    #[command(group(clap::ArgGroup::new("question").required(true)))]
+    Query {
+        /// The SQLite database of the results.
+        db: String,
+        /// List the binaries calling a function, and the functions calling it.
+        #[arg(long, value_name = "FUNCTION", group = "question")]
+        calls: Option<String>,
+        /// List the binaries linking a library, e.g. openssl.
+        #[arg(long, value_name = "NAME", group = "question")]
+        library: Option<String>,
+        /// With --library, list only the versions older than this one, e.g. 1.1.1.
+        #[arg(long, value_name = "VERSION", requires = "library")]
+        below: Option<String>,
+        /// List the binaries with an API of a capability, e.g. network-client.
+        #[arg(long, value_name = "CAPABILITY", group = "question")]
+        capability: Option<String>,
+        /// Print the matches as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Verify the signature of the manifests of an output directory.
+    Verify {
+        /// The directory holding the manifests and their signature.
+        dir: String,
+        /// The file holding the trusted public key, read from $MANIFEST_PRODUCER_PUBLIC_KEY
+        /// when not given.
+        #[arg(long, value_name = "KEY_FILE")]
+        public_key: Option<String>,
+        /// The analyzed binary, checked against the signed digest.
+        #[arg(long, value_name = "ELF_FILE")]
+        binary: Option<String>,
+    },
+    /// Serve the analysis over HTTP: `POST /analyze` queues the analysis of an uploaded ELF
+    /// binary and `GET /manifests/{id}` returns its manifests.
+    Serve(ServeArgs),
+    /// Generate an ed25519 key pair to sign the manifests.
+    Keygen,
+    /// Write the FLIRT signatures of the functions of an unstripped binary, e.g. a build of a
+    /// library, to recognize them in stripped binaries with --signatures.
+    Signatures {
+        /// The ELF binary with a symbol table.
+        binary: String,
+        /// The .pat file written, named after the library, stdout when not given.
+        #[arg(short, long, value_name = "PAT_FILE")]
+        output: Option<String>,
+        /// The name of the library, the one of the output file or of the binary when not given.
+        #[arg(long)]
+        library: Option<String>,
+    },
+    /// Print the JSON Schema of a manifest.
+    Schema {
+        /// The manifest: basic_info, flow_call, feature_manifest or firmware_manifest.
+        kind: ManifestKind,
+        /// The schema version, the current one when not given.
+        version: Option<String>,
+    },
+}
+
+#[derive(Args)]
+struct AnalyzeArgs {
+    /// The ELF binary to analyze, or directories and glob patterns of binaries to analyze in
+    /// batch, each in its own folder of the output directory.
+    #[arg(required = true, value_name = "PATH")]
+    paths: Vec<String>,
+    /// The configuration file of the project, ./manifest-producer.toml when not given.
+    #[arg(short, long, value_name = "TOML_FILE")]
+    config: Option<String>,
+    /// The JSON list of the APIs to look for.
+    #[arg(short, long, value_name = "JSON_FILE")]
+    api_list: Option<String>,
+    /// The directory where the manifests are written [default: ./manifest-produced].
+    #[arg(short, long, value_name = "DIR")]
+    output_dir: Option<String>,
+    /// The format of the basic info, flow call and feature manifests [default: json].
+    #[arg(long)]
+    format: Option<Format>,
+    /// Print the manifests to stdout, as a single JSON document or as one JSON line per
+    /// manifest, rather than writing them to the output directory.
+    #[arg(long, value_enum, value_name = "MODE", num_args = 0..=1, default_missing_value = "json")]
+    stdout: Option<StdoutMode>,
+    /// Report errors only, without progress bars.
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+    /// Also report the time spent in each phase; twice, each phase as it ends and the function
+    /// being disassembled.
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+    /// The number of threads analyzing the functions, one per CPU when not given.
+    #[arg(short, long, value_name = "N")]
+    jobs: Option<usize>,
+    /// Read the binary in chunks rather than mapping it in memory.
+    #[arg(long)]
+    no_mmap: bool,
+    /// The directory caching the call graphs of the binaries analyzed, by default
+    /// ~/.cache/manifest-producer.
+    #[arg(long, value_name = "DIR")]
+    cache_dir: Option<String>,
+    /// Disassemble the binaries again rather than reusing the cached call graphs.
+    #[arg(long, conflicts_with = "cache_dir")]
+    no_cache: bool,
+    /// Re-analyze only the functions changed since the build whose manifests are in the output
+    /// directory, and rewrite only the manifests changed.
+    #[arg(long, conflicts_with = "no_cache")]
+    incremental: bool,
+    /// Keep watching the binaries, or the directories holding them, and analyze them again
+    /// incrementally when they change, printing what changed in their behavior.
+    #[arg(long, conflicts_with_all = ["stdout", "no_cache"])]
+    watch: bool,
+    /// The time the disassembly of one function may take, e.g. 2s or 500ms; the functions
+    /// taking longer are cut short and reported as skipped.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration_arg)]
+    function_timeout: Option<Duration>,
+    /// The memory the analysis may use, e.g. 4G; the functions left once it is reached are
+    /// reported as skipped.
+    #[arg(long, value_name = "SIZE", value_parser = parse_size_arg)]
+    memory_limit: Option<u64>,
+    /// The expected architecture of the binary, e.g. x86-64.
+    #[arg(long)]
+    arch: Option<String>,
+    /// The kind of analysis to perform [default: static]; the dynamic analysis runs the binary
+    /// on a Linux host, under ptrace or QEMU.
+    #[arg(long, value_name = "static|dynamic|both")]
+    analysis: Option<AnalysisMode>,
+    /// The functions analyzed as the APIs [default: api-list]; with all, every function of the
+    /// binary is, and the API list is optional.
+    #[arg(long, value_name = "api-list|all")]
+    scope: Option<Scope>,
+    /// How long the binary runs for its dynamic analysis, e.g. 30s [default: 10s].
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration_arg)]
+    duration: Option<Duration>,
+    /// An argument given to the binary run for its dynamic analysis; may be repeated.
+    #[arg(long, value_name = "ARG", allow_hyphen_values = true)]
+    run_arg: Vec<String>,
+    /// Let the binary run for its dynamic analysis reach the network.
+    #[arg(long)]
+    allow_network: bool,
+    /// The QEMU user-mode emulator running the binaries of other architectures for their
+    /// dynamic analysis [default: qemu-<arch>].
+    #[arg(long, value_name = "PROGRAM")]
+    emulator: Option<String>,
+    /// The directory of the libraries of the emulated architecture, e.g.
+    /// /usr/arm-linux-gnueabihf.
+    #[arg(long, value_name = "DIR")]
+    sysroot: Option<String>,
+    /// Unpack the binaries packed with UPX before analyzing them, with the given UPX program
+    /// [default: upx].
+    #[arg(long, value_name = "PROGRAM", num_args = 0..=1, default_missing_value = DEFAULT_UPX)]
+    unpack: Option<String>,
+    /// Leave the functions attributed to a library, e.g. libc or openssl, out of the
+    /// transitive flows; may be repeated.
+    #[arg(long, value_name = "LIBRARY")]
+    exclude_library: Vec<String>,
+    /// A FLIRT .pat file of library signatures, naming the functions of stripped binaries,
+    /// which are only analyzed with signatures; may be repeated.
+    #[arg(long, value_name = "PAT_FILE")]
+    signatures: Vec<String>,
+    /// The root directory of the firmware tree, in which the libraries loaded with dlopen are
+    /// looked for to trace the flows of the symbols looked up in them.
+    #[arg(long, value_name = "DIR")]
+    library_root: Option<String>,
+    /// Keep going when an optional stage of the analysis or the flow of a function fails,
+    /// recording the error in the basic information manifest.
+    #[arg(long)]
+    best_effort: bool,
+    /// Leave the time of the analysis out of the generator block of the manifests, for
+    /// byte-identical manifests of the same binary and options.
+    #[arg(long)]
+    no_timestamp: bool,
+    /// Also write the call graph rooted at each API.
+    #[arg(long)]
+    call_graph: bool,
+    /// Also write the cross-references of each API.
+    #[arg(long)]
+    xrefs: bool,
+    /// Also write the control flow graph of each API.
+    #[arg(long)]
+    cfg: bool,
+    /// Also write a CycloneDX bill of materials.
+    #[arg(long)]
+    cyclonedx: bool,
+    /// Also write an SPDX document.
+    #[arg(long)]
+    spdx: bool,
+    /// Also write a SARIF log of the security findings.
+    #[arg(long)]
+    sarif: bool,
+    /// Also write an HTML report of the manifests.
+    #[arg(long)]
+    html: bool,
+    /// Also write a Markdown summary of the manifests.
+    #[arg(long)]
+    markdown: bool,
+    /// Also write a seccomp allowlist of the syscalls of the binary.
+    #[arg(long)]
+    seccomp: bool,
+    /// Also write a draft AppArmor profile of the binary.
+    #[arg(long)]
+    apparmor: bool,
+    /// Also write the disassembly of each API, its calls and syscalls annotated.
+    #[arg(long)]
+    disassembly: bool,
+    /// List the callees of each API up to N calls away in its disassembly.
+    #[arg(long, value_name = "N")]
+    disassembly_depth: Option<usize>,
+    /// Also write a MUD file of the network behavior, served from this https URL.
+    #[arg(long, value_name = "MUD_URL")]
+    mud: Option<String>,
+    /// Limit the transitive flows to call chains of at most N calls.
+    #[arg(long, value_name = "N")]
+    max_depth: Option<usize>,
+    /// Flag the flows whose worst-case stack depth exceeds this number of bytes.
+    #[arg(long, value_name = "BYTES")]
+    stack_threshold: Option<u64>,
+    /// The JSON list of the functions whose calls are reported, instead of the default one.
+    #[arg(long, value_name = "JSON_FILE")]
+    banned: Option<String>,
+    /// A JSON snapshot of OSV entries to match the libraries against.
+    #[arg(long, value_name = "JSON_FILE")]
+    vuln_db: Option<String>,
+    /// A TOML or JSON file of rules mapping functions, strings and ioctls to capabilities.
+    #[arg(long, value_name = "RULES_FILE")]
+    capability_rules: Option<String>,
+    /// The file holding the key signing the manifests, read from
+    /// $MANIFEST_PRODUCER_SIGNING_KEY when not given.
+    #[arg(long, value_name = "KEY_FILE")]
+    sign_key: Option<String>,
+    /// A shared library of analysis passes and manifest sections to run, which must be built
+    /// with the same compiler; may be repeated.
+    #[cfg(feature = "plugins")]
+    #[arg(long, value_name = "LIB")]
+    plugin: Vec<String>,
+    /// Also write the results of the analyses to this SQLite database, replacing the previous
+    /// results of the same binaries.
+    #[cfg(feature = "sqlite")]
+    #[arg(long, value_name = "DB")]
+    store: Option<String>,
+}
+
+#[derive(Args)]
+struct ServeArgs {
+    /// The address to listen on.
+    #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:8080")]
+    bind: String,
+    /// The configuration file of the project, ./manifest-producer.toml when not given; its
+    /// options apply to every analysis.
+    #[arg(short, long, value_name = "TOML_FILE")]
+    config: Option<String>,
+    /// The number of analyses run at once, one per CPU when not given.
+    #[arg(short, long, value_name = "N")]
+    workers: Option<usize>,
+    /// The number of analyses waiting for a worker past which uploads are refused.
+    #[arg(long, value_name = "N", default_value_t = 16)]
+    queue: usize,
+    /// The size of the largest upload accepted, e.g. 64M.
+    #[arg(long, value_name = "SIZE", value_parser = parse_size_arg, default_value = "256M")]
+    max_upload: u64,
+    /// The directory caching the call graphs of the binaries analyzed, by default
+    /// ~/.cache/manifest-producer.
+    #[arg(long, value_name = "DIR")]
+    cache_dir: Option<String>,
+    /// Disassemble the binaries again rather than reusing the cached call graphs.
+    #[arg(long, conflicts_with = "cache_dir")]
+    no_cache: bool,
+    /// The time the disassembly of one function may take, e.g. 2s or 500ms.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration_arg)]
+    function_timeout: Option<Duration>,
+    /// The memory one analysis may use, e.g. 4G.
+    #[arg(long, value_name = "SIZE", value_parser = parse_size_arg)]
+    memory_limit: Option<u64>,
+    /// Serve the gRPC service of proto/manifest_producer.proto rather than the HTTP endpoints.
+    #[cfg(feature = "grpc")]
+    #[arg(long)]
+    grpc: bool,
+}
+
+// Report an error on stderr, as text or as a JSON object, and exit with the status of its class.
+fn fail(context: &str, error: &Error, json_errors: bool) -> ! {
+    if json_errors {
+        eprintln!("{}", error.to_json());
+    } else {
+        eprintln!("{}: {}", context, error);
+    }
+    process::exit(error.exit_code());
+}
+
+fn main() {
+    let cli = Cli::parse();
+    init_logging(cli.log_format);
+    let json_errors = cli.json_errors;
+    match cli.command {
+        Command::Analyze(args) => match analyze_command(&args) {
+            Ok(true) => {}
+            Ok(false) => process::exit(EXIT_FAILURE),
+            Err(error) => fail("Elf analysis failed", &error, json_errors),
+        },
+        Command::Diff {
+            old,
+            new,
+            api_list,
+            json,
+        } => {
+            if let Err(error) = diff_command(&old, &new, api_list.as_deref(), json) {
+                fail("Diff failed", &error, json_errors);
+            }
+        }
+        Command::Validate { paths } => match validate_manifests(&paths) {
+            Ok(true) => {}
+            Ok(false) => process::exit(EXIT_FAILURE),
+            Err(error) => fail("Validation failed", &error, json_errors),
+        },
+        Command::Check {
+            dir,
+            policy,
+            declared,
+            fail_on,
+            json,
+        } => match check_command(&dir, policy.as_deref(), declared.as_deref(), fail_on, json) {
+            Ok(true) => {}
+            Ok(false) => process::exit(EXIT_FAILURE),
+            Err(error) => fail("Check failed", &error, json_errors),
+        },
+        Command::View { dir } => {
+            if let Err(error) = run_viewer(&dir) {
+                fail("View failed", &error, json_errors);
+            }
+        }
+        Command::Merge {
+            dirs,
+            output_dir,
+            format,
+        } => {
+            let dirs: Vec<&str> = dirs.iter().map(String::as_str).collect();
+            let merged = fs::create_dir_all(&output_dir)
+                .map_err(Error::from)
+                .and_then(|()| merge_manifests(&dirs, format, &output_dir));
+            if let Err(error) = merged {
+                fail("Merge failed", &error, json_errors);
+            }
+        }
+        Command::Matrix {
+            dirs,
+            format,
+            output,
+        } => {
+            if let Err(error) = matrix_command(&dirs, format, output.as_deref()) {
+                fail("Matrix failed", &error, json_errors);
+            }
+        }
+        Command::Resolve {
+            binary,
+            addresses,
+            base,
+            json,
+        } => {
+            if let Err(error) = resolve_command(&binary, &addresses, base, json) {
+                fail("Resolution failed", &error, json_errors);
+            }
+        }
+        #[cfg(feature = "sqlite")]
+        Command::Query {
+            db,
+            calls,
+            library,
+            below,
+            capability,
+            json,
+        } => {
+            let query = match (calls, library, capability) {
+                (Some(function), _, _) => Query::Calls(function),
+                (_, Some(name), _) => Query::Library { name, below },
+                (_, _, Some(capability)) => Query::Capability(capability),
+                (None, None, None) => unreachable!("clap requires one of the questions"),
+            };
+            if let Err(error) = query_command(&db, &query, json) {
+                fail("Query failed", &error, json_errors);
+            }
+        }
+        Command::Verify {
+            dir,
+            public_key,
+            binary,
+        } => {
+            let verified = read_key(public_key.as_deref(), PUBLIC_KEY_VAR)
+                .and_then(|key| {
+                    key.ok_or_else(|| {
+                        Error::InvalidKey(format!("no --public-key nor {}", PUBLIC_KEY_VAR))
+                    })
+                })
+                .and_then(|key| {
+                    let binary = binary.as_deref().map(read_elf_file).transpose()?;
+                    verify_manifests(&dir, &parse_verifying_key(&key)?, binary.as_deref())
+                });
+            match verified {
+                Ok(()) => println!("Signature verified"),
+                Err(error) => fail("Verification failed", &error, json_errors),
+            }
+        }
+        Command::Serve(args) => {
+            if let Err(error) = serve_command(&args) {
+                fail("Server failed", &error, json_errors);
+            }
+        }
+        Command::Keygen => {
+            if let Err(error) = keygen_command() {
+                fail("Key generation failed", &error, json_errors);
+            }
+        }
+        Command::Signatures {
+            binary,
+            output,
+            library,
+        } => {
+            if let Err(error) = signatures_command(&binary, output.as_deref(), library.as_deref()) {
+                fail("Signature generation failed", &error, json_errors);
+            }
+        }
+        Command::Schema { kind, version } => {
+            let version = version.as_deref().unwrap_or(SCHEMA_VERSION);
+            match json_schema(kind, version) {
+                Some(schema) => println!("{}", schema),
+                None => fail(
+                    "Schema failed",
+                    &Error::InvalidManifest(format!(
+                        "no {} schema for version {}",
+                        kind.as_str(),
+                        version
+                    )),
+                    json_errors,
+                ),
+            }
+        }
+    }
 }
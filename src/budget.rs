@@ -0,0 +1,167 @@
+use std::{
+    fmt, fs,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    time::Duration,
+};
+
+// Number of functions disassembled between two readings of the memory in use.
+const MEMORY_CHECK_INTERVAL: usize = 32;
+
+/// The resources the disassembly of a binary may use before functions are skipped.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Budget {
+    /// The time the disassembly of one function may take, unlimited when `None`.
+    pub function_timeout: Option<Duration>,
+    /// The memory in use by the process, in bytes, past which the functions left are skipped,
+    /// unlimited when `None`.
+    pub memory_limit: Option<u64>,
+}
+
+/// The budget a function went over.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Exceeded {
+    /// Its disassembly took longer than the time limit.
+    Time(Duration),
+    /// The memory limit was reached before it was disassembled.
+    Memory(u64),
+}
+
+impl fmt::Display for Exceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Exceeded::Time(limit) => {
+                write!(f, "skipped: budget exceeded (time limit of {:?})", limit)
+            }
+            Exceeded::Memory(limit) if *limit >= 1 << 20 => write!(
+                f,
+                "skipped: budget exceeded (memory limit of {} MiB)",
+                limit >> 20
+            ),
+            Exceeded::Memory(limit) => write!(
+                f,
+                "skipped: budget exceeded (memory limit of {} bytes)",
+                limit
+            ),
+        }
+    }
+}
+
+/// Tracks the memory budget across the functions disassembled, from several threads.
+pub struct MemoryTracker {
+    limit: Option<u64>,
+    functions: AtomicUsize,
+    exceeded: AtomicBool,
+}
+
+impl MemoryTracker {
+    /// Track the memory limit of a budget.
+    pub fn new(budget: &Budget) -> Self {
+        Self {
+            limit: budget.memory_limit,
+            functions: AtomicUsize::new(0),
+            exceeded: AtomicBool::new(false),
+        }
+    }
+
+    /// Check the memory budget before a function is disassembled.
+    ///
+    /// The memory in use is read every few functions; once over the limit, every function
+    /// left is skipped.
+    pub fn check(&self) -> Option<Exceeded> {
+        let limit = self.limit?;
+        if !self.exceeded.load(Ordering::Relaxed)
+            && self
+                .functions
+                .fetch_add(1, Ordering::Relaxed)
+                .is_multiple_of(MEMORY_CHECK_INTERVAL)
+            && resident_memory().is_some_and(|used| used > limit)
+        {
+            self.exceeded.store(true, Ordering::Relaxed);
+        }
+        self.exceeded
+            .load(Ordering::Relaxed)
+            .then_some(Exceeded::Memory(limit))
+    }
+}
+
+/// Return the memory in use by the process, in bytes, `None` where it cannot be read (outside
+/// Linux).
+pub fn resident_memory() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kib: u64 = line
+        .trim_start_matches("VmRSS:")
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()?;
+    Some(kib << 10)
+}
+
+/// Parse a memory size with an optional `K`, `M` or `G` binary suffix, e.g. `512M`.
+pub fn parse_size(size: &str) -> Option<u64> {
+    let size = size.trim();
+    let (digits, shift) = match size.chars().last()?.to_ascii_uppercase() {
+        'K' => (&size[..size.len() - 1], 10),
+        'M' => (&size[..size.len() - 1], 20),
+        'G' => (&size[..size.len() - 1], 30),
+        _ => (size, 0),
+    };
+    digits.trim().parse::<u64>().ok()?.checked_mul(1 << shift)
+}
+
+/// Parse a duration in seconds or with a `ms`, `s` or `m` suffix, e.g. `500ms`.
+pub fn parse_duration(duration: &str) -> Option<Duration> {
+    let duration = duration.trim();
+    let (number, unit) = match duration.find(|c: char| c.is_ascii_alphabetic()) {
+        Some(index) => duration.split_at(index),
+        None => (duration, "s"),
+    };
+    let number: f64 = number
+        .trim()
+        .parse()
+        .ok()
+        .filter(|n: &f64| n.is_finite() && *n >= 0.0)?;
+    let seconds = match unit {
+        "ms" => number / 1000.0,
+        "s" => number,
+        "m" => number * 60.0,
+        _ => return None,
+    };
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget() {
+        assert_eq!(parse_size("512M"), Some(512 << 20));
+        assert_eq!(parse_size("2g"), Some(2 << 30));
+        assert_eq!(parse_size("4096"), Some(4096));
+        assert_eq!(parse_size("lots"), None);
+        assert_eq!(parse_duration("500ms"), Some(Duration::from_millis(500)));
+        assert_eq!(parse_duration("2"), Some(Duration::from_secs(2)));
+        assert_eq!(parse_duration("1h"), None);
+        // Too long for a `Duration`.
+        assert_eq!(parse_duration("1e300"), None);
+        assert_eq!(parse_duration("1e307m"), None);
+
+        let unlimited = MemoryTracker::new(&Budget::default());
+        assert_eq!(unlimited.check(), None);
+        if resident_memory().is_some() {
+            let tight = MemoryTracker::new(&Budget {
+                memory_limit: Some(1),
+                ..Budget::default()
+            });
+            assert_eq!(tight.check(), Some(Exceeded::Memory(1)));
+            assert_eq!(tight.check(), Some(Exceeded::Memory(1)));
+        }
+        assert_eq!(
+            Exceeded::Time(Duration::from_millis(100)).to_string(),
+            "skipped: budget exceeded (time limit of 100ms)"
+        );
+    }
+}
@@ -0,0 +1,195 @@
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use goblin::elf::Elf;
+use serde_json::{json, Value};
+use tracing::{debug, warn};
+
+use crate::{
+    call_graph::{CallGraph, FunctionNode},
+    elf_utils::{build_id, code_bytes, sha256_hex},
+    error::Result,
+    xrefs::XrefDb,
+};
+
+// Version of the layout of the cache entries, bumped when the cached results change.
+const CACHE_VERSION: u64 = 3;
+
+/// The results of an analysis that do not depend on the API list nor on the outputs.
+pub struct CachedAnalysis {
+    /// The call graph of the whole binary.
+    pub graph: CallGraph,
+    /// The cross-references of the functions of the call graph.
+    pub xrefs: XrefDb,
+    /// The digests of the code of the functions, by starting address, see [`function_hashes`].
+    pub hashes: BTreeMap<u64, String>,
+}
+
+/// An on-disk cache of the call graphs and cross-references of the binaries analyzed.
+///
+/// The entries are keyed by [`cache_key`], so that a binary analyzed again, with another API
+/// list or other outputs, is not disassembled a second time.
+pub struct AnalysisCache {
+    dir: PathBuf,
+}
+
+impl AnalysisCache {
+    /// Use the cache in the directory `dir`, created when the first entry is stored.
+    pub fn new(dir: &str) -> Self {
+        Self {
+            dir: PathBuf::from(dir),
+        }
+    }
+
+    /// The default directory of the cache, `$XDG_CACHE_HOME/manifest-producer` or
+    /// `~/.cache/manifest-producer`.
+    pub fn default_dir() -> Option<String> {
+        let base = match env::var_os("XDG_CACHE_HOME").filter(|dir| !dir.is_empty()) {
+            Some(dir) => PathBuf::from(dir),
+            None => PathBuf::from(env::var_os("HOME")?).join(".cache"),
+        };
+        Some(base.join("manifest-producer").to_string_lossy().to_string())
+    }
+
+    /// Read the entry of a binary, `None` when there is none or it was written by another
+    /// version of the tool.
+    pub fn load(&self, key: &str) -> Option<CachedAnalysis> {
+        let path = self.entry_path(key);
+        let contents = fs::read(&path).ok()?;
+        let Ok(entry) = serde_json::from_slice::<Value>(&contents) else {
+            warn!(path = %path.display(), "malformed cache entry, ignored");
+            return None;
+        };
+        if entry["version"].as_u64() != Some(CACHE_VERSION)
+            || entry["tool"].as_str() != Some(env!("CARGO_PKG_VERSION"))
+        {
+            debug!(key, "stale cache entry");
+            return None;
+        }
+        let hashes = entry["hashes"]
+            .as_array()?
+            .iter()
+            .map(|hash| Some((hash[0].as_u64()?, hash[1].as_str()?.to_string())))
+            .collect::<Option<_>>()?;
+        let cached = CachedAnalysis {
+            graph: CallGraph::from_cache(&entry["graph"])?,
+            xrefs: XrefDb::from_cache(&entry["xrefs"])?,
+            hashes,
+        };
+        debug!(key, "cache hit");
+        Some(cached)
+    }
+
+    /// Write the entry of a binary, replacing the previous one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory of the cache or the entry cannot be written.
+    pub fn store(
+        &self,
+        key: &str,
+        graph: &CallGraph,
+        xrefs: &XrefDb,
+        hashes: &BTreeMap<u64, String>,
+    ) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let hashes: Vec<Value> = hashes
+            .iter()
+            .map(|(addr, hash)| json!([addr, hash]))
+            .collect();
+        let entry = json!({
+            "version": CACHE_VERSION,
+            "tool": env!("CARGO_PKG_VERSION"),
+            "graph": graph.to_cache(),
+            "xrefs": xrefs.to_cache(),
+            "hashes": hashes,
+        });
+        // Written aside and renamed, so that concurrent runs never read a partial entry.
+        let path = self.entry_path(key);
+        let partial = path.with_extension(format!("{}.tmp", std::process::id()));
+        fs::write(&partial, serde_json::to_vec(&entry)?)?;
+        fs::rename(&partial, &path)?;
+        debug!(key, path = %path.display(), "cache entry stored");
+        Ok(())
+    }
+
+    /// Remove every entry of the cache.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory of the cache cannot be removed.
+    pub fn clear(&self) -> Result<()> {
+        if Path::new(&self.dir).exists() {
+            fs::remove_dir_all(&self.dir)?;
+        }
+        Ok(())
+    }
+
+    // The file holding the entry of a key.
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+}
+
+/// The key of a binary in the cache: its GNU build ID, or the SHA-256 digest of its content when
+/// it has none.
+///
+/// The size of the binary is part of the build ID keys, so that a copy of a binary stripped or
+/// patched after the build is not mistaken for the original.
+pub fn cache_key(elf: &Elf, buffer: &[u8]) -> String {
+    match build_id(elf, buffer) {
+        Some(id) => format!("build-id-{}-{}", id, buffer.len()),
+        None => format!("sha256-{}", sha256_hex(buffer)),
+    }
+}
+
+/// The SHA-256 digests of the code of functions, by starting address, to tell the functions
+/// unchanged between two builds of a binary.
+pub fn function_hashes<'a>(
+    elf: &Elf,
+    buffer: &[u8],
+    functions: impl IntoIterator<Item = &'a FunctionNode>,
+) -> BTreeMap<u64, String> {
+    let mut hashes = BTreeMap::new();
+    for func in functions {
+        if let Some(code) = code_bytes(elf, buffer, func.start_addr, func.end_addr) {
+            hashes
+                .entry(func.start_addr)
+                .or_insert_with(|| sha256_hex(code));
+        }
+    }
+    hashes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf_utils::read_elf_file;
+    use std::env::temp_dir;
+
+    #[test]
+    fn test_analysis_cache() {
+        let buffer = read_elf_file("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        let key = cache_key(&elf, &buffer);
+        assert!(key.starts_with("build-id-9c6dfd55ffd5536ea4ac9ca333a395f187922b09-"));
+
+        let dir = temp_dir().join("manifest-cache");
+        let cache = AnalysisCache::new(dir.to_str().unwrap());
+        cache.clear().unwrap();
+        assert!(cache.load(&key).is_none());
+
+        let graph = CallGraph::build(&elf, &buffer, false).unwrap();
+        let xrefs = XrefDb::build(&elf, &buffer, &graph);
+        let hashes = function_hashes(&elf, &buffer, graph.functions());
+        cache.store(&key, &graph, &xrefs, &hashes).unwrap();
+        let cached = cache.load(&key).unwrap();
+        assert_eq!(cached.graph.to_cache(), graph.to_cache());
+        assert_eq!(cached.xrefs.to_cache(), xrefs.to_cache());
+        assert_eq!(cached.hashes, hashes);
+        cache.clear().unwrap();
+    }
+}
@@ -0,0 +1,1172 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use std::{fmt::Write, ops::Range};
+
+use goblin::elf::{section_header::SHF_ALLOC, Elf};
+use rayon::prelude::*;
+use tracing::warn;
+use web_time::Instant;
+
+use crate::{
+    budget::{Budget, Exceeded, MemoryTracker},
+    cleanup::demangle_api_name,
+    code_section_handler::{direct_target, lea_target, tail_call_target},
+    disassembler::{Disassembler, Instruction},
+    elf_utils::{code_bytes, is_static, symbol_index, API},
+    error,
+    indirect_calls::{
+        memory_operand, IndirectResolution, PointerResolver, PointerValue, RegisterState,
+    },
+    plt_mapping::{find_plt_section, load_rela_plt_relocations},
+    stack_usage::prologue_frame,
+};
+use error::{Error, Result};
+
+// Number of instructions of a function disassembled and analyzed between two checks of its time
+// limit.
+const TIMEOUT_CHECK_INTERVAL: usize = 256;
+
+/// A function of the binary, as a node of the call graph.
+pub struct FunctionNode {
+    /// The symbol name of the function.
+    pub name: String,
+    /// The starting address of the function.
+    pub start_addr: u64,
+    /// The ending address of the function.
+    pub end_addr: u64,
+}
+
+/// A call from a function to another one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CallEdge {
+    /// The address of the instruction performing the call.
+    pub site: u64,
+    /// The address of the called function, when it is known.
+    pub target: Option<u64>,
+    /// The name of the called function, or of the PLT entry for imported functions.
+    pub name: String,
+    /// Whether the call is indirect, its target being derived from a function pointer or a vtable.
+    pub indirect: bool,
+}
+
+/// An indirect call whose target could not be determined statically.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnresolvedCall {
+    /// The address of the instruction performing the call.
+    pub site: u64,
+    /// The operand of the call, in AT&T syntax.
+    pub operand: String,
+}
+
+/// An address of the binary loaded by the code of a function, as an immediate or a
+/// RIP-relative operand, e.g. of a string or of a callback.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Load {
+    /// The address of the instruction loading the address.
+    pub site: u64,
+    /// The address loaded, within an allocated section of the binary.
+    pub addr: u64,
+}
+
+/// The calls reachable from a function, as found by [`CallGraph::traverse`].
+pub struct Traversal<'a> {
+    /// The reachable calls in visit order, one per distinct callee.
+    pub calls: Vec<&'a CallEdge>,
+    /// The functions called again while they are still being visited, i.e. closing a cycle.
+    pub recursive: Vec<u64>,
+    /// Whether some functions were not visited because of the depth limit.
+    pub truncated: bool,
+}
+
+/// The caller→callee graph of a whole binary.
+///
+/// Every function of the symbol table is disassembled once, so the flows of any number of APIs
+/// can then be derived without disassembling their code again. The addresses loaded by the
+/// code are recorded along the way, see [`CallGraph::loads`].
+#[derive(Default)]
+pub struct CallGraph {
+    functions: BTreeMap<u64, FunctionNode>,
+    calls: HashMap<u64, Vec<CallEdge>>,
+    unresolved: HashMap<u64, Vec<UnresolvedCall>>,
+    loads: HashMap<u64, Vec<Load>>,
+    prologues: HashMap<u64, u64>,
+    skipped: BTreeMap<u64, String>,
+}
+
+// The calls found in a function, or copied from a previous build.
+struct Disassembled {
+    addr: u64,
+    edges: Vec<CallEdge>,
+    unresolved: Vec<UnresolvedCall>,
+    loads: Vec<Load>,
+    prologue: Option<u64>,
+    reused: bool,
+    skipped: Option<Exceeded>,
+}
+
+impl CallGraph {
+    /// Build the call graph of the binary.
+    ///
+    /// Calls are identified in the same way as in [`crate::code_section_handler::code_section`]:
+    /// through `call` instructions and tail jumps to other functions, resolved via the PLT for
+    /// dynamically linked binaries, or through the `lea` of function addresses for Rust binaries.
+    ///
+    /// The targets of indirect calls of non-Rust binaries are resolved, when possible, through
+    /// the function pointers loaded in registers, the GOT, tables of callbacks and vtables.
+    /// Those that cannot be resolved are kept apart, see [`CallGraph::unresolved_calls`].
+    ///
+    /// # Arguments
+    ///
+    /// * `elf` - The ELF object representing the binary.
+    /// * `buffer` - The buffer containing the binary data of the ELF file.
+    /// * `rust` - A boolean indicating whether the binary is written in Rust (`true`) or not (`false`).
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the call graph.
+    pub fn build(elf: &Elf, buffer: &[u8], rust: bool) -> Result<Self> {
+        Self::build_with_functions(
+            elf,
+            buffer,
+            rust,
+            function_nodes(elf).into_values().collect(),
+        )
+    }
+
+    /// Build the call graph over a given set of functions.
+    ///
+    /// This is used when function boundaries come from somewhere else than the symbol table,
+    /// e.g. the `.gopclntab` of stripped Go binaries. The names of these functions are also
+    /// used to resolve call targets that have no symbol.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the call graph.
+    pub fn build_with_functions(
+        elf: &Elf,
+        buffer: &[u8],
+        rust: bool,
+        nodes: Vec<FunctionNode>,
+    ) -> Result<Self> {
+        Self::build_observed(elf, buffer, rust, nodes, &|_, _| {})
+    }
+
+    /// Build the call graph over a given set of functions, calling `observer` with each function
+    /// before it is disassembled and the number of functions.
+    ///
+    /// This lets a caller report the progress of the analysis of large binaries. The functions
+    /// are disassembled in parallel, so `observer` is called from several threads and in no
+    /// particular order; the graph does not depend on it.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the call graph.
+    pub fn build_observed(
+        elf: &Elf,
+        buffer: &[u8],
+        rust: bool,
+        nodes: Vec<FunctionNode>,
+        observer: &(dyn Fn(&FunctionNode, usize) + Sync),
+    ) -> Result<Self> {
+        Self::build_budgeted(elf, buffer, rust, nodes, &Budget::default(), observer)
+    }
+
+    /// Build the call graph over a given set of functions within a resource budget.
+    ///
+    /// The disassembly of a function that takes longer than the time limit is cut short, and
+    /// the functions left once the memory limit is reached are not disassembled; both are
+    /// recorded, see [`CallGraph::skipped`], and the calls found up to then are kept.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the call graph.
+    pub fn build_budgeted(
+        elf: &Elf,
+        buffer: &[u8],
+        rust: bool,
+        nodes: Vec<FunctionNode>,
+        budget: &Budget,
+        observer: &(dyn Fn(&FunctionNode, usize) + Sync),
+    ) -> Result<Self> {
+        let (graph, _) = Self::build_reusing(elf, buffer, rust, nodes, budget, observer, None)?;
+        Ok(graph)
+    }
+
+    /// Build the call graph of a new build of a binary, reusing the calls and loads of the
+    /// functions unchanged since a previous build.
+    ///
+    /// A function is reused when it starts at the same address, has the same name and size,
+    /// its address is in `unchanged` (its code is the same) and none of its calls is indirect,
+    /// since those depend on the data of the binary. The names of the calls reused are resolved
+    /// again against the symbols and PLT of the new build.
+    ///
+    /// # Arguments
+    ///
+    /// * `elf` - The ELF object representing the new build.
+    /// * `buffer` - The buffer containing the binary data of the ELF file.
+    /// * `rust` - A boolean indicating whether the binary is written in Rust (`true`) or not (`false`).
+    /// * `nodes` - The functions of the new build.
+    /// * `previous` - The call graph of the previous build, and the starting addresses of the
+    ///   functions whose code did not change.
+    /// * `budget` - The resources the disassembly of the functions changed may use.
+    /// * `observer` - Called with each function before it is disassembled or reused.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the call graph and the starting addresses of the
+    /// functions reused.
+    pub fn build_incremental(
+        elf: &Elf,
+        buffer: &[u8],
+        rust: bool,
+        nodes: Vec<FunctionNode>,
+        previous: (&CallGraph, &BTreeSet<u64>),
+        budget: &Budget,
+        observer: &(dyn Fn(&FunctionNode, usize) + Sync),
+    ) -> Result<(Self, BTreeSet<u64>)> {
+        Self::build_reusing(elf, buffer, rust, nodes, budget, observer, Some(previous))
+    }
+
+    // Build the call graph, copying the calls of the unchanged functions of a previous graph
+    // rather than disassembling them. Returns the graph and the functions reused.
+    fn build_reusing(
+        elf: &Elf,
+        buffer: &[u8],
+        rust: bool,
+        nodes: Vec<FunctionNode>,
+        budget: &Budget,
+        observer: &(dyn Fn(&FunctionNode, usize) + Sync),
+        reuse: Option<(&CallGraph, &BTreeSet<u64>)>,
+    ) -> Result<(Self, BTreeSet<u64>)> {
+        let mut functions = BTreeMap::new();
+        for node in nodes {
+            functions.entry(node.start_addr).or_insert(node);
+        }
+        let mut names = symbol_index(elf);
+        for func in functions.values() {
+            names.entry(func.start_addr).or_insert(func.name.as_str());
+        }
+        let link = is_static(elf);
+        let allocated = allocated_ranges(elf);
+
+        let mut found_plt_sec = false;
+        let plt_map = if link {
+            None
+        } else {
+            find_plt_section(elf, &mut found_plt_sec).and_then(|plt_section| {
+                let plt_entry_size = plt_section.sh_entsize as usize;
+                load_rela_plt_relocations(elf, plt_section, plt_entry_size, found_plt_sec)
+            })
+        };
+
+        // Name the target of a direct call, falling back to its address when `fallback` is set.
+        let resolve = |site: u64, target: u64, op_str: &str, fallback: bool| {
+            let name = match &plt_map {
+                Some(plt) => plt
+                    .get(&target)
+                    .or(names.get(&target))
+                    .map(|n| n.to_string()),
+                None if fallback => Some(
+                    names
+                        .get(&target)
+                        .map_or_else(|| format!("CALL_to_<{}>", op_str), |n| n.to_string()),
+                ),
+                None => names.get(&target).map(|n| n.to_string()),
+            }?;
+            Some(CallEdge {
+                site,
+                target: Some(target),
+                name,
+                indirect: false,
+            })
+        };
+
+        // The calls of an unchanged function, named after the symbols of this build.
+        let reused = |func: &FunctionNode| -> Option<Vec<CallEdge>> {
+            let (previous, unchanged) = reuse?;
+            let old = previous.function(func.start_addr)?;
+            if !unchanged.contains(&func.start_addr)
+                || old.name != func.name
+                || old.end_addr != func.end_addr
+                || !previous.unresolved_calls(func.start_addr).is_empty()
+                || previous
+                    .callees(func.start_addr)
+                    .iter()
+                    .any(|edge| edge.indirect)
+            {
+                return None;
+            }
+            let edges = previous
+                .callees(func.start_addr)
+                .iter()
+                .map(|edge| {
+                    let name = edge.target.and_then(|target| match &plt_map {
+                        Some(plt) if !rust => plt.get(&target).or(names.get(&target)),
+                        _ => names.get(&target),
+                    });
+                    CallEdge {
+                        name: name.map_or_else(|| edge.name.clone(), |name| name.to_string()),
+                        ..edge.clone()
+                    }
+                })
+                .collect();
+            Some(edges)
+        };
+
+        let resolver = PointerResolver::new(elf, buffer);
+        // Each thread disassembles with its own disassembler.
+        Disassembler::new()?;
+        let memory = MemoryTracker::new(budget);
+        let disassembled: Vec<Disassembled> = functions
+            .par_iter()
+            .map_init(Disassembler::new, |disassembler, (_, func)| -> Result<_> {
+                observer(func, functions.len());
+                let mut found = Disassembled {
+                    addr: func.start_addr,
+                    edges: Vec::new(),
+                    unresolved: Vec::new(),
+                    loads: Vec::new(),
+                    prologue: None,
+                    reused: false,
+                    skipped: None,
+                };
+                if let Some(edges) = reused(func) {
+                    found.edges = edges;
+                    if let Some((previous, _)) = reuse {
+                        found.loads = previous.loads(func.start_addr).to_vec();
+                        found.prologue = previous.prologue_frame(func.start_addr);
+                    }
+                    found.reused = true;
+                    return Ok(Some(found));
+                }
+                if let Some(exceeded) = memory.check() {
+                    found.skipped = Some(exceeded);
+                    return Ok(Some(found));
+                }
+                let disassembler = match disassembler {
+                    Ok(disassembler) => disassembler,
+                    Err(error) => return Err(Error::Capstone(error.to_string())),
+                };
+                let Some(code) = code_bytes(elf, buffer, func.start_addr, func.end_addr) else {
+                    return Ok(None);
+                };
+                let started = Instant::now();
+                // Whether the disassembly of the function went over its time limit.
+                let timed_out = || {
+                    budget
+                        .function_timeout
+                        .filter(|limit| started.elapsed() > *limit)
+                        .map(Exceeded::Time)
+                };
+                let mut edges = Vec::new();
+                let mut unresolved_calls = Vec::new();
+                let mut loads = Vec::new();
+                let mut state = RegisterState::new();
+                // The function is disassembled a chunk at a time, its time limit being checked
+                // before each chunk.
+                let mut offset = 0;
+                while offset < code.len() {
+                    if let Some(exceeded) = timed_out() {
+                        found.skipped = Some(exceeded);
+                        break;
+                    }
+                    let instructions = match disassembler.disassemble_count(
+                        &code[offset..],
+                        func.start_addr + offset as u64,
+                        TIMEOUT_CHECK_INTERVAL,
+                    ) {
+                        Ok(instructions) => instructions,
+                        Err(_) if offset == 0 => return Ok(None),
+                        Err(_) => break,
+                    };
+                    if offset == 0 {
+                        found.prologue = Some(prologue_frame(&instructions, func.start_addr));
+                    }
+                    offset += instructions.iter().map(|insn| insn.size).sum::<usize>();
+                    for insn in &instructions {
+                        let (mnemonic, op_str) = (insn.mnemonic.as_str(), insn.op_str.as_str());
+                        let site = insn.address;
+                        let next_addr = insn.next_addr();
+                        loads.extend(
+                            loaded_addresses(insn)
+                                .filter(|addr| allocated.iter().any(|range| range.contains(addr)))
+                                .map(|addr| Load { site, addr }),
+                        );
+
+                        if !rust && mnemonic.starts_with("call") {
+                            match state.resolve_call(op_str, next_addr, &resolver) {
+                                IndirectResolution::Direct => {}
+                                IndirectResolution::Resolved(targets) => {
+                                    let resolved: Vec<CallEdge> = targets
+                                        .into_iter()
+                                        .filter_map(|value| {
+                                            let (target, name) = match value {
+                                                PointerValue::Address(addr) => {
+                                                    (Some(addr), names.get(&addr)?.to_string())
+                                                }
+                                                PointerValue::Import(name) => (None, name),
+                                            };
+                                            Some(CallEdge {
+                                                site,
+                                                target,
+                                                name,
+                                                indirect: true,
+                                            })
+                                        })
+                                        .collect();
+                                    if resolved.is_empty() {
+                                        unresolved_calls.push(UnresolvedCall {
+                                            site,
+                                            operand: op_str.to_string(),
+                                        });
+                                    }
+                                    edges.extend(resolved);
+                                }
+                                IndirectResolution::Unresolved => {
+                                    unresolved_calls.push(UnresolvedCall {
+                                        site,
+                                        operand: op_str.to_string(),
+                                    })
+                                }
+                            }
+                        }
+                        state.update(mnemonic, op_str, next_addr, &resolver);
+
+                        let edge = if rust && mnemonic.starts_with("lea") {
+                            lea_target(op_str, site).and_then(|target| {
+                                names.get(&target).map(|name| CallEdge {
+                                    site,
+                                    target: Some(target),
+                                    name: name.to_string(),
+                                    indirect: false,
+                                })
+                            })
+                        } else if !rust && mnemonic.starts_with("call") {
+                            direct_target(op_str)
+                                .and_then(|target| resolve(site, target, op_str, link))
+                        } else if !rust {
+                            // Tail calls must land on a known function, other jumps stay in the code.
+                            tail_call_target(mnemonic, op_str, func.start_addr, func.end_addr)
+                                .and_then(|target| resolve(site, target, op_str, false))
+                        } else {
+                            None
+                        };
+                        edges.extend(edge);
+                    }
+                    if instructions.len() < TIMEOUT_CHECK_INTERVAL {
+                        break;
+                    }
+                }
+                found.edges = edges;
+                found.unresolved = unresolved_calls;
+                found.loads = loads;
+                Ok(Some(found))
+            })
+            .filter_map(Result::transpose)
+            .collect::<Result<_>>()?;
+
+        let mut calls = HashMap::new();
+        let mut unresolved = HashMap::new();
+        let mut loads = HashMap::new();
+        let mut prologues = HashMap::new();
+        let mut skipped = BTreeMap::new();
+        let mut memory_skipped = 0;
+        let mut reused_functions = BTreeSet::new();
+        for found in disassembled {
+            calls.insert(found.addr, found.edges);
+            if !found.unresolved.is_empty() {
+                unresolved.insert(found.addr, found.unresolved);
+            }
+            if !found.loads.is_empty() {
+                loads.insert(found.addr, found.loads);
+            }
+            if let Some(frame) = found.prologue {
+                prologues.insert(found.addr, frame);
+            }
+            if found.reused {
+                reused_functions.insert(found.addr);
+            }
+            match found.skipped {
+                Some(exceeded @ Exceeded::Time(_)) => {
+                    warn!(function = functions[&found.addr].name, "{}", exceeded);
+                    skipped.insert(found.addr, exceeded.to_string());
+                }
+                Some(exceeded @ Exceeded::Memory(_)) => {
+                    memory_skipped += 1;
+                    skipped.insert(found.addr, exceeded.to_string());
+                }
+                None => {}
+            }
+        }
+        if memory_skipped > 0 {
+            warn!(
+                "memory budget exceeded, {} functions skipped",
+                memory_skipped
+            );
+        }
+
+        let graph = Self {
+            functions,
+            calls,
+            unresolved,
+            loads,
+            prologues,
+            skipped,
+        };
+        Ok((graph, reused_functions))
+    }
+
+    /// Assemble a call graph from the calls found by another decoder than the
+    /// [`Disassembler`], e.g. that of the Thumb code of [`crate::cortex_m`] images.
+    ///
+    /// # Arguments
+    ///
+    /// * `nodes` - The functions of the binary.
+    /// * `calls` - The calls of each function, by its starting address.
+    /// * `unresolved` - The indirect calls of each function whose target is unknown.
+    pub fn from_calls(
+        nodes: Vec<FunctionNode>,
+        calls: HashMap<u64, Vec<CallEdge>>,
+        unresolved: HashMap<u64, Vec<UnresolvedCall>>,
+    ) -> Self {
+        let mut functions = BTreeMap::new();
+        for node in nodes {
+            functions.entry(node.start_addr).or_insert(node);
+        }
+        Self {
+            functions,
+            calls,
+            unresolved,
+            loads: HashMap::new(),
+            prologues: HashMap::new(),
+            skipped: BTreeMap::new(),
+        }
+    }
+
+    /// Return the functions of the graph, sorted by address.
+    pub fn functions(&self) -> impl Iterator<Item = &FunctionNode> {
+        self.functions.values()
+    }
+
+    /// Return why the disassembly of the function starting at the given address was cut short
+    /// or skipped, `None` when it was disassembled whole.
+    pub fn skipped(&self, addr: u64) -> Option<&str> {
+        self.skipped.get(&addr).map(String::as_str)
+    }
+
+    /// Whether the disassembly of some function was cut short or skipped.
+    pub fn is_complete(&self) -> bool {
+        self.skipped.is_empty()
+    }
+
+    /// Return the function starting at the given address.
+    pub fn function(&self, addr: u64) -> Option<&FunctionNode> {
+        self.functions.get(&addr)
+    }
+
+    /// Return the function whose code contains the given address.
+    pub fn function_containing(&self, addr: u64) -> Option<&FunctionNode> {
+        self.functions
+            .range(..=addr)
+            .next_back()
+            .map(|(_, func)| func)
+            .filter(|func| addr < func.end_addr)
+    }
+
+    /// Return the calls performed by the function starting at the given address, in program order.
+    pub fn callees(&self, addr: u64) -> &[CallEdge] {
+        self.calls.get(&addr).map_or(&[], Vec::as_slice)
+    }
+
+    /// Return the indirect calls of the function at the given address whose target is unknown.
+    pub fn unresolved_calls(&self, addr: u64) -> &[UnresolvedCall] {
+        self.unresolved.get(&addr).map_or(&[], Vec::as_slice)
+    }
+
+    /// Return the addresses loaded by the code of the function starting at the given address,
+    /// in program order.
+    pub fn loads(&self, addr: u64) -> &[Load] {
+        self.loads.get(&addr).map_or(&[], Vec::as_slice)
+    }
+
+    /// Return the stack frame set up by the prologue of the function starting at the given
+    /// address, see [`crate::stack_usage::StackFrames`], `None` when it was not disassembled.
+    pub fn prologue_frame(&self, addr: u64) -> Option<u64> {
+        self.prologues.get(&addr).copied()
+    }
+
+    /// Return the names of the functions directly called by the function at the given address.
+    pub fn callee_names(&self, addr: u64) -> Vec<String> {
+        self.callees(addr)
+            .iter()
+            .map(|edge| edge.name.clone())
+            .collect()
+    }
+
+    /// Return every call reachable from the function at the given address.
+    ///
+    /// The graph is visited depth-first, each function once, so recursive functions terminate.
+    /// Calls are returned in visit order, one per distinct callee.
+    pub fn transitive_callees(&self, addr: u64) -> Vec<&CallEdge> {
+        self.traverse(addr, None).calls
+    }
+
+    /// Visit the calls reachable from the function at the given address, up to a maximum depth.
+    ///
+    /// The direct calls of the function are at depth 1. Each function is visited once, and the
+    /// calls back into a function that is still being visited are reported as recursion.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The starting address of the function.
+    /// * `max_depth` - The maximum length of the followed call chains, unlimited when `None`.
+    ///
+    /// # Returns
+    ///
+    /// Returns the reachable calls together with the recursive functions met along the way.
+    pub fn traverse(&self, addr: u64, max_depth: Option<usize>) -> Traversal<'_> {
+        let mut traversal = Traversal {
+            calls: Vec::new(),
+            recursive: Vec::new(),
+            truncated: false,
+        };
+        let mut visited = HashSet::from([addr]);
+        let mut seen_names = HashSet::new();
+        // The functions of the current call chain, each with the index of its next call to visit.
+        let mut stack = vec![(addr, 0)];
+        let mut on_stack = HashSet::from([addr]);
+
+        while let Some((caller, next)) = stack.last_mut() {
+            let caller = *caller;
+            let Some(edge) = self.callees(caller).get(*next) else {
+                stack.pop();
+                on_stack.remove(&caller);
+                continue;
+            };
+            *next += 1;
+            let depth = stack.len();
+
+            if seen_names.insert(edge.name.as_str()) {
+                traversal.calls.push(edge);
+            }
+            let Some(target) = edge.target.filter(|t| self.functions.contains_key(t)) else {
+                continue;
+            };
+            if on_stack.contains(&target) {
+                if !traversal.recursive.contains(&target) {
+                    traversal.recursive.push(target);
+                }
+            } else if max_depth.is_some_and(|max| depth >= max) {
+                traversal.truncated |= !visited.contains(&target);
+            } else if visited.insert(target) {
+                stack.push((target, 0));
+                on_stack.insert(target);
+            }
+        }
+        traversal
+    }
+
+    /// Return the addresses of the functions reachable from the given roots, roots included.
+    ///
+    /// With no roots, every function of the graph is returned.
+    pub fn reachable(&self, roots: &[u64]) -> BTreeSet<u64> {
+        if roots.is_empty() {
+            return self.functions.keys().copied().collect();
+        }
+        let mut reachable = BTreeSet::new();
+        let mut stack: Vec<u64> = roots.to_vec();
+        while let Some(addr) = stack.pop() {
+            if !self.functions.contains_key(&addr) || !reachable.insert(addr) {
+                continue;
+            }
+            stack.extend(self.callees(addr).iter().filter_map(|edge| edge.target));
+        }
+        reachable
+    }
+
+    /// Return the functions starting at the given addresses as the roots of their own flows,
+    /// e.g. the start routines of threads, once each and sorted by address.
+    ///
+    /// The addresses where no function of the graph starts are left out.
+    pub fn root_apis(&self, addrs: impl IntoIterator<Item = u64>) -> Vec<API> {
+        let addrs: BTreeSet<u64> = addrs.into_iter().collect();
+        addrs
+            .iter()
+            .filter_map(|&addr| self.function(addr))
+            .map(|func| {
+                API::new(
+                    demangle_api_name(&func.name),
+                    func.start_addr,
+                    func.end_addr,
+                )
+            })
+            .collect()
+    }
+
+    /// Render the subgraph reachable from the given roots in the DOT language of Graphviz.
+    ///
+    /// With no roots, the whole graph is rendered. Imported functions, which have no code in
+    /// the binary, are drawn as dashed boxes.
+    pub fn to_dot(&self, roots: &[u64]) -> String {
+        let (nodes, edges) = self.subgraph(roots);
+        let mut dot = String::from("digraph call_graph {\n    node [shape=box];\n");
+        for node in &nodes {
+            let style = if node.external { ", style=dashed" } else { "" };
+            let _ = writeln!(
+                dot,
+                "    \"{}\" [label=\"{}\"{}];",
+                escape_dot(&node.id),
+                escape_dot(&node.label),
+                style
+            );
+        }
+        for (source, target) in &edges {
+            let _ = writeln!(
+                dot,
+                "    \"{}\" -> \"{}\";",
+                escape_dot(source),
+                escape_dot(target)
+            );
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Describe the subgraph reachable from the given roots as a node/edge JSON document.
+    ///
+    /// With no roots, the whole graph is described.
+    pub fn to_json(&self, roots: &[u64]) -> serde_json::Value {
+        let (nodes, edges) = self.subgraph(roots);
+        let nodes: Vec<serde_json::Value> = nodes
+            .iter()
+            .map(|node| {
+                serde_json::json!({
+                    "id": node.id,
+                    "name": node.label,
+                    "address": node.address.map(|addr| format!("{:#x}", addr)),
+                    "external": node.external,
+                })
+            })
+            .collect();
+        let edges: Vec<serde_json::Value> = edges
+            .iter()
+            .map(|(source, target)| serde_json::json!({ "source": source, "target": target }))
+            .collect();
+        serde_json::json!({ "nodes": nodes, "edges": edges })
+    }
+
+    /// Serialize the whole graph, to be read back with [`CallGraph::from_cache`].
+    pub fn to_cache(&self) -> serde_json::Value {
+        let functions: Vec<serde_json::Value> = self
+            .functions
+            .values()
+            .map(|func| serde_json::json!([func.start_addr, func.end_addr, func.name]))
+            .collect();
+        let mut calls: Vec<(&u64, &Vec<CallEdge>)> = self.calls.iter().collect();
+        calls.sort_by_key(|(addr, _)| **addr);
+        let calls: Vec<serde_json::Value> = calls
+            .into_iter()
+            .map(|(addr, edges)| {
+                let edges: Vec<serde_json::Value> = edges
+                    .iter()
+                    .map(|edge| {
+                        serde_json::json!([edge.site, edge.target, edge.name, edge.indirect])
+                    })
+                    .collect();
+                serde_json::json!([addr, edges])
+            })
+            .collect();
+        let mut unresolved: Vec<(&u64, &Vec<UnresolvedCall>)> = self.unresolved.iter().collect();
+        unresolved.sort_by_key(|(addr, _)| **addr);
+        let unresolved: Vec<serde_json::Value> = unresolved
+            .into_iter()
+            .map(|(addr, calls)| {
+                let calls: Vec<serde_json::Value> = calls
+                    .iter()
+                    .map(|call| serde_json::json!([call.site, call.operand]))
+                    .collect();
+                serde_json::json!([addr, calls])
+            })
+            .collect();
+        let mut loads: Vec<(&u64, &Vec<Load>)> = self.loads.iter().collect();
+        loads.sort_by_key(|(addr, _)| **addr);
+        let loads: Vec<serde_json::Value> = loads
+            .into_iter()
+            .map(|(addr, loads)| {
+                let loads: Vec<serde_json::Value> = loads
+                    .iter()
+                    .map(|load| serde_json::json!([load.site, load.addr]))
+                    .collect();
+                serde_json::json!([addr, loads])
+            })
+            .collect();
+        let mut prologues: Vec<(&u64, &u64)> = self.prologues.iter().collect();
+        prologues.sort_by_key(|(addr, _)| **addr);
+        serde_json::json!({
+            "functions": functions,
+            "calls": calls,
+            "unresolved": unresolved,
+            "loads": loads,
+            "prologues": prologues,
+        })
+    }
+
+    /// Read back a graph serialized by [`CallGraph::to_cache`], `None` if it is malformed.
+    pub fn from_cache(value: &serde_json::Value) -> Option<Self> {
+        let mut graph = Self::default();
+        for func in value["functions"].as_array()? {
+            let node = FunctionNode {
+                start_addr: func[0].as_u64()?,
+                end_addr: func[1].as_u64()?,
+                name: func[2].as_str()?.to_string(),
+            };
+            graph.functions.insert(node.start_addr, node);
+        }
+        for entry in value["calls"].as_array()? {
+            let edges = entry[1]
+                .as_array()?
+                .iter()
+                .map(|edge| {
+                    Some(CallEdge {
+                        site: edge[0].as_u64()?,
+                        target: edge[1].as_u64(),
+                        name: edge[2].as_str()?.to_string(),
+                        indirect: edge[3].as_bool()?,
+                    })
+                })
+                .collect::<Option<_>>()?;
+            graph.calls.insert(entry[0].as_u64()?, edges);
+        }
+        for entry in value["unresolved"].as_array()? {
+            let calls = entry[1]
+                .as_array()?
+                .iter()
+                .map(|call| {
+                    Some(UnresolvedCall {
+                        site: call[0].as_u64()?,
+                        operand: call[1].as_str()?.to_string(),
+                    })
+                })
+                .collect::<Option<_>>()?;
+            graph.unresolved.insert(entry[0].as_u64()?, calls);
+        }
+        for entry in value["loads"].as_array()? {
+            let loads = entry[1]
+                .as_array()?
+                .iter()
+                .map(|load| {
+                    Some(Load {
+                        site: load[0].as_u64()?,
+                        addr: load[1].as_u64()?,
+                    })
+                })
+                .collect::<Option<_>>()?;
+            graph.loads.insert(entry[0].as_u64()?, loads);
+        }
+        for entry in value["prologues"].as_array()? {
+            graph
+                .prologues
+                .insert(entry[0].as_u64()?, entry[1].as_u64()?);
+        }
+        Some(graph)
+    }
+
+    // Collect the nodes and the distinct edges of the subgraph reachable from the roots.
+    fn subgraph(&self, roots: &[u64]) -> (Vec<ExportNode>, BTreeSet<(String, String)>) {
+        let reachable = self.reachable(roots);
+        let mut nodes: BTreeMap<String, ExportNode> = BTreeMap::new();
+        let mut edges = BTreeSet::new();
+        for &addr in &reachable {
+            let func = &self.functions[&addr];
+            let source = node_id(Some(addr), &func.name);
+            nodes.entry(source.clone()).or_insert_with(|| ExportNode {
+                id: source.clone(),
+                label: demangle_api_name(&func.name),
+                address: Some(addr),
+                external: false,
+            });
+            for edge in self.callees(addr) {
+                let internal = edge
+                    .target
+                    .filter(|target| self.functions.contains_key(target));
+                let target = node_id(internal, &edge.name);
+                nodes.entry(target.clone()).or_insert_with(|| ExportNode {
+                    id: target.clone(),
+                    label: demangle_api_name(&edge.name),
+                    address: internal,
+                    external: internal.is_none(),
+                });
+                edges.insert((source.clone(), target));
+            }
+        }
+        (nodes.into_values().collect(), edges)
+    }
+}
+
+// A node of an exported (sub)graph.
+struct ExportNode {
+    id: String,
+    label: String,
+    address: Option<u64>,
+    external: bool,
+}
+
+// Functions of the binary are identified by address, imported ones by name.
+fn node_id(addr: Option<u64>, name: &str) -> String {
+    match addr {
+        Some(addr) => format!("{:#x}", addr),
+        None => name.to_string(),
+    }
+}
+
+fn escape_dot(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// The addresses an instruction loads, as immediates or RIP-relative operands.
+fn loaded_addresses(insn: &Instruction) -> impl Iterator<Item = u64> + '_ {
+    insn.op_str
+        .split(", ")
+        .filter_map(|operand| match operand.strip_prefix("$0x") {
+            Some(imm) => u64::from_str_radix(imm, 16).ok(),
+            None => memory_operand(operand)
+                .filter(|(_, base)| base == "%rip")
+                .map(|(disp, _)| insn.next_addr().wrapping_add(disp as u64)),
+        })
+}
+
+// The address ranges of the allocated sections, which the addresses loaded by the code must
+// fall in to be kept.
+fn allocated_ranges(elf: &Elf) -> Vec<Range<u64>> {
+    elf.section_headers
+        .iter()
+        .filter(|sec| sec.sh_flags as u32 & SHF_ALLOC != 0 && sec.sh_addr != 0)
+        .map(|sec| sec.sh_addr..sec.sh_addr.saturating_add(sec.sh_size))
+        .collect()
+}
+
+/// Collect the functions defined in the symbol table, one per starting address.
+pub fn function_nodes(elf: &Elf) -> BTreeMap<u64, FunctionNode> {
+    let mut functions = BTreeMap::new();
+    for sym in elf.syms.iter() {
+        if sym.st_type() != goblin::elf::sym::STT_FUNC || sym.st_shndx == 0 || sym.st_size == 0 {
+            continue;
+        }
+        if let Some(name) = elf.strtab.get_at(sym.st_name) {
+            functions
+                .entry(sym.st_value)
+                .or_insert_with(|| FunctionNode {
+                    name: name.to_string(),
+                    start_addr: sym.st_value,
+                    end_addr: sym.st_value + sym.st_size,
+                });
+        }
+    }
+    functions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf_utils::read_elf_file;
+
+    fn graph_of(file_path: &str) -> (Vec<u8>, Option<u64>) {
+        let buffer = read_elf_file(file_path).unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        let addr = elf
+            .syms
+            .iter()
+            .find(|sym| elf.strtab.get_at(sym.st_name) == Some("writeOnDrive"))
+            .map(|sym| sym.st_value);
+        (buffer, addr)
+    }
+
+    #[test]
+    fn test_call_graph_dynamic() {
+        let (buffer, addr) = graph_of("./tests/elf_file/fake-firmware-c-dynamic");
+        let elf = Elf::parse(&buffer).unwrap();
+        let graph = CallGraph::build(&elf, &buffer, false).unwrap();
+        let addr = addr.unwrap();
+
+        assert_eq!(graph.function(addr).unwrap().name, "writeOnDrive");
+        let names = graph.callee_names(addr);
+        assert!(names.iter().any(|name| name == "fopen64"));
+        assert!(names.iter().any(|name| name == "fclose"));
+        // The file name and mode passed to `fopen64`, at least.
+        assert!(graph.loads(addr).len() >= 2);
+
+        let transitive = graph.transitive_callees(addr);
+        assert!(transitive.len() >= graph.callees(addr).len() - 1);
+    }
+
+    #[test]
+    fn test_transitive_callees_static() {
+        let (buffer, addr) = graph_of("./tests/elf_file/minimal-fake-firmware-c-static");
+        let elf = Elf::parse(&buffer).unwrap();
+        let graph = CallGraph::build(&elf, &buffer, false).unwrap();
+        let addr = addr.unwrap();
+
+        let direct = graph.callee_names(addr);
+        let transitive: Vec<&str> = graph
+            .transitive_callees(addr)
+            .iter()
+            .map(|edge| edge.name.as_str())
+            .collect();
+        let reachable = graph.reachable(&[addr]);
+        assert!(reachable.contains(&addr));
+        assert!(reachable.len() > 1);
+
+        let dot = graph.to_dot(&[addr]);
+        assert!(dot.starts_with("digraph call_graph {"));
+        assert!(dot.contains("[label=\"writeOnDrive\"]"));
+        let json = graph.to_json(&[addr]);
+        assert_eq!(
+            json["nodes"].as_array().unwrap().len(),
+            dot.lines().filter(|line| line.contains("[label=")).count()
+        );
+        assert!(!json["edges"].as_array().unwrap().is_empty());
+
+        // The transitive flow starts with the direct calls of the API and goes deeper.
+        assert_eq!(transitive[0], direct[0]);
+        assert!(transitive.len() > direct.len());
+    }
+
+    #[test]
+    fn test_build_budgeted() {
+        let (buffer, addr) = graph_of("./tests/elf_file/fake-firmware-c-dynamic");
+        let elf = Elf::parse(&buffer).unwrap();
+        let addr = addr.unwrap();
+        let nodes = || function_nodes(&elf).into_values().collect();
+        let build = |budget: Budget| {
+            CallGraph::build_budgeted(&elf, &buffer, false, nodes(), &budget, &|_, _| {}).unwrap()
+        };
+
+        let graph = build(Budget::default());
+        assert!(graph.is_complete());
+        let graph = build(Budget {
+            function_timeout: Some(std::time::Duration::ZERO),
+            ..Budget::default()
+        });
+        assert!(graph
+            .skipped(addr)
+            .is_some_and(|reason| reason.starts_with("skipped: budget exceeded")));
+        // The functions are all there, only their calls are missing: the time limit is
+        // checked before their code is disassembled.
+        assert_eq!(graph.function(addr).unwrap().name, "writeOnDrive");
+        assert!(graph.callees(addr).is_empty() && graph.loads(addr).is_empty());
+    }
+
+    fn edge(site: u64, target: u64, name: &str) -> CallEdge {
+        CallEdge {
+            site,
+            target: Some(target),
+            name: name.to_string(),
+            indirect: false,
+        }
+    }
+
+    #[test]
+    fn test_traverse_recursion_and_depth() {
+        let functions = [(0x10, "even"), (0x20, "odd"), (0x30, "leaf")]
+            .into_iter()
+            .map(|(addr, name)| {
+                let node = FunctionNode {
+                    name: name.to_string(),
+                    start_addr: addr,
+                    end_addr: addr + 0x10,
+                };
+                (addr, node)
+            })
+            .collect();
+        let calls = HashMap::from([
+            (0x10, vec![edge(0x11, 0x20, "odd")]),
+            (
+                0x20,
+                vec![edge(0x21, 0x10, "even"), edge(0x22, 0x30, "leaf")],
+            ),
+        ]);
+        let graph = CallGraph {
+            functions,
+            calls,
+            unresolved: HashMap::new(),
+            skipped: BTreeMap::new(),
+            ..CallGraph::default()
+        };
+
+        let full = graph.traverse(0x10, None);
+        let names: Vec<&str> = full.calls.iter().map(|edge| edge.name.as_str()).collect();
+        assert_eq!(names, ["odd", "even", "leaf"]);
+        assert_eq!(full.recursive, [0x10]);
+        assert!(!full.truncated);
+
+        let shallow = graph.traverse(0x10, Some(1));
+        assert_eq!(shallow.calls.len(), 1);
+        assert!(shallow.recursive.is_empty());
+        assert!(shallow.truncated);
+    }
+
+    #[test]
+    fn test_to_dot_escapes_ids() {
+        let functions = BTreeMap::from([(
+            0x10,
+            FunctionNode {
+                name: "main".to_string(),
+                start_addr: 0x10,
+                end_addr: 0x20,
+            },
+        )]);
+        let external = CallEdge {
+            site: 0x11,
+            target: None,
+            name: "quote\"back\\slash".to_string(),
+            indirect: false,
+        };
+        let graph = CallGraph {
+            functions,
+            calls: HashMap::from([(0x10, vec![external])]),
+            unresolved: HashMap::new(),
+            skipped: BTreeMap::new(),
+            ..CallGraph::default()
+        };
+
+        let dot = graph.to_dot(&[0x10]);
+        assert!(dot.contains("    \"quote\\\"back\\\\slash\" [label="));
+        assert!(dot.contains("    \"0x10\" -> \"quote\\\"back\\\\slash\";"));
+    }
+
+    #[test]
+    fn test_traverse_deep_chain() {
+        // A chain deep enough to overflow the stack of a recursive visit.
+        let depth = 200_000u64;
+        let functions = (0..depth)
+            .map(|i| {
+                let node = FunctionNode {
+                    name: format!("f{}", i),
+                    start_addr: i * 0x10,
+                    end_addr: i * 0x10 + 0x10,
+                };
+                (i * 0x10, node)
+            })
+            .collect();
+        let calls = (0..depth - 1)
+            .map(|i| {
+                let target = (i + 1) * 0x10;
+                (
+                    i * 0x10,
+                    vec![edge(i * 0x10 + 1, target, &format!("f{}", i + 1))],
+                )
+            })
+            .collect();
+        let graph = CallGraph {
+            functions,
+            calls,
+            unresolved: HashMap::new(),
+            skipped: BTreeMap::new(),
+            ..CallGraph::default()
+        };
+
+        let traversal = graph.traverse(0, None);
+        assert_eq!(traversal.calls.len() as u64, depth - 1);
+        assert!(traversal.recursive.is_empty());
+    }
+}
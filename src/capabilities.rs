@@ -0,0 +1,509 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+
+use goblin::elf::Elf;
+use serde_json::Value;
+
+use crate::{
+    call_graph::CallGraph,
+    disassembler::Disassembler,
+    elf_utils::{code_bytes, API},
+    error::{Error, Result},
+    indirect_calls::{PointerResolver, RegisterState},
+};
+
+// A built-in capability, as (capability, functions, strings and ioctl requests revealing it).
+type BuiltinRule = (
+    &'static str,
+    &'static [&'static str],
+    &'static [&'static str],
+    &'static [u64],
+);
+
+// Built-in capabilities. Functions written with `::`, `.` or `/` are looked for in the full
+// names, the others are compared with the base names of the functions called.
+const BUILTIN_RULES: [BuiltinRule; 8] = [
+    (
+        "filesystem-read",
+        &[
+            "read",
+            "pread",
+            "fread",
+            "fgets",
+            "fgetc",
+            "getline",
+            "fscanf",
+            "readdir",
+            "opendir",
+            "std::fs::read",
+            "os.ReadFile",
+        ],
+        &[],
+        &[],
+    ),
+    (
+        "filesystem-write",
+        &[
+            "write",
+            "pwrite",
+            "fwrite",
+            "fputs",
+            "fputc",
+            "fprintf",
+            "creat",
+            "rename",
+            "unlink",
+            "mkdir",
+            "rmdir",
+            "truncate",
+            "ftruncate",
+            "chmod",
+            "std::fs::write",
+            "os.WriteFile",
+        ],
+        &[],
+        &[],
+    ),
+    (
+        "network-client",
+        &[
+            "connect",
+            "getaddrinfo",
+            "gethostbyname",
+            "curl_easy_perform",
+            "SSL_connect",
+            "mbedtls_net_connect",
+            "TcpStream::connect",
+            "UdpSocket::connect",
+            "net.Dial",
+            "cpr::",
+        ],
+        &[],
+        &[],
+    ),
+    (
+        "network-server",
+        &[
+            "bind",
+            "listen",
+            "accept",
+            "accept4",
+            "TcpListener::bind",
+            "net.Listen",
+        ],
+        &[],
+        &[],
+    ),
+    (
+        "camera",
+        &["v4l2_open", "v4l2_ioctl", "rscam::", "cv::VideoCapture"],
+        &["/dev/video", "/dev/v4l"],
+        // VIDIOC_QUERYCAP, VIDIOC_STREAMON and VIDIOC_DQBUF.
+        &[0x80685600, 0x40045612, 0xc0585611],
+    ),
+    (
+        "audio",
+        &["snd_pcm_open", "alcOpenDevice", "pa_simple_new"],
+        &["/dev/snd", "/dev/dsp"],
+        // SNDRV_PCM_IOCTL_PREPARE.
+        &[0x4140],
+    ),
+    (
+        "gpio",
+        &[
+            "gpiod_chip_open",
+            "gpiod_chip_open_by_name",
+            "gpiod_line_request_output",
+        ],
+        &["/sys/class/gpio", "/dev/gpiochip"],
+        // GPIO_GET_CHIPINFO_IOCTL, GPIO_GET_LINEHANDLE_IOCTL and GPIO_V2_GET_LINE_IOCTL.
+        &[0x8044b401, 0xc16cb403, 0xc250b407],
+    ),
+    (
+        "process-spawn",
+        &[
+            "fork",
+            "vfork",
+            "clone",
+            "execve",
+            "execv",
+            "execvp",
+            "execl",
+            "execlp",
+            "system",
+            "popen",
+            "posix_spawn",
+            "posix_spawnp",
+            "process::Command",
+            "os/exec.",
+        ],
+        &[],
+        &[],
+    ),
+];
+
+/// A rule telling a capability from the functions called, the strings used and the `ioctl`
+/// requests made by an API.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CapabilityRule {
+    /// The label of the capability, e.g. `network-client`.
+    pub capability: String,
+    /// The functions revealing the capability.
+    pub functions: Vec<String>,
+    /// The substrings of the strings revealing the capability, e.g. device paths.
+    pub strings: Vec<String>,
+    /// The `ioctl` request codes revealing the capability.
+    pub ioctls: Vec<u64>,
+}
+
+/// A capability of an API, with the functions and strings revealing it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ApiCapability {
+    /// The label of the capability.
+    pub capability: String,
+    /// The functions called and strings used by the API that matched the rule, sorted.
+    pub evidence: Vec<String>,
+}
+
+/// Return the built-in capability rules: filesystem read and write, network client and server,
+/// camera, audio, GPIO and process spawn.
+pub fn builtin_rules() -> Vec<CapabilityRule> {
+    BUILTIN_RULES
+        .iter()
+        .map(|(capability, functions, strings, ioctls)| CapabilityRule {
+            capability: capability.to_string(),
+            functions: functions.iter().map(|name| name.to_string()).collect(),
+            strings: strings.iter().map(|value| value.to_string()).collect(),
+            ioctls: ioctls.to_vec(),
+        })
+        .collect()
+}
+
+/// Load capability rules from a TOML or JSON file, told by its extension.
+///
+/// The file lists `rules`, each with a `capability` label and the `functions`, `strings` and
+/// `ioctls` (numbers or hexadecimal strings) revealing it:
+///
+/// ```toml
+/// [[rules]]
+/// capability = "lamp-control"
+/// functions = ["gpiod_line_set_value"]
+/// ioctls = ["0xc16cb403"]
+/// ```
+///
+/// A rule with the label of a built-in rule extends it, or replaces it with `replace = true`;
+/// `builtin = false` at the top of the file drops all the built-in rules.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidRules`] if the file does not follow this layout.
+pub fn load_rules(path: &str) -> Result<Vec<CapabilityRule>> {
+    let contents = fs::read_to_string(path)?;
+    let json: Value = if path.ends_with(".toml") {
+        toml::from_str(&contents).map_err(|error| Error::InvalidRules(error.to_string()))?
+    } else {
+        serde_json::from_str(&contents)?
+    };
+    rules_from_json(&json)
+}
+
+/// Read capability rules from a JSON document, see [`load_rules`].
+pub fn rules_from_json(json: &Value) -> Result<Vec<CapabilityRule>> {
+    let invalid = |reason: String| Error::InvalidRules(reason);
+    let mut rules = match json.get("builtin") {
+        None | Some(Value::Bool(true)) => builtin_rules(),
+        Some(Value::Bool(false)) => Vec::new(),
+        Some(value) => return Err(invalid(format!("builtin must be a boolean, not {}", value))),
+    };
+    let entries = json
+        .get("rules")
+        .and_then(Value::as_array)
+        .ok_or_else(|| invalid("expected an array of rules".to_string()))?;
+
+    for entry in entries {
+        let capability = entry
+            .get("capability")
+            .and_then(Value::as_str)
+            .ok_or_else(|| invalid(format!("missing capability in {}", entry)))?;
+        let names = |field: &str| -> Result<Vec<String>> {
+            entry
+                .get(field)
+                .map_or(&[][..], |value| {
+                    value.as_array().map_or(&[][..], Vec::as_slice)
+                })
+                .iter()
+                .map(|value| {
+                    value
+                        .as_str()
+                        .map(str::to_string)
+                        .ok_or_else(|| invalid(format!("{} of {}: {}", field, capability, value)))
+                })
+                .collect()
+        };
+        let ioctls = entry
+            .get("ioctls")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .map(|value| {
+                value
+                    .as_u64()
+                    .or_else(|| {
+                        let hex = value.as_str()?.strip_prefix("0x")?;
+                        u64::from_str_radix(hex, 16).ok()
+                    })
+                    .ok_or_else(|| invalid(format!("ioctl of {}: {}", capability, value)))
+            })
+            .collect::<Result<Vec<u64>>>()?;
+        let rule = CapabilityRule {
+            capability: capability.to_string(),
+            functions: names("functions")?,
+            strings: names("strings")?,
+            ioctls,
+        };
+
+        let replace = entry.get("replace").and_then(Value::as_bool) == Some(true);
+        match rules
+            .iter_mut()
+            .find(|known| known.capability == capability)
+        {
+            Some(known) if replace => *known = rule,
+            Some(known) => {
+                known.functions.extend(rule.functions);
+                known.strings.extend(rule.strings);
+                known.ioctls.extend(rule.ioctls);
+            }
+            None => rules.push(rule),
+        }
+    }
+    Ok(rules)
+}
+
+/// Recover the constant request codes of the `ioctl` calls of each function.
+///
+/// The request is the second argument of `ioctl`, read from `%rsi` before the call.
+///
+/// # Arguments
+///
+/// * `elf` - The ELF object representing the binary.
+/// * `buffer` - The buffer containing the binary data of the ELF file.
+/// * `graph` - The call graph of the binary.
+///
+/// # Returns
+///
+/// Returns a `Result` containing the requests of each function making some, by address.
+pub fn ioctl_requests(
+    elf: &Elf,
+    buffer: &[u8],
+    graph: &CallGraph,
+) -> Result<HashMap<u64, BTreeSet<u64>>> {
+    let resolver = PointerResolver::new(elf, buffer);
+    let disassembler = Disassembler::new()?;
+    let mut requests = HashMap::new();
+
+    for func in graph.functions() {
+        let sites: BTreeSet<u64> = graph
+            .callees(func.start_addr)
+            .iter()
+            .filter(|edge| {
+                let name = edge.name.split('@').next().unwrap_or(&edge.name);
+                name.trim_start_matches('_') == "ioctl"
+            })
+            .map(|edge| edge.site)
+            .collect();
+        if sites.is_empty() {
+            continue;
+        }
+        let Some(code) = code_bytes(elf, buffer, func.start_addr, func.end_addr) else {
+            continue;
+        };
+        let Ok(instructions) = disassembler.disassemble(code, func.start_addr) else {
+            continue;
+        };
+
+        let mut found = BTreeSet::new();
+        let mut state = RegisterState::new();
+        for insn in instructions.iter() {
+            if sites.contains(&insn.address) {
+                // `ioctl` takes an unsigned long, but the request codes are 32-bit.
+                found.extend(state.constant("%rsi").map(|request| request & 0xffff_ffff));
+            }
+            state.update(&insn.mnemonic, &insn.op_str, insn.next_addr(), &resolver);
+        }
+        if !found.is_empty() {
+            requests.insert(func.start_addr, found);
+        }
+    }
+    Ok(requests)
+}
+
+/// Collect the `ioctl` requests made by the functions reachable from an API, sorted.
+pub fn api_ioctls(
+    requests: &HashMap<u64, BTreeSet<u64>>,
+    graph: &CallGraph,
+    addr: u64,
+) -> Vec<u64> {
+    graph
+        .reachable(&[addr])
+        .iter()
+        .filter_map(|func| requests.get(func))
+        .flatten()
+        .copied()
+        .collect::<BTreeSet<u64>>()
+        .into_iter()
+        .collect()
+}
+
+/// Classify the flow of an API into high-level capabilities.
+///
+/// # Arguments
+///
+/// * `api` - The API, with its syscalls, transitive calls, strings and ioctl requests.
+/// * `rules` - The capability rules.
+///
+/// # Returns
+///
+/// Returns the capabilities matched by at least one function, string or ioctl request, in the
+/// order of the rules.
+pub fn classify_api(api: &API, rules: &[CapabilityRule]) -> Vec<ApiCapability> {
+    let mut evidence: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for call in api.syscalls.iter().chain(&api.transitive_calls) {
+        for rule in rules {
+            if rule.functions.iter().any(|function| calls(call, function)) {
+                evidence
+                    .entry(&rule.capability)
+                    .or_default()
+                    .push(call.clone());
+            }
+        }
+    }
+    for string in &api.strings {
+        for rule in rules {
+            if rule
+                .strings
+                .iter()
+                .any(|value| string.value.contains(value.as_str()))
+            {
+                evidence
+                    .entry(&rule.capability)
+                    .or_default()
+                    .push(string.value.clone());
+            }
+        }
+    }
+    for request in &api.ioctls {
+        for rule in rules.iter().filter(|rule| rule.ioctls.contains(request)) {
+            evidence
+                .entry(&rule.capability)
+                .or_default()
+                .push(format!("ioctl {:#x}", request));
+        }
+    }
+
+    let mut capabilities = Vec::new();
+    for rule in rules {
+        let Some(mut evidence) = evidence.remove(rule.capability.as_str()) else {
+            continue;
+        };
+        evidence.sort_unstable();
+        evidence.dedup();
+        capabilities.push(ApiCapability {
+            capability: rule.capability.clone(),
+            evidence,
+        });
+    }
+    capabilities
+}
+
+// Whether a called function is the one of a rule: `_IO_fopen64`, `__libc_write` and
+// `write@plt` all call `write` or `fopen`.
+fn calls(call: &str, function: &str) -> bool {
+    if function.contains(['.', ':', '/']) {
+        return call.contains(function);
+    }
+    let name = call.split('@').next().unwrap_or(call);
+    let name = name.strip_suffix("64").unwrap_or(name);
+    name == function
+        || name
+            .strip_suffix(function)
+            .is_some_and(|prefix| prefix.ends_with('_'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strings::{StringClass, StringRef};
+
+    #[test]
+    fn test_calls() {
+        assert!(calls("write", "write"));
+        assert!(calls("__libc_write", "write"));
+        assert!(calls("fopen64@GLIBC_2.2.5", "fopen"));
+        assert!(calls("_IO_fwrite", "fwrite"));
+        assert!(!calls("fwrite", "write"));
+        assert!(!calls("rewrite", "write"));
+        assert!(calls(
+            "std::net::tcp::TcpStream::connect",
+            "TcpStream::connect"
+        ));
+    }
+
+    #[test]
+    fn test_rules_from_json() {
+        let rules = rules_from_json(&serde_json::json!({
+            "rules": [
+                { "capability": "camera", "ioctls": ["0x40045612", 42] },
+                { "capability": "gpio", "functions": ["gpio_write"], "replace": true },
+                { "capability": "lamp-control", "functions": ["turnLampOn"] },
+            ],
+        }))
+        .unwrap();
+        let rule = |name: &str| rules.iter().find(|rule| rule.capability == name).unwrap();
+        assert_eq!(rules.len(), BUILTIN_RULES.len() + 1);
+        assert!(rule("camera").ioctls.ends_with(&[0x40045612, 42]));
+        assert!(rule("camera").strings.contains(&"/dev/video".to_string()));
+        assert_eq!(rule("gpio").functions, ["gpio_write"]);
+        assert!(rule("gpio").strings.is_empty());
+        assert_eq!(rule("lamp-control").functions, ["turnLampOn"]);
+
+        let rules = rules_from_json(&serde_json::json!({
+            "builtin": false,
+            "rules": [{ "capability": "lamp-control", "functions": ["turnLampOn"] }],
+        }))
+        .unwrap();
+        assert_eq!(rules.len(), 1);
+        assert!(rules_from_json(&serde_json::json!({ "rules": [{ "functions": [] }] })).is_err());
+        assert!(rules_from_json(&serde_json::json!({
+            "rules": [{ "capability": "camera", "ioctls": ["VIDIOC"] }],
+        }))
+        .is_err());
+    }
+
+    #[test]
+    fn test_classify_api() {
+        let mut api = API::new("accessWebcam".to_string(), 0, 0);
+        api.syscalls = vec!["open".to_string(), "ioctl".to_string(), "fork".to_string()];
+        api.transitive_calls = vec!["execve".to_string()];
+        api.ioctls = vec![0x80685600];
+        api.strings = vec![StringRef {
+            value: "/dev/video0".to_string(),
+            class: StringClass::FilePath,
+            functions: vec!["accessWebcam".to_string()],
+        }];
+
+        let capabilities = classify_api(&api, &builtin_rules());
+        assert_eq!(
+            capabilities,
+            [
+                ApiCapability {
+                    capability: "camera".to_string(),
+                    evidence: vec!["/dev/video0".to_string(), "ioctl 0x80685600".to_string()],
+                },
+                ApiCapability {
+                    capability: "process-spawn".to_string(),
+                    evidence: vec!["execve".to_string(), "fork".to_string()],
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,314 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use std::fmt::Write;
+
+use goblin::elf::Elf;
+
+use crate::{
+    code_section_handler::direct_target, disassembler::Disassembler, elf_utils::code_bytes, error,
+};
+use error::Result;
+
+/// How a basic block ends.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Terminator {
+    /// The block falls through into the next one, which is the target of a branch.
+    Fallthrough,
+    /// The block ends with an unconditional jump.
+    Jump,
+    /// The block ends with a conditional branch: the target and the next block are successors.
+    Branch,
+    /// The block ends with an indirect jump, e.g. through a switch table, whose targets are unknown.
+    IndirectJump,
+    /// The block leaves the function: return, tail call or trap.
+    Exit,
+}
+
+/// A maximal sequence of instructions executed one after the other.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BasicBlock {
+    /// The address of the first instruction of the block.
+    pub start_addr: u64,
+    /// The address following the last instruction of the block.
+    pub end_addr: u64,
+    /// The number of instructions of the block.
+    pub instructions: usize,
+    /// The instruction ending the block.
+    pub terminator: Terminator,
+    /// The starting addresses of the blocks control can flow to.
+    pub successors: Vec<u64>,
+}
+
+/// The size and complexity of a function, from its control flow graph.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Complexity {
+    /// The cyclomatic complexity of McCabe, `edges - blocks + 2`: the number of independent
+    /// paths through the function. The targets of the indirect jumps are not counted.
+    pub cyclomatic: usize,
+    /// The number of instructions of the function.
+    pub instructions: usize,
+    /// The number of basic blocks of the function.
+    pub basic_blocks: usize,
+}
+
+impl Complexity {
+    /// Convert the metrics to JSON.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "cyclomatic complexity": self.cyclomatic,
+            "instructions": self.instructions,
+            "basic blocks": self.basic_blocks,
+        })
+    }
+}
+
+/// The intra-procedural control flow graph of a function.
+pub struct ControlFlowGraph {
+    /// The name of the function.
+    pub name: String,
+    /// The starting address of the function, i.e. of its entry block.
+    pub start_addr: u64,
+    blocks: BTreeMap<u64, BasicBlock>,
+}
+
+impl ControlFlowGraph {
+    /// Split the code of a function into basic blocks.
+    ///
+    /// Leaders are the entry of the function, the targets of the branches that stay in the
+    /// function and the instructions following a branch. Jumps leaving the function are tail
+    /// calls and end the block like a return.
+    ///
+    /// # Arguments
+    ///
+    /// * `elf` - The ELF object representing the binary.
+    /// * `buffer` - The buffer containing the binary data of the ELF file.
+    /// * `name` - The name of the function.
+    /// * `start_addr` - The starting address of the function.
+    /// * `end_addr` - The ending address of the function.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the control flow graph, or `None` if the code of the
+    /// function is not in the file.
+    pub fn build(
+        elf: &Elf,
+        buffer: &[u8],
+        name: &str,
+        start_addr: u64,
+        end_addr: u64,
+    ) -> Result<Option<Self>> {
+        let Some(code) = code_bytes(elf, buffer, start_addr, end_addr) else {
+            return Ok(None);
+        };
+        let Ok(instructions) = Disassembler::new()?.disassemble(code, start_addr) else {
+            return Ok(None);
+        };
+
+        // Each instruction as (address, next address, kind, in-function branch target).
+        let mut insns = Vec::new();
+        let mut leaders = BTreeSet::from([start_addr]);
+        for insn in instructions.iter() {
+            let (addr, next) = (insn.address, insn.next_addr());
+            let mnemonic = insn.mnemonic.split_whitespace().last().unwrap_or_default();
+            let op_str = insn.op_str.as_str();
+            let target = direct_target(op_str).filter(|t| (start_addr..end_addr).contains(t));
+
+            let kind = if mnemonic.starts_with("jmp") {
+                match (target, op_str.starts_with('*')) {
+                    (Some(_), _) => Terminator::Jump,
+                    (None, true) => Terminator::IndirectJump,
+                    (None, false) => Terminator::Exit,
+                }
+            } else if mnemonic.starts_with('j') || mnemonic.starts_with("loop") {
+                Terminator::Branch
+            } else if mnemonic.starts_with("ret") || mnemonic == "hlt" || mnemonic == "ud2" {
+                Terminator::Exit
+            } else {
+                Terminator::Fallthrough
+            };
+            if kind != Terminator::Fallthrough {
+                leaders.insert(next);
+                leaders.extend(target);
+            }
+            insns.push((addr, next, kind, target));
+        }
+
+        let mut blocks = BTreeMap::new();
+        let mut current: Option<BasicBlock> = None;
+        for (addr, next, kind, target) in insns {
+            if leaders.contains(&addr) {
+                if let Some(mut block) = current.take() {
+                    block.successors.push(addr);
+                    blocks.insert(block.start_addr, block);
+                }
+            }
+            let block = current.get_or_insert_with(|| BasicBlock {
+                start_addr: addr,
+                end_addr: next,
+                instructions: 0,
+                terminator: Terminator::Fallthrough,
+                successors: Vec::new(),
+            });
+            block.end_addr = next;
+            block.instructions += 1;
+            if kind == Terminator::Fallthrough {
+                continue;
+            }
+            block.terminator = kind;
+            block.successors.extend(target);
+            if kind == Terminator::Branch && next < end_addr {
+                block.successors.push(next);
+            }
+            let block = current.take().unwrap();
+            blocks.insert(block.start_addr, block);
+        }
+        if let Some(block) = current {
+            blocks.insert(block.start_addr, block);
+        }
+
+        // Successors pointing in the middle of an instruction are not blocks.
+        let starts: BTreeSet<u64> = blocks.keys().copied().collect();
+        for block in blocks.values_mut() {
+            block.successors.retain(|succ| starts.contains(succ));
+            block.successors.dedup();
+        }
+
+        Ok(Some(Self {
+            name: name.to_string(),
+            start_addr,
+            blocks,
+        }))
+    }
+
+    /// Return the basic blocks of the function, sorted by address.
+    pub fn blocks(&self) -> impl Iterator<Item = &BasicBlock> {
+        self.blocks.values()
+    }
+
+    /// Return the basic block starting at the given address.
+    pub fn block(&self, addr: u64) -> Option<&BasicBlock> {
+        self.blocks.get(&addr)
+    }
+
+    /// Return the edges of the graph, as (source block, target block) pairs.
+    pub fn edges(&self) -> Vec<(u64, u64)> {
+        self.blocks
+            .values()
+            .flat_map(|block| {
+                block
+                    .successors
+                    .iter()
+                    .map(|&succ| (block.start_addr, succ))
+            })
+            .collect()
+    }
+
+    /// Measure the size and the cyclomatic complexity of the function.
+    pub fn complexity(&self) -> Complexity {
+        let (edges, blocks) = (self.edges().len(), self.blocks.len());
+        Complexity {
+            cyclomatic: (edges + 2).saturating_sub(blocks).max(1),
+            instructions: self.blocks.values().map(|block| block.instructions).sum(),
+            basic_blocks: blocks,
+        }
+    }
+
+    /// Render the graph in the DOT language of Graphviz.
+    pub fn to_dot(&self) -> String {
+        let mut dot = format!(
+            "digraph \"{}\" {{\n    node [shape=box];\n",
+            self.name.replace('\\', "\\\\").replace('"', "\\\"")
+        );
+        for block in self.blocks.values() {
+            let _ = writeln!(
+                dot,
+                "    \"{:#x}\" [label=\"{:#x}-{:#x} ({} insns)\"];",
+                block.start_addr, block.start_addr, block.end_addr, block.instructions
+            );
+        }
+        for (source, target) in self.edges() {
+            let _ = writeln!(dot, "    \"{:#x}\" -> \"{:#x}\";", source, target);
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Describe the graph as a block/edge JSON document.
+    pub fn to_json(&self) -> serde_json::Value {
+        let blocks: Vec<serde_json::Value> = self
+            .blocks
+            .values()
+            .map(|block| {
+                serde_json::json!({
+                    "start": format!("{:#x}", block.start_addr),
+                    "end": format!("{:#x}", block.end_addr),
+                    "instructions": block.instructions,
+                    "terminator": format!("{:?}", block.terminator),
+                    "successors": block
+                        .successors
+                        .iter()
+                        .map(|succ| format!("{:#x}", succ))
+                        .collect::<Vec<_>>(),
+                })
+            })
+            .collect();
+        serde_json::json!({
+            "name": self.name,
+            "entry": format!("{:#x}", self.start_addr),
+            "blocks": blocks,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf_utils::read_elf_file;
+
+    #[test]
+    fn test_cfg_dynamic() {
+        let buffer = read_elf_file("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        let sym = elf
+            .syms
+            .iter()
+            .find(|sym| elf.strtab.get_at(sym.st_name) == Some("accessWebcam"))
+            .unwrap();
+        let cfg = ControlFlowGraph::build(
+            &elf,
+            &buffer,
+            "accessWebcam",
+            sym.st_value,
+            sym.st_value + sym.st_size,
+        )
+        .unwrap()
+        .unwrap();
+
+        let blocks: Vec<&BasicBlock> = cfg.blocks().collect();
+        assert_eq!(blocks[0].start_addr, sym.st_value);
+        assert_eq!(blocks.last().unwrap().end_addr, sym.st_value + sym.st_size);
+        // Blocks cover the function without overlapping.
+        for pair in blocks.windows(2) {
+            assert_eq!(pair[0].end_addr, pair[1].start_addr);
+        }
+        assert!(blocks.len() > 1);
+        assert!(blocks
+            .iter()
+            .any(|block| block.terminator == Terminator::Branch && block.successors.len() == 2));
+        assert!(cfg
+            .edges()
+            .iter()
+            .all(|(_, target)| cfg.block(*target).is_some()));
+        assert!(cfg.to_dot().starts_with("digraph \"accessWebcam\""));
+
+        let complexity = cfg.complexity();
+        assert_eq!(complexity.basic_blocks, blocks.len());
+        assert_eq!(complexity.cyclomatic, cfg.edges().len() + 2 - blocks.len());
+        assert!(complexity.cyclomatic > 1);
+        assert_eq!(
+            complexity.instructions,
+            blocks.iter().map(|block| block.instructions).sum::<usize>()
+        );
+        assert_eq!(complexity.to_json()["basic blocks"], blocks.len());
+    }
+}
@@ -1,10 +1,63 @@
 use cpp_demangle::{DemangleOptions, Symbol};
 use rustc_demangle::demangle;
+use tracing::{debug, instrument, trace};
 
 use crate::{elf_utils, error};
 use elf_utils::API;
 use error::Result;
 
+// Standard library typedefs whose fully expanded template form is replaced by the short name.
+const STD_TYPEDEFS: [(&str, &str); 12] = [
+    (
+        "std::__cxx11::basic_string<char, std::char_traits<char>, std::allocator<char> >",
+        "std::string",
+    ),
+    (
+        "std::basic_string<char, std::char_traits<char>, std::allocator<char> >",
+        "std::string",
+    ),
+    (
+        "std::__cxx11::basic_stringstream<char, std::char_traits<char>, std::allocator<char> >",
+        "std::stringstream",
+    ),
+    (
+        "std::__cxx11::basic_ostringstream<char, std::char_traits<char>, std::allocator<char> >",
+        "std::ostringstream",
+    ),
+    (
+        "std::__cxx11::basic_istringstream<char, std::char_traits<char>, std::allocator<char> >",
+        "std::istringstream",
+    ),
+    (
+        "std::basic_ostream<char, std::char_traits<char> >",
+        "std::ostream",
+    ),
+    (
+        "std::basic_istream<char, std::char_traits<char> >",
+        "std::istream",
+    ),
+    (
+        "std::basic_iostream<char, std::char_traits<char> >",
+        "std::iostream",
+    ),
+    (
+        "std::basic_ofstream<char, std::char_traits<char> >",
+        "std::ofstream",
+    ),
+    (
+        "std::basic_ifstream<char, std::char_traits<char> >",
+        "std::ifstream",
+    ),
+    (
+        "std::basic_fstream<char, std::char_traits<char> >",
+        "std::fstream",
+    ),
+    (
+        "std::basic_streambuf<char, std::char_traits<char> >",
+        "std::streambuf",
+    ),
+];
+
 /// Encapsulate the call flow within the appropriate structure.
 ///
 /// This function encapsulates the call flow within the API structure, cleaning up and adding the system calls.
@@ -18,34 +71,310 @@ use error::Result;
 /// # Returns
 ///
 /// Returns a `Result` indicating success or failure.
+#[instrument(skip_all, fields(api = %api.name))]
 pub fn syscall_flow(api: &mut API, sys: Vec<String>, lang: &str) -> Result<()> {
+    for name in clean_names(sys, lang)? {
+        api.add_syscall(name);
+    }
+    Ok(())
+}
+
+/// Encapsulate the transitive call flow within the appropriate structure.
+///
+/// Like [`syscall_flow`], but for the functions reached through the call graph.
+///
+/// # Arguments
+///
+/// * `api` - A mutable reference to the API structure.
+/// * `calls` - A vector containing the names of the functions reached by the API.
+/// * `lang` - A string indicating the programming language used (e.g., "Rust", "C++").
+///
+/// # Returns
+///
+/// Returns a `Result` indicating success or failure.
+#[instrument(skip_all, fields(api = %api.name))]
+pub fn transitive_flow(api: &mut API, calls: Vec<String>, lang: &str) -> Result<()> {
+    api.transitive_calls = clean_names(calls, lang)?;
+    Ok(())
+}
+
+// Demangle and filter the called function names according to the language.
+fn clean_names(sys: Vec<String>, lang: &str) -> Result<Vec<String>> {
+    let mut names = Vec::new();
     for s in sys {
-        if lang.contains("Rust") {
-            if let Some(name) = clean_rust(&demangle_function_name(&s, true)?) {
-                api.add_syscall(name);
+        let rust = lang.contains("Rust");
+        let demangled_name = demangle_function_name(&s, rust)?;
+        let cleaned = if rust {
+            clean_rust(&demangled_name)
+        } else {
+            clean_cpp(&demangled_name)
+        };
+        match cleaned {
+            Some(name) => {
+                trace!(call = %s, name = %name, "call kept");
+                names.push(name);
             }
-        } else if let Some(name) = clean_cpp(&demangle_function_name(&s, false)?) {
-            api.add_syscall(name);
+            None => debug!(call = %s, demangled = %demangled_name, "call filtered out"),
         }
     }
-    Ok(())
+    Ok(names)
 }
 
+// Prefixes used by the Swift 4.0, Swift 4.2 and Swift 5 manglings.
+const SWIFT_PREFIXES: [&str; 5] = ["_$s", "$s", "_$S", "$S", "_T0"];
+
 // This function attempts to demangle the mangled function names.
+//
+// The language detected from DWARF selects the default scheme, but each symbol is also checked
+// on its own, since C++ binaries can link Rust code and vice versa.
 fn demangle_function_name(mangled_name: &str, rust: bool) -> Result<String> {
+    if let Some(demangled_name) = demangle_swift(mangled_name) {
+        return Ok(demangled_name);
+    }
+    if is_rust_v0(mangled_name) {
+        return Ok(demangle_rust(mangled_name));
+    }
     if mangled_name.starts_with("_Z") {
-        if rust {
-            let demangled_name = demangle(mangled_name).to_string();
-            return Ok(demangled_name);
+        if rust || is_rust_legacy(mangled_name) {
+            return Ok(demangle_rust(mangled_name));
         }
+        // Some valid manglings are not supported by the demangler, e.g. the `enable_if`
+        // expressions of template arguments: they are kept as they are.
         let options = DemangleOptions::default();
-        let demangled_name = Symbol::new(mangled_name)?.demangle(&options)?;
-        Ok(demangled_name)
+        match Symbol::new(mangled_name).map(|sym| sym.demangle(&options)) {
+            Ok(Ok(demangled_name)) => Ok(simplify_templates(&demangled_name)),
+            _ => Ok(mangled_name.to_string()),
+        }
     } else {
         Ok(mangled_name.to_string())
     }
 }
 
+/// Demangle a Swift symbol into the dotted path of the declaration it refers to.
+///
+/// Only the declaration context is recovered (module, nominal types and member name), which is
+/// what API matching needs: `$s4main6CameraC4openyyF` becomes `main.Camera.open`. Initializers,
+/// deinitializers and accessors are named `init`, `deinit`, `getter`, `setter` and `modify`.
+///
+/// # Returns
+///
+/// Returns `None` if the name does not use the Swift mangling or cannot be decoded.
+pub fn demangle_swift(mangled_name: &str) -> Option<String> {
+    let body = SWIFT_PREFIXES
+        .iter()
+        .find_map(|prefix| mangled_name.strip_prefix(prefix))?;
+    let mut parser = SwiftParser::new(body.as_bytes());
+    let mut path = vec![parser.identifier()?];
+
+    // Each context identifier is followed by the kind of the nominal type it names.
+    let mut member = None;
+    while let Some(name) = parser.identifier() {
+        match parser.peek() {
+            Some(b'C' | b'V' | b'O' | b'P') => {
+                parser.pos += 1;
+                path.push(name);
+            }
+            _ => {
+                member = Some(name);
+                break;
+            }
+        }
+    }
+
+    let rest = &body[parser.pos..];
+    let accessor = [
+        ("fC", "init"),
+        ("fc", "init"),
+        ("fD", "deinit"),
+        ("fd", "deinit"),
+        ("vg", "getter"),
+        ("vs", "setter"),
+        ("vM", "modify"),
+    ]
+    .iter()
+    .find(|(suffix, _)| rest.ends_with(suffix))
+    .map(|(_, name)| name.to_string());
+
+    match (member, accessor) {
+        (Some(name), Some(accessor)) if accessor != "init" && accessor != "deinit" => {
+            path.push(name);
+            path.push(accessor);
+        }
+        (Some(name), _) => path.push(name),
+        (None, Some(accessor)) => path.push(accessor),
+        (None, None) => {}
+    }
+    Some(path.join("."))
+}
+
+// Minimal reader for the identifier grammar of the Swift mangling, with word substitutions.
+struct SwiftParser<'a> {
+    text: &'a [u8],
+    pos: usize,
+    words: Vec<String>,
+}
+
+impl<'a> SwiftParser<'a> {
+    fn new(text: &'a [u8]) -> Self {
+        Self {
+            text,
+            pos: 0,
+            words: Vec::new(),
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.get(self.pos).copied()
+    }
+
+    fn natural(&mut self) -> Option<usize> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.text[start..self.pos])
+            .ok()?
+            .parse()
+            .ok()
+    }
+
+    // identifier ::= NATURAL CHARS | '0' (WORD-SUBST* NATURAL CHARS)* ('0' | last WORD-SUBST)
+    fn identifier(&mut self) -> Option<String> {
+        let start = self.pos;
+        if !self.peek()?.is_ascii_digit() {
+            return None;
+        }
+        let mut word_substs = false;
+        if self.peek() == Some(b'0') {
+            self.pos += 1;
+            if self.peek() == Some(b'0') {
+                // Punycode encoded identifiers are not supported.
+                self.pos = start;
+                return None;
+            }
+            word_substs = true;
+        }
+
+        let mut identifier = String::new();
+        loop {
+            while word_substs && self.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+                let c = self.text[self.pos];
+                self.pos += 1;
+                let index = if c.is_ascii_lowercase() {
+                    (c - b'a') as usize
+                } else {
+                    word_substs = false;
+                    (c - b'A') as usize
+                };
+                identifier.push_str(self.words.get(index)?);
+            }
+            if self.peek() == Some(b'0') {
+                self.pos += 1;
+                break;
+            }
+            let len = self.natural().filter(|&len| len > 0)?;
+            let end = self.pos.checked_add(len)?;
+            let part = std::str::from_utf8(self.text.get(self.pos..end)?).ok()?;
+            self.pos += len;
+            identifier.push_str(part);
+            self.remember_words(part);
+            if !word_substs {
+                break;
+            }
+        }
+        Some(identifier)
+    }
+
+    // Record the words of an identifier, which later identifiers can reference by index.
+    fn remember_words(&mut self, part: &str) {
+        let bytes = part.as_bytes();
+        let mut word_start = None;
+        for i in 0..=bytes.len() {
+            let c = bytes.get(i).copied().unwrap_or(0);
+            if let Some(start) = word_start {
+                let prev: u8 = bytes[i - 1];
+                let word_end =
+                    c == b'_' || c == 0 || (!prev.is_ascii_uppercase() && c.is_ascii_uppercase());
+                if word_end {
+                    if i - start >= 2 && self.words.len() < 26 {
+                        self.words.push(part[start..i].to_string());
+                    }
+                    word_start = None;
+                }
+            }
+            if word_start.is_none() && !c.is_ascii_digit() && c != b'_' && c != 0 {
+                word_start = Some(i);
+            }
+        }
+    }
+}
+
+/// Demangle a Rust symbol, using either the legacy or the v0 mangling scheme.
+///
+/// The legacy `::h<hash>` suffix and the v0 crate disambiguators are dropped, so the result is
+/// the plain `crate::module::function` path.
+pub fn demangle_rust(mangled_name: &str) -> String {
+    format!("{:#}", demangle(mangled_name))
+}
+
+/// Demangle a symbol name so that it can be matched against a user-provided API name.
+///
+/// The mangling scheme is guessed from the symbol itself. For C++ symbols the parameter list is
+/// dropped, so `_ZN6Camera4openEi` becomes `Camera::open`. Names that are not mangled, or that
+/// cannot be demangled, are returned unchanged.
+pub fn demangle_api_name(symbol_name: &str) -> String {
+    if let Some(demangled_name) = demangle_swift(symbol_name) {
+        return demangled_name;
+    }
+    if is_rust_symbol(symbol_name) {
+        return demangle_rust(symbol_name);
+    }
+    if !symbol_name.starts_with("_Z") {
+        return symbol_name.to_string();
+    }
+    let options = DemangleOptions::default().no_params();
+    Symbol::new(symbol_name)
+        .ok()
+        .and_then(|sym| sym.demangle(&options).ok())
+        .map_or_else(|| symbol_name.to_string(), |name| simplify_templates(&name))
+}
+
+/// Replace the expanded template form of the common standard library typedefs with their short name.
+///
+/// For instance `std::basic_ostream<char, std::char_traits<char> >&` becomes `std::ostream&`.
+pub fn simplify_templates(demangled_name: &str) -> String {
+    let mut name = demangled_name.to_string();
+    for (expanded, short) in &STD_TYPEDEFS {
+        name = name
+            .replace(&format!("{} >", expanded), &format!("{}>", short))
+            .replace(expanded, short);
+    }
+    name
+}
+
+/// Whether a symbol name is mangled by rustc, with the legacy or the v0 scheme.
+pub fn is_rust_symbol(mangled_name: &str) -> bool {
+    is_rust_v0(mangled_name) || is_rust_legacy(mangled_name)
+}
+
+// Rust v0 symbols start with `_R`, optionally preceded by the platform underscore.
+fn is_rust_v0(mangled_name: &str) -> bool {
+    let body = mangled_name
+        .strip_prefix("__R")
+        .or_else(|| mangled_name.strip_prefix("_R"));
+    body.and_then(|body| body.chars().next())
+        .is_some_and(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+}
+
+// Rust legacy symbols reuse the Itanium prefix, but always end with a 16 hex digits hash.
+fn is_rust_legacy(mangled_name: &str) -> bool {
+    mangled_name
+        .strip_suffix('E')
+        .and_then(|name| name.get(name.len().saturating_sub(19)..))
+        .and_then(|hash| hash.strip_prefix("17h"))
+        .is_some_and(|hash| hash.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
 // This function cleans up the demangled Rust function names.
 fn clean_rust(demangled_name: &str) -> Option<String> {
     let excluded_keywords = ["core::result", "shake_intern", "core::iter"];
@@ -80,10 +409,7 @@ mod tests {
     fn test_demangle_function_name_rust() {
         let mangled_name = "_ZN4core9panicking16panic_in_cleanup17h55eb1d85cadde1a1E";
         let demangled_name = demangle_function_name(mangled_name, true).unwrap();
-        assert_eq!(
-            demangled_name,
-            "core::panicking::panic_in_cleanup::h55eb1d85cadde1a1"
-        );
+        assert_eq!(demangled_name, "core::panicking::panic_in_cleanup");
     }
 
     #[test]
@@ -93,6 +419,79 @@ mod tests {
         assert_eq!(demangled_name, "example_name::foo");
     }
 
+    #[test]
+    fn test_demangle_function_name_rust_in_cpp() {
+        let mangled_name = "_ZN4core9panicking16panic_in_cleanup17h55eb1d85cadde1a1E";
+        let demangled_name = demangle_function_name(mangled_name, false).unwrap();
+        assert_eq!(demangled_name, "core::panicking::panic_in_cleanup");
+    }
+
+    #[test]
+    fn test_demangle_rust_v0() {
+        let mangled_name = "_RNvNtCs1234_7mycrate6camera4open";
+        assert_eq!(
+            demangle_function_name(mangled_name, false).unwrap(),
+            "mycrate::camera::open"
+        );
+        assert_eq!(demangle_api_name(mangled_name), "mycrate::camera::open");
+        assert!(!is_rust_v0("_Rename"));
+    }
+
+    #[test]
+    fn test_demangle_api_name_cpp() {
+        assert_eq!(demangle_api_name("_ZN6Camera4openEi"), "Camera::open");
+        assert_eq!(demangle_api_name("turnLampOn"), "turnLampOn");
+        assert_eq!(demangle_api_name("_Zinvalid"), "_Zinvalid");
+        assert_eq!(
+            demangle_function_name("_Zinvalid", false).unwrap(),
+            "_Zinvalid"
+        );
+    }
+
+    #[test]
+    fn test_demangle_swift() {
+        assert_eq!(
+            demangle_swift("$s4main6CameraC4openyyF").as_deref(),
+            Some("main.Camera.open")
+        );
+        assert_eq!(
+            demangle_swift("_$s4main12CameraDeviceC06cameraC0yyF").as_deref(),
+            Some("main.CameraDevice.cameraDevice")
+        );
+        assert_eq!(
+            demangle_swift("$s4main6CameraC5frameSivg").as_deref(),
+            Some("main.Camera.frame.getter")
+        );
+        assert_eq!(
+            demangle_swift("$s4main6CameraCACycfC").as_deref(),
+            Some("main.Camera.init")
+        );
+        assert_eq!(demangle_swift("turnLampOn"), None);
+        assert_eq!(demangle_swift("$s18446744073709551615mainyyF"), None);
+        assert_eq!(
+            demangle_api_name("$s4main10turnLampOnyyF"),
+            "main.turnLampOn"
+        );
+    }
+
+    #[test]
+    fn test_simplify_templates() {
+        let demangled_name = "std::basic_ostream<char, std::char_traits<char> >& std::operator<< <std::char_traits<char> >(std::basic_ostream<char, std::char_traits<char> >&, char const*)";
+        assert_eq!(
+            simplify_templates(demangled_name),
+            "std::ostream& std::operator<< <std::char_traits<char> >(std::ostream&, char const*)"
+        );
+    }
+
+    #[test]
+    fn test_simplify_nested_templates() {
+        let demangled_name = "cpr::Url::Url(std::initializer_list<std::__cxx11::basic_string<char, std::char_traits<char>, std::allocator<char> > >)";
+        assert_eq!(
+            simplify_templates(demangled_name),
+            "cpr::Url::Url(std::initializer_list<std::string>)"
+        );
+    }
+
     #[test]
     fn test_clean_rust_excluded_keyword() {
         let demangled_name = "core::result::Result";
@@ -1,14 +1,117 @@
 use std::collections::HashMap;
 
 use goblin::elf::Elf;
+use tracing::{debug, trace, warn};
 
 use crate::{
-    elf_utils::{cs_init, find_text_section, get_name_addr, API},
+    call_graph::CallGraph,
+    cleanup::demangle_api_name,
+    disassembler::{Disassembler, Instruction},
+    elf_utils::{code_bytes, find_text_section, get_name_addr, API},
     error,
+    indirect_calls::{memory_operand, register, CALLER_SAVED},
     plt_mapping::{find_plt_section, load_rela_plt_relocations},
+    reader::slice,
 };
 use error::{Error, Result};
 
+// The functions taking a format string, with the index of the format among their arguments.
+// Their `va_list` variants are left out: they are called by the wrappers forwarding the format
+// of their own caller.
+const FORMAT_FUNCTIONS: [(&str, usize); 11] = [
+    ("printf", 0),
+    ("fprintf", 1),
+    ("dprintf", 1),
+    ("sprintf", 1),
+    ("asprintf", 1),
+    ("snprintf", 2),
+    ("syslog", 1),
+    ("__printf_chk", 1),
+    ("__fprintf_chk", 2),
+    ("__sprintf_chk", 3),
+    ("__snprintf_chk", 4),
+];
+
+// The copies without bound into their first argument, as (function, whether a constant second
+// argument, the source or the format, keeps them in bounds).
+const UNBOUNDED_COPIES: [(&str, bool); 8] = [
+    ("strcpy", true),
+    ("stpcpy", true),
+    ("strcat", true),
+    ("wcscpy", true),
+    ("wcscat", true),
+    ("sprintf", false),
+    ("vsprintf", false),
+    ("gets", false),
+];
+
+// The functions running their first argument as a shell command.
+const COMMAND_FUNCTIONS: [&str; 2] = ["system", "popen"];
+
+// The functions building a string from pieces, e.g. a command from its arguments.
+const STRING_BUILDERS: [&str; 9] = [
+    "sprintf",
+    "snprintf",
+    "vsprintf",
+    "vsnprintf",
+    "asprintf",
+    "strcat",
+    "strncat",
+    "__sprintf_chk",
+    "__snprintf_chk",
+];
+
+// The registers of the first arguments of a call (System V ABI).
+const ARGUMENT_REGISTERS: [&str; 6] = ["%rdi", "%rsi", "%rdx", "%rcx", "%r8", "%r9"];
+
+/// A classic vulnerability pattern recognized in the disassembly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum VulnerabilityKind {
+    /// A format string that is not a constant, possibly controlled by the user.
+    FormatString,
+    /// A stack buffer filled by a copy without bound.
+    StackOverflow,
+    /// A shell command built from pieces, possibly from the input.
+    CommandInjection,
+}
+
+impl VulnerabilityKind {
+    /// Return the name of the pattern, e.g. `format-string`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VulnerabilityKind::FormatString => "format-string",
+            VulnerabilityKind::StackOverflow => "stack-buffer-overflow",
+            VulnerabilityKind::CommandInjection => "command-injection",
+        }
+    }
+}
+
+/// An occurrence of a vulnerability pattern at a call site.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VulnerabilityPattern {
+    /// The pattern recognized.
+    pub kind: VulnerabilityKind,
+    /// The function called, e.g. `printf`.
+    pub function: String,
+    /// The address of the call instruction.
+    pub site: u64,
+    /// The name of the function performing the call.
+    pub caller: String,
+    /// What was found, e.g. the stack buffer copied into.
+    pub detail: String,
+    /// The names of the APIs whose flow reaches the call.
+    pub apis: Vec<String>,
+}
+
+// Where the value of a register comes from, when it is known.
+#[derive(Clone, Debug, PartialEq)]
+enum Origin {
+    // An address or a number written in the code, e.g. of a string literal.
+    Constant,
+    // The address of a buffer on the stack, at a displacement from the base register.
+    Stack(String, i64),
+}
+
 /// Extracts and disassembles code sections of APIs, handling static or dynamic linking.
 ///
 /// This function extracts and disassembles the code of the specified API section, managing static or dynamic linking.
@@ -35,20 +138,22 @@ pub fn code_section(
     let text_section = find_text_section(elf).ok_or(Error::TextSectionNotFound)?;
     let code_slice: &[u8];
     let sys_call;
+    let size = api.end_addr.saturating_sub(api.start_addr);
 
     if link {
         // Static linking
-        let text_start_index = text_section.sh_offset as usize;
-        let func_start_offset = (api.start_addr - text_section.sh_addr) as usize;
-        let func_end_offset = (api.end_addr - text_section.sh_addr) as usize;
-        code_slice =
-            &buffer[text_start_index + func_start_offset..text_start_index + func_end_offset];
-
-        println!("\n{:#x}\t<{}>", &api.start_addr, &api.name);
-        sys_call = disassemble(elf, code_slice, api.start_addr, link, None, rust)?;
+        let func_start_offset = api.start_addr.wrapping_sub(text_section.sh_addr);
+        code_slice = slice(
+            buffer,
+            text_section.sh_offset.wrapping_add(func_start_offset),
+            size,
+        )?;
+
+        debug!(api = %api.name, start = api.start_addr, "disassembling");
+        sys_call = disassemble(elf, code_slice, api, link, None, rust)?;
     } else {
         // Dynamic linking
-        code_slice = &buffer[(api.start_addr) as usize..(api.end_addr) as usize];
+        code_slice = slice(buffer, api.start_addr, size)?;
 
         let mut found_plt_sec = false;
         let plt_section =
@@ -56,8 +161,8 @@ pub fn code_section(
         let plt_entry_size = plt_section.sh_entsize as usize;
         let tbl = load_rela_plt_relocations(elf, plt_section, plt_entry_size, found_plt_sec);
 
-        println!("\n{:#x}\t<{}>", &api.start_addr, &api.name);
-        sys_call = disassemble(elf, code_slice, api.start_addr, link, tbl, rust)?;
+        debug!(api = %api.name, start = api.start_addr, "disassembling");
+        sys_call = disassemble(elf, code_slice, api, link, tbl, rust)?;
     }
 
     Ok(sys_call)
@@ -70,19 +175,19 @@ pub fn code_section(
 fn disassemble(
     elf: &Elf,
     code_slice: &[u8],
-    addr: u64,
+    api: &API,
     link: bool,
     plt_map: Option<HashMap<u64, &str>>,
     rust: bool,
 ) -> Result<Vec<String>> {
-    let cs = cs_init()?;
+    let disassembler = Disassembler::new()?;
     let mut sys_call: Vec<String> = vec![];
 
-    let instructions = cs.disasm_all(code_slice, addr).unwrap();
+    let instructions = disassembler.disassemble(code_slice, api.start_addr)?;
     for insn in instructions.iter() {
-        let insn_addr = insn.address();
-        let insn_name = cs.insn_name(insn.id()).unwrap();
-        let op_str = insn.op_str().unwrap();
+        let insn_addr = insn.address;
+        let insn_name = insn.name().to_string();
+        let op_str = insn.op_str.as_str();
 
         if rust && insn_name == "lea" {
             if let Some(name) = lea_instruction(elf, op_str, insn_addr, insn_name.clone()) {
@@ -99,8 +204,23 @@ fn disassemble(
             ) {
                 sys_call.push(name);
             }
+        } else if !rust
+            && tail_call_target(&insn_name, op_str, api.start_addr, api.end_addr)
+                .is_some_and(|target| !link || get_name_addr(elf, target).is_some())
+        {
+            // Jumps leaving the API are tail calls, unless they land on unnamed shared code.
+            if let Some(name) = call_instruction(
+                elf,
+                op_str,
+                insn_addr,
+                insn_name.clone(),
+                link,
+                plt_map.clone(),
+            ) {
+                sys_call.push(name);
+            }
         } else {
-            println!("0x{:x}:\t{}\t{}", insn_addr, insn_name, op_str);
+            trace!("0x{:x}:\t{}\t{}", insn_addr, insn_name, op_str);
         }
     }
     Ok(sys_call)
@@ -113,30 +233,36 @@ fn lea_instruction<'a>(
     insn_addr: u64,
     insn_name: String,
 ) -> Option<String> {
-    if let Some(offset_str) = op_str.strip_suffix("(%rip), %rax") {
-        if offset_str.starts_with('-') {
-            if let Some(addr_str) = offset_str.strip_prefix("-0x") {
-                if let Ok(addr) = u64::from_str_radix(addr_str, 16) {
-                    let target_addr = insn_addr.wrapping_sub(addr);
-                    let target_addr_aligned = target_addr + 7;
-                    if let Some(name) = get_name_addr(elf, target_addr_aligned) {
-                        println!("0x{:x}:\t{}\t<{}>", insn_addr, insn_name, name);
-                        return Some(name.to_string());
-                    }
-                }
-            }
-        } else if let Some(addr_str) = offset_str.strip_prefix("0x") {
-            if let Ok(addr) = u64::from_str_radix(addr_str, 16) {
-                let target_addr = insn_addr.wrapping_add(addr);
-                let target_addr_aligned = target_addr + 7;
-                if let Some(name) = get_name_addr(elf, target_addr_aligned) {
-                    println!("0x{:x}:\t{}\t<{}>", insn_addr, insn_name, name);
-                    return Some(name.to_string());
-                }
-            }
-        }
+    let target_addr = lea_target(op_str, insn_addr)?;
+    let name = get_name_addr(elf, target_addr)?;
+    trace!("0x{:x}:\t{}\t<{}>", insn_addr, insn_name, name);
+    Some(name.to_string())
+}
+
+// Compute the address loaded by a RIP-relative 'lea' into %rax.
+//
+// The displacement is relative to the next instruction, 7 bytes after the 'lea'.
+pub(crate) fn lea_target(op_str: &str, insn_addr: u64) -> Option<u64> {
+    let offset_str = op_str.strip_suffix("(%rip), %rax")?;
+    let target_addr = if let Some(addr_str) = offset_str.strip_prefix("-0x") {
+        insn_addr.wrapping_sub(u64::from_str_radix(addr_str, 16).ok()?)
+    } else {
+        insn_addr.wrapping_add(u64::from_str_radix(offset_str.strip_prefix("0x")?, 16).ok()?)
+    };
+    Some(target_addr + 7)
+}
+
+// Parse the immediate target address of a direct 'call' or 'jmp'.
+pub(crate) fn direct_target(op_str: &str) -> Option<u64> {
+    u64::from_str_radix(op_str.strip_prefix("0x")?, 16).ok()
+}
+
+// Return the target of a 'jmp' leaving the function code, i.e. of a tail call.
+pub(crate) fn tail_call_target(mnemonic: &str, op_str: &str, start: u64, end: u64) -> Option<u64> {
+    if !mnemonic.starts_with("jmp") {
+        return None;
     }
-    None
+    direct_target(op_str).filter(|target| *target < start || *target >= end)
 }
 
 // Handles the instruction 'call', identifies the function name, and adds any interface called by API.
@@ -153,32 +279,327 @@ fn call_instruction<'a>(
             if link {
                 // Statically linked
                 if let Some(name) = get_name_addr(elf, addr) {
-                    println!("0x{:x}:\t{}\t<{}>", address, name_func, name);
+                    trace!("0x{:x}:\t{}\t<{}>", address, name_func, name);
                     return Some(name.to_string());
                 }
                 let name = format!("CALL_to_<{}>", op_str);
-                println!("0x{:x}:\t{}\t<{}>", address, name_func, name);
+                trace!("0x{:x}:\t{}\t<{}>", address, name_func, name);
                 return Some(name.to_string());
             }
             // Dynamically linked
             match plt_map {
                 Some(map) => {
                     if let Some(plt_value) = map.get(&addr) {
-                        println!("0x{:x}:\t{}\t<{}>", address, name_func, plt_value);
+                        trace!("0x{:x}:\t{}\t<{}>", address, name_func, plt_value);
                         return Some(plt_value.to_string());
                     }
                     if let Some(name) = get_name_addr(elf, addr) {
-                        println!("0x{:x}:\t{}\t<{}>", address, name_func, name);
+                        trace!("0x{:x}:\t{}\t<{}>", address, name_func, name);
                         return Some(name.to_string());
                     }
                 }
                 None => {
-                    println!("PLT map is not available");
+                    warn!(address, "PLT map is not available");
                 }
             }
         } else {
-            println!("Invalid address format: {}", op_str);
+            debug!(address, op_str, "invalid call target");
         }
     }
     None
 }
+
+/// Find the classic vulnerability patterns at the call sites of the binary.
+///
+/// The arguments of each call are traced back through the instructions before it in the
+/// calling function, which reports:
+///
+/// * the printf-family calls whose format string is not a constant, but for the `va_list`
+///   variants;
+/// * the copies without bound into a stack buffer, from a source that is not a constant;
+/// * the `system` and `popen` calls of a command that is not a constant, built in the same
+///   function with `sprintf`, `strcat` or the like.
+///
+/// # Arguments
+///
+/// * `elf` - The ELF object representing the binary.
+/// * `buffer` - The buffer containing the binary data of the ELF file.
+/// * `graph` - The call graph of the binary.
+/// * `api_list` - The APIs found, to tell which of them reach each call.
+///
+/// # Returns
+///
+/// Returns a `Result` containing the patterns found, sorted by call site.
+pub fn find_vulnerability_patterns(
+    elf: &Elf,
+    buffer: &[u8],
+    graph: &CallGraph,
+    api_list: &[API],
+) -> Result<Vec<VulnerabilityPattern>> {
+    let disassembler = Disassembler::new()?;
+    let flows: Vec<_> = api_list
+        .iter()
+        .map(|api| (api.name.as_str(), graph.reachable(&[api.start_addr])))
+        .collect();
+
+    let mut patterns = Vec::new();
+    for func in graph.functions() {
+        let sites: HashMap<u64, &str> = graph
+            .callees(func.start_addr)
+            .iter()
+            .map(|edge| (edge.site, edge.name.split('@').next().unwrap_or(&edge.name)))
+            .collect();
+        if sites.is_empty() || sites.values().all(|name| !is_sink(name)) {
+            continue;
+        }
+        let Some(code) = code_bytes(elf, buffer, func.start_addr, func.end_addr) else {
+            continue;
+        };
+        let Ok(instructions) = disassembler.disassemble(code, func.start_addr) else {
+            continue;
+        };
+        for (kind, site, function, detail) in scan_calls(&instructions, &sites) {
+            patterns.push(VulnerabilityPattern {
+                kind,
+                function: function.to_string(),
+                site,
+                caller: demangle_api_name(&func.name),
+                detail,
+                apis: flows
+                    .iter()
+                    .filter(|(_, reachable)| reachable.contains(&func.start_addr))
+                    .map(|(name, _)| name.to_string())
+                    .collect(),
+            });
+        }
+    }
+    patterns.sort_by_key(|pattern| (pattern.site, pattern.kind));
+    Ok(patterns)
+}
+
+// Whether a function is one of those whose calls are checked.
+fn is_sink(name: &str) -> bool {
+    FORMAT_FUNCTIONS
+        .iter()
+        .any(|(function, _)| *function == name)
+        || UNBOUNDED_COPIES
+            .iter()
+            .any(|(function, _)| *function == name)
+        || COMMAND_FUNCTIONS.contains(&name)
+}
+
+// Check the calls of a function, given the names of the functions called at each site, and
+// return the patterns found as (kind, site, function called, detail).
+fn scan_calls<'a>(
+    instructions: &[Instruction],
+    sites: &HashMap<u64, &'a str>,
+) -> Vec<(VulnerabilityKind, u64, &'a str, String)> {
+    let mut found = Vec::new();
+    let mut origins: HashMap<String, Origin> = HashMap::new();
+    let mut builders: Vec<&str> = Vec::new();
+    for insn in instructions {
+        if let Some(&name) = sites.get(&insn.address) {
+            let argument = |index: usize| origins.get(ARGUMENT_REGISTERS[index]);
+            if let Some((_, format)) = FORMAT_FUNCTIONS.iter().find(|(f, _)| *f == name) {
+                match argument(*format) {
+                    Some(Origin::Constant) => {}
+                    Some(Origin::Stack(base, disp)) => found.push((
+                        VulnerabilityKind::FormatString,
+                        insn.address,
+                        name,
+                        format!(
+                            "the format string is the stack buffer at {}",
+                            offset(base, *disp)
+                        ),
+                    )),
+                    None => found.push((
+                        VulnerabilityKind::FormatString,
+                        insn.address,
+                        name,
+                        "the format string is not a constant".to_string(),
+                    )),
+                }
+            }
+            if let Some((_, bounded)) = UNBOUNDED_COPIES.iter().find(|(f, _)| *f == name) {
+                if let Some(Origin::Stack(base, disp)) = argument(0) {
+                    if !(*bounded && argument(1) == Some(&Origin::Constant)) {
+                        found.push((
+                            VulnerabilityKind::StackOverflow,
+                            insn.address,
+                            name,
+                            format!(
+                                "copies without bound into the stack buffer at {}",
+                                offset(base, *disp)
+                            ),
+                        ));
+                    }
+                }
+            }
+            if COMMAND_FUNCTIONS.contains(&name)
+                && argument(0) != Some(&Origin::Constant)
+                && !builders.is_empty()
+            {
+                found.push((
+                    VulnerabilityKind::CommandInjection,
+                    insn.address,
+                    name,
+                    format!("runs a command built with {}", builders.join(", ")),
+                ));
+            }
+            if STRING_BUILDERS.contains(&name) && !builders.contains(&name) {
+                builders.push(name);
+            }
+        }
+        track_origin(&mut origins, insn);
+    }
+    found
+}
+
+// Update the origins of the registers with the effect of an instruction.
+fn track_origin(origins: &mut HashMap<String, Origin>, insn: &Instruction) {
+    let mnemonic = insn.name();
+    if mnemonic == "call" {
+        for reg in CALLER_SAVED {
+            origins.remove(reg);
+        }
+        return;
+    }
+    let Some((src, dst)) = insn.op_str.rsplit_once(", ") else {
+        // A single register operand is overwritten, e.g. by `pop`.
+        if let Some(reg) = register(&insn.op_str) {
+            if !mnemonic.starts_with("push") {
+                origins.remove(&reg);
+            }
+        }
+        return;
+    };
+    let Some(dst) = register(dst) else {
+        return;
+    };
+    let origin = if mnemonic == "lea" {
+        memory_operand(src).and_then(|(disp, base)| match base.as_str() {
+            "%rip" => Some(Origin::Constant),
+            "%rbp" | "%rsp" => Some(Origin::Stack(base, disp)),
+            _ => match origins.get(&base) {
+                Some(Origin::Stack(base, start)) => Some(Origin::Stack(base.clone(), start + disp)),
+                _ => None,
+            },
+        })
+    } else if mnemonic.starts_with("mov") && src.starts_with('$') {
+        Some(Origin::Constant)
+    } else if mnemonic.starts_with("mov") {
+        register(src).and_then(|reg| origins.get(&reg).cloned())
+    } else if let (true, Some(Origin::Stack(base, disp))) =
+        (mnemonic.starts_with("add"), origins.get(&dst))
+    {
+        // A field of a stack buffer, e.g. `add $0x11, %rdi`.
+        immediate(src).map(|imm| Origin::Stack(base.clone(), disp + imm))
+    } else {
+        None
+    };
+    match origin {
+        Some(origin) => origins.insert(dst, origin),
+        None => origins.remove(&dst),
+    };
+}
+
+// Parse a `$imm` operand.
+fn immediate(operand: &str) -> Option<i64> {
+    let imm = operand.strip_prefix('$')?;
+    match imm.strip_prefix("0x") {
+        Some(hex) => i64::from_str_radix(hex, 16).ok(),
+        None => imm.parse().ok(),
+    }
+}
+
+// Format a stack offset as a memory operand, e.g. `-0x40(%rbp)`.
+fn offset(base: &str, disp: i64) -> String {
+    match disp < 0 {
+        true => format!("-{:#x}({})", -disp, base),
+        false => format!("{:#x}({})", disp, base),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tail_call_target() {
+        assert_eq!(
+            tail_call_target("jmp", "0x1150", 0x1170, 0x1180),
+            Some(0x1150)
+        );
+        assert_eq!(tail_call_target("jmp", "0x1175", 0x1170, 0x1180), None);
+        assert_eq!(tail_call_target("jmp", "*%rax", 0x1170, 0x1180), None);
+        assert_eq!(tail_call_target("je", "0x1150", 0x1170, 0x1180), None);
+    }
+
+    #[test]
+    fn test_scan_calls() {
+        let code = [
+            ("lea", "-0x40(%rbp), %rax"),
+            ("mov", "%rax, %rdi"),
+            ("mov", "-0x58(%rbp), %rsi"),
+            ("call", "0x1030"), // strcpy(buffer, input)
+            ("lea", "-0x50(%rbp), %rdi"),
+            ("add", "$0x11, %rdi"),
+            ("lea", "0x2000(%rip), %rsi"),
+            ("call", "0x1030"), // strcpy(buffer + 0x11, "literal")
+            ("mov", "-0x58(%rbp), %rdi"),
+            ("call", "0x1040"), // printf(input)
+            ("lea", "0x2010(%rip), %rdi"),
+            ("call", "0x1040"), // printf("%s\n", ...)
+            ("lea", "-0x40(%rbp), %rdi"),
+            ("lea", "0x2020(%rip), %rsi"),
+            ("call", "0x1050"), // sprintf(command, "ping %s", ...)
+            ("lea", "-0x40(%rbp), %rdi"),
+            ("call", "0x1060"), // system(command)
+            ("lea", "0x2030(%rip), %rdi"),
+            ("call", "0x1060"), // system("reboot")
+        ];
+        let instructions: Vec<Instruction> = code
+            .iter()
+            .enumerate()
+            .map(|(i, (mnemonic, op_str))| Instruction {
+                address: 0x1100 + 4 * i as u64,
+                size: 4,
+                mnemonic: mnemonic.to_string(),
+                op_str: op_str.to_string(),
+            })
+            .collect();
+        let names = [
+            (3, "strcpy"),
+            (7, "strcpy"),
+            (9, "printf"),
+            (11, "printf"),
+            (14, "sprintf"),
+            (16, "system"),
+            (18, "system"),
+        ];
+        let sites: HashMap<u64, &str> = names
+            .iter()
+            .map(|(i, name)| (0x1100 + 4 * i, *name))
+            .collect();
+
+        let found: Vec<(VulnerabilityKind, u64, &str)> = scan_calls(&instructions, &sites)
+            .into_iter()
+            .map(|(kind, site, name, _)| (kind, site, name))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (VulnerabilityKind::StackOverflow, 0x110c, "strcpy"),
+                (VulnerabilityKind::FormatString, 0x1124, "printf"),
+                (VulnerabilityKind::StackOverflow, 0x1138, "sprintf"),
+                (VulnerabilityKind::CommandInjection, 0x1140, "system"),
+            ]
+        );
+        let details = scan_calls(&instructions, &sites);
+        assert_eq!(
+            details[0].3,
+            "copies without bound into the stack buffer at -0x40(%rbp)"
+        );
+        assert_eq!(details[3].3, "runs a command built with sprintf");
+    }
+}
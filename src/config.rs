@@ -0,0 +1,371 @@
+use std::{fs, path::Path};
+
+use serde_json::Value;
+
+use crate::{
+    analyzer::{AnalysisMode, Scope},
+    api_detection::{parse_api_list, ApiSpec},
+    budget::{parse_duration, parse_size, Budget},
+    dynamic_analysis::DynamicOptions,
+    error::{Error, Result},
+    manifest_creation::Format,
+    unpacking::DEFAULT_UPX,
+};
+
+/// The name of the configuration file looked for in the current directory.
+pub const CONFIG_FILE: &str = "manifest-producer.toml";
+
+// Optional outputs that can be enabled in the configuration file.
+const OUTPUTS: [&str; 11] = [
+    "call-graph",
+    "xrefs",
+    "cfg",
+    "cyclonedx",
+    "spdx",
+    "sarif",
+    "html",
+    "markdown",
+    "seccomp",
+    "apparmor",
+    "disassembly",
+];
+
+/// The project defaults read from a configuration file. The command line overrides them.
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    /// The path of the JSON API list.
+    pub api_list: Option<String>,
+    /// The API list written in the configuration file, used when no `api_list` file is given.
+    pub apis: Option<Vec<ApiSpec>>,
+    /// The directory where the manifests are written.
+    pub output_dir: Option<String>,
+    /// The format of the basic info, flow call and feature manifests.
+    pub format: Option<Format>,
+    /// The optional outputs to write, e.g. `sarif`.
+    pub outputs: Vec<String>,
+    /// The path of the capability rules file.
+    pub capability_rules: Option<String>,
+    /// The expected architecture of the binaries.
+    pub arch: Option<String>,
+    /// The glob patterns of the paths left out of batch analyses.
+    pub exclude: Vec<String>,
+    /// The maximum depth of the transitive flows.
+    pub max_depth: Option<usize>,
+    /// The path of the JSON list of banned functions.
+    pub banned: Option<String>,
+    /// The path of the OSV snapshot.
+    pub vuln_db: Option<String>,
+    /// The directory of the analysis cache.
+    pub cache_dir: Option<String>,
+    /// The resources the disassembly may use.
+    pub budget: Budget,
+    /// The kind of analysis to perform.
+    pub analysis: Option<AnalysisMode>,
+    /// The functions analyzed as the APIs of the binaries.
+    pub scope: Option<Scope>,
+    /// How the binaries are run for their dynamic analysis.
+    pub dynamic: DynamicOptions,
+    /// The program unpacking the binaries packed with UPX.
+    pub unpacker: Option<String>,
+    /// The libraries left out of the transitive flows.
+    pub exclude_libraries: Vec<String>,
+    /// The paths of the FLIRT pattern files recognizing the functions of stripped binaries.
+    pub signatures: Vec<String>,
+    /// The root directory of the firmware tree holding the libraries loaded with `dlopen`.
+    pub library_root: Option<String>,
+    /// Whether the errors of the optional stages are recorded rather than failing the analysis.
+    pub best_effort: bool,
+    /// Whether the time of the analysis is left out of the manifests.
+    pub no_timestamp: bool,
+    /// The stack depth in bytes beyond which the flows are flagged.
+    pub stack_threshold: Option<u64>,
+    /// How many calls away from the APIs their callees are listed in the annotated disassembly.
+    pub disassembly_depth: Option<usize>,
+    /// The path of the SQLite database the results are also written to.
+    pub store: Option<String>,
+}
+
+impl Config {
+    /// Load a configuration file. Relative paths in the file are taken from its directory.
+    ///
+    /// ```toml
+    /// api_list = "apis.json"
+    /// output_dir = "manifests"
+    /// format = "yaml"
+    /// outputs = ["sarif", "spdx"]
+    /// capability_rules = "rules.toml"
+    /// arch = "x86-64"
+    /// exclude = ["**/busybox", "**/*.ko"]
+    /// function_timeout = "2s"
+    /// memory_limit = "4G"
+    /// analysis = "both"
+    /// scope = "api-list"
+    /// duration = "30s"
+    /// run_args = ["--once"]
+    /// allow_network = false
+    /// sysroot = "/usr/arm-linux-gnueabihf"
+    /// unpack = true
+    /// exclude_libraries = ["libc", "openssl"]
+    /// signatures = ["signatures/musl.pat"]
+    /// library_root = "rootfs"
+    /// best_effort = true
+    /// no_timestamp = true
+    /// stack_threshold = 2048
+    /// disassembly_depth = 1
+    /// store = "fleet.db"
+    /// ```
+    ///
+    /// `unpack` is either a boolean or the path of the UPX program.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidConfig`] if the file is not valid TOML, has an unknown key or a
+    /// value of the wrong type.
+    pub fn load(path: &str) -> Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let json: Value = toml::from_str(&contents)
+            .map_err(|error| Error::InvalidConfig(format!("{}: {}", path, error)))?;
+        let base = Path::new(path).parent().unwrap_or(Path::new(""));
+        Self::from_json(&json, base)
+    }
+
+    /// Load [`CONFIG_FILE`] from the current directory, if there is one.
+    pub fn discover() -> Result<Option<Self>> {
+        if !Path::new(CONFIG_FILE).is_file() {
+            return Ok(None);
+        }
+        Self::load(CONFIG_FILE).map(Some)
+    }
+
+    /// Read a configuration from a JSON document, see [`Config::load`], resolving the relative
+    /// paths from `base`.
+    pub fn from_json(json: &Value, base: &Path) -> Result<Self> {
+        let invalid = |key: &str, expected: &str| {
+            Error::InvalidConfig(format!("{} must be {}", key, expected))
+        };
+        let table = json
+            .as_object()
+            .ok_or_else(|| Error::InvalidConfig("expected a table".to_string()))?;
+        let path = |key: &str| -> Result<Option<String>> {
+            match table.get(key) {
+                None => Ok(None),
+                Some(Value::String(path)) => {
+                    Ok(Some(base.join(path).to_string_lossy().to_string()))
+                }
+                Some(_) => Err(invalid(key, "a path")),
+            }
+        };
+        let strings = |key: &str| -> Result<Vec<String>> {
+            match table.get(key) {
+                None => Ok(Vec::new()),
+                Some(Value::Array(values)) => values
+                    .iter()
+                    .map(|value| value.as_str().map(str::to_string))
+                    .collect::<Option<_>>()
+                    .ok_or_else(|| invalid(key, "an array of strings")),
+                Some(_) => Err(invalid(key, "an array of strings")),
+            }
+        };
+
+        let paths = |key: &str| -> Result<Vec<String>> {
+            Ok(strings(key)?
+                .iter()
+                .map(|path| base.join(path).to_string_lossy().to_string())
+                .collect())
+        };
+
+        let mut config = Config {
+            api_list: path("api_list")?,
+            output_dir: path("output_dir")?,
+            capability_rules: path("capability_rules")?,
+            banned: path("banned")?,
+            vuln_db: path("vuln_db")?,
+            cache_dir: path("cache_dir")?,
+            library_root: path("library_root")?,
+            store: path("store")?,
+            outputs: strings("outputs")?,
+            exclude: strings("exclude")?,
+            exclude_libraries: strings("exclude_libraries")?,
+            signatures: paths("signatures")?,
+            dynamic: DynamicOptions {
+                args: strings("run_args")?,
+                sysroot: path("sysroot")?,
+                ..DynamicOptions::default()
+            },
+            ..Config::default()
+        };
+        for (key, value) in table {
+            match key.as_str() {
+                "api_list" | "output_dir" | "capability_rules" | "banned" | "vuln_db"
+                | "cache_dir" | "outputs" | "exclude" | "exclude_libraries" | "signatures"
+                | "run_args" | "sysroot" | "library_root" | "store" => {}
+                "apis" => config.apis = Some(parse_api_list(value)?),
+                "format" => {
+                    let format = value.as_str().ok_or_else(|| invalid(key, "a string"))?;
+                    config.format = Some(format.parse()?);
+                }
+                "arch" => {
+                    let arch = value.as_str().ok_or_else(|| invalid(key, "a string"))?;
+                    config.arch = Some(arch.to_string());
+                }
+                "function_timeout" => {
+                    let timeout = value.as_str().and_then(parse_duration);
+                    config.budget.function_timeout =
+                        Some(timeout.ok_or_else(|| invalid(key, "a duration, e.g. \"2s\""))?);
+                }
+                "memory_limit" => {
+                    let limit = value.as_str().and_then(parse_size);
+                    config.budget.memory_limit =
+                        Some(limit.ok_or_else(|| invalid(key, "a size, e.g. \"2G\""))?);
+                }
+                "analysis" => {
+                    let analysis = value.as_str().ok_or_else(|| invalid(key, "a string"))?;
+                    config.analysis = Some(analysis.parse()?);
+                }
+                "scope" => {
+                    let scope = value.as_str().ok_or_else(|| invalid(key, "a string"))?;
+                    config.scope = Some(scope.parse()?);
+                }
+                "duration" => {
+                    let duration = value.as_str().and_then(parse_duration);
+                    config.dynamic.duration =
+                        duration.ok_or_else(|| invalid(key, "a duration, e.g. \"30s\""))?;
+                }
+                "emulator" => {
+                    let emulator = value.as_str().ok_or_else(|| invalid(key, "a string"))?;
+                    config.dynamic.emulator = Some(emulator.to_string());
+                }
+                "unpack" => {
+                    config.unpacker = match value {
+                        Value::Bool(true) => Some(DEFAULT_UPX.to_string()),
+                        Value::Bool(false) => None,
+                        Value::String(program) => Some(program.clone()),
+                        _ => return Err(invalid(key, "a boolean or a program")),
+                    }
+                }
+                "allow_network" => {
+                    config.dynamic.allow_network =
+                        value.as_bool().ok_or_else(|| invalid(key, "a boolean"))?;
+                }
+                "best_effort" => {
+                    config.best_effort =
+                        value.as_bool().ok_or_else(|| invalid(key, "a boolean"))?;
+                }
+                "no_timestamp" => {
+                    config.no_timestamp =
+                        value.as_bool().ok_or_else(|| invalid(key, "a boolean"))?;
+                }
+                "max_depth" => {
+                    let depth = value.as_u64().ok_or_else(|| invalid(key, "a number"))?;
+                    config.max_depth = Some(depth as usize);
+                }
+                "stack_threshold" => {
+                    let bytes = value.as_u64().ok_or_else(|| invalid(key, "a number"))?;
+                    config.stack_threshold = Some(bytes);
+                }
+                "disassembly_depth" => {
+                    let depth = value.as_u64().ok_or_else(|| invalid(key, "a number"))?;
+                    config.disassembly_depth = Some(depth as usize);
+                }
+                _ => return Err(Error::InvalidConfig(format!("unknown key {}", key))),
+            }
+        }
+        if let Some(output) = config
+            .outputs
+            .iter()
+            .find(|output| !OUTPUTS.contains(&output.as_str()))
+        {
+            return Err(Error::InvalidConfig(format!(
+                "unknown output {}, expected one of {}",
+                output,
+                OUTPUTS.join(", ")
+            )));
+        }
+        Ok(config)
+    }
+
+    /// Whether an optional output is enabled, e.g. `sarif`.
+    pub fn output(&self, name: &str) -> bool {
+        self.outputs.iter().any(|output| output == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_json() {
+        let json: Value = toml::from_str(
+            r#"
+            apis = ["writeOnDrive", { name = "accessNetwork", capability = "Network Access" }]
+            output_dir = "manifests"
+            format = "yaml"
+            outputs = ["sarif"]
+            arch = "x86-64"
+            exclude = ["**/busybox"]
+            max_depth = 4
+            function_timeout = "500ms"
+            analysis = "dynamic"
+            scope = "all"
+            duration = "1m"
+            run_args = ["--once"]
+            sysroot = "sysroots/arm"
+            unpack = true
+            exclude_libraries = ["libc"]
+            signatures = ["musl.pat"]
+            library_root = "rootfs"
+            best_effort = true
+            no_timestamp = true
+            stack_threshold = 2048
+            disassembly_depth = 1
+            store = "fleet.db"
+            "#,
+        )
+        .unwrap();
+        let config = Config::from_json(&json, Path::new("ci")).unwrap();
+        assert_eq!(config.apis.as_ref().map(Vec::len), Some(2));
+        assert_eq!(config.output_dir.as_deref(), Some("ci/manifests"));
+        assert_eq!(config.format, Some(Format::Yaml));
+        assert!(config.output("sarif") && !config.output("spdx"));
+        assert_eq!(config.exclude, ["**/busybox"]);
+        assert_eq!(config.max_depth, Some(4));
+        assert_eq!(
+            config.budget.function_timeout,
+            Some(std::time::Duration::from_millis(500))
+        );
+        assert_eq!(config.analysis, Some(AnalysisMode::Dynamic));
+        assert_eq!(config.scope, Some(Scope::All));
+        assert_eq!(config.dynamic.duration, std::time::Duration::from_secs(60));
+        assert_eq!(config.dynamic.args, ["--once"]);
+        assert!(!config.dynamic.allow_network);
+        assert_eq!(config.dynamic.sysroot.as_deref(), Some("ci/sysroots/arm"));
+        assert_eq!(config.unpacker.as_deref(), Some(DEFAULT_UPX));
+        assert_eq!(config.exclude_libraries, ["libc"]);
+        assert_eq!(config.signatures, ["ci/musl.pat"]);
+        assert_eq!(config.library_root.as_deref(), Some("ci/rootfs"));
+        assert!(config.best_effort && config.no_timestamp);
+        assert_eq!(config.stack_threshold, Some(2048));
+        assert_eq!(config.disassembly_depth, Some(1));
+        assert_eq!(config.store.as_deref(), Some("ci/fleet.db"));
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let config = |source: &str| {
+            let json: Value = toml::from_str(source).unwrap();
+            Config::from_json(&json, Path::new(""))
+        };
+        assert!(config("api_lists = \"apis.json\"").is_err());
+        assert!(config("outputs = [\"pdf\"]").is_err());
+        assert!(config("format = \"xml\"").is_err());
+        assert!(config("max_depth = \"4\"").is_err());
+        assert!(config("memory_limit = \"lots\"").is_err());
+        assert!(config("allow_network = \"yes\"").is_err());
+        assert!(config("unpack = 1").is_err());
+        assert!(config("scope = \"functions\"").is_err());
+        assert!(config("best_effort = \"yes\"").is_err());
+        assert!(config("no_timestamp = 0").is_err());
+        assert!(config("stack_threshold = \"2k\"").is_err());
+    }
+}
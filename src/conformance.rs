@@ -0,0 +1,379 @@
+//! Conformance of the manifests of a binary to the behavior declared by its vendor.
+//!
+//! A declaration is a TOML or JSON file listing the `apis` the binary is meant to expose, each
+//! with the `capabilities` it is meant to have, as named in the `capabilities` section of the
+//! feature manifest, and optionally the `syscalls` it is meant to call directly. The top-level
+//! `capabilities` and `syscalls` are allowed for every API:
+//!
+//! ```toml
+//! capabilities = ["filesystem-read"]
+//!
+//! [[apis]]
+//! name = "writeOnDrive"
+//! capabilities = ["filesystem-write"]
+//! syscalls = ["fopen64", "fprintf", "fclose"]
+//!
+//! [[apis]]
+//! name = "turnLampOn"
+//! ```
+//!
+//! The behavior found in the manifests but not declared is reported: the APIs not declared,
+//! the capabilities of an API that neither it nor the declaration allows, and, when calls are
+//! declared for the API or the whole binary, the calls it makes that are not. The declared APIs
+//! not found are reported too.
+
+use std::{collections::BTreeMap, fmt, fs};
+
+use serde_json::{json, Value};
+
+use crate::{
+    error::{Error, Result},
+    manifest_creation::find_manifest,
+};
+
+/// The behavior declared for an API.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DeclaredApi {
+    /// The name of the API.
+    pub name: String,
+    /// The capabilities the API is meant to have, e.g. `filesystem-write`.
+    pub capabilities: Vec<String>,
+    /// The functions the API is meant to call directly, not checked when `None`.
+    pub syscalls: Option<Vec<String>>,
+}
+
+/// The behavior a vendor declares for a binary.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Declaration {
+    /// The APIs the binary is meant to expose.
+    pub apis: Vec<DeclaredApi>,
+    /// The capabilities allowed for every API.
+    pub capabilities: Vec<String>,
+    /// The functions every API may call directly, not checked when `None`.
+    pub syscalls: Option<Vec<String>>,
+}
+
+/// The deviations of the manifests of a binary from its declaration.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConformanceReport {
+    /// The APIs found but not declared.
+    pub undeclared_apis: Vec<String>,
+    /// The APIs declared but not found.
+    pub missing_apis: Vec<String>,
+    /// The capabilities of the APIs that are not declared, as (API, capability).
+    pub undeclared_capabilities: Vec<(String, String)>,
+    /// The direct calls of the APIs that are not declared, as (API, function).
+    pub undeclared_syscalls: Vec<(String, String)>,
+}
+
+impl ConformanceReport {
+    /// Whether the binary behaves as declared.
+    pub fn conforms(&self) -> bool {
+        self.undeclared_apis.is_empty()
+            && self.missing_apis.is_empty()
+            && self.undeclared_capabilities.is_empty()
+            && self.undeclared_syscalls.is_empty()
+    }
+
+    /// The report as JSON.
+    pub fn to_json(&self) -> Value {
+        let pairs = |pairs: &[(String, String)], field: &str| -> Vec<Value> {
+            pairs
+                .iter()
+                .map(|(api, value)| json!({ "api": api, field: value }))
+                .collect()
+        };
+        json!({
+            "conforms": self.conforms(),
+            "undeclared APIs": self.undeclared_apis,
+            "missing APIs": self.missing_apis,
+            "undeclared capabilities": pairs(&self.undeclared_capabilities, "capability"),
+            "undeclared syscalls": pairs(&self.undeclared_syscalls, "function"),
+        })
+    }
+}
+
+impl fmt::Display for ConformanceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut deviations = 0;
+        let mut section = |f: &mut fmt::Formatter<'_>, title: &str, items: Vec<String>| {
+            if items.is_empty() {
+                return Ok(());
+            }
+            deviations += items.len();
+            writeln!(f, "{}:", title)?;
+            for item in items {
+                writeln!(f, "  - {}", item)?;
+            }
+            Ok(())
+        };
+        let pairs = |pairs: &[(String, String)]| -> Vec<String> {
+            pairs
+                .iter()
+                .map(|(api, value)| format!("{}: {}", api, value))
+                .collect()
+        };
+        section(f, "Undeclared APIs", self.undeclared_apis.clone())?;
+        section(f, "Missing declared APIs", self.missing_apis.clone())?;
+        section(
+            f,
+            "Undeclared capabilities",
+            pairs(&self.undeclared_capabilities),
+        )?;
+        section(f, "Undeclared syscalls", pairs(&self.undeclared_syscalls))?;
+        match deviations {
+            0 => write!(f, "The binary conforms to the declaration"),
+            1 => write!(f, "1 deviation from the declaration"),
+            n => write!(f, "{} deviations from the declaration", n),
+        }
+    }
+}
+
+impl Declaration {
+    /// Load a declaration from a TOML or JSON file, told by its extension, see
+    /// [`crate::conformance`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidDeclaration`] if the file does not follow the layout of a
+    /// declaration.
+    pub fn load(path: &str) -> Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let json: Value = if path.ends_with(".toml") {
+            toml::from_str(&contents)
+                .map_err(|error| Error::InvalidDeclaration(format!("{}: {}", path, error)))?
+        } else {
+            serde_json::from_str(&contents)?
+        };
+        Self::from_json(&json)
+    }
+
+    /// Read a declaration from a JSON document, see [`Declaration::load`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidDeclaration`] if the document does not follow the layout of a
+    /// declaration.
+    pub fn from_json(json: &Value) -> Result<Self> {
+        let entries = json
+            .get("apis")
+            .and_then(Value::as_array)
+            .ok_or_else(|| Error::InvalidDeclaration("expected an array of apis".to_string()))?;
+        let mut apis: Vec<DeclaredApi> = Vec::new();
+        for entry in entries {
+            let name = entry
+                .get("name")
+                .and_then(Value::as_str)
+                .ok_or_else(|| Error::InvalidDeclaration(format!("missing name in {}", entry)))?;
+            if apis.iter().any(|api| api.name == name) {
+                return Err(Error::InvalidDeclaration(format!(
+                    "{} is declared twice",
+                    name
+                )));
+            }
+            apis.push(DeclaredApi {
+                name: name.to_string(),
+                capabilities: strings(entry, "capabilities", name)?.unwrap_or_default(),
+                syscalls: strings(entry, "syscalls", name)?,
+            });
+        }
+        Ok(Self {
+            apis,
+            capabilities: strings(json, "capabilities", "the declaration")?.unwrap_or_default(),
+            syscalls: strings(json, "syscalls", "the declaration")?,
+        })
+    }
+
+    /// Compare the manifests of a binary, by name, as returned by
+    /// [`crate::analyzer::Analyzer::manifests`], against the declaration.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidManifest`] if the flow call or feature manifest is missing.
+    pub fn check(&self, manifests: &BTreeMap<String, Value>) -> Result<ConformanceReport> {
+        let manifest = |name: &str| {
+            manifests
+                .get(name)
+                .ok_or_else(|| Error::InvalidManifest(format!("no {} manifest", name)))
+        };
+        let (flow_call, feature) = (manifest("flow_call")?, manifest("feature_manifest")?);
+        let mut report = ConformanceReport::default();
+        let mut found = Vec::new();
+        for flow in flow_call["Public APIs flow"]
+            .as_array()
+            .into_iter()
+            .flatten()
+        {
+            let Some(name) = flow["name"].as_str() else {
+                continue;
+            };
+            found.push(name);
+            let declared = self.apis.iter().find(|api| api.name == name);
+            if declared.is_none() {
+                report.undeclared_apis.push(name.to_string());
+            }
+
+            let allowed = |capability: &String| {
+                self.capabilities.contains(capability)
+                    || declared.is_some_and(|api| api.capabilities.contains(capability))
+            };
+            for capability in feature["capabilities"][name]
+                .as_object()
+                .into_iter()
+                .flat_map(|capabilities| capabilities.keys())
+                .filter(|capability| !allowed(capability))
+            {
+                report
+                    .undeclared_capabilities
+                    .push((name.to_string(), capability.clone()));
+            }
+
+            let declared_calls = declared.and_then(|api| api.syscalls.as_ref());
+            if declared_calls.is_none() && self.syscalls.is_none() {
+                continue;
+            }
+            let mut undeclared: Vec<&str> = flow["syscalls"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+                .filter(|call| {
+                    let listed = |calls: Option<&Vec<String>>| {
+                        calls.is_some_and(|calls| calls.iter().any(|declared| declared == call))
+                    };
+                    !listed(declared_calls) && !listed(self.syscalls.as_ref())
+                })
+                .collect();
+            undeclared.sort_unstable();
+            undeclared.dedup();
+            report.undeclared_syscalls.extend(
+                undeclared
+                    .into_iter()
+                    .map(|call| (name.to_string(), call.to_string())),
+            );
+        }
+        report.missing_apis = self
+            .apis
+            .iter()
+            .filter(|api| !found.contains(&api.name.as_str()))
+            .map(|api| api.name.clone())
+            .collect();
+        Ok(report)
+    }
+
+    /// Compare the manifests of an output directory, in any format, against the declaration.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidManifest`] if the directory lacks a manifest.
+    pub fn check_dir(&self, dir: &str) -> Result<ConformanceReport> {
+        let mut manifests = BTreeMap::new();
+        for name in ["flow_call", "feature_manifest"] {
+            manifests.insert(name.to_string(), find_manifest(dir, name)?);
+        }
+        self.check(&manifests)
+    }
+}
+
+// Read an optional array of strings of an entry of the declaration.
+fn strings(entry: &Value, field: &str, owner: &str) -> Result<Option<Vec<String>>> {
+    let invalid = || {
+        Error::InvalidDeclaration(format!(
+            "{} of {} must be an array of strings",
+            field, owner
+        ))
+    };
+    match entry.get(field) {
+        None => Ok(None),
+        Some(Value::Array(values)) => values
+            .iter()
+            .map(|value| value.as_str().map(str::to_string))
+            .collect::<Option<_>>()
+            .map(Some)
+            .ok_or_else(invalid),
+        Some(_) => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{analyzer::Analyzer, api_detection::ApiSpec, progress::Verbosity};
+
+    #[test]
+    fn test_check() {
+        let analyzer = Analyzer::new("./tests/elf_file/fake-firmware-c-dynamic")
+            .with_api_list(vec![
+                ApiSpec::new("writeOnDrive"),
+                ApiSpec::new("accessWebcam"),
+            ])
+            .with_verbosity(Verbosity::Quiet);
+        let analysis = analyzer.analyze().unwrap();
+        let manifests = analyzer.manifests(&analysis).unwrap();
+
+        let declaration: Value = toml::from_str(
+            r#"
+            [[apis]]
+            name = "writeOnDrive"
+            capabilities = ["filesystem-write"]
+            syscalls = ["fopen64", "fprintf"]
+
+            [[apis]]
+            name = "turnLampOn"
+            "#,
+        )
+        .unwrap();
+        let declaration = Declaration::from_json(&declaration).unwrap();
+        let report = declaration.check(&manifests).unwrap();
+        assert_eq!(report.undeclared_apis, ["accessWebcam"]);
+        assert_eq!(report.missing_apis, ["turnLampOn"]);
+        assert!(report
+            .undeclared_capabilities
+            .iter()
+            .all(|(api, _)| api == "accessWebcam"));
+        assert!(!report.undeclared_capabilities.is_empty());
+        assert_eq!(
+            report.undeclared_syscalls,
+            [("writeOnDrive".to_string(), "fclose".to_string())]
+        );
+        assert!(!report.conforms());
+        assert_eq!(report.to_json()["missing APIs"][0], "turnLampOn");
+        assert!(report
+            .to_string()
+            .starts_with("Undeclared APIs:\n  - accessWebcam\n"));
+
+        let capabilities: Vec<String> = report
+            .undeclared_capabilities
+            .iter()
+            .map(|(_, capability)| capability.clone())
+            .collect();
+        let declaration = Declaration {
+            apis: vec![
+                DeclaredApi {
+                    name: "writeOnDrive".to_string(),
+                    capabilities: vec!["filesystem-write".to_string()],
+                    syscalls: None,
+                },
+                DeclaredApi {
+                    name: "accessWebcam".to_string(),
+                    ..DeclaredApi::default()
+                },
+            ],
+            capabilities,
+            syscalls: None,
+        };
+        let report = declaration.check(&manifests).unwrap();
+        assert!(report.conforms());
+        assert_eq!(report.to_string(), "The binary conforms to the declaration");
+    }
+
+    #[test]
+    fn test_invalid_declaration() {
+        let declaration = |source: &str| Declaration::from_json(&toml::from_str(source).unwrap());
+        assert!(declaration("capabilities = []").is_err());
+        assert!(declaration("apis = [{ capabilities = [] }]").is_err());
+        assert!(declaration("apis = [{ name = \"a\", syscalls = \"open\" }]").is_err());
+        assert!(declaration("apis = [{ name = \"a\" }, { name = \"a\" }]").is_err());
+        assert!(declaration("apis = [{ name = \"a\" }]").is_ok());
+    }
+}
@@ -0,0 +1,182 @@
+use goblin::elf::{
+    section_header::{SHT_FINI_ARRAY, SHT_INIT_ARRAY, SHT_PREINIT_ARRAY},
+    sym::STT_FUNC,
+    Elf,
+};
+use serde_json::{json, Value};
+
+use crate::{
+    call_graph::CallGraph,
+    cleanup::demangle_api_name,
+    indirect_calls::{PointerResolver, PointerValue},
+};
+
+/// When a constructor or destructor runs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    /// Before `main`.
+    Startup,
+    /// After `main` returns or `exit` is called.
+    Shutdown,
+}
+
+impl Phase {
+    /// Return the name of the phase, as written in the flow call manifest.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Phase::Startup => "startup",
+            Phase::Shutdown => "shutdown",
+        }
+    }
+}
+
+/// A function run by the loader or the C runtime around `main`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Constructor {
+    /// The section holding it, e.g. `.init_array`, or `.init` and `.fini` for the functions of
+    /// these sections.
+    pub section: String,
+    /// Its index in the array of the section, 0 for `.init` and `.fini`.
+    pub index: usize,
+    /// The address of the function.
+    pub address: u64,
+    /// The name of the function, when it has a symbol.
+    pub name: Option<String>,
+    /// When it runs.
+    pub phase: Phase,
+}
+
+impl Constructor {
+    /// Return the constructor as an entry of the `constructors` of the flow call manifest.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "section": self.section,
+            "index": self.index,
+            "address": format!("{:#x}", self.address),
+            "function": self.name,
+            "phase": self.phase.as_str(),
+        })
+    }
+}
+
+/// List the constructors and destructors of the binary: the functions of the `.init` and
+/// `.fini` sections and the entries of `.preinit_array`, `.init_array` and `.fini_array`, as
+/// filled by `__attribute__((constructor))`, C++ static initializers and `atexit`-like
+/// destructors.
+///
+/// The entries of a position-independent binary are read from their relocations, and the null
+/// and `-1` entries are left out.
+///
+/// # Arguments
+///
+/// * `elf` - The ELF object representing the binary.
+/// * `buffer` - The buffer containing the binary data of the ELF file.
+/// * `graph` - The call graph of the binary, naming the functions with the symbol table.
+///
+/// # Returns
+///
+/// Returns the constructors in the order they are run, then the destructors.
+pub fn constructors(elf: &Elf, buffer: &[u8], graph: &CallGraph) -> Vec<Constructor> {
+    let resolver = PointerResolver::new(elf, buffer);
+    // The functions without a size, e.g. `frame_dummy`, are only named by their symbol.
+    let name = |address: u64| {
+        let symbol = || {
+            elf.syms
+                .iter()
+                .find(|sym| sym.st_value == address && sym.st_type() == STT_FUNC)
+                .and_then(|sym| elf.strtab.get_at(sym.st_name))
+        };
+        graph
+            .function(address)
+            .map(|func| func.name.as_str())
+            .or_else(symbol)
+            .map(demangle_api_name)
+    };
+    let section_named = |wanted: &str| {
+        elf.section_headers
+            .iter()
+            .find(|sh| elf.shdr_strtab.get_at(sh.sh_name) == Some(wanted))
+    };
+
+    let mut entries = Vec::new();
+    // The runtime runs `.preinit_array`, `.init`, then `.init_array`; and `.fini_array`
+    // before `.fini`.
+    let order = [
+        (Some(SHT_PREINIT_ARRAY), ".preinit_array", Phase::Startup),
+        (None, ".init", Phase::Startup),
+        (Some(SHT_INIT_ARRAY), ".init_array", Phase::Startup),
+        (Some(SHT_FINI_ARRAY), ".fini_array", Phase::Shutdown),
+        (None, ".fini", Phase::Shutdown),
+    ];
+    for (kind, section, phase) in order {
+        let Some(kind) = kind else {
+            if let Some(sh) = section_named(section).filter(|sh| sh.sh_size > 0) {
+                entries.push(Constructor {
+                    section: section.to_string(),
+                    index: 0,
+                    address: sh.sh_addr,
+                    name: name(sh.sh_addr),
+                    phase,
+                });
+            }
+            continue;
+        };
+        for sh in elf.section_headers.iter().filter(|sh| sh.sh_type == kind) {
+            let section = elf.shdr_strtab.get_at(sh.sh_name).unwrap_or(section);
+            for index in 0..(sh.sh_size / 8) as usize {
+                let Some(PointerValue::Address(address)) =
+                    resolver.read_pointer(sh.sh_addr + index as u64 * 8)
+                else {
+                    continue;
+                };
+                if address == u64::MAX {
+                    continue;
+                }
+                entries.push(Constructor {
+                    section: section.to_string(),
+                    index,
+                    address,
+                    name: name(address),
+                    phase,
+                });
+            }
+        }
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf_utils::read_elf_file;
+
+    #[test]
+    fn test_constructors() {
+        assert_eq!(Phase::Shutdown.as_str(), "shutdown");
+
+        // A position-independent binary, whose arrays are filled by relocations.
+        let buffer = read_elf_file("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        let graph = CallGraph::build(&elf, &buffer, false).unwrap();
+        let entries = constructors(&elf, &buffer, &graph);
+        let named = |section: &str, function: &str| {
+            entries
+                .iter()
+                .any(|entry| entry.section == section && entry.name.as_deref() == Some(function))
+        };
+        assert!(named(".init", "_init"));
+        assert!(named(".init_array", "frame_dummy"));
+        assert!(named(".fini_array", "__do_global_dtors_aux"));
+        assert!(named(".fini", "_fini"));
+        assert_eq!(
+            entries
+                .iter()
+                .filter(|entry| entry.section == ".init_array")
+                .count(),
+            0xd0 / 8
+        );
+        let last = entries.last().unwrap();
+        assert_eq!(last.phase, Phase::Shutdown);
+        assert_eq!(last.to_json()["phase"], "shutdown");
+    }
+}
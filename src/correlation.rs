@@ -0,0 +1,378 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde_json::{json, Value};
+
+use crate::{call_graph::CallGraph, dynamic_analysis::ObservedBehavior, elf_utils::API};
+
+/// The libc functions the static analysis sees called, with the Linux syscalls they make.
+/// Functions named after a syscall make it as well, e.g. `ioctl`.
+const WRAPPERS: [(&str, &[&str]); 42] = [
+    ("open", &["openat", "open"]),
+    ("fopen", &["openat", "open"]),
+    ("freopen", &["openat", "open"]),
+    ("opendir", &["openat", "open"]),
+    ("creat", &["openat", "creat"]),
+    ("read", &["read"]),
+    ("fread", &["read"]),
+    ("fgets", &["read"]),
+    ("getline", &["read"]),
+    ("fscanf", &["read"]),
+    ("pread", &["pread64"]),
+    ("readdir", &["getdents64"]),
+    ("write", &["write"]),
+    ("fwrite", &["write"]),
+    ("printf", &["write"]),
+    ("fprintf", &["write"]),
+    ("vfprintf", &["write"]),
+    ("puts", &["write"]),
+    ("fputs", &["write"]),
+    ("fflush", &["write"]),
+    ("pwrite", &["pwrite64"]),
+    ("fclose", &["close"]),
+    ("closedir", &["close"]),
+    ("stat", &["newfstatat", "stat", "statx"]),
+    ("lstat", &["newfstatat", "lstat", "statx"]),
+    ("fstat", &["newfstatat", "fstat", "statx"]),
+    ("unlink", &["unlinkat", "unlink"]),
+    ("mkdir", &["mkdirat", "mkdir"]),
+    ("rename", &["renameat2", "renameat", "rename"]),
+    ("accept", &["accept", "accept4"]),
+    ("send", &["sendto"]),
+    ("recv", &["recvfrom"]),
+    (
+        "getaddrinfo",
+        &[
+            "socket", "connect", "sendto", "sendmmsg", "recvfrom", "poll",
+        ],
+    ),
+    (
+        "gethostbyname",
+        &["socket", "connect", "sendto", "recvfrom", "poll"],
+    ),
+    ("pthread_create", &["clone", "clone3"]),
+    ("fork", &["clone", "fork"]),
+    ("system", &["clone", "clone3", "vfork", "execve", "wait4"]),
+    ("popen", &["clone", "clone3", "vfork", "execve", "pipe2"]),
+    ("execl", &["execve"]),
+    ("execvp", &["execve"]),
+    ("waitpid", &["wait4"]),
+    ("sleep", &["clock_nanosleep", "nanosleep"]),
+];
+
+/// The syscalls made by the dynamic loader and the C runtime of any binary, before its main
+/// function and at its exit, which are not suspicious when the binary does not call them.
+pub const RUNTIME_SYSCALLS: [&str; 26] = [
+    "execve",
+    "brk",
+    "mmap",
+    "munmap",
+    "mprotect",
+    "madvise",
+    "arch_prctl",
+    "set_tid_address",
+    "set_robust_list",
+    "rseq",
+    "prlimit64",
+    "access",
+    "openat",
+    "newfstatat",
+    "fstat",
+    "read",
+    "pread64",
+    "close",
+    "getrandom",
+    "futex",
+    "rt_sigaction",
+    "rt_sigprocmask",
+    "sigaltstack",
+    "sched_getaffinity",
+    "exit",
+    "exit_group",
+];
+
+/// Where the evidence of a syscall or an API comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Evidence {
+    /// Predicted by the static analysis only.
+    Static,
+    /// Observed while the binary ran only.
+    Dynamic,
+    /// Both predicted and observed.
+    Both,
+}
+
+impl Evidence {
+    /// The name of the evidence in the manifests, e.g. `statically-predicted`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Evidence::Static => "statically-predicted",
+            Evidence::Dynamic => "dynamically-observed",
+            Evidence::Both => "both",
+        }
+    }
+}
+
+/// A syscall predicted by the static analysis or observed while the binary ran.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CorrelatedSyscall {
+    /// The name of the syscall, e.g. `openat`.
+    pub name: String,
+    /// Whether the syscall was predicted, observed or both.
+    pub evidence: Evidence,
+    /// The functions called by the binary that make the syscall.
+    pub predicted_by: Vec<String>,
+    /// Whether the syscall was observed although no call of the binary makes it, nor its
+    /// runtime: code the static analysis cannot reach, e.g. loaded with `dlopen`.
+    pub suspicious: bool,
+}
+
+/// The syscalls an API is predicted to make, and those of them observed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CorrelatedApi {
+    /// The name of the API.
+    pub name: String,
+    /// [`Evidence::Both`] when some syscall predicted for the API was observed.
+    pub evidence: Evidence,
+    /// The syscalls made by the functions the API reaches.
+    pub predicted: Vec<String>,
+    /// The syscalls predicted that were observed.
+    pub observed: Vec<String>,
+}
+
+/// The correlation of the static and dynamic analyses of a binary.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Correlation {
+    /// Every syscall predicted or observed, by name.
+    pub syscalls: Vec<CorrelatedSyscall>,
+    /// The APIs, with their predicted syscalls.
+    pub apis: Vec<CorrelatedApi>,
+}
+
+impl Correlation {
+    /// The syscalls observed although the static analysis does not predict them.
+    pub fn suspicious(&self) -> impl Iterator<Item = &CorrelatedSyscall> {
+        self.syscalls.iter().filter(|syscall| syscall.suspicious)
+    }
+
+    /// The `correlation` section of the flow call manifest.
+    pub fn to_json(&self) -> Value {
+        let syscalls: Vec<Value> = self
+            .syscalls
+            .iter()
+            .map(|syscall| {
+                json!({
+                    "name": syscall.name,
+                    "evidence": syscall.evidence.as_str(),
+                    "predicted by": syscall.predicted_by,
+                    "suspicious": syscall.suspicious,
+                })
+            })
+            .collect();
+        let apis: Vec<Value> = self
+            .apis
+            .iter()
+            .map(|api| {
+                json!({
+                    "name": api.name,
+                    "evidence": api.evidence.as_str(),
+                    "predicted syscalls": api.predicted,
+                    "observed syscalls": api.observed,
+                })
+            })
+            .collect();
+        let suspicious: Vec<&str> = self.suspicious().map(|s| s.name.as_str()).collect();
+        json!({
+            "syscalls": syscalls,
+            "APIs": apis,
+            "suspicious": suspicious,
+        })
+    }
+}
+
+/// Correlate the syscalls predicted by the calls of a binary with those observed while it ran.
+///
+/// The calls of the call graph to libc functions are mapped to the syscalls those functions
+/// make; an observed syscall that no call makes, and that the dynamic loader and the C runtime
+/// do not make either (see [`RUNTIME_SYSCALLS`]), is flagged as suspicious.
+///
+/// # Arguments
+///
+/// * `graph` - The call graph of the binary.
+/// * `apis` - The APIs found, with their transitive calls.
+/// * `observed` - The behavior of the binary observed while it ran.
+///
+/// # Returns
+///
+/// Returns the correlation, with the syscalls sorted by name.
+pub fn correlate(graph: &CallGraph, apis: &[API], observed: &ObservedBehavior) -> Correlation {
+    let predicted = predicted_syscalls(graph);
+    let seen = observed.syscalls();
+
+    let names: BTreeSet<&str> = predicted
+        .keys()
+        .copied()
+        .chain(seen.iter().copied())
+        .collect();
+    let syscalls = names
+        .into_iter()
+        .map(|name| {
+            let (is_predicted, is_observed) = (predicted.contains_key(name), seen.contains(name));
+            CorrelatedSyscall {
+                name: name.to_string(),
+                evidence: evidence(is_predicted, is_observed),
+                predicted_by: predicted
+                    .get(name)
+                    .map(|functions| functions.iter().cloned().collect())
+                    .unwrap_or_default(),
+                suspicious: is_observed && !is_predicted && !RUNTIME_SYSCALLS.contains(&name),
+            }
+        })
+        .collect();
+    let apis = apis
+        .iter()
+        .map(|api| {
+            let predicted: BTreeSet<&str> = api
+                .syscalls
+                .iter()
+                .chain(&api.transitive_calls)
+                .flat_map(|call| syscalls_of(call))
+                .collect();
+            let observed: Vec<String> = predicted
+                .iter()
+                .filter(|syscall| seen.contains(*syscall))
+                .map(|syscall| syscall.to_string())
+                .collect();
+            CorrelatedApi {
+                name: api.name.clone(),
+                evidence: evidence(true, !observed.is_empty()),
+                predicted: predicted
+                    .iter()
+                    .map(|syscall| syscall.to_string())
+                    .collect(),
+                observed,
+            }
+        })
+        .collect();
+    Correlation { syscalls, apis }
+}
+
+/// Predict the syscalls a binary makes from the calls of its call graph: those of the libc
+/// functions called, e.g. `openat` for `fopen64`, and the syscalls that the functions called
+/// are named after, e.g. `ioctl`.
+///
+/// # Returns
+///
+/// Returns the names of the functions called making each syscall, by syscall.
+pub fn predicted_syscalls(graph: &CallGraph) -> BTreeMap<&'static str, BTreeSet<String>> {
+    let mut predicted: BTreeMap<&str, BTreeSet<String>> = BTreeMap::new();
+    for function in graph.functions() {
+        for edge in graph.callees(function.start_addr) {
+            let name = edge.name.split('@').next().unwrap_or(&edge.name);
+            for syscall in syscalls_of(name) {
+                predicted
+                    .entry(syscall)
+                    .or_default()
+                    .insert(name.to_string());
+            }
+        }
+    }
+    predicted
+}
+
+// The syscalls made by a called function: those of the libc function it is, e.g. `openat`
+// for `fopen64` or `_IO_fopen`, else the syscall it is named after, e.g. `ioctl`.
+pub(crate) fn syscalls_of(call: &str) -> Vec<&'static str> {
+    let name = call.split('@').next().unwrap_or(call);
+    let name = ["__libc_", "_IO_", "__"]
+        .iter()
+        .find_map(|prefix| name.strip_prefix(prefix))
+        .unwrap_or(name);
+    let function = name.strip_suffix("64").unwrap_or(name);
+    match WRAPPERS.iter().find(|(wrapper, _)| *wrapper == function) {
+        Some((_, syscalls)) => syscalls.to_vec(),
+        None => syscall_named(name)
+            .or_else(|| syscall_named(function))
+            .into_iter()
+            .collect(),
+    }
+}
+
+// The Linux syscall of the host named `name`.
+#[cfg(all(feature = "native", target_os = "linux"))]
+fn syscall_named(name: &str) -> Option<&'static str> {
+    name.parse::<syscalls::Sysno>()
+        .ok()
+        .map(|sysno| sysno.name())
+}
+
+// Without the syscall table, only the libc functions are mapped to syscalls; the binaries are
+// not run on such hosts anyway.
+#[cfg(not(all(feature = "native", target_os = "linux")))]
+fn syscall_named(_name: &str) -> Option<&'static str> {
+    None
+}
+
+// The evidence of a syscall or an API, from whether it was predicted and observed.
+fn evidence(predicted: bool, observed: bool) -> Evidence {
+    match (predicted, observed) {
+        (true, true) => Evidence::Both,
+        (false, true) => Evidence::Dynamic,
+        _ => Evidence::Static,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{analyzer::Analyzer, api_detection::ApiSpec, dynamic_analysis::ThreadTrace};
+
+    #[test]
+    fn test_correlate() {
+        let analysis = Analyzer::new("./tests/elf_file/fake-firmware-c-dynamic")
+            .with_api_list(vec![ApiSpec::new("writeOnDrive")])
+            .analyze()
+            .unwrap();
+        let observed = ObservedBehavior {
+            threads: vec![ThreadTrace {
+                thread: 1,
+                syscalls: ["mmap", "openat", "write", "close", "uname"]
+                    .map(String::from)
+                    .to_vec(),
+                calls: 5,
+            }],
+            exit_code: Some(0),
+            ..ObservedBehavior::default()
+        };
+        let correlation = correlate(&analysis.graph, &analysis.apis, &observed);
+        let syscall = |name: &str| {
+            correlation
+                .syscalls
+                .iter()
+                .find(|syscall| syscall.name == name)
+                .unwrap()
+        };
+        assert_eq!(syscall("openat").evidence, Evidence::Both);
+        assert!(syscall("openat")
+            .predicted_by
+            .contains(&"fopen64".to_string()));
+        assert_eq!(syscall("bind").evidence, Evidence::Static);
+        // Made by the runtime of every binary.
+        assert_eq!(syscall("mmap").evidence, Evidence::Dynamic);
+        assert!(!syscall("mmap").suspicious);
+        assert!(syscall("uname").suspicious);
+        assert_eq!(
+            correlation
+                .suspicious()
+                .map(|s| s.name.as_str())
+                .collect::<Vec<_>>(),
+            ["uname"]
+        );
+
+        let api = &correlation.apis[0];
+        assert_eq!(api.evidence, Evidence::Both);
+        assert_eq!(api.observed, ["close", "openat", "write"]);
+        let json = correlation.to_json();
+        assert_eq!(json["suspicious"], serde_json::json!(["uname"]));
+        assert_eq!(json["APIs"][0]["evidence"], "both");
+    }
+}
@@ -0,0 +1,746 @@
+use std::collections::{BTreeMap, HashMap};
+
+use goblin::elf::{
+    header::EM_ARM,
+    program_header::{PF_X, PT_LOAD},
+    sym::STT_FUNC,
+    Elf,
+};
+use serde_json::{json, Value};
+
+use crate::{
+    arguments::CallArguments,
+    call_graph::{CallEdge, CallGraph, FunctionNode, UnresolvedCall},
+    elf_utils::code_bytes,
+};
+
+/// The sections holding the vector table in the linker scripts of the SDKs of the vendors.
+pub const VECTOR_SECTIONS: [&str; 5] = [
+    ".isr_vector",
+    ".isr_vectors",
+    ".vectors",
+    ".vector_table",
+    ".intvecs",
+];
+
+// The names of the system exceptions, by number. The entry 0 is the initial stack pointer, and
+// the numbers without a name are reserved.
+const EXCEPTION_NAMES: [Option<&str>; 16] = [
+    None,
+    Some("Reset"),
+    Some("NMI"),
+    Some("HardFault"),
+    Some("MemManage"),
+    Some("BusFault"),
+    Some("UsageFault"),
+    Some("SecureFault"),
+    None,
+    None,
+    None,
+    Some("SVCall"),
+    Some("DebugMonitor"),
+    None,
+    Some("PendSV"),
+    Some("SysTick"),
+];
+
+// The largest number of external interrupts of the NVIC.
+const MAX_INTERRUPTS: usize = 496;
+
+// The number of instructions at the start of a function read for its prologue.
+const MAX_PROLOGUE: usize = 16;
+
+/// An exception or interrupt handler of the vector table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VectorEntry {
+    /// The number of the exception, 1 for the reset and 16 for the first interrupt.
+    pub index: usize,
+    /// The name of the exception, e.g. `SysTick` or `IRQ3`.
+    pub exception: String,
+    /// The address of the handler, without the Thumb bit.
+    pub address: u64,
+    /// The name of the handler, when a function symbol starts at its address.
+    pub handler: Option<String>,
+}
+
+impl VectorEntry {
+    /// Return the entry as an element of the `entries` of the `vector table`.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "index": self.index,
+            "exception": self.exception,
+            "handler": self.handler,
+            "address": format!("{:#x}", self.address),
+        })
+    }
+}
+
+/// The interrupt vector table of a Cortex-M image.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VectorTable {
+    /// The address of the table.
+    pub address: u64,
+    /// The initial value of the main stack pointer, its first word.
+    pub initial_sp: u64,
+    /// The handlers set, in the order of the table; the null and reserved entries are left out.
+    pub entries: Vec<VectorEntry>,
+}
+
+impl VectorTable {
+    /// Return the handler of the reset, the first code run by the core.
+    pub fn reset_handler(&self) -> Option<&VectorEntry> {
+        self.entries.iter().find(|entry| entry.index == 1)
+    }
+
+    /// Return the addresses of the handlers, once each and sorted.
+    pub fn handlers(&self) -> Vec<u64> {
+        let mut handlers: Vec<u64> = self.entries.iter().map(|entry| entry.address).collect();
+        handlers.sort_unstable();
+        handlers.dedup();
+        handlers
+    }
+
+    /// Return the table as the `vector table` of the flow call manifest.
+    pub fn to_json(&self) -> Value {
+        let entries: Vec<Value> = self.entries.iter().map(VectorEntry::to_json).collect();
+        json!({
+            "address": format!("{:#x}", self.address),
+            "initial stack pointer": format!("{:#x}", self.initial_sp),
+            "reset handler": self.reset_handler().and_then(|entry| entry.handler.clone()),
+            "entries": entries,
+        })
+    }
+}
+
+/// Read the interrupt vector table of a Cortex-M image.
+///
+/// The table is the content of one of the [`VECTOR_SECTIONS`], or else the start of the lowest
+/// loadable segment, where the core reads it from on reset. It is only taken for one when its
+/// reset handler is a Thumb address, with its lowest bit set, in executable code; without a
+/// section, the table ends at its first entry that is neither null nor such an address.
+///
+/// # Arguments
+///
+/// * `elf` - The ELF object representing the image.
+/// * `buffer` - The buffer containing the binary data of the ELF file.
+///
+/// # Returns
+///
+/// Returns the vector table, `None` for a binary that is not a 32-bit ARM image or has none.
+pub fn vector_table(elf: &Elf, buffer: &[u8]) -> Option<VectorTable> {
+    if elf.header.e_machine != EM_ARM || elf.is_64 {
+        return None;
+    }
+    let section = elf.section_headers.iter().find(|sh| {
+        VECTOR_SECTIONS.contains(&elf.shdr_strtab.get_at(sh.sh_name).unwrap_or_default())
+    });
+    let (address, bytes, sized) = match section {
+        Some(sh) => (sh.sh_addr, buffer.get(sh.file_range()?)?, true),
+        None => {
+            let ph = elf
+                .program_headers
+                .iter()
+                .filter(|ph| ph.p_type == PT_LOAD && ph.p_filesz > 0)
+                .min_by_key(|ph| ph.p_vaddr)?;
+            (ph.p_vaddr, buffer.get(ph.file_range())?, false)
+        }
+    };
+    let words: Vec<u64> = bytes
+        .chunks_exact(4)
+        .take(EXCEPTION_NAMES.len() + MAX_INTERRUPTS)
+        .map(|word| u64::from(u32::from_le_bytes([word[0], word[1], word[2], word[3]])))
+        .collect();
+    let handler = |word: u64| (word & 1 == 1 && in_code(elf, word & !1)).then_some(word & !1);
+    handler(*words.get(1)?)?;
+
+    let names = function_names(elf);
+    let mut entries = Vec::new();
+    for (index, &word) in words.iter().enumerate().skip(1) {
+        let address = match handler(word) {
+            Some(address) => address,
+            None if word == 0 || sized => continue,
+            None => break,
+        };
+        let exception = match EXCEPTION_NAMES.get(index) {
+            Some(Some(name)) => name.to_string(),
+            Some(None) => continue,
+            None => format!("IRQ{}", index - EXCEPTION_NAMES.len()),
+        };
+        entries.push(VectorEntry {
+            index,
+            exception,
+            address,
+            handler: names.get(&address).map(|name| name.to_string()),
+        });
+    }
+    Some(VectorTable {
+        address,
+        initial_sp: words[0],
+        entries,
+    })
+}
+
+/// Return the functions of a Thumb image, as nodes of its call graph.
+///
+/// The lowest bit of the address of a Thumb function symbol only tells its instruction set: it
+/// is cleared from the boundaries of the functions.
+pub fn thumb_functions(elf: &Elf) -> Vec<FunctionNode> {
+    let mut functions = BTreeMap::new();
+    for sym in elf.syms.iter() {
+        if sym.st_type() != STT_FUNC || sym.st_shndx == 0 || sym.st_size == 0 {
+            continue;
+        }
+        if let Some(name) = elf.strtab.get_at(sym.st_name) {
+            let start_addr = sym.st_value & !1;
+            functions.entry(start_addr).or_insert_with(|| FunctionNode {
+                name: name.to_string(),
+                start_addr,
+                end_addr: start_addr + sym.st_size,
+            });
+        }
+    }
+    functions.into_values().collect()
+}
+
+/// Build the call graph of a Thumb image, such as the firmware of a Cortex-M core.
+///
+/// The calls are the `bl` and `blx` with an immediate target, and the `b.w` to the start of
+/// another function, i.e. tail calls; the `blx` of a register are kept as unresolved calls.
+/// The literal pools marked by the `$d` mapping symbols are not decoded.
+///
+/// # Arguments
+///
+/// * `elf` - The ELF object representing the image.
+/// * `buffer` - The buffer containing the binary data of the ELF file.
+///
+/// # Returns
+///
+/// Returns the call graph of the functions of [`thumb_functions`].
+pub fn thumb_call_graph(elf: &Elf, buffer: &[u8]) -> CallGraph {
+    let nodes = thumb_functions(elf);
+    let names = function_names(elf);
+    let data = data_ranges(elf);
+    let mut calls = HashMap::new();
+    let mut unresolved = HashMap::new();
+
+    for func in &nodes {
+        let Some(code) = code_bytes(elf, buffer, func.start_addr, func.end_addr) else {
+            continue;
+        };
+        let mut edges = Vec::new();
+        let mut indirect = Vec::new();
+        for insn in instructions(code, func.start_addr, &data) {
+            let Some(second) = insn.second else {
+                // blx <Rm>
+                if insn.first & 0xff87 == 0x4780 {
+                    indirect.push(UnresolvedCall {
+                        site: insn.site,
+                        operand: format!("r{}", (insn.first >> 3) & 0xf),
+                    });
+                }
+                continue;
+            };
+            let tail = |target: u64| {
+                !(func.start_addr..func.end_addr).contains(&target) && names.contains_key(&target)
+            };
+            let target = match decode_branch(insn.first, second, insn.site) {
+                Some(Branch::Call(target)) => Some(target),
+                Some(Branch::Jump(target)) if tail(target) => Some(target),
+                _ => None,
+            };
+            if let Some(target) = target {
+                edges.push(CallEdge {
+                    site: insn.site,
+                    target: Some(target),
+                    name: names
+                        .get(&target)
+                        .map_or_else(|| format!("CALL_to_<{:#x}>", target), |n| n.to_string()),
+                    indirect: false,
+                });
+            }
+        }
+        calls.insert(func.start_addr, edges);
+        if !indirect.is_empty() {
+            unresolved.insert(func.start_addr, indirect);
+        }
+    }
+    CallGraph::from_calls(nodes, calls, unresolved)
+}
+
+/// Estimate the stack frame of each function of a Thumb image from its prologue: the registers
+/// saved by `push`, `push.w` and `vpush`, and the space reserved by `sub sp` and `sub.w sp`,
+/// until the first branch or `pop`.
+///
+/// # Arguments
+///
+/// * `elf` - The ELF object representing the image.
+/// * `buffer` - The buffer containing the binary data of the ELF file.
+/// * `graph` - The call graph of the image, from [`thumb_call_graph`].
+///
+/// # Returns
+///
+/// Returns the frames in bytes, by starting address of the functions.
+pub fn thumb_frame_sizes(elf: &Elf, buffer: &[u8], graph: &CallGraph) -> HashMap<u64, u64> {
+    let data = data_ranges(elf);
+    let mut frames = HashMap::new();
+    for func in graph.functions() {
+        let Some(code) = code_bytes(elf, buffer, func.start_addr, func.end_addr) else {
+            continue;
+        };
+        let mut frame = 0;
+        for insn in instructions(code, func.start_addr, &data)
+            .iter()
+            .take(MAX_PROLOGUE)
+        {
+            let first = insn.first;
+            let Some(second) = insn.second else {
+                match first {
+                    // pop, bx, b and the conditional branches end the prologue.
+                    _ if first & 0xfe00 == 0xbc00 || first & 0xff00 == 0x4700 => break,
+                    _ if first >> 12 == 0b1101 || first >> 11 == 0b11100 => break,
+                    // push <registers>, with lr in bit 8.
+                    _ if first & 0xfe00 == 0xb400 => frame += 4 * (first & 0x1ff).count_ones(),
+                    // sub sp, sp, #<imm7 * 4>
+                    _ if first & 0xff80 == 0xb080 => frame += 4 * u32::from(first & 0x7f),
+                    _ => {}
+                }
+                continue;
+            };
+            if decode_branch(first, second, insn.site).is_some() {
+                break;
+            }
+            let rd = (second >> 8) & 0xf;
+            let imm12 = ((u32::from(first) >> 10) & 1) << 11
+                | ((u32::from(second) >> 12) & 7) << 8
+                | u32::from(second & 0xff);
+            if first == 0xe92d {
+                // push.w <registers>
+                frame += 4 * (second & 0x5fff).count_ones();
+            } else if first == 0xf84d && second & 0x0fff == 0x0d04 {
+                // push.w <register>
+                frame += 4;
+            } else if first & 0xffbf == 0xed2d {
+                // vpush <registers>, of imm8 words.
+                frame += 4 * u32::from(second & 0xff);
+            } else if first & 0xfbff == 0xf2ad && rd == 13 {
+                // subw sp, sp, #<imm12>
+                frame += imm12;
+            } else if first & 0xfbff == 0xf1ad && rd == 13 {
+                // sub.w sp, sp, #<modified immediate>
+                frame += expand_immediate(imm12);
+            }
+        }
+        frames.insert(func.start_addr, u64::from(frame));
+    }
+    frames
+}
+
+/// Find the calls of a Thumb image to some functions and recover the constants of their
+/// arguments, as [`crate::arguments::call_arguments`] does for x86-64.
+///
+/// The first four arguments are held by `r0` to `r3` in the ARM calling convention, so only the
+/// first four values of each call are set. They are the constants loaded by `movs`, by
+/// `movw`/`movt` pairs, by `adr` and from the literal pools; a register written by another
+/// instruction, or by a call, is forgotten.
+///
+/// # Arguments
+///
+/// * `elf` - The ELF object representing the image.
+/// * `buffer` - The buffer containing the binary data of the ELF file.
+/// * `graph` - The call graph of the image, from [`thumb_call_graph`].
+/// * `functions` - The names of the functions whose calls are looked for.
+///
+/// # Returns
+///
+/// Returns the calls found, sorted by call site.
+pub fn thumb_call_arguments(
+    elf: &Elf,
+    buffer: &[u8],
+    graph: &CallGraph,
+    functions: &[&'static str],
+) -> Vec<CallArguments> {
+    let data = data_ranges(elf);
+    let matching = |name: &str| {
+        functions
+            .iter()
+            .find(|function| **function == name)
+            .copied()
+    };
+    let word = |addr: u64| {
+        let bytes = code_bytes(elf, buffer, addr, addr + 4)?;
+        Some(u64::from(u32::from_le_bytes(bytes.try_into().ok()?)))
+    };
+    let mut calls = Vec::new();
+
+    for func in graph.functions() {
+        if matching(&func.name).is_some() {
+            continue;
+        }
+        let sites: Vec<(u64, &'static str)> = graph
+            .callees(func.start_addr)
+            .iter()
+            .filter_map(|edge| Some((edge.site, matching(&edge.name)?)))
+            .collect();
+        if sites.is_empty() {
+            continue;
+        }
+        let Some(code) = code_bytes(elf, buffer, func.start_addr, func.end_addr) else {
+            continue;
+        };
+        let mut regs = [None; 16];
+        for insn in instructions(code, func.start_addr, &data) {
+            if let Some((site, function)) = sites.iter().find(|(site, _)| *site == insn.site) {
+                let mut values = [None; 6];
+                values[..4].copy_from_slice(&regs[..4]);
+                calls.push(CallArguments {
+                    function,
+                    site: *site,
+                    caller: func.start_addr,
+                    values,
+                });
+            }
+            update_registers(&mut regs, &insn, &word);
+        }
+    }
+    calls.sort_by_key(|call| call.site);
+    calls
+}
+
+// A Thumb instruction: its address and its one or two halfwords.
+struct Thumb {
+    site: u64,
+    first: u16,
+    second: Option<u16>,
+}
+
+// Split the code of a function loaded at `start` into its instructions, skipping the literal
+// pools.
+fn instructions(code: &[u8], start: u64, data: &BTreeMap<u64, u64>) -> Vec<Thumb> {
+    let mut instructions = Vec::new();
+    let mut offset = 0;
+    while offset + 2 <= code.len() {
+        let site = start + offset as u64;
+        let pool = data.range(..=site).next_back();
+        if let Some((_, &end)) = pool.filter(|(_, end)| site < **end) {
+            offset = (end - start) as usize;
+            continue;
+        }
+        let first = u16::from_le_bytes([code[offset], code[offset + 1]]);
+        if !is_wide(first) {
+            instructions.push(Thumb {
+                site,
+                first,
+                second: None,
+            });
+            offset += 2;
+            continue;
+        }
+        let Some(second) = code.get(offset + 2..offset + 4) else {
+            break;
+        };
+        instructions.push(Thumb {
+            site,
+            first,
+            second: Some(u16::from_le_bytes([second[0], second[1]])),
+        });
+        offset += 4;
+    }
+    instructions
+}
+
+// Update the constants held by the registers with the effect of an instruction, reading the
+// literal pools with `word`.
+fn update_registers(regs: &mut [Option<u64>; 16], insn: &Thumb, word: &dyn Fn(u64) -> Option<u64>) {
+    let (first, literal_base) = (insn.first, (insn.site + 4) & !3);
+    let Some(second) = insn.second else {
+        let low = usize::from((first >> 8) & 7);
+        match first >> 11 {
+            // movs <Rd>, #<imm8>
+            0b00100 => regs[low] = Some(u64::from(first & 0xff)),
+            // ldr <Rt>, [pc, #<imm8>]
+            0b01001 => regs[low] = word(literal_base + u64::from(first & 0xff) * 4),
+            // adr <Rd>, <label>
+            0b10100 => regs[low] = Some(literal_base + u64::from(first & 0xff) * 4),
+            // The stores and the compares write no register.
+            0b01100 | 0b01110 | 0b10000 | 0b10010 | 0b00101 => {}
+            // mov <Rd>, <Rm>, of any register.
+            _ if first & 0xff00 == 0x4600 => {
+                let rd = usize::from(((first >> 4) & 8) | (first & 7));
+                regs[rd] = regs[usize::from((first >> 3) & 0xf)];
+            }
+            // blx <Rm>
+            _ if first & 0xff87 == 0x4780 => regs[..4].fill(None),
+            // push, pop and the others: the registers they may write are forgotten.
+            _ => {
+                regs[usize::from(first & 7)] = None;
+                regs[low] = None;
+                if first & 0xfe00 == 0xbc00 {
+                    regs[..8].fill(None);
+                }
+            }
+        }
+        return;
+    };
+    // bl and blx: the registers of the arguments and of the result are not preserved.
+    if let Some(Branch::Call(_)) = decode_branch(first, second, insn.site) {
+        regs[..4].fill(None);
+        return;
+    }
+    let (first, second) = (u32::from(first), u32::from(second));
+    let rd = ((second >> 8) & 0xf) as usize;
+    // The 16 bits of movw and movt.
+    let imm16 = ((first & 0xf) << 12) | (((first >> 10) & 1) << 11) | (((second >> 12) & 7) << 8);
+    let imm16 = u64::from(imm16 | (second & 0xff));
+    if first & 0xfbf0 == 0xf240 && second & 0x8000 == 0 {
+        regs[rd] = Some(imm16);
+    } else if first & 0xfbf0 == 0xf2c0 && second & 0x8000 == 0 {
+        regs[rd] = regs[rd].map(|low| (low & 0xffff) | (imm16 << 16));
+    } else if first & 0xff7f == 0xf85f {
+        // ldr.w <Rt>, [pc, #+/-<imm12>]
+        let offset = u64::from(second & 0xfff);
+        regs[(second >> 12) as usize] = match first & 0x80 != 0 {
+            true => word(literal_base + offset),
+            false => word(literal_base.wrapping_sub(offset)),
+        };
+    } else {
+        regs[rd] = None;
+        regs[(second >> 12) as usize] = None;
+    }
+}
+
+// A branch with an immediate target.
+#[derive(Debug, PartialEq)]
+enum Branch {
+    // bl, or blx to ARM code.
+    Call(u64),
+    // b.w
+    Jump(u64),
+}
+
+// The value of the modified immediate of a Thumb-2 data-processing instruction.
+fn expand_immediate(imm12: u32) -> u32 {
+    let imm8 = imm12 & 0xff;
+    if imm12 & 0xc00 != 0 {
+        return (0x80 | (imm12 & 0x7f)).rotate_right(imm12 >> 7);
+    }
+    match (imm12 >> 8) & 3 {
+        0 => imm8,
+        1 => (imm8 << 16) | imm8,
+        2 => (imm8 << 24) | (imm8 << 8),
+        _ => imm8 * 0x0101_0101,
+    }
+}
+
+// Whether a halfword is the first one of a 32-bit Thumb-2 instruction.
+fn is_wide(first: u16) -> bool {
+    matches!(first >> 11, 0b11101..=0b11111)
+}
+
+// Decode the `bl`, `blx` or `b.w` (encoding T4) made of two halfwords at `site`.
+fn decode_branch(first: u16, second: u16, site: u64) -> Option<Branch> {
+    if first & 0xf800 != 0xf000 || second & 0x8000 == 0 {
+        return None;
+    }
+    let (first, second) = (u32::from(first), u32::from(second));
+    let sign = (first >> 10) & 1;
+    let i1 = !((second >> 13) ^ sign) & 1;
+    let i2 = !((second >> 11) ^ sign) & 1;
+    let imm =
+        (sign << 24) | (i1 << 23) | (i2 << 22) | ((first & 0x3ff) << 12) | ((second & 0x7ff) << 1);
+    // Sign-extend the 25 bits of the offset, relative to the instruction after the first
+    // halfword.
+    let offset = ((imm << 7) as i32 >> 7) as i64;
+    let pc = site as i64 + 4;
+    match (second >> 12) & 0b101 {
+        0b101 => Some(Branch::Call((pc + offset) as u64)),
+        // blx switches to ARM code, at an address aligned to 4.
+        0b100 => Some(Branch::Call(((pc & !3) + offset) as u64)),
+        0b001 => Some(Branch::Jump((pc + offset) as u64)),
+        _ => None,
+    }
+}
+
+// Whether an address is in a loadable and executable segment.
+fn in_code(elf: &Elf, addr: u64) -> bool {
+    elf.program_headers.iter().any(|ph| {
+        ph.p_type == PT_LOAD && ph.p_flags & PF_X != 0 && ph.vm_range().contains(&(addr as usize))
+    })
+}
+
+// The names of the function symbols, by address without the Thumb bit.
+fn function_names<'a>(elf: &'a Elf) -> HashMap<u64, &'a str> {
+    let mut names = HashMap::new();
+    for sym in elf.syms.iter().filter(|sym| sym.st_type() == STT_FUNC) {
+        if let Some(name) = elf
+            .strtab
+            .get_at(sym.st_name)
+            .filter(|name| !name.is_empty())
+        {
+            names.entry(sym.st_value & !1).or_insert(name);
+        }
+    }
+    names
+}
+
+// The ranges of data inside the code, from a `$d` mapping symbol to the following `$t` or `$a`.
+fn data_ranges(elf: &Elf) -> BTreeMap<u64, u64> {
+    let mut mappings: Vec<(u64, bool)> = elf
+        .syms
+        .iter()
+        .filter_map(|sym| {
+            let name = elf.strtab.get_at(sym.st_name)?;
+            let kind = name.strip_prefix('$')?.split('.').next()?;
+            matches!(kind, "a" | "d" | "t").then_some((sym.st_value, kind == "d"))
+        })
+        .collect();
+    mappings.sort_unstable();
+    let mut ranges = BTreeMap::new();
+    for (index, &(start, data)) in mappings.iter().enumerate() {
+        if !data {
+            continue;
+        }
+        let end = mappings[index + 1..]
+            .iter()
+            .find(|(_, data)| !data)
+            .map_or(u64::MAX, |&(end, _)| end);
+        ranges.entry(start).or_insert(end);
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf_utils::read_elf_file;
+
+    #[test]
+    fn test_decode_branch() {
+        // bl 0x800005a at 0x8000050
+        assert_eq!(
+            decode_branch(0xf000, 0xf803, 0x8000050),
+            Some(Branch::Call(0x800005a))
+        );
+        // bl 0x8000062 at 0x800007e, backwards
+        assert_eq!(
+            decode_branch(0xf7ff, 0xfff0, 0x800007e),
+            Some(Branch::Call(0x8000062))
+        );
+        // b.w 0x80000ae at 0x80000b0
+        assert_eq!(
+            decode_branch(0xf7ff, 0xbffd, 0x80000b0),
+            Some(Branch::Jump(0x80000ae))
+        );
+        // blx 0x1000 at 0x802, to ARM code
+        assert_eq!(
+            decode_branch(0xf000, 0xebfe, 0x802),
+            Some(Branch::Call(0x1000))
+        );
+        // A conditional branch and a movw.
+        assert_eq!(decode_branch(0xf000, 0x8000, 0x0), None);
+        assert_eq!(decode_branch(0xf240, 0x0000, 0x0), None);
+        assert!(is_wide(0xf000));
+        assert!(!is_wide(0x4780));
+    }
+
+    #[test]
+    fn test_vector_table() {
+        let buffer = read_elf_file("./tests/elf_file/minimal-fake-firmware-cortex-m").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        let table = vector_table(&elf, &buffer).unwrap();
+        assert_eq!(table.address, 0x8000000);
+        assert_eq!(table.initial_sp, 0x20004000);
+        let reset = table.reset_handler().unwrap();
+        assert_eq!(reset.handler.as_deref(), Some("Reset_Handler"));
+        assert_eq!(reset.address, elf.entry & !1);
+        let irqs: Vec<(&str, Option<&str>)> = table
+            .entries
+            .iter()
+            .filter(|entry| entry.index >= 16)
+            .map(|entry| (entry.exception.as_str(), entry.handler.as_deref()))
+            .collect();
+        assert_eq!(
+            irqs,
+            [
+                ("IRQ0", Some("UART0_IRQHandler")),
+                ("IRQ1", Some("TIMER0_IRQHandler")),
+                ("IRQ3", Some("Default_Handler")),
+            ]
+        );
+        // The entries sharing Default_Handler give one handler.
+        assert_eq!(table.handlers().len(), 8);
+        assert_eq!(table.to_json()["reset handler"], "Reset_Handler");
+
+        // Without its section, the table is read from the start of the flash.
+        let mut patched = buffer.clone();
+        let sh = elf
+            .section_headers
+            .iter()
+            .find(|sh| elf.shdr_strtab.get_at(sh.sh_name) == Some(".isr_vector"))
+            .unwrap();
+        let name = elf.header.e_shoff as usize
+            + elf.header.e_shstrndx as usize * elf.header.e_shentsize as usize
+            + 16;
+        let names = u32::from_le_bytes(patched[name..name + 4].try_into().unwrap()) as usize;
+        patched[names + sh.sh_name] = b'_';
+        let elf = Elf::parse(&patched).unwrap();
+        assert_eq!(vector_table(&elf, &patched), Some(table));
+
+        let buffer = read_elf_file("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        assert_eq!(vector_table(&elf, &buffer), None);
+    }
+
+    #[test]
+    fn test_thumb_call_graph() {
+        let buffer = read_elf_file("./tests/elf_file/minimal-fake-firmware-cortex-m").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        let graph = thumb_call_graph(&elf, &buffer);
+        let addr = |name: &str| {
+            graph
+                .functions()
+                .find(|func| func.name == name)
+                .unwrap()
+                .start_addr
+        };
+        assert_eq!(addr("Reset_Handler") & 1, 0);
+        assert_eq!(
+            graph.callee_names(addr("Reset_Handler")),
+            ["init_clocks", "main"]
+        );
+        assert_eq!(
+            graph.callee_names(addr("UART0_IRQHandler")),
+            ["uart_read", "ring_push"]
+        );
+        // A tail call, and a jump to itself.
+        assert_eq!(graph.callee_names(addr("SysTick_Handler")), ["tick"]);
+        assert!(graph.callee_names(addr("NMI_Handler")).is_empty());
+        // The literal pool of write_reg is not decoded.
+        assert!(graph.callee_names(addr("write_reg")).is_empty());
+        let unresolved = graph.unresolved_calls(addr("process_command"));
+        assert_eq!(unresolved[0].operand, "r4");
+
+        let reachable = graph.reachable(&[addr("Reset_Handler")]);
+        assert!(reachable.contains(&addr("write_reg")));
+        assert!(!reachable.contains(&addr("uart_read")));
+    }
+
+    #[test]
+    fn test_thumb_call_arguments() {
+        let buffer = read_elf_file("./tests/elf_file/minimal-fake-firmware-freertos").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        let graph = thumb_call_graph(&elf, &buffer);
+        let calls = thumb_call_arguments(&elf, &buffer, &graph, &["xTaskCreate"]);
+        assert_eq!(calls.len(), 2);
+        assert!(calls.windows(2).all(|pair| pair[0].site < pair[1].site));
+        // A literal pool entry keeps the Thumb bit of the function pointer.
+        assert_eq!(calls[0].values[0], Some(0x0800_0095));
+        assert_eq!(calls[0].values[1], Some(0x0800_00cc));
+        assert!(calls[0].values[4..].iter().all(Option::is_none));
+        // pvPortMalloc is only called by xTaskCreate itself.
+        assert!(
+            thumb_call_arguments(&elf, &buffer, &graph, &["xTaskCreate", "pvPortMalloc"])
+                .iter()
+                .all(|call| call.function == "xTaskCreate")
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use goblin::elf::{sym::STT_FUNC, Elf};
+use serde_json::{json, Value};
+
+use crate::{
+    analyzer::Binary,
+    api_detection::{compile_patterns, rank_candidates, ApiCandidate, ApiPattern, ApiSpec},
+    cleanup::demangle_api_name,
+    dwarf_analysis::inlined_functions,
+    elf_utils::{is_stripped, API},
+    error::Result,
+};
+
+/// The lowest similarity of the symbols suggested for an API that was not found.
+pub const MIN_CANDIDATE_CONFIDENCE: f64 = 0.5;
+/// The number of symbols suggested for an API that was not found.
+pub const MAX_CANDIDATES: usize = 3;
+
+/// Why an entry of the API list was not found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Unmatched {
+    /// The binary has no symbol table, and no signature named the function.
+    StrippedSymbols,
+    /// The debugging information defines the function, but of no code of its own: it was
+    /// inlined into its callers.
+    Inlined,
+    /// Symbols of similar names exist, below the confidence of the fuzzy matching.
+    NameMismatch,
+    /// No symbol names the function, and the debugging information does not define it.
+    Absent,
+}
+
+impl Unmatched {
+    /// Return the reason, as written in the coverage report.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Unmatched::StrippedSymbols => "stripped symbol table",
+            Unmatched::Inlined => "inlined",
+            Unmatched::NameMismatch => "name mismatch",
+            Unmatched::Absent => "absent",
+        }
+    }
+}
+
+/// A symbol an entry of the API list was found under.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CoverageMatch {
+    /// The (demangled) name of the symbol.
+    pub symbol: String,
+    /// The address of the function.
+    pub address: u64,
+    /// The similarity of the names, for a symbol found by fuzzy matching.
+    pub confidence: Option<f64>,
+}
+
+/// Whether an entry of the API list was found, and under which symbols, or why not.
+pub struct ApiCoverage {
+    /// The name or pattern of the entry, as written in the API list.
+    pub requested: String,
+    /// The symbols the entry was found under.
+    pub matches: Vec<CoverageMatch>,
+    /// Why the entry was not found, `None` when it was.
+    pub reason: Option<Unmatched>,
+    /// The symbols of the closest names, for an entry that was not found.
+    pub candidates: Vec<ApiCandidate>,
+}
+
+impl ApiCoverage {
+    /// Return the coverage of the entry as an element of the `APIs` of the coverage report.
+    pub fn to_json(&self) -> Value {
+        let symbols: Vec<Value> = self
+            .matches
+            .iter()
+            .map(|found| {
+                json!({
+                    "name": found.symbol,
+                    "address": format!("{:#x}", found.address),
+                    "match": match found.confidence {
+                        Some(_) => "similarity",
+                        None => "name",
+                    },
+                    "confidence": found.confidence,
+                })
+            })
+            .collect();
+        let candidates: Vec<Value> = self
+            .candidates
+            .iter()
+            .map(|candidate| json!({ "symbol": candidate.symbol, "confidence": candidate.confidence }))
+            .collect();
+        json!({
+            "api": self.requested,
+            "found": self.reason.is_none(),
+            "symbols": symbols,
+            "reason": self.reason.map(|reason| reason.as_str()),
+            "candidates": candidates,
+        })
+    }
+}
+
+/// Tell, for each entry of the API list, whether it was found, the symbols it was found under,
+/// and why it was not.
+///
+/// An entry is not found in a stripped binary whose functions no signature named; else it is
+/// inlined when the DWARF information defines it without code, and mismatched when symbols of a
+/// similar name exist.
+///
+/// # Arguments
+///
+/// * `binary` - The binary analyzed.
+/// * `specs` - The entries of the API list.
+/// * `apis` - The APIs found.
+///
+/// # Returns
+///
+/// Returns the coverage of each entry, in the order of the API list.
+///
+/// # Errors
+///
+/// Returns an error if the binary cannot be parsed again, or an entry of the API list is not a
+/// valid pattern.
+pub fn api_coverage(binary: &Binary, specs: &[ApiSpec], apis: &[API]) -> Result<Vec<ApiCoverage>> {
+    let elf = binary.elf()?;
+    let names = function_names(binary, &elf);
+    let unnamed =
+        is_stripped(&elf) && binary.go_functions.is_none() && binary.recognized_functions.is_none();
+    let inlined = match binary.language.is_empty() {
+        true => Vec::new(),
+        false => inlined_functions(&binary.data)?,
+    };
+    let mut coverage = Vec::new();
+    for spec in specs {
+        let patterns = compile_patterns(&spec.names().collect::<Vec<_>>())?;
+        let matches: Vec<CoverageMatch> = apis
+            .iter()
+            .filter(|api| {
+                let requested = api.fuzzy_match.as_ref().map(|fuzzy| &fuzzy.requested);
+                patterns.iter().any(|pattern| {
+                    pattern.matches(&api.name)
+                        || requested.is_some_and(|name| pattern.matches(name))
+                })
+            })
+            .map(|api| CoverageMatch {
+                symbol: api.name.clone(),
+                address: api.start_addr,
+                confidence: api.fuzzy_match.as_ref().map(|fuzzy| fuzzy.confidence),
+            })
+            .collect();
+        let (reason, candidates) = match matches.is_empty() {
+            false => (None, Vec::new()),
+            true if unnamed => (Some(Unmatched::StrippedSymbols), Vec::new()),
+            true => {
+                let candidates: Vec<ApiCandidate> = rank_candidates(&spec.name, &names)
+                    .into_iter()
+                    .filter(|candidate| candidate.confidence >= MIN_CANDIDATE_CONFIDENCE)
+                    .take(MAX_CANDIDATES)
+                    .collect();
+                let defined = inlined.iter().any(|name| {
+                    patterns.iter().any(|pattern| match pattern {
+                        ApiPattern::Exact(requested) => requested == name,
+                        ApiPattern::Pattern(_) => false,
+                    })
+                });
+                let reason = if defined {
+                    Unmatched::Inlined
+                } else if !candidates.is_empty() {
+                    Unmatched::NameMismatch
+                } else {
+                    Unmatched::Absent
+                };
+                (Some(reason), candidates)
+            }
+        };
+        coverage.push(ApiCoverage {
+            requested: spec.name.clone(),
+            matches,
+            reason,
+            candidates,
+        });
+    }
+    Ok(coverage)
+}
+
+/// Return the coverage of the API list as the coverage report written alongside the feature
+/// manifest: the number of entries found, and the coverage of each.
+pub fn coverage_report(coverage: &[ApiCoverage]) -> Value {
+    let found = coverage.iter().filter(|api| api.reason.is_none()).count();
+    let apis: Vec<Value> = coverage.iter().map(ApiCoverage::to_json).collect();
+    json!({
+        "requested": coverage.len(),
+        "found": found,
+        "APIs": apis,
+    })
+}
+
+// The names of the functions the APIs are looked for among: those of the `.gopclntab` of a Go
+// binary, those recognized in a stripped binary, else the function symbols.
+fn function_names(binary: &Binary, elf: &Elf) -> Vec<String> {
+    if let Some(functions) = &binary.go_functions {
+        return functions.iter().map(|func| func.name.clone()).collect();
+    }
+    if let Some(functions) = &binary.recognized_functions {
+        return functions.iter().map(|func| func.name.clone()).collect();
+    }
+    elf.syms
+        .iter()
+        .filter(|sym| sym.st_type() == STT_FUNC && sym.st_shndx != 0)
+        .filter_map(|sym| elf.strtab.get_at(sym.st_name))
+        .map(demangle_api_name)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{analyzer::Analyzer, progress::Verbosity};
+
+    #[test]
+    fn test_api_coverage() {
+        let specs = vec![
+            ApiSpec::new("writeOnDrive"),
+            ApiSpec::new("turnLampUp"),
+            ApiSpec::new("_mm_getcsr"),
+            ApiSpec::new("notAnApi"),
+        ];
+        let analyzer = Analyzer::new("./tests/elf_file/fake-firmware-c-dynamic")
+            .with_api_list(specs.clone())
+            .with_verbosity(Verbosity::Quiet);
+        let binary = analyzer.load().unwrap();
+        let apis = analyzer.detect_apis(&binary).unwrap();
+        let coverage = api_coverage(&binary, &specs, &apis).unwrap();
+        assert_eq!(coverage.len(), 4);
+        assert_eq!(coverage[0].reason, None);
+        assert_eq!(coverage[0].matches[0].symbol, "writeOnDrive");
+        assert_eq!(coverage[1].reason, Some(Unmatched::NameMismatch));
+        assert!(coverage[1]
+            .candidates
+            .iter()
+            .any(|candidate| candidate.symbol == "turnLampOn"));
+        // An intrinsic of the SSE headers, only ever inlined.
+        assert_eq!(coverage[2].reason, Some(Unmatched::Inlined));
+        assert_eq!(coverage[3].reason, Some(Unmatched::Absent));
+        assert!(coverage[3].candidates.is_empty());
+
+        let report = coverage_report(&coverage);
+        assert_eq!(report["requested"], 4);
+        assert_eq!(report["found"], 1);
+        assert_eq!(report["APIs"][1]["reason"], "name mismatch");
+        assert_eq!(report["APIs"][0]["symbols"][0]["match"], "name");
+    }
+}
@@ -0,0 +1,315 @@
+use std::collections::BTreeMap;
+
+use goblin::elf::{
+    section_header::{SHF_ALLOC, SHF_EXECINSTR, SHT_NOBITS},
+    sym::{STT_FUNC, STT_OBJECT},
+    Elf,
+};
+
+use crate::{call_graph::CallGraph, cleanup::demangle_api_name, xrefs::XrefDb};
+
+// Known algorithms, as (name, weak, words naming them in symbols).
+const ALGORITHMS: [(&str, bool, &[&str]); 12] = [
+    ("AES", false, &["aes", "aesni", "rijndael"]),
+    ("DES", true, &["des", "des3", "3des", "desx"]),
+    ("RC4", true, &["rc4", "arc4", "arcfour"]),
+    ("MD4", true, &["md4"]),
+    ("MD5", true, &["md5"]),
+    ("SHA-1", true, &["sha1"]),
+    ("SHA-256", false, &["sha224", "sha256"]),
+    ("SHA-512", false, &["sha384", "sha512"]),
+    ("ChaCha20", false, &["chacha", "chacha20", "xchacha20"]),
+    ("Blowfish", false, &["blowfish"]),
+    ("RSA", false, &["rsa"]),
+    ("ECC", false, &["ecdsa", "ecdh", "x25519", "ed25519"]),
+];
+
+// Constants of the implementations, as (algorithm, constant, little-endian bytes). Constants
+// shorter than 8 bytes are looked for in the code only, where they are instruction immediates.
+const CONSTANTS: [(&str, &str, &[u8]); 11] = [
+    (
+        "AES",
+        "S-box",
+        &[
+            0x63, 0x7c, 0x77, 0x7b, 0xf2, 0x6b, 0x6f, 0xc5, 0x30, 0x01, 0x67, 0x2b, 0xfe, 0xd7,
+            0xab, 0x76,
+        ],
+    ),
+    (
+        "AES",
+        "inverse S-box",
+        &[
+            0x52, 0x09, 0x6a, 0xd5, 0x30, 0x36, 0xa5, 0x38, 0xbf, 0x40, 0xa3, 0x9e, 0x81, 0xf3,
+            0xd7, 0xfb,
+        ],
+    ),
+    (
+        "AES",
+        "T-table",
+        &[0xa5, 0x63, 0x63, 0xc6, 0x84, 0x7c, 0x7c, 0xf8],
+    ),
+    (
+        "DES",
+        "S-box",
+        &[
+            0x0e, 0x04, 0x0d, 0x01, 0x02, 0x0f, 0x0b, 0x08, 0x03, 0x0a, 0x06, 0x0c, 0x05, 0x09,
+            0x00, 0x07,
+        ],
+    ),
+    (
+        "DES",
+        "SP table",
+        &[0x00, 0x08, 0x08, 0x02, 0x00, 0x00, 0x08, 0x00],
+    ),
+    ("MD5", "sine constant", &[0x78, 0xa4, 0x6a, 0xd7]),
+    ("SHA-1", "round constant", &[0xd6, 0xc1, 0x62, 0xca]),
+    (
+        "SHA-256",
+        "round constants",
+        &[0x98, 0x2f, 0x8a, 0x42, 0x91, 0x44, 0x37, 0x71],
+    ),
+    (
+        "SHA-512",
+        "round constants",
+        &[0x22, 0xae, 0x28, 0xd7, 0x98, 0x2f, 0x8a, 0x42],
+    ),
+    (
+        "Blowfish",
+        "P-array",
+        &[0x88, 0x6a, 0x3f, 0x24, 0xd3, 0x08, 0xa3, 0x85],
+    ),
+    ("ChaCha20", "sigma", b"expand 32-byte k"),
+];
+
+// Maximum number of symbols listed for each algorithm.
+const MAX_SYMBOLS: usize = 16;
+
+/// A constant of a cryptographic algorithm found in the binary.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CryptoConstant {
+    /// The name of the constant, e.g. `S-box`.
+    pub name: &'static str,
+    /// The address of the constant.
+    pub addr: u64,
+}
+
+/// A cryptographic algorithm used by the binary, with the evidence of its use.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CryptoAlgorithm {
+    /// The name of the algorithm, e.g. `AES`.
+    pub name: &'static str,
+    /// Whether the algorithm is considered broken or too weak for new designs.
+    pub weak: bool,
+    /// The names of the symbols implementing or importing the algorithm, at most sixteen.
+    pub symbols: Vec<String>,
+    /// The constants of the algorithm found in the code and data.
+    pub constants: Vec<CryptoConstant>,
+    /// The functions implementing or calling the algorithm, by starting address.
+    pub functions: BTreeMap<u64, String>,
+}
+
+/// Detect the cryptographic algorithms used by the binary.
+///
+/// Algorithms are recognized from the names of the symbols, defined or imported (OpenSSL,
+/// mbedTLS and Rust crate names like `EVP_md5`, `mbedtls_aes_crypt_cbc` or `sha2::sha256`),
+/// and from the constants of their implementations (AES S-boxes, SHA round constants, ...),
+/// which survive stripping and static linking.
+///
+/// # Arguments
+///
+/// * `elf` - The ELF object representing the binary.
+/// * `buffer` - The buffer containing the binary data of the ELF file.
+/// * `graph` - The call graph of the binary.
+/// * `xrefs` - The cross-references of the binary, to find the functions using the constants.
+///
+/// # Returns
+///
+/// Returns the algorithms found, in the order of [`ALGORITHMS`].
+pub fn detect_crypto(
+    elf: &Elf,
+    buffer: &[u8],
+    graph: &CallGraph,
+    xrefs: &XrefDb,
+) -> Vec<CryptoAlgorithm> {
+    let mut found: BTreeMap<usize, CryptoAlgorithm> = BTreeMap::new();
+    // Defined functions and tables, then imports.
+    let symbols = elf
+        .syms
+        .iter()
+        .filter(|sym| sym.st_value != 0 && matches!(sym.st_type(), STT_FUNC | STT_OBJECT))
+        .filter_map(|sym| Some((elf.strtab.get_at(sym.st_name)?, sym.st_value)))
+        .chain(
+            elf.dynsyms
+                .iter()
+                .filter(|sym| sym.is_import())
+                .filter_map(|sym| Some((elf.dynstrtab.get_at(sym.st_name)?, 0))),
+        );
+    let mut symbol_algorithms = BTreeMap::new();
+    for (name, addr) in symbols {
+        let name = name.split('@').next().unwrap_or(name);
+        let Some(algorithm) = symbol_algorithm(&demangle_api_name(name)) else {
+            continue;
+        };
+        symbol_algorithms.insert(name.to_string(), algorithm);
+        let Some(entry) = entry(&mut found, algorithm) else {
+            continue;
+        };
+        if let Some(func) = graph.function(addr) {
+            entry.functions.insert(addr, demangle_api_name(&func.name));
+        }
+        if entry.symbols.len() < MAX_SYMBOLS && !entry.symbols.contains(&name.to_string()) {
+            entry.symbols.push(name.to_string());
+        }
+    }
+    // Callers of the algorithm symbols use the algorithm.
+    for func in graph.functions() {
+        for edge in graph.callees(func.start_addr) {
+            let name = edge.name.split('@').next().unwrap_or(&edge.name);
+            if let Some(entry) = symbol_algorithms
+                .get(name)
+                .and_then(|alg| entry(&mut found, alg))
+            {
+                entry
+                    .functions
+                    .insert(func.start_addr, demangle_api_name(&func.name));
+            }
+        }
+    }
+
+    for sec in elf.section_headers.iter() {
+        let flags = sec.sh_flags as u32;
+        if flags & SHF_ALLOC == 0 || sec.sh_type == SHT_NOBITS || sec.sh_addr == 0 {
+            continue;
+        }
+        let start = sec.sh_offset as usize;
+        let Some(data) = buffer.get(start..start + sec.sh_size as usize) else {
+            continue;
+        };
+        let code = flags & SHF_EXECINSTR != 0;
+        for (algorithm, name, bytes) in CONSTANTS {
+            if bytes.len() < 8 && !code {
+                continue;
+            }
+            let Some(offset) = find(data, bytes) else {
+                continue;
+            };
+            let addr = sec.sh_addr + offset as u64;
+            let Some(entry) = entry(&mut found, algorithm) else {
+                continue;
+            };
+            entry.constants.push(CryptoConstant { name, addr });
+            if code {
+                if let Some(func) = graph.function_containing(addr) {
+                    entry
+                        .functions
+                        .insert(func.start_addr, demangle_api_name(&func.name));
+                }
+                continue;
+            }
+            let table = addr..addr + bytes.len() as u64;
+            for func in graph.functions() {
+                if xrefs
+                    .data_refs(func.start_addr)
+                    .iter()
+                    .any(|data| table.contains(&data.addr))
+                {
+                    entry
+                        .functions
+                        .insert(func.start_addr, demangle_api_name(&func.name));
+                }
+            }
+        }
+    }
+
+    found.into_values().collect()
+}
+
+/// Return the names of the algorithms used by the functions reachable from an API.
+///
+/// # Arguments
+///
+/// * `algorithms` - The algorithms used by the binary.
+/// * `graph` - The call graph of the binary.
+/// * `addr` - The starting address of the API.
+pub fn api_crypto(algorithms: &[CryptoAlgorithm], graph: &CallGraph, addr: u64) -> Vec<String> {
+    let reachable = graph.reachable(&[addr]);
+    algorithms
+        .iter()
+        .filter(|algorithm| {
+            algorithm
+                .functions
+                .keys()
+                .any(|func| reachable.contains(func))
+        })
+        .map(|algorithm| algorithm.name.to_string())
+        .collect()
+}
+
+// The entry of an algorithm, created on its first evidence.
+fn entry<'a>(
+    found: &'a mut BTreeMap<usize, CryptoAlgorithm>,
+    name: &str,
+) -> Option<&'a mut CryptoAlgorithm> {
+    let index = ALGORITHMS.iter().position(|(alg, _, _)| *alg == name)?;
+    let (name, weak, _) = ALGORITHMS[index];
+    Some(found.entry(index).or_insert_with(|| CryptoAlgorithm {
+        name,
+        weak,
+        symbols: Vec::new(),
+        constants: Vec::new(),
+        functions: BTreeMap::new(),
+    }))
+}
+
+// Find the algorithm named by one of the words of a symbol, e.g. `md5` in `Curl_MD5_init`.
+fn symbol_algorithm(name: &str) -> Option<&'static str> {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .map(str::to_ascii_lowercase)
+        .find_map(|word| {
+            ALGORITHMS
+                .iter()
+                .find(|(_, _, words)| words.contains(&word.as_str()))
+                .map(|(name, _, _)| *name)
+        })
+}
+
+// Offset of the first occurrence of a byte sequence.
+fn find(data: &[u8], bytes: &[u8]) -> Option<usize> {
+    data.windows(bytes.len()).position(|window| window == bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf_utils::read_elf_file;
+
+    #[test]
+    fn test_symbol_algorithm() {
+        assert_eq!(symbol_algorithm("Curl_MD5_init"), Some("MD5"));
+        assert_eq!(symbol_algorithm("DES_ecb_encrypt"), Some("DES"));
+        assert_eq!(symbol_algorithm("EVP_aes_128_cbc"), Some("AES"));
+        assert_eq!(symbol_algorithm("mbedtls_arc4_crypt"), Some("RC4"));
+        assert_eq!(
+            symbol_algorithm("sha2::sha256::compress256"),
+            Some("SHA-256")
+        );
+        assert_eq!(symbol_algorithm("describe_modes"), None);
+        assert_eq!(symbol_algorithm("writeOnDrive"), None);
+    }
+
+    #[test]
+    fn test_detect_crypto_dynamic() {
+        let buffer = read_elf_file("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        let graph = CallGraph::build(&elf, &buffer, false).unwrap();
+        let xrefs = XrefDb::build(&elf, &buffer, &graph);
+        let algorithms = detect_crypto(&elf, &buffer, &graph, &xrefs);
+
+        let des = algorithms.iter().find(|alg| alg.name == "DES").unwrap();
+        assert!(des.weak);
+        assert!(des.symbols.iter().any(|sym| sym == "DES_ecb_encrypt"));
+        assert!(!des.functions.is_empty());
+        let md5 = algorithms.iter().find(|alg| alg.name == "MD5").unwrap();
+        assert!(md5.symbols.iter().any(|sym| sym == "Curl_MD5_init"));
+    }
+}
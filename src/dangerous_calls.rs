@@ -0,0 +1,123 @@
+use crate::{call_graph::CallGraph, cleanup::demangle_api_name, elf_utils::API};
+
+/// The libc functions reported by default: unbounded copies and formatting, input without
+/// limits, shell execution and insecure temporary files.
+pub const DEFAULT_BANNED_FUNCTIONS: [&str; 20] = [
+    "gets", "strcpy", "stpcpy", "strcat", "wcscpy", "wcscat", "sprintf", "vsprintf", "scanf",
+    "sscanf", "fscanf", "vscanf", "vsscanf", "system", "popen", "mktemp", "tmpnam", "tempnam",
+    "getwd", "alloca",
+];
+
+/// A call to a banned function.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DangerousCall {
+    /// The name of the banned function.
+    pub function: String,
+    /// The address of the call instruction.
+    pub site: u64,
+    /// The name of the function performing the call.
+    pub caller: String,
+    /// The names of the APIs whose flow reaches the call.
+    pub apis: Vec<String>,
+}
+
+/// Find the calls to banned functions among the resolved call targets of the binary.
+///
+/// glibc aliases of the banned functions, like `__isoc99_sscanf` for `sscanf`, are matched as
+/// well, while their fortified `_chk` variants are not, since they check their bounds.
+///
+/// # Arguments
+///
+/// * `graph` - The call graph of the binary.
+/// * `banned` - The names of the banned functions.
+/// * `api_list` - The APIs found, to tell which of them reach each call.
+///
+/// # Returns
+///
+/// Returns the calls to banned functions, sorted by call site.
+pub fn find_dangerous_calls(
+    graph: &CallGraph,
+    banned: &[String],
+    api_list: &[API],
+) -> Vec<DangerousCall> {
+    let flows: Vec<_> = api_list
+        .iter()
+        .map(|api| (api.name.as_str(), graph.reachable(&[api.start_addr])))
+        .collect();
+
+    let mut calls = Vec::new();
+    for func in graph.functions() {
+        // A banned function calling itself, or its aliases, is not a use of it.
+        if banned_name(&func.name, banned).is_some() {
+            continue;
+        }
+        for edge in graph.callees(func.start_addr) {
+            let Some(function) = banned_name(&edge.name, banned) else {
+                continue;
+            };
+            calls.push(DangerousCall {
+                function: function.to_string(),
+                site: edge.site,
+                caller: demangle_api_name(&func.name),
+                apis: flows
+                    .iter()
+                    .filter(|(_, reachable)| reachable.contains(&func.start_addr))
+                    .map(|(name, _)| name.to_string())
+                    .collect(),
+            });
+        }
+    }
+    calls.sort_by_key(|call| call.site);
+    calls
+}
+
+// Return the banned function a called symbol stands for, if any.
+fn banned_name<'a>(name: &str, banned: &'a [String]) -> Option<&'a str> {
+    let name = name.split('@').next().unwrap_or(name);
+    let name = name
+        .strip_prefix("__isoc99_")
+        .or_else(|| name.strip_prefix("__isoc23_"))
+        .unwrap_or(name);
+    banned
+        .iter()
+        .find(|function| function.as_str() == name)
+        .map(String::as_str)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf_utils::read_elf_file;
+    use goblin::elf::Elf;
+
+    fn default_banned() -> Vec<String> {
+        DEFAULT_BANNED_FUNCTIONS
+            .iter()
+            .map(|name| name.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_banned_name() {
+        let banned = default_banned();
+        assert_eq!(banned_name("strcpy", &banned), Some("strcpy"));
+        assert_eq!(banned_name("sprintf@GLIBC_2.2.5", &banned), Some("sprintf"));
+        assert_eq!(banned_name("__isoc99_sscanf", &banned), Some("sscanf"));
+        assert_eq!(banned_name("__sprintf_chk", &banned), None);
+        assert_eq!(banned_name("strncpy", &banned), None);
+    }
+
+    #[test]
+    fn test_find_dangerous_calls() {
+        let buffer = read_elf_file("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        let graph = CallGraph::build(&elf, &buffer, false).unwrap();
+        let calls = find_dangerous_calls(&graph, &default_banned(), &[]);
+
+        assert!(calls.windows(2).all(|pair| pair[0].site <= pair[1].site));
+        assert!(calls.iter().any(|call| call.function == "strcpy"));
+        assert!(calls.iter().any(|call| call.function == "sscanf"));
+        let only_strcpy = find_dangerous_calls(&graph, &["strcpy".to_string()], &[]);
+        assert!(only_strcpy.iter().all(|call| call.function == "strcpy"));
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::BTreeSet;
+
+use goblin::elf::{
+    header::{EM_ARM, EM_X86_64},
+    section_header::{SHF_ALLOC, SHF_EXECINSTR, SHT_NOBITS},
+    sym::STB_LOCAL,
+    Elf,
+};
+use serde_json::{json, Value};
+
+use crate::{
+    call_graph::{CallGraph, FunctionNode},
+    cleanup::demangle_api_name,
+};
+
+// The functions called by the C runtime, which the call graph does not see being called.
+const RUNTIME_ROOTS: [&str; 2] = ["main", "_start"];
+
+/// Return the functions of the call graph that no entry point of the binary reaches.
+///
+/// The entry points are, besides the given roots, the entry point of the ELF header, `main`,
+/// the exported functions and the functions whose address is stored in the data of the binary
+/// or in its relocations: the `.init_array` entries, the callbacks of static tables and the
+/// virtual functions of C++. The functions whose address is loaded by the x86-64 code of a
+/// reachable function, e.g. a callback passed to `qsort`, are reachable too, as recorded by
+/// [`CallGraph::loads`]. A function only reached through an address computed at run time is
+/// reported although it may run.
+///
+/// # Arguments
+///
+/// * `elf` - The ELF object representing the binary.
+/// * `buffer` - The buffer containing the binary data of the ELF file.
+/// * `graph` - The call graph of the whole binary.
+/// * `roots` - The starting addresses of the other entry points, e.g. the APIs, the thread
+///   start routines, the signal handlers and the interrupt handlers.
+///
+/// # Returns
+///
+/// Returns the unreachable functions, sorted by address.
+pub fn unreachable_functions<'a>(
+    elf: &Elf,
+    buffer: &[u8],
+    graph: &'a CallGraph,
+    roots: &[u64],
+) -> Vec<&'a FunctionNode> {
+    let mut stack: Vec<u64> = roots.to_vec();
+    stack.extend(entry_points(elf, graph));
+    stack.extend(stored_addresses(elf, buffer));
+    let x86_64 = elf.header.e_machine == EM_X86_64;
+
+    let mut reachable = BTreeSet::new();
+    while let Some(addr) = stack.pop() {
+        if graph.function(addr).is_none() || !reachable.insert(addr) {
+            continue;
+        }
+        stack.extend(graph.callees(addr).iter().filter_map(|edge| edge.target));
+        if x86_64 {
+            stack.extend(graph.loads(addr).iter().map(|load| load.addr));
+        }
+    }
+    graph
+        .functions()
+        .filter(|func| !reachable.contains(&func.start_addr))
+        .collect()
+}
+
+/// Return the unreachable functions as the `unreachable functions` section of the flow call
+/// manifest, with their number and the size of their code.
+pub fn unreachable_json(functions: &[&FunctionNode]) -> Value {
+    let size: u64 = functions
+        .iter()
+        .map(|func| func.end_addr - func.start_addr)
+        .sum();
+    let functions: Vec<Value> = functions
+        .iter()
+        .map(|func| {
+            json!({
+                "name": demangle_api_name(&func.name),
+                "address": format!("{:#x}", func.start_addr),
+                "size": func.end_addr - func.start_addr,
+            })
+        })
+        .collect();
+    json!({
+        "count": functions.len(),
+        "size": size,
+        "functions": functions,
+    })
+}
+
+// The entry point of the header, the functions called by the C runtime and the exported
+// functions.
+fn entry_points(elf: &Elf, graph: &CallGraph) -> Vec<u64> {
+    let mut entries = vec![code_address(elf, elf.header.e_entry)];
+    entries.extend(
+        graph
+            .functions()
+            .filter(|func| RUNTIME_ROOTS.contains(&func.name.as_str()))
+            .map(|func| func.start_addr),
+    );
+    entries.extend(
+        elf.dynsyms
+            .iter()
+            .filter(|sym| sym.is_function() && sym.st_value != 0 && sym.st_bind() != STB_LOCAL)
+            .map(|sym| code_address(elf, sym.st_value)),
+    );
+    entries
+}
+
+// The addresses stored in the relocations and in the pointer-sized slots of the data sections,
+// of which those starting a function are the functions whose address is taken.
+fn stored_addresses(elf: &Elf, buffer: &[u8]) -> BTreeSet<u64> {
+    let mut addrs = BTreeSet::new();
+    for reloc in elf
+        .dynrelas
+        .iter()
+        .chain(elf.dynrels.iter())
+        .chain(elf.pltrelocs.iter())
+    {
+        if let Some(addend) = reloc.r_addend {
+            addrs.insert(code_address(elf, addend as u64));
+        }
+        if let Some(sym) = elf.dynsyms.get(reloc.r_sym).filter(|sym| sym.st_value != 0) {
+            addrs.insert(code_address(elf, sym.st_value));
+        }
+    }
+
+    let width = if elf.is_64 { 8 } else { 4 };
+    for sec in elf.section_headers.iter().filter(|sec| {
+        let flags = sec.sh_flags as u32;
+        flags & SHF_ALLOC != 0 && flags & SHF_EXECINSTR == 0 && sec.sh_type != SHT_NOBITS
+    }) {
+        let start = sec.sh_offset as usize;
+        let Some(data) = buffer.get(start..start + sec.sh_size as usize) else {
+            continue;
+        };
+        for word in data.chunks_exact(width) {
+            let value = match (width, elf.little_endian) {
+                (8, true) => u64::from_le_bytes(word.try_into().unwrap_or_default()),
+                (8, false) => u64::from_be_bytes(word.try_into().unwrap_or_default()),
+                (_, true) => u32::from_le_bytes(word.try_into().unwrap_or_default()) as u64,
+                (_, false) => u32::from_be_bytes(word.try_into().unwrap_or_default()) as u64,
+            };
+            if value != 0 {
+                addrs.insert(code_address(elf, value));
+            }
+        }
+    }
+    addrs
+}
+
+// The address of the code a pointer designates, without the Thumb bit of the ARM pointers.
+fn code_address(elf: &Elf, addr: u64) -> u64 {
+    match elf.header.e_machine {
+        EM_ARM => addr & !1,
+        _ => addr,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf_utils::read_elf_file;
+
+    #[test]
+    fn test_unreachable_functions() {
+        let buffer = read_elf_file("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        let graph = CallGraph::build(&elf, &buffer, false).unwrap();
+        let addr = |name: &str| {
+            graph
+                .functions()
+                .find(|func| func.name == name)
+                .unwrap()
+                .start_addr
+        };
+        let unreachable = unreachable_functions(&elf, &buffer, &graph, &[]);
+        let dead = |name: &str| unreachable.iter().any(|func| func.name == name);
+        // An API of the libcurl linked in that the firmware does not use.
+        assert!(dead("curl_easy_pause"));
+        // Reached from `main`, from `.init_array` and through a pointer loaded by the code.
+        assert!(!dead("main") && !dead("writeOnDrive"));
+        assert!(!dead("frame_dummy"));
+        assert!(!dead("mime_mem_read"));
+
+        let json = unreachable_json(&unreachable);
+        assert_eq!(json["count"], unreachable.len());
+        assert!(json["size"].as_u64().unwrap() > 0);
+        assert_eq!(
+            json["functions"].as_array().unwrap().len(),
+            unreachable.len()
+        );
+
+        // The roots given are entry points too.
+        let rooted = unreachable_functions(&elf, &buffer, &graph, &[addr("curl_easy_pause")]);
+        assert!(rooted.iter().all(|func| func.name != "curl_easy_pause"));
+        assert!(rooted.len() < unreachable.len());
+    }
+}
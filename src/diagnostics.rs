@@ -0,0 +1,313 @@
+use std::cmp::Reverse;
+
+use serde_json::{json, Value};
+
+use crate::{
+    analyzer::Analysis,
+    api_detection::{compile_patterns, ApiSpec},
+    elf_utils::API,
+    error::Result,
+};
+
+/// How much a diagnostic limits what the manifests tell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Worth knowing, the manifests are complete.
+    Info,
+    /// Part of the behavior of the binary may be missing from the manifests.
+    Warning,
+    /// A stage of the analysis failed, its results are missing from the manifests.
+    Error,
+}
+
+impl Severity {
+    /// Return the severity, as written in the manifests.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+/// A blind spot of the analysis, e.g. the indirect calls whose target is unknown.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    /// The class of the diagnostic, e.g. `unresolved-indirect-calls`.
+    pub code: String,
+    /// How much the diagnostic limits what the manifests tell.
+    pub severity: Severity,
+    /// What the analysis could not see.
+    pub message: String,
+    /// The functions, calls or entries of the API list affected, sorted.
+    pub items: Vec<String>,
+}
+
+impl Diagnostic {
+    // Build a diagnostic of sorted, unique items.
+    fn new(code: &str, severity: Severity, message: &str, mut items: Vec<String>) -> Self {
+        items.sort();
+        items.dedup();
+        Self {
+            code: code.to_string(),
+            severity,
+            message: message.to_string(),
+            items,
+        }
+    }
+
+    /// Return the diagnostic as an entry of the `diagnostics` section of the manifests.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "code": self.code,
+            "severity": self.severity.as_str(),
+            "message": self.message,
+            "items": self.items,
+        })
+    }
+}
+
+/// Collect the blind spots of an analysis, for the `diagnostics` section of the manifests.
+///
+/// # Arguments
+///
+/// * `analysis` - The analysis of the binary.
+/// * `specs` - The entries of the API list.
+///
+/// # Returns
+///
+/// Returns the diagnostics, the most severe first, and those of no items left out.
+///
+/// # Errors
+///
+/// Returns an error if an entry of the API list is not a valid pattern.
+pub fn collect_diagnostics(analysis: &Analysis, specs: &[ApiSpec]) -> Result<Vec<Diagnostic>> {
+    let binary = &analysis.binary;
+    let mut diagnostics: Vec<Diagnostic> = analysis
+        .errors
+        .iter()
+        .map(|error| {
+            let function = error.context().and_then(|context| context.function.clone());
+            Diagnostic::new(
+                error.kind(),
+                Severity::Error,
+                &error.to_string(),
+                function.into_iter().collect(),
+            )
+        })
+        .collect();
+
+    let mut missing = Vec::new();
+    for spec in specs {
+        let patterns = compile_patterns(&spec.names().collect::<Vec<_>>())?;
+        let found = analysis.apis.iter().any(|api| {
+            let requested = api.fuzzy_match.as_ref().map(|fuzzy| &fuzzy.requested);
+            patterns.iter().any(|pattern| {
+                pattern.matches(&api.name) || requested.is_some_and(|name| pattern.matches(name))
+            })
+        });
+        if !found {
+            missing.push(spec.name.clone());
+        }
+    }
+    diagnostics.push(Diagnostic::new(
+        "api-not-found",
+        Severity::Warning,
+        "Entries of the API list found in no symbol of the binary",
+        missing,
+    ));
+
+    let flows = || all_flows(analysis);
+    diagnostics.push(Diagnostic::new(
+        "unresolved-indirect-calls",
+        Severity::Warning,
+        "Indirect calls of an unknown target, whose callees are missing from the flows",
+        flows()
+            .flat_map(|api| api.unresolved_calls.clone())
+            .collect(),
+    ));
+    diagnostics.push(Diagnostic::new(
+        "skipped-functions",
+        Severity::Warning,
+        "Functions over the resource budget of the disassembly, missing from the flows",
+        flows()
+            .flat_map(|api| api.skipped_functions.clone())
+            .collect(),
+    ));
+
+    let mut callbacks = Vec::new();
+    for thread in &analysis.threads {
+        if thread.routine.is_none() && thread.routine_name.is_none() {
+            callbacks.push(format!(
+                "{} at {:#x} in {}",
+                thread.function, thread.site, thread.caller
+            ));
+        }
+    }
+    for signal in &analysis.signals {
+        if signal.handler.is_none() && signal.handler_name.is_none() {
+            callbacks.push(format!(
+                "{} at {:#x} in {}",
+                signal.function, signal.site, signal.caller
+            ));
+        }
+    }
+    for task in &analysis.tasks {
+        if task.entry.is_none() && task.entry_name.is_none() {
+            callbacks.push(format!(
+                "{} at {:#x} in {}",
+                task.function, task.site, task.caller
+            ));
+        }
+    }
+    diagnostics.push(Diagnostic::new(
+        "unresolved-callbacks",
+        Severity::Warning,
+        "Threads, signal handlers or tasks of an unknown function, whose flows are missing",
+        callbacks,
+    ));
+
+    let loads = analysis
+        .loading
+        .libraries
+        .iter()
+        .filter(|load| load.library.is_none());
+    let lookups = analysis
+        .loading
+        .symbols
+        .iter()
+        .filter(|lookup| lookup.symbol.is_none());
+    let mut dynamic: Vec<String> = loads
+        .map(|load| format!("{} at {:#x} in {}", load.function, load.site, load.caller))
+        .collect();
+    dynamic.extend(lookups.map(|lookup| {
+        format!(
+            "{} at {:#x} in {}",
+            lookup.function, lookup.site, lookup.caller
+        )
+    }));
+    diagnostics.push(Diagnostic::new(
+        "unresolved-dynamic-loading",
+        Severity::Warning,
+        "Libraries or symbols loaded at run time whose name is not a constant",
+        dynamic,
+    ));
+    diagnostics.push(Diagnostic::new(
+        "library-not-analyzed",
+        Severity::Warning,
+        "Libraries loaded at run time whose flows could not be traced",
+        analysis
+            .library_flows
+            .iter()
+            .filter_map(|library| {
+                let error = library.error.as_ref()?;
+                Some(format!("{}: {}", library.library, error))
+            })
+            .collect(),
+    ));
+
+    let unknown = binary.language.is_empty() || binary.language_inferred;
+    let debug_info = match (unknown, &binary.recognized_functions) {
+        (true, None) => vec![binary.path.clone()],
+        _ => Vec::new(),
+    };
+    diagnostics.push(Diagnostic::new(
+        "no-debug-info",
+        Severity::Warning,
+        "No DWARF debugging information: the language of the binary is guessed or unknown",
+        debug_info,
+    ));
+    let recognized = match &binary.recognized_functions {
+        Some(_) => vec![binary.path.clone()],
+        None => Vec::new(),
+    };
+    diagnostics.push(Diagnostic::new(
+        "stripped-binary",
+        Severity::Info,
+        "No symbol table: only the functions recognized by the signatures are named",
+        recognized,
+    ));
+    diagnostics.push(Diagnostic::new(
+        "depth-truncated",
+        Severity::Info,
+        "Flows cut by the maximum depth of the traversal",
+        flows()
+            .filter(|api| api.depth_truncated)
+            .map(|api| api.name.clone())
+            .collect(),
+    ));
+    diagnostics.push(Diagnostic::new(
+        "fuzzy-match",
+        Severity::Info,
+        "APIs found under another name than the one of the API list",
+        analysis
+            .apis
+            .iter()
+            .filter_map(|api| {
+                let fuzzy = api.fuzzy_match.as_ref()?;
+                Some(format!(
+                    "{} as {} ({:.2})",
+                    fuzzy.requested, api.name, fuzzy.confidence
+                ))
+            })
+            .collect(),
+    ));
+
+    diagnostics.retain(|diagnostic| {
+        !diagnostic.items.is_empty() || diagnostic.severity == Severity::Error
+    });
+    diagnostics.sort_by_key(|diagnostic| Reverse(diagnostic.severity));
+    Ok(diagnostics)
+}
+
+// Every flow of the analysis: those of the APIs, and those rooted at the threads, handlers,
+// constructors, destructors, interrupts and tasks.
+fn all_flows(analysis: &Analysis) -> impl Iterator<Item = &API> {
+    analysis
+        .apis
+        .iter()
+        .chain(&analysis.thread_flows)
+        .chain(&analysis.handler_flows)
+        .chain(&analysis.startup_flows)
+        .chain(&analysis.shutdown_flows)
+        .chain(&analysis.interrupt_flows)
+        .chain(&analysis.task_flows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{analyzer::Analyzer, progress::Verbosity};
+
+    #[test]
+    fn test_collect_diagnostics() {
+        let specs = vec![ApiSpec::new("writeOnDrive"), ApiSpec::new("notAnApi")];
+        let analyzer = Analyzer::new("./tests/elf_file/fake-firmware-c-dynamic")
+            .with_api_list(specs.clone())
+            .with_verbosity(Verbosity::Quiet);
+        let analysis = analyzer.analyze().unwrap();
+        let diagnostics = collect_diagnostics(&analysis, &specs).unwrap();
+        let missing = diagnostics
+            .iter()
+            .find(|diagnostic| diagnostic.code == "api-not-found")
+            .unwrap();
+        assert_eq!(missing.severity, Severity::Warning);
+        assert_eq!(missing.items, ["notAnApi"]);
+        assert!(diagnostics
+            .iter()
+            .all(|diagnostic| !diagnostic.items.is_empty()));
+        assert!(diagnostics
+            .windows(2)
+            .all(|pair| pair[0].severity >= pair[1].severity));
+        assert_eq!(missing.to_json()["severity"], "warning");
+
+        let analyzer = analyzer
+            .with_api_list(vec![ApiSpec::new("notAnApi")])
+            .with_best_effort(true);
+        let analysis = analyzer.analyze().unwrap();
+        let diagnostics = collect_diagnostics(&analysis, &[ApiSpec::new("notAnApi")]).unwrap();
+        assert_eq!(diagnostics[0].code, "no-api-found");
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+    }
+}
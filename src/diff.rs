@@ -0,0 +1,342 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    path::Path,
+};
+
+use serde_json::Value;
+
+use crate::{
+    error::{Error, Result},
+    manifest_creation::find_manifest,
+};
+
+/// The behavior of an API, as recorded in the flow call and feature manifests.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ApiBehavior {
+    /// The functions called by the API, in order.
+    pub syscalls: Vec<String>,
+    /// The functions reachable from the API.
+    pub transitive_calls: Vec<String>,
+    /// The feature categories of the API, e.g. `Network Access`.
+    pub capabilities: Vec<String>,
+}
+
+/// The APIs of one set of manifests.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ManifestSet {
+    /// The behavior of each API, by name.
+    pub apis: BTreeMap<String, ApiBehavior>,
+}
+
+impl ManifestSet {
+    /// Load the flow call and feature manifests of an output directory, in any format.
+    pub fn load(dir: &str) -> Result<Self> {
+        Ok(Self::from_json(
+            &find_manifest(dir, "flow_call")?,
+            &find_manifest(dir, "feature_manifest")?,
+        ))
+    }
+
+    /// Read the APIs of a flow call manifest and their capabilities from a feature manifest.
+    pub fn from_json(flow_call: &Value, feature: &Value) -> Self {
+        let strings = |value: Option<&Value>| -> Vec<String> {
+            value
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(|value| value.as_str().map(str::to_string))
+                .collect()
+        };
+        let apis = flow_call
+            .get("Public APIs flow")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|api| {
+                let name = api.get("name")?.as_str()?;
+                Some((
+                    name.to_string(),
+                    ApiBehavior {
+                        syscalls: strings(api.get("syscalls")),
+                        transitive_calls: strings(api.get("transitive calls")),
+                        capabilities: strings(feature.get(name)),
+                    },
+                ))
+            })
+            .collect();
+        Self { apis }
+    }
+}
+
+/// The changes of the calls of an API present in both sets.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FlowChange {
+    /// The name of the API.
+    pub api: String,
+    /// The functions called or reached only in the new version.
+    pub added_calls: Vec<String>,
+    /// The functions called or reached only in the old version.
+    pub removed_calls: Vec<String>,
+    /// Whether the API calls the same functions in a different order.
+    pub reordered: bool,
+}
+
+/// The behavioral differences between two versions of a firmware.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ManifestDiff {
+    /// The APIs found only in the new version.
+    pub added_apis: Vec<String>,
+    /// The APIs found only in the old version.
+    pub removed_apis: Vec<String>,
+    /// The APIs whose calls changed.
+    pub changed_flows: Vec<FlowChange>,
+    /// The functions called by no API of the old version and by some of the new one.
+    pub new_syscalls: Vec<String>,
+    /// The capabilities gained, as (API, capability).
+    pub new_capabilities: Vec<(String, String)>,
+    /// The capabilities lost, as (API, capability).
+    pub removed_capabilities: Vec<(String, String)>,
+}
+
+impl ManifestDiff {
+    /// Compare the manifests of an old and a new version.
+    pub fn compute(old: &ManifestSet, new: &ManifestSet) -> Self {
+        let names = |set: &ManifestSet| set.apis.keys().cloned().collect::<BTreeSet<_>>();
+        let (old_names, new_names) = (names(old), names(new));
+        let all_calls =
+            |set: &ManifestSet| -> BTreeSet<String> { set.apis.values().flat_map(calls).collect() };
+        let old_calls = all_calls(old);
+
+        let mut diff = Self {
+            added_apis: new_names.difference(&old_names).cloned().collect(),
+            removed_apis: old_names.difference(&new_names).cloned().collect(),
+            new_syscalls: all_calls(new).difference(&old_calls).cloned().collect(),
+            ..Self::default()
+        };
+        for (name, after) in &new.apis {
+            let Some(before) = old.apis.get(name) else {
+                diff.new_capabilities.extend(
+                    after
+                        .capabilities
+                        .iter()
+                        .map(|capability| (name.clone(), capability.clone())),
+                );
+                continue;
+            };
+            let (before_calls, after_calls) = (calls(before), calls(after));
+            let change = FlowChange {
+                api: name.clone(),
+                added_calls: after_calls.difference(&before_calls).cloned().collect(),
+                removed_calls: before_calls.difference(&after_calls).cloned().collect(),
+                reordered: before_calls == after_calls && before.syscalls != after.syscalls,
+            };
+            if !change.added_calls.is_empty()
+                || !change.removed_calls.is_empty()
+                || change.reordered
+            {
+                diff.changed_flows.push(change);
+            }
+            for capability in after.capabilities.iter() {
+                if !before.capabilities.contains(capability) {
+                    diff.new_capabilities
+                        .push((name.clone(), capability.clone()));
+                }
+            }
+            for capability in before.capabilities.iter() {
+                if !after.capabilities.contains(capability) {
+                    diff.removed_capabilities
+                        .push((name.clone(), capability.clone()));
+                }
+            }
+        }
+        diff
+    }
+
+    /// Whether the two versions behave the same.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Describe the differences in one line, e.g. after a rebuild.
+    pub fn summary(&self) -> String {
+        if self.is_empty() {
+            return "no behavioral change".to_string();
+        }
+        format!(
+            "{} APIs added, {} removed, {} flows changed, {} functions newly called, \
+             {} capabilities gained, {} lost",
+            self.added_apis.len(),
+            self.removed_apis.len(),
+            self.changed_flows.len(),
+            self.new_syscalls.len(),
+            self.new_capabilities.len(),
+            self.removed_capabilities.len()
+        )
+    }
+
+    /// Return the differences as a JSON document.
+    pub fn to_json(&self) -> Value {
+        let capabilities = |pairs: &[(String, String)]| -> Value {
+            let mut per_api: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+            for (api, capability) in pairs {
+                per_api.entry(api).or_default().push(capability);
+            }
+            serde_json::json!(per_api)
+        };
+        let changed_flows: Vec<Value> = self
+            .changed_flows
+            .iter()
+            .map(|change| {
+                serde_json::json!({
+                    "api": change.api,
+                    "added calls": change.added_calls,
+                    "removed calls": change.removed_calls,
+                    "reordered": change.reordered,
+                })
+            })
+            .collect();
+        serde_json::json!({
+            "added APIs": self.added_apis,
+            "removed APIs": self.removed_apis,
+            "changed flows": changed_flows,
+            "new syscalls": self.new_syscalls,
+            "new capabilities": capabilities(&self.new_capabilities),
+            "removed capabilities": capabilities(&self.removed_capabilities),
+        })
+    }
+}
+
+impl fmt::Display for ManifestDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No behavioral change");
+        }
+        for api in &self.added_apis {
+            writeln!(f, "+ API {}", api)?;
+        }
+        for api in &self.removed_apis {
+            writeln!(f, "- API {}", api)?;
+        }
+        for change in &self.changed_flows {
+            writeln!(f, "~ API {}", change.api)?;
+            for call in &change.added_calls {
+                writeln!(f, "    + calls {}", call)?;
+            }
+            for call in &change.removed_calls {
+                writeln!(f, "    - calls {}", call)?;
+            }
+            if change.reordered {
+                writeln!(f, "    calls in a different order")?;
+            }
+        }
+        for call in &self.new_syscalls {
+            writeln!(f, "+ syscall {}", call)?;
+        }
+        for (api, capability) in &self.new_capabilities {
+            writeln!(f, "+ capability {} of {}", capability, api)?;
+        }
+        for (api, capability) in &self.removed_capabilities {
+            writeln!(f, "- capability {} of {}", capability, api)?;
+        }
+        Ok(())
+    }
+}
+
+/// Compare the manifests of two output directories.
+pub fn diff_manifests(old_dir: &str, new_dir: &str) -> Result<ManifestDiff> {
+    if !Path::new(old_dir).is_dir() || !Path::new(new_dir).is_dir() {
+        return Err(Error::InvalidManifest(
+            "expected two manifest directories".to_string(),
+        ));
+    }
+    Ok(ManifestDiff::compute(
+        &ManifestSet::load(old_dir)?,
+        &ManifestSet::load(new_dir)?,
+    ))
+}
+
+// Functions called or reached by an API.
+fn calls(api: &ApiBehavior) -> BTreeSet<String> {
+    api.syscalls
+        .iter()
+        .chain(&api.transitive_calls)
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(flows: Value, features: Value) -> ManifestSet {
+        ManifestSet::from_json(&serde_json::json!({ "Public APIs flow": flows }), &features)
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = set(
+            serde_json::json!([
+                { "name": "writeOnDrive", "syscalls": ["fopen64", "fprintf", "fclose"] },
+                { "name": "turnLampOn", "syscalls": [] },
+            ]),
+            serde_json::json!({ "writeOnDrive": ["File Manipulation"] }),
+        );
+        let new = set(
+            serde_json::json!([
+                { "name": "writeOnDrive", "syscalls": ["fopen64", "fwrite", "fclose"] },
+                { "name": "accessNetwork", "syscalls": ["curl_easy_init"] },
+            ]),
+            serde_json::json!({
+                "writeOnDrive": ["File Manipulation"],
+                "accessNetwork": ["Network Access"],
+            }),
+        );
+        let diff = ManifestDiff::compute(&old, &new);
+
+        assert_eq!(diff.added_apis, ["accessNetwork"]);
+        assert_eq!(diff.removed_apis, ["turnLampOn"]);
+        assert_eq!(
+            diff.changed_flows,
+            [FlowChange {
+                api: "writeOnDrive".to_string(),
+                added_calls: vec!["fwrite".to_string()],
+                removed_calls: vec!["fprintf".to_string()],
+                reordered: false,
+            }]
+        );
+        assert_eq!(diff.new_syscalls, ["curl_easy_init", "fwrite"]);
+        assert_eq!(
+            diff.new_capabilities,
+            [("accessNetwork".to_string(), "Network Access".to_string())]
+        );
+        assert_eq!(
+            diff.to_json()["new capabilities"],
+            serde_json::json!({ "accessNetwork": ["Network Access"] })
+        );
+        assert!(diff
+            .to_string()
+            .contains("~ API writeOnDrive\n    + calls fwrite\n"));
+        assert_eq!(
+            diff.summary(),
+            "1 APIs added, 1 removed, 1 flows changed, 2 functions newly called, \
+             1 capabilities gained, 0 lost"
+        );
+        assert!(ManifestDiff::compute(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn test_reordered() {
+        let old = set(
+            serde_json::json!([{ "name": "f", "syscalls": ["open", "read"] }]),
+            serde_json::json!({}),
+        );
+        let new = set(
+            serde_json::json!([{ "name": "f", "syscalls": ["read", "open"] }]),
+            serde_json::json!({}),
+        );
+        let diff = ManifestDiff::compute(&old, &new);
+        assert!(diff.changed_flows[0].reordered);
+        assert!(diff.new_syscalls.is_empty());
+    }
+}
@@ -0,0 +1,228 @@
+#[cfg(feature = "native")]
+use capstone::Capstone;
+#[cfg(not(feature = "native"))]
+use iced_x86::{Decoder, DecoderOptions, Formatter, GasFormatter};
+
+#[cfg(feature = "native")]
+use crate::elf_utils::cs_init;
+use crate::error::Result;
+
+/// An x86-64 instruction decoded by a [`Disassembler`], in AT&T syntax.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Instruction {
+    /// The address of the instruction.
+    pub address: u64,
+    /// The length of the instruction, in bytes.
+    pub size: usize,
+    /// The mnemonic of the instruction, with its prefixes, e.g. `rep stosq`.
+    pub mnemonic: String,
+    /// The operands of the instruction, e.g. `0x2edf(%rip), %rax`.
+    pub op_str: String,
+}
+
+impl Instruction {
+    /// The name of the instruction: its mnemonic without its prefixes nor, for the calls,
+    /// jumps and `lea`, its size suffix, e.g. `call` for `callq`.
+    pub fn name(&self) -> &str {
+        let mnemonic = self.mnemonic.split_whitespace().last().unwrap_or_default();
+        match mnemonic.strip_suffix(['q', 'l', 'w']) {
+            Some(name @ ("call" | "jmp" | "lea")) => name,
+            _ => mnemonic,
+        }
+    }
+
+    /// The address of the instruction following this one.
+    pub fn next_addr(&self) -> u64 {
+        self.address + self.size as u64
+    }
+}
+
+/// The x86-64 disassembler of the analyses: Capstone with the `native` feature, the pure Rust
+/// iced-x86 otherwise, so that the analysis also compiles to `wasm32-unknown-unknown`.
+///
+/// Both print the instructions in the AT&T syntax of Capstone, which is what the analyses
+/// parse.
+pub struct Disassembler {
+    #[cfg(feature = "native")]
+    cs: Capstone,
+    #[cfg(not(feature = "native"))]
+    formatter: std::cell::RefCell<GasFormatter>,
+}
+
+impl Disassembler {
+    /// Create a disassembler.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`crate::error::Error::Capstone`] if Capstone cannot be initialized.
+    pub fn new() -> Result<Self> {
+        #[cfg(feature = "native")]
+        let disassembler = Self { cs: cs_init()? };
+        #[cfg(not(feature = "native"))]
+        let disassembler = Self {
+            formatter: std::cell::RefCell::new(gas_formatter()),
+        };
+        Ok(disassembler)
+    }
+
+    /// Disassemble code loaded at `addr`, up to its end or its first invalid instruction.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`crate::error::Error::Capstone`] if Capstone fails.
+    #[cfg(feature = "native")]
+    pub fn disassemble(&self, code: &[u8], addr: u64) -> Result<Vec<Instruction>> {
+        instructions_of(self.cs.disasm_all(code, addr))
+    }
+
+    /// Disassemble at most `count` instructions of code loaded at `addr`, up to its end or its
+    /// first invalid instruction, so that the disassembly of long code can be interleaved with
+    /// other work.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`crate::error::Error::Capstone`] if Capstone fails.
+    #[cfg(feature = "native")]
+    pub fn disassemble_count(
+        &self,
+        code: &[u8],
+        addr: u64,
+        count: usize,
+    ) -> Result<Vec<Instruction>> {
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+        instructions_of(self.cs.disasm_count(code, addr, count))
+    }
+
+    /// Disassemble code loaded at `addr`, up to its end or its first invalid instruction.
+    ///
+    /// # Errors
+    ///
+    /// Never fails with iced-x86.
+    #[cfg(not(feature = "native"))]
+    pub fn disassemble(&self, code: &[u8], addr: u64) -> Result<Vec<Instruction>> {
+        self.disassemble_count(code, addr, usize::MAX)
+    }
+
+    /// Disassemble at most `count` instructions of code loaded at `addr`, up to its end or its
+    /// first invalid instruction, so that the disassembly of long code can be interleaved with
+    /// other work.
+    ///
+    /// # Errors
+    ///
+    /// Never fails with iced-x86.
+    #[cfg(not(feature = "native"))]
+    pub fn disassemble_count(
+        &self,
+        code: &[u8],
+        addr: u64,
+        count: usize,
+    ) -> Result<Vec<Instruction>> {
+        let mut formatter = self.formatter.borrow_mut();
+        let mut decoder = Decoder::with_ip(64, code, addr, DecoderOptions::NONE);
+        let mut instructions = Vec::new();
+        while decoder.can_decode() && instructions.len() < count {
+            let insn = decoder.decode();
+            if insn.is_invalid() {
+                break;
+            }
+            let (mut mnemonic, mut op_str) = (String::new(), String::new());
+            formatter.format_mnemonic(&insn, &mut mnemonic);
+            formatter.format_all_operands(&insn, &mut op_str);
+            // Capstone leaves out the `notrack` prefix of jumps and the `rep` prefix of `ret`.
+            let mnemonic = match mnemonic.strip_prefix("notrack ") {
+                Some(jump) => jump.to_string(),
+                None => match mnemonic.strip_prefix("rep ret") {
+                    Some(suffix) => format!("ret{}", suffix),
+                    None => mnemonic,
+                },
+            };
+            instructions.push(Instruction {
+                address: insn.ip(),
+                size: insn.len(),
+                mnemonic,
+                op_str,
+            });
+        }
+        Ok(instructions)
+    }
+}
+
+// The instructions decoded by Capstone, or its error.
+#[cfg(feature = "native")]
+fn instructions_of(
+    instructions: capstone::CsResult<capstone::Instructions<'_>>,
+) -> Result<Vec<Instruction>> {
+    let instructions =
+        instructions.map_err(|error| crate::error::Error::Capstone(error.to_string()))?;
+    Ok(instructions
+        .iter()
+        .map(|insn| Instruction {
+            address: insn.address(),
+            size: insn.bytes().len(),
+            mnemonic: insn.mnemonic().unwrap_or_default().to_string(),
+            op_str: insn.op_str().unwrap_or_default().to_string(),
+        })
+        .collect())
+}
+
+// A formatter printing the AT&T syntax of Capstone: lowercase hexadecimal numbers with a `0x`
+// prefix, RIP-relative operands kept relative, a space after the commas of memory operands and
+// size suffixes on the mnemonics.
+#[cfg(not(feature = "native"))]
+fn gas_formatter() -> GasFormatter {
+    let mut formatter = GasFormatter::new();
+    let options = formatter.options_mut();
+    options.set_hex_prefix("0x");
+    options.set_hex_suffix("");
+    options.set_uppercase_hex(false);
+    options.set_space_after_operand_separator(true);
+    options.set_rip_relative_addresses(true);
+    options.set_branch_leading_zeros(false);
+    options.set_leading_zeros(false);
+    options.set_gas_show_mnemonic_size_suffix(true);
+    options.set_gas_space_after_memory_operand_comma(true);
+    options.set_show_useless_prefixes(true);
+    options.set_show_branch_size(false);
+    formatter
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf_utils::{code_bytes, read_elf_file};
+    use goblin::elf::Elf;
+
+    #[test]
+    fn test_disassembler() {
+        let disassembler = Disassembler::new().unwrap();
+        // lea 0x10(%rip), %rdi; call 0x1000; ret; then an invalid opcode.
+        let code = [
+            0x48, 0x8d, 0x3d, 0x10, 0x00, 0x00, 0x00, 0xe8, 0xf4, 0x0f, 0x00, 0x00, 0xc3, 0x06,
+        ];
+        let instructions = disassembler.disassemble(&code, 0x0).unwrap();
+        assert_eq!(instructions.len(), 3);
+        assert!(instructions[0].mnemonic.starts_with("lea"));
+        assert_eq!(instructions[0].op_str, "0x10(%rip), %rdi");
+        assert!(instructions[1].mnemonic.starts_with("call"));
+        assert_eq!(instructions[1].op_str, "0x1000");
+        assert_eq!(instructions[1].next_addr(), 0xc);
+        let first = disassembler.disassemble_count(&code, 0x0, 2).unwrap();
+        assert_eq!(first, instructions[..2]);
+
+        let buffer = read_elf_file("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        let main = elf
+            .syms
+            .iter()
+            .find(|sym| elf.strtab.get_at(sym.st_name) == Some("main"))
+            .unwrap();
+        let code = code_bytes(&elf, &buffer, main.st_value, main.st_value + main.st_size).unwrap();
+        let instructions = disassembler.disassemble(code, main.st_value).unwrap();
+        assert_eq!(
+            instructions.iter().map(|insn| insn.size).sum::<usize>(),
+            code.len()
+        );
+    }
+}
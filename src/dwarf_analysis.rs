@@ -1,13 +1,23 @@
-use std::{borrow, fs};
+use std::{borrow, fmt};
 
+use goblin::elf::Elf;
 use object::{Object, ObjectSection};
+use tracing::{debug, instrument};
 
-use crate::error;
+use crate::{
+    cleanup::{demangle_api_name, demangle_swift, is_rust_symbol},
+    error,
+    reader::{BinaryData, ReadMode},
+};
 use error::Result;
 
+// Strings of the Rust standard library kept in the binaries it is linked into.
+const RUST_STRINGS: [&[u8]; 3] = [b"/rustc/", b"panicked at", b"RUST_BACKTRACE"];
+
 /// Parse an ELF file to determine the programming language used.
 ///
 /// This function analyzes the Dwarf information in the ELF file to determine the programming language used.
+/// The language of a binary without DWARF information can be guessed with [`infer_language`].
 ///
 /// # Arguments
 ///
@@ -17,10 +27,14 @@ use error::Result;
 ///
 /// Returns a `Result` containing the programming language used, if successfully determined.
 /// Analysis example from: <https://github.com/gimli-rs/gimli/blob/master/crates/examples/src/bin/simple.rs>
+#[instrument]
 pub fn dwarf_analysis(file_path: &str) -> Result<String> {
-    let file = fs::File::open(file_path)?;
-    let mmap = unsafe { memmap2::Mmap::map(&file)? };
-    let object = object::File::parse(&*mmap)?;
+    dwarf_language(&BinaryData::open(file_path, ReadMode::Mmap)?)
+}
+
+/// Determine the programming language of an ELF file already read, see [`dwarf_analysis`].
+pub fn dwarf_language(buffer: &[u8]) -> Result<String> {
+    let object = object::File::parse(buffer)?;
     let endian = if object.is_little_endian() {
         gimli::RunTimeEndian::Little
     } else {
@@ -28,9 +42,138 @@ pub fn dwarf_analysis(file_path: &str) -> Result<String> {
     };
 
     let lang = analyze_elf_file(&object, endian)?;
+    debug!(lang, "language detected");
     Ok(lang.to_string())
 }
 
+/// Guess the programming language of an ELF file without DWARF information.
+///
+/// Go is told by its `.gopclntab` or `.go.buildinfo` section, Swift by its manglings or the
+/// `libswiftCore.so` it links, Rust by its `.rustc` section, its manglings, its panic runtime or
+/// the strings of its standard library, C++ by the Itanium manglings or the `libstdc++.so` or
+/// `libc++.so` it links, and C by the C library alone. A binary mixing Rust and C++ takes the
+/// language of most of its mangled symbols, so that they are demangled with the right scheme.
+///
+/// # Arguments
+///
+/// * `elf` - The ELF file structure.
+/// * `buffer` - The content of the ELF file.
+///
+/// # Returns
+///
+/// Returns the language as DWARF names it, without its `DW_LANG_` prefix, e.g. `Rust` or
+/// `C_plus_plus`, or `None` when nothing tells it.
+pub fn infer_language(elf: &Elf, buffer: &[u8]) -> Option<&'static str> {
+    let sections: Vec<&str> = elf
+        .section_headers
+        .iter()
+        .filter_map(|section| elf.shdr_strtab.get_at(section.sh_name))
+        .collect();
+    let symbols: Vec<&str> = elf
+        .syms
+        .iter()
+        .filter_map(|sym| elf.strtab.get_at(sym.st_name))
+        .chain(
+            elf.dynsyms
+                .iter()
+                .filter_map(|sym| elf.dynstrtab.get_at(sym.st_name)),
+        )
+        .filter(|name| !name.is_empty())
+        .collect();
+    let links = |prefix: &str| elf.libraries.iter().any(|lib| lib.starts_with(prefix));
+
+    let language = if sections.contains(&".gopclntab")
+        || sections.contains(&".go.buildinfo")
+        || symbols.contains(&"runtime.main")
+    {
+        Some("Go")
+    } else if links("libswiftCore.so") || symbols.iter().any(|name| demangle_swift(name).is_some())
+    {
+        Some("Swift")
+    } else {
+        let rust = symbols.iter().filter(|name| is_rust_symbol(name)).count();
+        let cpp = symbols
+            .iter()
+            .filter(|name| name.starts_with("_Z") && !is_rust_symbol(name))
+            .count();
+        let rust_runtime = || {
+            sections.contains(&".rustc")
+                || symbols.contains(&"rust_begin_unwind")
+                || RUST_STRINGS
+                    .iter()
+                    .any(|marker| buffer.windows(marker.len()).any(|window| window == *marker))
+        };
+        if rust >= cpp && (rust > 0 || rust_runtime()) {
+            Some("Rust")
+        } else if cpp > 0 || links("libstdc++.so") || links("libc++.so") {
+            Some("C_plus_plus")
+        } else if links("libc.so") || symbols.contains(&"__libc_start_main") {
+            Some("C")
+        } else {
+            None
+        }
+    };
+    debug!(?language, "language inferred without DWARF");
+    language
+}
+
+/// List the functions of the DWARF information of an ELF file that were only inlined: those
+/// defined in the source, but of no code of their own.
+///
+/// # Arguments
+///
+/// * `buffer` - The content of the ELF file.
+///
+/// # Returns
+///
+/// Returns the names of the functions, sorted, none when there is no DWARF information.
+pub fn inlined_functions(buffer: &[u8]) -> Result<Vec<String>> {
+    let object = object::File::parse(buffer)?;
+    let endian = if object.is_little_endian() {
+        gimli::RunTimeEndian::Little
+    } else {
+        gimli::RunTimeEndian::Big
+    };
+    let load_section = |id: gimli::SectionId| -> Result<borrow::Cow<[u8]>> {
+        Ok(match object.section_by_name(id.name()) {
+            Some(section) => section
+                .uncompressed_data()
+                .unwrap_or(borrow::Cow::Borrowed(&[][..])),
+            None => borrow::Cow::Borrowed(&[][..]),
+        })
+    };
+    let dwarf_cow = gimli::DwarfSections::load(&load_section)?;
+    let dwarf = dwarf_cow.borrow(|section| gimli::EndianSlice::new(section, endian));
+
+    let mut names = Vec::new();
+    let mut iter = dwarf.units();
+    while let Some(header) = iter.next()? {
+        let unit = dwarf.unit(header)?;
+        let mut entries = unit.entries();
+        while let Some((_, entry)) = entries.next_dfs()? {
+            // The declarations are prototypes, the functions of code have an address.
+            if entry.tag() != gimli::DW_TAG_subprogram
+                || entry.attr(gimli::DW_AT_declaration)?.is_some()
+                || entry.attr(gimli::DW_AT_low_pc)?.is_some()
+                || entry.attr(gimli::DW_AT_ranges)?.is_some()
+            {
+                continue;
+            }
+            if let Some(name) = entry.attr_value(gimli::DW_AT_name)? {
+                names.push(
+                    dwarf
+                        .attr_string(&unit, name)?
+                        .to_string_lossy()
+                        .into_owned(),
+                );
+            }
+        }
+    }
+    names.sort();
+    names.dedup();
+    Ok(names)
+}
+
 // Parse the dwarf format in the .debug_info section. Language attributes table available here: https://dwarfstd.org/languages.html
 fn analyze_elf_file<'b>(
     object: &'b object::File<'b>,
@@ -45,7 +188,7 @@ fn analyze_elf_file<'b>(
         }
     };
     let mut lang = "";
-    let dwarf_cow = gimli::Dwarf::load(&load_section)?;
+    let dwarf_cow = gimli::DwarfSections::load(&load_section)?;
     let borrow_section: &dyn for<'a> Fn(
         &'a borrow::Cow<[u8]>,
     ) -> gimli::EndianSlice<'a, gimli::RunTimeEndian> =
@@ -65,6 +208,11 @@ fn analyze_elf_file<'b>(
                     _ => continue,
                 };
                 if let Some(name) = language.static_string() {
+                    debug!(
+                        unit = ?unit.header.offset().as_debug_info_offset().map(|offset| offset.0),
+                        language = name,
+                        "compilation unit"
+                    );
                     if lang.contains(name) {
                         return Ok(lang);
                     }
@@ -76,6 +224,173 @@ fn analyze_elf_file<'b>(
     Ok(lang)
 }
 
+/// The source location of an address of a binary, as told by `addr2line`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SourceLocation {
+    /// The address resolved.
+    pub address: u64,
+    /// The demangled name of the function of the symbol table holding the address.
+    pub function: Option<String>,
+    /// The offset of the address from the start of the function.
+    pub offset: u64,
+    /// The source file of the instruction at the address, with its directory.
+    pub file: Option<String>,
+    /// The line of the instruction in the source file.
+    pub line: Option<u64>,
+    /// The column of the instruction in the line.
+    pub column: Option<u64>,
+}
+
+impl SourceLocation {
+    /// Convert the location to JSON.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "address": format!("{:#x}", self.address),
+            "function": self.function,
+            "offset": format!("{:#x}", self.offset),
+            "file": self.file,
+            "line": self.line,
+            "column": self.column,
+        })
+    }
+}
+
+impl fmt::Display for SourceLocation {
+    // Print the location as `addr2line -f -C` does, on one line.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:#x}: ", self.address)?;
+        match &self.function {
+            Some(function) if self.offset > 0 => write!(f, "{}+{:#x}", function, self.offset)?,
+            Some(function) => write!(f, "{}", function)?,
+            None => write!(f, "??")?,
+        }
+        write!(
+            f,
+            " at {}:{}",
+            self.file.as_deref().unwrap_or("??"),
+            self.line.unwrap_or(0)
+        )?;
+        match self.column {
+            Some(column) => write!(f, ":{}", column),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Map addresses of a binary to their function, source file and line, as `addr2line` does.
+///
+/// The function is the one of the symbol table holding the address, and the file and line come
+/// from the DWARF line tables; they are `None` when the address is in no function, or when the
+/// binary has no line information for it.
+///
+/// # Arguments
+///
+/// * `buffer` - The content of the ELF file.
+/// * `addresses` - The virtual addresses to resolve, e.g. of a crash, for the load address of
+///   the binary in its program headers.
+///
+/// # Returns
+///
+/// Returns a `Result` containing the location of each address, in the order given.
+pub fn resolve_addresses(buffer: &[u8], addresses: &[u64]) -> Result<Vec<SourceLocation>> {
+    let elf = Elf::parse(buffer)?;
+    let mut locations: Vec<SourceLocation> = addresses
+        .iter()
+        .map(|&address| {
+            let symbol = elf.syms.iter().find(|sym| {
+                sym.is_function()
+                    && sym.st_value <= address
+                    && address < sym.st_value.saturating_add(sym.st_size)
+            });
+            SourceLocation {
+                address,
+                function: symbol
+                    .and_then(|sym| elf.strtab.get_at(sym.st_name))
+                    .map(demangle_api_name),
+                offset: symbol.map_or(0, |sym| address - sym.st_value),
+                ..SourceLocation::default()
+            }
+        })
+        .collect();
+
+    let object = object::File::parse(buffer)?;
+    let endian = if object.is_little_endian() {
+        gimli::RunTimeEndian::Little
+    } else {
+        gimli::RunTimeEndian::Big
+    };
+    let load_section = |id: gimli::SectionId| -> Result<borrow::Cow<[u8]>> {
+        Ok(match object.section_by_name(id.name()) {
+            Some(section) => section
+                .uncompressed_data()
+                .unwrap_or(borrow::Cow::Borrowed(&[][..])),
+            None => borrow::Cow::Borrowed(&[][..]),
+        })
+    };
+    let dwarf_cow = gimli::DwarfSections::load(&load_section)?;
+    let dwarf = dwarf_cow.borrow(|section| gimli::EndianSlice::new(section, endian));
+
+    let mut iter = dwarf.units();
+    while let Some(header) = iter.next()? {
+        let unit = dwarf.unit(header)?;
+        let Some(program) = unit.line_program.clone() else {
+            continue;
+        };
+        // Each row holds from its address up to the one of the next row of its sequence. The
+        // sequences at address 0 are of the functions discarded by the linker.
+        let mut rows = program.rows();
+        let (mut previous, mut sequence): (Option<(u64, gimli::LineRow)>, Option<u64>) =
+            (None, None);
+        while let Some((header, row)) = rows.next_row()? {
+            let start = *sequence.get_or_insert(row.address());
+            if let Some((start, line_row)) = previous.take() {
+                for location in locations
+                    .iter_mut()
+                    .filter(|location| location.file.is_none())
+                    .filter(|location| (start..row.address()).contains(&location.address))
+                {
+                    location.file = source_file(&dwarf, &unit, header, &line_row);
+                    location.line = line_row.line().map(|line| line.get());
+                    location.column = match line_row.column() {
+                        gimli::ColumnType::LeftEdge => None,
+                        gimli::ColumnType::Column(column) => Some(column.get()),
+                    };
+                }
+            }
+            if row.end_sequence() {
+                sequence = None;
+            } else if start != 0 {
+                previous = Some((row.address(), *row));
+            }
+        }
+    }
+    Ok(locations)
+}
+
+// The path of the source file of a row of a line table, joined to its directory and to the
+// compilation directory of its unit when they are relative.
+fn source_file<R: gimli::Reader>(
+    dwarf: &gimli::Dwarf<R>,
+    unit: &gimli::Unit<R>,
+    header: &gimli::LineProgramHeader<R>,
+    row: &gimli::LineRow,
+) -> Option<String> {
+    let file = row.file(header)?;
+    let text = |value| -> Option<String> {
+        let name = dwarf.attr_string(unit, value).ok()?;
+        Some(name.to_string_lossy().ok()?.into_owned())
+    };
+    let mut path = std::path::PathBuf::new();
+    if let Some(dir) = &unit.comp_dir {
+        path.push(dir.to_string_lossy().ok()?.as_ref());
+    }
+    if let Some(dir) = file.directory(header).and_then(text) {
+        path.push(dir);
+    }
+    path.push(text(file.path_name())?);
+    Some(path.to_string_lossy().into_owned())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -89,11 +404,63 @@ mod tests {
 
     #[test]
     fn test_analyze_elf_file() {
-        let file = fs::File::open("./tests/elf_file/fake-firmware-rust-dynamic").unwrap();
-        let mmap = unsafe { memmap2::Mmap::map(&file).unwrap() };
-        let object = object::File::parse(&*mmap).unwrap();
+        let data = BinaryData::open(
+            "./tests/elf_file/fake-firmware-rust-dynamic",
+            ReadMode::Read,
+        )
+        .unwrap();
+        let object = object::File::parse(&*data).unwrap();
         let endian = gimli::RunTimeEndian::Little;
         let result = analyze_elf_file(&object, endian).unwrap();
         assert_eq!(result, "DW_LANG_Rust");
     }
+
+    #[test]
+    fn test_infer_language() {
+        let language = |path: &str| {
+            let data = std::fs::read(path).unwrap();
+            infer_language(&Elf::parse(&data).unwrap(), &data)
+        };
+        assert_eq!(
+            language("./tests/elf_file/fake-firmware-rust-dynamic-stripped"),
+            Some("Rust")
+        );
+        assert_eq!(
+            language("./tests/elf_file/minimal-fake-firmware-cpp-static"),
+            Some("C_plus_plus")
+        );
+        assert_eq!(
+            language("./tests/elf_file/minimal-fake-firmware-c-static"),
+            Some("C")
+        );
+        assert_eq!(
+            language("./tests/elf_file/minimal-fake-firmware-cortex-m"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_resolve_addresses() {
+        let data = std::fs::read("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let locations = resolve_addresses(&data, &[0x1b208, 0x10]).unwrap();
+        let write = &locations[0];
+        assert_eq!(write.function.as_deref(), Some("writeOnDrive"));
+        assert_eq!(write.offset, 8);
+        assert!(write.file.as_deref().unwrap().ends_with("src/feature.c"));
+        assert_eq!((write.line, write.column), (Some(6), Some(18)));
+        assert!(write
+            .to_string()
+            .starts_with("0x1b208: writeOnDrive+0x8 at /"));
+        assert_eq!(write.to_json()["line"], 6);
+        assert_eq!(locations[1].to_string(), "0x10: ?? at ??:0");
+    }
+
+    #[test]
+    fn test_inlined_functions() {
+        let data = std::fs::read("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let inlined = inlined_functions(&data).unwrap();
+        assert!(inlined.iter().any(|name| name == "_mm_getcsr"));
+        assert!(!inlined.iter().any(|name| name == "writeOnDrive"));
+        assert!(inlined.windows(2).all(|pair| pair[0] < pair[1]));
+    }
 }
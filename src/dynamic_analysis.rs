@@ -0,0 +1,664 @@
+use std::{collections::BTreeSet, time::Duration};
+
+use serde_json::{json, Map, Value};
+
+use crate::error::Result;
+
+/// How a binary is run for its dynamic analysis.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DynamicOptions {
+    /// How long the binary may run before it is killed, with every process it started.
+    pub duration: Duration,
+    /// The arguments given to the binary.
+    pub args: Vec<String>,
+    /// Let the binary reach the network, which is otherwise cut off by running it in an empty
+    /// network namespace.
+    pub allow_network: bool,
+    /// The QEMU user-mode emulator running the binaries of another architecture than the host,
+    /// `qemu-<arch>` from the `PATH` when `None`, e.g. `qemu-arm`.
+    pub emulator: Option<String>,
+    /// The directory of the libraries of the emulated architecture, where the emulator looks
+    /// for the dynamic loader, e.g. `/usr/arm-linux-gnueabihf`.
+    pub sysroot: Option<String>,
+}
+
+impl Default for DynamicOptions {
+    fn default() -> Self {
+        Self {
+            duration: Duration::from_secs(10),
+            args: Vec::new(),
+            allow_network: false,
+            emulator: None,
+            sysroot: None,
+        }
+    }
+}
+
+/// The syscalls made by one thread of a traced binary.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ThreadTrace {
+    /// The thread, numbered from 1 in the order the threads and processes were created. Under
+    /// emulation, the threads of a process are not told apart.
+    pub thread: usize,
+    /// The syscalls made, by name, in the order of their first call.
+    pub syscalls: Vec<String>,
+    /// The number of syscalls made.
+    pub calls: usize,
+}
+
+/// The behavior of a binary observed while it ran.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ObservedBehavior {
+    /// The syscalls of each thread, and of each process started by the binary.
+    pub threads: Vec<ThreadTrace>,
+    /// The exit status of the binary, `None` when killed by a signal or at the end of the run.
+    pub exit_code: Option<i32>,
+    /// Whether the binary was still running at the end of the run.
+    pub timed_out: bool,
+    /// The emulator the binary was run with, of another architecture than the host.
+    pub emulator: Option<String>,
+    /// Why the emulator could not run the binary, nothing being observed.
+    pub error: Option<String>,
+}
+
+impl ObservedBehavior {
+    /// The syscalls made by any thread, by name.
+    pub fn syscalls(&self) -> BTreeSet<&str> {
+        self.threads
+            .iter()
+            .flat_map(|thread| thread.syscalls.iter().map(String::as_str))
+            .collect()
+    }
+
+    /// The `observed` section of the flow call manifest.
+    pub fn to_json(&self) -> Value {
+        let threads: Vec<Value> = self
+            .threads
+            .iter()
+            .map(|thread| {
+                json!({
+                    "thread": thread.thread,
+                    "syscalls": thread.syscalls,
+                    "calls": thread.calls,
+                })
+            })
+            .collect();
+        let mut section = Map::new();
+        section.insert("syscalls".to_string(), json!(self.syscalls()));
+        section.insert("threads".to_string(), Value::Array(threads));
+        section.insert("exit code".to_string(), json!(self.exit_code));
+        section.insert("timed out".to_string(), json!(self.timed_out));
+        if let Some(emulator) = &self.emulator {
+            section.insert("emulator".to_string(), json!(emulator));
+        }
+        if let Some(error) = &self.error {
+            section.insert("error".to_string(), json!(error));
+        }
+        Value::Object(section)
+    }
+}
+
+/// Run a binary and record the syscalls of each of its threads: under ptrace for an x86-64
+/// binary on an x86-64 host, else under the QEMU user-mode emulator of its architecture.
+///
+/// The binary is copied to an empty scratch directory and run from it, with an empty
+/// environment, no standard input nor output and, unless `allow_network`, in an empty network
+/// namespace. This confines a well-behaved binary, not a hostile one: run untrusted firmware in
+/// a virtual machine.
+///
+/// # Arguments
+///
+/// * `name` - The file name of the binary.
+/// * `data` - The content of the binary.
+/// * `options` - How long to run the binary, with which arguments and emulator.
+///
+/// # Returns
+///
+/// Returns a `Result` containing the behavior observed. When the emulator cannot run the
+/// binary, e.g. it is not installed or the sysroot lacks the dynamic loader, nothing is
+/// observed and the behavior holds the error.
+///
+/// # Errors
+///
+/// Returns [`crate::error::Error::DynamicAnalysis`] if the host cannot run the binary, e.g. it
+/// is not a Linux host or QEMU cannot emulate the architecture, or the binary cannot be traced.
+pub fn trace(name: &str, data: &[u8], options: &DynamicOptions) -> Result<ObservedBehavior> {
+    #[cfg(all(feature = "native", target_os = "linux"))]
+    return tracer::run(name, data, options);
+    #[cfg(not(all(feature = "native", target_os = "linux")))]
+    {
+        let _ = (name, data, options);
+        Err(crate::error::Error::DynamicAnalysis(
+            "running a binary needs a Linux host".to_string(),
+        ))
+    }
+}
+
+#[cfg(all(feature = "native", target_os = "linux"))]
+mod tracer {
+    use std::{
+        collections::{BTreeSet, HashMap},
+        env, fs,
+        io::ErrorKind,
+        os::unix::{fs::PermissionsExt, process::CommandExt},
+        path::Path,
+        process::{self, Command, Stdio},
+        sync::{
+            atomic::{AtomicBool, AtomicUsize, Ordering},
+            mpsc, Arc,
+        },
+        thread,
+        time::Duration,
+    };
+
+    use goblin::elf::{header, Elf};
+    use nix::{
+        sched::{unshare, CloneFlags},
+        sys::signal::{kill, Signal},
+        unistd::{setpgid, Pid},
+    };
+    use tracing::warn;
+
+    use super::{DynamicOptions, ObservedBehavior, ThreadTrace};
+    use crate::error::{Error, Result};
+
+    // Number of binaries run by the process, to name their scratch directories.
+    static TRACES: AtomicUsize = AtomicUsize::new(0);
+
+    // The log of the syscalls written by the emulator, in the scratch directory.
+    const EMULATOR_LOG: &str = ".strace";
+
+    // The syscalls of a thread, unique in the order of their first call.
+    struct Thread {
+        seen: BTreeSet<String>,
+        trace: ThreadTrace,
+    }
+
+    impl Thread {
+        // A thread numbered `number`.
+        fn new(number: usize) -> Self {
+            Self {
+                seen: BTreeSet::new(),
+                trace: ThreadTrace {
+                    thread: number,
+                    ..ThreadTrace::default()
+                },
+            }
+        }
+
+        // Record a call of the syscall `name`.
+        fn call(&mut self, name: &str) {
+            self.trace.calls += 1;
+            if self.seen.insert(name.to_string()) {
+                self.trace.syscalls.push(name.to_string());
+            }
+        }
+    }
+
+    // Run a binary from a scratch directory and trace it.
+    pub(super) fn run(
+        name: &str,
+        data: &[u8],
+        options: &DynamicOptions,
+    ) -> Result<ObservedBehavior> {
+        let elf = Elf::parse(data)?;
+        let native = cfg!(target_arch = "x86_64") && elf.header.e_machine == header::EM_X86_64;
+        let target = match native {
+            true => None,
+            false => Some(qemu_target(&elf).ok_or_else(|| {
+                Error::DynamicAnalysis(format!(
+                    "QEMU cannot emulate {} binaries",
+                    header::machine_to_str(elf.header.e_machine)
+                ))
+            })?),
+        };
+
+        let dir = env::temp_dir().join(format!(
+            "manifest-trace-{}-{}",
+            process::id(),
+            TRACES.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&dir)?;
+        let file_name = Path::new(name).file_name().unwrap_or("binary".as_ref());
+        let binary = dir.join(file_name);
+        fs::write(&binary, data)?;
+        fs::set_permissions(&binary, fs::Permissions::from_mode(0o700))?;
+        let traced = match target {
+            Some(target) => emulated::run(&dir, &binary, target, options),
+            #[cfg(target_arch = "x86_64")]
+            None => ptraced::run(&dir, &binary, options),
+            #[cfg(not(target_arch = "x86_64"))]
+            None => unreachable!("x86-64 binaries are emulated on other hosts"),
+        };
+        fs::remove_dir_all(&dir)?;
+        traced
+    }
+
+    // The error of an operation of the analysis that failed.
+    fn failed(what: &str, error: &dyn std::fmt::Display) -> Error {
+        Error::DynamicAnalysis(format!("{}: {}", what, error))
+    }
+
+    // Run a command from the scratch directory `dir`, with an empty environment, no standard
+    // input, in a process group of its own and, unless the network is allowed, in an empty
+    // network namespace; `traced` stops it on its exec under ptrace. `what` runs is named in the
+    // errors.
+    fn spawn(
+        what: &str,
+        mut command: Command,
+        dir: &Path,
+        options: &DynamicOptions,
+        traced: bool,
+    ) -> Result<process::Child> {
+        let isolate = !options.allow_network;
+        command
+            .current_dir(dir)
+            .env_clear()
+            .stdin(Stdio::null())
+            .stdout(Stdio::null());
+        // Only syscalls are made between the fork and the exec.
+        unsafe {
+            command.pre_exec(move || {
+                setpgid(Pid::from_raw(0), Pid::from_raw(0))?;
+                if isolate {
+                    unshare(CloneFlags::CLONE_NEWUSER | CloneFlags::CLONE_NEWNET)?;
+                }
+                #[cfg(target_arch = "x86_64")]
+                if traced {
+                    nix::sys::ptrace::traceme()?;
+                }
+                Ok(())
+            });
+        }
+        #[cfg(not(target_arch = "x86_64"))]
+        let _ = traced;
+        command.spawn().map_err(|error| match error.kind() {
+            ErrorKind::PermissionDenied if isolate => failed(
+                "the network could not be cut off, allow it to run the binary",
+                &error,
+            ),
+            _ => failed(&format!("{} could not be run", what), &error),
+        })
+    }
+
+    // Run `wait` until the process group of `root` is gone, killing it once `duration` is up.
+    // Returns the result of `wait` and whether the group was killed.
+    fn with_deadline<T>(root: Pid, duration: Duration, wait: impl FnOnce() -> T) -> (T, bool) {
+        let timed_out = Arc::new(AtomicBool::new(false));
+        let (done, stop) = mpsc::channel::<()>();
+        let killer = {
+            let timed_out = timed_out.clone();
+            thread::spawn(move || {
+                if stop.recv_timeout(duration).is_err() {
+                    timed_out.store(true, Ordering::SeqCst);
+                    let _ = kill(Pid::from_raw(-root.as_raw()), Signal::SIGKILL);
+                }
+            })
+        };
+        let result = wait();
+        let _ = done.send(());
+        let _ = killer.join();
+        (result, timed_out.load(Ordering::SeqCst))
+    }
+
+    // The name of the QEMU user-mode target of a binary, e.g. `arm` for `qemu-arm`.
+    fn qemu_target(elf: &Elf) -> Option<&'static str> {
+        let (is_64, little) = (elf.is_64, elf.little_endian);
+        let target = match elf.header.e_machine {
+            header::EM_X86_64 => "x86_64",
+            header::EM_386 => "i386",
+            header::EM_ARM if little => "arm",
+            header::EM_ARM => "armeb",
+            header::EM_AARCH64 if little => "aarch64",
+            header::EM_AARCH64 => "aarch64_be",
+            header::EM_MIPS => match (is_64, little) {
+                (false, false) => "mips",
+                (false, true) => "mipsel",
+                (true, false) => "mips64",
+                (true, true) => "mips64el",
+            },
+            header::EM_PPC => "ppc",
+            header::EM_PPC64 if little => "ppc64le",
+            header::EM_PPC64 => "ppc64",
+            header::EM_RISCV if is_64 => "riscv64",
+            header::EM_RISCV => "riscv32",
+            _ => return None,
+        };
+        Some(target)
+    }
+
+    // Read the syscalls of each process from a log of `qemu -strace`, whose lines start with
+    // the process id and the syscall: `1234 openat(AT_FDCWD,"/etc/passwd",O_RDONLY) = 3`.
+    pub(super) fn parse_qemu_log(log: &str) -> Vec<ThreadTrace> {
+        let mut processes: HashMap<&str, Thread> = HashMap::new();
+        for line in log.lines() {
+            let Some((pid, call)) = line.split_once(' ') else {
+                continue;
+            };
+            if pid.is_empty() || !pid.bytes().all(|byte| byte.is_ascii_digit()) {
+                continue;
+            }
+            let name = match call.strip_prefix("Unknown syscall ") {
+                Some(number) => format!("syscall_{}", number.trim()),
+                None => match call.split_once('(') {
+                    Some((name, _))
+                        if !name.is_empty()
+                            && name
+                                .bytes()
+                                .all(|byte| byte.is_ascii_alphanumeric() || byte == b'_') =>
+                    {
+                        name.to_string()
+                    }
+                    // Signals, and the results of the syscalls printed apart.
+                    _ => continue,
+                },
+            };
+            let number = processes.len() + 1;
+            processes
+                .entry(pid)
+                .or_insert_with(|| Thread::new(number))
+                .call(&name);
+        }
+        let mut threads: Vec<ThreadTrace> = processes
+            .into_values()
+            .map(|process| process.trace)
+            .collect();
+        threads.sort_by_key(|thread| thread.thread);
+        threads
+    }
+
+    mod emulated {
+        use super::*;
+
+        // Run a binary of another architecture under `qemu-<target> -strace`; when the
+        // emulator cannot run it, nothing is observed and the behavior holds the error.
+        pub(in super::super) fn run(
+            dir: &Path,
+            binary: &Path,
+            target: &str,
+            options: &DynamicOptions,
+        ) -> Result<ObservedBehavior> {
+            let emulator = options
+                .emulator
+                .clone()
+                .unwrap_or_else(|| format!("qemu-{}", target));
+            let log = dir.join(EMULATOR_LOG);
+            let mut command = Command::new(&emulator);
+            command.arg("-strace").arg("-D").arg(&log);
+            if let Some(sysroot) = &options.sysroot {
+                command.arg("-L").arg(sysroot);
+            }
+            command
+                .arg(binary)
+                .args(&options.args)
+                .stderr(Stdio::piped());
+            let unavailable = |error: String| {
+                warn!("{}: the binary could not be emulated: {}", emulator, error);
+                Ok(ObservedBehavior {
+                    emulator: Some(emulator.clone()),
+                    error: Some(error),
+                    ..ObservedBehavior::default()
+                })
+            };
+            let child = match spawn("the emulator", command, dir, options, false) {
+                Ok(child) => child,
+                Err(Error::DynamicAnalysis(error)) => return unavailable(error),
+                Err(error) => return Err(error),
+            };
+            let root = Pid::from_raw(child.id() as i32);
+            let (output, timed_out) =
+                with_deadline(root, options.duration, || child.wait_with_output());
+            // The processes the binary left behind.
+            let _ = kill(Pid::from_raw(-root.as_raw()), Signal::SIGKILL);
+            let output = output.map_err(|error| failed("the emulator failed", &error))?;
+            let threads = parse_qemu_log(&fs::read_to_string(&log).unwrap_or_default());
+            if threads.is_empty() && !timed_out {
+                // The emulator reports why it could not load the binary last.
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                let error = stderr.lines().rev().find(|line| !line.trim().is_empty());
+                return unavailable(
+                    error
+                        .unwrap_or("the emulator exited before the binary ran")
+                        .to_string(),
+                );
+            }
+            Ok(ObservedBehavior {
+                threads,
+                exit_code: output.status.code(),
+                timed_out,
+                emulator: Some(emulator),
+                error: None,
+            })
+        }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    mod ptraced {
+        use std::io;
+
+        use nix::{
+            errno::Errno,
+            sys::{
+                ptrace,
+                wait::{waitpid, WaitPidFlag, WaitStatus},
+            },
+        };
+        use syscalls::Sysno;
+
+        use super::*;
+
+        // A thread being traced, between the entry and the exit of its syscalls.
+        struct Traced {
+            in_syscall: bool,
+            thread: Thread,
+        }
+
+        // Start the binary stopped under ptrace, then trace it until it exits or the time is
+        // up.
+        pub(in super::super) fn run(
+            dir: &Path,
+            binary: &Path,
+            options: &DynamicOptions,
+        ) -> Result<ObservedBehavior> {
+            let mut command = Command::new(binary);
+            command.args(&options.args).stderr(Stdio::null());
+            let child = spawn("the binary", command, dir, options, true)?;
+            let root = Pid::from_raw(child.id() as i32);
+
+            // The binary stops on its exec, before running any instruction.
+            waitpid(root, Some(WaitPidFlag::__WALL)).map_err(|error| failed("waitpid", &error))?;
+            let ptrace_options = ptrace::Options::PTRACE_O_TRACESYSGOOD
+                | ptrace::Options::PTRACE_O_TRACECLONE
+                | ptrace::Options::PTRACE_O_TRACEFORK
+                | ptrace::Options::PTRACE_O_TRACEVFORK
+                | ptrace::Options::PTRACE_O_EXITKILL;
+            if let Err(error) =
+                ptrace::setoptions(root, ptrace_options).and_then(|_| ptrace::syscall(root, None))
+            {
+                let _ = kill(root, Signal::SIGKILL);
+                let _ = waitpid(root, None);
+                return Err(failed("the binary could not be traced", &error));
+            }
+
+            let (observed, timed_out) = with_deadline(root, options.duration, || trace_group(root));
+            let (threads, exit_code) =
+                observed.map_err(|error| failed("tracing failed", &error))?;
+            Ok(ObservedBehavior {
+                threads,
+                exit_code,
+                timed_out,
+                ..ObservedBehavior::default()
+            })
+        }
+
+        // Trace the processes of the group of `root` until they are all gone, returning the
+        // syscalls of each thread and the exit status of `root`.
+        fn trace_group(root: Pid) -> io::Result<(Vec<ThreadTrace>, Option<i32>)> {
+            let mut threads: HashMap<Pid, Traced> = HashMap::new();
+            let mut finished = Vec::new();
+            let mut exit_code = None;
+            let track = |threads: &mut HashMap<Pid, Traced>, finished: &Vec<ThreadTrace>, tid| {
+                let number = threads.len() + finished.len() + 1;
+                threads.entry(tid).or_insert_with(|| Traced {
+                    in_syscall: false,
+                    thread: Thread::new(number),
+                });
+            };
+            track(&mut threads, &finished, root);
+            loop {
+                let group = Pid::from_raw(-root.as_raw());
+                let status = match waitpid(group, Some(WaitPidFlag::__WALL)) {
+                    Ok(status) => status,
+                    Err(Errno::EINTR) => continue,
+                    Err(Errno::ECHILD) => break,
+                    Err(error) => return Err(error.into()),
+                };
+                match status {
+                    WaitStatus::PtraceSyscall(tid) => {
+                        track(&mut threads, &finished, tid);
+                        let traced = threads.get_mut(&tid).expect("tracked thread");
+                        traced.in_syscall = !traced.in_syscall;
+                        if traced.in_syscall {
+                            if let Ok(regs) = ptrace::getregs(tid) {
+                                traced.thread.call(&syscall_name(regs.orig_rax));
+                            }
+                        }
+                        let _ = ptrace::syscall(tid, None);
+                    }
+                    WaitStatus::PtraceEvent(tid, _, _) => {
+                        // The new thread or process is traced from now on, stopping first with
+                        // a SIGSTOP.
+                        let _ = ptrace::syscall(tid, None);
+                    }
+                    WaitStatus::Stopped(tid, signal) => {
+                        let new = !threads.contains_key(&tid);
+                        track(&mut threads, &finished, tid);
+                        let signal = match (new, signal) {
+                            (true, Signal::SIGSTOP) => None,
+                            _ => Some(signal),
+                        };
+                        let _ = ptrace::syscall(tid, signal);
+                    }
+                    WaitStatus::Exited(tid, code) => {
+                        if tid == root {
+                            exit_code = Some(code);
+                        }
+                        finished.extend(threads.remove(&tid).map(|traced| traced.thread.trace));
+                    }
+                    WaitStatus::Signaled(tid, _, _) => {
+                        finished.extend(threads.remove(&tid).map(|traced| traced.thread.trace));
+                    }
+                    _ => {}
+                }
+            }
+            // The processes that left the group are still stopped under ptrace.
+            for (tid, traced) in threads {
+                let _ = kill(tid, Signal::SIGKILL);
+                let _ = waitpid(tid, Some(WaitPidFlag::__WALL));
+                finished.push(traced.thread.trace);
+            }
+            finished.sort_by_key(|thread| thread.thread);
+            Ok((finished, exit_code))
+        }
+
+        // The name of an x86-64 syscall, e.g. `openat`.
+        fn syscall_name(number: u64) -> String {
+            match Sysno::new(number as usize) {
+                Some(sysno) => sysno.name().to_string(),
+                None => format!("syscall_{}", number),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    // Run with `cargo test -- --ignored` where ptrace and user namespaces are allowed, which
+    // they are not in some containers.
+    #[test]
+    #[ignore = "needs ptrace and user namespaces"]
+    fn test_trace() {
+        let data = fs::read("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let observed = trace("fake-firmware-c-dynamic", &data, &DynamicOptions::default()).unwrap();
+        // Without the network, the binary gives up on its connection and exits with 1.
+        assert_eq!(observed.exit_code, Some(1));
+        assert!(!observed.timed_out);
+        // The main thread and the one it starts.
+        assert_eq!(observed.threads.len(), 2);
+        assert!(observed.threads[0].syscalls.contains(&"clone3".to_string()));
+        assert!(observed.syscalls().contains("connect"));
+        assert!(observed.syscalls().contains("exit_group"));
+        let json = observed.to_json();
+        assert_eq!(json["exit code"], 1);
+        assert_eq!(json["threads"][1]["thread"], 2);
+    }
+
+    #[cfg(all(feature = "native", target_os = "linux"))]
+    #[test]
+    fn test_parse_qemu_log() {
+        let log = "\
+            1201 brk(NULL) = 0x00021000\n\
+            1201 openat(AT_FDCWD,\"/etc/ld.so.cache\",O_RDONLY|O_CLOEXEC) = 3\n\
+            1201 clone(CLONE_VM|CLONE_FS,child_stack=0x3f7ff000) = 1202\n\
+            1202 wait4(-1,0x3f7fe68c,0,(nil))\n\
+             = 0\n\
+            1201 Unknown syscall 451\n\
+            1201 --- SIGCHLD {si_signo=SIGCHLD, si_code=CLD_EXITED} ---\n\
+            1201 openat(AT_FDCWD,\"/dev/sda\",O_RDWR) = -1 errno=2 (No such file or directory)\n\
+            1201 exit_group(0)\n";
+        let threads = tracer::parse_qemu_log(log);
+        assert_eq!(threads.len(), 2);
+        assert_eq!(
+            threads[0].syscalls,
+            ["brk", "openat", "clone", "syscall_451", "exit_group"]
+        );
+        assert_eq!(threads[0].calls, 6);
+        assert_eq!(threads[1].thread, 2);
+        assert_eq!(threads[1].syscalls, ["wait4"]);
+    }
+
+    #[cfg(all(feature = "native", target_os = "linux"))]
+    #[test]
+    fn test_emulation() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // The C binary, marked as an ARM one.
+        let mut data = fs::read("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        data[18..20].copy_from_slice(&goblin::elf::header::EM_ARM.to_le_bytes());
+        // An emulator logging two syscalls to the file given with -D.
+        let emulator = std::env::temp_dir().join(format!("fake-qemu-{}", std::process::id()));
+        fs::write(
+            &emulator,
+            "#!/bin/sh\necho \"7 openat(AT_FDCWD,\\\"$6\\\",O_RDONLY) = 3\" > \"$3\"\n\
+             echo \"7 exit_group(3)\" >> \"$3\"\nexit 3\n",
+        )
+        .unwrap();
+        fs::set_permissions(&emulator, fs::Permissions::from_mode(0o700)).unwrap();
+        let options = DynamicOptions {
+            emulator: Some(emulator.to_string_lossy().to_string()),
+            sysroot: Some("/usr/arm-linux-gnueabihf".to_string()),
+            allow_network: true,
+            ..DynamicOptions::default()
+        };
+        let observed = trace("fake-firmware-arm", &data, &options).unwrap();
+        assert_eq!(observed.exit_code, Some(3));
+        assert_eq!(observed.threads[0].syscalls, ["openat", "exit_group"]);
+        assert_eq!(observed.error, None);
+        fs::remove_file(&emulator).unwrap();
+
+        // The emulator is missing: nothing is observed, without failing the analysis.
+        let options = DynamicOptions {
+            emulator: Some("/nonexistent/qemu-arm".to_string()),
+            ..DynamicOptions::default()
+        };
+        let observed = trace("fake-firmware-arm", &data, &options).unwrap();
+        assert!(observed.threads.is_empty());
+        assert!(observed.error.is_some());
+        let json = observed.to_json();
+        assert_eq!(json["emulator"], "/nonexistent/qemu-arm");
+        assert!(json["error"].is_string());
+    }
+}
@@ -0,0 +1,338 @@
+use std::path::{Component, Path, PathBuf};
+
+use goblin::elf::Elf;
+use serde_json::{json, Value};
+
+use crate::{
+    arguments::{call_arguments, CallArguments},
+    call_graph::CallGraph,
+    cleanup::demangle_api_name,
+    elf_utils::API,
+    error::Result,
+    manifest_creation::api_flow_json,
+    xrefs::XrefDb,
+};
+
+/// The functions loading a library, with the indexes of their path and flags arguments.
+pub const LOAD_FUNCTIONS: [(&str, usize, usize); 3] = [
+    ("dlopen", 0, 1),
+    ("dlmopen", 1, 2),
+    ("__libc_dlopen_mode", 0, 1),
+];
+
+/// The functions looking up a symbol, with the indexes of their handle and name arguments.
+pub const LOOKUP_FUNCTIONS: [(&str, usize, usize); 3] =
+    [("dlsym", 0, 1), ("dlvsym", 0, 1), ("__libc_dlsym", 0, 1)];
+
+/// The directories of a firmware tree the libraries loaded by name are looked for in.
+pub const LIBRARY_DIRS: [&str; 7] = [
+    "lib",
+    "usr/lib",
+    "lib64",
+    "usr/lib64",
+    "usr/local/lib",
+    "lib/x86_64-linux-gnu",
+    "usr/lib/x86_64-linux-gnu",
+];
+
+// The flags of `dlopen`, from dlfcn.h.
+const RTLD_FLAGS: [(u64, &str); 6] = [
+    (0x1, "RTLD_LAZY"),
+    (0x2, "RTLD_NOW"),
+    (0x4, "RTLD_NOLOAD"),
+    (0x8, "RTLD_DEEPBIND"),
+    (0x100, "RTLD_GLOBAL"),
+    (0x1000, "RTLD_NODELETE"),
+];
+
+/// A call loading a library at run time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LibraryLoad {
+    /// The function called, e.g. `dlopen`.
+    pub function: String,
+    /// The address of the call instruction.
+    pub site: u64,
+    /// The name of the function performing the call.
+    pub caller: String,
+    /// The path or name of the library, when it is a constant string.
+    pub library: Option<String>,
+    /// The flags, when they are a constant.
+    pub flags: Option<u64>,
+    /// The names of the APIs whose flow loads the library.
+    pub apis: Vec<String>,
+}
+
+impl LibraryLoad {
+    /// Return the names of the flags, e.g. `RTLD_NOW`, when they are a constant.
+    pub fn flag_names(&self) -> Option<Vec<&'static str>> {
+        let flags = self.flags?;
+        Some(
+            RTLD_FLAGS
+                .iter()
+                .filter(|(bit, _)| flags & bit != 0)
+                .map(|(_, name)| *name)
+                .collect(),
+        )
+    }
+
+    /// Return the load as an entry of the `dynamic loading` libraries of the flow call
+    /// manifest.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "loaded by": self.function,
+            "site": format!("{:#x}", self.site),
+            "caller": self.caller,
+            "library": self.library,
+            "flags": self.flag_names(),
+            "APIs": self.apis,
+        })
+    }
+}
+
+/// A call looking up a symbol of a library loaded at run time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SymbolLookup {
+    /// The function called, e.g. `dlsym`.
+    pub function: String,
+    /// The address of the call instruction.
+    pub site: u64,
+    /// The name of the function performing the call.
+    pub caller: String,
+    /// The name of the symbol, when it is a constant string.
+    pub symbol: Option<String>,
+    /// The pseudo-handle searched, `RTLD_DEFAULT` or `RTLD_NEXT`, when it is one.
+    pub handle: Option<&'static str>,
+    /// The names of the APIs whose flow looks up the symbol.
+    pub apis: Vec<String>,
+}
+
+impl SymbolLookup {
+    /// Return the lookup as an entry of the `dynamic loading` symbols of the flow call
+    /// manifest.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "looked up by": self.function,
+            "site": format!("{:#x}", self.site),
+            "caller": self.caller,
+            "symbol": self.symbol,
+            "handle": self.handle,
+            "APIs": self.apis,
+        })
+    }
+}
+
+/// The libraries a binary loads at run time and the symbols it looks up in them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DynamicLoading {
+    /// The calls loading a library, sorted by call site.
+    pub libraries: Vec<LibraryLoad>,
+    /// The calls looking up a symbol, sorted by call site.
+    pub symbols: Vec<SymbolLookup>,
+}
+
+impl DynamicLoading {
+    /// Whether the binary neither loads libraries nor looks up symbols.
+    pub fn is_empty(&self) -> bool {
+        self.libraries.is_empty() && self.symbols.is_empty()
+    }
+
+    /// Return the constant names of the symbols looked up, sorted and deduplicated.
+    pub fn symbol_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .symbols
+            .iter()
+            .filter_map(|lookup| lookup.symbol.clone())
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Return the loading as the `dynamic loading` section of the flow call manifest.
+    pub fn to_json(&self) -> Value {
+        let libraries: Vec<Value> = self.libraries.iter().map(LibraryLoad::to_json).collect();
+        let symbols: Vec<Value> = self.symbols.iter().map(SymbolLookup::to_json).collect();
+        json!({ "libraries": libraries, "symbols": symbols })
+    }
+}
+
+/// The flows of the symbols looked up in a library loaded at run time, found in the firmware
+/// tree.
+pub struct LibraryFlows {
+    /// The library, as given to `dlopen`.
+    pub library: String,
+    /// The path of the library in the firmware tree.
+    pub path: String,
+    /// The flows of the symbols the library defines.
+    pub flows: Vec<API>,
+    /// Why the library could not be analyzed, e.g. it defines none of the symbols.
+    pub error: Option<String>,
+}
+
+impl LibraryFlows {
+    /// Return the flows as an entry of the `Loaded library flows` of the flow call manifest.
+    pub fn to_json(&self) -> Value {
+        let flows: Vec<Value> = self.flows.iter().map(api_flow_json).collect();
+        json!({
+            "library": self.library,
+            "path": self.path,
+            "flows": flows,
+            "error": self.error,
+        })
+    }
+}
+
+/// Find the calls to `dlopen` and `dlsym` and recover the constant library paths and symbol
+/// names given to them.
+///
+/// # Arguments
+///
+/// * `elf` - The ELF object representing the binary.
+/// * `buffer` - The buffer containing the binary data of the ELF file.
+/// * `graph` - The call graph of the binary.
+/// * `xrefs` - The cross-references of the functions, holding the strings they reference.
+/// * `api_list` - The APIs found, to tell which of them load each library.
+///
+/// # Returns
+///
+/// Returns a `Result` containing the libraries loaded and the symbols looked up.
+pub fn dynamic_loading(
+    elf: &Elf,
+    buffer: &[u8],
+    graph: &CallGraph,
+    xrefs: &XrefDb,
+    api_list: &[API],
+) -> Result<DynamicLoading> {
+    let flows: Vec<_> = api_list
+        .iter()
+        .map(|api| (api.name.as_str(), graph.reachable(&[api.start_addr])))
+        .collect();
+    let apis = |call: &CallArguments| -> Vec<String> {
+        flows
+            .iter()
+            .filter(|(_, reachable)| reachable.contains(&call.caller))
+            .map(|(name, _)| name.to_string())
+            .collect()
+    };
+    let caller = |call: &CallArguments| {
+        graph
+            .function(call.caller)
+            .map(|func| demangle_api_name(&func.name))
+            .unwrap_or_default()
+    };
+    let functions: Vec<&'static str> = LOAD_FUNCTIONS
+        .iter()
+        .chain(&LOOKUP_FUNCTIONS)
+        .map(|(function, _, _)| *function)
+        .collect();
+
+    let mut loading = DynamicLoading::default();
+    for call in call_arguments(elf, buffer, graph, &functions)? {
+        if let Some((_, path, flags)) = LOAD_FUNCTIONS
+            .iter()
+            .find(|(function, _, _)| *function == call.function)
+        {
+            loading.libraries.push(LibraryLoad {
+                function: call.function.to_string(),
+                site: call.site,
+                caller: caller(&call),
+                library: call.string(*path, xrefs),
+                flags: call.values[*flags],
+                apis: apis(&call),
+            });
+        } else if let Some((_, handle, name)) = LOOKUP_FUNCTIONS
+            .iter()
+            .find(|(function, _, _)| *function == call.function)
+        {
+            loading.symbols.push(SymbolLookup {
+                function: call.function.to_string(),
+                site: call.site,
+                caller: caller(&call),
+                symbol: call.string(*name, xrefs),
+                handle: match call.values[*handle] {
+                    Some(0) => Some("RTLD_DEFAULT"),
+                    Some(u64::MAX) => Some("RTLD_NEXT"),
+                    _ => None,
+                },
+                apis: apis(&call),
+            });
+        }
+    }
+    Ok(loading)
+}
+
+/// Find a library loaded at run time in a firmware tree.
+///
+/// An absolute path is taken from the root of the tree; a bare name, e.g. `libplugin.so`, is
+/// looked for in the [`LIBRARY_DIRS`]. Paths going up out of the tree are not followed.
+///
+/// # Arguments
+///
+/// * `root` - The root directory of the firmware tree.
+/// * `library` - The path or name given to `dlopen`.
+///
+/// # Returns
+///
+/// Returns the path of the library, when it is a file of the tree.
+pub fn resolve_library(root: &Path, library: &str) -> Option<PathBuf> {
+    let relative = Path::new(library.trim_start_matches('/'));
+    if relative
+        .components()
+        .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir))
+    {
+        return None;
+    }
+    let candidates: Vec<PathBuf> = match library.contains('/') {
+        true => vec![root.join(relative)],
+        false => LIBRARY_DIRS
+            .iter()
+            .map(|dir| root.join(dir).join(relative))
+            .collect(),
+    };
+    candidates.into_iter().find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf_utils::read_elf_file;
+    use std::{env::temp_dir, fs};
+
+    #[test]
+    fn test_dynamic_loading() {
+        // The gconv modules of the static glibc, loaded with __libc_dlopen_mode.
+        let buffer = read_elf_file("./tests/elf_file/minimal-fake-firmware-c-static").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        let graph = CallGraph::build(&elf, &buffer, false).unwrap();
+        let xrefs = XrefDb::build(&elf, &buffer, &graph);
+        let loading = dynamic_loading(&elf, &buffer, &graph, &xrefs, &[]).unwrap();
+        assert!(!loading.is_empty());
+        let gconv = loading
+            .libraries
+            .iter()
+            .find(|load| load.caller == "__gconv_find_shlib")
+            .unwrap();
+        assert_eq!(gconv.library, None);
+        assert_eq!(gconv.flag_names(), Some(vec!["RTLD_NOW"]));
+        assert!(loading
+            .symbol_names()
+            .iter()
+            .any(|name| name == "gconv_init"));
+        assert!(loading
+            .symbols
+            .windows(2)
+            .all(|pair| pair[0].site <= pair[1].site));
+
+        let root = temp_dir().join(format!("manifest-dlopen-{}", std::process::id()));
+        fs::create_dir_all(root.join("usr/lib")).unwrap();
+        fs::write(root.join("usr/lib/libplugin.so"), b"").unwrap();
+        assert_eq!(
+            resolve_library(&root, "libplugin.so"),
+            Some(root.join("usr/lib/libplugin.so"))
+        );
+        assert!(resolve_library(&root, "/usr/lib/libplugin.so").is_some());
+        assert_eq!(resolve_library(&root, "libmissing.so"), None);
+        assert_eq!(resolve_library(&root, "/usr/lib/../../etc/passwd"), None);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
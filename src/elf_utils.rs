@@ -1,10 +1,20 @@
-use std::{fs::File, io::Read};
+use std::collections::{BTreeMap, HashMap};
 
+#[cfg(feature = "native")]
 use capstone::prelude::*;
 use goblin::elf::{Elf, SectionHeader};
 use object::elf::SHT_PROGBITS;
 
-use crate::error;
+use crate::{
+    api_detection::ApiSpec,
+    capabilities::ApiCapability,
+    cfg::Complexity,
+    error,
+    network::Endpoint,
+    reader::{BinaryData, ReadMode},
+    strings::StringRef,
+    syscall_arguments::SyscallArguments,
+};
 use error::{Error, Result};
 
 /// Structure used to collect API data identified in the code.
@@ -17,6 +27,47 @@ pub struct API {
     pub end_addr: u64,
     /// The list of system calls associated with the API.
     pub syscalls: Vec<String>,
+    /// The arguments recovered at the calls of the API to the key syscalls, e.g. the path and
+    /// flags of `open`.
+    pub syscall_arguments: Vec<SyscallArguments>,
+    /// The functions reachable from the API through the call graph.
+    pub transitive_calls: Vec<String>,
+    /// The transitive calls grouped by the library they are attributed to, see
+    /// [`crate::provenance::library_of`].
+    pub libraries: BTreeMap<String, Vec<String>>,
+    /// The indirect calls reachable from the API whose target could not be determined.
+    pub unresolved_calls: Vec<String>,
+    /// The functions reachable from the API that take part in a recursion cycle.
+    pub recursive_functions: Vec<String>,
+    /// Whether the transitive flow was cut by the maximum traversal depth.
+    pub depth_truncated: bool,
+    /// The functions reachable from the API whose disassembly went over the resource budget,
+    /// with the budget exceeded.
+    pub skipped_functions: Vec<String>,
+    /// The classified strings referenced by the code reachable from the API.
+    pub strings: Vec<StringRef>,
+    /// The network endpoints reachable from the API.
+    pub endpoints: Vec<Endpoint>,
+    /// The cryptographic algorithms used by the code reachable from the API.
+    pub crypto: Vec<String>,
+    /// The constant request codes of the `ioctl` calls reachable from the API.
+    pub ioctls: Vec<u64>,
+    /// The high-level capabilities of the flow of the API.
+    pub capabilities: Vec<ApiCapability>,
+    /// The size and cyclomatic complexity of the code of the API, for x86-64 binaries.
+    pub complexity: Option<Complexity>,
+    /// How the API was matched, when it was not found under the requested name.
+    pub fuzzy_match: Option<FuzzyMatch>,
+    /// The entry of the API list this API was found for, when it carries metadata.
+    pub spec: Option<ApiSpec>,
+}
+
+/// Structure describing an API found by fuzzy matching a requested name.
+pub struct FuzzyMatch {
+    /// The API name as written in the API list.
+    pub requested: String,
+    /// The similarity between the requested name and the symbol, from 0 to 1.
+    pub confidence: f64,
 }
 
 impl API {
@@ -27,6 +78,21 @@ impl API {
             start_addr,
             end_addr,
             syscalls: Vec::new(),
+            syscall_arguments: Vec::new(),
+            transitive_calls: Vec::new(),
+            libraries: BTreeMap::new(),
+            unresolved_calls: Vec::new(),
+            recursive_functions: Vec::new(),
+            depth_truncated: false,
+            skipped_functions: Vec::new(),
+            strings: Vec::new(),
+            endpoints: Vec::new(),
+            crypto: Vec::new(),
+            ioctls: Vec::new(),
+            capabilities: Vec::new(),
+            complexity: None,
+            fuzzy_match: None,
+            spec: None,
         }
     }
     /// Adds a system call to the list of system calls associated with the API.
@@ -45,10 +111,30 @@ impl API {
 ///
 /// Returns a `Result` containing the vector of bytes read from the ELF file.
 pub fn read_elf_file(file_path: &str) -> Result<Vec<u8>> {
-    let mut file = File::open(file_path)?;
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)?;
-    Ok(buffer)
+    Ok(BinaryData::open(file_path, ReadMode::Read)?.into_bytes())
+}
+
+/// Compute the SHA-256 digest of the content of a file, as a lowercase hexadecimal string.
+pub fn sha256_hex(buffer: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+
+    Sha256::digest(buffer)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Return the GNU build ID of a binary, from its `NT_GNU_BUILD_ID` note, as a lowercase
+/// hexadecimal string.
+pub fn build_id(elf: &Elf, buffer: &[u8]) -> Option<String> {
+    all_notes(elf, buffer)
+        .find(|note| note.n_type == goblin::elf::note::NT_GNU_BUILD_ID && note.name == "GNU")
+        .map(|note| {
+            note.desc
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect()
+        })
 }
 
 /// Check whether the specified ELF file has been stripped of debug symbols.
@@ -73,9 +159,16 @@ fn has_sections(elf: &Elf, section_type: u32) -> bool {
 pub fn get_arch<'a>(elf: &'a Elf<'a>) -> Result<&'a str> {
     match elf.header.e_machine {
         goblin::elf::header::EM_X86_64 => Ok("x86-64"),
-        _ => Err(Error::InvalidElf {
-            source: goblin::error::Error::Malformed("Unknown Architecture".to_string()),
-        }),
+        goblin::elf::header::EM_386 => Ok("x86"),
+        goblin::elf::header::EM_ARM => Ok("arm"),
+        goblin::elf::header::EM_AARCH64 => Ok("aarch64"),
+        goblin::elf::header::EM_MIPS => Ok("mips"),
+        goblin::elf::header::EM_PPC => Ok("powerpc"),
+        goblin::elf::header::EM_PPC64 => Ok("powerpc64"),
+        goblin::elf::header::EM_RISCV => Ok("riscv"),
+        machine => Err(Error::UnsupportedArch(
+            goblin::elf::header::machine_to_str(machine).to_string(),
+        )),
     }
 }
 
@@ -95,6 +188,400 @@ pub fn is_static(elf: &Elf) -> bool {
     elf.dynamic.is_none()
 }
 
+/// The outcome of a hardening check of the binary.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HardeningCheck {
+    /// The name of the protection, e.g. `RELRO`.
+    pub name: &'static str,
+    /// Whether the binary passes the check.
+    pub passed: bool,
+    /// What was found, e.g. `partial` for RELRO.
+    pub detail: String,
+}
+
+/// Assess the exploit mitigations the binary was built with.
+///
+/// The checks are: PIE, RELRO (passed only when full, i.e. with immediate binding), stack
+/// canaries, non-executable stack, FORTIFY_SOURCE, stripped symbols, and the absence of
+/// `DT_RPATH` and of `DT_RUNPATH` entries that are relative or writable by anyone.
+///
+/// # Returns
+///
+/// Returns the result of each check, in the order above.
+pub fn hardening_checks(elf: &Elf) -> Vec<HardeningCheck> {
+    use goblin::elf::{dynamic, header, program_header};
+
+    let check = |name, passed, detail: &str| HardeningCheck {
+        name,
+        passed,
+        detail: detail.to_string(),
+    };
+    let has_segment = |p_type| elf.program_headers.iter().any(|ph| ph.p_type == p_type);
+    let symbols: Vec<&str> = elf
+        .dynsyms
+        .iter()
+        .filter_map(|sym| elf.dynstrtab.get_at(sym.st_name))
+        .chain(
+            elf.syms
+                .iter()
+                .filter_map(|sym| elf.strtab.get_at(sym.st_name)),
+        )
+        .map(|name| name.split('@').next().unwrap_or(name))
+        .collect();
+
+    // Static PIEs have no interpreter, but are flagged as PIE.
+    let pie_flag = elf
+        .dynamic
+        .as_ref()
+        .is_some_and(|dynamic| dynamic.info.flags_1 & dynamic::DF_1_PIE != 0);
+    let pie = match elf.header.e_type {
+        header::ET_DYN if elf.interpreter.is_some() || pie_flag => {
+            check("PIE", true, "position independent executable")
+        }
+        header::ET_DYN => check("PIE", true, "shared object"),
+        _ => check("PIE", false, "loaded at a fixed address"),
+    };
+
+    let bind_now = elf.dynamic.as_ref().is_some_and(|dynamic| {
+        dynamic.info.flags & dynamic::DF_BIND_NOW != 0
+            || dynamic.info.flags_1 & dynamic::DF_1_NOW != 0
+            || dynamic
+                .dyns
+                .iter()
+                .any(|dyn_| dyn_.d_tag == dynamic::DT_BIND_NOW)
+    });
+    let relro = match (has_segment(program_header::PT_GNU_RELRO), bind_now) {
+        (true, true) => check("RELRO", true, "full"),
+        (true, false) => check("RELRO", false, "partial"),
+        (false, _) => check("RELRO", false, "none"),
+    };
+
+    let canary = match symbols.iter().find(|name| {
+        [
+            "__stack_chk_fail",
+            "__stack_chk_fail_local",
+            "__stack_chk_guard",
+        ]
+        .contains(name)
+    }) {
+        Some(name) => check("stack canary", true, name),
+        None => check("stack canary", false, "no __stack_chk_fail"),
+    };
+
+    let nx = match elf
+        .program_headers
+        .iter()
+        .find(|ph| ph.p_type == program_header::PT_GNU_STACK)
+    {
+        Some(ph) if ph.p_flags & program_header::PF_X == 0 => {
+            check("NX", true, "non-executable stack")
+        }
+        Some(_) => check("NX", false, "executable stack"),
+        None => check("NX", false, "no PT_GNU_STACK, the stack may be executable"),
+    };
+
+    let mut fortified: Vec<&str> = symbols
+        .iter()
+        .copied()
+        .filter(|name| {
+            name.strip_prefix("__")
+                .and_then(|name| name.strip_suffix("_chk"))
+                .is_some_and(|name| !name.is_empty() && !name.starts_with("stack"))
+        })
+        .collect();
+    fortified.sort_unstable();
+    fortified.dedup();
+    let fortify = check(
+        "FORTIFY_SOURCE",
+        !fortified.is_empty(),
+        &format!("{} fortified functions", fortified.len()),
+    );
+
+    let stripped = if is_stripped(elf) {
+        check("stripped", true, "no symbol table")
+    } else {
+        check("stripped", false, "symbol table present")
+    };
+
+    let insecure_runpaths: Vec<&str> = elf
+        .runpaths
+        .iter()
+        .flat_map(|runpath| runpath.split(':'))
+        .filter(|entry| search_path_issue(entry).is_some())
+        .collect();
+    let rpath = if !elf.rpaths.is_empty() {
+        check(
+            "RPATH",
+            false,
+            &format!("DT_RPATH {}", elf.rpaths.join(":")),
+        )
+    } else if !insecure_runpaths.is_empty() {
+        check(
+            "RPATH",
+            false,
+            &format!("insecure DT_RUNPATH entries {:?}", insecure_runpaths),
+        )
+    } else {
+        check("RPATH", true, "no insecure search path")
+    };
+
+    vec![pie, relro, canary, nx, fortify, stripped, rpath]
+}
+
+/// The shared objects a dynamically linked binary needs and where the loader looks for them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DynamicDependencies {
+    /// The `DT_NEEDED` shared libraries, in the order of the dynamic section.
+    pub needed: Vec<String>,
+    /// The entries of `DT_RPATH`.
+    pub rpath: Vec<String>,
+    /// The entries of `DT_RUNPATH`.
+    pub runpath: Vec<String>,
+    /// The program interpreter of `PT_INTERP`, e.g. `/lib64/ld-linux-x86-64.so.2`.
+    pub interpreter: Option<String>,
+    /// The insecure search paths and interpreter.
+    pub warnings: Vec<String>,
+}
+
+/// Read the dynamic dependencies of the binary from its dynamic section and its `PT_INTERP`
+/// segment.
+///
+/// The search path entries that are empty, relative to the current directory instead of
+/// `$ORIGIN`, or in a directory writable by anyone are reported as warnings, since they let
+/// whoever controls that directory inject a library, as is a relative interpreter.
+pub fn dynamic_dependencies(elf: &Elf) -> DynamicDependencies {
+    let entries = |paths: &[&str]| -> Vec<String> {
+        paths
+            .iter()
+            .flat_map(|path| path.split(':'))
+            .map(str::to_string)
+            .collect()
+    };
+    let mut dependencies = DynamicDependencies {
+        needed: elf.libraries.iter().map(|lib| lib.to_string()).collect(),
+        rpath: entries(&elf.rpaths),
+        runpath: entries(&elf.runpaths),
+        interpreter: elf.interpreter.map(str::to_string),
+        warnings: Vec::new(),
+    };
+    for (tag, entries) in [
+        ("DT_RPATH", &dependencies.rpath),
+        ("DT_RUNPATH", &dependencies.runpath),
+    ] {
+        for entry in entries {
+            if let Some(issue) = search_path_issue(entry) {
+                dependencies
+                    .warnings
+                    .push(format!("{} entry \"{}\" {}", tag, entry, issue));
+            }
+        }
+    }
+    if let Some(interpreter) = dependencies.interpreter.as_ref() {
+        if !interpreter.starts_with('/') {
+            dependencies.warnings.push(format!(
+                "relative interpreter {}, resolved from the current directory",
+                interpreter
+            ));
+        }
+    }
+    dependencies
+}
+
+// Tell what makes a library search path entry insecure, if anything.
+fn search_path_issue(entry: &str) -> Option<&'static str> {
+    if entry.is_empty() {
+        Some("is empty, searching the current directory")
+    } else if !(entry.starts_with('/') || entry.starts_with("$ORIGIN")) {
+        Some("is relative to the current directory")
+    } else if entry.starts_with("/tmp") || entry.starts_with("/var/tmp") {
+        Some("is in a directory writable by anyone")
+    } else {
+        None
+    }
+}
+
+/// The facts recorded in the notes of the binary besides its build ID.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ElfNotes {
+    /// The operating system of the `NT_GNU_ABI_TAG` note and the oldest kernel the binary runs
+    /// on, e.g. `("Linux", "3.2.0")`.
+    pub abi_tag: Option<(String, String)>,
+    /// The control-flow protections the binary is built for, from its `.note.gnu.property`:
+    /// `IBT` and `SHSTK` for Intel CET, `BTI` and `PAC` on AArch64.
+    pub features: Vec<String>,
+    /// The x86-64 micro-architecture levels the code needs, e.g. `x86-64-baseline`.
+    pub isa_needed: Vec<String>,
+    /// The stack size asked for by `GNU_PROPERTY_STACK_SIZE`.
+    pub stack_size: Option<u64>,
+    /// The metadata of the distribution package shipping it, from the JSON of `.note.package`.
+    pub package: Option<serde_json::Value>,
+}
+
+impl ElfNotes {
+    /// Return the notes as the `notes` entry of the basic information manifest.
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut notes = serde_json::json!({
+            "features": self.features,
+            "x86 ISA needed": self.isa_needed,
+        });
+        if let Some((os, kernel)) = &self.abi_tag {
+            notes["ABI tag"] = serde_json::json!({ "os": os, "minimum kernel": kernel });
+        }
+        if let Some(size) = self.stack_size {
+            notes["stack size"] = serde_json::json!(size);
+        }
+        if let Some(package) = &self.package {
+            notes["package"] = package.clone();
+        }
+        notes
+    }
+}
+
+// The note types and properties read by `elf_notes`, from the GNU and systemd specifications.
+const NT_GNU_ABI_TAG: u32 = 1;
+const NT_GNU_PROPERTY_TYPE_0: u32 = 5;
+const NT_FDO_PACKAGING_METADATA: u32 = 0xcafe_1a7e;
+const GNU_PROPERTY_STACK_SIZE: u32 = 1;
+const GNU_PROPERTY_AARCH64_FEATURE_1_AND: u32 = 0xc000_0000;
+const GNU_PROPERTY_X86_FEATURE_1_AND: u32 = 0xc000_0002;
+const GNU_PROPERTY_X86_ISA_1_NEEDED: u32 = 0xc000_8002;
+
+/// Read the ABI tag, the GNU properties and the package metadata of the notes of the binary.
+///
+/// # Arguments
+///
+/// * `elf` - The parsed ELF structure.
+/// * `buffer` - The content of the binary.
+///
+/// # Returns
+///
+/// Returns the facts found, the notes that cannot be parsed being left out.
+pub fn elf_notes(elf: &Elf, buffer: &[u8]) -> ElfNotes {
+    let mut notes = ElfNotes::default();
+    let (mut abi_tag, mut properties, mut package) = (false, false, false);
+    for note in all_notes(elf, buffer) {
+        match (note.name, note.n_type) {
+            ("GNU", NT_GNU_ABI_TAG) if !abi_tag && note.desc.len() >= 16 => {
+                abi_tag = true;
+                let word = |index: usize| elf_word(elf, &note.desc[index * 4..index * 4 + 4]);
+                let os = match word(0) {
+                    0 => "Linux".to_string(),
+                    1 => "Hurd".to_string(),
+                    2 => "Solaris".to_string(),
+                    3 => "FreeBSD".to_string(),
+                    other => format!("unknown ({})", other),
+                };
+                notes.abi_tag = Some((os, format!("{}.{}.{}", word(1), word(2), word(3))));
+            }
+            ("GNU", NT_GNU_PROPERTY_TYPE_0) if !properties => {
+                properties = true;
+                read_properties(elf, note.desc, &mut notes);
+            }
+            ("FDO", NT_FDO_PACKAGING_METADATA) if !package => {
+                package = true;
+                notes.package = Some(package_metadata(note.desc));
+            }
+            _ => {}
+        }
+    }
+    notes
+}
+
+// Iterate over the notes of the binary, from its note sections then from its note segments, so
+// that a binary stripped of its section headers keeps them.
+fn all_notes<'a>(
+    elf: &'a Elf,
+    buffer: &'a [u8],
+) -> impl Iterator<Item = goblin::elf::note::Note<'a>> {
+    elf.iter_note_sections(buffer, None)
+        .into_iter()
+        .flatten()
+        .chain(elf.iter_note_headers(buffer).into_iter().flatten())
+        .filter_map(|note| note.ok())
+}
+
+// Read a 32-bit word of a note in the byte order of the binary.
+fn elf_word(elf: &Elf, bytes: &[u8]) -> u32 {
+    let bytes: [u8; 4] = bytes.try_into().unwrap_or_default();
+    if elf.little_endian {
+        u32::from_le_bytes(bytes)
+    } else {
+        u32::from_be_bytes(bytes)
+    }
+}
+
+// Read the properties of an `NT_GNU_PROPERTY_TYPE_0` note, each padded to the word size.
+fn read_properties(elf: &Elf, desc: &[u8], notes: &mut ElfNotes) {
+    const X86_FEATURES: [(u32, &str); 2] = [(1, "IBT"), (2, "SHSTK")];
+    const AARCH64_FEATURES: [(u32, &str); 2] = [(1, "BTI"), (2, "PAC")];
+    const X86_ISA_LEVELS: [(u32, &str); 4] = [
+        (1, "x86-64-baseline"),
+        (2, "x86-64-v2"),
+        (4, "x86-64-v3"),
+        (8, "x86-64-v4"),
+    ];
+    let align = if elf.is_64 { 8 } else { 4 };
+    let names = |bits: u32, table: &[(u32, &str)]| -> Vec<String> {
+        table
+            .iter()
+            .filter(|(bit, _)| bits & bit != 0)
+            .map(|(_, name)| name.to_string())
+            .collect()
+    };
+    let mut offset = 0;
+    while offset + 8 <= desc.len() {
+        let kind = elf_word(elf, &desc[offset..offset + 4]);
+        let size = elf_word(elf, &desc[offset + 4..offset + 8]) as usize;
+        let Some(data) = desc.get(offset + 8..offset + 8 + size) else {
+            break;
+        };
+        let value = if data.len() >= 4 {
+            elf_word(elf, &data[..4])
+        } else {
+            0
+        };
+        match kind {
+            GNU_PROPERTY_STACK_SIZE if data.len() == 8 => {
+                let bytes: [u8; 8] = data.try_into().unwrap_or_default();
+                notes.stack_size = Some(if elf.little_endian {
+                    u64::from_le_bytes(bytes)
+                } else {
+                    u64::from_be_bytes(bytes)
+                });
+            }
+            GNU_PROPERTY_STACK_SIZE => notes.stack_size = Some(u64::from(value)),
+            GNU_PROPERTY_X86_FEATURE_1_AND if is_x86(elf) => {
+                notes.features.extend(names(value, &X86_FEATURES));
+            }
+            GNU_PROPERTY_AARCH64_FEATURE_1_AND
+                if elf.header.e_machine == goblin::elf::header::EM_AARCH64 =>
+            {
+                notes.features.extend(names(value, &AARCH64_FEATURES));
+            }
+            GNU_PROPERTY_X86_ISA_1_NEEDED if is_x86(elf) => {
+                notes.isa_needed.extend(names(value, &X86_ISA_LEVELS));
+            }
+            _ => {}
+        }
+        offset += 8 + size.div_ceil(align) * align;
+    }
+}
+
+// Whether the binary is built for x86 or x86-64, whose properties share their numbers.
+fn is_x86(elf: &Elf) -> bool {
+    matches!(
+        elf.header.e_machine,
+        goblin::elf::header::EM_X86_64 | goblin::elf::header::EM_386
+    )
+}
+
+// Parse the JSON of a `.note.package` note, kept as a string when it is not valid JSON.
+fn package_metadata(desc: &[u8]) -> serde_json::Value {
+    let text = String::from_utf8_lossy(desc);
+    let text = text.trim_end_matches('\0');
+    serde_json::from_str(text).unwrap_or_else(|_| serde_json::Value::String(text.to_string()))
+}
+
 /// Locate the `.text` section in the ELF file.
 pub fn find_text_section<'a>(elf: &'a Elf<'a>) -> Option<&'a SectionHeader> {
     elf.section_headers.iter().find(|sec| {
@@ -106,6 +593,7 @@ pub fn find_text_section<'a>(elf: &'a Elf<'a>) -> Option<&'a SectionHeader> {
 }
 
 /// Initialize Capstone disassembly engine.
+#[cfg(feature = "native")]
 pub fn cs_init() -> Result<Capstone> {
     let cs = Capstone::new()
         .x86()
@@ -116,6 +604,54 @@ pub fn cs_init() -> Result<Capstone> {
     cs.map_err(|err| Error::Capstone(format!("Failed to create Capstone instance: {}", err)))
 }
 
+/// Return the bytes of the code between two virtual addresses.
+///
+/// The file offset is computed from the section containing `start_addr`, so this works for both
+/// statically and dynamically linked binaries.
+///
+/// # Returns
+///
+/// Returns `None` if the range does not lie within a section backed by the file.
+pub fn code_bytes<'a>(
+    elf: &Elf,
+    buffer: &'a [u8],
+    start_addr: u64,
+    end_addr: u64,
+) -> Option<&'a [u8]> {
+    let section = elf.section_headers.iter().find(|sec| {
+        sec.sh_type == SHT_PROGBITS
+            && sec.sh_addr <= start_addr
+            && end_addr <= sec.sh_addr + sec.sh_size
+    })?;
+    let start = (section.sh_offset + start_addr - section.sh_addr) as usize;
+    let end = (section.sh_offset + end_addr - section.sh_addr) as usize;
+    buffer.get(start..end)
+}
+
+/// Return the executable sections of the binary, or its executable segments when it has no
+/// section headers, as (address, file offset, size).
+pub fn executable_regions(elf: &Elf) -> Vec<(u64, u64, u64)> {
+    use goblin::elf::{program_header, section_header};
+    if elf.section_headers.len() > 1 {
+        elf.section_headers
+            .iter()
+            .filter(|sh| {
+                sh.sh_type == SHT_PROGBITS
+                    && sh.sh_flags & u64::from(section_header::SHF_EXECINSTR) != 0
+            })
+            .map(|sh| (sh.sh_addr, sh.sh_offset, sh.sh_size))
+            .collect()
+    } else {
+        elf.program_headers
+            .iter()
+            .filter(|ph| {
+                ph.p_type == program_header::PT_LOAD && ph.p_flags & program_header::PF_X != 0
+            })
+            .map(|ph| (ph.p_vaddr, ph.p_offset, ph.p_filesz))
+            .collect()
+    }
+}
+
 /// Retrieve the name associated with the given address in the ELF file.
 ///
 /// # Arguments
@@ -145,6 +681,25 @@ pub fn get_name_addr<'a>(elf: &'a Elf<'a>, address: u64) -> Option<&'a str> {
     None
 }
 
+/// Build an index from addresses to symbol names, with the same precedence as [`get_name_addr`].
+///
+/// Looking names up in the index avoids a scan of the symbol tables for every call site.
+pub fn symbol_index<'a>(elf: &'a Elf<'a>) -> HashMap<u64, &'a str> {
+    let mut index = HashMap::new();
+    // The first matching entry of the symbol table wins, then the dynamic symbols are used.
+    for sym in elf.syms.iter().filter(|sym| sym.st_value != 0) {
+        if let Some(name) = elf.strtab.get_at(sym.st_name) {
+            index.entry(sym.st_value).or_insert(name);
+        }
+    }
+    for dsym in elf.dynsyms.iter().filter(|dsym| dsym.st_value != 0) {
+        if let Some(name) = elf.dynstrtab.get_at(dsym.st_name) {
+            index.entry(dsym.st_value).or_insert(name);
+        }
+    }
+    index
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -158,6 +713,15 @@ mod tests {
     }
 
     #[test]
+    fn test_sha256_hex() {
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "native")]
     fn test_cs_init() {
         let result = cs_init();
         assert!(result.is_ok());
@@ -168,24 +732,95 @@ mod tests {
         let elf_data =
             read_elf_file("./tests/elf_file/fake-firmware-rust-dynamic-stripped").unwrap();
         let elf = goblin::elf::Elf::parse(&elf_data).unwrap();
-        assert_eq!(true, is_stripped(&elf))
+        assert!(is_stripped(&elf))
+    }
+
+    #[test]
+    fn test_hardening_checks() {
+        let elf_data = read_elf_file("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let elf = goblin::elf::Elf::parse(&elf_data).unwrap();
+        let checks = hardening_checks(&elf);
+        let names: Vec<&str> = checks.iter().map(|check| check.name).collect();
+        assert_eq!(
+            names,
+            [
+                "PIE",
+                "RELRO",
+                "stack canary",
+                "NX",
+                "FORTIFY_SOURCE",
+                "stripped",
+                "RPATH"
+            ]
+        );
+        assert!(checks[0].passed);
+        assert!(!checks[5].passed);
+    }
+
+    #[test]
+    fn test_dynamic_dependencies() {
+        let elf_data = read_elf_file("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let elf = goblin::elf::Elf::parse(&elf_data).unwrap();
+        let dependencies = dynamic_dependencies(&elf);
+        assert!(dependencies
+            .needed
+            .iter()
+            .any(|lib| lib.starts_with("libc.so")));
+        assert!(dependencies.interpreter.is_some());
+
+        assert_eq!(search_path_issue("$ORIGIN/../lib"), None);
+        assert_eq!(search_path_issue("/usr/lib"), None);
+        assert!(search_path_issue("").is_some());
+        assert!(search_path_issue("lib").is_some());
+        assert!(search_path_issue("/tmp/lib").is_some());
+    }
+
+    #[test]
+    fn test_elf_notes() {
+        let elf_data = read_elf_file("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let elf = goblin::elf::Elf::parse(&elf_data).unwrap();
+        let notes = elf_notes(&elf, &elf_data);
+        assert_eq!(
+            notes.abi_tag,
+            Some(("Linux".to_string(), "3.2.0".to_string()))
+        );
+        assert_eq!(notes.isa_needed, ["x86-64-baseline"]);
+        assert!(notes.features.is_empty());
+        assert_eq!(notes.to_json()["ABI tag"]["minimum kernel"], "3.2.0");
+
+        let elf_data = read_elf_file("./tests/elf_file/ffmpeg").unwrap();
+        let elf = goblin::elf::Elf::parse(&elf_data).unwrap();
+        assert_eq!(elf_notes(&elf, &elf_data).features, ["IBT", "SHSTK"]);
+
+        // A stack size property, then an unknown one.
+        let mut desc = Vec::new();
+        for (kind, value) in [(GNU_PROPERTY_STACK_SIZE, 0x10000u64), (0xc000_1234, 1)] {
+            desc.extend_from_slice(&kind.to_le_bytes());
+            desc.extend_from_slice(&8u32.to_le_bytes());
+            desc.extend_from_slice(&value.to_le_bytes());
+        }
+        let mut notes = ElfNotes::default();
+        read_properties(&elf, &desc, &mut notes);
+        assert_eq!(notes.stack_size, Some(0x10000));
+        assert!(notes.features.is_empty());
+
+        let package = package_metadata(b"{\"type\":\"rpm\",\"name\":\"fw\"}\0");
+        assert_eq!(package["name"], "fw");
+        assert_eq!(package_metadata(b"not json\0"), "not json");
     }
 
     #[test]
     fn test_is_static() {
         let elf_data = read_elf_file("./tests/elf_file/fake-firmware-rust-dynamic").unwrap();
         let elf = goblin::elf::Elf::parse(&elf_data).unwrap();
-        assert_eq!(false, is_static(&elf))
+        assert!(!is_static(&elf))
     }
 
     #[test]
     fn test_has_sections() {
         let elf_data = read_elf_file("./tests/elf_file/fake-firmware-rust-dynamic").unwrap();
         let elf = goblin::elf::Elf::parse(&elf_data).unwrap();
-        assert_eq!(
-            true,
-            has_sections(&elf, goblin::elf::section_header::SHT_DYNSYM)
-        )
+        assert!(has_sections(&elf, goblin::elf::section_header::SHT_DYNSYM))
     }
 
     #[test]
@@ -0,0 +1,171 @@
+use goblin::elf::{
+    program_header::{PF_X, PT_LOAD},
+    section_header::{SHF_EXECINSTR, SHT_NOBITS},
+    Elf,
+};
+
+use crate::error::Error;
+
+// Minimum Shannon entropy, in bits per byte, of compressed or encrypted data: code and tables
+// stay below 7, compressed data is close to 8.
+const PACKED_ENTROPY: f64 = 7.2;
+
+// Minimum size of a section for its entropy to be meaningful, smaller ones never looking random.
+const MIN_PACKED_SIZE: u64 = 256;
+
+/// The entropy of a section of the binary, or of a loadable segment when the binary has no
+/// section headers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SectionEntropy {
+    /// The name of the section, e.g. `.text`, or `LOAD[1]` for the second loadable segment.
+    pub name: String,
+    /// The size of the section in the file.
+    pub size: u64,
+    /// The Shannon entropy of its content, in bits per byte.
+    pub entropy: f64,
+    /// Whether the section holds code.
+    pub executable: bool,
+    /// Whether its content looks compressed or encrypted.
+    pub packed: bool,
+}
+
+/// Compute the Shannon entropy of some bytes, in bits per byte.
+pub fn shannon_entropy(data: &[u8]) -> f64 {
+    let mut counts = [0usize; 256];
+    for &byte in data {
+        counts[byte as usize] += 1;
+    }
+    let len = data.len() as f64;
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Compute the entropy of the sections of the binary with content in the file.
+///
+/// A binary stripped of its section headers, as left by most packers, is measured by
+/// loadable segments instead.
+///
+/// # Arguments
+///
+/// * `elf` - The parsed ELF structure.
+/// * `buffer` - The content of the binary.
+///
+/// # Returns
+///
+/// Returns the entropy of each section, in the order of the section headers.
+pub fn section_entropy(elf: &Elf, buffer: &[u8]) -> Vec<SectionEntropy> {
+    let sections: Vec<(String, u64, u64, bool)> = if elf.section_headers.len() > 1 {
+        elf.section_headers
+            .iter()
+            .filter(|sh| sh.sh_type != SHT_NOBITS && sh.sh_size > 0)
+            .map(|sh| {
+                (
+                    elf.shdr_strtab.get_at(sh.sh_name).unwrap_or("").to_string(),
+                    sh.sh_offset,
+                    sh.sh_size,
+                    sh.sh_flags & u64::from(SHF_EXECINSTR) != 0,
+                )
+            })
+            .collect()
+    } else {
+        elf.program_headers
+            .iter()
+            .filter(|ph| ph.p_type == PT_LOAD && ph.p_filesz > 0)
+            .enumerate()
+            .map(|(index, ph)| {
+                (
+                    format!("LOAD[{}]", index),
+                    ph.p_offset,
+                    ph.p_filesz,
+                    ph.p_flags & PF_X != 0,
+                )
+            })
+            .collect()
+    };
+    sections
+        .into_iter()
+        .filter_map(|(name, offset, size, executable)| {
+            let start = usize::try_from(offset).ok()?;
+            let data = buffer.get(start..start.checked_add(usize::try_from(size).ok()?)?)?;
+            let entropy = shannon_entropy(data);
+            Some(SectionEntropy {
+                name,
+                size,
+                entropy,
+                executable,
+                packed: size >= MIN_PACKED_SIZE && entropy >= PACKED_ENTROPY,
+            })
+        })
+        .collect()
+}
+
+/// Tell whether the binary is probably packed or encrypted: some of its code looks compressed
+/// or encrypted, and cannot be disassembled.
+///
+/// # Returns
+///
+/// Returns an [`Error::PackedBinary`] naming the sections of code with high entropy, `None` if
+/// there are none.
+pub fn packed_error(elf: &Elf, buffer: &[u8]) -> Option<Error> {
+    let packed: Vec<String> = section_entropy(elf, buffer)
+        .into_iter()
+        .filter(|section| section.executable && section.packed)
+        .map(|section| format!("{} ({:.2} bits per byte)", section.name, section.entropy))
+        .collect();
+    (!packed.is_empty())
+        .then(|| Error::PackedBinary(format!("high entropy in {}", packed.join(", "))))
+}
+
+/// Round an entropy to the thousandth, as written in the manifests.
+pub fn rounded(entropy: f64) -> f64 {
+    (entropy * 1000.0).round() / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_section_entropy() {
+        assert_eq!(shannon_entropy(b""), 0.0);
+        assert_eq!(shannon_entropy(b"aaaa"), 0.0);
+        assert_eq!(shannon_entropy(b"abab"), 1.0);
+        let all: Vec<u8> = (0..=255).collect();
+        assert_eq!(shannon_entropy(&all), 8.0);
+
+        let buffer = fs::read("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        let sections = section_entropy(&elf, &buffer);
+        let text = sections.iter().find(|s| s.name == ".text").unwrap();
+        assert!(text.executable && !text.packed);
+        assert!(text.entropy > 4.0 && text.entropy < PACKED_ENTROPY);
+        assert!(sections.iter().all(|s| s.name != ".bss"));
+        assert!(packed_error(&elf, &buffer).is_none());
+
+        // The code replaced with random-looking bytes, as left by a packer.
+        let mut packed = buffer.clone();
+        let sh = elf
+            .section_headers
+            .iter()
+            .find(|sh| elf.shdr_strtab.get_at(sh.sh_name) == Some(".text"))
+            .unwrap();
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        for byte in &mut packed[sh.sh_offset as usize..(sh.sh_offset + sh.sh_size) as usize] {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            *byte = state as u8;
+        }
+        let elf = Elf::parse(&packed).unwrap();
+        let error = packed_error(&elf, &packed).unwrap();
+        assert!(error.to_string().contains(".text ("));
+        assert_eq!(rounded(7.123456), 7.123);
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::BTreeMap;
+
+use goblin::elf::Elf;
+
+use crate::{
+    arguments::call_arguments, call_graph::CallGraph, cleanup::demangle_api_name, elf_utils::API,
+    error::Result, xrefs::XrefDb,
+};
+
+/// The functions reading an environment variable, whose first argument is its name.
+pub const ENV_FUNCTIONS: [&str; 4] = [
+    "getenv",
+    "secure_getenv",
+    "__secure_getenv",
+    "__libc_secure_getenv",
+];
+
+/// An environment variable read by the binary.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EnvVariable {
+    /// The name of the variable, e.g. `HOME`.
+    pub name: String,
+    /// The names of the functions reading it, sorted.
+    pub functions: Vec<String>,
+    /// The names of the APIs whose flow reads it.
+    pub apis: Vec<String>,
+}
+
+/// List the environment variables read by the binary, from the constant names given to
+/// `getenv` and `secure_getenv`.
+///
+/// The names built at run time, or passed by the caller of the function reading them, cannot be
+/// recovered.
+///
+/// # Arguments
+///
+/// * `elf` - The ELF object representing the binary.
+/// * `buffer` - The buffer containing the binary data of the ELF file.
+/// * `graph` - The call graph of the binary.
+/// * `xrefs` - The cross-references of the functions, holding the strings they reference.
+/// * `api_list` - The APIs found, to tell which of them read each variable.
+///
+/// # Returns
+///
+/// Returns a `Result` containing the variables, sorted by name.
+pub fn environment_variables(
+    elf: &Elf,
+    buffer: &[u8],
+    graph: &CallGraph,
+    xrefs: &XrefDb,
+    api_list: &[API],
+) -> Result<Vec<EnvVariable>> {
+    let flows: Vec<_> = api_list
+        .iter()
+        .map(|api| (api.name.as_str(), graph.reachable(&[api.start_addr])))
+        .collect();
+    let mut variables: BTreeMap<String, EnvVariable> = BTreeMap::new();
+    for call in call_arguments(elf, buffer, graph, &ENV_FUNCTIONS)? {
+        let Some(name) = call.string(0, xrefs) else {
+            continue;
+        };
+        let variable = variables
+            .entry(name.clone())
+            .or_insert_with(|| EnvVariable {
+                name,
+                ..EnvVariable::default()
+            });
+        if let Some(func) = graph.function(call.caller) {
+            variable.functions.push(demangle_api_name(&func.name));
+        }
+        variable.apis.extend(
+            flows
+                .iter()
+                .filter(|(_, reachable)| reachable.contains(&call.caller))
+                .map(|(name, _)| name.to_string()),
+        );
+    }
+    Ok(variables
+        .into_values()
+        .map(|mut variable| {
+            for names in [&mut variable.functions, &mut variable.apis] {
+                names.sort();
+                names.dedup();
+            }
+            variable
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf_utils::read_elf_file;
+
+    #[test]
+    fn test_environment_variables() {
+        let buffer = read_elf_file("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        let graph = CallGraph::build(&elf, &buffer, false).unwrap();
+        let xrefs = XrefDb::build(&elf, &buffer, &graph);
+        let variables = environment_variables(&elf, &buffer, &graph, &xrefs, &[]).unwrap();
+
+        assert!(!variables.is_empty());
+        assert!(variables.windows(2).all(|pair| pair[0].name < pair[1].name));
+        assert!(variables
+            .iter()
+            .all(|variable| !variable.functions.is_empty()));
+        assert!(variables.iter().all(|variable| variable.apis.is_empty()));
+    }
+}
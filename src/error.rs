@@ -1,5 +1,63 @@
+use std::fmt;
+
 use thiserror::Error;
 
+/// Where an error occurred: the phase of the analysis, and the function and address it was
+/// working on.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ErrorContext {
+    /// The phase of the analysis, e.g. `flows` or `data analysis`.
+    pub phase: String,
+    /// The function being analyzed, if any.
+    pub function: Option<String>,
+    /// The address of the function, or the offset in the binary, being analyzed.
+    pub offset: Option<u64>,
+}
+
+impl ErrorContext {
+    /// Create the context of an error of the phase `phase`.
+    pub fn new(phase: &str) -> Self {
+        Self {
+            phase: phase.to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// Name the function the error occurred in.
+    pub fn with_function(mut self, function: &str) -> Self {
+        self.function = Some(function.to_string());
+        self
+    }
+
+    /// Give the address or offset the error occurred at.
+    pub fn with_offset(mut self, offset: u64) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Return the context as a JSON object.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "phase": self.phase,
+            "function": self.function,
+            "offset": self.offset.map(|offset| format!("{:#x}", offset)),
+        })
+    }
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "in {}", self.phase)?;
+        if let Some(function) = &self.function {
+            write!(f, " of {}", function)?;
+        }
+        if let Some(offset) = self.offset {
+            write!(f, " at {:#x}", offset)?;
+        }
+        Ok(())
+    }
+}
+
 /// Custom error type for manifest-producer.
 ///
 /// This error type encompasses various kinds of errors that can occur during the execution of manifest-producer.
@@ -24,10 +82,90 @@ pub enum Error {
     #[error("No Debug-info")]
     DebugInfo,
 
+    /// The code of the binary looks compressed or encrypted, and cannot be disassembled.
+    #[error("Probably packed or encrypted binary: {0}")]
+    PackedBinary(String),
+
+    /// A packed binary could not be unpacked.
+    #[error("Unpacking failed: {0}")]
+    Unpacking(String),
+
     /// No API was found.
     #[error("No API found")]
     APIListEmpty,
 
+    /// The API list does not follow the expected schema.
+    #[error("Invalid API list: {0}")]
+    InvalidApiList(String),
+
+    /// An entry of the API list is not a valid regex or glob pattern.
+    #[error("Invalid API pattern: {0}")]
+    InvalidPattern(String),
+
+    /// The vulnerability database is not a valid OSV snapshot.
+    #[error("Invalid vulnerability database: {0}")]
+    InvalidVulnDb(String),
+
+    /// The binary is of an architecture the disassembler does not support.
+    #[error("Unsupported architecture: {0}")]
+    UnsupportedArch(String),
+
+    /// The binary is not of the architecture it was expected to be.
+    #[error("Architecture mismatch: expected {expected}, found {found}")]
+    ArchMismatch {
+        /// The architecture given on the command line.
+        expected: String,
+        /// The architecture of the binary.
+        found: String,
+    },
+
+    /// The binary could not be analyzed while running.
+    #[error("Dynamic analysis failed: {0}")]
+    DynamicAnalysis(String),
+
+    /// The analysis mode is not one of the supported ones.
+    #[error("Unsupported analysis mode: {0}")]
+    InvalidAnalysisMode(String),
+
+    /// The analysis scope is not one of the supported ones.
+    #[error("Unsupported analysis scope: {0}")]
+    InvalidScope(String),
+
+    /// The capability rules file does not follow the expected layout.
+    #[error("Invalid capability rules: {0}")]
+    InvalidRules(String),
+
+    /// The policy file does not follow the expected layout.
+    #[error("Invalid policy: {0}")]
+    InvalidPolicy(String),
+
+    /// The declared behavior manifest does not follow the expected layout.
+    #[error("Invalid declaration: {0}")]
+    InvalidDeclaration(String),
+
+    /// A FLIRT pattern file of library signatures is malformed.
+    #[error("Invalid pattern file: {0}")]
+    InvalidPatternFile(String),
+
+    /// The configuration file is malformed.
+    #[error("Invalid configuration: {0}")]
+    InvalidConfig(String),
+
+    /// A path given to analyze does not exist.
+    #[error("Invalid path: {0}")]
+    InvalidPath(String),
+
+    /// A read went past the end of the binary.
+    #[error("Read of {len} bytes at {offset:#x} out of the {size} bytes of the binary")]
+    OutOfBounds {
+        /// The offset of the read.
+        offset: u64,
+        /// The number of bytes read.
+        len: u64,
+        /// The size of the binary.
+        size: u64,
+    },
+
     /// The `.text` section was not found.
     #[error(".text section not found")]
     TextSectionNotFound,
@@ -48,18 +186,272 @@ pub enum Error {
     #[error("JSON serialization error")]
     Json(#[from] serde_json::Error),
 
+    /// An error occurred during YAML serialization.
+    #[error("YAML serialization error")]
+    Yaml(#[from] serde_yaml::Error),
+
+    /// An error occurred during TOML serialization.
+    #[error("TOML serialization error")]
+    Toml(#[from] toml::ser::Error),
+
+    /// An error occurred during CBOR serialization.
+    #[error("CBOR serialization error")]
+    Cbor(#[from] ciborium::ser::Error<std::io::Error>),
+
+    /// An error occurred during MessagePack serialization.
+    #[error("MessagePack serialization error")]
+    MsgPack(#[from] rmp_serde::encode::Error),
+
+    /// A manifest could not be read or does not declare a known schema version.
+    #[error("Invalid manifest: {0}")]
+    InvalidManifest(String),
+
+    /// A signing or public key is malformed.
+    #[error("Invalid key: {0}")]
+    InvalidKey(String),
+
+    /// The signature of the manifests does not verify.
+    #[error("Invalid signature: {0}")]
+    InvalidSignature(String),
+
+    /// The URL of a MUD file is not an `https` URL.
+    #[error("Invalid MUD URL: {0}")]
+    InvalidMudUrl(String),
+
+    /// A plugin could not be loaded, or its passes clash.
+    #[error("Invalid plugin: {0}")]
+    InvalidPlugin(String),
+
+    /// A request to the analysis server is malformed.
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+
+    /// The manifest format is not one of the supported ones.
+    #[error("Unsupported manifest format: {0}")]
+    InvalidFormat(String),
+
+    /// A binary could not be downloaded, or its digest is not the one pinned.
+    #[error("Download failed: {0}")]
+    Download(String),
+
     /// A Gimli error occurred.
     #[error("Gimli error")]
     GimliError(#[from] gimli::Error),
 
+    /// An error occurred in the SQLite database of the results.
+    #[cfg(feature = "sqlite")]
+    #[error("Database error: {0}")]
+    Database(#[from] rusqlite::Error),
+
     /// An error occurred related to object handling.
     #[error("Object error")]
     ObjectError(#[from] object::Error),
 
+    /// The `.gopclntab` section of a Go binary could not be parsed.
+    #[error("Invalid .gopclntab: {0}")]
+    InvalidPclntab(String),
+
     /// The prefix was not found.
     #[error("Prefix not found")]
     PrefixNotFound,
+
+    /// An error, with where it occurred.
+    #[error("{source}, {context}")]
+    Context {
+        /// Where the error occurred.
+        context: ErrorContext,
+        /// The error.
+        source: Box<Error>,
+    },
+}
+
+/// The exit status of a run where some check failed: an invalid manifest, a signature that does
+/// not verify or a binary of a batch that could not be analyzed.
+pub const EXIT_FAILURE: i32 = 1;
+/// The exit status of the errors of no other class.
+pub const EXIT_ERROR: i32 = 2;
+/// The exit status of a binary without debug information.
+pub const EXIT_STRIPPED: i32 = 3;
+/// The exit status of a binary of an unsupported or unexpected architecture.
+pub const EXIT_UNSUPPORTED_ARCH: i32 = 4;
+/// The exit status of a binary where no API of the list was found.
+pub const EXIT_NO_API: i32 = 5;
+/// The exit status of an I/O error.
+pub const EXIT_IO: i32 = 6;
+/// The exit status of a malformed input: API list, configuration, rules, key or pattern.
+pub const EXIT_INVALID_INPUT: i32 = 7;
+/// The exit status of a binary that is not a well-formed ELF file.
+pub const EXIT_INVALID_ELF: i32 = 8;
+/// The exit status of a binary whose code looks compressed or encrypted.
+pub const EXIT_PACKED: i32 = 9;
+
+impl Error {
+    /// Tell where the error occurred.
+    ///
+    /// The errors of the inputs, e.g. an invalid API list, are left as they are since they
+    /// occur before the analysis, and so are the errors already given a context, whose
+    /// innermost context is the most precise.
+    pub fn with_context(self, context: ErrorContext) -> Self {
+        match self.kind() {
+            "invalid-input" => self,
+            _ if self.context().is_some() => self,
+            _ => Error::Context {
+                context,
+                source: Box::new(self),
+            },
+        }
+    }
+
+    /// Return where the error occurred, if known.
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            Error::Context { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+
+    /// Return the error without its context.
+    pub fn root(&self) -> &Error {
+        match self {
+            Error::Context { source, .. } => source.root(),
+            error => error,
+        }
+    }
+
+    /// Return the name of the class of the error, e.g. `stripped-binary`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Context { source, .. } => source.kind(),
+            Error::DebugInfo => "stripped-binary",
+            Error::UnsupportedArch(_) | Error::ArchMismatch { .. } => "unsupported-arch",
+            Error::PackedBinary(_) | Error::Unpacking(_) => "packed-binary",
+            Error::APIListEmpty => "no-api-found",
+            Error::Io(_) | Error::Download(_) => "io",
+            Error::InvalidApiList(_)
+            | Error::InvalidPattern(_)
+            | Error::InvalidVulnDb(_)
+            | Error::InvalidRules(_)
+            | Error::InvalidPolicy(_)
+            | Error::InvalidDeclaration(_)
+            | Error::InvalidConfig(_)
+            | Error::InvalidPatternFile(_)
+            | Error::InvalidPath(_)
+            | Error::InvalidFormat(_)
+            | Error::InvalidAnalysisMode(_)
+            | Error::InvalidScope(_)
+            | Error::InvalidKey(_)
+            | Error::InvalidMudUrl(_)
+            | Error::InvalidRequest(_)
+            | Error::InvalidPlugin(_) => "invalid-input",
+            Error::InvalidElf { .. }
+            | Error::InvalidPclntab(_)
+            | Error::GimliError(_)
+            | Error::ObjectError(_)
+            | Error::OutOfBounds { .. }
+            | Error::TextSectionNotFound
+            | Error::PLTSectionNotFound => "invalid-elf",
+            Error::InvalidManifest(_) => "invalid-manifest",
+            Error::InvalidSignature(_) => "invalid-signature",
+            _ => "error",
+        }
+    }
+
+    /// Return the exit status of the command line tool for the error.
+    pub fn exit_code(&self) -> i32 {
+        match self.kind() {
+            "stripped-binary" => EXIT_STRIPPED,
+            "unsupported-arch" => EXIT_UNSUPPORTED_ARCH,
+            "no-api-found" => EXIT_NO_API,
+            "io" => EXIT_IO,
+            "invalid-input" | "invalid-manifest" => EXIT_INVALID_INPUT,
+            "invalid-elf" => EXIT_INVALID_ELF,
+            "packed-binary" => EXIT_PACKED,
+            "invalid-signature" => EXIT_FAILURE,
+            _ => EXIT_ERROR,
+        }
+    }
+
+    /// Return the error as a JSON object with its class, message, causes and exit status, and
+    /// where it occurred when known.
+    pub fn to_json(&self) -> serde_json::Value {
+        let root = self.root();
+        let mut causes = Vec::new();
+        let mut source = std::error::Error::source(root);
+        while let Some(error) = source {
+            causes.push(error.to_string());
+            source = error.source();
+        }
+        let mut json = serde_json::json!({
+            "error": self.kind(),
+            "message": root.to_string(),
+            "causes": causes,
+            "exit_code": self.exit_code(),
+        });
+        if let Some(context) = self.context() {
+            json["context"] = context.to_json();
+        }
+        json
+    }
 }
 
 /// A specialized `Result` type for manifest-producer.
 pub type Result<T> = ::std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_json() {
+        let error = Error::from(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "fw.elf not found",
+        ));
+        assert_eq!(
+            error.to_json(),
+            serde_json::json!({
+                "error": "io",
+                "message": "I/O error",
+                "causes": ["fw.elf not found"],
+                "exit_code": EXIT_IO,
+            })
+        );
+        assert_eq!(Error::DebugInfo.exit_code(), EXIT_STRIPPED);
+        assert_eq!(Error::APIListEmpty.exit_code(), EXIT_NO_API);
+        assert_eq!(
+            Error::PackedBinary("UPX1".to_string()).exit_code(),
+            EXIT_PACKED
+        );
+        assert_eq!(
+            Error::UnsupportedArch("ARM".to_string()).exit_code(),
+            EXIT_UNSUPPORTED_ARCH
+        );
+    }
+
+    #[test]
+    fn test_error_context() {
+        let context = ErrorContext::new("flows")
+            .with_function("writeOnDrive")
+            .with_offset(0x1189);
+        let error = Error::TextSectionNotFound.with_context(context.clone());
+        assert_eq!(
+            error.to_string(),
+            ".text section not found, in flows of writeOnDrive at 0x1189"
+        );
+        assert_eq!(error.kind(), "invalid-elf");
+        assert_eq!(error.exit_code(), EXIT_INVALID_ELF);
+        assert!(matches!(error.root(), Error::TextSectionNotFound));
+        assert_eq!(
+            error.to_json()["context"],
+            serde_json::json!({"phase": "flows", "function": "writeOnDrive", "offset": "0x1189"})
+        );
+        assert_eq!(error.to_json()["message"], ".text section not found");
+
+        // The innermost context is kept, the errors of the inputs are left as they are.
+        let error = error.with_context(ErrorContext::new("data analysis"));
+        assert_eq!(error.context(), Some(&context));
+        let error = Error::InvalidPlugin("clash".to_string()).with_context(context);
+        assert!(matches!(error, Error::InvalidPlugin(_)));
+        assert_eq!(error.context(), None);
+    }
+}
@@ -0,0 +1,274 @@
+use std::collections::BTreeMap;
+
+use goblin::elf::Elf;
+
+use crate::{
+    arguments::{call_arguments, CallArguments},
+    call_graph::CallGraph,
+    cleanup::demangle_api_name,
+    elf_utils::API,
+    error::Result,
+    xrefs::XrefDb,
+};
+
+// The bits of the `open` flags telling the access mode, and those creating or truncating.
+const O_ACCMODE: u64 = 0o3;
+const O_WRONLY: u64 = 0o1;
+const O_RDWR: u64 = 0o2;
+const O_CREAT: u64 = 0o100;
+const O_TRUNC: u64 = 0o1000;
+
+/// What a call does with the file at a path.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Access {
+    /// The file is opened for reading, or its metadata is read.
+    Read,
+    /// The file is opened for writing, created or truncated.
+    Write,
+    /// The file is removed.
+    Delete,
+}
+
+impl Access {
+    /// Return the name of the access, as written in the feature manifest.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Access::Read => "read",
+            Access::Write => "write",
+            Access::Delete => "delete",
+        }
+    }
+}
+
+// How the access of a call is told: from its `open` flags or `fopen` mode, given as the index
+// of the argument, or from the function alone.
+#[derive(Clone, Copy)]
+enum Usage {
+    Flags(usize),
+    Mode(usize),
+    Always(Access),
+}
+
+// The functions taking a path, with the index of the path argument and how their access is told.
+const FILE_FUNCTIONS: [(&str, usize, Usage); 26] = [
+    ("open", 0, Usage::Flags(1)),
+    ("open64", 0, Usage::Flags(1)),
+    ("__open", 0, Usage::Flags(1)),
+    ("__open64", 0, Usage::Flags(1)),
+    ("__open_2", 0, Usage::Flags(1)),
+    ("__open64_2", 0, Usage::Flags(1)),
+    ("openat", 1, Usage::Flags(2)),
+    ("openat64", 1, Usage::Flags(2)),
+    ("fopen", 0, Usage::Mode(1)),
+    ("fopen64", 0, Usage::Mode(1)),
+    ("freopen", 0, Usage::Mode(1)),
+    ("creat", 0, Usage::Always(Access::Write)),
+    ("creat64", 0, Usage::Always(Access::Write)),
+    ("truncate", 0, Usage::Always(Access::Write)),
+    ("mkdir", 0, Usage::Always(Access::Write)),
+    ("stat", 0, Usage::Always(Access::Read)),
+    ("stat64", 0, Usage::Always(Access::Read)),
+    ("lstat", 0, Usage::Always(Access::Read)),
+    ("__xstat", 1, Usage::Always(Access::Read)),
+    ("__lxstat", 1, Usage::Always(Access::Read)),
+    ("access", 0, Usage::Always(Access::Read)),
+    ("opendir", 0, Usage::Always(Access::Read)),
+    ("unlink", 0, Usage::Always(Access::Delete)),
+    ("unlinkat", 1, Usage::Always(Access::Delete)),
+    ("remove", 0, Usage::Always(Access::Delete)),
+    ("rmdir", 0, Usage::Always(Access::Delete)),
+];
+
+/// A filesystem path used by the binary.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FileAccess {
+    /// The path, as given to the function, e.g. `/etc/passwd`.
+    pub path: String,
+    /// What is done with the file, sorted.
+    pub accesses: Vec<Access>,
+    /// The names of the functions using the path, sorted.
+    pub functions: Vec<String>,
+    /// The names of the APIs whose flow uses the path.
+    pub apis: Vec<String>,
+}
+
+/// List the filesystem paths used by the binary, from the constant paths given to `open`,
+/// `fopen`, `stat`, `unlink` and the like, with what is done with each of them.
+///
+/// A file opened by `open` is read or written as told by its flags, and one opened by `fopen`
+/// as told by its mode; when they are not constants, the file is counted as read. The paths
+/// built at run time, or passed by the caller of the function using them, cannot be recovered.
+///
+/// # Arguments
+///
+/// * `elf` - The ELF object representing the binary.
+/// * `buffer` - The buffer containing the binary data of the ELF file.
+/// * `graph` - The call graph of the binary.
+/// * `xrefs` - The cross-references of the functions, holding the strings they reference.
+/// * `api_list` - The APIs found, to tell which of them use each path.
+///
+/// # Returns
+///
+/// Returns a `Result` containing the paths, sorted.
+pub fn file_accesses(
+    elf: &Elf,
+    buffer: &[u8],
+    graph: &CallGraph,
+    xrefs: &XrefDb,
+    api_list: &[API],
+) -> Result<Vec<FileAccess>> {
+    let flows: Vec<_> = api_list
+        .iter()
+        .map(|api| (api.name.as_str(), graph.reachable(&[api.start_addr])))
+        .collect();
+    let functions = FILE_FUNCTIONS.map(|(function, _, _)| function);
+    let mut paths: BTreeMap<String, FileAccess> = BTreeMap::new();
+    for call in call_arguments(elf, buffer, graph, &functions)? {
+        let (_, index, usage) = FILE_FUNCTIONS
+            .iter()
+            .find(|(function, _, _)| *function == call.function)
+            .copied()
+            .unwrap();
+        let Some(path) = call.string(index, xrefs) else {
+            continue;
+        };
+        let file = paths.entry(path.clone()).or_insert_with(|| FileAccess {
+            path,
+            ..FileAccess::default()
+        });
+        file.accesses.extend(accesses(&call, usage, xrefs));
+        if let Some(func) = graph.function(call.caller) {
+            file.functions.push(demangle_api_name(&func.name));
+        }
+        file.apis.extend(
+            flows
+                .iter()
+                .filter(|(_, reachable)| reachable.contains(&call.caller))
+                .map(|(name, _)| name.to_string()),
+        );
+    }
+    Ok(paths
+        .into_values()
+        .map(|mut file| {
+            file.accesses.sort();
+            file.accesses.dedup();
+            for names in [&mut file.functions, &mut file.apis] {
+                names.sort();
+                names.dedup();
+            }
+            file
+        })
+        .collect())
+}
+
+/// Return the calls to the functions of [`file_accesses`] whose path is not a constant, i.e.
+/// built at run time or passed by the caller of the function using it.
+///
+/// # Arguments
+///
+/// * `elf` - The ELF object representing the binary.
+/// * `buffer` - The buffer containing the binary data of the ELF file.
+/// * `graph` - The call graph of the binary.
+/// * `xrefs` - The cross-references of the functions, holding the strings they reference.
+///
+/// # Returns
+///
+/// Returns a `Result` containing the calls, sorted by call site.
+pub fn unresolved_file_calls(
+    elf: &Elf,
+    buffer: &[u8],
+    graph: &CallGraph,
+    xrefs: &XrefDb,
+) -> Result<Vec<CallArguments>> {
+    let functions = FILE_FUNCTIONS.map(|(function, _, _)| function);
+    Ok(call_arguments(elf, buffer, graph, &functions)?
+        .into_iter()
+        .filter(|call| {
+            FILE_FUNCTIONS
+                .iter()
+                .find(|(function, _, _)| *function == call.function)
+                .is_some_and(|(_, index, _)| call.string(*index, xrefs).is_none())
+        })
+        .collect())
+}
+
+// Tell what a call does with its file.
+fn accesses(call: &CallArguments, usage: Usage, xrefs: &XrefDb) -> Vec<Access> {
+    match usage {
+        Usage::Always(access) => vec![access],
+        Usage::Flags(index) => match call.values[index] {
+            Some(flags) => flag_accesses(flags),
+            None => vec![Access::Read],
+        },
+        Usage::Mode(index) => match call.string(index, xrefs) {
+            Some(mode) => mode_accesses(&mode),
+            None => vec![Access::Read],
+        },
+    }
+}
+
+// Tell the access of the flags given to `open`.
+fn flag_accesses(flags: u64) -> Vec<Access> {
+    let mut accesses = Vec::new();
+    if flags & O_ACCMODE != O_WRONLY {
+        accesses.push(Access::Read);
+    }
+    if flags & O_ACCMODE == O_WRONLY
+        || flags & O_ACCMODE == O_RDWR
+        || flags & (O_CREAT | O_TRUNC) != 0
+    {
+        accesses.push(Access::Write);
+    }
+    accesses
+}
+
+// Tell the access of the mode given to `fopen`: `r` reads, `w` and `a` write, `+` does both.
+fn mode_accesses(mode: &str) -> Vec<Access> {
+    if mode.contains('+') {
+        return vec![Access::Read, Access::Write];
+    }
+    match mode.chars().next() {
+        Some('w') | Some('a') => vec![Access::Write],
+        _ => vec![Access::Read],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf_utils::read_elf_file;
+
+    #[test]
+    fn test_file_accesses() {
+        assert_eq!(flag_accesses(0), [Access::Read]);
+        assert_eq!(flag_accesses(O_WRONLY | O_CREAT), [Access::Write]);
+        assert_eq!(flag_accesses(O_RDWR), [Access::Read, Access::Write]);
+        assert_eq!(mode_accesses("rb"), [Access::Read]);
+        assert_eq!(mode_accesses("a"), [Access::Write]);
+        assert_eq!(mode_accesses("r+"), [Access::Read, Access::Write]);
+
+        let buffer = read_elf_file("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        let graph = CallGraph::build(&elf, &buffer, false).unwrap();
+        let xrefs = XrefDb::build(&elf, &buffer, &graph);
+        let files = file_accesses(&elf, &buffer, &graph, &xrefs, &[]).unwrap();
+
+        assert!(!files.is_empty());
+        assert!(files.windows(2).all(|pair| pair[0].path < pair[1].path));
+        assert!(files
+            .iter()
+            .all(|file| !file.accesses.is_empty() && !file.functions.is_empty()));
+        assert!(files.iter().all(|file| file.apis.is_empty()));
+
+        let unresolved = unresolved_file_calls(&elf, &buffer, &graph, &xrefs).unwrap();
+        assert!(!unresolved.is_empty());
+        assert!(unresolved
+            .windows(2)
+            .all(|pair| pair[0].site <= pair[1].site));
+        // `writeOnDrive` opens the constant `foo.txt`.
+        assert!(files.iter().any(|file| file.path == "foo.txt"));
+        assert!(unresolved.iter().all(|call| graph
+            .function(call.caller)
+            .is_some_and(|func| func.name != "writeOnDrive")));
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::BTreeSet;
+
+use goblin::elf::Elf;
+
+use crate::{
+    disassembler::{Disassembler, Instruction},
+    elf_utils::executable_regions,
+    error::Result,
+};
+
+/// The maximum number of instructions of a gadget, its final `ret` or indirect branch included.
+pub const MAX_GADGET_INSTRUCTIONS: usize = 5;
+
+// Maximum number of bytes before the final instruction tried as the start of a gadget.
+const MAX_GADGET_BYTES: usize = 24;
+
+/// The gadgets an attacker can chain in the code of a binary, as an indicator of how much a
+/// memory corruption gives away.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GadgetSurface {
+    /// The number of gadgets ending with a `ret`, for return-oriented programming.
+    pub rop: usize,
+    /// The number of gadgets ending with an indirect `jmp` or `call` through a register, for
+    /// jump-oriented programming.
+    pub jop: usize,
+    /// The size of the executable code scanned, in bytes.
+    pub code_size: u64,
+}
+
+impl GadgetSurface {
+    /// Return the number of gadgets per KiB of code.
+    pub fn density(&self) -> f64 {
+        match self.code_size {
+            0 => 0.0,
+            size => (self.rop + self.jop) as f64 * 1024.0 / size as f64,
+        }
+    }
+
+    /// Return the surface as the `gadgets` entry of the hardening section.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "ROP": self.rop,
+            "JOP": self.jop,
+            "code size": self.code_size,
+            "density": (self.density() * 100.0).round() / 100.0,
+            "max instructions": MAX_GADGET_INSTRUCTIONS,
+        })
+    }
+}
+
+/// Count the gadgets of the executable sections of the binary, or of its executable segments
+/// when it has no section headers.
+///
+/// A gadget is a sequence of at most [`MAX_GADGET_INSTRUCTIONS`] instructions, decoded from
+/// any byte, ending with a `ret` or an indirect branch and without other control flow; the
+/// gadgets are counted once per start address.
+///
+/// # Arguments
+///
+/// * `elf` - The parsed ELF structure.
+/// * `buffer` - The content of the binary.
+///
+/// # Returns
+///
+/// Returns a `Result` containing the gadgets found.
+///
+/// # Errors
+///
+/// Returns [`crate::error::Error::Capstone`] if the disassembler cannot be initialized.
+pub fn gadget_surface(elf: &Elf, buffer: &[u8]) -> Result<GadgetSurface> {
+    let disassembler = Disassembler::new()?;
+    let mut surface = GadgetSurface::default();
+    for (addr, offset, size) in executable_regions(elf) {
+        let Some(code) = usize::try_from(offset)
+            .ok()
+            .and_then(|start| buffer.get(start..start.checked_add(size as usize)?))
+        else {
+            continue;
+        };
+        let (rop, jop) = count_gadgets(&disassembler, code, addr);
+        surface.rop += rop;
+        surface.jop += jop;
+        surface.code_size += size;
+    }
+    Ok(surface)
+}
+
+// Count the ROP and JOP gadgets of code loaded at `addr`.
+fn count_gadgets(disassembler: &Disassembler, code: &[u8], addr: u64) -> (usize, usize) {
+    let (mut rop, mut jop) = (BTreeSet::new(), BTreeSet::new());
+    for end in 1..=code.len() {
+        let Some(kind) = terminator(&code[..end]) else {
+            continue;
+        };
+        let starts = if kind { &mut rop } else { &mut jop };
+        for start in end.saturating_sub(MAX_GADGET_BYTES + 3)..end {
+            let Ok(instructions) = disassembler.disassemble(&code[start..end], addr + start as u64)
+            else {
+                continue;
+            };
+            if is_gadget(&instructions, end - start) {
+                starts.insert(start);
+            }
+        }
+    }
+    (rop.len(), jop.len())
+}
+
+// Tell whether the code ends with the bytes of a gadget end: `Some(true)` for a `ret`,
+// `Some(false)` for an indirect `jmp` or `call` through a register, e.g. `jmp *%rax`.
+fn terminator(code: &[u8]) -> Option<bool> {
+    match code {
+        [.., 0xc3] | [.., 0xc2, _, _] => Some(true),
+        // FF /2 and FF /4, with a register or a register-indirect operand.
+        [.., 0xff, modrm] if matches!(modrm & 0xf8, 0xd0 | 0xe0 | 0x10 | 0x20) => match modrm & 7 {
+            // %rsp needs a SIB byte and %rbp a displacement in the register-indirect forms.
+            4 | 5 if modrm & 0xc0 == 0 => None,
+            _ => Some(false),
+        },
+        _ => None,
+    }
+}
+
+// Whether the instructions decode exactly `len` bytes into a gadget: short enough, ending with
+// its only control flow instruction.
+fn is_gadget(instructions: &[Instruction], len: usize) -> bool {
+    let Some((last, body)) = instructions.split_last() else {
+        return false;
+    };
+    instructions.len() <= MAX_GADGET_INSTRUCTIONS
+        && instructions.iter().map(|insn| insn.size).sum::<usize>() == len
+        && is_branch(last.name())
+        && body.iter().all(|insn| !is_branch(insn.name()))
+}
+
+// Whether an instruction transfers control.
+fn is_branch(name: &str) -> bool {
+    name.starts_with('j')
+        || name.starts_with("ret")
+        || name.starts_with("loop")
+        || name.starts_with("call")
+        || name.starts_with("int")
+        || name.starts_with("hlt")
+        || name.starts_with("ud")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf_utils::read_elf_file;
+
+    #[test]
+    fn test_gadgets() {
+        let disassembler = Disassembler::new().unwrap();
+        // pop %rax; ret: the ret alone and the pop before it.
+        assert_eq!(count_gadgets(&disassembler, &[0x58, 0xc3], 0x1000), (2, 0));
+        // A call ends no gadget: call 0x1005; ret.
+        let code = [0xe8, 0x00, 0x00, 0x00, 0x00, 0xc3];
+        let instructions = disassembler.disassemble(&code, 0x1000).unwrap();
+        assert!(!is_gadget(&instructions, code.len()));
+        assert!(is_gadget(&instructions[1..], 1));
+        // jmp *%rax, then jmp *%r8.
+        assert_eq!(
+            count_gadgets(&disassembler, &[0xff, 0xe0, 0x41, 0xff, 0xe0], 0x1000),
+            (0, 3)
+        );
+        assert_eq!(terminator(&[0xff, 0x24]), None);
+
+        let buffer = read_elf_file("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        let surface = gadget_surface(&elf, &buffer).unwrap();
+        assert!(surface.rop > 0 && surface.jop > 0);
+        assert!(surface.density() > 0.0);
+        assert_eq!(
+            surface.to_json()["max instructions"],
+            MAX_GADGET_INSTRUCTIONS
+        );
+    }
+}
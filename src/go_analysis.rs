@@ -0,0 +1,713 @@
+use goblin::elf::{program_header::PT_LOAD, Elf};
+use serde_json::{json, Map, Value};
+
+use crate::{api_detection::compile_patterns, elf_utils, error};
+use elf_utils::API;
+use error::{Error, Result};
+
+// Function names of the Go runtime that mark the entry points of a Go program.
+const RUNTIME_ENTRY_POINTS: [&str; 6] = [
+    "_rt0_amd64_linux",
+    "runtime.rt0_go",
+    "runtime.main",
+    "runtime.goexit",
+    "main.init",
+    "main.main",
+];
+
+/// Structure used to collect the data recovered from `.gopclntab` for a Go function.
+pub struct GoFunction {
+    /// The name of the function, in the Go `package.(*Type).Method` notation.
+    pub name: String,
+    /// The starting address of the function.
+    pub start_addr: u64,
+    /// The ending address of the function.
+    pub end_addr: u64,
+    /// The source file in which the function is defined, if recorded.
+    pub file: Option<String>,
+    /// The source line at the function entry, if recorded.
+    pub line: Option<u32>,
+}
+
+/// A Go module, as recorded in the build information of a binary.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GoModule {
+    /// The module path, e.g. `golang.org/x/net`.
+    pub path: String,
+    /// The version of the module, e.g. `v0.17.0`, or `(devel)` for the main module built from
+    /// its source tree.
+    pub version: String,
+    /// The `go.sum` checksum of the module, if recorded.
+    pub sum: Option<String>,
+    /// The module replacing it, from a `replace` directive.
+    pub replacement: Option<Box<GoModule>>,
+}
+
+impl GoModule {
+    /// Return the module as an entry of the `go build info` of the basic information manifest.
+    pub fn to_json(&self) -> Value {
+        let mut module = json!({
+            "path": self.path,
+            "version": self.version,
+            "sum": self.sum,
+        });
+        if let Some(replacement) = &self.replacement {
+            module["replaced by"] = replacement.to_json();
+        }
+        module
+    }
+}
+
+/// The build information embedded by the Go linker in `.go.buildinfo`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GoBuildInfo {
+    /// The version of the Go toolchain, e.g. `go1.21.5`.
+    pub go_version: String,
+    /// The package path of the `main` package.
+    pub path: Option<String>,
+    /// The main module, when the binary was built in module mode.
+    pub main: Option<GoModule>,
+    /// The modules the binary depends on, in the order of the module graph.
+    pub dependencies: Vec<GoModule>,
+    /// The build settings, e.g. `GOOS`, `CGO_ENABLED` or `vcs.revision`, in their order.
+    pub settings: Vec<(String, String)>,
+}
+
+impl GoBuildInfo {
+    /// Return the build information as the `go build info` entry of the basic information
+    /// manifest.
+    pub fn to_json(&self) -> Value {
+        let settings: Map<String, Value> = self
+            .settings
+            .iter()
+            .map(|(key, value)| (key.clone(), Value::String(value.clone())))
+            .collect();
+        json!({
+            "go version": self.go_version,
+            "path": self.path,
+            "main module": self.main.as_ref().map(GoModule::to_json),
+            "dependencies": self.dependencies.iter().map(GoModule::to_json).collect::<Vec<_>>(),
+            "settings": settings,
+        })
+    }
+}
+
+// The magic number starting the build information, aligned on 16 bytes.
+const BUILDINFO_MAGIC: &[u8; 14] = b"\xff Go buildinf:";
+
+// The length of the binary sentinels around the module information, so that the linker can
+// find it.
+const SENTINEL_LEN: usize = 16;
+
+// Versions of the pclntab layout, identified by the header magic number.
+#[derive(Clone, Copy, PartialEq)]
+enum PclnVersion {
+    Go12,
+    Go116,
+    Go118,
+    Go120,
+}
+
+// Decoded pclntab header, with every offset relative to the start of the table.
+struct PclnHeader {
+    version: PclnVersion,
+    quantum: u64,
+    ptr_size: usize,
+    nfunc: usize,
+    text_start: u64,
+    funcname_off: usize,
+    cu_off: usize,
+    filetab_off: usize,
+    pctab_off: usize,
+    functab_off: usize,
+}
+
+/// Locate the `.gopclntab` section and return its content.
+///
+/// # Arguments
+///
+/// * `elf` - A reference to the ELF structure representing the binary file.
+/// * `buffer` - The buffer containing the binary data of the ELF file.
+///
+/// # Returns
+///
+/// Returns the bytes of the `.gopclntab` section, or `None` if the binary was not built by Go.
+pub fn find_gopclntab<'a>(elf: &Elf, buffer: &'a [u8]) -> Option<&'a [u8]> {
+    let section = elf
+        .section_headers
+        .iter()
+        .find(|sec| elf.shdr_strtab.get_at(sec.sh_name) == Some(".gopclntab"))?;
+    let start = section.sh_offset as usize;
+    buffer.get(start..start + section.sh_size as usize)
+}
+
+/// Parse the Go program counter line table.
+///
+/// The table survives stripping, so this recovers the function names, their address ranges and
+/// their source position even when the symbol table is missing.
+/// Layouts from Go 1.2 up to Go 1.20+ are supported.
+///
+/// # Arguments
+///
+/// * `data` - The content of the `.gopclntab` section.
+///
+/// # Returns
+///
+/// Returns a `Result` containing the functions described by the table, sorted by address.
+pub fn parse_pclntab(data: &[u8]) -> Result<Vec<GoFunction>> {
+    let header = parse_header(data)?;
+    let mut functions = Vec::with_capacity(header.nfunc);
+
+    for i in 0..header.nfunc {
+        let (entry, func_off) = functab_entry(data, &header, i)?;
+        let (end, _) = functab_entry(data, &header, i + 1)?;
+        let func = offset(header.functab_off, func_off)?;
+
+        // The `_func` structure starts with the entry (a pointer before Go 1.18) and the name offset.
+        let fields = if header.version == PclnVersion::Go12 || header.version == PclnVersion::Go116
+        {
+            offset(func, header.ptr_size)?
+        } else {
+            offset(func, 4)?
+        };
+        let name_off = read_u32(data, fields)? as usize;
+        let name_base = if header.version == PclnVersion::Go12 {
+            0
+        } else {
+            header.funcname_off
+        };
+        let name = read_cstr(data, offset(name_base, name_off)?)
+            .ok_or_else(|| Error::InvalidPclntab(format!("bad name offset for function {}", i)))?;
+
+        // args, deferreturn, pcsp, pcfile, pcln, npcdata, cuOffset
+        let pcfile = read_u32(data, offset(fields, 16)?)? as usize;
+        let pcln = read_u32(data, offset(fields, 20)?)? as usize;
+        let cu_index = read_u32(data, offset(fields, 28)?).unwrap_or(0) as usize;
+
+        let file = first_pcvalue(data, &header, pcfile)
+            .and_then(|index| file_name(data, &header, cu_index, index));
+        let line = first_pcvalue(data, &header, pcln).and_then(|line| u32::try_from(line).ok());
+
+        functions.push(GoFunction {
+            name: name.to_string(),
+            start_addr: entry,
+            end_addr: end,
+            file,
+            line,
+        });
+    }
+
+    Ok(functions)
+}
+
+/// Do an API lookup in the Go function table.
+///
+/// Go binaries are often stripped, but the function names recovered from `.gopclntab` can still
+/// be matched against the list of API names provided.
+///
+/// # Arguments
+///
+/// * `functions` - The functions recovered from `.gopclntab`.
+/// * `api_list` - A vector containing the names or patterns of the APIs to search for.
+///
+/// # Returns
+///
+/// Returns a `Result` containing a vector of `API` structures representing the APIs found.
+pub fn go_api_search(functions: &[GoFunction], api_list: &[&str]) -> Result<Vec<API>> {
+    let patterns = compile_patterns(api_list)?;
+    Ok(functions
+        .iter()
+        .filter(|func| patterns.iter().any(|pattern| pattern.matches(&func.name)))
+        .map(|func| API::new(func.name.clone(), func.start_addr, func.end_addr))
+        .collect())
+}
+
+/// Return the functions of the Go runtime that act as program entry points.
+pub fn go_entry_points(functions: &[GoFunction]) -> Vec<&GoFunction> {
+    functions
+        .iter()
+        .filter(|func| RUNTIME_ENTRY_POINTS.contains(&func.name.as_str()))
+        .collect()
+}
+
+/// Read the Go version and the module graph embedded in a Go binary.
+///
+/// The information is found in the `.go.buildinfo` section, or by its magic number in a binary
+/// without section headers. Go 1.18 and later store it inline; older releases point to the
+/// strings in the data of the binary.
+///
+/// # Arguments
+///
+/// * `elf` - A reference to the ELF structure representing the binary file.
+/// * `buffer` - The buffer containing the binary data of the ELF file.
+///
+/// # Returns
+///
+/// Returns the build information, or `None` if the binary was not built by Go 1.13 or later.
+pub fn go_build_info(elf: &Elf, buffer: &[u8]) -> Option<GoBuildInfo> {
+    let section = elf
+        .section_headers
+        .iter()
+        .find(|sec| elf.shdr_strtab.get_at(sec.sh_name) == Some(".go.buildinfo"));
+    let start = match section {
+        Some(sec) => sec.sh_offset as usize,
+        None => (0..buffer.len().saturating_sub(32))
+            .step_by(16)
+            .find(|&at| buffer[at..].starts_with(BUILDINFO_MAGIC))?,
+    };
+    let header = buffer.get(start..start + 32)?;
+    if !header.starts_with(BUILDINFO_MAGIC) {
+        return None;
+    }
+    let (ptr_size, flags) = (header[14] as usize, header[15]);
+    let (version, modinfo) = if flags & 2 != 0 {
+        let mut pos = start + 32;
+        let version = varint_bytes(buffer, &mut pos)?;
+        (version, varint_bytes(buffer, &mut pos).unwrap_or_default())
+    } else {
+        if (ptr_size != 4 && ptr_size != 8) || flags & 1 != 0 {
+            return None;
+        }
+        let pointer = |at: usize| read_uint(header, at, ptr_size).ok();
+        let version = go_string(elf, buffer, pointer(16)?, ptr_size)?;
+        let modinfo = pointer(16 + ptr_size)
+            .and_then(|addr| go_string(elf, buffer, addr, ptr_size))
+            .unwrap_or_default();
+        (version, modinfo)
+    };
+    let version = std::str::from_utf8(version).ok()?;
+    if !version.starts_with("go") {
+        return None;
+    }
+    Some(parse_modinfo(version, modinfo))
+}
+
+// Parse the module information: tab-separated `path`, `mod`, `dep`, `=>` and `build` lines.
+fn parse_modinfo(go_version: &str, modinfo: &[u8]) -> GoBuildInfo {
+    let len = modinfo.len();
+    let modinfo = match len > 2 * SENTINEL_LEN && modinfo[len - SENTINEL_LEN - 1] == b'\n' {
+        true => &modinfo[SENTINEL_LEN..len - SENTINEL_LEN],
+        false => modinfo,
+    };
+    let mut info = GoBuildInfo {
+        go_version: go_version.to_string(),
+        ..GoBuildInfo::default()
+    };
+    let module = |fields: &[&str]| GoModule {
+        path: fields.first().unwrap_or(&"").to_string(),
+        version: fields.get(1).unwrap_or(&"").to_string(),
+        sum: fields
+            .get(2)
+            .filter(|sum| !sum.is_empty())
+            .map(|sum| sum.to_string()),
+        replacement: None,
+    };
+    for line in String::from_utf8_lossy(modinfo).lines() {
+        let fields: Vec<&str> = line.split('\t').collect();
+        match fields[0] {
+            "path" if fields.len() > 1 => info.path = Some(fields[1].to_string()),
+            "mod" if fields.len() > 1 => info.main = Some(module(&fields[1..])),
+            "dep" if fields.len() > 1 => info.dependencies.push(module(&fields[1..])),
+            // A replacement applies to the module of the line before.
+            "=>" if fields.len() > 1 => {
+                let replaced = match info.dependencies.last_mut() {
+                    Some(dep) => Some(dep),
+                    None => info.main.as_mut(),
+                };
+                if let Some(replaced) = replaced {
+                    replaced.replacement = Some(Box::new(module(&fields[1..])));
+                }
+            }
+            "build" if fields.len() > 1 => {
+                if let Some((key, value)) = fields[1].split_once('=') {
+                    info.settings.push((key.to_string(), value.to_string()));
+                }
+            }
+            _ => {}
+        }
+    }
+    info
+}
+
+// Read the bytes of a string prefixed by its length as an unsigned varint.
+fn varint_bytes<'a>(data: &'a [u8], pos: &mut usize) -> Option<&'a [u8]> {
+    let len = read_uvarint(data, pos)? as usize;
+    let bytes = data.get(*pos..pos.checked_add(len)?)?;
+    *pos += len;
+    Some(bytes)
+}
+
+// Read the bytes of the Go string whose header, a pointer and a length, is at an address of the
+// binary.
+fn go_string<'a>(elf: &Elf, buffer: &'a [u8], addr: u64, ptr_size: usize) -> Option<&'a [u8]> {
+    let header = loaded_bytes(elf, buffer, addr, 2 * ptr_size)?;
+    let data = read_uint(header, 0, ptr_size).ok()?;
+    let len = read_uint(header, ptr_size, ptr_size).ok()? as usize;
+    loaded_bytes(elf, buffer, data, len)
+}
+
+// Return the bytes loaded at an address of the binary.
+fn loaded_bytes<'a>(elf: &Elf, buffer: &'a [u8], addr: u64, len: usize) -> Option<&'a [u8]> {
+    let ph = elf.program_headers.iter().find(|ph| {
+        ph.p_type == PT_LOAD && ph.p_vaddr <= addr && addr + len as u64 <= ph.p_vaddr + ph.p_filesz
+    })?;
+    let start = (ph.p_offset + addr - ph.p_vaddr) as usize;
+    buffer.get(start..start + len)
+}
+
+// Decode the pclntab header.
+fn parse_header(data: &[u8]) -> Result<PclnHeader> {
+    let magic = read_u32(data, 0)?;
+    let version = match magic {
+        0xfffffffb => PclnVersion::Go12,
+        0xfffffffa => PclnVersion::Go116,
+        0xfffffff0 => PclnVersion::Go118,
+        0xfffffff1 => PclnVersion::Go120,
+        _ => return Err(Error::InvalidPclntab(format!("unknown magic {:#x}", magic))),
+    };
+    let quantum = *data
+        .get(6)
+        .ok_or(Error::InvalidPclntab("truncated".into()))? as u64;
+    let ptr_size = *data
+        .get(7)
+        .ok_or(Error::InvalidPclntab("truncated".into()))? as usize;
+    if ptr_size != 4 && ptr_size != 8 {
+        return Err(Error::InvalidPclntab(format!(
+            "unsupported pointer size {}",
+            ptr_size
+        )));
+    }
+    let word = |index: usize| read_uint(data, 8 + index * ptr_size, ptr_size);
+    let nfunc = word(0)?;
+    // Each function takes at least two words of the table, so a larger count is corrupted.
+    if nfunc > (data.len() / (2 * ptr_size)) as u64 {
+        return Err(Error::InvalidPclntab(format!(
+            "function count {} larger than the table",
+            nfunc
+        )));
+    }
+    let nfunc = nfunc as usize;
+
+    let header = match version {
+        PclnVersion::Go12 => {
+            let functab_off = 8 + ptr_size;
+            let filetab_at = nfunc
+                .checked_mul(2)
+                .and_then(|words| words.checked_add(1))
+                .and_then(|words| words.checked_mul(ptr_size))
+                .and_then(|size| size.checked_add(functab_off))
+                .ok_or_else(|| Error::InvalidPclntab("function table overflow".into()))?;
+            PclnHeader {
+                version,
+                quantum,
+                ptr_size,
+                nfunc,
+                text_start: 0,
+                funcname_off: 0,
+                cu_off: 0,
+                filetab_off: read_u32(data, filetab_at)? as usize,
+                pctab_off: 0,
+                functab_off: 0,
+            }
+        }
+        PclnVersion::Go116 => PclnHeader {
+            version,
+            quantum,
+            ptr_size,
+            nfunc,
+            text_start: 0,
+            funcname_off: word(2)? as usize,
+            cu_off: word(3)? as usize,
+            filetab_off: word(4)? as usize,
+            pctab_off: word(5)? as usize,
+            functab_off: word(6)? as usize,
+        },
+        PclnVersion::Go118 | PclnVersion::Go120 => PclnHeader {
+            version,
+            quantum,
+            ptr_size,
+            nfunc,
+            text_start: word(2)?,
+            funcname_off: word(3)? as usize,
+            cu_off: word(4)? as usize,
+            filetab_off: word(5)? as usize,
+            pctab_off: word(6)? as usize,
+            functab_off: word(7)? as usize,
+        },
+    };
+    Ok(header)
+}
+
+// Read the entry address and the `_func` offset of the i-th element of the function table.
+fn functab_entry(data: &[u8], header: &PclnHeader, index: usize) -> Result<(u64, usize)> {
+    match header.version {
+        PclnVersion::Go12 => {
+            let at = offset(
+                8 + header.ptr_size,
+                table_offset(index, 2 * header.ptr_size)?,
+            )?;
+            let entry = read_uint(data, at, header.ptr_size)?;
+            let func_off = read_uint(data, offset(at, header.ptr_size)?, header.ptr_size)? as usize;
+            Ok((entry, func_off))
+        }
+        PclnVersion::Go116 => {
+            let at = offset(
+                header.functab_off,
+                table_offset(index, 2 * header.ptr_size)?,
+            )?;
+            let entry = read_uint(data, at, header.ptr_size)?;
+            let func_off = read_uint(data, offset(at, header.ptr_size)?, header.ptr_size)? as usize;
+            Ok((entry, func_off))
+        }
+        PclnVersion::Go118 | PclnVersion::Go120 => {
+            let at = offset(header.functab_off, table_offset(index, 8)?)?;
+            let entry = header.text_start.wrapping_add(read_u32(data, at)? as u64);
+            Ok((entry, read_u32(data, offset(at, 4)?)? as usize))
+        }
+    }
+}
+
+// Return the value that a pc-value table associates with the function entry.
+fn first_pcvalue(data: &[u8], header: &PclnHeader, offset: usize) -> Option<i64> {
+    if offset == 0 {
+        return None;
+    }
+    let mut pos = header.pctab_off.checked_add(offset)?;
+    let delta = read_uvarint(data, &mut pos)?;
+    // The value starts at -1 and is updated with a zig-zag encoded delta.
+    let delta = ((delta >> 1) as i64) ^ -((delta & 1) as i64);
+    let _pc_delta = read_uvarint(data, &mut pos)?.wrapping_mul(header.quantum);
+    Some(delta - 1)
+}
+
+// Resolve a file index found in the pcfile table into the file name.
+fn file_name(data: &[u8], header: &PclnHeader, cu_index: usize, index: i64) -> Option<String> {
+    let index = usize::try_from(index).ok()?;
+    let offset = if header.version == PclnVersion::Go12 {
+        let at = index.checked_mul(4)?.checked_add(header.filetab_off)?;
+        read_u32(data, at).ok()? as usize
+    } else {
+        let at = cu_index
+            .checked_add(index)?
+            .checked_mul(4)?
+            .checked_add(header.cu_off)?;
+        header
+            .filetab_off
+            .checked_add(read_u32(data, at).ok()? as usize)?
+    };
+    read_cstr(data, offset).map(str::to_string)
+}
+
+// Add an offset read from the table to a position in it.
+fn offset(at: usize, by: usize) -> Result<usize> {
+    at.checked_add(by)
+        .ok_or_else(|| Error::InvalidPclntab(format!("offset overflow at {:#x}", at)))
+}
+
+// Return the offset of the i-th element of a table of entries of the given size.
+fn table_offset(index: usize, size: usize) -> Result<usize> {
+    index
+        .checked_mul(size)
+        .ok_or_else(|| Error::InvalidPclntab(format!("index {} out of the table", index)))
+}
+
+fn read_u32(data: &[u8], at: usize) -> Result<u32> {
+    read_uint(data, at, 4).map(|value| value as u32)
+}
+
+// Read a little-endian unsigned integer of the given size.
+fn read_uint(data: &[u8], at: usize, size: usize) -> Result<u64> {
+    let bytes = at
+        .checked_add(size)
+        .and_then(|end| data.get(at..end))
+        .ok_or_else(|| Error::InvalidPclntab(format!("read out of bounds at {:#x}", at)))?;
+    Ok(bytes
+        .iter()
+        .rev()
+        .fold(0u64, |acc, &byte| (acc << 8) | byte as u64))
+}
+
+fn read_uvarint(data: &[u8], pos: &mut usize) -> Option<u64> {
+    let mut value = 0u64;
+    let mut shift = 0;
+    loop {
+        let byte = *data.get(*pos)?;
+        *pos += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+        shift += 7;
+        if shift > 63 {
+            return None;
+        }
+    }
+}
+
+fn read_cstr(data: &[u8], at: usize) -> Option<&str> {
+    let bytes = data.get(at..)?;
+    let end = bytes.iter().position(|&b| b == 0)?;
+    std::str::from_utf8(&bytes[..end]).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Build a Go 1.20 pclntab describing `main.main` and `main.helper`.
+    fn sample_pclntab() -> Vec<u8> {
+        let mut data = vec![0xf1, 0xff, 0xff, 0xff, 0, 0, 1, 8];
+        let funcname_off = 8 + 8 * 8;
+        let names = b"main.main\0main.helper\0";
+        let cu_off = funcname_off + names.len();
+        let filetab_off = cu_off + 4;
+        let files = b"/src/main.go\0";
+        let pctab_off = filetab_off + files.len();
+        // pcfile: file 0, pcln: line 12 (deltas from -1, zig-zag encoded).
+        let pctab = [0u8, 0x02, 0x10, 0x00, 0x1a, 0x10, 0x00];
+        let functab_off = pctab_off + pctab.len();
+
+        for word in [
+            2,
+            1,
+            0x401000,
+            funcname_off,
+            cu_off,
+            filetab_off,
+            pctab_off,
+            functab_off,
+        ] {
+            data.extend_from_slice(&(word as u64).to_le_bytes());
+        }
+        data.extend_from_slice(names);
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(files);
+        data.extend_from_slice(&pctab);
+
+        let funcs_off = 3 * 8;
+        for (entry, func) in [(0x0u32, funcs_off), (0x40, funcs_off + 44), (0x80, 0)] {
+            data.extend_from_slice(&entry.to_le_bytes());
+            data.extend_from_slice(&(func as u32).to_le_bytes());
+        }
+        for (entry, name_off) in [(0x0u32, 0u32), (0x40, 10)] {
+            let fields = [entry, name_off, 0, 0, 0, 1, 4, 0, 0, 12, 0];
+            for field in fields {
+                data.extend_from_slice(&field.to_le_bytes());
+            }
+        }
+        data
+    }
+
+    // Build the module information of a binary, between its sentinels.
+    fn sample_modinfo() -> Vec<u8> {
+        let mut modinfo = vec![0x30; SENTINEL_LEN];
+        modinfo.extend_from_slice(
+            b"path\texample.com/sensor\n\
+              mod\texample.com/sensor\t(devel)\t\n\
+              dep\tgithub.com/eclipse/paho.mqtt.golang\tv1.4.3\th1:2kwcUGn8seMUfWndX0hGbvH8r7crgcJguQNCyp70xik=\n\
+              dep\tgolang.org/x/net\tv0.8.0\n\
+              =>\t../net\t(devel)\t\n\
+              build\tCGO_ENABLED=0\n\
+              build\tGOARCH=amd64\n",
+        );
+        modinfo.extend_from_slice(&[0xf9; SENTINEL_LEN]);
+        modinfo
+    }
+
+    #[test]
+    fn test_parse_modinfo() {
+        let info = parse_modinfo("go1.21.5", &sample_modinfo());
+        assert_eq!(info.path.as_deref(), Some("example.com/sensor"));
+        assert_eq!(info.main.as_ref().unwrap().version, "(devel)");
+        assert_eq!(info.main.as_ref().unwrap().sum, None);
+        assert_eq!(info.dependencies.len(), 2);
+        assert_eq!(info.dependencies[0].version, "v1.4.3");
+        assert!(info.dependencies[0]
+            .sum
+            .as_ref()
+            .unwrap()
+            .starts_with("h1:"));
+        assert_eq!(info.dependencies[1].sum, None);
+        let replacement = info.dependencies[1].replacement.as_ref().unwrap();
+        assert_eq!(replacement.path, "../net");
+        assert_eq!(info.settings[0], ("CGO_ENABLED".into(), "0".into()));
+
+        let json = info.to_json();
+        assert_eq!(json["settings"]["GOARCH"], "amd64");
+        assert_eq!(json["dependencies"][1]["replaced by"]["path"], "../net");
+        assert!(json["dependencies"][0].get("replaced by").is_none());
+    }
+
+    #[test]
+    fn test_go_build_info() {
+        let mut buffer =
+            elf_utils::read_elf_file("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        assert_eq!(go_build_info(&Elf::parse(&buffer).unwrap(), &buffer), None);
+
+        // The inline layout of Go 1.18, found by its magic number past the end of the image.
+        buffer.resize(buffer.len().next_multiple_of(16), 0);
+        buffer.extend_from_slice(BUILDINFO_MAGIC);
+        buffer.extend_from_slice(&[8, 2]);
+        buffer.extend_from_slice(&[0; 16]);
+        let modinfo = sample_modinfo();
+        buffer.extend_from_slice(&[8]);
+        buffer.extend_from_slice(b"go1.21.5");
+        buffer.extend_from_slice(&[
+            (modinfo.len() & 0x7f) as u8 | 0x80,
+            (modinfo.len() >> 7) as u8,
+        ]);
+        buffer.extend_from_slice(&modinfo);
+        let elf = Elf::parse(&buffer).unwrap();
+        let info = go_build_info(&elf, &buffer).unwrap();
+        assert_eq!(info.go_version, "go1.21.5");
+        assert_eq!(
+            info.dependencies[0].path,
+            "github.com/eclipse/paho.mqtt.golang"
+        );
+    }
+
+    #[test]
+    fn test_parse_pclntab() {
+        let functions = parse_pclntab(&sample_pclntab()).unwrap();
+        assert_eq!(functions.len(), 2);
+        assert_eq!(functions[0].name, "main.main");
+        assert_eq!(functions[0].start_addr, 0x401000);
+        assert_eq!(functions[0].end_addr, 0x401040);
+        assert_eq!(functions[0].file.as_deref(), Some("/src/main.go"));
+        assert_eq!(functions[0].line, Some(12));
+        assert_eq!(functions[1].name, "main.helper");
+        assert_eq!(functions[1].end_addr, 0x401080);
+    }
+
+    #[test]
+    fn test_parse_pclntab_bad_magic() {
+        assert!(parse_pclntab(&[0; 64]).is_err());
+    }
+
+    #[test]
+    fn test_parse_pclntab_bad_nfunc() {
+        let mut data = sample_pclntab();
+        data[8..16].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(matches!(
+            parse_pclntab(&data),
+            Err(Error::InvalidPclntab(_))
+        ));
+        // The Go 1.2 layout computes the offset of the file table from the count.
+        data[..4].copy_from_slice(&0xfffffffbu32.to_le_bytes());
+        assert!(matches!(
+            parse_pclntab(&data),
+            Err(Error::InvalidPclntab(_))
+        ));
+    }
+
+    #[test]
+    fn test_go_api_search_and_entry_points() {
+        let functions = parse_pclntab(&sample_pclntab()).unwrap();
+        let apis = go_api_search(&functions, &["main.h*"]).unwrap();
+        assert_eq!(apis.len(), 1);
+        assert_eq!(apis[0].start_addr, 0x401040);
+        let entry_points = go_entry_points(&functions);
+        assert_eq!(entry_points.len(), 1);
+        assert_eq!(entry_points[0].name, "main.main");
+    }
+}
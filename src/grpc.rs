@@ -0,0 +1,380 @@
+//! The gRPC service of `manifest-producer serve --grpc`, built with the `grpc` feature, from
+//! `proto/manifest_producer.proto`.
+//!
+//! `Analyze` streams the phases of the analysis of a binary as they start and end, the number
+//! of functions disassembled, then each manifest; `Diff` compares two versions of a binary. Both
+//! run in the bounded pool of workers of the HTTP server, with the same options.
+
+use std::{
+    net,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use serde_json::{json, Value};
+use tokio::sync::{mpsc, Semaphore};
+use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
+use tonic::{codegen::Bytes, Code, Request, Response, Status};
+use tracing::info;
+
+use crate::{
+    analyzer::Options,
+    api_detection::{parse_api_list, ApiSpec},
+    diff::{ManifestDiff, ManifestSet},
+    error::{Error, Result},
+    progress::{ProgressEvent, ProgressListener},
+    server::{analyze_upload, request_options, ServerConfig},
+};
+
+/// The messages and the client and server of the service, generated by tonic.
+#[allow(missing_docs, clippy::all)]
+pub mod proto {
+    tonic::include_proto!("manifest_producer.v1");
+}
+
+use proto::{
+    analyze_event::Event,
+    manifest_producer_server::{ManifestProducer, ManifestProducerServer},
+    AnalyzeEvent, AnalyzeRequest, Binary, DiffRequest, DiffResponse, FunctionProgress, Manifest,
+    PhaseFinished, PhaseStarted,
+};
+
+// Number of events buffered for a client reading them slowly.
+const EVENT_BUFFER: usize = 64;
+
+/// The gRPC service, running the analyses in a bounded pool of workers.
+pub struct Service {
+    config: ServerConfig,
+    workers: Arc<Semaphore>,
+    pending: Arc<AtomicUsize>,
+}
+
+// A call counted among those running or waiting for a worker, until dropped.
+struct Admission(Arc<AtomicUsize>);
+
+impl Drop for Admission {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Service {
+    /// Create the service, with the workers, queue and options of the configuration.
+    pub fn new(config: ServerConfig) -> Self {
+        Self {
+            workers: Arc::new(Semaphore::new(config.workers.max(1))),
+            pending: Arc::new(AtomicUsize::new(0)),
+            config,
+        }
+    }
+
+    /// Wrap the service into a tonic server accepting messages up to the largest upload.
+    pub fn into_server(self) -> ManifestProducerServer<Self> {
+        let max_upload = usize::try_from(self.config.max_upload).unwrap_or(usize::MAX);
+        ManifestProducerServer::new(self).max_decoding_message_size(max_upload)
+    }
+
+    // Count a call, unless as many as the workers and the queue hold are already running or
+    // waiting.
+    fn admit(&self) -> Option<Admission> {
+        let capacity = self.config.workers.max(1) + self.config.queue;
+        if self.pending.fetch_add(1, Ordering::SeqCst) >= capacity {
+            self.pending.fetch_sub(1, Ordering::SeqCst);
+            return None;
+        }
+        Some(Admission(self.pending.clone()))
+    }
+
+    // Read the API list and the options of a call.
+    fn read_options(&self, apis: &str, options: &str) -> Result<(Options, Vec<ApiSpec>)> {
+        let options: Value = match options.trim().is_empty() {
+            true => json!({}),
+            false => serde_json::from_str(options)?,
+        };
+        let (options, specs) = request_options(&self.config.options, &options)?;
+        let specs = match (apis.trim().is_empty(), specs) {
+            (false, _) => parse_api_list(&serde_json::from_str(apis)?)?,
+            (true, Some(specs)) => specs,
+            (true, None) => return Err(Error::InvalidRequest("no apis given".to_string())),
+        };
+        Ok((options, specs))
+    }
+}
+
+#[tonic::async_trait]
+impl ManifestProducer for Service {
+    type AnalyzeStream = ReceiverStream<std::result::Result<AnalyzeEvent, Status>>;
+
+    async fn analyze(
+        &self,
+        request: Request<AnalyzeRequest>,
+    ) -> std::result::Result<Response<Self::AnalyzeStream>, Status> {
+        let request = request.into_inner();
+        let binary = request
+            .binary
+            .ok_or_else(|| Status::invalid_argument("no binary given"))?;
+        let (options, specs) = self
+            .read_options(&request.apis, &request.options)
+            .map_err(|error| status(&error))?;
+        let admission = self.admit().ok_or_else(queue_full)?;
+        let workers = self.workers.clone();
+        let (sender, receiver) = mpsc::channel(EVENT_BUFFER);
+        tokio::spawn(async move {
+            let _admission = admission;
+            let Ok(_permit) = workers.acquire_owned().await else {
+                return;
+            };
+            let options = Options {
+                listener: Some(stream_progress(sender.clone())),
+                ..options
+            };
+            let analyzed = tokio::task::spawn_blocking(move || {
+                analyze_upload(&binary.name, binary.data, specs, options)
+            })
+            .await;
+            match analyzed {
+                Ok(Ok(manifests)) => {
+                    for (name, manifest) in manifests {
+                        let json = manifest.to_string();
+                        let event = Event::Manifest(Manifest { name, json });
+                        if sender
+                            .send(Ok(AnalyzeEvent { event: Some(event) }))
+                            .await
+                            .is_err()
+                        {
+                            break;
+                        }
+                    }
+                }
+                Ok(Err(error)) => {
+                    let _ = sender.send(Err(status(&error))).await;
+                }
+                Err(_) => {
+                    let _ = sender
+                        .send(Err(Status::internal("the analysis panicked")))
+                        .await;
+                }
+            }
+        });
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+
+    async fn diff(
+        &self,
+        request: Request<DiffRequest>,
+    ) -> std::result::Result<Response<DiffResponse>, Status> {
+        let request = request.into_inner();
+        let (Some(old), Some(new)) = (request.old, request.new) else {
+            return Err(Status::invalid_argument(
+                "the old and new binaries are required",
+            ));
+        };
+        let (options, specs) = self
+            .read_options(&request.apis, &request.options)
+            .map_err(|error| status(&error))?;
+        let _admission = self.admit().ok_or_else(queue_full)?;
+        let _permit = self
+            .workers
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|_| Status::unavailable("the server is shutting down"))?;
+        let diff = tokio::task::spawn_blocking(move || -> Result<ManifestDiff> {
+            let load = |Binary { name, data }| -> Result<ManifestSet> {
+                let manifests = analyze_upload(&name, data, specs.clone(), options.clone())?;
+                let manifest = |name: &str| manifests.get(name).cloned().unwrap_or_default();
+                Ok(ManifestSet::from_json(
+                    &manifest("flow_call"),
+                    &manifest("feature_manifest"),
+                ))
+            };
+            Ok(ManifestDiff::compute(&load(old)?, &load(new)?))
+        })
+        .await
+        .map_err(|_| Status::internal("the analysis panicked"))?
+        .map_err(|error| status(&error))?;
+        Ok(Response::new(DiffResponse {
+            json: diff.to_json().to_string(),
+            text: diff.to_string(),
+            empty: diff.is_empty(),
+        }))
+    }
+}
+
+/// Serve the gRPC service on a bound listener until the process ends.
+///
+/// # Errors
+///
+/// Returns [`Error::Io`] if the runtime cannot be started or the server fails.
+pub fn serve(listener: net::TcpListener, config: ServerConfig) -> Result<()> {
+    listener.set_nonblocking(true)?;
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async move {
+        let listener = tokio::net::TcpListener::from_std(listener)?;
+        info!(addr = %listener.local_addr()?, workers = config.workers, "listening");
+        tonic::transport::Server::builder()
+            .add_service(Service::new(config).into_server())
+            .serve_with_incoming(TcpListenerStream::new(listener))
+            .await
+            .map_err(|error| Error::Io(std::io::Error::other(error.to_string())))
+    })
+}
+
+// A listener streaming the phases of an analysis, and the functions disassembled at most once
+// per percent.
+fn stream_progress(
+    sender: mpsc::Sender<std::result::Result<AnalyzeEvent, Status>>,
+) -> ProgressListener {
+    let percent = AtomicUsize::new(0);
+    Arc::new(move |event: &ProgressEvent| {
+        let event = match *event {
+            ProgressEvent::PhaseStarted(phase) => {
+                percent.store(0, Ordering::Relaxed);
+                Event::PhaseStarted(PhaseStarted {
+                    phase: phase.to_string(),
+                })
+            }
+            ProgressEvent::PhaseFinished(phase, elapsed) => Event::PhaseFinished(PhaseFinished {
+                phase: phase.to_string(),
+                seconds: elapsed.as_secs_f64(),
+            }),
+            ProgressEvent::Item { done, total } => {
+                let reached = done * 100 / total.max(1);
+                if percent.fetch_max(reached, Ordering::Relaxed) >= reached && done != total {
+                    return;
+                }
+                Event::Functions(FunctionProgress {
+                    done: done as u64,
+                    total: total as u64,
+                })
+            }
+        };
+        // The analysis goes on when the client is gone.
+        let _ = sender.blocking_send(Ok(AnalyzeEvent { event: Some(event) }));
+    })
+}
+
+// The status of a call refused because the queue is full.
+fn queue_full() -> Status {
+    Status::resource_exhausted("the queue is full")
+}
+
+// The status of a failed call: the request was malformed, or its binary cannot be analyzed, or
+// the server failed. The details hold the error as with `--json-errors`.
+fn status(error: &Error) -> Status {
+    let code = match error.kind() {
+        "invalid-input" => Code::InvalidArgument,
+        "io" | "error" => Code::Internal,
+        _ => Code::FailedPrecondition,
+    };
+    Status::with_details(
+        code,
+        error.to_string(),
+        Bytes::from(error.to_json().to_string()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proto::manifest_producer_client::ManifestProducerClient;
+    use std::{fs, thread};
+
+    #[test]
+    fn test_grpc() {
+        let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            serve(
+                listener,
+                ServerConfig {
+                    workers: 1,
+                    ..ServerConfig::default()
+                },
+            )
+        });
+
+        let elf = fs::read("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let binary = Binary {
+            name: "fw.elf".to_string(),
+            data: elf,
+        };
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let mut client = ManifestProducerClient::connect(format!("http://{}", addr))
+                .await
+                .unwrap();
+            let mut stream = client
+                .analyze(AnalyzeRequest {
+                    binary: Some(binary.clone()),
+                    apis: r#"["writeOnDrive"]"#.to_string(),
+                    options: String::new(),
+                })
+                .await
+                .unwrap()
+                .into_inner();
+            let (mut phases, mut manifests) = (Vec::new(), Vec::new());
+            while let Some(event) = stream.message().await.unwrap() {
+                match event.event.unwrap() {
+                    Event::PhaseStarted(started) => phases.push(started.phase),
+                    Event::Manifest(manifest) => manifests.push(manifest),
+                    _ => {}
+                }
+            }
+            assert_eq!(phases.first().map(String::as_str), Some("parsing"));
+            assert!(phases.iter().any(|phase| phase == "disassembly"));
+            let flow_call = manifests
+                .iter()
+                .find(|manifest| manifest.name == "flow_call")
+                .unwrap();
+            let flow_call: Value = serde_json::from_str(&flow_call.json).unwrap();
+            assert_eq!(flow_call["Public APIs flow"][0]["name"], "writeOnDrive");
+
+            let diff = client
+                .diff(DiffRequest {
+                    old: Some(binary.clone()),
+                    new: Some(binary.clone()),
+                    apis: r#"["writeOnDrive"]"#.to_string(),
+                    options: String::new(),
+                })
+                .await
+                .unwrap()
+                .into_inner();
+            assert!(diff.empty);
+
+            let error = client
+                .analyze(AnalyzeRequest {
+                    binary: Some(binary.clone()),
+                    apis: String::new(),
+                    options: r#"{"output_dir": "/tmp"}"#.to_string(),
+                })
+                .await
+                .unwrap_err();
+            assert_eq!(error.code(), Code::InvalidArgument);
+            let mut stream = client
+                .analyze(AnalyzeRequest {
+                    binary: Some(Binary {
+                        name: "upload".to_string(),
+                        data: b"not an ELF".to_vec(),
+                    }),
+                    apis: r#"["main"]"#.to_string(),
+                    options: String::new(),
+                })
+                .await
+                .unwrap()
+                .into_inner();
+            let error = loop {
+                match stream.message().await {
+                    Ok(Some(_)) => continue,
+                    Ok(None) => panic!("the analysis of a non-ELF file succeeded"),
+                    Err(error) => break error,
+                }
+            };
+            assert_eq!(error.code(), Code::FailedPrecondition);
+            let details: Value = serde_json::from_slice(error.details()).unwrap();
+            assert_eq!(details["error"], "invalid-elf");
+        });
+    }
+}
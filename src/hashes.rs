@@ -0,0 +1,225 @@
+use goblin::elf::{
+    program_header::{PF_X, PT_LOAD},
+    section_header::{SHF_EXECINSTR, SHT_NOBITS},
+    Elf,
+};
+use serde_json::{json, Value};
+
+use crate::elf_utils::sha256_hex;
+
+// The number of bytes of the rolling hash window of ssdeep.
+const ROLLING_WINDOW: usize = 7;
+
+// The smallest block size of ssdeep.
+const MIN_BLOCKSIZE: u64 = 3;
+
+// The maximum number of characters of the first part of an ssdeep hash, the second part
+// holding half as many.
+const SPAMSUM_LENGTH: usize = 64;
+
+// The FNV parameters of the hash of each block.
+const HASH_PRIME: u32 = 0x0100_0193;
+const HASH_INIT: u32 = 0x2802_1967;
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// The digests of an executable section of the binary, or of an executable segment when it has
+/// no section headers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SectionHashes {
+    /// The name of the section, e.g. `.text`, or `LOAD[1]` for the second loadable segment.
+    pub name: String,
+    /// The SHA-256 digest of its content.
+    pub sha256: String,
+    /// The ssdeep fuzzy hash of its content.
+    pub ssdeep: String,
+}
+
+impl SectionHashes {
+    /// Return the digests as an entry of the `section hashes` of the basic information manifest.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "sha256": self.sha256,
+            "ssdeep": self.ssdeep,
+        })
+    }
+}
+
+/// Compute the ssdeep fuzzy hash of some bytes, as `blocksize:hash:hash`.
+///
+/// The content is cut where a rolling hash of its last 7 bytes hits a value depending on the
+/// block size, and each piece is written as one character: two binaries differing in a few
+/// places share most of their hash, and ssdeep compares them with a similarity score.
+pub fn ssdeep(data: &[u8]) -> String {
+    let mut block_size = MIN_BLOCKSIZE;
+    while block_size * (SPAMSUM_LENGTH as u64) < data.len() as u64 {
+        block_size *= 2;
+    }
+    loop {
+        let (first, second) = spamsum(data, block_size);
+        // A hash too short for the block size is computed again with smaller blocks.
+        if block_size > MIN_BLOCKSIZE && first.len() < SPAMSUM_LENGTH / 2 {
+            block_size /= 2;
+            continue;
+        }
+        return format!("{}:{}:{}", block_size, first, second);
+    }
+}
+
+/// Compute the digests of the executable sections of the binary with content in the file.
+///
+/// # Arguments
+///
+/// * `elf` - The parsed ELF structure.
+/// * `buffer` - The content of the binary.
+///
+/// # Returns
+///
+/// Returns the digests of each section, in the order of the section headers.
+pub fn section_hashes(elf: &Elf, buffer: &[u8]) -> Vec<SectionHashes> {
+    let sections: Vec<(String, u64, u64)> = if elf.section_headers.len() > 1 {
+        elf.section_headers
+            .iter()
+            .filter(|sh| {
+                sh.sh_type != SHT_NOBITS
+                    && sh.sh_size > 0
+                    && sh.sh_flags & u64::from(SHF_EXECINSTR) != 0
+            })
+            .map(|sh| {
+                (
+                    elf.shdr_strtab.get_at(sh.sh_name).unwrap_or("").to_string(),
+                    sh.sh_offset,
+                    sh.sh_size,
+                )
+            })
+            .collect()
+    } else {
+        elf.program_headers
+            .iter()
+            .filter(|ph| ph.p_type == PT_LOAD && ph.p_filesz > 0)
+            .enumerate()
+            .filter(|(_, ph)| ph.p_flags & PF_X != 0)
+            .map(|(index, ph)| (format!("LOAD[{}]", index), ph.p_offset, ph.p_filesz))
+            .collect()
+    };
+    sections
+        .into_iter()
+        .filter_map(|(name, offset, size)| {
+            let start = usize::try_from(offset).ok()?;
+            let data = buffer.get(start..start.checked_add(usize::try_from(size).ok()?)?)?;
+            Some(SectionHashes {
+                name,
+                sha256: sha256_hex(data),
+                ssdeep: ssdeep(data),
+            })
+        })
+        .collect()
+}
+
+// The rolling hash of the last bytes read, cutting the content into pieces.
+#[derive(Default)]
+struct RollingHash {
+    window: [u8; ROLLING_WINDOW],
+    h1: u32,
+    h2: u32,
+    h3: u32,
+    n: usize,
+}
+
+impl RollingHash {
+    // Read a byte and return the hash of the window.
+    fn update(&mut self, byte: u8) -> u32 {
+        let byte32 = u32::from(byte);
+        let slot = self.n % ROLLING_WINDOW;
+        self.h2 = self
+            .h2
+            .wrapping_sub(self.h1)
+            .wrapping_add(ROLLING_WINDOW as u32 * byte32);
+        self.h1 = self
+            .h1
+            .wrapping_add(byte32)
+            .wrapping_sub(u32::from(self.window[slot]));
+        self.window[slot] = byte;
+        self.n += 1;
+        self.h3 = (self.h3 << 5) ^ byte32;
+        self.h1.wrapping_add(self.h2).wrapping_add(self.h3)
+    }
+}
+
+// Compute the two parts of the ssdeep hash for a block size and twice it.
+fn spamsum(data: &[u8], block_size: u64) -> (String, String) {
+    let block_size = block_size as u32;
+    let mut roll = RollingHash::default();
+    let (mut first, mut second) = (Vec::new(), Vec::new());
+    let (mut h1, mut h2) = (HASH_INIT, HASH_INIT);
+    let mut rolling = 0;
+    // The last character of each part is rewritten once the part is full.
+    let push = |part: &mut Vec<u8>, max: usize, h: &mut u32| {
+        let character = BASE64[(*h % 64) as usize];
+        if part.len() < max {
+            part.push(character);
+            *h = HASH_INIT;
+        } else {
+            *part.last_mut().unwrap() = character;
+        }
+    };
+    for &byte in data {
+        rolling = roll.update(byte);
+        h1 = h1.wrapping_mul(HASH_PRIME) ^ u32::from(byte);
+        h2 = h2.wrapping_mul(HASH_PRIME) ^ u32::from(byte);
+        if rolling % block_size == block_size - 1 {
+            push(&mut first, SPAMSUM_LENGTH - 1, &mut h1);
+        }
+        if rolling % (block_size * 2) == block_size * 2 - 1 {
+            push(&mut second, SPAMSUM_LENGTH / 2 - 1, &mut h2);
+        }
+    }
+    // The end of the content closes the last pieces.
+    if rolling != 0 {
+        first.push(BASE64[(h1 % 64) as usize]);
+        second.push(BASE64[(h2 % 64) as usize]);
+    }
+    (
+        String::from_utf8(first).unwrap(),
+        String::from_utf8(second).unwrap(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_ssdeep() {
+        assert_eq!(ssdeep(b""), "3::");
+        let data = fs::read("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let hash = ssdeep(&data);
+        let parts: Vec<&str> = hash.split(':').collect();
+        assert_eq!(parts.len(), 3);
+        let block_size: u64 = parts[0].parse().unwrap();
+        assert_eq!(block_size % MIN_BLOCKSIZE, 0);
+        assert!(parts[1].len() >= SPAMSUM_LENGTH / 2 && parts[1].len() <= SPAMSUM_LENGTH);
+        assert!(parts[2].len() <= SPAMSUM_LENGTH / 2);
+        assert_eq!(ssdeep(&data), hash);
+
+        // A few bytes changed leave most of the hash untouched.
+        let mut patched = data.clone();
+        patched[data.len() / 2..data.len() / 2 + 16].fill(0xcc);
+        let patched_hash = ssdeep(&patched);
+        assert_ne!(patched_hash, hash);
+        let common = hash
+            .chars()
+            .zip(patched_hash.chars())
+            .take_while(|(a, b)| a == b)
+            .count();
+        assert!(common > hash.len() / 4);
+
+        let elf = Elf::parse(&data).unwrap();
+        let sections = section_hashes(&elf, &data);
+        let text = sections.iter().find(|s| s.name == ".text").unwrap();
+        assert_eq!(text.sha256.len(), 64);
+        assert!(text.ssdeep.contains(':'));
+        assert!(sections.iter().all(|s| s.name != ".data"));
+    }
+}
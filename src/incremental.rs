@@ -0,0 +1,220 @@
+use std::{collections::BTreeSet, fs, path::Path};
+
+use serde_json::{json, Value};
+
+use crate::{
+    call_graph::CallGraph, cleanup::demangle_api_name, elf_utils::API, error::Result,
+    manifest_creation::read_manifest, signing::SIGNATURE_FILE,
+};
+
+/// The name of the report of an incremental analysis, written next to the manifests.
+pub const REPORT_FILE: &str = "incremental.json";
+
+/// What an incremental analysis reused from the previous build and recomputed.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IncrementalReport {
+    /// The cache key of the previous build, `None` when there was none to start from.
+    pub baseline: Option<String>,
+    /// The cache key of the build analyzed.
+    pub key: String,
+    /// The number of functions of the build.
+    pub functions: usize,
+    /// The number of functions whose calls and references were reused.
+    pub reused: usize,
+    /// The functions disassembled again, by name.
+    pub recomputed: Vec<String>,
+    /// The APIs that reach a function disassembled again.
+    pub apis_affected: Vec<String>,
+    /// The manifests rewritten because their content changed.
+    pub manifests_updated: Vec<String>,
+    /// The manifests left as they were.
+    pub manifests_unchanged: Vec<String>,
+}
+
+impl IncrementalReport {
+    /// Describe the functions reused and recomputed in the call graph of a build.
+    ///
+    /// # Arguments
+    ///
+    /// * `baseline` - The cache key of the previous build.
+    /// * `key` - The cache key of the build analyzed.
+    /// * `graph` - The call graph of the build.
+    /// * `reused` - The starting addresses of the functions reused.
+    /// * `apis` - The APIs found in the build.
+    pub fn new(
+        baseline: Option<String>,
+        key: String,
+        graph: &CallGraph,
+        reused: &BTreeSet<u64>,
+        apis: &[API],
+    ) -> Self {
+        let recomputed: BTreeSet<u64> = graph
+            .functions()
+            .map(|func| func.start_addr)
+            .filter(|addr| !reused.contains(addr))
+            .collect();
+        let apis_affected = apis
+            .iter()
+            .filter(|api| {
+                graph
+                    .reachable(&[api.start_addr])
+                    .iter()
+                    .any(|addr| recomputed.contains(addr))
+            })
+            .map(|api| api.name.clone())
+            .collect();
+        Self {
+            baseline,
+            key,
+            functions: graph.functions().count(),
+            reused: reused.len(),
+            recomputed: recomputed
+                .iter()
+                .filter_map(|&addr| graph.function(addr))
+                .map(|func| demangle_api_name(&func.name))
+                .collect(),
+            apis_affected,
+            ..Self::default()
+        }
+    }
+
+    /// A one-line summary of the report.
+    pub fn summary(&self) -> String {
+        format!(
+            "{} of {} functions re-analyzed, {} manifests updated",
+            self.recomputed.len(),
+            self.functions,
+            self.manifests_updated.len()
+        )
+    }
+
+    /// Describe the report as a JSON document.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "baseline": self.baseline,
+            "key": self.key,
+            "functions": {
+                "total": self.functions,
+                "reused": self.reused,
+                "recomputed": self.recomputed,
+            },
+            "apis_affected": self.apis_affected,
+            "manifests": {
+                "updated": self.manifests_updated,
+                "unchanged": self.manifests_unchanged,
+            },
+        })
+    }
+
+    /// Write the report to [`REPORT_FILE`] in the directory `path`.
+    pub fn write(&self, path: &str) -> Result<()> {
+        fs::write(
+            Path::new(path).join(REPORT_FILE),
+            serde_json::to_string_pretty(&self.to_json())?,
+        )?;
+        Ok(())
+    }
+}
+
+/// Read the cache key of the build whose manifests are in the directory `path`, from the
+/// report of its incremental analysis.
+pub fn read_baseline(path: &str) -> Option<String> {
+    let report: Value =
+        serde_json::from_slice(&fs::read(Path::new(path).join(REPORT_FILE)).ok()?).ok()?;
+    report["key"].as_str().map(str::to_string)
+}
+
+/// Move the manifests of the directory `scratch` to the directory `path`, replacing only those
+/// whose content changed; the time of the analysis in their `generator` block is not compared.
+///
+/// # Returns
+///
+/// Returns a `Result` containing the names of the manifests updated and unchanged.
+///
+/// # Errors
+///
+/// Returns an error if a manifest cannot be read or moved.
+pub fn patch_manifests(scratch: &str, path: &str) -> Result<(Vec<String>, Vec<String>)> {
+    let mut names: Vec<String> = fs::read_dir(scratch)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_file())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| name != SIGNATURE_FILE && name != REPORT_FILE)
+        .collect();
+    names.sort();
+    let (mut updated, mut unchanged) = (Vec::new(), Vec::new());
+    for name in names {
+        let (new, old) = (Path::new(scratch).join(&name), Path::new(path).join(&name));
+        if same_content(&old, &new)? {
+            unchanged.push(name);
+        } else {
+            fs::rename(&new, &old).or_else(|_| fs::copy(&new, &old).map(|_| ()))?;
+            updated.push(name);
+        }
+    }
+    Ok((updated, unchanged))
+}
+
+// Whether two versions of a manifest are the same, but for the time of their analysis.
+fn same_content(old: &Path, new: &Path) -> Result<bool> {
+    let contents = fs::read(new)?;
+    match fs::read(old) {
+        Ok(previous) if previous == contents => return Ok(true),
+        Ok(_) => {}
+        Err(_) => return Ok(false),
+    }
+    let undated = |path: &Path| {
+        let mut manifest = read_manifest(path.to_str()?).ok()?;
+        manifest
+            .get_mut("generator")?
+            .as_object_mut()?
+            .remove("timestamp");
+        Some(manifest)
+    };
+    Ok(matches!((undated(old), undated(new)), (Some(old), Some(new)) if old == new))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env::temp_dir;
+
+    #[test]
+    fn test_patch_manifests() {
+        let dir = temp_dir().join("manifest-incremental");
+        let _ = fs::remove_dir_all(&dir);
+        let (scratch, path) = (dir.join("scratch"), dir.join("out"));
+        fs::create_dir_all(&scratch).unwrap();
+        fs::create_dir_all(&path).unwrap();
+        fs::write(path.join("flow_call.json"), "{}").unwrap();
+        fs::write(scratch.join("flow_call.json"), "{}").unwrap();
+        fs::write(scratch.join("basic_info.json"), "{\"name\": \"fw\"}").unwrap();
+        let dated = |time: &str| format!("{{\"generator\": {{\"timestamp\": \"{}\"}}}}", time);
+        fs::write(
+            path.join("feature_manifest.json"),
+            dated("2024-01-01T00:00:00Z"),
+        )
+        .unwrap();
+        fs::write(
+            scratch.join("feature_manifest.json"),
+            dated("2024-06-01T00:00:00Z"),
+        )
+        .unwrap();
+
+        let (updated, unchanged) =
+            patch_manifests(scratch.to_str().unwrap(), path.to_str().unwrap()).unwrap();
+        assert_eq!(updated, ["basic_info.json"]);
+        assert_eq!(unchanged, ["feature_manifest.json", "flow_call.json"]);
+        assert!(path.join("basic_info.json").is_file());
+
+        let report = IncrementalReport {
+            key: "build-id-1".to_string(),
+            ..IncrementalReport::default()
+        };
+        report.write(path.to_str().unwrap()).unwrap();
+        assert_eq!(
+            read_baseline(path.to_str().unwrap()).as_deref(),
+            Some("build-id-1")
+        );
+    }
+}
@@ -0,0 +1,485 @@
+use std::collections::{BTreeSet, HashMap};
+
+use goblin::elf::{
+    reloc::{R_X86_64_64, R_X86_64_GLOB_DAT, R_X86_64_JUMP_SLOT, R_X86_64_RELATIVE},
+    section_header::SHT_NOBITS,
+    Elf,
+};
+
+// Maximum number of entries read from a table of function pointers indexed by a register.
+const MAX_TABLE_ENTRIES: usize = 64;
+
+// Registers that a called function is allowed to overwrite (System V ABI).
+pub(crate) const CALLER_SAVED: [&str; 9] = [
+    "%rax", "%rcx", "%rdx", "%rsi", "%rdi", "%r8", "%r9", "%r10", "%r11",
+];
+
+/// A value found in a pointer-sized slot of the binary.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PointerValue {
+    /// The address of code or data of the binary itself.
+    Address(u64),
+    /// A function imported from a shared library, resolved at load time.
+    Import(String),
+}
+
+/// The outcome of the analysis of a call instruction.
+#[derive(Debug, PartialEq)]
+pub enum IndirectResolution {
+    /// The instruction is a direct call.
+    Direct,
+    /// The instruction is an indirect call whose possible targets are statically known.
+    Resolved(Vec<PointerValue>),
+    /// The instruction is an indirect call whose target could not be determined.
+    Unresolved,
+}
+
+/// Reads function pointers stored in the binary.
+///
+/// Pointers are looked up in the dynamic relocations first, as position-independent binaries
+/// and GOT entries only get their value at load time, and then in the file content. The slots of
+/// the C++ virtual tables (`_ZTV*` symbols) are indexed by offset for virtual call resolution.
+pub struct PointerResolver<'a> {
+    relocs: HashMap<u64, PointerValue>,
+    sections: Vec<(u64, &'a [u8])>,
+    vtable_slots: HashMap<u64, BTreeSet<PointerValue>>,
+}
+
+impl<'a> PointerResolver<'a> {
+    /// Creates the resolver for the given binary.
+    pub fn new(elf: &'a Elf<'a>, buffer: &'a [u8]) -> Self {
+        let mut relocs = HashMap::new();
+        for reloc in elf
+            .dynrelas
+            .iter()
+            .chain(elf.dynrels.iter())
+            .chain(elf.pltrelocs.iter())
+        {
+            let value = match reloc.r_type {
+                R_X86_64_RELATIVE => reloc
+                    .r_addend
+                    .map(|addend| PointerValue::Address(addend as u64)),
+                R_X86_64_GLOB_DAT | R_X86_64_JUMP_SLOT | R_X86_64_64 => elf
+                    .dynsyms
+                    .get(reloc.r_sym)
+                    .filter(|sym| sym.st_value == 0)
+                    .and_then(|sym| elf.dynstrtab.get_at(sym.st_name))
+                    .map(|name| PointerValue::Import(name.to_string())),
+                _ => None,
+            };
+            if let Some(value) = value {
+                relocs.insert(reloc.r_offset, value);
+            }
+        }
+
+        let sections = elf
+            .section_headers
+            .iter()
+            .filter(|sec| sec.sh_type != SHT_NOBITS && sec.sh_addr != 0)
+            .filter_map(|sec| {
+                let start = sec.sh_offset as usize;
+                let data = buffer.get(start..start.checked_add(sec.sh_size as usize)?)?;
+                Some((sec.sh_addr, data))
+            })
+            .collect();
+
+        let mut resolver = Self {
+            relocs,
+            sections,
+            vtable_slots: HashMap::new(),
+        };
+
+        // A vtable starts with the offset-to-top and the typeinfo pointer, then the virtual functions.
+        for sym in elf.syms.iter().filter(|sym| sym.st_size > 16) {
+            let is_vtable = elf
+                .strtab
+                .get_at(sym.st_name)
+                .is_some_and(|name| name.starts_with("_ZTV"));
+            if !is_vtable {
+                continue;
+            }
+            for offset in (0..sym.st_size - 16).step_by(8) {
+                let slot = sym.st_value.checked_add(16 + offset);
+                if let Some(value) = slot.and_then(|slot| resolver.read_pointer(slot)) {
+                    resolver
+                        .vtable_slots
+                        .entry(offset)
+                        .or_default()
+                        .insert(value);
+                }
+            }
+        }
+        resolver
+    }
+
+    /// Read the pointer stored at the given address.
+    pub fn read_pointer(&self, addr: u64) -> Option<PointerValue> {
+        if let Some(value) = self.relocs.get(&addr) {
+            return Some(value.clone());
+        }
+        let (start, data) = self.sections.iter().find(|(start, data)| {
+            let end = start.checked_add(data.len() as u64);
+            *start <= addr && addr.checked_add(8).is_some_and(|last| Some(last) <= end)
+        })?;
+        let at = (addr - start) as usize;
+        let value = u64::from_le_bytes(data[at..at + 8].try_into().ok()?);
+        (value != 0).then_some(PointerValue::Address(value))
+    }
+
+    /// Return the functions found at the given offset of any virtual table.
+    pub fn vtable_candidates(&self, offset: u64) -> Vec<PointerValue> {
+        self.vtable_slots
+            .get(&offset)
+            .map(|slots| slots.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+// What is known about the content of a register.
+#[derive(Clone, Debug, PartialEq)]
+enum RegValue {
+    // A constant address.
+    Pointer(PointerValue),
+    // The first word of an object, i.e. its vtable pointer.
+    Vptr,
+    // A slot of an unknown vtable, at the given offset.
+    VtableSlot(u64),
+}
+
+/// Tracks the values of the registers along the instructions of a function.
+///
+/// This is a linear, intra-procedural points-to analysis: registers loaded with a constant
+/// address, with a pointer read from the binary, or with a vtable slot are remembered until
+/// they are overwritten, and any other write forgets them.
+#[derive(Default)]
+pub struct RegisterState {
+    regs: HashMap<String, RegValue>,
+}
+
+impl RegisterState {
+    /// Creates the state at the beginning of a function, where nothing is known.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Update the state with the effect of an instruction.
+    ///
+    /// # Arguments
+    ///
+    /// * `mnemonic` - The mnemonic of the instruction, in AT&T syntax.
+    /// * `op_str` - The operands of the instruction, in AT&T syntax.
+    /// * `next_addr` - The address of the next instruction, base of RIP-relative operands.
+    /// * `resolver` - The resolver used to read pointers from the binary.
+    pub fn update(
+        &mut self,
+        mnemonic: &str,
+        op_str: &str,
+        next_addr: u64,
+        resolver: &PointerResolver,
+    ) {
+        if mnemonic.starts_with("call") {
+            for reg in CALLER_SAVED {
+                self.regs.remove(reg);
+            }
+            return;
+        }
+        let Some((src, dst)) = op_str.rsplit_once(", ") else {
+            return;
+        };
+        let Some(dst) = register(dst) else {
+            return;
+        };
+
+        let value = if mnemonic.starts_with("lea") {
+            self.effective_address(src, next_addr)
+                .map(|addr| RegValue::Pointer(PointerValue::Address(addr)))
+        } else if matches!(mnemonic, "mov" | "movq" | "movl" | "movabs" | "movabsq") {
+            if let Some(imm) = src.strip_prefix('$') {
+                immediate(imm).map(|addr| RegValue::Pointer(PointerValue::Address(addr)))
+            } else if let Some(reg) = register(src) {
+                self.regs.get(&reg).cloned()
+            } else {
+                self.load(src, next_addr, resolver)
+            }
+        } else if mnemonic.starts_with("xor") && register(src).as_ref() == Some(&dst) {
+            Some(RegValue::Pointer(PointerValue::Address(0)))
+        } else {
+            None
+        };
+
+        match value {
+            Some(value) => self.regs.insert(dst, value),
+            None => self.regs.remove(&dst),
+        };
+    }
+
+    /// Return the constant held by a register (e.g. `%rdi`), when it is known.
+    ///
+    /// This is how the constant arguments of a call are recovered, before the call instruction.
+    pub fn constant(&self, reg: &str) -> Option<u64> {
+        match self.regs.get(reg)? {
+            RegValue::Pointer(PointerValue::Address(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Determine the possible targets of a call instruction.
+    ///
+    /// # Arguments
+    ///
+    /// * `op_str` - The operand of the call, in AT&T syntax.
+    /// * `next_addr` - The address of the next instruction, base of RIP-relative operands.
+    /// * `resolver` - The resolver used to read pointers from the binary.
+    pub fn resolve_call(
+        &self,
+        op_str: &str,
+        next_addr: u64,
+        resolver: &PointerResolver,
+    ) -> IndirectResolution {
+        let Some(operand) = op_str.strip_prefix('*') else {
+            return IndirectResolution::Direct;
+        };
+
+        let targets = if let Some(reg) = register(operand) {
+            match self.regs.get(&reg) {
+                Some(RegValue::Pointer(value)) => vec![value.clone()],
+                Some(RegValue::VtableSlot(offset)) => resolver.vtable_candidates(*offset),
+                _ => Vec::new(),
+            }
+        } else if let Some(table) = self.indexed_table(operand) {
+            // A table of callbacks indexed by a register: every entry is a possible target.
+            (0..MAX_TABLE_ENTRIES as u64)
+                .map_while(|i| resolver.read_pointer(table.checked_add(i * 8)?))
+                .collect()
+        } else {
+            match self.load(operand, next_addr, resolver) {
+                Some(RegValue::Pointer(value)) => vec![value],
+                Some(RegValue::VtableSlot(offset)) => resolver.vtable_candidates(offset),
+                _ => Vec::new(),
+            }
+        };
+
+        if targets.is_empty() {
+            IndirectResolution::Unresolved
+        } else {
+            IndirectResolution::Resolved(targets)
+        }
+    }
+
+    // Compute the address designated by a `disp(%base)` memory operand, when the base is known.
+    fn effective_address(&self, operand: &str, next_addr: u64) -> Option<u64> {
+        let (disp, base) = memory_operand(operand)?;
+        if base == "%rip" {
+            return Some(next_addr.wrapping_add(disp as u64));
+        }
+        match self.regs.get(&base)? {
+            RegValue::Pointer(PointerValue::Address(addr)) => Some(addr.wrapping_add(disp as u64)),
+            _ => None,
+        }
+    }
+
+    // Compute the address of a `disp(%base, %index, 8)` table of pointers, when the base is known.
+    fn indexed_table(&self, operand: &str) -> Option<u64> {
+        let (disp, rest) = operand.split_once('(')?;
+        let parts: Vec<&str> = rest.strip_suffix(')')?.split(',').map(str::trim).collect();
+        let [base, index, "8"] = parts.as_slice() else {
+            return None;
+        };
+        register(index)?;
+        let base = if base.is_empty() {
+            0
+        } else {
+            match self.regs.get(&register(base)?)? {
+                RegValue::Pointer(PointerValue::Address(addr)) => *addr,
+                _ => return None,
+            }
+        };
+        Some(base.wrapping_add(displacement(disp)? as u64))
+    }
+
+    // Determine the value loaded from a memory operand.
+    fn load(&self, operand: &str, next_addr: u64, resolver: &PointerResolver) -> Option<RegValue> {
+        if let Some(addr) = self.effective_address(operand, next_addr) {
+            return resolver.read_pointer(addr).map(RegValue::Pointer);
+        }
+        let (disp, base) = memory_operand(operand)?;
+        match self.regs.get(&base) {
+            Some(RegValue::Vptr) => Some(RegValue::VtableSlot(disp as u64)),
+            // The first word of an unknown object is assumed to be its vtable pointer.
+            None if disp == 0 => Some(RegValue::Vptr),
+            _ => None,
+        }
+    }
+}
+
+// Parse a `disp(%base)` memory operand, without index register.
+pub(crate) fn memory_operand(operand: &str) -> Option<(i64, String)> {
+    let (disp, rest) = operand.split_once('(')?;
+    let base = register(rest.strip_suffix(')')?)?;
+    Some((displacement(disp)?, base))
+}
+
+// Parse the displacement of a memory operand.
+fn displacement(disp: &str) -> Option<i64> {
+    if disp.is_empty() {
+        Some(0)
+    } else if let Some(hex) = disp.strip_prefix("-0x") {
+        i64::from_str_radix(hex, 16).ok().map(|value| -value)
+    } else {
+        i64::from_str_radix(disp.strip_prefix("0x")?, 16).ok()
+    }
+}
+
+// Parse an immediate operand, without its `$`, in hexadecimal or decimal notation.
+pub(crate) fn immediate(imm: &str) -> Option<u64> {
+    match imm.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => imm.parse().ok(),
+    }
+}
+
+// Normalize a register operand to the name of the 64-bit register.
+pub(crate) fn register(operand: &str) -> Option<String> {
+    let name = operand.strip_prefix('%')?;
+    if !name.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+    let full = match name {
+        "eax" => "rax",
+        "ebx" => "rbx",
+        "ecx" => "rcx",
+        "edx" => "rdx",
+        "esi" => "rsi",
+        "edi" => "rdi",
+        "ebp" => "rbp",
+        "esp" => "rsp",
+        other if other.starts_with('r') && other.ends_with('d') && other.len() <= 4 => {
+            &other[..other.len() - 1]
+        }
+        other => other,
+    };
+    Some(format!("%{}", full))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolver(sections: Vec<(u64, &[u8])>) -> PointerResolver<'_> {
+        PointerResolver {
+            relocs: HashMap::from([(0x5000, PointerValue::Import("fopen".to_string()))]),
+            sections,
+            vtable_slots: HashMap::from([(
+                0x10,
+                BTreeSet::from([PointerValue::Address(0x1100), PointerValue::Address(0x1200)]),
+            )]),
+        }
+    }
+
+    #[test]
+    fn test_function_pointer_in_register() {
+        let resolver = resolver(Vec::new());
+        let mut state = RegisterState::new();
+        state.update("leaq", "0xff0(%rip), %rax", 0x1010, &resolver);
+        state.update("movq", "%rax, %rdx", 0x1013, &resolver);
+        assert_eq!(
+            state.resolve_call("*%rdx", 0x1015, &resolver),
+            IndirectResolution::Resolved(vec![PointerValue::Address(0x2000)])
+        );
+
+        state.update("callq", "*%rdx", 0x1015, &resolver);
+        assert_eq!(
+            state.resolve_call("*%rdx", 0x1017, &resolver),
+            IndirectResolution::Unresolved
+        );
+    }
+
+    #[test]
+    fn test_got_and_callback_table() {
+        let table: Vec<u8> = [0x1100u64, 0x1200, 0]
+            .iter()
+            .flat_map(|addr| addr.to_le_bytes())
+            .collect();
+        let resolver = resolver(vec![(0x4000, &table)]);
+        let mut state = RegisterState::new();
+        assert_eq!(
+            state.resolve_call("*0x3ffa(%rip)", 0x1006, &resolver),
+            IndirectResolution::Resolved(vec![PointerValue::Import("fopen".to_string())])
+        );
+        assert_eq!(
+            state.resolve_call("*0x4000(, %rax, 8)", 0x1006, &resolver),
+            IndirectResolution::Resolved(vec![
+                PointerValue::Address(0x1100),
+                PointerValue::Address(0x1200)
+            ])
+        );
+        state.update("leaq", "0x2ffa(%rip), %rax", 0x1006, &resolver);
+        assert_eq!(
+            state.resolve_call("*(%rax, %rbx, 8)", 0x1009, &resolver),
+            IndirectResolution::Resolved(vec![
+                PointerValue::Address(0x1100),
+                PointerValue::Address(0x1200)
+            ])
+        );
+        assert_eq!(
+            state.resolve_call("0x1100", 0x1009, &resolver),
+            IndirectResolution::Direct
+        );
+    }
+
+    #[test]
+    fn test_address_overflow() {
+        let table: Vec<u8> = [0x1100u64, 0x1200]
+            .iter()
+            .flat_map(|addr| addr.to_le_bytes())
+            .collect();
+        let resolver = resolver(vec![(u64::MAX - 15, &table)]);
+        assert_eq!(resolver.read_pointer(u64::MAX - 3), None);
+        let state = RegisterState::new();
+        assert_eq!(
+            state.resolve_call("*-0x10(, %rax, 8)", 0x1006, &resolver),
+            IndirectResolution::Unresolved
+        );
+    }
+
+    #[test]
+    fn test_virtual_call() {
+        let resolver = resolver(Vec::new());
+        let mut state = RegisterState::new();
+        state.update("movq", "(%rdi), %rax", 0x1003, &resolver);
+        assert_eq!(
+            state.resolve_call("*0x10(%rax)", 0x1006, &resolver),
+            IndirectResolution::Resolved(vec![
+                PointerValue::Address(0x1100),
+                PointerValue::Address(0x1200)
+            ])
+        );
+        state.update("movq", "0x18(%rax), %rdx", 0x1009, &resolver);
+        assert_eq!(
+            state.resolve_call("*%rdx", 0x100b, &resolver),
+            IndirectResolution::Unresolved
+        );
+    }
+
+    #[test]
+    fn test_constants() {
+        let resolver = resolver(Vec::new());
+        let mut state = RegisterState::new();
+        state.update("movl", "$2, %edi", 0x1005, &resolver);
+        state.update("movl", "$0x1bb, %esi", 0x100a, &resolver);
+        state.update("xorl", "%edx, %edx", 0x100c, &resolver);
+        assert_eq!(state.constant("%rdi"), Some(2));
+        assert_eq!(state.constant("%rsi"), Some(0x1bb));
+        assert_eq!(state.constant("%rdx"), Some(0));
+        state.update("callq", "0x2000", 0x1011, &resolver);
+        assert_eq!(state.constant("%rdi"), None);
+    }
+
+    #[test]
+    fn test_register() {
+        assert_eq!(register("%eax").as_deref(), Some("%rax"));
+        assert_eq!(register("%r8d").as_deref(), Some("%r8"));
+        assert_eq!(register("%rdi").as_deref(), Some("%rdi"));
+        assert_eq!(register("0x10(%rax)"), None);
+    }
+}
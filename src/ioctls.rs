@@ -0,0 +1,274 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use serde_json::{json, Value};
+
+use crate::{call_graph::CallGraph, cleanup::demangle_api_name, elf_utils::API};
+
+// Well-known `ioctl` requests of the Linux headers, as (request, name, subsystem).
+const KNOWN_REQUESTS: [(u64, &str, &str); 63] = [
+    // linux/videodev2.h
+    (0x80685600, "VIDIOC_QUERYCAP", "video4linux"),
+    (0xc0d05604, "VIDIOC_G_FMT", "video4linux"),
+    (0xc0d05605, "VIDIOC_S_FMT", "video4linux"),
+    (0xc0145608, "VIDIOC_REQBUFS", "video4linux"),
+    (0xc0585609, "VIDIOC_QUERYBUF", "video4linux"),
+    (0xc058560f, "VIDIOC_QBUF", "video4linux"),
+    (0xc0585611, "VIDIOC_DQBUF", "video4linux"),
+    (0x40045612, "VIDIOC_STREAMON", "video4linux"),
+    (0x40045613, "VIDIOC_STREAMOFF", "video4linux"),
+    // linux/sockios.h
+    (0x890b, "SIOCADDRT", "network"),
+    (0x890c, "SIOCDELRT", "network"),
+    (0x8912, "SIOCGIFCONF", "network"),
+    (0x8913, "SIOCGIFFLAGS", "network"),
+    (0x8914, "SIOCSIFFLAGS", "network"),
+    (0x8915, "SIOCGIFADDR", "network"),
+    (0x8916, "SIOCSIFADDR", "network"),
+    (0x891b, "SIOCGIFNETMASK", "network"),
+    (0x891c, "SIOCSIFNETMASK", "network"),
+    (0x8921, "SIOCGIFMTU", "network"),
+    (0x8922, "SIOCSIFMTU", "network"),
+    (0x8927, "SIOCGIFHWADDR", "network"),
+    (0x8933, "SIOCGIFINDEX", "network"),
+    (0x8946, "SIOCETHTOOL", "network"),
+    // linux/if_tun.h
+    (0x400454ca, "TUNSETIFF", "network"),
+    // linux/wireless.h
+    (0x8b01, "SIOCGIWNAME", "wireless"),
+    (0x8b1b, "SIOCGIWESSID", "wireless"),
+    // linux/gpio.h
+    (0x8044b401, "GPIO_GET_CHIPINFO_IOCTL", "gpio"),
+    (0xc048b402, "GPIO_GET_LINEINFO_IOCTL", "gpio"),
+    (0xc16cb403, "GPIO_GET_LINEHANDLE_IOCTL", "gpio"),
+    (0xc030b404, "GPIO_GET_LINEEVENT_IOCTL", "gpio"),
+    (0xc250b407, "GPIO_V2_GET_LINE_IOCTL", "gpio"),
+    // asm-generic/ioctls.h
+    (0x5401, "TCGETS", "terminal"),
+    (0x5402, "TCSETS", "terminal"),
+    (0x5403, "TCSETSW", "terminal"),
+    (0x5404, "TCSETSF", "terminal"),
+    (0x540e, "TIOCSCTTY", "terminal"),
+    (0x5413, "TIOCGWINSZ", "terminal"),
+    (0x5414, "TIOCSWINSZ", "terminal"),
+    (0x541b, "FIONREAD", "file"),
+    (0x5421, "FIONBIO", "file"),
+    (0x5451, "FIOCLEX", "file"),
+    // linux/i2c-dev.h
+    (0x0703, "I2C_SLAVE", "i2c"),
+    (0x0706, "I2C_SLAVE_FORCE", "i2c"),
+    (0x0707, "I2C_RDWR", "i2c"),
+    (0x0720, "I2C_SMBUS", "i2c"),
+    // linux/spi/spidev.h
+    (0x40206b00, "SPI_IOC_MESSAGE(1)", "spi"),
+    (0x40016b01, "SPI_IOC_WR_MODE", "spi"),
+    (0x40046b04, "SPI_IOC_WR_MAX_SPEED_HZ", "spi"),
+    // linux/watchdog.h
+    (0x80045705, "WDIOC_KEEPALIVE", "watchdog"),
+    (0xc0045706, "WDIOC_SETTIMEOUT", "watchdog"),
+    (0x80045707, "WDIOC_GETTIMEOUT", "watchdog"),
+    // linux/rtc.h
+    (0x80247009, "RTC_RD_TIME", "rtc"),
+    (0x4024700a, "RTC_SET_TIME", "rtc"),
+    // linux/fb.h
+    (0x4600, "FBIOGET_VSCREENINFO", "framebuffer"),
+    (0x4601, "FBIOPUT_VSCREENINFO", "framebuffer"),
+    (0x4602, "FBIOGET_FSCREENINFO", "framebuffer"),
+    // sound/asound.h
+    (0x4140, "SNDRV_PCM_IOCTL_PREPARE", "sound"),
+    // linux/fs.h
+    (0x80081272, "BLKGETSIZE64", "block"),
+    // linux/input.h
+    (0x80044501, "EVIOCGVERSION", "input"),
+    (0x40044590, "EVIOCGRAB", "input"),
+    // mtd/mtd-abi.h
+    (0x80204d01, "MEMGETINFO", "mtd"),
+    (0x40084d02, "MEMERASE", "mtd"),
+    // linux/usbdevice_fs.h
+    (0xc0185500, "USBDEVFS_CONTROL", "usb"),
+];
+
+// The subsystems of the other requests, told by the type byte of their code.
+const REQUEST_TYPES: [(u64, &str); 18] = [
+    (0x07, "i2c"),
+    (0x12, "block"),
+    (0x41, "sound"),
+    (0x45, "input"),
+    (0x46, "framebuffer"),
+    (0x48, "hidraw"),
+    (0x4c, "loop"),
+    (0x4d, "mtd"),
+    (0x54, "terminal"),
+    (0x55, "usb"),
+    (0x56, "video4linux"),
+    (0x57, "watchdog"),
+    (0x6b, "spi"),
+    (0x70, "rtc"),
+    (0x89, "network"),
+    (0x8b, "wireless"),
+    (0xae, "kvm"),
+    (0xb4, "gpio"),
+];
+
+/// An `ioctl` request made by the binary.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IoctlUse {
+    /// The request code.
+    pub request: u64,
+    /// The names of the functions making the request, sorted.
+    pub functions: Vec<String>,
+    /// The names of the APIs whose flow makes the request.
+    pub apis: Vec<String>,
+}
+
+impl IoctlUse {
+    /// Return the request as an entry of the `ioctls` of the feature manifest.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "request": format!("{:#x}", self.request),
+            "name": ioctl_name(self.request),
+            "subsystem": ioctl_subsystem(self.request),
+            "decoded": decode_ioctl(self.request),
+            "used by": self.functions,
+            "reached from": self.apis,
+        })
+    }
+}
+
+/// Return the name of a well-known `ioctl` request, e.g. `VIDIOC_STREAMON`.
+pub fn ioctl_name(request: u64) -> Option<&'static str> {
+    KNOWN_REQUESTS
+        .iter()
+        .find(|(known, _, _)| *known == request)
+        .map(|(_, name, _)| *name)
+}
+
+/// Return the device subsystem an `ioctl` request belongs to, e.g. `gpio`, from the
+/// well-known requests or else the type byte of the code.
+pub fn ioctl_subsystem(request: u64) -> Option<&'static str> {
+    if let Some((_, _, subsystem)) = KNOWN_REQUESTS
+        .iter()
+        .find(|(known, _, _)| *known == request)
+    {
+        return Some(subsystem);
+    }
+    let kind = (request >> 8) & 0xff;
+    REQUEST_TYPES
+        .iter()
+        .find(|(known, _)| *known == kind)
+        .map(|(_, subsystem)| *subsystem)
+}
+
+/// Decode an `ioctl` request code into the `_IOC` macro building it, e.g. `_IOW('V', 18, 4)`
+/// for `VIDIOC_STREAMON`: its direction, type, number and argument size.
+pub fn decode_ioctl(request: u64) -> String {
+    let direction = (request >> 30) & 0x3;
+    let size = (request >> 16) & 0x3fff;
+    let kind = (request >> 8) & 0xff;
+    let number = request & 0xff;
+    let kind = match char::from_u32(kind as u32) {
+        Some(letter) if letter.is_ascii_alphanumeric() => format!("'{}'", letter),
+        _ => format!("{:#x}", kind),
+    };
+    match direction {
+        0 if size == 0 => format!("_IO({}, {})", kind, number),
+        0 => format!("_IOC(0, {}, {}, {})", kind, number, size),
+        1 => format!("_IOW({}, {}, {})", kind, number, size),
+        2 => format!("_IOR({}, {}, {})", kind, number, size),
+        _ => format!("_IOWR({}, {}, {})", kind, number, size),
+    }
+}
+
+/// List the `ioctl` requests of the binary, with the functions making them and the APIs
+/// reaching those.
+///
+/// # Arguments
+///
+/// * `requests` - The constant requests of each function, by address, as recovered by
+///   [`ioctl_requests`](crate::capabilities::ioctl_requests).
+/// * `graph` - The call graph of the binary.
+/// * `api_list` - The APIs found, to tell which of them make each request.
+///
+/// # Returns
+///
+/// Returns the requests, sorted by code.
+pub fn ioctl_inventory(
+    requests: &HashMap<u64, BTreeSet<u64>>,
+    graph: &CallGraph,
+    api_list: &[API],
+) -> Vec<IoctlUse> {
+    let flows: Vec<_> = api_list
+        .iter()
+        .map(|api| (api.name.as_str(), graph.reachable(&[api.start_addr])))
+        .collect();
+    let mut uses: BTreeMap<u64, (BTreeSet<String>, BTreeSet<String>)> = BTreeMap::new();
+    for (func, codes) in requests {
+        let name = graph
+            .function(*func)
+            .map(|func| demangle_api_name(&func.name));
+        let apis: Vec<&str> = flows
+            .iter()
+            .filter(|(_, reachable)| reachable.contains(func))
+            .map(|(name, _)| *name)
+            .collect();
+        for code in codes {
+            let (functions, reaching) = uses.entry(*code).or_default();
+            functions.extend(name.clone());
+            reaching.extend(apis.iter().map(|api| api.to_string()));
+        }
+    }
+    uses.into_iter()
+        .map(|(request, (functions, apis))| IoctlUse {
+            request,
+            functions: functions.into_iter().collect(),
+            apis: apis.into_iter().collect(),
+        })
+        .collect()
+}
+
+/// Return the device subsystems touched by the `ioctl` requests of an API, sorted.
+pub fn api_subsystems(api: &API) -> Vec<&'static str> {
+    api.ioctls
+        .iter()
+        .filter_map(|request| ioctl_subsystem(*request))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{capabilities::ioctl_requests, elf_utils::read_elf_file};
+    use goblin::elf::Elf;
+
+    #[test]
+    fn test_ioctl_inventory() {
+        assert_eq!(ioctl_name(0x40045612), Some("VIDIOC_STREAMON"));
+        assert_eq!(decode_ioctl(0x40045612), "_IOW('V', 18, 4)");
+        assert_eq!(decode_ioctl(0xc16cb403), "_IOWR(0xb4, 3, 364)");
+        assert_eq!(decode_ioctl(0x8933), "_IO(0x89, 51)");
+        assert_eq!(ioctl_subsystem(0x400454ca), Some("network"));
+        // An unknown request of the V4L2 type.
+        assert_eq!(ioctl_name(0xc0445624), None);
+        assert_eq!(ioctl_subsystem(0xc0445624), Some("video4linux"));
+        assert_eq!(ioctl_subsystem(0x2a01), None);
+
+        let mut api = API::new("capture".to_string(), 0, 0);
+        api.ioctls = vec![0x40045612, 0x5401, 0xc0d05605];
+        assert_eq!(api_subsystems(&api), ["terminal", "video4linux"]);
+
+        // The fake camera of the C firmware sets the format with VIDIOC_S_FMT.
+        let buffer = read_elf_file("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        let graph = CallGraph::build(&elf, &buffer, false).unwrap();
+        let requests = ioctl_requests(&elf, &buffer, &graph).unwrap();
+        let inventory = ioctl_inventory(&requests, &graph, &[]);
+        let format = inventory
+            .iter()
+            .find(|entry| entry.request == 0xc0d05605)
+            .unwrap();
+        assert_eq!(format.to_json()["name"], "VIDIOC_S_FMT");
+        assert_eq!(format.to_json()["decoded"], "_IOWR('V', 5, 208)");
+        assert!(!format.functions.is_empty() && format.apis.is_empty());
+    }
+}
@@ -0,0 +1,319 @@
+use goblin::elf::{
+    program_header::{self, PF_R, PF_W, PF_X, PT_GNU_STACK, PT_LOAD},
+    section_header::{
+        self, SHF_ALLOC, SHF_EXECINSTR, SHF_GROUP, SHF_INFO_LINK, SHF_LINK_ORDER, SHF_MERGE,
+        SHF_OS_NONCONFORMING, SHF_STRINGS, SHF_TLS, SHF_WRITE, SHT_NULL,
+    },
+    Elf,
+};
+use serde_json::{json, Value};
+
+// The letters of the section flags, as printed by `readelf -S`.
+const SECTION_FLAGS: [(u32, char); 10] = [
+    (SHF_WRITE, 'W'),
+    (SHF_ALLOC, 'A'),
+    (SHF_EXECINSTR, 'X'),
+    (SHF_MERGE, 'M'),
+    (SHF_STRINGS, 'S'),
+    (SHF_INFO_LINK, 'I'),
+    (SHF_LINK_ORDER, 'L'),
+    (SHF_OS_NONCONFORMING, 'O'),
+    (SHF_GROUP, 'G'),
+    (SHF_TLS, 'T'),
+];
+
+/// A program header of the binary.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Segment {
+    /// The type of the segment without its `PT_` prefix, e.g. `LOAD`.
+    pub kind: String,
+    /// The offset of its content in the file.
+    pub offset: u64,
+    /// The virtual address where it is loaded.
+    pub address: u64,
+    /// The size of its content in the file.
+    pub file_size: u64,
+    /// The size it takes in memory.
+    pub memory_size: u64,
+    /// Its permissions, as `rwx` with `-` for the missing ones.
+    pub permissions: String,
+    /// Its alignment.
+    pub alignment: u64,
+}
+
+/// A section header of the binary.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Section {
+    /// The name of the section, e.g. `.text`.
+    pub name: String,
+    /// The type of the section without its `SHT_` prefix, e.g. `PROGBITS`.
+    pub kind: String,
+    /// The virtual address where it is loaded, 0 if it is not.
+    pub address: u64,
+    /// The offset of its content in the file.
+    pub offset: u64,
+    /// Its size.
+    pub size: u64,
+    /// Its permissions once loaded, as `rwx` with `-` for the missing ones, `---` if it is not.
+    pub permissions: String,
+    /// Its flags, as the letters of `readelf -S`, e.g. `AX`.
+    pub flags: String,
+}
+
+/// The memory layout of the binary, with the anomalies found in it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Layout {
+    /// The program headers, in the order of the file.
+    pub segments: Vec<Segment>,
+    /// The section headers, in the order of the file, the null one excluded.
+    pub sections: Vec<Section>,
+    /// The anomalies, e.g. a writable and executable segment or overlapping sections.
+    pub anomalies: Vec<String>,
+}
+
+impl Layout {
+    /// Return the layout as the `layout` entry of the basic information manifest.
+    pub fn to_json(&self) -> Value {
+        let segments: Vec<Value> = self
+            .segments
+            .iter()
+            .map(|segment| {
+                json!({
+                    "type": segment.kind,
+                    "offset": format!("{:#x}", segment.offset),
+                    "address": format!("{:#x}", segment.address),
+                    "file size": segment.file_size,
+                    "memory size": segment.memory_size,
+                    "permissions": segment.permissions,
+                    "alignment": segment.alignment,
+                })
+            })
+            .collect();
+        let sections: Vec<Value> = self
+            .sections
+            .iter()
+            .map(|section| {
+                json!({
+                    "name": section.name,
+                    "type": section.kind,
+                    "address": format!("{:#x}", section.address),
+                    "offset": format!("{:#x}", section.offset),
+                    "size": section.size,
+                    "permissions": section.permissions,
+                    "flags": section.flags,
+                })
+            })
+            .collect();
+        json!({
+            "segments": segments,
+            "sections": sections,
+            "anomalies": self.anomalies,
+        })
+    }
+}
+
+/// Read the program and section headers of the binary and look for anomalies in them.
+///
+/// The anomalies are the segments and sections both writable and executable, an executable
+/// stack, the loadable segments overlapping in memory, the sections overlapping in memory or in
+/// the file, and the allocated sections outside of every loadable segment.
+///
+/// # Arguments
+///
+/// * `elf` - The parsed ELF structure.
+///
+/// # Returns
+///
+/// Returns the layout of the binary.
+pub fn memory_layout(elf: &Elf) -> Layout {
+    let segments: Vec<Segment> = elf
+        .program_headers
+        .iter()
+        .map(|ph| Segment {
+            kind: program_header::pt_to_str(ph.p_type)
+                .trim_start_matches("PT_")
+                .to_string(),
+            offset: ph.p_offset,
+            address: ph.p_vaddr,
+            file_size: ph.p_filesz,
+            memory_size: ph.p_memsz,
+            permissions: permissions(
+                ph.p_flags & PF_R != 0,
+                ph.p_flags & PF_W != 0,
+                ph.p_flags & PF_X != 0,
+            ),
+            alignment: ph.p_align,
+        })
+        .collect();
+    let sections: Vec<(Section, u64)> = elf
+        .section_headers
+        .iter()
+        .filter(|sh| sh.sh_type != SHT_NULL)
+        .map(|sh| {
+            let flag = |bit: u32| sh.sh_flags & u64::from(bit) != 0;
+            let section = Section {
+                name: elf.shdr_strtab.get_at(sh.sh_name).unwrap_or("").to_string(),
+                kind: section_header::sht_to_str(sh.sh_type)
+                    .trim_start_matches("SHT_")
+                    .to_string(),
+                address: sh.sh_addr,
+                offset: sh.sh_offset,
+                size: sh.sh_size,
+                permissions: permissions(flag(SHF_ALLOC), flag(SHF_WRITE), flag(SHF_EXECINSTR)),
+                flags: SECTION_FLAGS
+                    .iter()
+                    .filter(|(bit, _)| flag(*bit))
+                    .map(|(_, letter)| letter)
+                    .collect(),
+            };
+            (section, sh.sh_flags)
+        })
+        .collect();
+
+    let mut anomalies = Vec::new();
+    for (index, ph) in elf.program_headers.iter().enumerate() {
+        if ph.p_type == PT_LOAD && ph.p_flags & PF_W != 0 && ph.p_flags & PF_X != 0 {
+            anomalies.push(format!(
+                "segment {} (LOAD at {:#x}) is writable and executable",
+                index, ph.p_vaddr
+            ));
+        }
+        if ph.p_type == PT_GNU_STACK && ph.p_flags & PF_X != 0 {
+            anomalies.push(format!(
+                "segment {} (GNU_STACK) makes the stack executable",
+                index
+            ));
+        }
+    }
+    let loads: Vec<(String, u64, u64)> = elf
+        .program_headers
+        .iter()
+        .enumerate()
+        .filter(|(_, ph)| ph.p_type == PT_LOAD && ph.p_memsz > 0)
+        .map(|(index, ph)| (format!("segment {}", index), ph.p_vaddr, ph.p_memsz))
+        .collect();
+    anomalies.extend(overlaps(&loads, "in memory"));
+
+    let flagged = |flags: u64, bit: u32| flags & u64::from(bit) != 0;
+    for (section, flags) in &sections {
+        if flagged(*flags, SHF_WRITE) && flagged(*flags, SHF_EXECINSTR) {
+            anomalies.push(format!(
+                "section {} is writable and executable",
+                section.name
+            ));
+        }
+    }
+    // The thread-local `.tbss` takes no room in the address space of the program: it overlaps
+    // the sections after it by design.
+    let allocated: Vec<(String, u64, u64)> = sections
+        .iter()
+        .filter(|(section, flags)| {
+            flagged(*flags, SHF_ALLOC)
+                && section.size > 0
+                && !(flagged(*flags, SHF_TLS) && section.kind == "NOBITS")
+        })
+        .map(|(section, _)| (section.name.clone(), section.address, section.size))
+        .collect();
+    anomalies.extend(overlaps(&allocated, "in memory"));
+    let stored: Vec<(String, u64, u64)> = sections
+        .iter()
+        .filter(|(section, _)| section.kind != "NOBITS" && section.size > 0)
+        .map(|(section, _)| (section.name.clone(), section.offset, section.size))
+        .collect();
+    anomalies.extend(overlaps(&stored, "in the file"));
+    if !loads.is_empty() {
+        for (name, address, size) in &allocated {
+            let inside = loads
+                .iter()
+                .any(|(_, start, len)| address >= start && address + size <= start + len);
+            if !inside {
+                anomalies.push(format!("section {} is outside of every LOAD segment", name));
+            }
+        }
+    }
+
+    Layout {
+        segments,
+        sections: sections.into_iter().map(|(section, _)| section).collect(),
+        anomalies,
+    }
+}
+
+// Write permissions as `rwx`, with `-` for the missing ones.
+fn permissions(read: bool, write: bool, execute: bool) -> String {
+    [(read, 'r'), (write, 'w'), (execute, 'x')]
+        .iter()
+        .map(|&(set, letter)| if set { letter } else { '-' })
+        .collect()
+}
+
+// Describe the ranges, given as name, start and size, overlapping one another.
+fn overlaps(ranges: &[(String, u64, u64)], place: &str) -> Vec<String> {
+    let mut sorted: Vec<&(String, u64, u64)> = ranges.iter().collect();
+    sorted.sort_by_key(|(_, start, _)| *start);
+    let mut anomalies = Vec::new();
+    for (index, (name, start, size)) in sorted.iter().enumerate() {
+        for (other, other_start, _) in &sorted[index + 1..] {
+            if *other_start >= start.saturating_add(*size) {
+                break;
+            }
+            anomalies.push(format!("{} and {} overlap {}", name, other, place));
+        }
+    }
+    anomalies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_memory_layout() {
+        assert_eq!(permissions(true, false, true), "r-x");
+        let ranges = [
+            (".a".to_string(), 0x1000, 0x100),
+            (".b".to_string(), 0x1100, 0x10),
+            (".c".to_string(), 0x1108, 0x10),
+        ];
+        assert_eq!(
+            overlaps(&ranges, "in memory"),
+            [".b and .c overlap in memory"]
+        );
+
+        let buffer = fs::read("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        let layout = memory_layout(&elf);
+        assert!(layout.anomalies.is_empty(), "{:?}", layout.anomalies);
+        let text = layout.sections.iter().find(|s| s.name == ".text").unwrap();
+        assert_eq!(
+            (text.kind.as_str(), text.permissions.as_str()),
+            ("PROGBITS", "r-x")
+        );
+        assert_eq!(text.flags, "AX");
+        assert!(layout
+            .segments
+            .iter()
+            .any(|s| s.kind == "LOAD" && s.permissions == "r-x"));
+
+        // The first LOAD segment made writable and executable.
+        let index = elf
+            .program_headers
+            .iter()
+            .position(|ph| ph.p_type == PT_LOAD)
+            .unwrap();
+        let flags = elf.header.e_phoff as usize + index * elf.header.e_phentsize as usize + 4;
+        let mut patched = buffer.clone();
+        patched[flags..flags + 4].copy_from_slice(&(PF_R | PF_W | PF_X).to_le_bytes());
+        let elf = Elf::parse(&patched).unwrap();
+        let layout = memory_layout(&elf);
+        assert_eq!(
+            layout.anomalies,
+            [format!(
+                "segment {} (LOAD at {:#x}) is writable and executable",
+                index, elf.program_headers[index].p_vaddr
+            )]
+        );
+        assert_eq!(layout.to_json()["anomalies"].as_array().unwrap().len(), 1);
+    }
+}
@@ -5,32 +5,228 @@
 //! - C
 //! - C++
 //! - Rust
+//! - Go
+//! - Swift (symbol demangling)
 //!
 //! ## Manifest Produced
 //!
 //! - Basic informations:
 //!   - file_name: The name of the ELF file.
-//!   - programming language: The programming language used to build the ELF file.
+//!   - sha256, build id, ssdeep: The digests identifying the ELF file, the ssdeep one matching its near-identical builds.
+//!   - section hashes: The SHA-256 and ssdeep digests of each executable section.
+//!   - programming language: The programming language used to build the ELF file, guessed from
+//!     its symbols, sections and runtimes without DWARF information.
 //!   - architecture: The architecture of the ELF file.
 //!   - link: Whether the ELF file is statically or dynamically linked.
 //!   - file_type: The type of the ELF file.
 //!   - endianness: The endianness of the ELF file.
 //!   - header_size: The size of the ELF header.
 //!   - entry_point: The entry point of the ELF file.
-//!   - APIs found: The list of identified public APIs.
+//!   - hardening: The pass/fail verdict of each exploit mitigation (PIE, RELRO, stack canary, NX, FORTIFY_SOURCE, stripped, RPATH), and the number of ROP/JOP gadgets of the code.
+//!   - dependencies: The shared libraries needed, the RPATH/RUNPATH entries and the program interpreter, with warnings for the insecure ones.
+//!   - notes: The minimum kernel of the ABI tag, the CET/BTI/PAC features and ISA level of the GNU properties, and the package metadata.
+//!   - entropy: The entropy of each section, flagging those that look compressed or encrypted.
+//!   - layout: The program headers and sections, with their addresses, sizes and permissions, and the anomalies of the layout.
+//!   - function origins: The number of functions attributed to each library linked into the binary and to the application.
+//!   - recognized functions: For a stripped binary, the functions found and those named by library signatures.
+//!   - go build info: For a Go binary, the Go version, the main module, the modules it depends on and the build settings.
+//!   - rust crates: For a Rust binary, the rustc version and the crates it was built with, each with a confidence.
+//!   - APIs found: The list of identified public APIs, or of every function of the binary
+//!     with the whole-binary scope.
+//!   - errors: In best-effort mode, the errors the analysis recovered from, with the phase,
+//!     function and address where each occurred.
 //!
 //! - Flow called functions:
 //!   - For each identified API, lists the function calls (system calls or subfunctions).
+//!   - Decodes the arguments of the calls to key syscalls: the paths and flags of `open`, the
+//!     domain, type and protocol of `socket`, the ports of `connect` and the protections of `mmap`.
+//!   - When the whole-program call graph is built, also lists every function reachable from the API.
+//!   - The reachable functions are grouped by the library they are attributed to.
+//!   - The threads created by `pthread_create`, `thrd_create` or `clone`, with a flow rooted at each start routine.
+//!   - The signal handlers registered by `signal` or `sigaction`, with a flow rooted at each handler.
+//!   - The constructors and destructors of `.init_array` and `.fini_array`, with a flow rooted at each.
+//!   - For a bare-metal Cortex-M image, the handlers of its interrupt vector table, with a flow rooted at each.
+//!   - The RTOS of a firmware (FreeRTOS, Zephyr, RT-Thread) and the tasks it creates, with a flow rooted at each entry function.
+//!   - The libraries loaded with `dlopen` and the symbols looked up with `dlsym`, whose flows are traced in the libraries found in the firmware tree.
+//!   - The functions that no entry point reaches, with their size.
+//!   - The estimated stack frame and worst-case stack depth of each flow, flagging those
+//!     exceeding a threshold.
 //!
 //! - Features associated to each APIs:
 //!   - Categorizes APIs based on their functionality features.
+//!   - Measures the cyclomatic complexity, instructions and basic blocks of each API.
+//!   - Summarizes the flow of each API as high-level capabilities (filesystem write, network
+//!     client, camera, GPIO, process spawn, ...) with the calls and strings revealing them.
+//!   - Lists the environment variables read by the binary, with the functions reading them.
+//!   - Lists the filesystem paths used by the binary, tagged as read, written or deleted.
+//!   - Decodes the `ioctl` requests of the binary and tells the device subsystems of each API.
+//!   - Lists the license notices and copyright notices of the strings, summarized with the
+//!     copyleft licenses and the copyright holders of the binary.
+//!
+//! - API coverage:
+//!   - Whether each entry of the API list was found, under which symbols, and why not: a
+//!     stripped symbol table, an inlined function or a name mismatch, with the closest names.
+//!
+//! - Diagnostics:
+//!   - Each manifest lists the blind spots of the analysis, e.g. the unresolved indirect calls,
+//!     the skipped functions or the missing debug information, with their severity and the
+//!     items affected.
+//!
+//! - Reproducibility:
+//!   - The same binary and options give byte-identical manifests without their timestamp, with
+//!     the APIs, categories and keys sorted, and the dates of the SBOM and MUD files taken from `SOURCE_DATE_EPOCH`.
+//!   - A `generator` block in each manifest with the tool version, the options of the analysis,
+//!     the digest of the input and, unless left out, the time of the analysis.
+//!
+//! - Schemas:
+//!   - The manifests declare a `schema_version`, and can be validated against the embedded
+//!     JSON Schema documents of that version.
+//!
+//! - Signatures (optional):
+//!   - An ed25519 signature of the manifests and of the digest of the analyzed binary.
+//!
+//! - Diffs:
+//!   - The APIs, call flows, syscalls and capabilities changed between two versions.
+//!
+//! - Conformance:
+//!   - The undeclared APIs, capabilities and calls of the binary, and the missing APIs, against
+//!     the behavior declared by its vendor.
+//!
+//! - Address resolution:
+//!   - The function, source file and line of addresses of the binary, e.g. of a crash report,
+//!     from its symbol table and DWARF line tables.
+//!
+//! - Viewer:
+//!   - A terminal browser of the flows of the manifests, with the call tree and the findings
+//!     of each.
+//!
+//! - Watch mode:
+//!   - The binaries of a build analyzed again incrementally each time they are rebuilt, with a
+//!     summary of the changes in their behavior.
+//!
+//! - Remote inputs (optional):
+//!   - The binaries given as HTTP(S) URLs downloaded, with their pinned SHA-256 digest checked
+//!     and their URL recorded in the `generator` block of the manifests.
+//!
+//! - Results store (optional):
+//!   - The binaries, functions, flows and findings of the analyses in a SQLite database, for
+//!     the queries across the firmware of a fleet.
+//!   - The binaries calling a function, linking an old library or with a capability, queried
+//!     without analyzing them again.
+//!
+//! - Firmware manifest:
+//!   - The manifests of the binaries of a device image merged, with per-binary provenance and
+//!     the capabilities, APIs and syscalls of the whole device.
+//!   - A comparison matrix of the capabilities, hardening, languages and syscalls of the
+//!     binaries of a fleet, with the outliers of each.
+//!
+//! - Bill of materials (optional):
+//!   - A CycloneDX 1.5 BOM of the binary and of the libraries and Go modules it uses.
+//!   - An SPDX 2.3 document of the binary, its libraries and the license and copyright notices found.
+//!
+//! - Network profile (optional):
+//!   - An RFC 8520 MUD file allowing the endpoints the binary talks to.
+//!
+//! - Security findings (optional):
+//!   - A SARIF 2.1.0 log of the banned calls, secrets, weak algorithms, vulnerable libraries
+//!     and failed hardening checks.
+//!
+//! - HTML report (optional):
+//!   - The basic information, hardening verdicts, capability matrix and collapsible call-flow
+//!     tree of each API, for the readers who do not read JSON.
+//!
+//! - Markdown summary (optional):
+//!   - The language, linkage, hardening verdicts, capabilities of each API and notable
+//!     findings of the binary, for inclusion in assessment documents.
+//!
+//! - Seccomp profile (optional):
+//!   - An allowlist of the syscalls of the binary, as an OCI runtime profile and a BPF filter.
+//!
+//! - Annotated disassembly (optional):
+//!   - The instructions of each API, and of its callees up to a depth, with the targets of the
+//!     calls and the syscalls performed annotated inline.
+//!
+//! - AppArmor profile (optional):
+//!   - A draft profile of the files, sockets and programs the binary uses, with the accesses
+//!     left unresolved as TODO comments.
 //!
 
+pub mod analyzer;
 pub mod api_detection;
+pub mod apparmor;
+pub mod arguments;
+pub mod batch;
+pub mod budget;
+pub mod cache;
+pub mod call_graph;
+pub mod capabilities;
+pub mod cfg;
 pub mod cleanup;
 pub mod code_section_handler;
+pub mod config;
+pub mod conformance;
+pub mod constructors;
+pub mod correlation;
+pub mod cortex_m;
+pub mod coverage;
+pub mod crypto;
+pub mod dangerous_calls;
+pub mod dead_code;
+pub mod diagnostics;
+pub mod diff;
+pub mod disassembler;
 pub mod dwarf_analysis;
+pub mod dynamic_analysis;
+pub mod dynamic_loading;
 pub mod elf_utils;
+pub mod entropy;
+pub mod environment;
 pub mod error;
+pub mod files;
+pub mod gadgets;
+pub mod go_analysis;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod hashes;
+pub mod incremental;
+pub mod indirect_calls;
+pub mod ioctls;
+pub mod layout;
+pub mod libraries;
+pub mod licenses;
+pub mod listing;
 pub mod manifest_creation;
+pub mod matrix;
+pub mod network;
 pub mod plt_mapping;
+pub mod plugins;
+pub mod policy;
+pub mod progress;
+pub mod provenance;
+pub mod reader;
+pub mod remote;
+pub mod report;
+pub mod rtos;
+pub mod rust_crates;
+pub mod schema;
+pub mod seccomp;
+pub mod secrets;
+#[cfg(feature = "native")]
+pub mod server;
+pub mod signals;
+pub mod signatures;
+pub mod signing;
+pub mod stack_usage;
+#[cfg(feature = "sqlite")]
+pub mod store;
+pub mod strings;
+pub mod syscall_arguments;
+pub mod threads;
+pub mod unpacking;
+#[cfg(feature = "native")]
+pub mod viewer;
+pub mod vulnerabilities;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod watch;
+pub mod xrefs;
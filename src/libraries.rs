@@ -0,0 +1,312 @@
+use std::cmp::Ordering;
+use std::sync::OnceLock;
+
+use goblin::elf::Elf;
+use regex::Regex;
+
+use crate::strings::BinaryString;
+
+// Package names of the common shared objects, as (soname prefix, package).
+const PACKAGES: [(&str, &str); 27] = [
+    ("libc.so", "glibc"),
+    ("libm.so", "glibc"),
+    ("libpthread.so", "glibc"),
+    ("libdl.so", "glibc"),
+    ("librt.so", "glibc"),
+    ("ld-linux", "glibc"),
+    ("libssl.so", "openssl"),
+    ("libcrypto.so", "openssl"),
+    ("libcurl.so", "curl"),
+    ("libz.so", "zlib"),
+    ("libzstd.so", "zstd"),
+    ("libbrotli", "brotli"),
+    ("libstdc++.so", "gcc"),
+    ("libgcc_s.so", "gcc"),
+    ("libasound.so", "alsa-lib"),
+    ("libmbedtls.so", "mbedtls"),
+    ("libmbedcrypto.so", "mbedtls"),
+    ("libsqlite3.so", "sqlite"),
+    ("libpng", "libpng"),
+    ("libavcodec.so", "ffmpeg"),
+    ("libavdevice.so", "ffmpeg"),
+    ("libavfilter.so", "ffmpeg"),
+    ("libavformat.so", "ffmpeg"),
+    ("libavutil.so", "ffmpeg"),
+    ("libpostproc.so", "ffmpeg"),
+    ("libswresample.so", "ffmpeg"),
+    ("libswscale.so", "ffmpeg"),
+];
+
+// Version strings embedded by libraries, as (package, pattern capturing the version).
+const VERSION_STRINGS: [(&str, &str); 8] = [
+    ("openssl", r"\bOpenSSL (\d+\.\d+\.\d+[a-z]?)\b"),
+    ("curl", r"\blibcurl[/ ](\d+\.\d+\.\d+)\b"),
+    (
+        "zlib",
+        r"\b(?:deflate|inflate) (\d+\.\d+\.\d+(?:\.\d+)?) Copyright",
+    ),
+    ("mbedtls", r"\b[Mm]bed ?TLS (\d+\.\d+\.\d+)\b"),
+    ("busybox", r"\bBusyBox v(\d+\.\d+\.\d+)\b"),
+    ("glibc", r"\bGNU C Library [^\n]*version (\d+\.\d+)\b"),
+    ("libpng", r"\blibpng version (\d+\.\d+\.\d+)\b"),
+    ("sqlite", r"\bSQLite version (\d+\.\d+\.\d+)\b"),
+];
+
+// Prefixes of the symbol versions numbering an ABI rather than the releases of the package.
+const ABI_VERSIONS: [&str; 2] = ["GLIBCXX", "CXXABI"];
+
+// Functions defined by a library, revealing it when linked statically, as (package, symbol).
+const MARKER_SYMBOLS: [(&str, &str); 10] = [
+    ("openssl", "OPENSSL_init_crypto"),
+    ("openssl", "SSL_CTX_new"),
+    ("curl", "curl_easy_init"),
+    ("zlib", "zlibVersion"),
+    ("zstd", "ZSTD_versionNumber"),
+    ("mbedtls", "mbedtls_ssl_init"),
+    ("sqlite", "sqlite3_open"),
+    ("libpng", "png_create_read_struct"),
+    ("brotli", "BrotliDecoderCreateInstance"),
+    ("alsa-lib", "snd_pcm_open"),
+];
+
+/// How the version of a library was found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VersionSource {
+    /// A version string embedded in the binary: the version the binary was built with.
+    VersionString,
+    /// The highest symbol version required from the library: a minimum version.
+    SymbolVersions,
+}
+
+impl VersionSource {
+    /// Return the name of the source, as written in the manifests.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VersionSource::VersionString => "version string",
+            VersionSource::SymbolVersions => "symbol versions",
+        }
+    }
+}
+
+/// A library the binary depends on, either linked dynamically or embedded.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Library {
+    /// The name of the package providing the library, e.g. `openssl`.
+    pub name: String,
+    /// The shared objects of the library listed in `DT_NEEDED`, empty for embedded libraries.
+    pub sonames: Vec<String>,
+    /// The version of the library, if known.
+    pub version: Option<String>,
+    /// Where the version comes from.
+    pub version_source: Option<VersionSource>,
+}
+
+impl Library {
+    /// Whether the library is statically linked into the binary rather than loaded at run time.
+    pub fn is_embedded(&self) -> bool {
+        self.sonames.is_empty()
+    }
+}
+
+/// Identify the libraries used by the binary.
+///
+/// Shared objects come from the `DT_NEEDED` entries, grouped by package, with the minimum
+/// version deduced from the versioned symbols required from them (`OPENSSL_3.0.0`,
+/// `GLIBC_2.34`). Version strings embedded in the data, like `libcurl/8.5.0`, give the exact
+/// version of a library, and reveal those linked statically, as do the functions defined by
+/// well-known libraries (`curl_easy_init`, `OPENSSL_init_crypto`).
+///
+/// # Arguments
+///
+/// * `elf` - The ELF object representing the binary.
+/// * `strings` - The strings of the binary.
+///
+/// # Returns
+///
+/// Returns the libraries, dynamic ones first in `DT_NEEDED` order.
+pub fn detect_libraries(elf: &Elf, strings: &[BinaryString]) -> Vec<Library> {
+    let mut libraries: Vec<Library> = Vec::new();
+    for soname in &elf.libraries {
+        let name = package_name(soname);
+        match libraries.iter_mut().find(|library| library.name == name) {
+            Some(library) => library.sonames.push(soname.to_string()),
+            None => libraries.push(Library {
+                name,
+                sonames: vec![soname.to_string()],
+                version: None,
+                version_source: None,
+            }),
+        }
+    }
+
+    if let Some(verneed) = &elf.verneed {
+        for need in verneed.iter() {
+            let Some(soname) = elf.dynstrtab.get_at(need.vn_file) else {
+                continue;
+            };
+            let versions = need
+                .iter()
+                .filter_map(|aux| elf.dynstrtab.get_at(aux.vna_name))
+                .filter_map(|name| {
+                    let (prefix, version) = name.rsplit_once('_')?;
+                    (!ABI_VERSIONS.contains(&prefix)
+                        && version.starts_with(|c: char| c.is_ascii_digit()))
+                    .then_some(version)
+                });
+            let Some(version) = versions.max_by(|a, b| compare_versions(a, b)) else {
+                continue;
+            };
+            let Some(library) = libraries
+                .iter_mut()
+                .find(|library| library.sonames.iter().any(|name| name == soname))
+            else {
+                continue;
+            };
+            if library
+                .version
+                .as_deref()
+                .is_none_or(|current| compare_versions(version, current).is_gt())
+            {
+                library.version = Some(version.to_string());
+                library.version_source = Some(VersionSource::SymbolVersions);
+            }
+        }
+    }
+
+    static REGEXES: OnceLock<Vec<(&str, Regex)>> = OnceLock::new();
+    let regexes = REGEXES.get_or_init(|| {
+        VERSION_STRINGS
+            .iter()
+            .map(|(name, pattern)| (*name, Regex::new(pattern).unwrap()))
+            .collect()
+    });
+    for string in strings {
+        for (name, regex) in regexes {
+            let Some(caps) = regex.captures(&string.value) else {
+                continue;
+            };
+            let version = Some(caps[1].to_string());
+            match libraries.iter_mut().find(|library| library.name == *name) {
+                // The first version string found wins over the others.
+                Some(library) if library.version_source == Some(VersionSource::VersionString) => {}
+                Some(library) => {
+                    library.version = version;
+                    library.version_source = Some(VersionSource::VersionString);
+                }
+                None => libraries.push(Library {
+                    name: name.to_string(),
+                    sonames: Vec::new(),
+                    version,
+                    version_source: Some(VersionSource::VersionString),
+                }),
+            }
+        }
+    }
+
+    for sym in elf
+        .syms
+        .iter()
+        .filter(|sym| sym.is_function() && sym.st_value != 0)
+    {
+        let Some(name) = elf.strtab.get_at(sym.st_name) else {
+            continue;
+        };
+        for (package, _) in MARKER_SYMBOLS.iter().filter(|(_, marker)| *marker == name) {
+            if !libraries.iter().any(|library| library.name == *package) {
+                libraries.push(Library {
+                    name: package.to_string(),
+                    sonames: Vec::new(),
+                    version: None,
+                    version_source: None,
+                });
+            }
+        }
+    }
+    libraries
+}
+
+/// Compare two version numbers component by component, numerically when both are numbers.
+///
+/// Components are separated by any non-alphanumeric character, and letters following digits
+/// form their own component, so that `1.1.1k` is greater than `1.1.1` and lower than `1.1.10`.
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let components = |version: &str| -> Vec<String> {
+        let mut components = Vec::new();
+        for part in version.split(|c: char| !c.is_ascii_alphanumeric()) {
+            let digits = part.len() - part.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            if digits > 0 && digits < part.len() {
+                components.push(part[..digits].to_string());
+                components.push(part[digits..].to_string());
+            } else if !part.is_empty() {
+                components.push(part.to_string());
+            }
+        }
+        components
+    };
+    let (a, b) = (components(a), components(b));
+    for (x, y) in a.iter().zip(&b) {
+        let ordering = match (x.parse::<u64>(), y.parse::<u64>()) {
+            (Ok(x), Ok(y)) => x.cmp(&y),
+            // A release is greater than its pre-release letters, e.g. `1.0` > `1.0rc1`.
+            (Ok(_), Err(_)) => Ordering::Greater,
+            (Err(_), Ok(_)) => Ordering::Less,
+            (Err(_), Err(_)) => x.cmp(y),
+        };
+        if ordering.is_ne() {
+            return ordering;
+        }
+    }
+    a.len().cmp(&b.len())
+}
+
+// Name of the package providing a shared object, e.g. `openssl` for `libssl.so.3`.
+fn package_name(soname: &str) -> String {
+    if let Some((_, package)) = PACKAGES
+        .iter()
+        .find(|(prefix, _)| soname.starts_with(prefix))
+    {
+        return package.to_string();
+    }
+    let stem = soname.split(".so").next().unwrap_or(soname);
+    stem.strip_prefix("lib").unwrap_or(stem).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf_utils::read_elf_file;
+    use crate::strings::extract_strings;
+
+    #[test]
+    fn test_compare_versions() {
+        assert_eq!(compare_versions("1.2.11", "1.2.3"), Ordering::Greater);
+        assert_eq!(compare_versions("3.0.0", "3.0"), Ordering::Greater);
+        assert_eq!(compare_versions("1.1.1k", "1.1.1"), Ordering::Greater);
+        assert_eq!(compare_versions("1.1.1k", "1.1.10"), Ordering::Less);
+        assert_eq!(compare_versions("2.34", "2.34"), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_package_name() {
+        assert_eq!(package_name("libssl.so.3"), "openssl");
+        assert_eq!(package_name("libc.so.6"), "glibc");
+        assert_eq!(package_name("libfoo.so.1"), "foo");
+    }
+
+    #[test]
+    fn test_detect_libraries() {
+        let buffer = read_elf_file("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        let strings = extract_strings(&elf, &buffer, 4);
+        let libraries = detect_libraries(&elf, &strings);
+
+        let openssl = libraries.iter().find(|lib| lib.name == "openssl").unwrap();
+        assert_eq!(openssl.sonames, ["libssl.so.3", "libcrypto.so.3"]);
+        assert_eq!(openssl.version_source, Some(VersionSource::SymbolVersions));
+        assert!(openssl.version.as_deref().unwrap().starts_with('3'));
+
+        let curl = libraries.iter().find(|lib| lib.name == "curl").unwrap();
+        assert!(curl.is_embedded());
+        assert_eq!(curl.version.as_deref(), Some("8.5.0"));
+    }
+}
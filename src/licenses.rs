@@ -0,0 +1,341 @@
+use std::sync::OnceLock;
+
+use regex::Regex;
+use serde_json::{json, Value};
+
+use crate::strings::BinaryString;
+
+// License notices, as (SPDX identifier, pattern). The identifier of `SPDX-License-Identifier`
+// tags is the one written in the tag. The first pattern matching a string names its license, so
+// a license whose text quotes another comes first, e.g. the Boost license before MIT.
+const LICENSE_PATTERNS: [(&str, &str); 15] = [
+    ("", r"SPDX-License-Identifier:\s*([A-Za-z0-9.+-]+)"),
+    ("AGPL-3.0", r"(?i)GNU Affero General Public License"),
+    (
+        "LGPL-2.1",
+        r"(?i)GNU Lesser General Public License,? version 2\.1|\bLGPL-?v?2\.1",
+    ),
+    (
+        "LGPL-3.0",
+        r"(?i)GNU Lesser General Public License,? version 3|\bLGPL-?v?3",
+    ),
+    (
+        "GPL-2.0",
+        r"(?i)GNU General Public License,? version 2|\bGPL-?v?2\b",
+    ),
+    (
+        "GPL-3.0",
+        r"(?i)GNU General Public License,? version 3|\bGPL-?v?3\b",
+    ),
+    ("Apache-2.0", r"(?i)Apache License,? Version 2\.0"),
+    (
+        "MPL-2.0",
+        r"(?i)Mozilla Public License,? (?:v\. ?|version )?2\.0",
+    ),
+    ("BSL-1.0", r"(?i)Boost Software License"),
+    (
+        "MIT",
+        r"\bMIT License\b|Permission is hereby granted, free of charge",
+    ),
+    (
+        "ISC",
+        r"Permission to use, copy, modify, and(?:/or)? distribute this software for any\s+purpose with or without fee",
+    ),
+    (
+        "BSD-3-Clause",
+        r"Neither the name of .+ nor the names of its\s+contributors",
+    ),
+    (
+        "BSD-2-Clause",
+        r"Redistributions in binary form must reproduce the above\s+copyright notice",
+    ),
+    (
+        "Zlib",
+        r"(?i)provided 'as-is', without any express or implied\s+warranty",
+    ),
+    (
+        "Unlicense",
+        r"This is free and unencumbered software released into the public domain",
+    ),
+];
+
+/// The licenses requiring the source of the covered code to be offered with the binary.
+pub const COPYLEFT_LICENSES: [&str; 6] = [
+    "GPL-2.0", "GPL-3.0", "LGPL-2.1", "LGPL-3.0", "AGPL-3.0", "MPL-2.0",
+];
+
+// A copyright notice: `Copyright`, `(C)` or `©`, then the years and the holder.
+const COPYRIGHT_PATTERN: &str = r"(?:\b(?:Copyright|COPYRIGHT)\b|\([cC]\)|©)(?:\s*(\([cC]\)|©))?\s*(\d{4}(?:\s*[-–,]\s*\d{2,4})*)?[,\s]*(?:by\s+)?([^\n]*)";
+
+// The longest holder kept from a notice, in characters.
+const MAX_HOLDER_LEN: usize = 80;
+
+/// A license notice found in the strings of the binary.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LicenseString {
+    /// The SPDX identifier of the license, e.g. `GPL-2.0`.
+    pub license: String,
+    /// The address of the string containing the notice.
+    pub addr: u64,
+    /// The text of the notice.
+    pub text: String,
+}
+
+/// A copyright notice found in the strings of the binary.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CopyrightNotice {
+    /// The address of the string containing the notice.
+    pub addr: u64,
+    /// The years of the notice, e.g. `1995-2023`.
+    pub years: Option<String>,
+    /// The holder of the copyright, e.g. `Jean-loup Gailly and Mark Adler`.
+    pub holder: String,
+    /// The text of the notice.
+    pub text: String,
+}
+
+/// Search the strings of the binary for license notices and `SPDX-License-Identifier` tags.
+///
+/// # Returns
+///
+/// Returns the notices found, at most one per string, sorted by address.
+pub fn detect_license_strings(strings: &[BinaryString]) -> Vec<LicenseString> {
+    static REGEXES: OnceLock<Vec<(&str, Regex)>> = OnceLock::new();
+    let regexes = REGEXES.get_or_init(|| {
+        LICENSE_PATTERNS
+            .iter()
+            .map(|(license, pattern)| (*license, Regex::new(pattern).unwrap()))
+            .collect()
+    });
+
+    strings
+        .iter()
+        .filter_map(|string| {
+            let (license, caps) = regexes
+                .iter()
+                .find_map(|(license, regex)| Some((*license, regex.captures(&string.value)?)))?;
+            let license = caps
+                .get(1)
+                .map_or(license, |identifier| identifier.as_str());
+            Some(LicenseString {
+                license: license.to_string(),
+                addr: string.addr,
+                text: caps[0].to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Search the strings of the binary for copyright notices, e.g.
+/// `Copyright (C) 1995-2023 Jean-loup Gailly and Mark Adler`.
+///
+/// A notice needs a year or a `(C)` sign besides the word `Copyright`, or both of them after a
+/// sign alone, which leaves out the license boilerplate (`the above copyright notice`) and the
+/// templates (`Copyright (C) <year> <name of author>`, `Copyright (c) %d %s`).
+///
+/// # Returns
+///
+/// Returns the distinct notices found, sorted by address.
+pub fn detect_copyrights(strings: &[BinaryString]) -> Vec<CopyrightNotice> {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    let regex = REGEX.get_or_init(|| Regex::new(COPYRIGHT_PATTERN).unwrap());
+
+    let mut notices: Vec<CopyrightNotice> = Vec::new();
+    for string in strings {
+        for caps in regex.captures_iter(&string.value) {
+            let word = caps[0].starts_with('C');
+            let (sign, years) = (caps.get(1).is_some(), caps.get(2));
+            if !(word && (sign || years.is_some()) || !word && years.is_some()) {
+                continue;
+            }
+            let Some(holder) = copyright_holder(&caps[3]) else {
+                continue;
+            };
+            if notices
+                .iter()
+                .any(|notice| notice.text == caps[0].trim_end())
+            {
+                continue;
+            }
+            notices.push(CopyrightNotice {
+                addr: string.addr,
+                years: years.map(|years| years.as_str().to_string()),
+                holder,
+                text: caps[0].trim_end().to_string(),
+            });
+        }
+    }
+    notices
+}
+
+// Clean the holder of a notice, or return `None` when it looks like a template.
+fn copyright_holder(text: &str) -> Option<String> {
+    let holder = text
+        .split("All rights reserved")
+        .next()
+        .unwrap_or(text)
+        .trim()
+        .trim_end_matches(['.', ',', ';', ' '])
+        .trim();
+    let holder: String = holder.chars().take(MAX_HOLDER_LEN).collect();
+    let template = holder.starts_with('<') || holder.contains('%') || holder.contains('{');
+    match holder.chars().next() {
+        Some(first) if first.is_alphanumeric() && !template => Some(holder),
+        _ => None,
+    }
+}
+
+/// Summarize the licenses and copyrights of the binary, as the `license summary` of the feature
+/// manifest: the distinct licenses, those of them that are copyleft, and the distinct holders.
+///
+/// # Arguments
+///
+/// * `licenses` - The license notices found in the strings of the binary.
+/// * `copyrights` - The copyright notices found in the strings of the binary.
+pub fn license_summary(licenses: &[LicenseString], copyrights: &[CopyrightNotice]) -> Value {
+    let ids = license_ids(licenses);
+    let copyleft: Vec<&str> = ids
+        .iter()
+        .copied()
+        .filter(|id| {
+            let base = id.trim_end_matches("-only").trim_end_matches("-or-later");
+            COPYLEFT_LICENSES.contains(&base.trim_end_matches('+'))
+        })
+        .collect();
+    let mut holders: Vec<&str> = copyrights
+        .iter()
+        .map(|notice| notice.holder.as_str())
+        .collect();
+    holders.sort_unstable();
+    holders.dedup();
+    json!({
+        "licenses": ids,
+        "copyleft": copyleft,
+        "copyright holders": holders,
+    })
+}
+
+/// Return the distinct licenses of the notices, sorted.
+pub fn license_ids(licenses: &[LicenseString]) -> Vec<&str> {
+    let mut ids: Vec<&str> = licenses
+        .iter()
+        .map(|license| license.license.as_str())
+        .collect();
+    ids.sort_unstable();
+    ids.dedup();
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strings::classify;
+
+    fn licenses(values: &[&str]) -> Vec<String> {
+        let strings: Vec<BinaryString> = values
+            .iter()
+            .map(|value| BinaryString {
+                addr: 0x1000,
+                section: ".rodata".to_string(),
+                value: value.to_string(),
+                class: classify(value),
+                referenced_by: Vec::new(),
+            })
+            .collect();
+        detect_license_strings(&strings)
+            .into_iter()
+            .map(|license| license.license)
+            .collect()
+    }
+
+    #[test]
+    fn test_detect_license_strings() {
+        assert_eq!(
+            licenses(&[
+                "SPDX-License-Identifier: BSD-2-Clause",
+                "This program is free software, covered by the GNU General Public License, version 2",
+                "Licensed under the Apache License, Version 2.0",
+                "licensed under the GNU Lesser General Public License version 2.1 or later",
+                "Error opening device",
+            ]),
+            ["BSD-2-Clause", "GPL-2.0", "Apache-2.0", "LGPL-2.1"]
+        );
+    }
+
+    #[test]
+    fn test_more_license_texts() {
+        assert_eq!(
+            licenses(&[
+                "Permission to use, copy, modify, and/or distribute this software for any purpose with or without fee is hereby granted",
+                "Redistributions in binary form must reproduce the above copyright notice",
+                "This software is provided 'as-is', without any express or implied warranty.",
+                "Boost Software License - Version 1.0. Permission is hereby granted, free of charge",
+                "licensed under the GNU Affero General Public License version 3",
+            ]),
+            ["ISC", "BSD-2-Clause", "Zlib", "BSL-1.0", "AGPL-3.0"]
+        );
+    }
+
+    #[test]
+    fn test_detect_copyrights() {
+        let strings: Vec<BinaryString> = [
+            "deflate 1.3 Copyright 1995-2023 Jean-loup Gailly and Mark Adler ",
+            "Copyright (c) 1998-2021 The OpenSSL Project.  All rights reserved.",
+            "(C) 2019, Acme Devices Inc.",
+            "Copyright (C) <year> <name of author>",
+            "Copyright (c) %d %s",
+            "retain the above copyright notice, this list of conditions",
+            "THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS",
+        ]
+        .iter()
+        .enumerate()
+        .map(|(i, value)| BinaryString {
+            addr: 0x1000 + i as u64,
+            section: ".rodata".to_string(),
+            value: value.to_string(),
+            class: classify(value),
+            referenced_by: Vec::new(),
+        })
+        .collect();
+        let notices = detect_copyrights(&strings);
+        let found: Vec<(Option<&str>, &str)> = notices
+            .iter()
+            .map(|notice| (notice.years.as_deref(), notice.holder.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (Some("1995-2023"), "Jean-loup Gailly and Mark Adler"),
+                (Some("1998-2021"), "The OpenSSL Project"),
+                (Some("2019"), "Acme Devices Inc"),
+            ]
+        );
+        assert_eq!(notices[0].addr, 0x1000);
+
+        let notice = |license: &str| LicenseString {
+            license: license.to_string(),
+            addr: 0,
+            text: String::new(),
+        };
+        let summary = license_summary(
+            &[notice("MIT"), notice("GPL-2.0-or-later"), notice("MIT")],
+            &notices,
+        );
+        assert_eq!(summary["licenses"], json!(["GPL-2.0-or-later", "MIT"]));
+        assert_eq!(summary["copyleft"], json!(["GPL-2.0-or-later"]));
+        assert_eq!(summary["copyright holders"].as_array().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_license_ids() {
+        let notice = |license: &str| LicenseString {
+            license: license.to_string(),
+            addr: 0,
+            text: String::new(),
+        };
+        assert_eq!(
+            license_ids(&[notice("MIT"), notice("GPL-2.0"), notice("MIT")]),
+            ["GPL-2.0", "MIT"]
+        );
+    }
+}
@@ -0,0 +1,342 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    fmt::Write,
+};
+
+use goblin::elf::Elf;
+
+use crate::{
+    call_graph::CallGraph,
+    correlation::syscalls_of,
+    disassembler::{Disassembler, Instruction},
+    elf_utils::code_bytes,
+    error::Result,
+    indirect_calls::{immediate, register},
+};
+
+// The column where the annotations of the instructions start.
+const ANNOTATION_COLUMN: usize = 56;
+
+// How many instructions before a `syscall` are searched for the number loaded in `%eax`.
+const SYSCALL_LOOKBACK: usize = 8;
+
+/// A function of a [`Listing`], disassembled.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ListedFunction {
+    /// The name of the function.
+    pub name: String,
+    /// The starting address of the function.
+    pub start_addr: u64,
+    /// The ending address of the function.
+    pub end_addr: u64,
+    /// How many calls the function is away from the API, `0` for the API itself.
+    pub depth: usize,
+    /// The instructions of the function, each with what it is annotated with, if anything:
+    /// the functions called and the syscalls they perform, or the syscall performed.
+    pub instructions: Vec<(Instruction, Option<String>)>,
+}
+
+/// The annotated disassembly of an API and of the functions it calls, up to a depth.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Listing {
+    /// The name of the API.
+    pub name: String,
+    /// The depth the callees of the API are listed up to.
+    pub depth: usize,
+    /// The API, then its callees breadth first, each listed once.
+    pub functions: Vec<ListedFunction>,
+}
+
+impl Listing {
+    /// Disassemble an API and its callees, annotating the calls with their targets and the
+    /// syscalls of the libc functions called, and the raw `syscall` instructions with the
+    /// syscall whose number is loaded in `%eax`.
+    ///
+    /// The callees whose code is not in the file, e.g. the imported functions, are not listed.
+    ///
+    /// # Arguments
+    ///
+    /// * `elf` - The ELF object representing the binary.
+    /// * `buffer` - The buffer containing the binary data of the ELF file.
+    /// * `graph` - The call graph of the binary.
+    /// * `name` - The name of the API.
+    /// * `start_addr` - The starting address of the API.
+    /// * `end_addr` - The ending address of the API.
+    /// * `depth` - How many calls away from the API the callees are listed, `0` for the API only.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the listing, or `None` if the code of the API is not in
+    /// the file.
+    pub fn build(
+        elf: &Elf,
+        buffer: &[u8],
+        graph: &CallGraph,
+        name: &str,
+        start_addr: u64,
+        end_addr: u64,
+        depth: usize,
+    ) -> Result<Option<Self>> {
+        let disassembler = Disassembler::new()?;
+        let Some(api) = list_function(
+            elf,
+            buffer,
+            graph,
+            &disassembler,
+            (name, start_addr, end_addr),
+            0,
+        )?
+        else {
+            return Ok(None);
+        };
+
+        let mut functions = vec![api];
+        let mut seen = BTreeSet::from([start_addr]);
+        let mut queue = VecDeque::from([(start_addr, 0)]);
+        while let Some((addr, level)) = queue.pop_front() {
+            if level == depth {
+                continue;
+            }
+            for edge in graph.callees(addr) {
+                let Some(callee) = edge.target.and_then(|target| graph.function(target)) else {
+                    continue;
+                };
+                if !seen.insert(callee.start_addr) {
+                    continue;
+                }
+                let bounds = (callee.name.as_str(), callee.start_addr, callee.end_addr);
+                let listed = list_function(elf, buffer, graph, &disassembler, bounds, level + 1)?;
+                if let Some(function) = listed {
+                    functions.push(function);
+                    queue.push_back((callee.start_addr, level + 1));
+                }
+            }
+        }
+
+        Ok(Some(Self {
+            name: name.to_string(),
+            depth,
+            functions,
+        }))
+    }
+
+    /// Render the listing as text, one instruction per line with its annotation in a comment.
+    pub fn to_text(&self) -> String {
+        let mut text = match self.depth {
+            0 => format!("; Disassembly of {}\n", self.name),
+            depth => format!(
+                "; Disassembly of {} and of its callees up to depth {}\n",
+                self.name, depth
+            ),
+        };
+        for function in &self.functions {
+            let _ = write!(
+                text,
+                "\n{}:  ; {:#x}-{:#x}",
+                function.name, function.start_addr, function.end_addr
+            );
+            if function.depth > 0 {
+                let _ = write!(text, ", depth {}", function.depth);
+            }
+            text.push('\n');
+            for (insn, annotation) in &function.instructions {
+                let line = format!(
+                    "    {:#x}:  {:<8} {}",
+                    insn.address, insn.mnemonic, insn.op_str
+                );
+                let line = line.trim_end();
+                match annotation {
+                    Some(annotation) => {
+                        let _ = writeln!(
+                            text,
+                            "{:<width$} ; {}",
+                            line,
+                            annotation,
+                            width = ANNOTATION_COLUMN
+                        );
+                    }
+                    None => {
+                        let _ = writeln!(text, "{}", line);
+                    }
+                }
+            }
+        }
+        text
+    }
+}
+
+// Disassemble a function given as (name, start, end) and annotate its instructions, or `None`
+// if its code is not in the file.
+fn list_function(
+    elf: &Elf,
+    buffer: &[u8],
+    graph: &CallGraph,
+    disassembler: &Disassembler,
+    (name, start_addr, end_addr): (&str, u64, u64),
+    depth: usize,
+) -> Result<Option<ListedFunction>> {
+    let Some(code) = code_bytes(elf, buffer, start_addr, end_addr) else {
+        return Ok(None);
+    };
+    let Ok(instructions) = disassembler.disassemble(code, start_addr) else {
+        return Ok(None);
+    };
+
+    // The calls of the function by site, an indirect call possibly having several targets.
+    let mut calls: BTreeMap<u64, Vec<String>> = BTreeMap::new();
+    for edge in graph.callees(start_addr) {
+        let mut call = format!("-> {}", edge.name);
+        if edge.indirect {
+            call.push_str(" (indirect)");
+        }
+        let syscalls = syscalls_of(&edge.name);
+        if !syscalls.is_empty() {
+            let _ = write!(call, " [syscalls: {}]", syscalls.join(", "));
+        }
+        calls.entry(edge.site).or_default().push(call);
+    }
+    for call in graph.unresolved_calls(start_addr) {
+        calls
+            .entry(call.site)
+            .or_default()
+            .push("-> unresolved indirect call".to_string());
+    }
+
+    let listed = instructions
+        .iter()
+        .enumerate()
+        .map(|(index, insn)| {
+            let annotation = match calls.get(&insn.address) {
+                Some(calls) => Some(calls.join(", ")),
+                None if insn.name() == "syscall" => Some(raw_syscall(&instructions[..index])),
+                None => None,
+            };
+            (insn.clone(), annotation)
+        })
+        .collect();
+
+    Ok(Some(ListedFunction {
+        name: name.to_string(),
+        start_addr,
+        end_addr,
+        depth,
+        instructions: listed,
+    }))
+}
+
+// Name the syscall performed by a `syscall` instruction from the last write to `%eax` among
+// the instructions preceding it, when that write is an immediate.
+fn raw_syscall(preceding: &[Instruction]) -> String {
+    let number = preceding
+        .iter()
+        .rev()
+        .take(SYSCALL_LOOKBACK)
+        .find(|insn| {
+            let destination = insn.op_str.rsplit(", ").next().unwrap_or_default();
+            !matches!(insn.name(), "cmp" | "test")
+                && register(destination).as_deref() == Some("%rax")
+        })
+        .filter(|insn| insn.name().starts_with("mov"))
+        .and_then(|insn| insn.op_str.split_once(", "))
+        .and_then(|(source, _)| immediate(source.strip_prefix('$')?));
+    match number {
+        Some(number) => match syscall_name(number) {
+            Some(name) => format!("syscall {} ({})", name, number),
+            None => format!("syscall {}", number),
+        },
+        None => "syscall, number unknown".to_string(),
+    }
+}
+
+// The name of an x86-64 Linux syscall from its number.
+#[cfg(feature = "native")]
+fn syscall_name(number: u64) -> Option<&'static str> {
+    let number = usize::try_from(number).ok()?;
+    syscalls::x86_64::Sysno::new(number).map(|sysno| sysno.name())
+}
+
+// Without the syscall tables, the raw syscalls are annotated with their numbers only.
+#[cfg(not(feature = "native"))]
+fn syscall_name(_number: u64) -> Option<&'static str> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf_utils::read_elf_file;
+
+    // The bounds of the function named `name`.
+    fn bounds(elf: &Elf, name: &str) -> (u64, u64) {
+        let sym = elf
+            .syms
+            .iter()
+            .find(|sym| elf.strtab.get_at(sym.st_name) == Some(name))
+            .unwrap();
+        (sym.st_value, sym.st_value + sym.st_size)
+    }
+
+    #[test]
+    fn test_listing_dynamic() {
+        let buffer = read_elf_file("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        let graph = CallGraph::build(&elf, &buffer, false).unwrap();
+        let (start, end) = bounds(&elf, "writeOnDrive");
+
+        let listing = Listing::build(&elf, &buffer, &graph, "writeOnDrive", start, end, 0)
+            .unwrap()
+            .unwrap();
+        assert_eq!(listing.functions.len(), 1);
+        let function = &listing.functions[0];
+        assert_eq!(function.instructions[0].0.address, start);
+        let last = &function.instructions.last().unwrap().0;
+        assert_eq!(last.next_addr(), end);
+
+        let text = listing.to_text();
+        assert!(text.starts_with("; Disassembly of writeOnDrive\n"));
+        let open = text.lines().find(|line| line.contains("-> fopen")).unwrap();
+        assert!(open.contains("call"));
+        assert!(open.contains("openat"));
+        assert!(text
+            .lines()
+            .any(|line| line.ends_with("-> fclose [syscalls: close]")));
+    }
+
+    #[test]
+    fn test_listing_callees() {
+        let buffer = read_elf_file("./tests/elf_file/minimal-fake-firmware-c-static").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        let graph = CallGraph::build(&elf, &buffer, false).unwrap();
+        let (start, end) = bounds(&elf, "main");
+
+        let listing = Listing::build(&elf, &buffer, &graph, "main", start, end, 0)
+            .unwrap()
+            .unwrap();
+        assert_eq!(listing.functions.len(), 1);
+        let listing = Listing::build(&elf, &buffer, &graph, "main", start, end, 1)
+            .unwrap()
+            .unwrap();
+        let names: Vec<(&str, usize)> = listing
+            .functions
+            .iter()
+            .map(|function| (function.name.as_str(), function.depth))
+            .collect();
+        assert_eq!(names, [("main", 0), ("writeOnDrive", 1)]);
+        assert!(listing.to_text().contains("writeOnDrive:  ; "));
+
+        let (start, end) = bounds(&elf, "__libc_write");
+        let listing = Listing::build(&elf, &buffer, &graph, "__libc_write", start, end, 0)
+            .unwrap()
+            .unwrap();
+        let annotations: Vec<&str> = listing.functions[0]
+            .instructions
+            .iter()
+            .filter(|(insn, _)| insn.name() == "syscall")
+            .filter_map(|(_, annotation)| annotation.as_deref())
+            .collect();
+        #[cfg(feature = "native")]
+        assert_eq!(annotations[0], "syscall write (1)");
+        #[cfg(not(feature = "native"))]
+        assert_eq!(annotations[0], "syscall 1");
+    }
+}
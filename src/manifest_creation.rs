@@ -1,17 +1,59 @@
-use std::{collections::HashMap, fs::File, io::Write, path::Path};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fs::File,
+    io::Write,
+    path::Path,
+    str::FromStr,
+};
 
 use goblin::elf::Elf;
 
-use crate::{elf_utils, error};
-use elf_utils::{get_arch, get_file_type, is_static, API};
-use error::Result;
+use crate::{
+    batch::BatchEntry,
+    call_graph::CallGraph,
+    cfg::ControlFlowGraph,
+    cleanup::demangle_api_name,
+    code_section_handler::VulnerabilityPattern,
+    coverage::{coverage_report, ApiCoverage},
+    crypto::CryptoAlgorithm,
+    dangerous_calls::DangerousCall,
+    elf_utils,
+    entropy::{rounded, section_entropy},
+    environment::EnvVariable,
+    error,
+    files::FileAccess,
+    gadgets::gadget_surface,
+    go_analysis,
+    hashes::{section_hashes, ssdeep},
+    ioctls::{api_subsystems, IoctlUse},
+    layout::memory_layout,
+    libraries::{Library, VersionSource},
+    licenses::{license_ids, license_summary, CopyrightNotice, LicenseString},
+    listing::Listing,
+    network::Endpoint,
+    provenance::function_origins,
+    schema::SCHEMA_VERSION,
+    seccomp::SeccompProfile,
+    secrets::Secret,
+    vulnerabilities::LibraryVulnerabilities,
+    xrefs::XrefDb,
+};
+use elf_utils::{
+    build_id, dynamic_dependencies, elf_notes, get_arch, get_file_type, hardening_checks,
+    is_static, sha256_hex, API,
+};
+use error::{Error, Result};
+use go_analysis::{go_entry_points, GoBuildInfo, GoFunction, GoModule};
 
 const CATEGORIES: [(&str, &[&str]); 9] = [
     (
         "File Manipulation",
         &["fwrite", "fopen", "fclose", "File", "write"],
     ),
-    ("Network Access", &["curl", "sendto", "recvfrom", "cpr"]),
+    (
+        "Network Access",
+        &["curl", "sendto", "recvfrom", "cpr", "net.Dial", "net/http"],
+    ),
     ("Device Access", &["__libc", "ioctl", "close"]),
     ("Audio Access", &["audio", "alcOpenDevice"]),
     ("Video Access", &["video", "capture", "Camera", "rscam"]),
@@ -24,14 +66,166 @@ const CATEGORIES: [(&str, &[&str]); 9] = [
         "Data Compression/Decompression",
         &["compress", "decompress"],
     ),
-    ("Process Management", &["fork", "exec", "wait", "exit"]),
+    (
+        "Process Management",
+        &["fork", "exec", "wait", "exit", "os/exec"],
+    ),
+];
+
+// Rules of the SARIF log, as (identifier, level, description).
+const SARIF_RULES: [(&str, &str, &str); 14] = [
+    ("dangerous-call", "warning", "Call to a banned function"),
+    (
+        "format-string",
+        "error",
+        "Format string that is not a constant",
+    ),
+    (
+        "stack-buffer-overflow",
+        "error",
+        "Copy without bound into a stack buffer",
+    ),
+    (
+        "command-injection",
+        "error",
+        "Shell command built from its input",
+    ),
+    (
+        "hardcoded-secret",
+        "error",
+        "Hardcoded secret or credential",
+    ),
+    (
+        "weak-crypto",
+        "warning",
+        "Use of a weak cryptographic algorithm",
+    ),
+    (
+        "vulnerable-library",
+        "error",
+        "Library affected by a known vulnerability",
+    ),
+    (
+        "hardening-pie",
+        "warning",
+        "Executable loaded at a fixed address",
+    ),
+    (
+        "hardening-relro",
+        "warning",
+        "GOT not read-only after relocation",
+    ),
+    ("hardening-stack-canary", "warning", "No stack canaries"),
+    ("hardening-nx", "error", "Executable stack"),
+    (
+        "hardening-fortify-source",
+        "note",
+        "No fortified libc functions",
+    ),
+    (
+        "hardening-stripped",
+        "note",
+        "Symbol table left in the binary",
+    ),
+    ("hardening-rpath", "warning", "Insecure library search path"),
 ];
 
-/// Creates a JSON manifest that categorizes APIs based on their functionality features.
+/// The encoding of the basic info, flow call and feature manifests.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    /// Pretty-printed JSON.
+    #[default]
+    Json,
+    /// YAML.
+    Yaml,
+    /// TOML, which has no null values: the fields without a value are left out.
+    Toml,
+    /// CBOR (RFC 8949), a compact binary encoding of the JSON manifest.
+    Cbor,
+    /// MessagePack, a compact binary encoding of the JSON manifest.
+    MsgPack,
+}
+
+impl Format {
+    /// Return the extension of the manifests written in the format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Yaml => "yaml",
+            Format::Toml => "toml",
+            Format::Cbor => "cbor",
+            Format::MsgPack => "msgpack",
+        }
+    }
+}
+
+impl Format {
+    /// Return the format of a manifest from the extension of its file name.
+    pub fn from_extension(file_path: &str) -> Option<Self> {
+        let extension = Path::new(file_path).extension()?.to_str()?;
+        [
+            Format::Json,
+            Format::Yaml,
+            Format::Toml,
+            Format::Cbor,
+            Format::MsgPack,
+        ]
+        .into_iter()
+        .find(|format| format.extension() == extension)
+    }
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name {
+            "json" => Ok(Format::Json),
+            "yaml" => Ok(Format::Yaml),
+            "toml" => Ok(Format::Toml),
+            "cbor" => Ok(Format::Cbor),
+            "msgpack" => Ok(Format::MsgPack),
+            _ => Err(Error::InvalidFormat(name.to_string())),
+        }
+    }
+}
+
+/// Findings about the whole binary, reported in the feature manifest next to the API features.
+#[derive(Default)]
+pub struct Findings {
+    /// The hardcoded secrets found in the strings of the binary.
+    pub secrets: Vec<Secret>,
+    /// The network endpoints found in the binary.
+    pub endpoints: Vec<Endpoint>,
+    /// The cryptographic algorithms used by the binary.
+    pub crypto: Vec<CryptoAlgorithm>,
+    /// The calls to banned functions.
+    pub dangerous_calls: Vec<DangerousCall>,
+    /// The vulnerability patterns recognized at call sites.
+    pub vulnerability_patterns: Vec<VulnerabilityPattern>,
+    /// The libraries used by the binary.
+    pub libraries: Vec<Library>,
+    /// The advisories matching the libraries, `None` when no vulnerability database was given.
+    pub vulnerabilities: Option<Vec<LibraryVulnerabilities>>,
+    /// The license notices found in the strings of the binary.
+    pub licenses: Vec<LicenseString>,
+    /// The copyright notices found in the strings of the binary.
+    pub copyrights: Vec<CopyrightNotice>,
+    /// The environment variables read by the binary.
+    pub environment: Vec<EnvVariable>,
+    /// The filesystem paths used by the binary.
+    pub files: Vec<FileAccess>,
+    /// The `ioctl` requests made by the binary.
+    pub ioctls: Vec<IoctlUse>,
+}
+
+/// Creates a manifest that categorizes APIs based on their functionality features.
 ///
 /// # Arguments
 ///
 /// * `api_list` - A reference to a vector containing the list of APIs to be categorized.
+/// * `findings` - The findings about the whole binary, each listed in its own section.
+/// * `format` - The format of the manifest.
 ///
 /// # Returns
 ///
@@ -40,7 +234,22 @@ const CATEGORIES: [(&str, &[&str]); 9] = [
 /// # Errors
 ///
 /// Returns an error if there is an issue creating or writing to the output file.
-pub fn feature_manifest(api_list: &Vec<API>, path: &str) -> Result<()> {
+pub fn feature_manifest(
+    api_list: &[API],
+    findings: &Findings,
+    format: Format,
+    path: &str,
+) -> Result<()> {
+    write_manifest(
+        &feature_document(api_list, findings),
+        path,
+        "feature_manifest",
+        format,
+    )
+}
+
+/// Build the feature manifest of [`feature_manifest`] as a JSON document, without writing it.
+pub fn feature_document(api_list: &[API], findings: &Findings) -> serde_json::Value {
     let mut categorized_features: HashMap<String, Vec<String>> = HashMap::new();
 
     for api in api_list {
@@ -56,8 +265,48 @@ pub fn feature_manifest(api_list: &Vec<API>, path: &str) -> Result<()> {
             }
         }
     }
+    // The categories are listed by name rather than by the order of the calls revealing them.
+    for features in categorized_features.values_mut() {
+        features.sort();
+    }
 
     let mut features_json: serde_json::Map<String, serde_json::Value> = serde_json::Map::new();
+    features_json.insert(
+        "schema_version".to_string(),
+        serde_json::Value::String(SCHEMA_VERSION.to_string()),
+    );
+
+    // Metadata declared in the API list, checked against the detected features.
+    let declared: serde_json::Map<String, serde_json::Value> = api_list
+        .iter()
+        .filter_map(|api| {
+            let spec = api.spec.as_ref()?;
+            let features = categorized_features
+                .get(&api.name)
+                .cloned()
+                .unwrap_or_default();
+            let conformant = spec
+                .capability
+                .as_ref()
+                .map(|capability| capability_matches(capability, &features));
+            Some((
+                api.name.clone(),
+                serde_json::json!({
+                    "expected capability": spec.capability,
+                    "parameters": spec.parameters,
+                    "aliases": spec.aliases,
+                    "conformant": conformant,
+                }),
+            ))
+        })
+        .collect();
+    if !declared.is_empty() {
+        features_json.insert(
+            "declared APIs".to_string(),
+            serde_json::Value::Object(declared),
+        );
+    }
+
     for (api_name, features) in categorized_features {
         let features_array: Vec<serde_json::Value> = features
             .into_iter()
@@ -66,14 +315,373 @@ pub fn feature_manifest(api_list: &Vec<API>, path: &str) -> Result<()> {
         features_json.insert(api_name, serde_json::Value::Array(features_array));
     }
 
-    let json_obj = serde_json::json!(features_json);
-    let json_str = serde_json::to_string_pretty(&json_obj)?;
+    // APIs found under a different name than the requested one, with the match confidence.
+    let fuzzy_matches: Vec<serde_json::Value> = api_list
+        .iter()
+        .filter_map(|api| {
+            let fuzzy = api.fuzzy_match.as_ref()?;
+            Some(serde_json::json!({
+                "requested": fuzzy.requested,
+                "symbol": api.name,
+                "confidence": (fuzzy.confidence * 100.0).round() / 100.0,
+            }))
+        })
+        .collect();
+    if !fuzzy_matches.is_empty() {
+        features_json.insert(
+            "fuzzy matches".to_string(),
+            serde_json::Value::Array(fuzzy_matches),
+        );
+    }
 
-    let manifest_path = format!("{}/feature_manifest.json", path);
-    let mut file = File::create(manifest_path)?;
-    file.write_all(json_str.as_bytes())?;
+    // High-level capabilities of each API, with the calls and strings revealing them.
+    let capabilities: serde_json::Map<String, serde_json::Value> = api_list
+        .iter()
+        .filter(|api| !api.capabilities.is_empty())
+        .map(|api| {
+            let summary: serde_json::Map<String, serde_json::Value> = api
+                .capabilities
+                .iter()
+                .map(|capability| {
+                    (
+                        capability.capability.clone(),
+                        serde_json::json!(capability.evidence),
+                    )
+                })
+                .collect();
+            (api.name.clone(), serde_json::Value::Object(summary))
+        })
+        .collect();
+    if !capabilities.is_empty() {
+        features_json.insert(
+            "capabilities".to_string(),
+            serde_json::Value::Object(capabilities),
+        );
+    }
 
-    Ok(())
+    // Size and cyclomatic complexity of each API, to rank the functions to review.
+    let complexity: serde_json::Map<String, serde_json::Value> = api_list
+        .iter()
+        .filter_map(|api| Some((api.name.clone(), api.complexity?.to_json())))
+        .collect();
+    if !complexity.is_empty() {
+        features_json.insert(
+            "complexity".to_string(),
+            serde_json::Value::Object(complexity),
+        );
+    }
+
+    // Device subsystems touched by the ioctl requests of each API.
+    let subsystems: serde_json::Map<String, serde_json::Value> = api_list
+        .iter()
+        .map(|api| (api.name.clone(), api_subsystems(api)))
+        .filter(|(_, subsystems)| !subsystems.is_empty())
+        .map(|(name, subsystems)| (name, serde_json::json!(subsystems)))
+        .collect();
+    if !subsystems.is_empty() {
+        features_json.insert(
+            "device subsystems".to_string(),
+            serde_json::Value::Object(subsystems),
+        );
+    }
+
+    // Classified strings (URLs, addresses, paths, commands, formats) used by each API.
+    let strings: serde_json::Map<String, serde_json::Value> = api_list
+        .iter()
+        .filter(|api| !api.strings.is_empty())
+        .map(|api| {
+            let entries: Vec<serde_json::Value> = api
+                .strings
+                .iter()
+                .map(|string| {
+                    serde_json::json!({
+                        "value": string.value,
+                        "class": string.class.as_str(),
+                        "referenced by": string.functions,
+                    })
+                })
+                .collect();
+            (api.name.clone(), serde_json::Value::Array(entries))
+        })
+        .collect();
+    if !strings.is_empty() {
+        features_json.insert("strings".to_string(), serde_json::Value::Object(strings));
+    }
+
+    // Secrets are reported for the whole binary, with the functions using them.
+    if !findings.secrets.is_empty() {
+        let secrets: Vec<serde_json::Value> = findings
+            .secrets
+            .iter()
+            .map(|secret| {
+                serde_json::json!({
+                    "kind": secret.kind,
+                    "value": secret.redacted,
+                    "address": format!("{:#x}", secret.addr),
+                    "referenced by": secret.functions,
+                })
+            })
+            .collect();
+        features_json.insert("secrets".to_string(), serde_json::Value::Array(secrets));
+    }
+
+    // Endpoints of the whole binary, then of each API reaching some.
+    if !findings.endpoints.is_empty() {
+        let per_api: serde_json::Map<String, serde_json::Value> = api_list
+            .iter()
+            .filter(|api| !api.endpoints.is_empty())
+            .map(|api| (api.name.clone(), endpoints_json(&api.endpoints)))
+            .collect();
+        features_json.insert(
+            "network endpoints".to_string(),
+            serde_json::json!({
+                "all": endpoints_json(&findings.endpoints),
+                "per API": per_api,
+            }),
+        );
+    }
+
+    // Algorithms of the whole binary with their evidence, weak ones being flagged.
+    if !findings.crypto.is_empty() {
+        let algorithms: Vec<serde_json::Value> = findings
+            .crypto
+            .iter()
+            .map(|algorithm| {
+                let constants: Vec<serde_json::Value> = algorithm
+                    .constants
+                    .iter()
+                    .map(|constant| {
+                        serde_json::json!({
+                            "name": constant.name,
+                            "address": format!("{:#x}", constant.addr),
+                        })
+                    })
+                    .collect();
+                serde_json::json!({
+                    "algorithm": algorithm.name,
+                    "weak": algorithm.weak,
+                    "symbols": algorithm.symbols,
+                    "constants": constants,
+                    "used by": algorithm.functions.values().collect::<Vec<_>>(),
+                })
+            })
+            .collect();
+        let weak: Vec<&str> = findings
+            .crypto
+            .iter()
+            .filter(|algorithm| algorithm.weak)
+            .map(|algorithm| algorithm.name)
+            .collect();
+        let per_api: serde_json::Map<String, serde_json::Value> = api_list
+            .iter()
+            .filter(|api| !api.crypto.is_empty())
+            .map(|api| (api.name.clone(), serde_json::json!(api.crypto)))
+            .collect();
+        features_json.insert(
+            "crypto".to_string(),
+            serde_json::json!({
+                "algorithms": algorithms,
+                "weak": weak,
+                "per API": per_api,
+            }),
+        );
+    }
+
+    // Calls to banned functions, with the APIs whose flow reaches them.
+    if !findings.dangerous_calls.is_empty() {
+        let calls: Vec<serde_json::Value> = findings
+            .dangerous_calls
+            .iter()
+            .map(|call| {
+                serde_json::json!({
+                    "function": call.function,
+                    "call site": format!("{:#x}", call.site),
+                    "caller": call.caller,
+                    "reached from": call.apis,
+                })
+            })
+            .collect();
+        features_json.insert(
+            "dangerous calls".to_string(),
+            serde_json::Value::Array(calls),
+        );
+    }
+
+    // Vulnerability patterns at call sites, with the APIs whose flow reaches them.
+    if !findings.vulnerability_patterns.is_empty() {
+        let patterns: Vec<serde_json::Value> = findings
+            .vulnerability_patterns
+            .iter()
+            .map(|pattern| {
+                serde_json::json!({
+                    "pattern": pattern.kind.as_str(),
+                    "function": pattern.function,
+                    "call site": format!("{:#x}", pattern.site),
+                    "caller": pattern.caller,
+                    "detail": pattern.detail,
+                    "reached from": pattern.apis,
+                })
+            })
+            .collect();
+        features_json.insert(
+            "vulnerability patterns".to_string(),
+            serde_json::Value::Array(patterns),
+        );
+    }
+
+    // Linked and embedded libraries, then the advisories affecting them.
+    if !findings.libraries.is_empty() {
+        let libraries: Vec<serde_json::Value> = findings
+            .libraries
+            .iter()
+            .map(|library| {
+                serde_json::json!({
+                    "name": library.name,
+                    "linkage": if library.is_embedded() { "embedded" } else { "dynamic" },
+                    "shared objects": library.sonames,
+                    "version": library.version,
+                    "version source": library.version_source.map(|source| source.as_str()),
+                })
+            })
+            .collect();
+        features_json.insert("libraries".to_string(), serde_json::Value::Array(libraries));
+    }
+    if let Some(vulnerabilities) = &findings.vulnerabilities {
+        let per_library: serde_json::Map<String, serde_json::Value> = vulnerabilities
+            .iter()
+            .map(|library| {
+                let advisories: Vec<serde_json::Value> = library
+                    .advisories
+                    .iter()
+                    .map(|advisory| {
+                        serde_json::json!({
+                            "id": advisory.id,
+                            "aliases": advisory.aliases,
+                            "summary": advisory.summary,
+                            "fixed in": advisory.fixed,
+                        })
+                    })
+                    .collect();
+                (
+                    library.library.clone(),
+                    serde_json::json!({
+                        "version": library.version,
+                        "advisories": advisories,
+                    }),
+                )
+            })
+            .collect();
+        features_json.insert(
+            "vulnerabilities".to_string(),
+            serde_json::Value::Object(per_library),
+        );
+    }
+
+    // License notices found in the strings.
+    if !findings.licenses.is_empty() {
+        let licenses: Vec<serde_json::Value> = findings
+            .licenses
+            .iter()
+            .map(|license| {
+                serde_json::json!({
+                    "license": license.license,
+                    "address": format!("{:#x}", license.addr),
+                    "text": license.text,
+                })
+            })
+            .collect();
+        features_json.insert("licenses".to_string(), serde_json::Value::Array(licenses));
+    }
+    if !findings.copyrights.is_empty() {
+        let copyrights: Vec<serde_json::Value> = findings
+            .copyrights
+            .iter()
+            .map(|notice| {
+                serde_json::json!({
+                    "address": format!("{:#x}", notice.addr),
+                    "years": notice.years,
+                    "holder": notice.holder,
+                    "text": notice.text,
+                })
+            })
+            .collect();
+        features_json.insert(
+            "copyrights".to_string(),
+            serde_json::Value::Array(copyrights),
+        );
+    }
+    if !findings.licenses.is_empty() || !findings.copyrights.is_empty() {
+        features_json.insert(
+            "license summary".to_string(),
+            license_summary(&findings.licenses, &findings.copyrights),
+        );
+    }
+
+    // Environment variables read, with the functions reading them.
+    if !findings.environment.is_empty() {
+        let variables: Vec<serde_json::Value> = findings
+            .environment
+            .iter()
+            .map(|variable| {
+                serde_json::json!({
+                    "name": variable.name,
+                    "read by": variable.functions,
+                    "reached from": variable.apis,
+                })
+            })
+            .collect();
+        features_json.insert(
+            "environment variables".to_string(),
+            serde_json::Value::Array(variables),
+        );
+    }
+
+    // Filesystem paths used, with what is done with them.
+    if !findings.files.is_empty() {
+        let paths: Vec<serde_json::Value> = findings
+            .files
+            .iter()
+            .map(|file| {
+                let accesses: Vec<&str> =
+                    file.accesses.iter().map(|access| access.as_str()).collect();
+                serde_json::json!({
+                    "path": file.path,
+                    "access": accesses,
+                    "used by": file.functions,
+                    "reached from": file.apis,
+                })
+            })
+            .collect();
+        features_json.insert("file paths".to_string(), serde_json::Value::Array(paths));
+    }
+
+    // ioctl requests, decoded, with the functions making them.
+    if !findings.ioctls.is_empty() {
+        let requests: Vec<serde_json::Value> =
+            findings.ioctls.iter().map(IoctlUse::to_json).collect();
+        features_json.insert("ioctls".to_string(), serde_json::Value::Array(requests));
+    }
+
+    serde_json::json!(features_json)
+}
+
+// Check whether a capability declared in the API list is among the detected features.
+//
+// Capabilities are free-form words (`network`, `camera`, ...), compared with the feature names
+// and with a few common synonyms.
+fn capability_matches(capability: &str, features: &[String]) -> bool {
+    let capability = capability.to_lowercase();
+    let feature_word = match capability.as_str() {
+        "filesystem" | "fs" | "storage" => "file",
+        "camera" | "webcam" => "video",
+        "microphone" => "audio",
+        "crypto" | "cryptography" => "encryption",
+        "net" => "network",
+        other => other,
+    };
+    features
+        .iter()
+        .any(|feature| feature.to_lowercase().contains(feature_word))
 }
 
 // Helper function to categorize API under specific feature.
@@ -92,11 +700,12 @@ fn categorize_api(
     }
 }
 
-/// Creates a JSON manifest that presents, for each identified API, the list of function calls (system calls or subfunctions).
+/// Creates a manifest that presents, for each identified API, the list of function calls (system calls or subfunctions).
 ///
 /// # Arguments
 ///
 /// * `api_list` - A reference to a vector containing the list of APIs with their associated function calls.
+/// * `format` - The format of the manifest.
 ///
 /// # Returns
 ///
@@ -105,47 +714,92 @@ fn categorize_api(
 /// # Errors
 ///
 /// Returns an error if there is an issue creating or writing to the output file.
+pub fn flow_call_manifest(api_list: &[API], format: Format, path: &str) -> Result<()> {
+    write_manifest(&flow_call_document(api_list), path, "flow_call", format)
+}
 
-pub fn flow_call_manifest(api_list: &Vec<API>, path: &str) -> Result<()> {
-    let mut api_flow = Vec::new();
+/// Build the flow call manifest of [`flow_call_manifest`] as a JSON document, without writing
+/// it.
+pub fn flow_call_document(api_list: &[API]) -> serde_json::Value {
+    let api_flow: Vec<serde_json::Value> = api_list.iter().map(api_flow_json).collect();
 
-    for api in api_list {
-        let mut api_info = serde_json::Map::new();
-        let mut syscalls = Vec::new();
+    serde_json::json!({
+        "schema_version": SCHEMA_VERSION,
+        "Public APIs flow": api_flow
+    })
+}
 
-        for sys in &api.syscalls {
-            syscalls.push(serde_json::Value::String(sys.to_string()));
-        }
+/// Return the flow of an API as an entry of the flow call manifest: its syscalls, transitive
+/// calls and the limits met while reading them.
+pub fn api_flow_json(api: &API) -> serde_json::Value {
+    let mut api_info = serde_json::Map::new();
+    let mut syscalls = Vec::new();
+
+    for sys in &api.syscalls {
+        syscalls.push(serde_json::Value::String(sys.to_string()));
+    }
 
+    api_info.insert(
+        "name".to_string(),
+        serde_json::Value::String(api.name.clone()),
+    );
+    api_info.insert("syscalls".to_string(), serde_json::Value::Array(syscalls));
+    if !api.syscall_arguments.is_empty() {
+        let arguments = api.syscall_arguments.iter().map(|call| call.to_json());
         api_info.insert(
-            "name".to_string(),
-            serde_json::Value::String(api.name.clone()),
+            "syscall arguments".to_string(),
+            serde_json::Value::Array(arguments.collect()),
+        );
+    }
+    if !api.transitive_calls.is_empty() {
+        api_info.insert(
+            "transitive calls".to_string(),
+            serde_json::json!(api.transitive_calls),
+        );
+        api_info.insert(
+            "calls by library".to_string(),
+            serde_json::json!(api.libraries),
+        );
+    }
+    if !api.recursive_functions.is_empty() {
+        api_info.insert("recursion".to_string(), serde_json::Value::Bool(true));
+        api_info.insert(
+            "recursive functions".to_string(),
+            serde_json::json!(api.recursive_functions),
+        );
+    }
+    if api.depth_truncated {
+        api_info.insert(
+            "max depth reached".to_string(),
+            serde_json::Value::Bool(true),
+        );
+    }
+    if !api.unresolved_calls.is_empty() {
+        api_info.insert(
+            "unresolved indirect calls".to_string(),
+            serde_json::json!(api.unresolved_calls),
+        );
+    }
+    if !api.skipped_functions.is_empty() {
+        api_info.insert(
+            "skipped functions".to_string(),
+            serde_json::json!(api.skipped_functions),
         );
-        api_info.insert("syscalls".to_string(), serde_json::Value::Array(syscalls));
-
-        api_flow.push(serde_json::Value::Object(api_info));
     }
 
-    let json_obj = serde_json::json!({
-        "Public APIs flow": api_flow
-    });
-
-    let json_str = serde_json::to_string_pretty(&json_obj)?;
-    let manifest_path = format!("{}/flow_call.json", path);
-    let mut file = File::create(manifest_path)?;
-    file.write_all(json_str.as_bytes())?;
-
-    Ok(())
+    serde_json::Value::Object(api_info)
 }
 
-/// Prints general information about the ELF binary and the identified public APIs in a JSON manifest.
+/// Prints general information about the ELF binary and the identified public APIs in a manifest.
 ///
 /// # Arguments
 ///
 /// * `elf` - A reference to the ELF structure representing the binary file.
+/// * `buffer` - The content of the ELF file, to compute its digest.
 /// * `file_path` - The path to the ELF binary file.
 /// * `api_list` - A reference to a vector containing the list of identified public APIs.
 /// * `language` - The programming language used to build the ELF binary.
+/// * `format` - The format of the manifest.
 ///
 /// # Returns
 ///
@@ -156,20 +810,63 @@ pub fn flow_call_manifest(api_list: &Vec<API>, path: &str) -> Result<()> {
 /// Returns an error if there is an issue creating or writing to the output file.
 pub fn basic_info_manifest(
     elf: &Elf,
+    buffer: &[u8],
     file_path: &str,
     api_list: &[API],
     language: String,
+    format: Format,
     path: &str,
 ) -> Result<()> {
+    let info = basic_info_document(elf, buffer, file_path, api_list, language)?;
+    write_manifest(&info, path, "basic_info", format)
+}
+
+/// Build the basic information manifest of [`basic_info_manifest`] as a JSON document, without
+/// writing it.
+///
+/// # Errors
+///
+/// Returns an error if the architecture or the file type of the binary is unknown.
+pub fn basic_info_document(
+    elf: &Elf,
+    buffer: &[u8],
+    file_path: &str,
+    api_list: &[API],
+    language: String,
+) -> Result<serde_json::Value> {
     let mut info = serde_json::Map::new();
+    let origins = function_origins(elf, &language);
     let file_name = Path::new(file_path)
         .file_name()
         .map_or(file_path, |f| f.to_str().unwrap());
 
+    info.insert(
+        "schema_version".to_string(),
+        serde_json::Value::String(SCHEMA_VERSION.to_string()),
+    );
     info.insert(
         "file_name".to_string(),
         serde_json::Value::String(file_name.to_string()),
     );
+    info.insert(
+        "sha256".to_string(),
+        serde_json::Value::String(sha256_hex(buffer)),
+    );
+    if let Some(id) = build_id(elf, buffer) {
+        info.insert("build id".to_string(), serde_json::Value::String(id));
+    }
+    info.insert(
+        "ssdeep".to_string(),
+        serde_json::Value::String(ssdeep(buffer)),
+    );
+    let sections: serde_json::Map<String, serde_json::Value> = section_hashes(elf, buffer)
+        .iter()
+        .map(|section| (section.name.clone(), section.to_json()))
+        .collect();
+    info.insert(
+        "section hashes".to_string(),
+        serde_json::Value::Object(sections),
+    );
     info.insert(
         "programming language".to_string(),
         serde_json::Value::String(language),
@@ -203,16 +900,1454 @@ pub fn basic_info_manifest(
         serde_json::Value::String(format!("{:#x}", elf.header.e_entry)),
     );
 
+    let mut hardening: serde_json::Map<String, serde_json::Value> = hardening_checks(elf)
+        .into_iter()
+        .map(|check| {
+            (
+                check.name.to_string(),
+                serde_json::json!({
+                    "verdict": if check.passed { "pass" } else { "fail" },
+                    "detail": check.detail,
+                }),
+            )
+        })
+        .collect();
+    hardening.insert(
+        "gadgets".to_string(),
+        gadget_surface(elf, buffer)?.to_json(),
+    );
+    info.insert(
+        "hardening".to_string(),
+        serde_json::Value::Object(hardening),
+    );
+
+    let dependencies = dynamic_dependencies(elf);
+    let mut dynamic = serde_json::json!({
+        "needed": dependencies.needed,
+        "rpath": dependencies.rpath,
+        "runpath": dependencies.runpath,
+        "warnings": dependencies.warnings,
+    });
+    if let Some(interpreter) = dependencies.interpreter {
+        dynamic["interpreter"] = serde_json::json!(interpreter);
+    }
+    info.insert("dependencies".to_string(), dynamic);
+    info.insert("notes".to_string(), elf_notes(elf, buffer).to_json());
+
+    info.insert("function origins".to_string(), serde_json::json!(origins));
+
+    let sections = section_entropy(elf, buffer);
+    let entropy: serde_json::Map<String, serde_json::Value> = sections
+        .iter()
+        .map(|section| {
+            (
+                section.name.clone(),
+                serde_json::json!({
+                    "size": section.size,
+                    "entropy": rounded(section.entropy),
+                    "packed": section.packed,
+                }),
+            )
+        })
+        .collect();
+    info.insert(
+        "entropy".to_string(),
+        serde_json::json!({
+            "probably packed": sections.iter().any(|s| s.executable && s.packed),
+            "sections": entropy,
+        }),
+    );
+
+    info.insert("layout".to_string(), memory_layout(elf).to_json());
+
     let list: Vec<serde_json::Value> = api_list
         .iter()
         .map(|api| serde_json::Value::String(api.name.clone()))
         .collect();
     info.insert("APIs found".to_string(), serde_json::Value::Array(list));
 
-    let json_str = serde_json::to_string_pretty(&serde_json::Value::Object(info))?;
-    let manifest_path = format!("{}/basic_info.json", path);
-    let mut output_file = File::create(manifest_path)?;
-    output_file.write_all(json_str.as_bytes())?;
+    Ok(serde_json::Value::Object(info))
+}
+
+/// Creates a JSON manifest with the information recovered from the `.gopclntab` of a Go binary.
+///
+/// The manifest lists the runtime entry points and the source position of each identified API.
+///
+/// # Arguments
+///
+/// * `functions` - The functions recovered from `.gopclntab`.
+/// * `api_list` - A reference to a vector containing the list of identified public APIs.
+///
+/// # Returns
+///
+/// Returns a `Result` indicating success or failure.
+///
+/// # Errors
+///
+/// Returns an error if there is an issue creating or writing to the output file.
+pub fn go_manifest(functions: &[GoFunction], api_list: &[API], path: &str) -> Result<()> {
+    let go_function_json = |func: &GoFunction| {
+        serde_json::json!({
+            "name": func.name,
+            "address": format!("{:#x}", func.start_addr),
+            "file": func.file,
+            "line": func.line,
+        })
+    };
+
+    let entry_points: Vec<serde_json::Value> = go_entry_points(functions)
+        .into_iter()
+        .map(go_function_json)
+        .collect();
+    let apis: Vec<serde_json::Value> = api_list
+        .iter()
+        .filter_map(|api| functions.iter().find(|func| func.name == api.name))
+        .map(go_function_json)
+        .collect();
+
+    let json_obj = serde_json::json!({
+        "runtime entry points": entry_points,
+        "APIs source": apis,
+    });
+    let json_str = serde_json::to_string_pretty(&json_obj)?;
+    let manifest_path = format!("{}/go_info.json", path);
+    let mut file = File::create(manifest_path)?;
+    file.write_all(json_str.as_bytes())?;
+
+    Ok(())
+}
+
+/// Writes the coverage of the API list, alongside the feature manifest.
+///
+/// `api_coverage.json` tells, for each entry of the API list, whether it was found, the symbols
+/// it was found under, and why it was not, with the symbols of the closest names.
+///
+/// # Arguments
+///
+/// * `coverage` - The coverage of each entry of the API list.
+/// * `path` - The directory where the report is written.
+///
+/// # Returns
+///
+/// Returns a `Result` indicating success or failure.
+///
+/// # Errors
+///
+/// Returns an error if there is an issue creating or writing to the output file.
+pub fn coverage_manifest(coverage: &[ApiCoverage], path: &str) -> Result<()> {
+    let json_str = serde_json::to_string_pretty(&coverage_report(coverage))?;
+    let manifest_path = format!("{}/api_coverage.json", path);
+    let mut file = File::create(manifest_path)?;
+    file.write_all(json_str.as_bytes())?;
 
     Ok(())
 }
+
+/// Writes the HTML report of the manifests to `report.html`.
+///
+/// # Arguments
+///
+/// * `report` - The HTML document, as rendered by [`html_report`](crate::report::html_report).
+/// * `path` - The directory where the report is written.
+///
+/// # Returns
+///
+/// Returns a `Result` indicating success or failure.
+///
+/// # Errors
+///
+/// Returns an error if there is an issue creating or writing to the output file.
+pub fn html_manifest(report: &str, path: &str) -> Result<()> {
+    let manifest_path = format!("{}/report.html", path);
+    let mut file = File::create(manifest_path)?;
+    file.write_all(report.as_bytes())?;
+
+    Ok(())
+}
+
+/// Writes the Markdown summary of the manifests to `summary.md`.
+///
+/// # Arguments
+///
+/// * `summary` - The Markdown document, as rendered by
+///   [`markdown_summary`](crate::report::markdown_summary).
+/// * `path` - The directory where the summary is written.
+///
+/// # Returns
+///
+/// Returns a `Result` indicating success or failure.
+///
+/// # Errors
+///
+/// Returns an error if there is an issue creating or writing to the output file.
+pub fn markdown_manifest(summary: &str, path: &str) -> Result<()> {
+    let manifest_path = format!("{}/summary.md", path);
+    let mut file = File::create(manifest_path)?;
+    file.write_all(summary.as_bytes())?;
+
+    Ok(())
+}
+
+/// Writes the draft AppArmor profile of the binary to `apparmor.profile`.
+///
+/// # Arguments
+///
+/// * `profile` - The profile, as drafted by [`apparmor_profile`](crate::apparmor::apparmor_profile).
+/// * `path` - The directory where the profile is written.
+///
+/// # Returns
+///
+/// Returns a `Result` indicating success or failure.
+///
+/// # Errors
+///
+/// Returns an error if there is an issue creating or writing to the output file.
+pub fn apparmor_manifest(profile: &str, path: &str) -> Result<()> {
+    let manifest_path = format!("{}/apparmor.profile", path);
+    let mut file = File::create(manifest_path)?;
+    file.write_all(profile.as_bytes())?;
+
+    Ok(())
+}
+
+/// Writes the seccomp allowlist of the binary to `seccomp.json`, in the format of the OCI
+/// runtime specification, and to `seccomp.bpf`, as a BPF filter to install with `seccomp(2)`.
+///
+/// # Arguments
+///
+/// * `elf` - The ELF object representing the binary.
+/// * `profile` - The syscalls the binary may make.
+/// * `path` - The directory where the profile is written.
+///
+/// # Returns
+///
+/// Returns a `Result` indicating success or failure.
+///
+/// # Errors
+///
+/// Returns an error if there is an issue creating or writing to the output files.
+pub fn seccomp_manifest(elf: &Elf, profile: &SeccompProfile, path: &str) -> Result<()> {
+    let json_str = serde_json::to_string_pretty(&profile.to_oci())?;
+    let manifest_path = format!("{}/seccomp.json", path);
+    let mut file = File::create(manifest_path)?;
+    file.write_all(json_str.as_bytes())?;
+
+    if let Some(filter) = profile.to_bpf(elf) {
+        let mut file = File::create(format!("{}/seccomp.bpf", path))?;
+        file.write_all(&filter)?;
+    }
+
+    Ok(())
+}
+
+/// Writes the call graph rooted at the identified APIs, as a JSON document and as DOT files.
+///
+/// `call_graph.json` contains the nodes and edges reachable from any of the APIs, while a
+/// `<api>.dot` Graphviz file is written for the subgraph of each API.
+///
+/// # Arguments
+///
+/// * `graph` - The call graph of the binary.
+/// * `api_list` - A reference to a vector containing the list of identified public APIs.
+///
+/// # Returns
+///
+/// Returns a `Result` indicating success or failure.
+///
+/// # Errors
+///
+/// Returns an error if there is an issue creating or writing to the output files.
+pub fn call_graph_manifest(graph: &CallGraph, api_list: &[API], path: &str) -> Result<()> {
+    let roots: Vec<u64> = api_list.iter().map(|api| api.start_addr).collect();
+    let json_str = serde_json::to_string_pretty(&graph.to_json(&roots))?;
+    let manifest_path = format!("{}/call_graph.json", path);
+    let mut file = File::create(manifest_path)?;
+    file.write_all(json_str.as_bytes())?;
+
+    for api in api_list {
+        let dot_path = format!("{}/{}.dot", path, file_name(&api.name, api.start_addr));
+        let mut file = File::create(dot_path)?;
+        file.write_all(graph.to_dot(&[api.start_addr]).as_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// Generate the cross-reference manifest of the identified APIs.
+///
+/// For each API, the manifest lists the functions calling it and the data (strings, globals)
+/// referenced by its code.
+///
+/// # Arguments
+///
+/// * `xrefs` - The cross-references of the binary.
+/// * `graph` - The call graph the cross-references were built from.
+/// * `api_list` - List of API structures.
+/// * `path` - The directory where the manifest is written.
+///
+/// # Returns
+///
+/// Returns a `Result` indicating success or failure of the manifest generation.
+pub fn xrefs_manifest(
+    xrefs: &XrefDb,
+    graph: &CallGraph,
+    api_list: &[API],
+    path: &str,
+) -> Result<()> {
+    let mut apis = Vec::new();
+    for api in api_list {
+        let symbol = graph
+            .function(api.start_addr)
+            .map_or(api.name.as_str(), |func| func.name.as_str());
+        let callers: Vec<serde_json::Value> = xrefs
+            .callers_of(symbol)
+            .iter()
+            .map(|caller| {
+                let name = graph
+                    .function(caller.caller)
+                    .map(|func| demangle_api_name(&func.name));
+                serde_json::json!({
+                    "function": name,
+                    "site": format!("{:#x}", caller.site),
+                })
+            })
+            .collect();
+        let data: Vec<serde_json::Value> = xrefs
+            .data_refs(api.start_addr)
+            .iter()
+            .map(|data| {
+                let mut entry = serde_json::Map::new();
+                entry.insert("site".to_string(), format!("{:#x}", data.site).into());
+                entry.insert("address".to_string(), format!("{:#x}", data.addr).into());
+                entry.insert("section".to_string(), data.section.clone().into());
+                if let Some(string) = &data.string {
+                    entry.insert("string".to_string(), string.clone().into());
+                }
+                serde_json::Value::Object(entry)
+            })
+            .collect();
+        apis.push(serde_json::json!({
+            "name": api.name,
+            "called by": callers,
+            "data references": data,
+        }));
+    }
+
+    let json_str = serde_json::to_string_pretty(&serde_json::json!({ "APIs xrefs": apis }))?;
+    let manifest_path = format!("{}/xrefs.json", path);
+    let mut file = File::create(manifest_path)?;
+    file.write_all(json_str.as_bytes())?;
+
+    Ok(())
+}
+
+/// Generate the control flow graph manifest of the identified APIs.
+///
+/// The basic blocks of every API are written to `cfg.json`, and each graph is also rendered
+/// as a Graphviz `<api>.cfg.dot` file.
+///
+/// # Arguments
+///
+/// * `cfgs` - The control flow graphs of the APIs.
+/// * `path` - The directory where the manifest is written.
+///
+/// # Returns
+///
+/// Returns a `Result` indicating success or failure of the manifest generation.
+pub fn cfg_manifest(cfgs: &[ControlFlowGraph], path: &str) -> Result<()> {
+    let graphs: Vec<serde_json::Value> = cfgs.iter().map(ControlFlowGraph::to_json).collect();
+    let json_str = serde_json::to_string_pretty(&serde_json::json!({ "APIs CFG": graphs }))?;
+    let manifest_path = format!("{}/cfg.json", path);
+    let mut file = File::create(manifest_path)?;
+    file.write_all(json_str.as_bytes())?;
+
+    for cfg in cfgs {
+        let dot_path = format!("{}/{}.cfg.dot", path, file_name(&cfg.name, cfg.start_addr));
+        let mut file = File::create(dot_path)?;
+        file.write_all(cfg.to_dot().as_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// Write the annotated disassembly of each identified API, and of its callees up to the depth
+/// of its listing, as a `<api>.disasm.txt` file.
+///
+/// # Arguments
+///
+/// * `listings` - The annotated disassembly of the APIs.
+/// * `path` - The directory where the listings are written.
+///
+/// # Returns
+///
+/// Returns a `Result` indicating success or failure of the manifest generation.
+pub fn disassembly_manifest(listings: &[Listing], path: &str) -> Result<()> {
+    for listing in listings {
+        let addr = listing.functions.first().map_or(0, |func| func.start_addr);
+        let text_path = format!("{}/{}.disasm.txt", path, file_name(&listing.name, addr));
+        let mut file = File::create(text_path)?;
+        file.write_all(listing.to_text().as_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// Creates a CycloneDX 1.5 JSON bill of materials of the binary.
+///
+/// The binary is the main component of the BOM, with its SHA-256 digest; each library it
+/// uses, linked dynamically or embedded, is a component it depends on, and so is each module
+/// of a Go binary. Advisories matched against a vulnerability database are listed as the
+/// vulnerabilities of the libraries.
+///
+/// # Arguments
+///
+/// * `elf` - The ELF object representing the binary.
+/// * `buffer` - The content of the ELF file, to compute its digest.
+/// * `file_path` - The path of the ELF file.
+/// * `language` - The programming language of the binary.
+/// * `findings` - The findings about the binary, for its libraries and their advisories.
+/// * `go_build_info` - The build information of a Go binary, for its modules.
+/// * `path` - The directory where `sbom.cdx.json` is written.
+///
+/// # Returns
+///
+/// Returns a `Result` indicating success or failure.
+pub fn cyclonedx_manifest(
+    elf: &Elf,
+    buffer: &[u8],
+    file_path: &str,
+    language: &str,
+    findings: &Findings,
+    go_build_info: Option<&GoBuildInfo>,
+    path: &str,
+) -> Result<()> {
+    let file_name = Path::new(file_path)
+        .file_name()
+        .map_or(file_path, |f| f.to_str().unwrap());
+    let digest = sha256_hex(buffer);
+    let binary_ref = format!("binary:{}", file_name);
+    let library_ref = |name: &str| format!("library:{}", name);
+    let module_ref = |module: &GoModule| format!("go:{}", module.path);
+    let modules = go_build_info.map_or(&[][..], |info| &info.dependencies);
+
+    let property = |name: &str, value: &str| serde_json::json!({ "name": format!("manifest-producer:{}", name), "value": value });
+    let binary = serde_json::json!({
+        "type": if elf.interpreter.is_some() || is_static(elf) { "application" } else { "library" },
+        "bom-ref": binary_ref,
+        "name": file_name,
+        "hashes": [{ "alg": "SHA-256", "content": digest }],
+        "properties": [
+            property("architecture", get_arch(elf)?),
+            property("file type", get_file_type(elf)?),
+            property("language", language),
+            property("link", if is_static(elf) { "static" } else { "dynamic" }),
+        ],
+    });
+
+    let mut components: Vec<serde_json::Value> = findings
+        .libraries
+        .iter()
+        .map(|library| {
+            let mut properties = vec![property(
+                "linkage",
+                if library.is_embedded() {
+                    "embedded"
+                } else {
+                    "dynamic"
+                },
+            )];
+            properties.extend(
+                library
+                    .sonames
+                    .iter()
+                    .map(|soname| property("shared object", soname)),
+            );
+            let mut component = serde_json::json!({
+                "type": "library",
+                "bom-ref": library_ref(&library.name),
+                "name": library.name,
+                "purl": purl(library),
+            });
+            // Minimum versions are properties: the component version is the one in use.
+            match (&library.version, library.version_source) {
+                (Some(version), Some(VersionSource::VersionString)) => {
+                    component["version"] = version.as_str().into();
+                }
+                (Some(version), _) => properties.push(property("minimum version", version)),
+                (None, _) => {}
+            }
+            component["properties"] = properties.into();
+            component
+        })
+        .collect();
+    components.extend(modules.iter().map(|module| {
+        let mut properties = vec![property("linkage", "embedded")];
+        if let Some(sum) = &module.sum {
+            properties.push(property("go.sum", sum));
+        }
+        if let Some(replacement) = &module.replacement {
+            let replaced_by = format!("{}@{}", replacement.path, replacement.version);
+            properties.push(property("replaced by", &replaced_by));
+        }
+        serde_json::json!({
+            "type": "library",
+            "bom-ref": module_ref(module),
+            "name": module.path,
+            "version": module.version,
+            "purl": go_purl(module),
+            "properties": properties,
+        })
+    }));
+
+    let mut bom = serde_json::json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "serialNumber": format!("urn:uuid:{}", uuid_from_digest(&digest)),
+        "version": 1,
+        "metadata": {
+            "tools": {
+                "components": [{
+                    "type": "application",
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                }],
+            },
+            "component": binary,
+        },
+        "components": components,
+        "dependencies": [{
+            "ref": binary_ref,
+            "dependsOn": findings
+                .libraries
+                .iter()
+                .map(|library| library_ref(&library.name))
+                .chain(modules.iter().map(module_ref))
+                .collect::<Vec<_>>(),
+        }],
+    });
+    if let Some(vulnerabilities) = &findings.vulnerabilities {
+        let entries: Vec<serde_json::Value> = vulnerabilities
+            .iter()
+            .flat_map(|library| {
+                library.advisories.iter().map(move |advisory| {
+                    let mut entry = serde_json::json!({
+                        "id": advisory.id,
+                        "affects": [{ "ref": library_ref(&library.library) }],
+                    });
+                    if let Some(summary) = &advisory.summary {
+                        entry["description"] = summary.as_str().into();
+                    }
+                    if let Some(fixed) = &advisory.fixed {
+                        entry["recommendation"] = format!("Upgrade to {}", fixed).into();
+                    }
+                    entry
+                })
+            })
+            .collect();
+        bom["vulnerabilities"] = entries.into();
+    }
+
+    let json_str = serde_json::to_string_pretty(&bom)?;
+    let manifest_path = format!("{}/sbom.cdx.json", path);
+    let mut file = File::create(manifest_path)?;
+    file.write_all(json_str.as_bytes())?;
+
+    Ok(())
+}
+
+/// Creates an SPDX 2.3 JSON document describing the binary, the libraries it depends on and
+/// the license notices found in its strings.
+///
+/// The binary, each library and each module of a Go binary are packages: the document
+/// `DESCRIBES` the binary, which links the shared objects (`DYNAMIC_LINK`), the embedded
+/// libraries and the modules (`STATIC_LINK`).
+/// The creation time is taken from `SOURCE_DATE_EPOCH` when set, for reproducible documents.
+///
+/// # Arguments
+///
+/// * `elf` - The ELF object representing the binary.
+/// * `buffer` - The content of the ELF file, to compute its digest.
+/// * `file_path` - The path of the ELF file.
+/// * `findings` - The findings about the binary, for its libraries and license notices.
+/// * `go_build_info` - The build information of a Go binary, for its modules.
+/// * `path` - The directory where `sbom.spdx.json` is written.
+///
+/// # Returns
+///
+/// Returns a `Result` indicating success or failure.
+pub fn spdx_manifest(
+    elf: &Elf,
+    buffer: &[u8],
+    file_path: &str,
+    findings: &Findings,
+    go_build_info: Option<&GoBuildInfo>,
+    path: &str,
+) -> Result<()> {
+    let file_name = Path::new(file_path)
+        .file_name()
+        .map_or(file_path, |f| f.to_str().unwrap());
+    let digest = sha256_hex(buffer);
+    let binary_id = format!("SPDXRef-Package-{}", spdx_id(file_name));
+    let library_id = |name: &str| format!("SPDXRef-Package-library-{}", spdx_id(name));
+
+    let mut binary = serde_json::json!({
+        "SPDXID": binary_id,
+        "name": file_name,
+        "downloadLocation": "NOASSERTION",
+        "filesAnalyzed": false,
+        "checksums": [{ "algorithm": "SHA256", "checksumValue": digest }],
+        "primaryPackagePurpose": if elf.interpreter.is_some() || is_static(elf) { "APPLICATION" } else { "LIBRARY" },
+        "licenseConcluded": "NOASSERTION",
+        "licenseDeclared": "NOASSERTION",
+        "copyrightText": "NOASSERTION",
+    });
+    if !findings.copyrights.is_empty() {
+        let texts: Vec<&str> = findings
+            .copyrights
+            .iter()
+            .map(|c| c.text.as_str())
+            .collect();
+        binary["copyrightText"] = texts.join("\n").into();
+    }
+    // Notices can belong to any library of the binary: they are reported, not concluded.
+    if !findings.licenses.is_empty() {
+        binary["licenseComments"] = format!(
+            "License notices found in the strings of the binary: {}",
+            license_ids(&findings.licenses).join(", ")
+        )
+        .into();
+        binary["attributionTexts"] = findings
+            .licenses
+            .iter()
+            .map(|license| license.text.as_str())
+            .collect::<Vec<_>>()
+            .into();
+    }
+
+    let mut packages = vec![binary];
+    let mut relationships = vec![serde_json::json!({
+        "spdxElementId": "SPDXRef-DOCUMENT",
+        "relationshipType": "DESCRIBES",
+        "relatedSpdxElement": binary_id,
+    })];
+    for library in &findings.libraries {
+        let mut package = serde_json::json!({
+            "SPDXID": library_id(&library.name),
+            "name": library.name,
+            "downloadLocation": "NOASSERTION",
+            "filesAnalyzed": false,
+            "primaryPackagePurpose": "LIBRARY",
+            "licenseConcluded": "NOASSERTION",
+            "licenseDeclared": "NOASSERTION",
+            "copyrightText": "NOASSERTION",
+            "externalRefs": [{
+                "referenceCategory": "PACKAGE-MANAGER",
+                "referenceType": "purl",
+                "referenceLocator": purl(library),
+            }],
+        });
+        match (&library.version, library.version_source) {
+            (Some(version), Some(VersionSource::VersionString)) => {
+                package["versionInfo"] = version.as_str().into();
+            }
+            (Some(version), _) => {
+                package["comment"] = format!("Minimum version: {}", version).into();
+            }
+            (None, _) => {}
+        }
+        packages.push(package);
+        relationships.push(serde_json::json!({
+            "spdxElementId": binary_id,
+            "relationshipType": if library.is_embedded() { "STATIC_LINK" } else { "DYNAMIC_LINK" },
+            "relatedSpdxElement": library_id(&library.name),
+        }));
+    }
+    for module in go_build_info.map_or(&[][..], |info| &info.dependencies) {
+        let module_id = format!("SPDXRef-Package-go-{}", spdx_id(&module.path));
+        packages.push(serde_json::json!({
+            "SPDXID": module_id,
+            "name": module.path,
+            "versionInfo": module.version,
+            "downloadLocation": "NOASSERTION",
+            "filesAnalyzed": false,
+            "primaryPackagePurpose": "LIBRARY",
+            "licenseConcluded": "NOASSERTION",
+            "licenseDeclared": "NOASSERTION",
+            "copyrightText": "NOASSERTION",
+            "externalRefs": [{
+                "referenceCategory": "PACKAGE-MANAGER",
+                "referenceType": "purl",
+                "referenceLocator": go_purl(module),
+            }],
+        }));
+        relationships.push(serde_json::json!({
+            "spdxElementId": binary_id,
+            "relationshipType": "STATIC_LINK",
+            "relatedSpdxElement": module_id,
+        }));
+    }
+
+    let created = creation_time();
+    let document = serde_json::json!({
+        "spdxVersion": "SPDX-2.3",
+        "dataLicense": "CC0-1.0",
+        "SPDXID": "SPDXRef-DOCUMENT",
+        "name": file_name,
+        "documentNamespace": format!(
+            "https://spdx.org/spdxdocs/{}-{}",
+            spdx_id(file_name),
+            uuid_from_digest(&digest)
+        ),
+        "creationInfo": {
+            "created": iso8601(created),
+            "creators": [format!("Tool: {}-{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))],
+        },
+        "packages": packages,
+        "relationships": relationships,
+    });
+
+    let json_str = serde_json::to_string_pretty(&document)?;
+    let manifest_path = format!("{}/sbom.spdx.json", path);
+    let mut file = File::create(manifest_path)?;
+    file.write_all(json_str.as_bytes())?;
+
+    Ok(())
+}
+
+/// Creates a SARIF 2.1.0 log of the security findings, for code scanning dashboards.
+///
+/// Calls to banned functions, vulnerability patterns, hardcoded secrets, weak cryptographic
+/// algorithms, vulnerable libraries and failed hardening checks are results of their own rule, located by address
+/// in the binary and by the functions involved.
+///
+/// # Arguments
+///
+/// * `elf` - The ELF object representing the binary, for its hardening checks.
+/// * `file_path` - The path of the ELF file.
+/// * `findings` - The findings about the binary.
+/// * `path` - The directory where `findings.sarif` is written.
+///
+/// # Returns
+///
+/// Returns a `Result` indicating success or failure.
+pub fn sarif_manifest(elf: &Elf, file_path: &str, findings: &Findings, path: &str) -> Result<()> {
+    let file_name = Path::new(file_path)
+        .file_name()
+        .map_or(file_path, |f| f.to_str().unwrap());
+    let location = |addr: Option<u64>, functions: &[&str]| {
+        let mut physical = serde_json::json!({ "artifactLocation": { "uri": file_name } });
+        if let Some(addr) = addr {
+            physical["address"] = serde_json::json!({ "absoluteAddress": addr });
+        }
+        let logical: Vec<serde_json::Value> = functions
+            .iter()
+            .map(|function| serde_json::json!({ "name": function, "kind": "function" }))
+            .collect();
+        let mut location = serde_json::json!({ "physicalLocation": physical });
+        if !logical.is_empty() {
+            location["logicalLocations"] = logical.into();
+        }
+        location
+    };
+    let result = |rule: &str, message: String, locations: Vec<serde_json::Value>| {
+        let index = SARIF_RULES.iter().position(|(id, _, _)| *id == rule);
+        serde_json::json!({
+            "ruleId": rule,
+            "ruleIndex": index,
+            "level": index.map(|index| SARIF_RULES[index].1),
+            "message": { "text": message },
+            "locations": locations,
+        })
+    };
+
+    let mut results = Vec::new();
+    for call in &findings.dangerous_calls {
+        results.push(result(
+            "dangerous-call",
+            format!(
+                "{} calls the banned function {}",
+                call.caller, call.function
+            ),
+            vec![location(Some(call.site), &[&call.caller])],
+        ));
+    }
+    for pattern in &findings.vulnerability_patterns {
+        results.push(result(
+            pattern.kind.as_str(),
+            format!(
+                "{} calls {}: {}",
+                pattern.caller, pattern.function, pattern.detail
+            ),
+            vec![location(Some(pattern.site), &[&pattern.caller])],
+        ));
+    }
+    for secret in &findings.secrets {
+        let functions: Vec<&str> = secret.functions.iter().map(String::as_str).collect();
+        results.push(result(
+            "hardcoded-secret",
+            format!("Hardcoded {}: {}", secret.kind, secret.redacted),
+            vec![location(Some(secret.addr), &functions)],
+        ));
+    }
+    for algorithm in findings.crypto.iter().filter(|algorithm| algorithm.weak) {
+        let mut locations: Vec<serde_json::Value> = algorithm
+            .functions
+            .iter()
+            .map(|(addr, name)| location(Some(*addr), &[name]))
+            .collect();
+        if locations.is_empty() {
+            locations.push(location(None, &[]));
+        }
+        results.push(result(
+            "weak-crypto",
+            format!("The binary uses the weak algorithm {}", algorithm.name),
+            locations,
+        ));
+    }
+    for library in findings.vulnerabilities.iter().flatten() {
+        for advisory in &library.advisories {
+            results.push(result(
+                "vulnerable-library",
+                format!(
+                    "{} {} is affected by {}{}",
+                    library.library,
+                    library.version,
+                    advisory.id,
+                    advisory
+                        .fixed
+                        .as_ref()
+                        .map_or(String::new(), |fixed| format!(", fixed in {}", fixed))
+                ),
+                vec![location(None, &[])],
+            ));
+        }
+    }
+    for check in hardening_checks(elf).iter().filter(|check| !check.passed) {
+        let rule = format!(
+            "hardening-{}",
+            check.name.to_lowercase().replace([' ', '_'], "-")
+        );
+        results.push(result(
+            &rule,
+            format!("{} check failed: {}", check.name, check.detail),
+            vec![location(None, &[])],
+        ));
+    }
+
+    let rules: Vec<serde_json::Value> = SARIF_RULES
+        .iter()
+        .map(|(id, level, description)| {
+            serde_json::json!({
+                "id": id,
+                "shortDescription": { "text": description },
+                "defaultConfiguration": { "level": level },
+            })
+        })
+        .collect();
+    let log = serde_json::json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules,
+                },
+            },
+            "artifacts": [{ "location": { "uri": file_name } }],
+            "results": results,
+        }],
+    });
+
+    let json_str = serde_json::to_string_pretty(&log)?;
+    let manifest_path = format!("{}/findings.sarif", path);
+    let mut file = File::create(manifest_path)?;
+    file.write_all(json_str.as_bytes())?;
+
+    Ok(())
+}
+
+// Package URL of a library, with its version only when it is the one in use.
+fn purl(library: &Library) -> String {
+    match (&library.version, library.version_source) {
+        (Some(version), Some(VersionSource::VersionString)) => {
+            format!("pkg:generic/{}@{}", library.name, version)
+        }
+        _ => format!("pkg:generic/{}", library.name),
+    }
+}
+
+// The package URL of a Go module, whose path is the namespace and name of the package.
+fn go_purl(module: &GoModule) -> String {
+    format!("pkg:golang/{}@{}", module.path, module.version)
+}
+
+// Keep the characters allowed in SPDX identifiers: letters, digits, `.` and `-`.
+fn spdx_id(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+/// Build the `generator` block of the manifests, which tells how they were produced: the name
+/// and version of the tool, the options of the analysis and the digest of the input.
+///
+/// The time of the analysis, taken from `SOURCE_DATE_EPOCH` when set, is left out when
+/// `timestamp` is false, so that the manifests of the same input and options are identical.
+///
+/// # Arguments
+///
+/// * `file_path` - The path of the ELF file analyzed.
+/// * `buffer` - The content of the ELF file.
+/// * `options` - The options of the analysis, as a JSON object.
+/// * `timestamp` - Whether the time of the analysis is written.
+///
+/// # Returns
+///
+/// Returns the `generator` block as a JSON object.
+pub fn generator_document(
+    file_path: &str,
+    buffer: &[u8],
+    options: serde_json::Value,
+    timestamp: bool,
+) -> serde_json::Value {
+    let mut generator = serde_json::json!({
+        "name": env!("CARGO_PKG_NAME"),
+        "version": env!("CARGO_PKG_VERSION"),
+        "options": options,
+        "input": {
+            "file": file_path,
+            "sha256": sha256_hex(buffer),
+        },
+    });
+    if let (true, Some(block)) = (timestamp, generator.as_object_mut()) {
+        block.insert(
+            "timestamp".to_string(),
+            serde_json::Value::String(iso8601(creation_time())),
+        );
+    }
+    generator
+}
+
+// Seconds since the epoch to date the generated documents, from `SOURCE_DATE_EPOCH` when set.
+fn creation_time() -> u64 {
+    std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse().ok())
+        .unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs())
+        })
+}
+
+// Format seconds since the Unix epoch as an ISO 8601 UTC timestamp.
+fn iso8601(secs: u64) -> String {
+    let (days, secs) = (secs / 86400, secs % 86400);
+    // Civil date from the days since 1970-01-01, counting eras of 400 years from 0000-03-01.
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// Creates an RFC 8520 Manufacturer Usage Description (MUD) file of the network behavior of the
+/// binary, to be served from `mud_url`.
+///
+/// Each endpoint with a known host, other than the loopback, is allowed by an access control
+/// entry from the device and one to the device, for connections initiated by the device. Domain
+/// names are matched with the DNS name extension of RFC 8520 in both the IPv4 and IPv6 access
+/// lists, addresses in the list of their family; the port is matched when the transport is
+/// known. The last update time is taken from `SOURCE_DATE_EPOCH` when set.
+///
+/// # Arguments
+///
+/// * `file_path` - The path of the ELF file, which names the device model.
+/// * `endpoints` - The network endpoints of the binary.
+/// * `mud_url` - The `https` URL the MUD file is served from.
+/// * `path` - The directory where `mud.json` is written.
+///
+/// # Returns
+///
+/// Returns a `Result` indicating success or failure.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidMudUrl`] if `mud_url` is not an `https` URL.
+pub fn mud_manifest(
+    file_path: &str,
+    endpoints: &[Endpoint],
+    mud_url: &str,
+    path: &str,
+) -> Result<()> {
+    if !mud_url.starts_with("https://") {
+        return Err(Error::InvalidMudUrl(mud_url.to_string()));
+    }
+    let file_name = Path::new(file_path)
+        .file_name()
+        .map_or(file_path, |f| f.to_str().unwrap());
+    let model = file_name.replace(|c: char| !c.is_ascii_alphanumeric() && c != '-', "-");
+
+    // Access control entries from and to the device, per IP family.
+    let mut aces: BTreeMap<(&str, &str), Vec<serde_json::Value>> = BTreeMap::new();
+    for (index, endpoint) in endpoints.iter().enumerate() {
+        let Some(host) = endpoint.host.as_deref() else {
+            continue;
+        };
+        let address = host
+            .trim_matches(['[', ']'])
+            .parse::<std::net::IpAddr>()
+            .ok();
+        if host == "localhost" || address.is_some_and(|address| address.is_loopback()) {
+            continue;
+        }
+        let families: &[&str] = match address {
+            Some(std::net::IpAddr::V4(_)) => &["ipv4"],
+            Some(std::net::IpAddr::V6(_)) => &["ipv6"],
+            None => &["ipv4", "ipv6"],
+        };
+        let transport = match endpoint.ip_protocol() {
+            Some(6) => Some("tcp"),
+            Some(17) => Some("udp"),
+            _ => None,
+        };
+        for family in families {
+            for (direction, side, dns_side) in
+                [("from", "destination", "dst"), ("to", "source", "src")]
+            {
+                let mut network = serde_json::Map::new();
+                match address {
+                    Some(address) => network.insert(
+                        format!("{}-{}-network", side, family),
+                        serde_json::json!(format!(
+                            "{}/{}",
+                            address,
+                            if address.is_ipv4() { 32 } else { 128 }
+                        )),
+                    ),
+                    None => network.insert(
+                        format!("ietf-acldns:{}-dnsname", dns_side),
+                        serde_json::json!(host),
+                    ),
+                };
+                if let Some(protocol) = endpoint.ip_protocol() {
+                    network.insert("protocol".to_string(), serde_json::json!(protocol));
+                }
+                let mut matches = serde_json::Map::new();
+                matches.insert(family.to_string(), serde_json::Value::Object(network));
+                if let Some(transport) = transport {
+                    let mut ports = serde_json::Map::new();
+                    if let Some(port) = endpoint.port {
+                        ports.insert(
+                            format!("{}-port", side),
+                            serde_json::json!({ "operator": "eq", "port": port }),
+                        );
+                    }
+                    if transport == "tcp" {
+                        ports.insert(
+                            "ietf-mud:direction-initiated".to_string(),
+                            serde_json::json!("from-device"),
+                        );
+                    }
+                    matches.insert(transport.to_string(), serde_json::Value::Object(ports));
+                }
+                aces.entry((direction, family)).or_default().push(serde_json::json!({
+                    "name": format!("cl{}-{}dev", index, if direction == "from" { "fr" } else { "to" }),
+                    "matches": matches,
+                    "actions": { "forwarding": "accept" },
+                }));
+            }
+        }
+    }
+
+    let acl_name = |direction: &str, family: &str| format!("{}-{}-{}", direction, family, model);
+    let policy = |direction: &str| -> serde_json::Value {
+        let lists: Vec<serde_json::Value> = aces
+            .keys()
+            .filter(|(acl_direction, _)| *acl_direction == direction)
+            .map(|(_, family)| serde_json::json!({ "name": acl_name(direction, family) }))
+            .collect();
+        serde_json::json!({ "access-lists": { "access-list": lists } })
+    };
+    let acls: Vec<serde_json::Value> = aces
+        .iter()
+        .map(|((direction, family), aces)| {
+            serde_json::json!({
+                "name": acl_name(direction, family),
+                "type": format!("{}-acl-type", family),
+                "aces": { "ace": aces },
+            })
+        })
+        .collect();
+    let document = serde_json::json!({
+        "ietf-mud:mud": {
+            "mud-version": 1,
+            "mud-url": mud_url,
+            "last-update": iso8601(creation_time()),
+            "cache-validity": 48,
+            "is-supported": true,
+            "systeminfo": format!("{}, described by manifest-producer", file_name),
+            "model-name": model,
+            "from-device-policy": policy("from"),
+            "to-device-policy": policy("to"),
+        },
+        "ietf-access-control-list:acls": { "acl": acls },
+    });
+
+    let json_str = serde_json::to_string_pretty(&document)?;
+    let manifest_path = format!("{}/mud.json", path);
+    let mut file = File::create(manifest_path)?;
+    file.write_all(json_str.as_bytes())?;
+
+    Ok(())
+}
+
+/// Creates the summary report of a batch analysis, listing each binary with its output folder
+/// and, when its analysis failed, the error.
+///
+/// # Arguments
+///
+/// * `entries` - The outcome of the analysis of each binary.
+/// * `path` - The directory where `summary.json` is written.
+///
+/// # Returns
+///
+/// Returns a `Result` indicating success or failure.
+pub fn summary_manifest(entries: &[BatchEntry], path: &str) -> Result<()> {
+    let binaries: Vec<serde_json::Value> = entries
+        .iter()
+        .map(|entry| {
+            serde_json::json!({
+                "path": entry.path,
+                "output": entry.output_dir,
+                "status": if entry.error.is_none() { "analyzed" } else { "failed" },
+                "error": entry.error,
+            })
+        })
+        .collect();
+    let failed = entries.iter().filter(|entry| entry.error.is_some()).count();
+    let json_obj = serde_json::json!({
+        "analyzed": entries.len() - failed,
+        "failed": failed,
+        "binaries": binaries,
+    });
+
+    let json_str = serde_json::to_string_pretty(&json_obj)?;
+    let manifest_path = format!("{}/summary.json", path);
+    let mut file = File::create(manifest_path)?;
+    file.write_all(json_str.as_bytes())?;
+
+    Ok(())
+}
+
+/// Merges the manifests of the binaries of a firmware image into a device-level manifest.
+///
+/// Each binary is listed once with its provenance (file name, digest, architecture, language
+/// and link), its APIs and their capabilities. The capabilities of the device are deduplicated,
+/// each with the binaries and APIs exposing it, and every API and function called is listed with
+/// the binaries where it was found.
+///
+/// # Arguments
+///
+/// * `dirs` - The output directories holding the manifests of each binary, in any format.
+/// * `format` - The format of the manifest.
+/// * `path` - The directory where `firmware_manifest` is written.
+///
+/// # Returns
+///
+/// Returns a `Result` indicating success or failure.
+///
+/// # Errors
+///
+/// Returns an error if a directory lacks the basic info, flow call or feature manifest.
+pub fn merge_manifests(dirs: &[&str], format: Format, path: &str) -> Result<()> {
+    let mut binaries = Vec::new();
+    let mut capabilities: BTreeMap<String, BTreeSet<(String, String)>> = BTreeMap::new();
+    let mut apis: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    let mut syscalls: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+
+    for dir in dirs {
+        let basic_info = find_manifest(dir, "basic_info")?;
+        let flow_call = find_manifest(dir, "flow_call")?;
+        let feature = find_manifest(dir, "feature_manifest")?;
+        let binary = basic_info
+            .get("file_name")
+            .and_then(serde_json::Value::as_str)
+            .unwrap_or(dir)
+            .to_string();
+
+        let mut binary_apis = serde_json::Map::new();
+        for api in flow_call
+            .get("Public APIs flow")
+            .and_then(serde_json::Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            let Some(name) = api.get("name").and_then(serde_json::Value::as_str) else {
+                continue;
+            };
+            let api_capabilities = feature.get(name).cloned().unwrap_or(serde_json::json!([]));
+            for capability in api_capabilities
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(serde_json::Value::as_str)
+            {
+                capabilities
+                    .entry(capability.to_string())
+                    .or_default()
+                    .insert((binary.clone(), name.to_string()));
+            }
+            for call in ["syscalls", "transitive calls"]
+                .iter()
+                .filter_map(|field| api.get(*field)?.as_array())
+                .flatten()
+                .filter_map(serde_json::Value::as_str)
+            {
+                syscalls
+                    .entry(call.to_string())
+                    .or_default()
+                    .insert(binary.clone());
+            }
+            apis.entry(name.to_string())
+                .or_default()
+                .insert(binary.clone());
+            binary_apis.insert(name.to_string(), api_capabilities);
+        }
+
+        binaries.push(serde_json::json!({
+            "file_name": binary,
+            "sha256": basic_info.get("sha256"),
+            "architecture": basic_info.get("architecture"),
+            "programming language": basic_info.get("programming language"),
+            "link": basic_info.get("link"),
+            "APIs": binary_apis,
+        }));
+    }
+
+    let capabilities: serde_json::Map<String, serde_json::Value> = capabilities
+        .into_iter()
+        .map(|(capability, exposed_by)| {
+            let exposed_by: Vec<serde_json::Value> = exposed_by
+                .into_iter()
+                .map(|(binary, api)| serde_json::json!({ "binary": binary, "API": api }))
+                .collect();
+            (capability, serde_json::Value::Array(exposed_by))
+        })
+        .collect();
+    let json_obj = serde_json::json!({
+        "schema_version": SCHEMA_VERSION,
+        "binaries": binaries,
+        "capabilities": capabilities,
+        "APIs": apis,
+        "syscalls": syscalls,
+    });
+
+    write_manifest(&json_obj, path, "firmware_manifest", format)
+}
+
+/// Read the manifest of an output directory with the given file stem, in any format.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidManifest`] if the directory holds no such manifest.
+pub fn find_manifest(dir: &str, stem: &str) -> Result<serde_json::Value> {
+    let manifest_path = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .find(|path| {
+            path.file_stem().and_then(|s| s.to_str()) == Some(stem)
+                && Format::from_extension(path.to_str().unwrap_or_default()).is_some()
+        })
+        .ok_or_else(|| Error::InvalidManifest(format!("no {} manifest in {}", stem, dir)))?;
+    read_manifest(manifest_path.to_str().unwrap_or_default())
+}
+
+/// Read every manifest of an output directory, by name: the file name without its extension,
+/// e.g. `basic_info`, `sbom.cdx` or `findings`. The DOT graphs are left out.
+///
+/// # Errors
+///
+/// Returns an error if the directory or a manifest cannot be read.
+pub fn collect_manifests(dir: &str) -> Result<BTreeMap<String, serde_json::Value>> {
+    let mut manifests = BTreeMap::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let (Some(name), Some(file_path)) =
+            (path.file_name().and_then(|s| s.to_str()), path.to_str())
+        else {
+            continue;
+        };
+        if !path.is_file() {
+            continue;
+        }
+        let (name, manifest) = match name.strip_suffix(".sarif") {
+            Some(stem) => (stem, serde_json::from_slice(&std::fs::read(&path)?)?),
+            None if Format::from_extension(file_path).is_some() => (
+                name.rsplit_once('.').map_or(name, |(stem, _)| stem),
+                read_manifest(file_path)?,
+            ),
+            None => continue,
+        };
+        manifests.insert(name.to_string(), manifest);
+    }
+    Ok(manifests)
+}
+
+// Write a manifest as `<path>/<name>.<extension>` in the given format.
+pub(crate) fn write_manifest(
+    value: &serde_json::Value,
+    path: &str,
+    name: &str,
+    format: Format,
+) -> Result<()> {
+    let contents = match format {
+        Format::Json => serde_json::to_string_pretty(value)?.into_bytes(),
+        Format::Yaml => serde_yaml::to_string(value)?.into_bytes(),
+        Format::Toml => toml::to_string_pretty(&without_nulls(value))?.into_bytes(),
+        Format::Cbor => {
+            let mut contents = Vec::new();
+            ciborium::into_writer(value, &mut contents)?;
+            contents
+        }
+        Format::MsgPack => rmp_serde::to_vec(value)?,
+    };
+    let manifest_path = format!("{}/{}.{}", path, name, format.extension());
+    let mut file = File::create(manifest_path)?;
+    file.write_all(&contents)?;
+
+    Ok(())
+}
+
+/// Read a manifest written in any format, told by the extension of its file name.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read, or cannot be decoded in its format.
+pub fn read_manifest(file_path: &str) -> Result<serde_json::Value> {
+    let format = Format::from_extension(file_path)
+        .ok_or_else(|| Error::InvalidManifest(format!("unknown format of {}", file_path)))?;
+    let contents = std::fs::read(file_path)?;
+    let invalid = |error: String| Error::InvalidManifest(format!("{}: {}", file_path, error));
+    let text = || String::from_utf8(contents.clone()).map_err(|error| invalid(error.to_string()));
+    match format {
+        Format::Json => Ok(serde_json::from_slice(&contents)?),
+        Format::Yaml => serde_yaml::from_str(&text()?).map_err(|error| invalid(error.to_string())),
+        Format::Toml => toml::from_str(&text()?).map_err(|error| invalid(error.to_string())),
+        Format::Cbor => {
+            ciborium::from_reader(contents.as_slice()).map_err(|error| invalid(error.to_string()))
+        }
+        Format::MsgPack => {
+            rmp_serde::from_slice(&contents).map_err(|error| invalid(error.to_string()))
+        }
+    }
+}
+
+// Copy a JSON value leaving out the null fields and array elements.
+fn without_nulls(value: &serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => map
+            .iter()
+            .filter(|(_, value)| !value.is_null())
+            .map(|(key, value)| (key.clone(), without_nulls(value)))
+            .collect(),
+        serde_json::Value::Array(values) => values
+            .iter()
+            .filter(|value| !value.is_null())
+            .map(without_nulls)
+            .collect(),
+        value => value.clone(),
+    }
+}
+
+// Format the first 16 bytes of a hexadecimal digest as a version 4 UUID, so that the BOMs of
+// the same binary share the same serial number.
+fn uuid_from_digest(digest: &str) -> String {
+    let mut hex: Vec<char> = digest.chars().take(32).collect();
+    hex[12] = '4';
+    hex[16] = ['8', '9', 'a', 'b'][hex[16].to_digit(16).unwrap_or(0) as usize % 4];
+    let hex: String = hex.into_iter().collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+fn endpoints_json(endpoints: &[Endpoint]) -> serde_json::Value {
+    endpoints
+        .iter()
+        .map(|endpoint| {
+            let kind = match &endpoint.host {
+                Some(_) if endpoint.is_ip() => Some("ip"),
+                Some(_) => Some("domain"),
+                None => None,
+            };
+            serde_json::json!({
+                "host": endpoint.host,
+                "kind": kind,
+                "port": endpoint.port,
+                "protocol": endpoint.protocol,
+                "source": endpoint.source,
+            })
+        })
+        .collect()
+}
+
+// Turn an API name into a file name, replacing the characters of signatures and paths. The
+// names replaced are followed by the start address of the API, so that two APIs differing only
+// by those characters, e.g. `Camera::open` and `Camera__open`, are not written to the same file.
+fn file_name(name: &str, addr: u64) -> String {
+    let file_name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if file_name == name {
+        file_name
+    } else {
+        format!("{}@{:x}", file_name, addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(file_name("writeOnDrive", 0x1000), "writeOnDrive");
+        assert_eq!(file_name("Camera__open", 0x1000), "Camera__open");
+        assert_eq!(file_name("Camera::open", 0x2000), "Camera__open@2000");
+        assert_eq!(file_name("../open", 0x3000), "___open@3000");
+    }
+}
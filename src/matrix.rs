@@ -0,0 +1,425 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+
+use serde_json::{json, Value};
+
+use crate::{error::Result, manifest_creation::find_manifest, schema::SCHEMA_VERSION};
+
+// The flows of the flow call manifest whose calls make the syscall set of a binary.
+const FLOW_SECTIONS: [&str; 5] = [
+    "Public APIs flow",
+    "Thread flows",
+    "Signal handler flows",
+    "Startup flows",
+    "Shutdown flows",
+];
+
+/// What the manifests of one binary record, as compared across a fleet.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BinaryProfile {
+    /// The file name of the binary.
+    pub file_name: String,
+    /// The output directory its manifests were read from.
+    pub dir: String,
+    /// The architecture, e.g. `x86-64`.
+    pub architecture: Option<String>,
+    /// The programming language, e.g. `C99`.
+    pub language: Option<String>,
+    /// How the binary is linked, e.g. `dynamically linked`.
+    pub link: Option<String>,
+    /// The verdict of each hardening check, e.g. `PIE` → `pass`.
+    pub hardening: BTreeMap<String, String>,
+    /// The capabilities of its APIs, e.g. `filesystem-write`.
+    pub capabilities: BTreeSet<String>,
+    /// The functions called by its flows.
+    pub syscalls: BTreeSet<String>,
+}
+
+impl BinaryProfile {
+    /// Load the profile of a binary from the basic info, flow call and feature manifests of
+    /// its output directory, in any format.
+    pub fn load(dir: &str) -> Result<Self> {
+        Ok(Self::from_json(
+            dir,
+            &find_manifest(dir, "basic_info")?,
+            &find_manifest(dir, "flow_call")?,
+            &find_manifest(dir, "feature_manifest")?,
+        ))
+    }
+
+    /// Build the profile of a binary from its basic info, flow call and feature manifests.
+    pub fn from_json(dir: &str, basic_info: &Value, flow_call: &Value, feature: &Value) -> Self {
+        let text = |field: &str| basic_info.get(field)?.as_str().map(str::to_string);
+        let hardening = basic_info
+            .get("hardening")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flatten()
+            .filter_map(|(check, result)| {
+                let verdict = result.get("verdict")?.as_str()?;
+                Some((check.clone(), verdict.to_string()))
+            })
+            .collect();
+        let capabilities = feature
+            .get("capabilities")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flat_map(|apis| apis.values())
+            .filter_map(Value::as_object)
+            .flat_map(|capabilities| capabilities.keys().cloned())
+            .collect();
+        let syscalls = FLOW_SECTIONS
+            .iter()
+            .filter_map(|section| flow_call.get(*section)?.as_array())
+            .flatten()
+            .filter_map(|flow| flow.get("syscalls")?.as_array())
+            .flatten()
+            .filter_map(Value::as_str)
+            .map(str::to_string)
+            .collect();
+        Self {
+            file_name: text("file_name").unwrap_or_else(|| dir.to_string()),
+            dir: dir.to_string(),
+            architecture: text("architecture"),
+            language: text("programming language"),
+            link: text("link"),
+            hardening,
+            capabilities,
+            syscalls,
+        }
+    }
+}
+
+/// A comparison matrix of the binaries of a fleet or of a product line: one row per binary, one
+/// column per architecture, language, link, hardening check, capability and syscall.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FleetMatrix {
+    /// The binaries compared, sorted by file name and directory.
+    pub binaries: Vec<BinaryProfile>,
+}
+
+impl FleetMatrix {
+    /// Compare the binaries analyzed in the given directories.
+    ///
+    /// # Arguments
+    ///
+    /// * `dirs` - The output directories of the binaries, or directories holding them, e.g. the
+    ///   output directory of a batch analysis.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the matrix of the binaries found.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a directory cannot be read, or an output directory lacks the flow
+    /// call or feature manifest.
+    pub fn load(dirs: &[&str]) -> Result<Self> {
+        let mut binaries = Vec::new();
+        for dir in dirs {
+            if has_basic_info(dir) {
+                binaries.push(BinaryProfile::load(dir)?);
+                continue;
+            }
+            let mut children: Vec<String> = std::fs::read_dir(dir)?
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.is_dir())
+                .filter_map(|path| path.to_str().map(str::to_string))
+                .filter(|path| has_basic_info(path))
+                .collect();
+            children.sort();
+            for child in children {
+                binaries.push(BinaryProfile::load(&child)?);
+            }
+        }
+        Ok(Self::new(binaries))
+    }
+
+    /// Build the matrix of the given binaries.
+    pub fn new(mut binaries: Vec<BinaryProfile>) -> Self {
+        binaries.sort_by(|a, b| (&a.file_name, &a.dir).cmp(&(&b.file_name, &b.dir)));
+        Self { binaries }
+    }
+
+    /// Return the names of the columns, e.g. `hardening PIE`, `capability filesystem-write` or
+    /// `syscall fopen64`.
+    pub fn columns(&self) -> Vec<String> {
+        let union = |values: fn(&BinaryProfile) -> Vec<&String>| -> BTreeSet<&String> {
+            self.binaries.iter().flat_map(values).collect()
+        };
+        let mut columns = vec![
+            "architecture".to_string(),
+            "language".to_string(),
+            "link".to_string(),
+        ];
+        let hardening = union(|binary| binary.hardening.keys().collect());
+        columns.extend(hardening.iter().map(|check| format!("hardening {}", check)));
+        let capabilities = union(|binary| binary.capabilities.iter().collect());
+        columns.extend(
+            capabilities
+                .iter()
+                .map(|name| format!("capability {}", name)),
+        );
+        let syscalls = union(|binary| binary.syscalls.iter().collect());
+        columns.extend(syscalls.iter().map(|name| format!("syscall {}", name)));
+        columns
+    }
+
+    /// Return the cells of the row of a binary, in the order of [`FleetMatrix::columns`]: its
+    /// value, the verdict of a hardening check, or `x` for a capability or syscall it has. A
+    /// cell without a value is empty.
+    pub fn cells(&self, binary: &BinaryProfile) -> Vec<String> {
+        self.columns()
+            .iter()
+            .map(|column| cell(binary, column))
+            .collect()
+    }
+
+    /// Return the columns where a binary is an outlier: its cell differs from that of most of
+    /// the binaries, more than half of them sharing the same one.
+    pub fn outliers(&self, binary: &BinaryProfile) -> Vec<String> {
+        let columns = self.columns();
+        outliers(binary, &columns, &self.majorities(&columns))
+    }
+
+    // The cell shared by more than half of the binaries in each column, if any.
+    fn majorities(&self, columns: &[String]) -> Vec<Option<String>> {
+        columns
+            .iter()
+            .map(|column| {
+                let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+                for binary in &self.binaries {
+                    *counts.entry(cell(binary, column)).or_default() += 1;
+                }
+                counts
+                    .into_iter()
+                    .find(|(_, count)| 2 * count > self.binaries.len())
+                    .map(|(value, _)| value)
+            })
+            .collect()
+    }
+
+    /// Return the matrix as CSV (RFC 4180): a header line, then one line per binary with its
+    /// file name, directory, cells and outlier columns, separated by `;`.
+    pub fn to_csv(&self) -> String {
+        let mut header = vec!["binary".to_string(), "directory".to_string()];
+        let columns = self.columns();
+        let majorities = self.majorities(&columns);
+        header.extend(columns.iter().cloned());
+        header.push("outliers".to_string());
+        let mut csv = csv_line(&header);
+        for binary in &self.binaries {
+            let mut line = vec![binary.file_name.clone(), binary.dir.clone()];
+            line.extend(columns.iter().map(|column| cell(binary, column)));
+            line.push(outliers(binary, &columns, &majorities).join(";"));
+            csv.push_str(&csv_line(&line));
+        }
+        csv
+    }
+
+    /// Return the matrix as JSON, with the columns and each binary with its values and
+    /// outlier columns.
+    pub fn to_json(&self) -> Value {
+        let columns = self.columns();
+        let majorities = self.majorities(&columns);
+        let binaries: Vec<Value> = self
+            .binaries
+            .iter()
+            .map(|binary| {
+                json!({
+                    "file_name": binary.file_name,
+                    "directory": binary.dir,
+                    "architecture": binary.architecture,
+                    "programming language": binary.language,
+                    "link": binary.link,
+                    "hardening": binary.hardening,
+                    "capabilities": binary.capabilities,
+                    "syscalls": binary.syscalls,
+                    "outliers": outliers(binary, &columns, &majorities),
+                })
+            })
+            .collect();
+        json!({
+            "schema_version": SCHEMA_VERSION,
+            "columns": columns,
+            "binaries": binaries,
+        })
+    }
+}
+
+// Whether a directory holds the basic info manifest of a binary.
+fn has_basic_info(dir: &str) -> bool {
+    std::fs::read_dir(dir).is_ok_and(|entries| {
+        entries.filter_map(|entry| entry.ok()).any(|entry| {
+            Path::new(&entry.file_name())
+                .file_stem()
+                .and_then(|s| s.to_str())
+                == Some("basic_info")
+        })
+    })
+}
+
+// The cell of a binary in a column of the matrix.
+fn cell(binary: &BinaryProfile, column: &str) -> String {
+    let has = |present: bool| if present { "x" } else { "" }.to_string();
+    match column.split_once(' ') {
+        Some(("hardening", check)) => binary.hardening.get(check).cloned().unwrap_or_default(),
+        Some(("capability", name)) => has(binary.capabilities.contains(name)),
+        Some(("syscall", name)) => has(binary.syscalls.contains(name)),
+        _ => match column {
+            "architecture" => binary.architecture.clone(),
+            "language" => binary.language.clone(),
+            _ => binary.link.clone(),
+        }
+        .unwrap_or_default(),
+    }
+}
+
+// The columns where the cell of a binary differs from that of the majority.
+fn outliers(
+    binary: &BinaryProfile,
+    columns: &[String],
+    majorities: &[Option<String>],
+) -> Vec<String> {
+    columns
+        .iter()
+        .zip(majorities)
+        .filter(|(column, majority)| {
+            majority
+                .as_ref()
+                .is_some_and(|majority| *majority != cell(binary, column))
+        })
+        .map(|(column, _)| column.clone())
+        .collect()
+}
+
+// A line of CSV, the fields holding a separator, a quote or a line break being quoted.
+fn csv_line(fields: &[String]) -> String {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|field| match field.contains([',', '"', '\n', '\r']) {
+            true => format!("\"{}\"", field.replace('"', "\"\"")),
+            false => field.clone(),
+        })
+        .collect();
+    format!("{}\r\n", fields.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(name: &str, pie: &str, capability: &str, syscalls: &[&str]) -> BinaryProfile {
+        BinaryProfile::from_json(
+            &format!("out/{}", name),
+            &json!({
+                "file_name": name,
+                "architecture": "ARM",
+                "programming language": "C99",
+                "link": "dynamically linked",
+                "hardening": {
+                    "PIE": { "verdict": pie, "detail": "" },
+                    "gadgets": { "ROP": 10 },
+                },
+            }),
+            &json!({ "Public APIs flow": [{ "name": "api", "syscalls": syscalls }] }),
+            &json!({ "capabilities": { "api": { capability: ["fopen"] } } }),
+        )
+    }
+
+    #[test]
+    fn test_fleet_matrix() {
+        let matrix = FleetMatrix::new(vec![
+            profile("httpd", "pass", "network-server", &["socket", "bind"]),
+            profile("ntpd", "pass", "network-client", &["socket"]),
+            profile("camd", "fail", "camera", &["socket", "ioctl"]),
+        ]);
+        assert_eq!(matrix.binaries[0].file_name, "camd");
+        assert_eq!(
+            matrix.columns(),
+            [
+                "architecture",
+                "language",
+                "link",
+                "hardening PIE",
+                "capability camera",
+                "capability network-client",
+                "capability network-server",
+                "syscall bind",
+                "syscall ioctl",
+                "syscall socket",
+            ]
+        );
+        let camd = &matrix.binaries[0];
+        assert_eq!(
+            matrix.cells(camd),
+            [
+                "ARM",
+                "C99",
+                "dynamically linked",
+                "fail",
+                "x",
+                "",
+                "",
+                "",
+                "x",
+                "x"
+            ]
+        );
+        // Each capability is held by one binary out of three: the others share no cell.
+        assert_eq!(
+            matrix.outliers(camd),
+            ["hardening PIE", "capability camera", "syscall ioctl"]
+        );
+        assert!(matrix
+            .outliers(&matrix.binaries[1])
+            .contains(&"syscall bind".to_string()));
+
+        let csv = matrix.to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("binary,directory,architecture,"));
+        assert!(lines[1].starts_with("camd,out/camd,ARM,C99,dynamically linked,fail,x,"));
+        assert!(lines[1].ends_with(",hardening PIE;capability camera;syscall ioctl"));
+        assert_eq!(
+            csv_line(&["a,b".to_string(), "\"c\"".to_string()]),
+            "\"a,b\",\"\"\"c\"\"\"\r\n"
+        );
+
+        let json = matrix.to_json();
+        assert_eq!(json["binaries"][1]["file_name"], "httpd");
+        assert_eq!(json["binaries"][1]["hardening"]["PIE"], "pass");
+        assert_eq!(json["binaries"][1]["syscalls"], json!(["bind", "socket"]));
+        assert_eq!(
+            json["binaries"][2]["outliers"],
+            json!(["capability network-client"])
+        );
+    }
+
+    #[test]
+    fn test_load_fleet_matrix() {
+        let dir = std::env::temp_dir().join("manifest-matrix");
+        let _ = std::fs::remove_dir_all(&dir);
+        for binary in ["a", "b"] {
+            let output = dir.join(binary);
+            std::fs::create_dir_all(&output).unwrap();
+            let manifests = [
+                ("basic_info.json", json!({ "file_name": binary })),
+                ("flow_call.json", json!({})),
+                ("feature_manifest.json", json!({})),
+            ];
+            for (name, manifest) in manifests {
+                std::fs::write(output.join(name), manifest.to_string()).unwrap();
+            }
+        }
+        std::fs::create_dir_all(dir.join("logs")).unwrap();
+
+        let fleet = FleetMatrix::load(&[dir.to_str().unwrap()]).unwrap();
+        assert_eq!(fleet.binaries.len(), 2);
+        let single = FleetMatrix::load(&[dir.join("b").to_str().unwrap()]).unwrap();
+        assert_eq!(single.binaries[0].file_name, "b");
+    }
+}
@@ -0,0 +1,262 @@
+//! Custom analyses registered by other crates, or loaded from shared libraries with the
+//! `plugins` feature, whose results appear in the `plugins` section of the manifests.
+//!
+//! ```no_run
+//! use manifest_producer::analyzer::{Analysis, Analyzer};
+//! use manifest_producer::api_detection::ApiSpec;
+//! use manifest_producer::error::Result;
+//! use manifest_producer::plugins::AnalysisPass;
+//! use serde_json::{json, Value};
+//!
+//! struct NoExec;
+//!
+//! impl AnalysisPass for NoExec {
+//!     fn name(&self) -> &str {
+//!         "no-exec"
+//!     }
+//!
+//!     fn run(&self, analysis: &Analysis) -> Result<Value> {
+//!         let offending: Vec<&str> = analysis
+//!             .apis
+//!             .iter()
+//!             .filter(|api| api.transitive_calls.iter().any(|call| call.starts_with("exec")))
+//!             .map(|api| api.name.as_str())
+//!             .collect();
+//!         Ok(json!({"pass": offending.is_empty(), "offending APIs": offending}))
+//!     }
+//! }
+//!
+//! Analyzer::new("firmware.elf")
+//!     .with_api_list(vec![ApiSpec::new("writeOnDrive")])
+//!     .with_pass(NoExec)
+//!     .run("./manifest-produced")?;
+//! # Ok::<(), manifest_producer::error::Error>(())
+//! ```
+
+use std::{any::Any, collections::BTreeMap, sync::Arc};
+
+use serde_json::{Map, Value};
+
+use crate::{
+    analyzer::Analysis,
+    error::{Error, Result},
+    schema::ManifestKind,
+};
+
+/// A custom check run on a binary once the built-in analysis is done, e.g. a rule of a
+/// certification lab.
+pub trait AnalysisPass: Send + Sync {
+    /// The name of the pass, under which its result is kept, e.g. `no-exec`.
+    fn name(&self) -> &str;
+
+    /// The manifest whose `plugins` section holds the result of the pass, the feature manifest
+    /// by default. `None` keeps the result in [`Analysis::plugins`] only, e.g. for a
+    /// [`ManifestSection`] to present it.
+    fn manifest(&self) -> Option<ManifestKind> {
+        Some(ManifestKind::Feature)
+    }
+
+    /// Run the pass on the results of the analysis.
+    ///
+    /// # Errors
+    ///
+    /// An error of the pass fails the analysis.
+    fn run(&self, analysis: &Analysis) -> Result<Value>;
+}
+
+/// A section added to the `plugins` object of a manifest, built from the analysis and the
+/// results of the passes.
+pub trait ManifestSection: Send + Sync {
+    /// The name of the section, its key in the `plugins` object.
+    fn name(&self) -> &str;
+
+    /// The manifest the section is added to: the basic information, flow call or feature
+    /// manifest.
+    fn manifest(&self) -> ManifestKind;
+
+    /// Build the section; `None` leaves it out.
+    ///
+    /// # Errors
+    ///
+    /// An error of the section fails the writing of the manifests.
+    fn build(&self, analysis: &Analysis) -> Result<Option<Value>>;
+}
+
+/// The signature of the function a plugin library exports as `manifest_producer_plugin`,
+/// registering its passes and sections.
+///
+/// The Rust interface is not stable across compilers: the library must be built with the
+/// compiler, the version and the features of this crate the tool was built with.
+#[allow(improper_ctypes_definitions)]
+pub type RegisterFn = unsafe extern "C" fn(&mut Plugins);
+
+/// The passes and sections registered with an [`crate::analyzer::Analyzer`].
+#[derive(Clone, Default)]
+pub struct Plugins {
+    passes: Vec<Arc<dyn AnalysisPass>>,
+    sections: Vec<Arc<dyn ManifestSection>>,
+    // The libraries the passes and sections were loaded from, unloaded after them. Kept as
+    // `Any` so that the layout does not depend on the features the plugins were built with.
+    #[cfg_attr(not(feature = "plugins"), allow(dead_code))]
+    libraries: Vec<Arc<dyn Any + Send + Sync>>,
+}
+
+impl Plugins {
+    /// Register a pass, run after those registered before it.
+    pub fn register_pass(&mut self, pass: impl AnalysisPass + 'static) {
+        self.passes.push(Arc::new(pass));
+    }
+
+    /// Register a manifest section.
+    pub fn register_section(&mut self, section: impl ManifestSection + 'static) {
+        self.sections.push(Arc::new(section));
+    }
+
+    /// Whether no pass nor section is registered.
+    pub fn is_empty(&self) -> bool {
+        self.passes.is_empty() && self.sections.is_empty()
+    }
+
+    /// Load a plugin library and register its passes and sections, by calling the
+    /// `manifest_producer_plugin` function it exports (see [`RegisterFn`]).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidPlugin`] if the library cannot be loaded or exports no such
+    /// function.
+    ///
+    /// # Safety
+    ///
+    /// Loading a library runs its initialization code, and its registration function must
+    /// have the signature of [`RegisterFn`].
+    #[cfg(feature = "plugins")]
+    pub unsafe fn load(&mut self, path: &str) -> Result<()> {
+        let invalid =
+            |error: libloading::Error| Error::InvalidPlugin(format!("{}: {}", path, error));
+        let library = libloading::Library::new(path).map_err(invalid)?;
+        let register = *library
+            .get::<RegisterFn>(b"manifest_producer_plugin\0")
+            .map_err(invalid)?;
+        register(self);
+        self.libraries.push(Arc::new(library));
+        Ok(())
+    }
+
+    /// Run the passes on an analysis.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the result of each pass, by name.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidPlugin`] if two passes have the same name, or the first error of
+    /// a pass.
+    pub fn run_passes(&self, analysis: &Analysis) -> Result<BTreeMap<String, Value>> {
+        let mut results = BTreeMap::new();
+        for pass in &self.passes {
+            let result = pass.run(analysis)?;
+            if results.insert(pass.name().to_string(), result).is_some() {
+                return Err(Error::InvalidPlugin(format!(
+                    "two passes are named {}",
+                    pass.name()
+                )));
+            }
+        }
+        Ok(results)
+    }
+
+    /// Build the `plugins` section of a manifest: the results of the passes presented in it,
+    /// then its sections.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the section, `None` when empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error of a section.
+    pub fn section(&self, analysis: &Analysis, kind: ManifestKind) -> Result<Option<Value>> {
+        let mut section = Map::new();
+        for pass in &self.passes {
+            if let (Some(true), Some(result)) = (
+                pass.manifest().map(|manifest| manifest == kind),
+                analysis.plugins.get(pass.name()),
+            ) {
+                section.insert(pass.name().to_string(), result.clone());
+            }
+        }
+        for manifest_section in self.sections.iter().filter(|s| s.manifest() == kind) {
+            if let Some(value) = manifest_section.build(analysis)? {
+                section.insert(manifest_section.name().to_string(), value);
+            }
+        }
+        Ok((!section.is_empty()).then_some(Value::Object(section)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{analyzer::Analyzer, api_detection::ApiSpec, schema::validate};
+    use serde_json::json;
+
+    struct ApiCount;
+
+    impl AnalysisPass for ApiCount {
+        fn name(&self) -> &str {
+            "api-count"
+        }
+
+        fn run(&self, analysis: &Analysis) -> Result<Value> {
+            Ok(json!(analysis.apis.len()))
+        }
+    }
+
+    struct Language;
+
+    impl ManifestSection for Language {
+        fn name(&self) -> &str {
+            "lab-language"
+        }
+
+        fn manifest(&self) -> ManifestKind {
+            ManifestKind::BasicInfo
+        }
+
+        fn build(&self, analysis: &Analysis) -> Result<Option<Value>> {
+            Ok(Some(json!({
+                "language": analysis.binary.language,
+                "api-count": analysis.plugins["api-count"],
+            })))
+        }
+    }
+
+    #[test]
+    fn test_plugins() {
+        let analyzer = Analyzer::new("./tests/elf_file/fake-firmware-c-dynamic")
+            .with_api_list(vec![ApiSpec::new("writeOnDrive")])
+            .with_pass(ApiCount)
+            .with_section(Language);
+        let analysis = analyzer.analyze().unwrap();
+        assert_eq!(analysis.plugins["api-count"], 1);
+        let manifests = analyzer.manifests(&analysis).unwrap();
+        assert_eq!(manifests["feature_manifest"]["plugins"]["api-count"], 1);
+        assert_eq!(
+            manifests["basic_info"]["plugins"]["lab-language"]["api-count"],
+            1
+        );
+        assert!(manifests["flow_call"].get("plugins").is_none());
+        for (name, manifest) in &manifests {
+            assert!(validate(name.parse().unwrap(), manifest)
+                .unwrap()
+                .is_empty());
+        }
+
+        let analyzer = Analyzer::new("./tests/elf_file/fake-firmware-c-dynamic")
+            .with_api_list(vec![ApiSpec::new("writeOnDrive")])
+            .with_pass(ApiCount)
+            .with_pass(ApiCount);
+        assert!(matches!(analyzer.analyze(), Err(Error::InvalidPlugin(_))));
+    }
+}
@@ -0,0 +1,589 @@
+//! Compliance policies evaluated against the manifests of a binary, e.g. the rules of a
+//! certification lab.
+//!
+//! A policy is a TOML or JSON file listing `rules`, each with an `id`, a `description`, a
+//! `severity` (`info`, `low`, `medium`, `high` or `critical`) and the `check` it performs:
+//!
+//! ```toml
+//! [[rules]]
+//! id = "no-exec-from-network"
+//! description = "No process spawned from network-facing APIs"
+//! severity = "critical"
+//! check = "forbidden-calls"
+//! calls = ["execve", "execl", "execvp", "system", "popen"]
+//! capabilities = ["network-client", "network-server", "Network Access"]
+//!
+//! [[rules]]
+//! id = "tls-1.2"
+//! description = "TLS 1.2 or later"
+//! severity = "high"
+//! check = "library-version"
+//! library = "openssl"
+//! min_version = "1.0.1"
+//! ```
+//!
+//! The checks are:
+//!
+//! - `forbidden-calls`: none of the `calls` is reachable from the APIs, or only from those with
+//!   one of the `capabilities` or among the `apis` when given;
+//! - `library-version`: the `library`, when linked, is of `min_version` or later, and of a known
+//!   version;
+//! - `weak-crypto`: no weak cryptographic algorithm is used, but those in `allow`;
+//! - `dangerous-calls`: no banned function is called, or none of the `functions` when given;
+//! - `hardening`: the hardening checks in `require` pass, e.g. `PIE` or `RELRO`.
+
+use std::{collections::BTreeMap, fmt, fs, str::FromStr};
+
+use serde_json::{json, Value};
+
+use crate::{
+    error::{Error, Result},
+    libraries::compare_versions,
+    manifest_creation::find_manifest,
+};
+
+// The manifests the rules are evaluated against.
+const MANIFESTS: [&str; 3] = ["basic_info", "flow_call", "feature_manifest"];
+
+/// How serious the failure of a rule is.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Reported, without consequence.
+    Info,
+    /// A minor deviation.
+    Low,
+    /// A deviation to fix.
+    #[default]
+    Medium,
+    /// A deviation blocking the certification.
+    High,
+    /// A deviation to fix at once.
+    Critical,
+}
+
+impl Severity {
+    /// The name of the severity, e.g. `high`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+            Severity::Critical => "critical",
+        }
+    }
+}
+
+impl FromStr for Severity {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name {
+            "info" => Ok(Severity::Info),
+            "low" => Ok(Severity::Low),
+            "medium" => Ok(Severity::Medium),
+            "high" => Ok(Severity::High),
+            "critical" => Ok(Severity::Critical),
+            _ => Err(Error::InvalidPolicy(format!(
+                "unknown severity {}, expected info, low, medium, high or critical",
+                name
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// What a rule checks in the manifests.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Check {
+    /// None of the `calls` is reachable from the APIs selected, all of them when `capabilities`
+    /// and `apis` are empty.
+    ForbiddenCalls {
+        /// The functions that must not be reached, e.g. `execve`.
+        calls: Vec<String>,
+        /// The capabilities or feature categories of the APIs checked, e.g. `network-client`.
+        capabilities: Vec<String>,
+        /// The names of the APIs checked.
+        apis: Vec<String>,
+    },
+    /// The library, when linked, is of a known version, `min_version` or later.
+    LibraryVersion {
+        /// The name of the library, e.g. `openssl`.
+        library: String,
+        /// The oldest version allowed.
+        min_version: String,
+    },
+    /// No weak cryptographic algorithm is used, but those allowed.
+    WeakCrypto {
+        /// The weak algorithms allowed, e.g. `MD5`.
+        allow: Vec<String>,
+    },
+    /// No banned function is called, or none of `functions` when not empty.
+    DangerousCalls {
+        /// The banned functions checked.
+        functions: Vec<String>,
+    },
+    /// The hardening checks pass.
+    Hardening {
+        /// The hardening checks required, e.g. `PIE`.
+        require: Vec<String>,
+    },
+}
+
+/// A rule of a policy.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rule {
+    /// The identifier of the rule, e.g. `no-exec-from-network`.
+    pub id: String,
+    /// What the rule requires, for the reports.
+    pub description: String,
+    /// How serious a failure of the rule is.
+    pub severity: Severity,
+    /// What the rule checks.
+    pub check: Check,
+}
+
+/// The result of a rule on a set of manifests.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RuleResult {
+    /// The identifier of the rule.
+    pub id: String,
+    /// What the rule requires.
+    pub description: String,
+    /// How serious a failure of the rule is.
+    pub severity: Severity,
+    /// What breaks the rule, empty when it passes.
+    pub violations: Vec<String>,
+}
+
+impl RuleResult {
+    /// Whether the rule passes.
+    pub fn passed(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// The results of the rules of a policy.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PolicyReport {
+    /// The result of each rule, in the order of the policy.
+    pub results: Vec<RuleResult>,
+}
+
+impl PolicyReport {
+    /// Whether every rule of `severity` or more serious passes.
+    pub fn passed(&self, severity: Severity) -> bool {
+        self.failed().all(|result| result.severity < severity)
+    }
+
+    /// The results of the rules that fail.
+    pub fn failed(&self) -> impl Iterator<Item = &RuleResult> {
+        self.results.iter().filter(|result| !result.passed())
+    }
+
+    /// The report as JSON.
+    pub fn to_json(&self) -> Value {
+        let results: Vec<Value> = self
+            .results
+            .iter()
+            .map(|result| {
+                json!({
+                    "id": result.id,
+                    "description": result.description,
+                    "severity": result.severity.as_str(),
+                    "result": if result.passed() { "pass" } else { "fail" },
+                    "violations": result.violations,
+                })
+            })
+            .collect();
+        json!({
+            "passed": self.passed(Severity::Info),
+            "rules": results,
+        })
+    }
+}
+
+impl fmt::Display for PolicyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for result in &self.results {
+            let verdict = if result.passed() { "PASS" } else { "FAIL" };
+            writeln!(
+                f,
+                "{} [{}] {}: {}",
+                verdict, result.severity, result.id, result.description
+            )?;
+            for violation in &result.violations {
+                writeln!(f, "  - {}", violation)?;
+            }
+        }
+        let failed = self.failed().count();
+        write!(
+            f,
+            "{} of {} rules passed",
+            self.results.len() - failed,
+            self.results.len()
+        )
+    }
+}
+
+/// A policy: rules evaluated against the manifests of a binary.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Policy {
+    /// The rules, evaluated in order.
+    pub rules: Vec<Rule>,
+}
+
+impl Policy {
+    /// Load a policy from a TOML or JSON file, told by its extension, see [`crate::policy`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidPolicy`] if the file does not follow the layout of a policy.
+    pub fn load(path: &str) -> Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let json: Value = if path.ends_with(".toml") {
+            toml::from_str(&contents)
+                .map_err(|error| Error::InvalidPolicy(format!("{}: {}", path, error)))?
+        } else {
+            serde_json::from_str(&contents)?
+        };
+        Self::from_json(&json)
+    }
+
+    /// Read a policy from a JSON document, see [`Policy::load`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidPolicy`] if the document does not follow the layout of a policy.
+    pub fn from_json(json: &Value) -> Result<Self> {
+        let entries = json
+            .get("rules")
+            .and_then(Value::as_array)
+            .ok_or_else(|| Error::InvalidPolicy("expected an array of rules".to_string()))?;
+        let mut rules: Vec<Rule> = Vec::new();
+        for entry in entries {
+            let rule = rule_from_json(entry)?;
+            if rules.iter().any(|other| other.id == rule.id) {
+                return Err(Error::InvalidPolicy(format!(
+                    "two rules are named {}",
+                    rule.id
+                )));
+            }
+            rules.push(rule);
+        }
+        Ok(Self { rules })
+    }
+
+    /// Evaluate the rules against the manifests of a binary, by name, as returned by
+    /// [`crate::analyzer::Analyzer::manifests`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidManifest`] if the basic information, flow call or feature
+    /// manifest is missing.
+    pub fn evaluate(&self, manifests: &BTreeMap<String, Value>) -> Result<PolicyReport> {
+        for name in MANIFESTS {
+            if !manifests.contains_key(name) {
+                return Err(Error::InvalidManifest(format!("no {} manifest", name)));
+            }
+        }
+        let results = self
+            .rules
+            .iter()
+            .map(|rule| RuleResult {
+                id: rule.id.clone(),
+                description: rule.description.clone(),
+                severity: rule.severity,
+                violations: violations(&rule.check, manifests),
+            })
+            .collect();
+        Ok(PolicyReport { results })
+    }
+
+    /// Evaluate the rules against the manifests of an output directory, in any format.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidManifest`] if the directory lacks a manifest.
+    pub fn evaluate_dir(&self, dir: &str) -> Result<PolicyReport> {
+        let mut manifests = BTreeMap::new();
+        for name in MANIFESTS {
+            manifests.insert(name.to_string(), find_manifest(dir, name)?);
+        }
+        self.evaluate(&manifests)
+    }
+}
+
+// Read a rule of a policy.
+fn rule_from_json(entry: &Value) -> Result<Rule> {
+    let invalid = |reason: String| Error::InvalidPolicy(reason);
+    let id = entry
+        .get("id")
+        .and_then(Value::as_str)
+        .ok_or_else(|| invalid(format!("missing id in {}", entry)))?;
+    let string = |field: &str| -> Result<String> {
+        entry
+            .get(field)
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .ok_or_else(|| invalid(format!("{} of {} must be a string", field, id)))
+    };
+    let strings = |field: &str| -> Result<Vec<String>> {
+        match entry.get(field) {
+            None => Ok(Vec::new()),
+            Some(Value::Array(values)) => values
+                .iter()
+                .map(|value| value.as_str().map(str::to_string))
+                .collect::<Option<_>>()
+                .ok_or_else(|| invalid(format!("{} of {} must be an array of strings", field, id))),
+            Some(_) => Err(invalid(format!(
+                "{} of {} must be an array of strings",
+                field, id
+            ))),
+        }
+    };
+    let check = match string("check")?.as_str() {
+        "forbidden-calls" => Check::ForbiddenCalls {
+            calls: strings("calls")?,
+            capabilities: strings("capabilities")?,
+            apis: strings("apis")?,
+        },
+        "library-version" => Check::LibraryVersion {
+            library: string("library")?,
+            min_version: string("min_version")?,
+        },
+        "weak-crypto" => Check::WeakCrypto {
+            allow: strings("allow")?,
+        },
+        "dangerous-calls" => Check::DangerousCalls {
+            functions: strings("functions")?,
+        },
+        "hardening" => Check::Hardening {
+            require: strings("require")?,
+        },
+        check => return Err(invalid(format!("unknown check {} of {}", check, id))),
+    };
+    Ok(Rule {
+        id: id.to_string(),
+        description: match entry.get("description") {
+            Some(_) => string("description")?,
+            None => id.to_string(),
+        },
+        severity: match entry.get("severity") {
+            Some(_) => string("severity")?.parse()?,
+            None => Severity::default(),
+        },
+        check,
+    })
+}
+
+// What breaks a check in the manifests.
+fn violations(check: &Check, manifests: &BTreeMap<String, Value>) -> Vec<String> {
+    let (basic_info, flow_call, feature) = (
+        &manifests["basic_info"],
+        &manifests["flow_call"],
+        &manifests["feature_manifest"],
+    );
+    let names = |value: &Value| -> Vec<String> {
+        value
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|name| name.as_str().map(str::to_string))
+            .collect()
+    };
+    match check {
+        Check::ForbiddenCalls {
+            calls,
+            capabilities,
+            apis,
+        } => {
+            let mut violations = Vec::new();
+            for api in flow_call["Public APIs flow"]
+                .as_array()
+                .into_iter()
+                .flatten()
+            {
+                let name = api["name"].as_str().unwrap_or_default();
+                let labels: Vec<String> = feature["capabilities"][name]
+                    .as_object()
+                    .into_iter()
+                    .flat_map(|labels| labels.keys().cloned())
+                    .chain(names(&feature[name]))
+                    .collect();
+                let selected = (capabilities.is_empty() && apis.is_empty())
+                    || apis.iter().any(|api| api == name)
+                    || labels.iter().any(|label| {
+                        capabilities
+                            .iter()
+                            .any(|capability| capability.eq_ignore_ascii_case(label))
+                    });
+                if !selected {
+                    continue;
+                }
+                let reached: Vec<String> = names(&api["syscalls"])
+                    .into_iter()
+                    .chain(names(&api["transitive calls"]))
+                    .collect();
+                for call in calls {
+                    if reached
+                        .iter()
+                        .any(|reached| reached.split('@').next() == Some(call))
+                    {
+                        violations.push(format!("{} is reachable from {}", call, name));
+                    }
+                }
+            }
+            violations
+        }
+        Check::LibraryVersion {
+            library,
+            min_version,
+        } => feature["libraries"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|linked| linked["name"].as_str() == Some(library))
+            .filter_map(|linked| match linked["version"].as_str() {
+                None => Some(format!("the version of {} is unknown", library)),
+                Some(version) if compare_versions(version, min_version).is_lt() => Some(format!(
+                    "{} {} is older than {}",
+                    library, version, min_version
+                )),
+                Some(_) => None,
+            })
+            .collect(),
+        Check::WeakCrypto { allow } => feature["crypto"]["algorithms"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|algorithm| algorithm["weak"] == true)
+            .filter_map(|algorithm| algorithm["algorithm"].as_str())
+            .filter(|algorithm| !allow.iter().any(|allowed| allowed == algorithm))
+            .map(|algorithm| format!("the weak algorithm {} is used", algorithm))
+            .collect(),
+        Check::DangerousCalls { functions } => feature["dangerous calls"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|call| {
+                let function = call["function"].as_str()?;
+                (functions.is_empty() || functions.iter().any(|f| f == function)).then(|| {
+                    format!(
+                        "{} is called by {} at {}",
+                        function,
+                        call["caller"].as_str().unwrap_or("?"),
+                        call["call site"].as_str().unwrap_or("?")
+                    )
+                })
+            })
+            .collect(),
+        Check::Hardening { require } => require
+            .iter()
+            .filter_map(|name| match basic_info["hardening"].get(name) {
+                Some(check) if check["verdict"] == "pass" => None,
+                Some(check) => Some(format!(
+                    "{} fails: {}",
+                    name,
+                    check["detail"].as_str().unwrap_or_default()
+                )),
+                None => Some(format!("{} is not checked", name)),
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{analyzer::Analyzer, api_detection::ApiSpec};
+
+    #[test]
+    fn test_evaluate() {
+        let policy: Value = toml::from_str(
+            r#"
+            [[rules]]
+            id = "no-exec-from-files"
+            severity = "critical"
+            check = "forbidden-calls"
+            calls = ["execve", "system"]
+            capabilities = ["filesystem-write"]
+
+            [[rules]]
+            id = "no-fopen-from-files"
+            severity = "high"
+            check = "forbidden-calls"
+            calls = ["fopen64"]
+            capabilities = ["filesystem-write"]
+
+            [[rules]]
+            id = "tls-1.2"
+            description = "TLS 1.2 or later"
+            check = "library-version"
+            library = "openssl"
+            min_version = "1.0.1"
+
+            [[rules]]
+            id = "no-weak-crypto"
+            severity = "low"
+            check = "weak-crypto"
+            allow = ["MD5"]
+
+            [[rules]]
+            id = "pie"
+            severity = "info"
+            check = "hardening"
+            require = ["PIE", "NX", "FORTIFY_SOURCE"]
+            "#,
+        )
+        .unwrap();
+        let policy = Policy::from_json(&policy).unwrap();
+        let analyzer = Analyzer::new("./tests/elf_file/fake-firmware-c-dynamic")
+            .with_api_list(vec![ApiSpec::new("writeOnDrive")]);
+        let analysis = analyzer.analyze().unwrap();
+        let report = policy
+            .evaluate(&analyzer.manifests(&analysis).unwrap())
+            .unwrap();
+
+        let result = |id: &str| report.results.iter().find(|r| r.id == id).unwrap();
+        assert!(result("no-exec-from-files").passed());
+        assert_eq!(
+            result("no-fopen-from-files").violations,
+            ["fopen64 is reachable from writeOnDrive"]
+        );
+        assert!(result("tls-1.2").passed());
+        assert_eq!(result("tls-1.2").severity, Severity::Medium);
+        let weak = &result("no-weak-crypto").violations;
+        assert!(weak.iter().any(|violation| violation.contains("DES")));
+        assert!(!weak.iter().any(|violation| violation.contains("MD5")));
+        assert_eq!(result("pie").violations.len(), 1);
+
+        assert!(!report.passed(Severity::High));
+        assert!(report.passed(Severity::Critical));
+        let json = report.to_json();
+        assert_eq!(json["passed"], false);
+        assert_eq!(json["rules"][0]["result"], "pass");
+        assert!(report.to_string().ends_with("2 of 5 rules passed"));
+    }
+
+    #[test]
+    fn test_invalid_policy() {
+        let policy = |source: &str| Policy::from_json(&toml::from_str(source).unwrap());
+        assert!(policy("rules = [{ id = \"a\", check = \"magic\" }]").is_err());
+        assert!(
+            policy("rules = [{ id = \"a\", check = \"weak-crypto\", severity = \"dire\" }]")
+                .is_err()
+        );
+        assert!(policy("rules = [{ id = \"a\", check = \"library-version\" }]").is_err());
+        assert!(policy(
+            "rules = [{ id = \"a\", check = \"weak-crypto\" }, { id = \"a\", check = \"weak-crypto\" }]"
+        )
+        .is_err());
+        assert!(policy("rules = [{ id = \"a\", check = \"weak-crypto\" }]").is_ok());
+    }
+}
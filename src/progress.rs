@@ -0,0 +1,230 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use web_time::Instant;
+
+use indicatif::{ProgressBar, ProgressStyle};
+
+/// How much an analysis reports on stderr while it runs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Errors only.
+    Quiet,
+    /// Progress bars and the outcome of the analysis.
+    #[default]
+    Normal,
+    /// Also the time spent in each phase, at the end of the analysis.
+    Verbose,
+    /// Also each phase when it ends, and the function being disassembled.
+    Debug,
+}
+
+impl Verbosity {
+    /// Return the verbosity of the `-q` flag and the number of `-v` flags.
+    pub fn from_flags(quiet: bool, verbose: u8) -> Self {
+        match (quiet, verbose) {
+            (true, _) => Verbosity::Quiet,
+            (false, 0) => Verbosity::Normal,
+            (false, 1) => Verbosity::Verbose,
+            (false, _) => Verbosity::Debug,
+        }
+    }
+}
+
+/// A step of an analysis, reported to a [`ProgressListener`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProgressEvent {
+    /// A phase started, e.g. `disassembly`.
+    PhaseStarted(&'static str),
+    /// A phase ended, after the time given.
+    PhaseFinished(&'static str, Duration),
+    /// An item of the current phase was counted, e.g. a function disassembled.
+    Item {
+        /// The number of items counted so far in the phase.
+        done: usize,
+        /// The number of items of the phase.
+        total: usize,
+    },
+}
+
+/// A function called with each step of an analysis, from any thread, e.g. to stream the
+/// progress to a client.
+pub type ProgressListener = Arc<dyn Fn(&ProgressEvent) + Send + Sync>;
+
+/// The progress of the phases of an analysis, drawn as progress bars on stderr.
+///
+/// The bars are hidden when stderr is not a terminal, or when quiet.
+pub struct Progress {
+    verbosity: Verbosity,
+    bar: ProgressBar,
+    current: Option<(&'static str, Instant)>,
+    phases: Vec<(&'static str, Duration)>,
+    listener: Option<ProgressListener>,
+    done: AtomicUsize,
+}
+
+impl Progress {
+    /// Start reporting the progress of an analysis.
+    pub fn new(verbosity: Verbosity) -> Self {
+        Self {
+            verbosity,
+            bar: ProgressBar::hidden(),
+            current: None,
+            phases: Vec::new(),
+            listener: None,
+            done: AtomicUsize::new(0),
+        }
+    }
+
+    /// Also report each step of the analysis to `listener`, when given.
+    pub fn with_listener(mut self, listener: Option<ProgressListener>) -> Self {
+        self.listener = listener;
+        self
+    }
+
+    /// The verbosity of the analysis.
+    pub fn verbosity(&self) -> Verbosity {
+        self.verbosity
+    }
+
+    /// End the current phase and start a new one, e.g. `disassembly`.
+    pub fn phase(&mut self, name: &'static str) {
+        self.end_phase();
+        if self.verbosity > Verbosity::Quiet {
+            self.bar = ProgressBar::new_spinner()
+                .with_style(
+                    ProgressStyle::with_template("{spinner} {prefix} {msg}")
+                        .unwrap_or_else(|_| ProgressStyle::default_spinner()),
+                )
+                .with_prefix(name);
+            self.bar.enable_steady_tick(Duration::from_millis(100));
+        }
+        self.current = Some((name, Instant::now()));
+        self.done.store(0, Ordering::Relaxed);
+        self.notify(ProgressEvent::PhaseStarted(name));
+    }
+
+    /// Count an item of the current phase out of `total`, e.g. a function disassembled, making
+    /// the spinner a progress bar.
+    pub fn item(&self, name: &str, total: usize) {
+        if self.bar.length() != Some(total as u64) {
+            self.bar.set_length(total as u64);
+            self.bar.set_style(
+                ProgressStyle::with_template("{prefix} [{bar:40}] {pos}/{len} {msg}")
+                    .unwrap_or_else(|_| ProgressStyle::default_bar())
+                    .progress_chars("=> "),
+            );
+        }
+        self.bar.inc(1);
+        if self.verbosity >= Verbosity::Debug {
+            self.bar.set_message(name.to_string());
+        }
+        if self.listener.is_some() {
+            let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+            self.notify(ProgressEvent::Item { done, total });
+        }
+    }
+
+    /// Print a message on stderr above the progress bars, unless quiet.
+    pub fn message(&self, message: &str) {
+        if self.verbosity > Verbosity::Quiet {
+            self.bar.suspend(|| eprintln!("{}", message));
+        }
+    }
+
+    /// End the last phase, and print the time spent in each phase when verbose.
+    ///
+    /// # Returns
+    ///
+    /// Returns the phases with the time spent in each, in order.
+    pub fn finish(&mut self) -> Vec<(&'static str, Duration)> {
+        self.end_phase();
+        if self.verbosity >= Verbosity::Verbose {
+            eprint!("{}", self.summary());
+        }
+        self.phases.clone()
+    }
+
+    /// Return the time spent in each phase ended, and in total, one phase per line.
+    pub fn summary(&self) -> String {
+        let total: Duration = self.phases.iter().map(|(_, time)| *time).sum();
+        self.phases
+            .iter()
+            .chain([&("total", total)])
+            .map(|(name, time)| format!("{:>16} {:>9.3}s\n", name, time.as_secs_f64()))
+            .collect()
+    }
+
+    // Record the time spent in the current phase.
+    fn end_phase(&mut self) {
+        let Some((name, started)) = self.current.take() else {
+            return;
+        };
+        let elapsed = started.elapsed();
+        self.bar.finish_and_clear();
+        self.phases.push((name, elapsed));
+        if self.verbosity >= Verbosity::Debug {
+            eprintln!("{} done in {:.3}s", name, elapsed.as_secs_f64());
+        }
+        self.notify(ProgressEvent::PhaseFinished(name, elapsed));
+    }
+
+    // Report a step to the listener, if any.
+    fn notify(&self, event: ProgressEvent) {
+        if let Some(listener) = &self.listener {
+            listener(&event);
+        }
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        self.bar.finish_and_clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress() {
+        assert_eq!(Verbosity::from_flags(true, 2), Verbosity::Quiet);
+        assert_eq!(Verbosity::from_flags(false, 0), Verbosity::Normal);
+        assert_eq!(Verbosity::from_flags(false, 3), Verbosity::Debug);
+
+        let mut progress = Progress::new(Verbosity::Quiet);
+        progress.phase("parsing");
+        progress.phase("disassembly");
+        for name in ["main", "writeOnDrive"] {
+            progress.item(name, 2);
+        }
+        let phases = progress.finish();
+        let names: Vec<&str> = phases.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, ["parsing", "disassembly"]);
+        assert_eq!(progress.summary().lines().count(), 3);
+        assert!(progress.summary().contains("total"));
+
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = events.clone();
+        let listener: ProgressListener =
+            Arc::new(move |event: &ProgressEvent| recorded.lock().unwrap().push(event.clone()));
+        let mut progress = Progress::new(Verbosity::Quiet).with_listener(Some(listener));
+        progress.phase("disassembly");
+        progress.item("main", 1);
+        progress.finish();
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0], ProgressEvent::PhaseStarted("disassembly"));
+        assert_eq!(events[1], ProgressEvent::Item { done: 1, total: 1 });
+        assert!(matches!(
+            events[2],
+            ProgressEvent::PhaseFinished("disassembly", _)
+        ));
+    }
+}
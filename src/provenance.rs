@@ -0,0 +1,478 @@
+use std::collections::BTreeMap;
+
+use goblin::elf::{sym::STT_FUNC, Elf};
+
+use crate::cleanup::demangle_api_name;
+
+/// The origin of the functions attributed to no library: the code of the application itself.
+pub const APPLICATION: &str = "application";
+
+// Prefixes of the functions of the libraries commonly linked statically, as (prefix, library).
+// The longest matching prefix wins, so the order does not matter.
+const PREFIXES: [(&str, &str); 95] = [
+    ("SSL_", "openssl"),
+    ("ssl_", "openssl"),
+    ("ssl3_", "openssl"),
+    ("tls1_", "openssl"),
+    ("tls_", "openssl"),
+    ("dtls1_", "openssl"),
+    ("BIO_", "openssl"),
+    ("EVP_", "openssl"),
+    ("evp_", "openssl"),
+    ("CRYPTO_", "openssl"),
+    ("OPENSSL_", "openssl"),
+    ("ossl_", "openssl"),
+    ("ERR_", "openssl"),
+    ("X509", "openssl"),
+    ("x509_", "openssl"),
+    ("PEM_", "openssl"),
+    ("RSA_", "openssl"),
+    ("EC_", "openssl"),
+    ("BN_", "openssl"),
+    ("bn_", "openssl"),
+    ("ASN1_", "openssl"),
+    ("asn1_", "openssl"),
+    ("RAND_", "openssl"),
+    ("HMAC", "openssl"),
+    ("PKCS", "openssl"),
+    ("OBJ_", "openssl"),
+    ("mbedtls_", "mbedtls"),
+    ("wolfSSL_", "wolfssl"),
+    ("wc_", "wolfssl"),
+    ("inflate", "zlib"),
+    ("deflate", "zlib"),
+    ("zlibVersion", "zlib"),
+    ("crc32", "zlib"),
+    ("adler32", "zlib"),
+    ("zError", "zlib"),
+    ("gzopen", "zlib"),
+    ("gzread", "zlib"),
+    ("gzwrite", "zlib"),
+    ("gzclose", "zlib"),
+    ("ZSTD_", "zstd"),
+    ("Brotli", "brotli"),
+    ("curl_", "curl"),
+    ("Curl_", "curl"),
+    ("sqlite3", "sqlite"),
+    ("png_", "libpng"),
+    ("jpeg_", "libjpeg"),
+    ("snd_", "alsa-lib"),
+    ("av_", "ffmpeg"),
+    ("avcodec_", "ffmpeg"),
+    ("avformat_", "ffmpeg"),
+    ("swr_", "ffmpeg"),
+    ("sws_", "ffmpeg"),
+    ("SDL_", "SDL"),
+    ("lua_", "lua"),
+    ("luaL_", "lua"),
+    ("XML_", "expat"),
+    ("cJSON_", "cJSON"),
+    ("__libc_", "libc"),
+    ("_IO_", "libc"),
+    ("_dl_", "libc"),
+    ("__GI_", "libc"),
+    ("__isoc99_", "libc"),
+    ("pthread_", "libc"),
+    ("__stack_chk_", "libc"),
+    ("__stdio_", "libc"),
+    ("__syscall", "libc"),
+    ("dl_", "libc"),
+    ("do_dl", "libc"),
+    ("dlinfo", "libc"),
+    ("dlmopen", "libc"),
+    ("malloc_", "libc"),
+    ("mallinfo", "libc"),
+    ("sysmalloc", "libc"),
+    ("tcache_", "libc"),
+    ("arena_", "libc"),
+    ("ptmalloc_", "libc"),
+    ("register_printf_", "libc"),
+    ("sched_", "libc"),
+    ("strto", "libc"),
+    ("wcs", "libc"),
+    ("wmem", "libc"),
+    ("towc", "libc"),
+    ("frame_dummy", "libc"),
+    ("register_tm_clones", "libc"),
+    ("deregister_tm_clones", "libc"),
+    ("_Unwind_", "libgcc"),
+    ("__register_frame", "libgcc"),
+    ("__deregister_frame", "libgcc"),
+    ("__gcc_", "libgcc"),
+    ("uw_", "libgcc"),
+    ("fde_", "libgcc"),
+    ("execute_cfa_program", "libgcc"),
+    ("__rust", "rust-std"),
+    ("__rdl_", "rust-std"),
+    ("__rg_", "rust-std"),
+];
+
+// Namespaces of the standard libraries, as (prefix of the demangled name, library).
+const NAMESPACES: [(&str, &str); 6] = [
+    ("core::", "rust-std"),
+    ("alloc::", "rust-std"),
+    ("__gnu_cxx::", "libstdc++"),
+    ("__cxxabiv1::", "libstdc++"),
+    ("__cxa_", "libstdc++"),
+    ("operator new", "libstdc++"),
+];
+
+// Prefixes of the names of the functions the compiler generates for the code of the
+// application, e.g. the static initializers of C++, which start with an underscore although
+// they are no part of the C library.
+const COMPILER_GENERATED: [&str; 7] = [
+    "__static_initialization_and_destruction_",
+    "_GLOBAL__sub_I_",
+    "_GLOBAL__sub_D_",
+    "_GLOBAL__I_",
+    "_GLOBAL__D_",
+    "__cxx_global_var_init",
+    "__cxx_global_array_dtor",
+];
+
+// Prefixes of the names left mangled, by C++ and by the v0 mangling of Rust, which tell nothing
+// of the library of the function.
+const MANGLED: [&str; 2] = ["_Z", "_R"];
+
+// Functions of the C library, from the C and POSIX standards and the glibc and musl extensions.
+const LIBC_FUNCTIONS: [&str; 166] = [
+    "abort",
+    "accept",
+    "aligned_alloc",
+    "asprintf",
+    "atexit",
+    "bind",
+    "brk",
+    "btowc",
+    "bzero",
+    "calloc",
+    "chdir",
+    "chmod",
+    "clearenv",
+    "clock_gettime",
+    "close",
+    "closedir",
+    "connect",
+    "dladdr",
+    "dlclose",
+    "dlerror",
+    "dlopen",
+    "dlsym",
+    "dlvsym",
+    "dup2",
+    "execve",
+    "execvp",
+    "exit",
+    "fclose",
+    "fcntl",
+    "fdopendir",
+    "fflush",
+    "fgets",
+    "fopen",
+    "fork",
+    "fprintf",
+    "fputs",
+    "fread",
+    "free",
+    "fseek",
+    "fstat",
+    "ftell",
+    "fwrite",
+    "get_nprocs",
+    "getaddrinfo",
+    "getcwd",
+    "getdelim",
+    "getegid",
+    "getenv",
+    "geteuid",
+    "getgid",
+    "gethostbyname",
+    "getpagesize",
+    "getpid",
+    "getrandom",
+    "getrlimit",
+    "gettimeofday",
+    "getuid",
+    "gsignal",
+    "ioctl",
+    "isatty",
+    "kill",
+    "listen",
+    "lseek",
+    "lstat",
+    "madvise",
+    "mallinfo",
+    "malloc",
+    "malloc_trim",
+    "malloc_usable_size",
+    "mallopt",
+    "mbsrtowcs",
+    "memalign",
+    "memchr",
+    "memcmp",
+    "memcpy",
+    "memmem",
+    "memmove",
+    "memset",
+    "mkdir",
+    "mmap",
+    "mprotect",
+    "mremap",
+    "munmap",
+    "open",
+    "openat",
+    "opendir",
+    "perror",
+    "pipe",
+    "poll",
+    "popen",
+    "posix_memalign",
+    "printf",
+    "puts",
+    "pvalloc",
+    "qsort",
+    "qsort_r",
+    "raise",
+    "read",
+    "readdir",
+    "realloc",
+    "recv",
+    "recvfrom",
+    "rename",
+    "rewinddir",
+    "rmdir",
+    "sbrk",
+    "sched_yield",
+    "secure_getenv",
+    "select",
+    "send",
+    "sendto",
+    "setenv",
+    "setitimer",
+    "setlocale",
+    "setsockopt",
+    "sigaction",
+    "signal",
+    "sigprocmask",
+    "sleep",
+    "snprintf",
+    "socket",
+    "sprintf",
+    "sscanf",
+    "stat",
+    "strcat",
+    "strchr",
+    "strcmp",
+    "strcpy",
+    "strcspn",
+    "strdup",
+    "strerror",
+    "strlen",
+    "strncmp",
+    "strncpy",
+    "strndup",
+    "strrchr",
+    "strsep",
+    "strspn",
+    "strstr",
+    "strtok",
+    "strtol",
+    "strtoll",
+    "strtoul",
+    "strtoull",
+    "sysconf",
+    "sysinfo",
+    "system",
+    "tcgetattr",
+    "tdelete",
+    "tfind",
+    "time",
+    "tsearch",
+    "twalk",
+    "uname",
+    "unlink",
+    "unsetenv",
+    "usleep",
+    "valloc",
+    "vasprintf",
+    "vfprintf",
+    "vsnprintf",
+    "wcrtomb",
+    "wcsrtombs",
+    "wmemcpy",
+    "wmemmove",
+    "write",
+];
+
+// Suffixes of the variants of the libc functions: large file, locale, reentrant, unlocked and
+// ifunc resolver variants.
+const LIBC_SUFFIXES: [&str; 5] = ["64", "_l", "_r", "_unlocked", "_ifunc"];
+
+/// Attribute a function to the library it most likely comes from, from the prefix of its name
+/// or from the functions known to belong to the C library.
+///
+/// # Arguments
+///
+/// * `name` - The demangled name of the function.
+/// * `lang` - The programming language of the binary, telling the Rust `std::` from the C++ one.
+///
+/// # Returns
+///
+/// Returns the name of the library, e.g. `openssl`, or [`APPLICATION`] when the function is
+/// attributed to none.
+pub fn library_of(name: &str, lang: &str) -> &'static str {
+    // The clones made by the compiler, e.g. `uw_frame_state_for.cold`, belong with the function.
+    let name = name.split_once('.').map_or(name, |(function, _)| function);
+    // A method of a trait implementation, e.g. `<alloc::string::String as core::fmt::Write>`, goes
+    // with its type.
+    let name = name.trim_start_matches('<');
+    if name.starts_with("std::") {
+        return if lang.contains("Rust") {
+            "rust-std"
+        } else {
+            "libstdc++"
+        };
+    }
+    if let Some((_, library)) = NAMESPACES
+        .iter()
+        .find(|(prefix, _)| name.starts_with(prefix))
+    {
+        return library;
+    }
+    if let Some((_, library)) = PREFIXES
+        .iter()
+        .filter(|(prefix, _)| name.starts_with(prefix))
+        .max_by_key(|(prefix, _)| prefix.len())
+    {
+        return library;
+    }
+    // The identifiers starting with an underscore are reserved to the implementation at file
+    // scope, e.g. `__memcpy_avx_unaligned`, but for those of the compiler and the mangled
+    // names; the others are the libc functions and their variants, e.g. `strtol_l`.
+    let reserved = name.starts_with('_')
+        && !COMPILER_GENERATED
+            .iter()
+            .chain(&MANGLED)
+            .any(|prefix| name.starts_with(prefix));
+    let libc = reserved
+        || LIBC_FUNCTIONS.iter().any(|function| {
+            name.strip_prefix(function)
+                .is_some_and(|rest| rest.is_empty() || LIBC_SUFFIXES.contains(&rest))
+        });
+    if libc {
+        "libc"
+    } else {
+        APPLICATION
+    }
+}
+
+/// Group function names by the library they are attributed to, see [`library_of`].
+///
+/// # Returns
+///
+/// Returns the names of each library, in their original order.
+pub fn group_by_library(names: &[String], lang: &str) -> BTreeMap<&'static str, Vec<String>> {
+    let mut groups: BTreeMap<&'static str, Vec<String>> = BTreeMap::new();
+    for name in names {
+        groups
+            .entry(library_of(name, lang))
+            .or_default()
+            .push(name.clone());
+    }
+    groups
+}
+
+/// Count the functions defined in the symbol table of the binary by library of origin, telling
+/// the application code of a static binary from the libraries linked into it.
+///
+/// # Arguments
+///
+/// * `elf` - The parsed ELF structure.
+/// * `lang` - The programming language of the binary.
+///
+/// # Returns
+///
+/// Returns the number of functions of each library, empty for a stripped binary.
+pub fn function_origins(elf: &Elf, lang: &str) -> BTreeMap<&'static str, usize> {
+    let mut origins = BTreeMap::new();
+    for sym in elf.syms.iter() {
+        if sym.st_type() != STT_FUNC || sym.st_shndx == 0 || sym.st_value == 0 {
+            continue;
+        }
+        let Some(name) = elf.strtab.get_at(sym.st_name) else {
+            continue;
+        };
+        *origins
+            .entry(library_of(&demangle_api_name(name), lang))
+            .or_default() += 1;
+    }
+    origins
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf_utils::read_elf_file;
+
+    #[test]
+    fn test_library_of() {
+        assert_eq!(library_of("SSL_CTX_new", "C99"), "openssl");
+        assert_eq!(library_of("ssl3_read_bytes", "C99"), "openssl");
+        assert_eq!(library_of("inflateInit_", "C99"), "zlib");
+        assert_eq!(library_of("curl_easy_perform", "C99"), "curl");
+        assert_eq!(library_of("Curl_http_done", "C99"), "curl");
+        assert_eq!(library_of("fopen", "C99"), "libc");
+        assert_eq!(library_of("__libc_start_main", "C99"), "libc");
+        assert_eq!(library_of("__memcpy_avx_unaligned", "C99"), "libc");
+        assert_eq!(library_of("_IO_file_write", "C99"), "libc");
+        assert_eq!(library_of("std::fs::File::create", "Rust"), "rust-std");
+        assert_eq!(
+            library_of("std::ostream::flush", "C_plus_plus_14"),
+            "libstdc++"
+        );
+        assert_eq!(library_of("core::fmt::write", "Rust"), "rust-std");
+        assert_eq!(
+            library_of(
+                "<alloc::string::String as core::fmt::Write>::write_str",
+                "Rust"
+            ),
+            "rust-std"
+        );
+        assert_eq!(library_of("uw_frame_state_for.cold", "C99"), "libgcc");
+        assert_eq!(library_of("strtol_l", "C99"), "libc");
+        assert_eq!(library_of("writeOnDrive", "C99"), APPLICATION);
+        assert_eq!(library_of("memory_pool_init", "C99"), APPLICATION);
+        assert_eq!(library_of("openFile", "C99"), APPLICATION);
+        assert_eq!(
+            library_of(
+                "__static_initialization_and_destruction_0(int, int)",
+                "C_plus_plus_14"
+            ),
+            APPLICATION
+        );
+        assert_eq!(
+            library_of("_GLOBAL__sub_I_alc.cpp", "C_plus_plus_14"),
+            APPLICATION
+        );
+        assert_eq!(
+            library_of("_ZN5Mixer4stepEv", "C_plus_plus_14"),
+            APPLICATION
+        );
+        assert_eq!(library_of("_RNvCs1234_7mycrate4main", "Rust"), APPLICATION);
+
+        let names = ["fopen", "SSL_read", "helper", "fwrite"].map(String::from);
+        let groups = group_by_library(&names, "C99");
+        assert_eq!(groups["libc"], ["fopen", "fwrite"]);
+        assert_eq!(groups["openssl"], ["SSL_read"]);
+        assert_eq!(groups[APPLICATION], ["helper"]);
+    }
+
+    #[test]
+    fn test_function_origins() {
+        let buffer = read_elf_file("./tests/elf_file/minimal-fake-firmware-c-static").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        let origins = function_origins(&elf, "C99");
+        assert!(origins["libc"] > 4 * origins[APPLICATION]);
+        assert!(origins[APPLICATION] > 0);
+    }
+}
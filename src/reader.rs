@@ -0,0 +1,159 @@
+use std::{
+    fs::File,
+    io::Read,
+    ops::{Deref, Range},
+};
+
+#[cfg(feature = "native")]
+use tracing::debug;
+
+use crate::error::{Error, Result};
+
+// Size of the chunks in which a binary is read when it is not mapped.
+const CHUNK_SIZE: usize = 1 << 20;
+
+/// How the content of a binary is accessed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReadMode {
+    /// Map the file in memory, reading it when it cannot be mapped or without the `native`
+    /// feature.
+    #[default]
+    Mmap,
+    /// Read the file in chunks.
+    Read,
+}
+
+/// The content of a binary, mapped in memory or read.
+pub enum BinaryData {
+    /// A read-only mapping of the file.
+    #[cfg(feature = "native")]
+    Mapped(memmap2::Mmap),
+    /// The bytes of the file.
+    Owned(Vec<u8>),
+}
+
+impl BinaryData {
+    /// Open a binary in the given mode.
+    ///
+    /// A mapping can fail on special files and, on 32-bit hosts, on binaries larger than the
+    /// address space left: the file is then read in chunks instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened or read.
+    pub fn open(file_path: &str, mode: ReadMode) -> Result<Self> {
+        let mut file = File::open(file_path)?;
+        // Without the `native` feature, there is nothing to map files with.
+        #[cfg(not(feature = "native"))]
+        let _ = mode;
+        #[cfg(feature = "native")]
+        if mode == ReadMode::Mmap {
+            // SAFETY: the mapping is read-only and private to the analysis; the content is
+            // undefined only if another process truncates the file while it is analyzed.
+            match unsafe { memmap2::Mmap::map(&file) } {
+                Ok(mmap) => return Ok(BinaryData::Mapped(mmap)),
+                Err(error) => debug!(file_path, %error, "mapping failed, reading the file"),
+            }
+        }
+        Ok(BinaryData::Owned(read_chunks(&mut file)?))
+    }
+
+    /// Whether the binary is mapped in memory.
+    pub fn is_mapped(&self) -> bool {
+        match self {
+            #[cfg(feature = "native")]
+            BinaryData::Mapped(_) => true,
+            BinaryData::Owned(_) => false,
+        }
+    }
+
+    /// Return the bytes of the binary, copying them when it is mapped.
+    pub fn into_bytes(self) -> Vec<u8> {
+        match self {
+            #[cfg(feature = "native")]
+            BinaryData::Mapped(mmap) => mmap.to_vec(),
+            BinaryData::Owned(bytes) => bytes,
+        }
+    }
+
+    /// Return the bytes at `offset`, checking that they are within the binary.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::OutOfBounds`] if the bytes go past the end of the binary.
+    pub fn slice(&self, offset: u64, len: u64) -> Result<&[u8]> {
+        slice(self, offset, len)
+    }
+}
+
+impl Deref for BinaryData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            #[cfg(feature = "native")]
+            BinaryData::Mapped(mmap) => mmap,
+            BinaryData::Owned(bytes) => bytes,
+        }
+    }
+}
+
+/// Return `len` bytes of a buffer at `offset`, checking that they are within it.
+///
+/// # Errors
+///
+/// Returns [`Error::OutOfBounds`] if the bytes go past the end of the buffer.
+pub fn slice(buffer: &[u8], offset: u64, len: u64) -> Result<&[u8]> {
+    range(offset, len)
+        .and_then(|range| buffer.get(range))
+        .ok_or(Error::OutOfBounds {
+            offset,
+            len,
+            size: buffer.len() as u64,
+        })
+}
+
+// Read a file to its end, one chunk at a time.
+fn read_chunks(file: &mut File) -> Result<Vec<u8>> {
+    let size = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+    let mut bytes = Vec::with_capacity(usize::try_from(size).unwrap_or(0));
+    let mut chunk = vec![0u8; CHUNK_SIZE];
+    loop {
+        match file.read(&mut chunk)? {
+            0 => return Ok(bytes),
+            read => bytes.extend_from_slice(&chunk[..read]),
+        }
+    }
+}
+
+// The range of `len` bytes at `offset`, `None` if it does not fit in the address space.
+fn range(offset: u64, len: u64) -> Option<Range<usize>> {
+    let start = usize::try_from(offset).ok()?;
+    let end = start.checked_add(usize::try_from(len).ok()?)?;
+    Some(start..end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binary_data() {
+        let file_path = "./tests/elf_file/fake-firmware-c-dynamic";
+        let mapped = BinaryData::open(file_path, ReadMode::Mmap).unwrap();
+        let read = BinaryData::open(file_path, ReadMode::Read).unwrap();
+        assert_eq!(mapped.is_mapped(), cfg!(feature = "native"));
+        assert!(!read.is_mapped());
+        assert_eq!(*mapped, *read);
+        assert_eq!(read.slice(0, 4).unwrap(), b"\x7fELF");
+
+        let size = read.len() as u64;
+        assert!(read.slice(size - 1, 1).is_ok());
+        assert!(matches!(
+            read.slice(size - 1, 2),
+            Err(Error::OutOfBounds { .. })
+        ));
+        assert!(slice(&read, u64::MAX, 2).is_err());
+        assert!(BinaryData::open("./tests/elf_file/missing", ReadMode::Read).is_err());
+    }
+}
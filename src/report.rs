@@ -0,0 +1,571 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fmt::Write,
+};
+
+use serde_json::Value;
+
+use crate::{call_graph::CallGraph, cleanup::demangle_api_name, elf_utils::API};
+
+/// The depth of the call-flow trees of the HTML report, the deeper calls being left out.
+pub const MAX_TREE_DEPTH: usize = 8;
+/// The items of a notable finding listed in the Markdown summary, the others being counted.
+pub const MAX_SUMMARY_ITEMS: usize = 5;
+
+// The fields of the basic information manifest shown at the top of the report.
+const BASIC_INFO: [&str; 10] = [
+    "file_name",
+    "sha256",
+    "build id",
+    "programming language",
+    "architecture",
+    "link",
+    "file_type",
+    "endianness",
+    "entry_point",
+    "header_size",
+];
+
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; }
+th { background: #f0f0f0; }
+td.pass { color: #176f2c; font-weight: bold; }
+td.fail { color: #b3261e; font-weight: bold; }
+td.capability { text-align: center; }
+ul.tree { list-style: none; padding-left: 1.2em; }
+summary { cursor: pointer; }
+em { color: #777; }";
+
+const SCRIPT: &str = "function toggleAll(open) {
+  document.querySelectorAll('details').forEach(function (d) { d.open = open; });
+}";
+
+/// Render the manifests of an analysis as a standalone HTML report, for the readers of the
+/// assessment who do not read JSON.
+///
+/// The report shows the basic information and the hardening verdicts of the binary, the matrix
+/// of the capabilities of each API, and the call flow of each API as a tree of collapsible
+/// nodes. A function already expanded in the tree of an API is not expanded again.
+///
+/// # Arguments
+///
+/// * `manifests` - The manifests of the analysis, by name, as returned by
+///   [`Analyzer::manifests`](crate::analyzer::Analyzer::manifests).
+/// * `graph` - The call graph of the binary, the flows being the direct calls of the APIs when
+///   it is empty.
+/// * `apis` - The APIs found.
+///
+/// # Returns
+///
+/// Returns the HTML document.
+pub fn html_report(manifests: &BTreeMap<String, Value>, graph: &CallGraph, apis: &[API]) -> String {
+    let empty = Value::Null;
+    let basic_info = manifests.get("basic_info").unwrap_or(&empty);
+    let feature = manifests.get("feature_manifest").unwrap_or(&empty);
+    let title = escape(basic_info["file_name"].as_str().unwrap_or("binary"));
+
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Manifest of {title}</title>\n<style>\n{STYLE}\n</style>\n\
+         <script>\n{SCRIPT}\n</script>\n</head>\n<body>\n<h1>Manifest of {title}</h1>\n"
+    );
+
+    html.push_str("<h2>Basic information</h2>\n<table>\n");
+    for field in BASIC_INFO {
+        let Some(value) = basic_info.get(field).and_then(scalar) else {
+            continue;
+        };
+        let _ = writeln!(
+            html,
+            "<tr><th>{}</th><td>{}</td></tr>",
+            escape(field),
+            escape(&value)
+        );
+    }
+    if let Some(found) = basic_info["APIs found"].as_array() {
+        let _ = writeln!(html, "<tr><th>APIs found</th><td>{}</td></tr>", found.len());
+    }
+    html.push_str("</table>\n");
+
+    html.push_str(&hardening_section(&basic_info["hardening"]));
+    html.push_str(&capability_section(&feature["capabilities"]));
+
+    html.push_str("<h2>Call flows</h2>\n");
+    html.push_str(
+        "<p><button onclick=\"toggleAll(true)\">Expand all</button> \
+         <button onclick=\"toggleAll(false)\">Collapse all</button></p>\n",
+    );
+    for api in apis {
+        let _ = writeln!(
+            html,
+            "<details>\n<summary><code>{}</code> at {:#x}</summary>\n<ul class=\"tree\">",
+            escape(&api.name),
+            api.start_addr
+        );
+        match graph.function(api.start_addr) {
+            Some(_) => {
+                let mut path = vec![api.start_addr];
+                let mut expanded = HashSet::from([api.start_addr]);
+                call_tree(
+                    &mut html,
+                    graph,
+                    api.start_addr,
+                    1,
+                    &mut path,
+                    &mut expanded,
+                );
+            }
+            None => {
+                for call in &api.syscalls {
+                    let _ = writeln!(html, "<li><code>{}</code></li>", escape(call));
+                }
+            }
+        }
+        html.push_str("</ul>\n</details>\n");
+    }
+    if apis.is_empty() {
+        html.push_str("<p>No API found.</p>\n");
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+/// Summarize the manifests of an analysis in Markdown, for inclusion in assessment documents.
+///
+/// The summary tells the language, architecture and linkage of the binary, its hardening
+/// verdicts, the capabilities detected for each API, and the notable findings: banned calls,
+/// vulnerability patterns, weak algorithms, secrets, vulnerable libraries, copyleft licenses,
+/// network endpoints, anomalies of the layout and blind spots of the analysis.
+///
+/// # Arguments
+///
+/// * `manifests` - The manifests of the analysis, by name, as returned by
+///   [`Analyzer::manifests`](crate::analyzer::Analyzer::manifests).
+///
+/// # Returns
+///
+/// Returns the Markdown document.
+pub fn markdown_summary(manifests: &BTreeMap<String, Value>) -> String {
+    let empty = Value::Null;
+    let basic_info = manifests.get("basic_info").unwrap_or(&empty);
+    let feature = manifests.get("feature_manifest").unwrap_or(&empty);
+    let text = |field: &str| basic_info.get(field).and_then(scalar);
+
+    let mut md = String::new();
+    let name = text("file_name").unwrap_or_else(|| "binary".to_string());
+    let _ = writeln!(md, "# Summary of `{}`\n", name.replace('`', "'"));
+    md.push_str("| Property | Value |\n| --- | --- |\n");
+    let properties = [
+        (
+            "Language",
+            text("programming language").filter(|lang| !lang.is_empty()),
+        ),
+        ("Architecture", text("architecture")),
+        ("Linkage", text("link")),
+        ("File type", text("file_type")),
+        (
+            "SHA-256",
+            text("sha256").map(|sha256| format!("`{sha256}`")),
+        ),
+        (
+            "APIs found",
+            basic_info["APIs found"]
+                .as_array()
+                .map(|apis| apis.len().to_string()),
+        ),
+    ];
+    for (property, value) in properties {
+        let value = value.unwrap_or_else(|| "unknown".to_string());
+        let _ = writeln!(md, "| {} | {} |", property, cell(&value));
+    }
+
+    if let Some(checks) = basic_info["hardening"].as_object() {
+        md.push_str("\n## Hardening\n\n| Check | Verdict | Detail |\n| --- | --- | --- |\n");
+        for (check, result) in checks {
+            let Some(verdict) = result["verdict"].as_str() else {
+                continue;
+            };
+            let verdict = match verdict {
+                "fail" => "**fail**".to_string(),
+                verdict => verdict.to_string(),
+            };
+            let detail = result["detail"].as_str().unwrap_or_default();
+            let _ = writeln!(md, "| {} | {} | {} |", cell(check), verdict, cell(detail));
+        }
+    }
+
+    md.push_str("\n## Capabilities\n\n");
+    match feature["capabilities"].as_object() {
+        Some(apis) if !apis.is_empty() => {
+            for (api, capabilities) in apis {
+                let capabilities: Vec<String> = capabilities
+                    .as_object()
+                    .map(|capabilities| capabilities.keys().cloned().collect())
+                    .unwrap_or_default();
+                let _ = writeln!(md, "- `{}`: {}", api, capabilities.join(", "));
+            }
+        }
+        _ => md.push_str("No capability detected.\n"),
+    }
+
+    md.push_str("\n## Notable findings\n\n");
+    let findings = notable_findings(basic_info, feature);
+    if findings.is_empty() {
+        md.push_str("No notable finding.\n");
+    }
+    for finding in findings {
+        let _ = writeln!(md, "- {finding}");
+    }
+    md
+}
+
+// The table of the hardening verdicts, with the number of gadgets of the code.
+fn hardening_section(hardening: &Value) -> String {
+    let Some(checks) = hardening.as_object() else {
+        return String::new();
+    };
+    let mut html = String::from(
+        "<h2>Hardening</h2>\n<table>\n<tr><th>Check</th><th>Verdict</th><th>Detail</th></tr>\n",
+    );
+    for (check, result) in checks {
+        let Some(verdict) = result["verdict"].as_str() else {
+            continue;
+        };
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td class=\"{}\">{}</td><td>{}</td></tr>",
+            escape(check),
+            escape(verdict),
+            escape(verdict),
+            escape(result["detail"].as_str().unwrap_or_default())
+        );
+    }
+    html.push_str("</table>\n");
+    if let (Some(rop), Some(jop)) = (
+        hardening["gadgets"]["ROP"].as_u64(),
+        hardening["gadgets"]["JOP"].as_u64(),
+    ) {
+        let _ = writeln!(html, "<p>{rop} ROP gadgets and {jop} JOP gadgets.</p>");
+    }
+    html
+}
+
+// The matrix of the APIs and of their capabilities, each cell listing the evidence of the
+// capability in its title.
+fn capability_section(capabilities: &Value) -> String {
+    let mut html = String::from("<h2>Capabilities</h2>\n");
+    let apis = match capabilities.as_object() {
+        Some(apis) if !apis.is_empty() => apis,
+        _ => {
+            html.push_str("<p>No capability detected.</p>\n");
+            return html;
+        }
+    };
+    let columns: BTreeSet<&String> = apis
+        .values()
+        .filter_map(Value::as_object)
+        .flat_map(|capabilities| capabilities.keys())
+        .collect();
+    html.push_str("<table>\n<tr><th>API</th>");
+    for column in &columns {
+        let _ = write!(html, "<th>{}</th>", escape(column));
+    }
+    html.push_str("</tr>\n");
+    for (api, capabilities) in apis {
+        let _ = write!(html, "<tr><td><code>{}</code></td>", escape(api));
+        for column in &columns {
+            match capabilities[column.as_str()].as_array() {
+                Some(evidence) => {
+                    let evidence: Vec<String> = evidence.iter().filter_map(scalar).collect();
+                    let _ = write!(
+                        html,
+                        "<td class=\"capability\" title=\"{}\">&#x25CF;</td>",
+                        escape(&evidence.join(", "))
+                    );
+                }
+                None => html.push_str("<td></td>"),
+            }
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n");
+    html
+}
+
+// Render the callees of the function at `addr` as items of the tree, expanding each function
+// once, down to `MAX_TREE_DEPTH`.
+fn call_tree(
+    html: &mut String,
+    graph: &CallGraph,
+    addr: u64,
+    depth: usize,
+    path: &mut Vec<u64>,
+    expanded: &mut HashSet<u64>,
+) {
+    for call in graph.callees(addr) {
+        let mut label = format!("<code>{}</code>", escape(&demangle_api_name(&call.name)));
+        if call.indirect {
+            label.push_str(" <em>(indirect)</em>");
+        }
+        let target = call
+            .target
+            .filter(|&target| !graph.callees(target).is_empty());
+        let Some(target) = target else {
+            let _ = writeln!(html, "<li>{label}</li>");
+            continue;
+        };
+        let note = if path.contains(&target) {
+            Some("recursive")
+        } else if expanded.contains(&target) {
+            Some("expanded above")
+        } else if depth >= MAX_TREE_DEPTH {
+            Some("deeper calls left out")
+        } else {
+            None
+        };
+        if let Some(note) = note {
+            let _ = writeln!(html, "<li>{label} <em>({note})</em></li>");
+            continue;
+        }
+        expanded.insert(target);
+        path.push(target);
+        let _ = writeln!(
+            html,
+            "<li><details><summary>{label}</summary><ul class=\"tree\">"
+        );
+        call_tree(html, graph, target, depth + 1, path, expanded);
+        html.push_str("</ul></details></li>\n");
+        path.pop();
+    }
+}
+
+// The notable findings of the manifests, as the items of the Markdown summary.
+fn notable_findings(basic_info: &Value, feature: &Value) -> Vec<String> {
+    let items = |value: &Value, field: &str| -> Vec<String> {
+        let mut items: Vec<String> = value
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|item| scalar(&item[field]))
+            .collect();
+        items.sort();
+        items.dedup();
+        items
+    };
+    let mut findings = Vec::new();
+    let mut finding = |label: &str, count: usize, items: Vec<String>| {
+        if count > 0 {
+            findings.push(format!("{label} ({count}): {}", summary_list(&items)));
+        }
+    };
+
+    let calls = &feature["dangerous calls"];
+    let count = calls.as_array().map_or(0, Vec::len);
+    finding("Calls to banned functions", count, items(calls, "function"));
+    let patterns = &feature["vulnerability patterns"];
+    let count = patterns.as_array().map_or(0, Vec::len);
+    finding("Vulnerability patterns", count, items(patterns, "pattern"));
+    let weak: Vec<Value> = feature["crypto"]["algorithms"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|algorithm| algorithm["weak"] == true)
+        .cloned()
+        .collect();
+    let weak = Value::Array(weak);
+    let algorithms = items(&weak, "algorithm");
+    finding(
+        "Weak cryptographic algorithms",
+        algorithms.len(),
+        algorithms,
+    );
+    let secrets = &feature["secrets"];
+    let count = secrets.as_array().map_or(0, Vec::len);
+    finding("Hardcoded secrets", count, items(secrets, "kind"));
+    let vulnerable: Vec<String> = feature["vulnerabilities"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .map(|(library, entry)| {
+            let ids = items(&entry["advisories"], "id");
+            match entry["version"].as_str() {
+                Some(version) => format!("{library} {version} ({})", ids.join(", ")),
+                None => format!("{library} ({})", ids.join(", ")),
+            }
+        })
+        .collect();
+    finding(
+        "Libraries with known advisories",
+        vulnerable.len(),
+        vulnerable,
+    );
+    let copyleft: Vec<String> = feature["license summary"]["copyleft"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(scalar)
+        .collect();
+    finding("Copyleft licenses", copyleft.len(), copyleft);
+    let hosts = items(&feature["network endpoints"]["all"], "host");
+    finding("Network endpoints", hosts.len(), hosts);
+
+    let strings = |value: &Value| -> Vec<String> {
+        value
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(scalar)
+            .collect()
+    };
+    let anomalies = strings(&basic_info["layout"]["anomalies"]);
+    finding("Anomalies of the layout", anomalies.len(), anomalies);
+    let warnings = strings(&basic_info["dependencies"]["warnings"]);
+    finding("Insecure dependencies", warnings.len(), warnings);
+    let blind_spots: Vec<String> = basic_info["diagnostics"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|diagnostic| diagnostic["severity"] != "info")
+        .filter_map(|diagnostic| {
+            let code = diagnostic["code"].as_str()?;
+            let count = diagnostic["items"].as_array().map_or(0, Vec::len);
+            Some(format!("{code} ({count})"))
+        })
+        .collect();
+    finding(
+        "Blind spots of the analysis",
+        blind_spots.len(),
+        blind_spots,
+    );
+    if basic_info["entropy"]["probably packed"] == true {
+        findings
+            .push("Packed code: an executable section looks compressed or encrypted".to_string());
+    }
+    findings
+}
+
+// The first items of a list, in code spans, the others being counted.
+fn summary_list(items: &[String]) -> String {
+    let mut list: Vec<String> = items
+        .iter()
+        .take(MAX_SUMMARY_ITEMS)
+        .map(|item| format!("`{}`", item.replace('`', "'")))
+        .collect();
+    if items.len() > MAX_SUMMARY_ITEMS {
+        list.push(format!("and {} more", items.len() - MAX_SUMMARY_ITEMS));
+    }
+    list.join(", ")
+}
+
+// Escape the pipes of the text of a Markdown table cell.
+fn cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+// A string, number or boolean of a manifest as text.
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(string) => Some(string.clone()),
+        Value::Number(_) | Value::Bool(_) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+// Escape the characters of a text that are markup in HTML.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    use crate::{
+        analyzer::Analyzer, api_detection::ApiSpec, call_graph::CallEdge, progress::Verbosity,
+    };
+
+    #[test]
+    fn test_html_report() {
+        let analyzer = Analyzer::new("./tests/elf_file/fake-firmware-c-dynamic")
+            .with_api_list(vec![ApiSpec::new("writeOnDrive")])
+            .with_verbosity(Verbosity::Quiet);
+        let analysis = analyzer.analyze().unwrap();
+        let manifests = analyzer.manifests(&analysis).unwrap();
+        let html = html_report(&manifests, &analysis.graph, &analysis.apis);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<th>architecture</th><td>x86-64</td>"));
+        assert!(html.contains("<tr><td>PIE</td><td class=\"pass\">pass</td>"));
+        assert!(html.contains("<th>filesystem-write</th>"));
+        assert!(html.contains("<summary><code>writeOnDrive</code>"));
+        assert!(html.contains("<li><code>fopen64</code></li>"));
+        assert_eq!(
+            html.matches("<details").count(),
+            html.matches("</details>").count()
+        );
+    }
+
+    #[test]
+    fn test_markdown_summary() {
+        let analyzer = Analyzer::new("./tests/elf_file/fake-firmware-c-dynamic")
+            .with_api_list(vec![ApiSpec::new("writeOnDrive")])
+            .with_verbosity(Verbosity::Quiet);
+        let analysis = analyzer.analyze().unwrap();
+        let summary = markdown_summary(&analyzer.manifests(&analysis).unwrap());
+        assert!(summary.starts_with("# Summary of `fake-firmware-c-dynamic`"));
+        assert!(summary.contains("| Linkage | dynamically linked |"));
+        assert!(summary.contains("| FORTIFY_SOURCE | **fail** |"));
+        assert!(summary.contains("- `writeOnDrive`: filesystem-write"));
+        assert!(summary.contains("- Weak cryptographic algorithms"));
+        assert!(summary.contains("- Calls to banned functions"));
+        assert!(summary.contains("`strcpy`"));
+
+        let items: Vec<String> = (0..7).map(|i| i.to_string()).collect();
+        assert_eq!(summary_list(&items), "`0`, `1`, `2`, `3`, `4`, and 2 more");
+        assert_eq!(cell("a|b"), "a\\|b");
+    }
+
+    #[test]
+    fn test_call_tree() {
+        let edge = |site, target, name: &str| CallEdge {
+            site,
+            target: Some(target),
+            name: name.to_string(),
+            indirect: false,
+        };
+        let calls = HashMap::from([
+            (0x10, vec![edge(0x11, 0x20, "a<b>"), edge(0x12, 0x30, "c")]),
+            (0x20, vec![edge(0x21, 0x30, "c")]),
+            (0x30, vec![edge(0x31, 0x10, "api")]),
+        ]);
+        let graph = CallGraph::from_calls(Vec::new(), calls, HashMap::new());
+        let mut html = String::new();
+        call_tree(
+            &mut html,
+            &graph,
+            0x10,
+            1,
+            &mut vec![0x10],
+            &mut HashSet::from([0x10]),
+        );
+        assert!(html.contains("<summary><code>a&lt;b&gt;</code></summary>"));
+        assert!(html.contains("<li><code>api</code> <em>(recursive)</em></li>"));
+        assert!(html.contains("<li><code>c</code> <em>(expanded above)</em></li>"));
+        assert_eq!(escape("\"x\" & 'y'"), "&quot;x&quot; &amp; &#39;y&#39;");
+    }
+}
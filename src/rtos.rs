@@ -0,0 +1,362 @@
+use std::sync::OnceLock;
+
+use goblin::elf::{header::EM_ARM, Elf};
+use regex::Regex;
+use serde_json::{json, Value};
+
+use crate::{
+    arguments::call_arguments, call_graph::CallGraph, cleanup::demangle_api_name,
+    cortex_m::thumb_call_arguments, elf_utils::API, error::Result, strings::extract_strings,
+};
+
+/// The symbols revealing each RTOS: the functions of its kernel and its global structures, e.g.
+/// the control block of the running task.
+pub const RTOS_SYMBOLS: [(&str, &[&str]); 3] = [
+    (
+        "FreeRTOS",
+        &[
+            "vTaskStartScheduler",
+            "xTaskCreate",
+            "xTaskCreateStatic",
+            "xTaskGenericCreate",
+            "vTaskDelay",
+            "xTaskGetTickCount",
+            "xQueueGenericCreate",
+            "xQueueGenericSend",
+            "xQueueReceive",
+            "pvPortMalloc",
+            "vPortFree",
+            "xPortPendSVHandler",
+            "xPortSysTickHandler",
+            "pxCurrentTCB",
+            "uxTopReadyPriority",
+            "xSchedulerRunning",
+        ],
+    ),
+    (
+        "Zephyr",
+        &[
+            "k_thread_create",
+            "z_impl_k_thread_create",
+            "z_cstart",
+            "z_thread_entry",
+            "k_sched_lock",
+            "z_impl_k_sleep",
+            "z_impl_k_sem_take",
+            "z_impl_k_sem_give",
+            "k_msgq_put",
+            "z_swap_irqlock",
+            "_kernel",
+            "z_main_thread",
+        ],
+    ),
+    (
+        "RT-Thread",
+        &[
+            "rtthread_startup",
+            "rt_thread_create",
+            "rt_thread_init",
+            "rt_thread_startup",
+            "rt_thread_delay",
+            "rt_system_scheduler_start",
+            "rt_sem_take",
+            "rt_mq_send",
+            "rt_kprintf",
+            "rt_current_thread",
+            "rt_thread_priority_table",
+        ],
+    ),
+];
+
+/// The functions creating tasks, as (RTOS, function, index of the entry function argument,
+/// index of the task name argument).
+pub const TASK_FUNCTIONS: [(&str, &str, usize, Option<usize>); 7] = [
+    ("FreeRTOS", "xTaskCreate", 0, Some(1)),
+    ("FreeRTOS", "xTaskCreateStatic", 0, Some(1)),
+    ("FreeRTOS", "xTaskCreatePinnedToCore", 0, Some(1)),
+    ("Zephyr", "k_thread_create", 3, None),
+    ("Zephyr", "z_impl_k_thread_create", 3, None),
+    ("RT-Thread", "rt_thread_create", 1, Some(0)),
+    ("RT-Thread", "rt_thread_init", 2, Some(1)),
+];
+
+// The strings naming each RTOS, with its version as first group when they hold it.
+const RTOS_PATTERNS: [(&str, &str); 3] = [
+    ("FreeRTOS", r"FreeRTOS(?: Kernel)? V?(\d+\.\d+\.\d+)?"),
+    (
+        "Zephyr",
+        r"Zephyr OS(?: build)?(?: zephyr-)?(?: v(\d+\.\d+\.\d+))?",
+    ),
+    (
+        "RT-Thread",
+        r"RT-Thread(?: [vV]?(\d+\.\d+\.\d+))?|Thread Operating System",
+    ),
+];
+
+// The minimum number of symbols of an RTOS that tell it is there, without a string naming it:
+// a firmware may well have a function named like one of them.
+const MIN_SYMBOLS: usize = 2;
+
+/// The RTOS a firmware is built on.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rtos {
+    /// The name of the RTOS, e.g. `FreeRTOS`.
+    pub name: String,
+    /// Its version, when a string of the binary tells it.
+    pub version: Option<String>,
+    /// The symbols and strings revealing it.
+    pub evidence: Vec<String>,
+}
+
+impl Rtos {
+    /// Return the RTOS as the `rtos` entry of the basic information manifest.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "version": self.version,
+            "evidence": self.evidence,
+        })
+    }
+}
+
+/// A call creating a task of an RTOS.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TaskCreation {
+    /// The function called to create the task, e.g. `xTaskCreate`.
+    pub function: String,
+    /// The address of the call instruction.
+    pub site: u64,
+    /// The name of the function performing the call.
+    pub caller: String,
+    /// The name given to the task, when it is a constant string.
+    pub name: Option<String>,
+    /// The address of the entry function of the task, when it is a constant.
+    pub entry: Option<u64>,
+    /// The name of the entry function, when it is a function of the binary.
+    pub entry_name: Option<String>,
+    /// The names of the APIs whose flow creates the task.
+    pub apis: Vec<String>,
+}
+
+impl TaskCreation {
+    /// Return the creation as an entry of the `tasks` of the flow call manifest.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "created by": self.function,
+            "site": format!("{:#x}", self.site),
+            "caller": self.caller,
+            "task": self.name,
+            "entry": self.entry_name,
+            "entry address": self.entry.map(|addr| format!("{:#x}", addr)),
+            "APIs": self.apis,
+        })
+    }
+}
+
+/// Detect the RTOS of a firmware, from the symbols of its kernel and the strings naming it.
+///
+/// An RTOS is detected when a string names it, or when at least two of its [`RTOS_SYMBOLS`] are
+/// defined or imported; with several candidates, the one with the most evidence is taken.
+///
+/// # Arguments
+///
+/// * `elf` - The ELF object representing the binary.
+/// * `buffer` - The buffer containing the binary data of the ELF file.
+///
+/// # Returns
+///
+/// Returns the RTOS detected, `None` when there is none.
+pub fn detect_rtos(elf: &Elf, buffer: &[u8]) -> Option<Rtos> {
+    static REGEXES: OnceLock<Vec<(&str, Regex)>> = OnceLock::new();
+    let regexes = REGEXES.get_or_init(|| {
+        RTOS_PATTERNS
+            .iter()
+            .map(|(rtos, pattern)| (*rtos, Regex::new(pattern).unwrap()))
+            .collect()
+    });
+    let symbols: Vec<&str> = elf
+        .syms
+        .iter()
+        .filter_map(|sym| elf.strtab.get_at(sym.st_name))
+        .chain(
+            elf.dynsyms
+                .iter()
+                .filter_map(|sym| elf.dynstrtab.get_at(sym.st_name)),
+        )
+        .collect();
+    let strings = extract_strings(elf, buffer, 8);
+
+    let mut candidates = Vec::new();
+    for (name, kernel) in RTOS_SYMBOLS {
+        let mut found: Vec<&str> = kernel
+            .iter()
+            .copied()
+            .filter(|symbol| symbols.contains(symbol))
+            .collect();
+        found.sort_unstable();
+        let regex = &regexes.iter().find(|(rtos, _)| *rtos == name).unwrap().1;
+        let named: Vec<(String, Option<String>)> = strings
+            .iter()
+            .filter_map(|string| {
+                let caps = regex.captures(&string.value)?;
+                Some((
+                    caps[0].trim().to_string(),
+                    caps.get(1).map(|version| version.as_str().to_string()),
+                ))
+            })
+            .collect();
+        if named.is_empty() && found.len() < MIN_SYMBOLS {
+            continue;
+        }
+        let mut evidence: Vec<String> = found.iter().map(|symbol| symbol.to_string()).collect();
+        evidence.extend(named.iter().map(|(text, _)| format!("\"{}\"", text)));
+        evidence.dedup();
+        candidates.push(Rtos {
+            name: name.to_string(),
+            version: named.into_iter().find_map(|(_, version)| version),
+            evidence,
+        });
+    }
+    candidates
+        .into_iter()
+        .rev()
+        .max_by_key(|rtos| rtos.evidence.len())
+}
+
+/// Find the calls creating tasks of an RTOS and resolve their entry function and name.
+///
+/// The arguments are those of [`call_arguments`] for x86-64 builds, e.g. of the FreeRTOS POSIX
+/// port or of the Zephyr `native_sim` board, and those of [`thumb_call_arguments`] for ARM
+/// images.
+///
+/// # Arguments
+///
+/// * `elf` - The ELF object representing the binary.
+/// * `buffer` - The buffer containing the binary data of the ELF file.
+/// * `graph` - The call graph of the binary.
+/// * `api_list` - The APIs found, to tell which of them create each task.
+///
+/// # Returns
+///
+/// Returns a `Result` containing the task creations, sorted by call site.
+pub fn task_creations(
+    elf: &Elf,
+    buffer: &[u8],
+    graph: &CallGraph,
+    api_list: &[API],
+) -> Result<Vec<TaskCreation>> {
+    let flows: Vec<_> = api_list
+        .iter()
+        .map(|api| (api.name.as_str(), graph.reachable(&[api.start_addr])))
+        .collect();
+    let functions = TASK_FUNCTIONS.map(|(_, function, _, _)| function);
+    let thumb = elf.header.e_machine == EM_ARM;
+    let calls = match thumb {
+        true => thumb_call_arguments(elf, buffer, graph, &functions),
+        false => call_arguments(elf, buffer, graph, &functions)?,
+    };
+    let mut creations = Vec::new();
+    for call in calls {
+        let (_, _, entry, name) = TASK_FUNCTIONS
+            .iter()
+            .find(|(_, function, _, _)| *function == call.function)
+            .unwrap();
+        // The lowest bit of a Thumb function pointer only tells its instruction set.
+        let entry =
+            call.values[*entry]
+                .filter(|&addr| addr != 0)
+                .map(|addr| if thumb { addr & !1 } else { addr });
+        creations.push(TaskCreation {
+            function: call.function.to_string(),
+            site: call.site,
+            caller: graph
+                .function(call.caller)
+                .map(|func| demangle_api_name(&func.name))
+                .unwrap_or_default(),
+            name: name
+                .and_then(|index| call.values[index])
+                .and_then(|addr| c_string(elf, buffer, addr)),
+            entry,
+            entry_name: entry
+                .and_then(|addr| graph.function(addr))
+                .map(|func| demangle_api_name(&func.name)),
+            apis: flows
+                .iter()
+                .filter(|(_, reachable)| reachable.contains(&call.caller))
+                .map(|(name, _)| name.to_string())
+                .collect(),
+        });
+    }
+    creations.sort_by_key(|creation| creation.site);
+    Ok(creations)
+}
+
+// Read the printable, NUL-terminated string at an address of the binary.
+fn c_string(elf: &Elf, buffer: &[u8], addr: u64) -> Option<String> {
+    let sh = elf
+        .section_headers
+        .iter()
+        .find(|sh| sh.sh_addr <= addr && addr < sh.sh_addr + sh.sh_size && sh.sh_addr != 0)?;
+    let data = buffer.get(sh.file_range()?)?;
+    let text = &data[(addr - sh.sh_addr) as usize..];
+    let len = text.iter().position(|&byte| byte == 0)?;
+    let text = std::str::from_utf8(&text[..len]).ok()?;
+    (!text.is_empty() && text.chars().all(|c| c.is_ascii_graphic() || c == ' '))
+        .then(|| text.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cortex_m::thumb_call_graph, elf_utils::read_elf_file};
+
+    #[test]
+    fn test_detect_rtos() {
+        let buffer = read_elf_file("./tests/elf_file/minimal-fake-firmware-freertos").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        let rtos = detect_rtos(&elf, &buffer).unwrap();
+        assert_eq!(rtos.name, "FreeRTOS");
+        assert_eq!(rtos.version.as_deref(), Some("10.4.6"));
+        assert!(rtos.evidence.contains(&"pxCurrentTCB".to_string()));
+        assert!(rtos
+            .evidence
+            .contains(&"\"FreeRTOS Kernel V10.4.6\"".to_string()));
+        assert_eq!(rtos.to_json()["name"], "FreeRTOS");
+
+        let buffer = read_elf_file("./tests/elf_file/minimal-fake-firmware-cortex-m").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        assert_eq!(detect_rtos(&elf, &buffer), None);
+    }
+
+    #[test]
+    fn test_task_creations() {
+        let buffer = read_elf_file("./tests/elf_file/minimal-fake-firmware-freertos").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        let graph = thumb_call_graph(&elf, &buffer);
+        let main = graph.functions().find(|f| f.name == "main").unwrap();
+        let api = API::new("main".to_string(), main.start_addr, main.end_addr);
+        let tasks = task_creations(&elf, &buffer, &graph, &[api]).unwrap();
+        let found: Vec<(Option<&str>, Option<&str>)> = tasks
+            .iter()
+            .map(|task| (task.name.as_deref(), task.entry_name.as_deref()))
+            .collect();
+        // An entry loaded from the literal pool, then one built by movw and movt.
+        assert_eq!(
+            found,
+            [
+                (Some("sensor"), Some("vSensorTask")),
+                (Some("network"), Some("vNetworkTask")),
+            ]
+        );
+        assert!(tasks.iter().all(|task| task.caller == "main"));
+        assert_eq!(tasks[0].apis, ["main"]);
+        assert_eq!(tasks[1].to_json()["created by"], "xTaskCreate");
+
+        let buffer = read_elf_file("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        let graph = CallGraph::build(&elf, &buffer, false).unwrap();
+        assert!(task_creations(&elf, &buffer, &graph, &[])
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,259 @@
+use std::{collections::BTreeMap, sync::OnceLock};
+
+use goblin::elf::{section_header::SHT_NOBITS, Elf};
+use regex::{bytes, Regex};
+use serde_json::{json, Value};
+
+use crate::cleanup::{demangle_rust, is_rust_symbol};
+
+/// The crates of the Rust toolchain, linked into every binary: they are not dependencies.
+pub const SYSROOT_CRATES: [&str; 13] = [
+    "std",
+    "core",
+    "alloc",
+    "proc_macro",
+    "test",
+    "panic_unwind",
+    "panic_abort",
+    "compiler_builtins",
+    "std_detect",
+    "unwind",
+    "rustc_std_workspace_core",
+    "rustc_std_workspace_alloc",
+    "rustc_std_workspace_std",
+];
+
+// The sections holding source paths, besides the `.rodata*` ones.
+const SOURCE_SECTIONS: [&str; 4] = [
+    ".data.rel.ro",
+    ".debug_str",
+    ".debug_line_str",
+    ".debug_line",
+];
+
+/// How sure the analysis is that a crate is a dependency of the binary.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Confidence {
+    /// Only symbols name the crate: it may be the binary crate itself, or a crate the standard
+    /// library depends on, e.g. `gimli` or `hashbrown`.
+    Low,
+    /// A source path of the Cargo registry names the crate, but no symbol does: its code may be
+    /// inlined.
+    Medium,
+    /// Both a source path of the Cargo registry and the symbols name the crate.
+    High,
+}
+
+impl Confidence {
+    /// Return the confidence, as written in the manifests.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Confidence::Low => "low",
+            Confidence::Medium => "medium",
+            Confidence::High => "high",
+        }
+    }
+}
+
+/// A crate the binary was built with.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RustCrate {
+    /// The name of the crate, as published, e.g. `serde_json` or `futures-util`.
+    pub name: String,
+    /// The versions of the crate, from the source paths of the Cargo registry, sorted.
+    pub versions: Vec<String>,
+    /// The number of symbols of the crate.
+    pub symbols: usize,
+    /// How sure the analysis is that the crate is a dependency.
+    pub confidence: Confidence,
+}
+
+/// The toolchain and the crates of a Rust binary.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RustDependencies {
+    /// The version of rustc, from the `.comment` section, e.g. `1.76.0`.
+    pub rustc: Option<String>,
+    /// The crates the binary was built with, sorted by name.
+    pub crates: Vec<RustCrate>,
+}
+
+impl RustDependencies {
+    /// Return the dependencies as the `rust crates` entry of the basic information manifest.
+    pub fn to_json(&self) -> Value {
+        let crates: Vec<Value> = self
+            .crates
+            .iter()
+            .map(|krate| {
+                json!({
+                    "name": krate.name,
+                    "versions": krate.versions,
+                    "symbols": krate.symbols,
+                    "confidence": krate.confidence.as_str(),
+                })
+            })
+            .collect();
+        json!({ "rustc": self.rustc, "crates": crates })
+    }
+}
+
+/// Infer the crates a Rust binary was built with, on a best-effort basis.
+///
+/// The crates are the first segments of the paths of the demangled Rust symbols, e.g. `hyper`
+/// for `<hyper::proto::h1::conn::Conn<I,B,T> as core::ops::drop::Drop>::drop`, and the
+/// `<name>-<version>` directories of the Cargo registry in the source paths kept for the panic
+/// messages and the debugging information. The crates of the toolchain are left out.
+///
+/// # Arguments
+///
+/// * `elf` - The ELF object representing the binary.
+/// * `buffer` - The buffer containing the binary data of the ELF file.
+///
+/// # Returns
+///
+/// Returns the toolchain and the crates, or `None` if the binary was not built by rustc.
+pub fn rust_dependencies(elf: &Elf, buffer: &[u8]) -> Option<RustDependencies> {
+    static PATH: OnceLock<Regex> = OnceLock::new();
+    static REGISTRY: OnceLock<bytes::Regex> = OnceLock::new();
+    let path = PATH.get_or_init(|| Regex::new(r"(?:^|[<&*(\[,; ])([a-z_][a-z0-9_]*)::").unwrap());
+    let registry = REGISTRY.get_or_init(|| {
+        bytes::Regex::new(
+            r"registry/src/[^/]+/([A-Za-z0-9_-]+?)-(\d+\.\d+\.\d+(?:-[0-9A-Za-z.]+)?(?:\+[0-9A-Za-z.]+)?)/",
+        )
+        .unwrap()
+    });
+
+    let mut symbols: BTreeMap<String, usize> = BTreeMap::new();
+    for sym in elf.syms.iter() {
+        let Some(name) = elf.strtab.get_at(sym.st_name) else {
+            continue;
+        };
+        if !is_rust_symbol(name) {
+            continue;
+        }
+        let demangled = demangle_rust(name);
+        let mut crates: Vec<&str> = path
+            .captures_iter(&demangled)
+            .map(|caps| caps.get(1).unwrap().as_str())
+            .filter(|krate| *krate != "_" && !SYSROOT_CRATES.contains(krate))
+            .collect();
+        crates.sort_unstable();
+        crates.dedup();
+        for krate in crates {
+            *symbols.entry(krate.to_string()).or_default() += 1;
+        }
+    }
+
+    let mut published: BTreeMap<String, (String, Vec<String>)> = BTreeMap::new();
+    for data in source_path_sections(elf, buffer) {
+        for caps in registry.captures_iter(data) {
+            let name = String::from_utf8_lossy(&caps[1]).into_owned();
+            let version = String::from_utf8_lossy(&caps[2]).into_owned();
+            // Symbols name the crates with underscores for the dashes of their package name.
+            let (_, versions) = published
+                .entry(name.replace('-', "_"))
+                .or_insert_with(|| (name, Vec::new()));
+            versions.push(version);
+        }
+    }
+
+    let rustc = rustc_version(elf, buffer);
+    if rustc.is_none() && symbols.is_empty() {
+        return None;
+    }
+    let mut crates: Vec<RustCrate> = published
+        .into_iter()
+        .map(|(key, (name, mut versions))| {
+            versions.sort();
+            versions.dedup();
+            let count = symbols.remove(&key).unwrap_or(0);
+            RustCrate {
+                name,
+                versions,
+                symbols: count,
+                confidence: match count {
+                    0 => Confidence::Medium,
+                    _ => Confidence::High,
+                },
+            }
+        })
+        .collect();
+    crates.extend(symbols.into_iter().map(|(name, count)| RustCrate {
+        name,
+        versions: Vec::new(),
+        symbols: count,
+        confidence: Confidence::Low,
+    }));
+    crates.sort_by(|a, b| a.name.cmp(&b.name));
+    Some(RustDependencies { rustc, crates })
+}
+
+// Read the version of rustc recorded in the `.comment` section, e.g.
+// `rustc version 1.76.0 (07dca489a 2024-02-04)`.
+fn rustc_version(elf: &Elf, buffer: &[u8]) -> Option<String> {
+    static RUSTC: OnceLock<bytes::Regex> = OnceLock::new();
+    let rustc = RUSTC.get_or_init(|| {
+        bytes::Regex::new(r"rustc version (\d+\.\d+\.\d+(?:-[a-z0-9.]+)?)").unwrap()
+    });
+    let sec = elf
+        .section_headers
+        .iter()
+        .find(|sec| elf.shdr_strtab.get_at(sec.sh_name) == Some(".comment"))?;
+    let data = buffer.get(sec.file_range()?)?;
+    let caps = rustc.captures(data)?;
+    Some(String::from_utf8_lossy(&caps[1]).into_owned())
+}
+
+// The content of the sections holding source paths: the read-only data, for the panic
+// messages, and the DWARF strings and line tables.
+fn source_path_sections<'a>(elf: &Elf, buffer: &'a [u8]) -> Vec<&'a [u8]> {
+    elf.section_headers
+        .iter()
+        .filter(|sec| sec.sh_type != SHT_NOBITS)
+        .filter(|sec| {
+            elf.shdr_strtab
+                .get_at(sec.sh_name)
+                .is_some_and(|name| name.starts_with(".rodata") || SOURCE_SECTIONS.contains(&name))
+        })
+        .filter_map(|sec| buffer.get(sec.file_range()?))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf_utils::read_elf_file;
+
+    #[test]
+    fn test_rust_dependencies() {
+        let buffer = read_elf_file("./tests/elf_file/fake-firmware-rust-dynamic").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        let dependencies = rust_dependencies(&elf, &buffer).unwrap();
+        assert_eq!(dependencies.rustc.as_deref(), Some("1.76.0"));
+        let crates = &dependencies.crates;
+        assert!(crates.windows(2).all(|pair| pair[0].name < pair[1].name));
+        assert!(crates
+            .iter()
+            .all(|krate| !SYSROOT_CRATES.contains(&krate.name.as_str())));
+
+        let hyper = crates.iter().find(|krate| krate.name == "hyper").unwrap();
+        assert_eq!(hyper.versions, ["0.14.28"]);
+        assert_eq!(hyper.confidence, Confidence::High);
+        assert!(hyper.symbols > 0);
+        let bitflags = crates
+            .iter()
+            .find(|krate| krate.name == "bitflags")
+            .unwrap();
+        assert_eq!(bitflags.versions, ["1.3.2", "2.4.2"]);
+        let futures = crates.iter().find(|krate| krate.name == "futures-util");
+        assert!(futures.is_some_and(|krate| krate.versions == ["0.3.30"]));
+        assert!(crates
+            .iter()
+            .filter(|krate| krate.confidence == Confidence::Low)
+            .all(|krate| krate.versions.is_empty()));
+        assert_eq!(dependencies.to_json()["rustc"], "1.76.0");
+
+        let buffer = read_elf_file("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        assert_eq!(rust_dependencies(&elf, &buffer), None);
+    }
+}
@@ -0,0 +1,302 @@
+use std::str::FromStr;
+
+use regex::Regex;
+use serde_json::Value;
+
+use crate::error::{Error, Result};
+
+/// The version of the manifest schema written in the `schema_version` field of the manifests.
+///
+/// Fields may be added to the manifests within a version; removing or changing a field
+/// requires a new version.
+pub const SCHEMA_VERSION: &str = "1.0";
+
+// JSON Schema documents of the manifests, as (kind, schema version, document).
+const SCHEMAS: [(ManifestKind, &str, &str); 4] = [
+    (
+        ManifestKind::BasicInfo,
+        "1.0",
+        include_str!("../schemas/1.0/basic_info.schema.json"),
+    ),
+    (
+        ManifestKind::FlowCall,
+        "1.0",
+        include_str!("../schemas/1.0/flow_call.schema.json"),
+    ),
+    (
+        ManifestKind::Feature,
+        "1.0",
+        include_str!("../schemas/1.0/feature_manifest.schema.json"),
+    ),
+    (
+        ManifestKind::Firmware,
+        "1.0",
+        include_str!("../schemas/1.0/firmware_manifest.schema.json"),
+    ),
+];
+
+/// The manifests described by a schema.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ManifestKind {
+    /// `basic_info`: general information about the binary.
+    BasicInfo,
+    /// `flow_call`: the functions called by each API.
+    FlowCall,
+    /// `feature_manifest`: the features of each API and the findings about the binary.
+    Feature,
+    /// `firmware_manifest`: the binaries of a firmware image and their merged capabilities.
+    Firmware,
+}
+
+impl ManifestKind {
+    /// Return the name of the manifest, which is also the stem of its file name.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ManifestKind::BasicInfo => "basic_info",
+            ManifestKind::FlowCall => "flow_call",
+            ManifestKind::Feature => "feature_manifest",
+            ManifestKind::Firmware => "firmware_manifest",
+        }
+    }
+}
+
+impl FromStr for ManifestKind {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name {
+            "basic_info" => Ok(ManifestKind::BasicInfo),
+            "flow_call" => Ok(ManifestKind::FlowCall),
+            "feature_manifest" => Ok(ManifestKind::Feature),
+            "firmware_manifest" => Ok(ManifestKind::Firmware),
+            _ => Err(Error::InvalidManifest(format!(
+                "unknown manifest kind {}",
+                name
+            ))),
+        }
+    }
+}
+
+/// Return the JSON Schema document of a kind of manifest in a schema version, if it exists.
+pub fn json_schema(kind: ManifestKind, version: &str) -> Option<&'static str> {
+    SCHEMAS
+        .iter()
+        .find(|(schema_kind, schema_version, _)| *schema_kind == kind && *schema_version == version)
+        .map(|(_, _, document)| *document)
+}
+
+/// Check a manifest against the schema of the version it declares in `schema_version`.
+///
+/// The schemas use a subset of JSON Schema: `type`, `const`, `enum`, `pattern`, `properties`,
+/// `required`, `additionalProperties`, `items` and local `$ref`s to `$defs`.
+///
+/// # Arguments
+///
+/// * `kind` - The kind of the manifest.
+/// * `manifest` - The content of the manifest.
+///
+/// # Returns
+///
+/// Returns the violations of the schema, each prefixed by the JSON pointer of the value, empty
+/// for a valid manifest.
+///
+/// # Errors
+///
+/// Returns an error if the manifest declares no schema version, or one without a schema.
+pub fn validate(kind: ManifestKind, manifest: &Value) -> Result<Vec<String>> {
+    let version = manifest
+        .get("schema_version")
+        .and_then(Value::as_str)
+        .ok_or_else(|| Error::InvalidManifest("missing schema_version".to_string()))?;
+    let document = json_schema(kind, version).ok_or_else(|| {
+        Error::InvalidManifest(format!(
+            "no {} schema for version {}",
+            kind.as_str(),
+            version
+        ))
+    })?;
+    let schema: Value = serde_json::from_str(document)?;
+    let mut violations = Vec::new();
+    check(manifest, &schema, &schema, "", &mut violations);
+    Ok(violations)
+}
+
+// Check a value against a schema, recording the violations found under its pointer.
+fn check(value: &Value, schema: &Value, root: &Value, pointer: &str, violations: &mut Vec<String>) {
+    let here = if pointer.is_empty() { "/" } else { pointer };
+
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        match reference
+            .strip_prefix('#')
+            .and_then(|pointer| root.pointer(pointer))
+        {
+            Some(target) => check(value, target, root, pointer, violations),
+            None => violations.push(format!("{}: unresolved reference {}", here, reference)),
+        }
+        return;
+    }
+
+    if let Some(types) = schema.get("type") {
+        let types: Vec<&str> = match types {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !types.iter().any(|name| has_type(value, name)) {
+            violations.push(format!(
+                "{}: expected {}, found {}",
+                here,
+                types.join(" or "),
+                value
+            ));
+            return;
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if value != expected {
+            violations.push(format!("{}: expected {}, found {}", here, expected, value));
+        }
+    }
+    if let Some(Value::Array(allowed)) = schema.get("enum") {
+        if !allowed.contains(value) {
+            violations.push(format!(
+                "{}: {} is not one of {}",
+                here,
+                value,
+                Value::from(allowed.clone())
+            ));
+        }
+    }
+    if let (Some(pattern), Value::String(string)) =
+        (schema.get("pattern").and_then(Value::as_str), value)
+    {
+        if !Regex::new(pattern).is_ok_and(|regex| regex.is_match(string)) {
+            violations.push(format!("{}: {:?} does not match {}", here, string, pattern));
+        }
+    }
+
+    if let Value::Object(map) = value {
+        for name in schema
+            .get("required")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+        {
+            if !map.contains_key(name) {
+                violations.push(format!("{}: missing field {:?}", here, name));
+            }
+        }
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for (name, field) in map {
+            let field_pointer =
+                format!("{}/{}", pointer, name.replace('~', "~0").replace('/', "~1"));
+            match (
+                properties.and_then(|properties| properties.get(name)),
+                schema.get("additionalProperties"),
+            ) {
+                (Some(field_schema), _) => {
+                    check(field, field_schema, root, &field_pointer, violations)
+                }
+                (None, Some(Value::Bool(false))) => {
+                    violations.push(format!("{}: unexpected field", field_pointer))
+                }
+                (None, Some(additional @ Value::Object(_))) => {
+                    check(field, additional, root, &field_pointer, violations)
+                }
+                (None, _) => {}
+            }
+        }
+    }
+    if let (Value::Array(items), Some(item_schema)) = (value, schema.get("items")) {
+        for (index, item) in items.iter().enumerate() {
+            check(
+                item,
+                item_schema,
+                root,
+                &format!("{}/{}", pointer, index),
+                violations,
+            );
+        }
+    }
+}
+
+// Whether a value has a JSON Schema type.
+fn has_type(value: &Value, name: &str) -> bool {
+    match name {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "string" => value.is_string(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schemas_are_json() {
+        for (kind, version, document) in SCHEMAS {
+            let schema: Value = serde_json::from_str(document).unwrap();
+            assert_eq!(
+                schema.pointer("/properties/schema_version/const"),
+                Some(&Value::from(version)),
+                "{}",
+                kind.as_str()
+            );
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut manifest = serde_json::json!({
+            "schema_version": SCHEMA_VERSION,
+            "Public APIs flow": [
+                { "name": "accessNetwork", "syscalls": ["curl_easy_init"], "recursion": true },
+            ],
+        });
+        assert!(validate(ManifestKind::FlowCall, &manifest)
+            .unwrap()
+            .is_empty());
+
+        manifest["Public APIs flow"][0]["syscalls"] = serde_json::json!([1]);
+        manifest["Public APIs flow"][0]
+            .as_object_mut()
+            .unwrap()
+            .remove("name");
+        assert_eq!(
+            validate(ManifestKind::FlowCall, &manifest).unwrap(),
+            [
+                "/Public APIs flow/0: missing field \"name\"",
+                "/Public APIs flow/0/syscalls/0: expected string, found 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_version() {
+        let feature = serde_json::json!({ "accessNetwork": ["Network Access"] });
+        assert!(validate(ManifestKind::Feature, &feature).is_err());
+        let feature = serde_json::json!({ "schema_version": "0.1" });
+        assert!(validate(ManifestKind::Feature, &feature).is_err());
+        let feature = serde_json::json!({
+            "schema_version": SCHEMA_VERSION,
+            "accessNetwork": ["Network Access"],
+            "dangerous calls": [{
+                "function": "strcpy",
+                "call site": "1f40",
+                "caller": "main",
+                "reached from": [],
+            }],
+        });
+        assert_eq!(
+            validate(ManifestKind::Feature, &feature).unwrap(),
+            ["/dangerous calls/0/call site: \"1f40\" does not match ^0x[0-9a-f]+$"]
+        );
+    }
+}
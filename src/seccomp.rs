@@ -0,0 +1,260 @@
+use std::collections::BTreeSet;
+
+use goblin::elf::{
+    header::{EM_AARCH64, EM_ARM, EM_X86_64},
+    Elf,
+};
+use serde_json::{json, Value};
+
+use crate::{
+    call_graph::CallGraph,
+    correlation::{predicted_syscalls, RUNTIME_SYSCALLS},
+    dynamic_analysis::ObservedBehavior,
+    elf_utils::get_arch,
+    error::{Error, Result},
+};
+
+// The syscalls any process may need besides those of the binary: returning from a signal
+// handler and restarting an interrupted syscall.
+const KERNEL_SYSCALLS: [&str; 2] = ["rt_sigreturn", "restart_syscall"];
+
+// The BPF instructions of the filter: load a word of `struct seccomp_data`, compare the
+// accumulator with a constant, and return a verdict.
+const BPF_LD_W_ABS: u16 = 0x20;
+const BPF_JMP_JEQ_K: u16 = 0x15;
+const BPF_RET_K: u16 = 0x06;
+
+// The offsets of the syscall number and of the architecture in `struct seccomp_data`.
+const SECCOMP_DATA_NR: u32 = 0;
+const SECCOMP_DATA_ARCH: u32 = 4;
+
+// The verdicts of the filter.
+const SECCOMP_RET_KILL_PROCESS: u32 = 0x8000_0000;
+const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
+const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
+const EPERM: u32 = 1;
+
+/// A seccomp allowlist of the syscalls a binary may make, every other syscall failing with
+/// `EPERM`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SeccompProfile {
+    /// The architecture of the binary, as named by libseccomp, e.g. `SCMP_ARCH_X86_64`.
+    pub arch: &'static str,
+    /// The `AUDIT_ARCH_*` value of the architecture, checked by the BPF filter.
+    pub audit_arch: u32,
+    /// The syscalls allowed, sorted by name.
+    pub syscalls: BTreeSet<String>,
+}
+
+impl SeccompProfile {
+    /// Build the allowlist of a binary: the syscalls predicted from its calls, those of the
+    /// dynamic loader and of the C runtime, of the returns from signal handlers, and, after a
+    /// dynamic analysis, those observed while it ran.
+    ///
+    /// The syscalls made through an indirect call or a raw `syscall` instruction are not
+    /// predicted: the profile should be checked by running the binary under it.
+    ///
+    /// # Arguments
+    ///
+    /// * `elf` - The ELF object representing the binary.
+    /// * `graph` - The call graph of the binary.
+    /// * `observed` - What the binary did while it ran, for a dynamic analysis.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::UnsupportedArch`] for an architecture other than x86-64, little-endian
+    /// AArch64 and little-endian ARM, the big-endian ones having no libseccomp name.
+    pub fn new(elf: &Elf, graph: &CallGraph, observed: Option<&ObservedBehavior>) -> Result<Self> {
+        let (arch, audit_arch) = match elf.header.e_machine {
+            EM_X86_64 => ("SCMP_ARCH_X86_64", 0xc000_003e),
+            EM_AARCH64 if elf.little_endian => ("SCMP_ARCH_AARCH64", 0xc000_00b7),
+            EM_ARM if elf.little_endian => ("SCMP_ARCH_ARM", 0x4000_0028),
+            EM_AARCH64 | EM_ARM => {
+                return Err(Error::UnsupportedArch(format!(
+                    "big-endian {}, no seccomp profile",
+                    get_arch(elf)?
+                )))
+            }
+            _ => {
+                return Err(Error::UnsupportedArch(format!(
+                    "{}, no seccomp profile",
+                    get_arch(elf)?
+                )))
+            }
+        };
+        let mut syscalls: BTreeSet<String> = predicted_syscalls(graph)
+            .into_keys()
+            .chain(RUNTIME_SYSCALLS)
+            .chain(KERNEL_SYSCALLS)
+            .map(str::to_string)
+            .collect();
+        if let Some(observed) = observed {
+            syscalls.extend(observed.syscalls().into_iter().map(str::to_string));
+        }
+        let machine = elf.header.e_machine;
+        syscalls.retain(|name| syscall_number(machine, name).is_some() || !has_table());
+        Ok(Self {
+            arch,
+            audit_arch,
+            syscalls,
+        })
+    }
+
+    /// Return the profile in the `linux.seccomp` format of the OCI runtime specification, as
+    /// read by runc, crun, Docker and Podman.
+    pub fn to_oci(&self) -> Value {
+        json!({
+            "defaultAction": "SCMP_ACT_ERRNO",
+            "defaultErrnoRet": EPERM,
+            "architectures": [self.arch],
+            "syscalls": [{
+                "names": self.syscalls,
+                "action": "SCMP_ACT_ALLOW",
+            }],
+        })
+    }
+
+    /// Return the profile as a classic BPF program, to install with
+    /// `prctl(PR_SET_SECCOMP, SECCOMP_MODE_FILTER, ...)` or `seccomp(2)`: an array of
+    /// `struct sock_filter` in the byte order of the binary.
+    ///
+    /// The filter kills the process calling from another architecture, allows the syscalls of
+    /// the profile and makes the others fail with `EPERM`.
+    ///
+    /// # Returns
+    ///
+    /// Returns the program, `None` without the syscall tables of the `native` feature.
+    pub fn to_bpf(&self, elf: &Elf) -> Option<Vec<u8>> {
+        if !has_table() {
+            return None;
+        }
+        let machine = elf.header.e_machine;
+        let mut program = vec![
+            (BPF_LD_W_ABS, 0, 0, SECCOMP_DATA_ARCH),
+            (BPF_JMP_JEQ_K, 1, 0, self.audit_arch),
+            (BPF_RET_K, 0, 0, SECCOMP_RET_KILL_PROCESS),
+            (BPF_LD_W_ABS, 0, 0, SECCOMP_DATA_NR),
+        ];
+        let mut numbers: Vec<u32> = self
+            .syscalls
+            .iter()
+            .filter_map(|name| syscall_number(machine, name))
+            .collect();
+        numbers.sort_unstable();
+        for number in numbers {
+            program.push((BPF_JMP_JEQ_K, 0, 1, number));
+            program.push((BPF_RET_K, 0, 0, SECCOMP_RET_ALLOW));
+        }
+        program.push((BPF_RET_K, 0, 0, SECCOMP_RET_ERRNO | EPERM));
+
+        let mut bytes = Vec::with_capacity(program.len() * 8);
+        for (code, jt, jf, k) in program {
+            match elf.little_endian {
+                true => bytes.extend(code.to_le_bytes()),
+                false => bytes.extend(code.to_be_bytes()),
+            }
+            bytes.extend([jt, jf]);
+            match elf.little_endian {
+                true => bytes.extend(k.to_le_bytes()),
+                false => bytes.extend(k.to_be_bytes()),
+            }
+        }
+        Some(bytes)
+    }
+}
+
+// Whether the syscall numbers of the architectures are known.
+fn has_table() -> bool {
+    cfg!(feature = "native")
+}
+
+// The number of a syscall on the architecture of an ELF machine.
+#[cfg(feature = "native")]
+fn syscall_number(machine: u16, name: &str) -> Option<u32> {
+    let id = match machine {
+        EM_X86_64 => name.parse::<syscalls::x86_64::Sysno>().ok()?.id(),
+        EM_AARCH64 => name.parse::<syscalls::aarch64::Sysno>().ok()?.id(),
+        EM_ARM => name.parse::<syscalls::arm::Sysno>().ok()?.id(),
+        _ => return None,
+    };
+    u32::try_from(id).ok()
+}
+
+// Without the syscall tables, no number is known and the names are kept as predicted.
+#[cfg(not(feature = "native"))]
+fn syscall_number(_machine: u16, _name: &str) -> Option<u32> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf_utils::read_elf_file;
+
+    #[test]
+    fn test_seccomp_profile() {
+        let buffer = read_elf_file("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        let graph = CallGraph::build(&elf, &buffer, false).unwrap();
+        let profile = SeccompProfile::new(&elf, &graph, None).unwrap();
+        assert_eq!(profile.arch, "SCMP_ARCH_X86_64");
+        // fopen64, the start-up of the process and the returns from the handlers.
+        for syscall in ["openat", "execve", "exit_group", "rt_sigreturn"] {
+            assert!(profile.syscalls.contains(syscall), "{}", syscall);
+        }
+
+        let oci = profile.to_oci();
+        assert_eq!(oci["defaultAction"], "SCMP_ACT_ERRNO");
+        assert_eq!(oci["architectures"], json!(["SCMP_ARCH_X86_64"]));
+        assert_eq!(oci["syscalls"][0]["action"], "SCMP_ACT_ALLOW");
+        assert_eq!(
+            oci["syscalls"][0]["names"].as_array().unwrap().len(),
+            profile.syscalls.len()
+        );
+
+        let bpf = profile.to_bpf(&elf).unwrap();
+        let instruction = |index: usize| {
+            let bytes = &bpf[index * 8..index * 8 + 8];
+            (
+                u16::from_le_bytes([bytes[0], bytes[1]]),
+                bytes[2],
+                bytes[3],
+                u32::from_le_bytes(bytes[4..].try_into().unwrap()),
+            )
+        };
+        assert_eq!(bpf.len(), (5 + 2 * profile.syscalls.len()) * 8);
+        assert_eq!(instruction(1), (BPF_JMP_JEQ_K, 1, 0, 0xc000_003e));
+        // read is syscall 0 of x86-64, the first one compared.
+        assert_eq!(instruction(4), (BPF_JMP_JEQ_K, 0, 1, 0));
+        assert_eq!(instruction(5), (BPF_RET_K, 0, 0, SECCOMP_RET_ALLOW));
+        let last = bpf.len() / 8 - 1;
+        assert_eq!(
+            instruction(last),
+            (BPF_RET_K, 0, 0, SECCOMP_RET_ERRNO | EPERM)
+        );
+    }
+
+    #[test]
+    fn test_seccomp_profile_endianness() {
+        let buffer = read_elf_file("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let mut elf = Elf::parse(&buffer).unwrap();
+        let graph = CallGraph::build(&elf, &buffer, false).unwrap();
+
+        elf.header.e_machine = EM_ARM;
+        let profile = SeccompProfile::new(&elf, &graph, None).unwrap();
+        assert_eq!(profile.audit_arch, 0x4000_0028);
+
+        // The audit values of the big-endian ARM architectures differ from the little-endian
+        // ones, and libseccomp has no name for them.
+        elf.little_endian = false;
+        assert!(matches!(
+            SeccompProfile::new(&elf, &graph, None),
+            Err(Error::UnsupportedArch(_))
+        ));
+        elf.header.e_machine = EM_AARCH64;
+        assert!(matches!(
+            SeccompProfile::new(&elf, &graph, None),
+            Err(Error::UnsupportedArch(_))
+        ));
+    }
+}
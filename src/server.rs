@@ -0,0 +1,662 @@
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    env, fs,
+    io::Read,
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    process,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{sync_channel, Receiver, SyncSender, TrySendError},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response};
+use tracing::{info, warn};
+
+use crate::{
+    analyzer::{AnalysisMode, Analyzer, Options},
+    api_detection::{parse_api_list, ApiSpec},
+    config::Config,
+    elf_utils::sha256_hex,
+    error::{Error, Result},
+    manifest_creation::collect_manifests,
+    progress::Verbosity,
+};
+
+// Number of binaries analyzed by the process, to name their scratch directories.
+static UPLOADS: AtomicUsize = AtomicUsize::new(0);
+
+// Number of threads reading the requests, so that a slow upload does not hold the others.
+const HTTP_THREADS: usize = 4;
+
+// The keys of the configuration a request may set in its `options` field; the others name files
+// of the server and are left to its own configuration.
+const REQUEST_OPTIONS: [&str; 4] = ["apis", "outputs", "arch", "max_depth"];
+
+/// The settings of the analysis server.
+#[derive(Clone)]
+pub struct ServerConfig {
+    /// The address to listen on, e.g. `127.0.0.1:8080`.
+    pub bind: String,
+    /// The number of analyses run at once.
+    pub workers: usize,
+    /// The number of analyses waiting for a worker past which uploads are refused.
+    pub queue: usize,
+    /// The size of the largest upload accepted, in bytes.
+    pub max_upload: u64,
+    /// The number of finished analyses whose results are kept, the oldest dropped first.
+    pub keep: usize,
+    /// The options of every analysis, on top of which the requests set theirs.
+    pub options: Options,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind: "127.0.0.1:8080".to_string(),
+            workers: thread::available_parallelism().map_or(1, |n| n.get()),
+            queue: 16,
+            max_upload: 256 << 20,
+            keep: 1000,
+            options: Options {
+                verbosity: Verbosity::Quiet,
+                ..Options::default()
+            },
+        }
+    }
+}
+
+// The state of an analysis submitted to the server.
+enum JobState {
+    Queued,
+    Running,
+    Done(Value),
+    Failed(u16, Value),
+}
+
+// An analysis waiting for a worker.
+struct Job {
+    id: String,
+    name: String,
+    bytes: Vec<u8>,
+    specs: Vec<ApiSpec>,
+    options: Options,
+}
+
+// The analyses submitted, by identifier.
+#[derive(Default)]
+struct Jobs {
+    next: u64,
+    states: HashMap<String, JobState>,
+    finished: VecDeque<String>,
+}
+
+/// An HTTP server analyzing the ELF binaries uploaded to it in a bounded pool of workers.
+///
+/// - `POST /analyze` takes a `multipart/form-data` body with the binary in its `elf` field, the
+///   JSON API list in its `apis` field and, optionally, a JSON document with the `outputs`,
+///   `arch` and `max_depth` keys of the configuration file in its `options` field. It answers
+///   `202 Accepted` with the identifier of the analysis, or `503` when the queue is full.
+/// - `GET /manifests/{id}` answers `202` while the analysis runs, then `200` with its manifests
+///   by name, or the status of its error with its description.
+/// - `GET /manifests/{id}/{name}` answers one manifest, e.g. `flow_call`.
+/// - `GET /health` answers the number of workers and of analyses waiting.
+pub struct Server {
+    http: tiny_http::Server,
+    config: ServerConfig,
+    jobs: Arc<Mutex<Jobs>>,
+    sender: SyncSender<Job>,
+}
+
+impl Server {
+    /// Listen on the address of the configuration and start the workers.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`] if the address cannot be listened on.
+    pub fn bind(config: ServerConfig) -> Result<Self> {
+        let http = tiny_http::Server::http(&config.bind)
+            .map_err(|error| Error::Io(std::io::Error::other(error.to_string())))?;
+        let jobs = Arc::new(Mutex::new(Jobs::default()));
+        let (sender, receiver) = sync_channel(config.queue);
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..config.workers.max(1) {
+            let (jobs, receiver, keep) = (jobs.clone(), receiver.clone(), config.keep);
+            thread::spawn(move || work(&jobs, &receiver, keep));
+        }
+        Ok(Self {
+            http,
+            config,
+            jobs,
+            sender,
+        })
+    }
+
+    /// The address the server listens on, e.g. `127.0.0.1:8080`.
+    pub fn addr(&self) -> String {
+        self.http.server_addr().to_string()
+    }
+
+    /// Answer the requests until [`Server::shutdown`] is called.
+    pub fn run(&self) {
+        info!(addr = %self.addr(), workers = self.config.workers, "listening");
+        thread::scope(|scope| {
+            for _ in 0..HTTP_THREADS {
+                scope.spawn(|| {
+                    while let Ok(request) = self.http.recv() {
+                        self.handle(request);
+                    }
+                });
+            }
+        });
+    }
+
+    /// Stop answering the requests, making [`Server::run`] return.
+    pub fn shutdown(&self) {
+        for _ in 0..HTTP_THREADS {
+            self.http.unblock();
+        }
+    }
+
+    // Route a request and send its response.
+    fn handle(&self, mut request: Request) {
+        let url = request.url().to_string();
+        let path = url.split('?').next().unwrap_or_default();
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        let (status, body) = match (request.method(), segments.as_slice()) {
+            (Method::Post, ["analyze"]) => self.submit(&mut request),
+            (Method::Get, ["manifests", id]) => self.result(id, None),
+            (Method::Get, ["manifests", id, name]) => self.result(id, Some(name)),
+            (Method::Get, ["health"]) => {
+                let queued = self.lock_jobs().waiting();
+                (
+                    200,
+                    json!({"status": "ok", "workers": self.config.workers, "queued": queued}),
+                )
+            }
+            (_, ["analyze"] | ["manifests", ..] | ["health"]) => {
+                (405, json!({"error": "method-not-allowed"}))
+            }
+            _ => (404, json!({"error": "not-found"})),
+        };
+        let mut response = Response::from_string(body.to_string())
+            .with_status_code(status)
+            .with_header(header("Content-Type", "application/json"));
+        if status == 202 {
+            if let Some(id) = body["id"].as_str() {
+                response.add_header(header("Location", &format!("/manifests/{}", id)));
+            }
+        }
+        if let Err(error) = request.respond(response) {
+            warn!(%error, "the response could not be sent");
+        }
+    }
+
+    // Queue the analysis of an uploaded binary.
+    fn submit(&self, request: &mut Request) -> (u16, Value) {
+        let job = match self.read_job(request) {
+            Ok(job) => job,
+            Err((status, error)) => return (status, json!({"error": error.to_json()})),
+        };
+        let id = job.id.clone();
+        self.lock_jobs().states.insert(id.clone(), JobState::Queued);
+        match self.sender.try_send(job) {
+            Ok(()) => {
+                info!(id, "analysis queued");
+                (202, json!({"id": id, "status": "queued"}))
+            }
+            Err(TrySendError::Full(job) | TrySendError::Disconnected(job)) => {
+                self.lock_jobs().states.remove(&job.id);
+                (503, json!({"error": "queue-full"}))
+            }
+        }
+    }
+
+    // Read the binary, the API list and the options of an upload.
+    fn read_job(&self, request: &mut Request) -> std::result::Result<Job, (u16, Error)> {
+        let invalid = |message: &str| (400, Error::InvalidRequest(message.to_string()));
+        let content_type = request
+            .headers()
+            .iter()
+            .find(|header| header.field.equiv("Content-Type"))
+            .map(|header| header.value.to_string())
+            .unwrap_or_default();
+        let boundary = boundary(&content_type)
+            .ok_or_else(|| invalid("the body is not multipart/form-data"))?;
+        if request
+            .body_length()
+            .is_some_and(|len| len as u64 > self.config.max_upload)
+        {
+            return Err((
+                413,
+                Error::InvalidRequest("the upload is too large".to_string()),
+            ));
+        }
+        let mut body = Vec::new();
+        request
+            .as_reader()
+            .take(self.config.max_upload + 1)
+            .read_to_end(&mut body)
+            .map_err(|error| (400, Error::Io(error)))?;
+        if body.len() as u64 > self.config.max_upload {
+            return Err((
+                413,
+                Error::InvalidRequest("the upload is too large".to_string()),
+            ));
+        }
+
+        let parts = parse_multipart(&body, &boundary).map_err(|error| (400, error))?;
+        let field = |name: &str| parts.iter().find(|part| part.name == name);
+        let elf = field("elf").ok_or_else(|| invalid("no elf field"))?;
+        let options: Value = match field("options") {
+            Some(part) => {
+                serde_json::from_slice(&part.data).map_err(|error| (400, error.into()))?
+            }
+            None => json!({}),
+        };
+        let (options, apis) =
+            request_options(&self.config.options, &options).map_err(|error| (400, error))?;
+        let specs = match (field("apis"), apis) {
+            (Some(part), _) => serde_json::from_slice(&part.data)
+                .map_err(Error::from)
+                .and_then(|apis| parse_api_list(&apis))
+                .map_err(|error| (400, error))?,
+            (None, Some(apis)) => apis,
+            (None, None) => return Err(invalid("no apis field")),
+        };
+
+        let name = elf
+            .filename
+            .as_deref()
+            .and_then(|name| Path::new(name).file_name())
+            .map_or("upload".to_string(), |name| {
+                name.to_string_lossy().to_string()
+            });
+        let id = {
+            let mut jobs = self.lock_jobs();
+            jobs.next += 1;
+            format!("{}-{}", &sha256_hex(&elf.data)[..16], jobs.next)
+        };
+        Ok(Job {
+            id,
+            name,
+            bytes: elf.data.clone(),
+            specs,
+            options,
+        })
+    }
+
+    // Answer the state of an analysis, or one of its manifests.
+    fn result(&self, id: &str, name: Option<&str>) -> (u16, Value) {
+        let jobs = self.lock_jobs();
+        match (jobs.states.get(id), name) {
+            (None, _) => (404, json!({"error": "not-found", "id": id})),
+            (Some(JobState::Queued), _) => (202, json!({"id": id, "status": "queued"})),
+            (Some(JobState::Running), _) => (202, json!({"id": id, "status": "running"})),
+            (Some(JobState::Failed(status, error)), _) => (
+                *status,
+                json!({"id": id, "status": "failed", "error": error}),
+            ),
+            (Some(JobState::Done(manifests)), None) => (
+                200,
+                json!({"id": id, "status": "done", "manifests": manifests}),
+            ),
+            (Some(JobState::Done(manifests)), Some(name)) => match manifests.get(name) {
+                Some(manifest) => (200, manifest.clone()),
+                None => (
+                    404,
+                    json!({"error": "not-found", "id": id, "manifest": name}),
+                ),
+            },
+        }
+    }
+
+    // Lock the table of the analyses, even if a thread panicked while holding it.
+    fn lock_jobs(&self) -> std::sync::MutexGuard<'_, Jobs> {
+        self.jobs
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Jobs {
+    // The number of analyses waiting for a worker.
+    fn waiting(&self) -> usize {
+        self.states
+            .values()
+            .filter(|state| matches!(state, JobState::Queued))
+            .count()
+    }
+
+    // Record the outcome of an analysis, dropping the oldest outcomes past `keep`.
+    fn finish(&mut self, id: String, state: JobState, keep: usize) {
+        self.states.insert(id.clone(), state);
+        self.finished.push_back(id);
+        while self.finished.len() > keep {
+            if let Some(oldest) = self.finished.pop_front() {
+                self.states.remove(&oldest);
+            }
+        }
+    }
+}
+
+// Run the analyses of the queue, one at a time, until the server is dropped.
+fn work(jobs: &Mutex<Jobs>, receiver: &Mutex<Receiver<Job>>, keep: usize) {
+    let lock = || jobs.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    loop {
+        let job = match receiver.lock() {
+            Ok(receiver) => receiver.recv(),
+            Err(_) => return,
+        };
+        let Ok(job) = job else {
+            return;
+        };
+        let id = job.id.clone();
+        lock().states.insert(id.clone(), JobState::Running);
+        let state = match panic::catch_unwind(AssertUnwindSafe(|| analyze(job))) {
+            Ok(Ok(manifests)) => {
+                info!(id, "analysis done");
+                JobState::Done(manifests)
+            }
+            Ok(Err(error)) => {
+                info!(id, %error, "analysis failed");
+                JobState::Failed(status(&error), error.to_json())
+            }
+            Err(_) => {
+                warn!(id, "analysis panicked");
+                JobState::Failed(
+                    500,
+                    json!({"error": "panic", "message": "the analysis panicked"}),
+                )
+            }
+        };
+        lock().finish(id, state, keep);
+    }
+}
+
+// Analyze an uploaded binary and return its manifests.
+fn analyze(job: Job) -> Result<Value> {
+    Ok(json!(analyze_upload(
+        &job.name,
+        job.bytes,
+        job.specs,
+        job.options
+    )?))
+}
+
+/// Read the options set by a request to a server: a JSON object with the `apis`, `outputs`,
+/// `arch` and `max_depth` keys of the configuration file, on top of the options of the server.
+///
+/// # Returns
+///
+/// Returns a `Result` containing the options of the analysis and the APIs given, if any.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidRequest`] if another key is given, e.g. `output_dir`, which names a
+/// file of the server.
+pub(crate) fn request_options(
+    base: &Options,
+    options: &Value,
+) -> Result<(Options, Option<Vec<ApiSpec>>)> {
+    if let Some(key) = options.as_object().and_then(|options| {
+        options
+            .keys()
+            .find(|key| !REQUEST_OPTIONS.contains(&key.as_str()))
+    }) {
+        return Err(Error::InvalidRequest(format!(
+            "the option {} cannot be set by a request",
+            key
+        )));
+    }
+    let config = Config::from_json(options, Path::new(""))?;
+    let request = Options::from_config(&config)?;
+    let options = Options {
+        outputs: match config.outputs.is_empty() {
+            true => base.outputs,
+            false => request.outputs,
+        },
+        arch: request.arch.or_else(|| base.arch.clone()),
+        max_depth: request.max_depth.or(base.max_depth),
+        // The binaries uploaded are never run.
+        analysis: AnalysisMode::Static,
+        ..base.clone()
+    };
+    Ok((options, config.apis))
+}
+
+/// Analyze a binary uploaded to a server in a scratch directory, and return its manifests by
+/// name.
+pub(crate) fn analyze_upload(
+    name: &str,
+    bytes: Vec<u8>,
+    specs: Vec<ApiSpec>,
+    options: Options,
+) -> Result<BTreeMap<String, Value>> {
+    let dir = env::temp_dir().join(format!(
+        "manifest-serve-{}-{}",
+        process::id(),
+        UPLOADS.fetch_add(1, Ordering::Relaxed)
+    ));
+    let dir = dir.to_string_lossy().to_string();
+    fs::create_dir_all(&dir)?;
+    let manifests = Analyzer::from_bytes(name, bytes)
+        .with_api_list(specs)
+        .with_options(options)
+        .run(&dir)
+        .and_then(|_| collect_manifests(&dir));
+    // The scratch directory is left behind rather than losing the result of the analysis.
+    if let Err(error) = fs::remove_dir_all(&dir) {
+        warn!(%error, dir, "the scratch directory could not be removed");
+    }
+    manifests
+}
+
+// The HTTP status of a failed analysis: the request was malformed, or its binary cannot be
+// analyzed, or the server failed.
+fn status(error: &Error) -> u16 {
+    match error.kind() {
+        "invalid-input" => 400,
+        "io" | "error" => 500,
+        _ => 422,
+    }
+}
+
+// Build a header from ASCII strings.
+fn header(field: &str, value: &str) -> Header {
+    Header::from_bytes(field.as_bytes(), value.as_bytes()).expect("ASCII header")
+}
+
+// A field of a multipart/form-data body.
+struct Part {
+    name: String,
+    filename: Option<String>,
+    data: Vec<u8>,
+}
+
+// Read the boundary of a multipart/form-data content type.
+fn boundary(content_type: &str) -> Option<String> {
+    let (kind, params) = content_type.split_once(';')?;
+    if !kind.trim().eq_ignore_ascii_case("multipart/form-data") {
+        return None;
+    }
+    params.split(';').find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        (key.trim() == "boundary").then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+// Split a multipart/form-data body into its fields.
+fn parse_multipart(body: &[u8], boundary: &str) -> Result<Vec<Part>> {
+    let malformed = || Error::InvalidRequest("malformed multipart body".to_string());
+    let delimiter = format!("--{}", boundary).into_bytes();
+    let mut rest = &body[find(body, &delimiter).ok_or_else(malformed)? + delimiter.len()..];
+    let mut parts = Vec::new();
+    while !rest.starts_with(b"--") {
+        rest = rest.strip_prefix(b"\r\n").ok_or_else(malformed)?;
+        let headers_end = find(rest, b"\r\n\r\n").ok_or_else(malformed)?;
+        let headers = String::from_utf8_lossy(&rest[..headers_end]).to_string();
+        rest = &rest[headers_end + 4..];
+        let end = find(rest, &[b"\r\n".as_slice(), &delimiter].concat()).ok_or_else(malformed)?;
+        let disposition = headers
+            .lines()
+            .find(|line| {
+                line.to_ascii_lowercase()
+                    .starts_with("content-disposition:")
+            })
+            .ok_or_else(malformed)?;
+        let param = |key: &str| {
+            disposition.split(';').find_map(|param| {
+                let (name, value) = param.split_once('=')?;
+                (name.trim() == key).then(|| value.trim().trim_matches('"').to_string())
+            })
+        };
+        parts.push(Part {
+            name: param("name").ok_or_else(malformed)?,
+            filename: param("filename"),
+            data: rest[..end].to_vec(),
+        });
+        rest = &rest[end + 2 + delimiter.len()..];
+    }
+    Ok(parts)
+}
+
+// The position of the first occurrence of `needle` in `haystack`.
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{io::Write, net::TcpStream, time::Duration};
+
+    // Send a request to the server and return the status and the body of its response.
+    fn send(addr: &str, request: &[u8]) -> (u16, Value) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(request).unwrap();
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).unwrap();
+        let response = String::from_utf8_lossy(&response).to_string();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+        let body = match head
+            .to_ascii_lowercase()
+            .contains("transfer-encoding: chunked")
+        {
+            true => dechunk(body),
+            false => body.to_string(),
+        };
+        (status, serde_json::from_str(&body).unwrap_or(Value::Null))
+    }
+
+    // Join the chunks of a body sent with the chunked transfer encoding, as the large ones are.
+    fn dechunk(mut body: &str) -> String {
+        let mut joined = String::new();
+        while let Some((size, rest)) = body.split_once("\r\n") {
+            let size = usize::from_str_radix(size.trim(), 16).unwrap();
+            if size == 0 {
+                break;
+            }
+            joined.push_str(&rest[..size]);
+            body = &rest[size + 2..];
+        }
+        joined
+    }
+
+    // An upload of a binary with an API list.
+    fn upload(elf: &[u8], apis: &str) -> Vec<u8> {
+        let mut body = Vec::new();
+        body.extend_from_slice(
+            b"--XyZ\r\nContent-Disposition: form-data; name=\"elf\"; filename=\"fw.elf\"\r\n\r\n",
+        );
+        body.extend_from_slice(elf);
+        body.extend_from_slice(
+            b"\r\n--XyZ\r\nContent-Disposition: form-data; name=\"apis\"\r\n\r\n",
+        );
+        body.extend_from_slice(apis.as_bytes());
+        body.extend_from_slice(b"\r\n--XyZ--\r\n");
+        let mut request = format!(
+            "POST /analyze HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\
+             Content-Type: multipart/form-data; boundary=XyZ\r\nContent-Length: {}\r\n\r\n",
+            body.len()
+        )
+        .into_bytes();
+        request.extend_from_slice(&body);
+        request
+    }
+
+    #[test]
+    fn test_server() {
+        let server = Server::bind(ServerConfig {
+            bind: "127.0.0.1:0".to_string(),
+            workers: 1,
+            ..ServerConfig::default()
+        })
+        .unwrap();
+        let addr = server.addr();
+        thread::scope(|scope| {
+            scope.spawn(|| server.run());
+
+            let elf = fs::read("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+            let (status, queued) = send(&addr, &upload(&elf, r#"["writeOnDrive"]"#));
+            assert_eq!(status, 202);
+            let get = format!(
+                "GET /manifests/{} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+                queued["id"].as_str().unwrap()
+            );
+            let done = loop {
+                let (status, body) = send(&addr, get.as_bytes());
+                if status != 202 {
+                    assert_eq!(status, 200);
+                    break body;
+                }
+                thread::sleep(Duration::from_millis(50));
+            };
+            assert_eq!(done["manifests"]["basic_info"]["file_name"], "fw.elf");
+            assert_eq!(
+                done["manifests"]["flow_call"]["Public APIs flow"][0]["name"],
+                "writeOnDrive"
+            );
+
+            let (status, _) = send(&addr, &upload(b"not an ELF", r#"["main"]"#));
+            assert_eq!(status, 202);
+            let (status, _) = send(&addr, &upload(&elf, "writeOnDrive"));
+            assert_eq!(status, 400);
+            let (status, _) = send(
+                &addr,
+                b"GET /manifests/missing HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+            );
+            assert_eq!(status, 404);
+            server.shutdown();
+        });
+    }
+
+    #[test]
+    fn test_parse_multipart() {
+        assert_eq!(
+            boundary("multipart/form-data; boundary=\"a b\"").as_deref(),
+            Some("a b")
+        );
+        assert_eq!(boundary("application/json"), None);
+        let body =
+            b"preamble\r\n--b\r\nContent-Disposition: form-data; name=\"x\"\r\n\r\n1\r\n--b\r\n\
+            content-disposition: form-data; name=\"f\"; filename=\"../a.elf\"\r\n\
+            Content-Type: application/octet-stream\r\n\r\n\x7fELF\r\n\r\n--b--\r\n";
+        let parts = parse_multipart(body, "b").unwrap();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(
+            (parts[0].name.as_str(), parts[0].data.as_slice()),
+            ("x", b"1".as_slice())
+        );
+        assert_eq!(parts[1].filename.as_deref(), Some("../a.elf"));
+        assert_eq!(parts[1].data, b"\x7fELF\r\n");
+        assert!(parse_multipart(b"--b\r\nno headers", "b").is_err());
+    }
+}
@@ -0,0 +1,311 @@
+use std::collections::HashMap;
+
+use goblin::elf::Elf;
+use serde_json::{json, Value};
+
+use crate::{
+    call_graph::CallGraph,
+    cleanup::demangle_api_name,
+    disassembler::Disassembler,
+    elf_utils::{code_bytes, API},
+    error::Result,
+    indirect_calls::{immediate, memory_operand, register, PointerResolver, RegisterState},
+};
+
+/// The functions registering signal handlers: `true` for those taking a `struct sigaction`,
+/// whose first field is the handler, `false` for those taking the handler itself.
+pub const SIGNAL_FUNCTIONS: [(&str, bool); 8] = [
+    ("signal", false),
+    ("bsd_signal", false),
+    ("sysv_signal", false),
+    ("__sysv_signal", false),
+    ("sigset", false),
+    ("sigaction", true),
+    ("__sigaction", true),
+    ("__libc_sigaction", true),
+];
+
+// The names of the standard signals of Linux, by number.
+const SIGNAL_NAMES: [&str; 31] = [
+    "SIGHUP",
+    "SIGINT",
+    "SIGQUIT",
+    "SIGILL",
+    "SIGTRAP",
+    "SIGABRT",
+    "SIGBUS",
+    "SIGFPE",
+    "SIGKILL",
+    "SIGUSR1",
+    "SIGSEGV",
+    "SIGUSR2",
+    "SIGPIPE",
+    "SIGALRM",
+    "SIGTERM",
+    "SIGSTKFLT",
+    "SIGCHLD",
+    "SIGCONT",
+    "SIGSTOP",
+    "SIGTSTP",
+    "SIGTTIN",
+    "SIGTTOU",
+    "SIGURG",
+    "SIGXCPU",
+    "SIGXFSZ",
+    "SIGVTALRM",
+    "SIGPROF",
+    "SIGWINCH",
+    "SIGIO",
+    "SIGPWR",
+    "SIGSYS",
+];
+
+/// A call registering what to do on a signal.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SignalRegistration {
+    /// The function called, e.g. `sigaction`.
+    pub function: String,
+    /// The address of the call instruction.
+    pub site: u64,
+    /// The name of the function performing the call.
+    pub caller: String,
+    /// The number of the signal, when it is a constant.
+    pub signal: Option<u64>,
+    /// The handler given, when it is a constant: `0` for `SIG_DFL`, `1` for `SIG_IGN`, else
+    /// the address of a function.
+    pub handler: Option<u64>,
+    /// The name of the handler, when it is a function of the binary.
+    pub handler_name: Option<String>,
+    /// The names of the APIs whose flow registers the handler.
+    pub apis: Vec<String>,
+}
+
+impl SignalRegistration {
+    /// Return the name of the signal, e.g. `SIGTERM`, or its number for a real-time signal.
+    pub fn signal_name(&self) -> Option<String> {
+        let signal = self.signal?;
+        Some(match SIGNAL_NAMES.get((signal as usize).wrapping_sub(1)) {
+            Some(name) => name.to_string(),
+            None => format!("signal {}", signal),
+        })
+    }
+
+    /// Return what the registration does with the signal: `default`, `ignore`, `handler`, or
+    /// `unknown` when the handler is not a constant.
+    pub fn disposition(&self) -> &'static str {
+        match self.handler {
+            Some(0) => "default",
+            Some(1) => "ignore",
+            Some(_) => "handler",
+            None => "unknown",
+        }
+    }
+
+    /// Return the registration as an entry of the `signal handlers` of the flow call manifest.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "registered by": self.function,
+            "site": format!("{:#x}", self.site),
+            "caller": self.caller,
+            "signal": self.signal_name(),
+            "disposition": self.disposition(),
+            "handler": self.handler_name,
+            "handler address": self
+                .handler
+                .filter(|&addr| addr > 1)
+                .map(|addr| format!("{:#x}", addr)),
+            "APIs": self.apis,
+        })
+    }
+}
+
+/// Find the calls registering signal handlers and resolve the signal and the handler.
+///
+/// The handler given to `signal` is the constant held by its argument register; the one given
+/// to `sigaction` is read from the `struct sigaction` built on the stack of the caller, whose
+/// address is passed. The registrations with a null `struct sigaction`, which only query the
+/// current handler, are left out.
+///
+/// # Arguments
+///
+/// * `elf` - The ELF object representing the binary.
+/// * `buffer` - The buffer containing the binary data of the ELF file.
+/// * `graph` - The call graph of the binary.
+/// * `api_list` - The APIs found, to tell which of them register each handler.
+///
+/// # Returns
+///
+/// Returns a `Result` containing the registrations, sorted by call site.
+pub fn signal_registrations(
+    elf: &Elf,
+    buffer: &[u8],
+    graph: &CallGraph,
+    api_list: &[API],
+) -> Result<Vec<SignalRegistration>> {
+    let flows: Vec<_> = api_list
+        .iter()
+        .map(|api| (api.name.as_str(), graph.reachable(&[api.start_addr])))
+        .collect();
+    let resolver = PointerResolver::new(elf, buffer);
+    let disassembler = Disassembler::new()?;
+    let mut registrations = Vec::new();
+
+    for func in graph.functions() {
+        // The wrappers of glibc calling one another are not registrations.
+        if signal_function(&func.name).is_some() {
+            continue;
+        }
+        let sites: HashMap<u64, (&str, bool)> = graph
+            .callees(func.start_addr)
+            .iter()
+            .filter_map(|edge| Some((edge.site, signal_function(&edge.name)?)))
+            .collect();
+        if sites.is_empty() {
+            continue;
+        }
+        let Some(code) = code_bytes(elf, buffer, func.start_addr, func.end_addr) else {
+            continue;
+        };
+        let Ok(instructions) = disassembler.disassemble(code, func.start_addr) else {
+            continue;
+        };
+
+        let mut state = RegisterState::new();
+        let mut stack = StackSlots::default();
+        for insn in instructions.iter() {
+            if let Some(&(function, takes_struct)) = sites.get(&insn.address) {
+                let handler = match takes_struct {
+                    false => Some(state.constant("%rsi")),
+                    // A null `struct sigaction` only queries the current handler.
+                    true if state.constant("%rsi") == Some(0) => None,
+                    true => Some(
+                        stack
+                            .sigaction
+                            .as_ref()
+                            .and_then(|slot| stack.values.get(slot).copied()),
+                    ),
+                };
+                if let Some(handler) = handler {
+                    registrations.push(SignalRegistration {
+                        function: function.to_string(),
+                        site: insn.address,
+                        caller: demangle_api_name(&func.name),
+                        signal: state.constant("%rdi"),
+                        handler,
+                        handler_name: handler
+                            .filter(|&addr| addr > 1)
+                            .and_then(|addr| graph.function(addr))
+                            .map(|handler| demangle_api_name(&handler.name)),
+                        apis: flows
+                            .iter()
+                            .filter(|(_, reachable)| reachable.contains(&func.start_addr))
+                            .map(|(name, _)| name.to_string())
+                            .collect(),
+                    });
+                }
+            }
+            stack.update(&insn.mnemonic, &insn.op_str, &state);
+            state.update(&insn.mnemonic, &insn.op_str, insn.next_addr(), &resolver);
+        }
+    }
+    registrations.sort_by_key(|registration| registration.site);
+    Ok(registrations)
+}
+
+// The constants stored in the stack frame of a function, and the slot whose address is passed
+// as the `struct sigaction`.
+#[derive(Default)]
+struct StackSlots {
+    values: HashMap<(String, i64), u64>,
+    sigaction: Option<(String, i64)>,
+}
+
+impl StackSlots {
+    // Update the slots with the effect of an instruction, before `state` is.
+    fn update(&mut self, mnemonic: &str, op_str: &str, state: &RegisterState) {
+        let Some((src, dst)) = op_str.rsplit_once(", ") else {
+            return;
+        };
+        let frame_slot = |operand: &str| {
+            memory_operand(operand)
+                .filter(|(_, base)| base == "%rbp" || base == "%rsp")
+                .map(|(disp, base)| (base, disp))
+        };
+        if let Some(slot) = frame_slot(dst) {
+            let value = match src.strip_prefix('$') {
+                Some(imm) if mnemonic.starts_with("mov") => immediate(imm),
+                _ if mnemonic.starts_with("mov") => {
+                    register(src).and_then(|reg| state.constant(&reg))
+                }
+                _ => None,
+            };
+            match value {
+                Some(value) => self.values.insert(slot, value),
+                None => self.values.remove(&slot),
+            };
+        } else if register(dst).as_deref() == Some("%rsi") {
+            self.sigaction = frame_slot(src).filter(|_| mnemonic.starts_with("lea"));
+        }
+    }
+}
+
+// Return the registration function a called symbol stands for, and whether it takes a
+// `struct sigaction`.
+fn signal_function(name: &str) -> Option<(&'static str, bool)> {
+    let name = name.split('@').next().unwrap_or(name);
+    SIGNAL_FUNCTIONS
+        .iter()
+        .find(|(function, _)| *function == name)
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf_utils::read_elf_file;
+
+    #[test]
+    fn test_signal_registrations() {
+        assert_eq!(
+            signal_function("sigaction@GLIBC_2.2.5"),
+            Some(("sigaction", true))
+        );
+        assert_eq!(signal_function("signal"), Some(("signal", false)));
+        assert_eq!(signal_function("sigprocmask"), None);
+
+        let registration = SignalRegistration {
+            function: "signal".to_string(),
+            site: 0x1000,
+            caller: "main".to_string(),
+            signal: Some(15),
+            handler: Some(0x2000),
+            handler_name: Some("on_term".to_string()),
+            apis: Vec::new(),
+        };
+        assert_eq!(registration.signal_name().as_deref(), Some("SIGTERM"));
+        assert_eq!(registration.disposition(), "handler");
+        assert_eq!(registration.to_json()["handler address"], "0x2000");
+        let realtime = SignalRegistration {
+            signal: Some(34),
+            handler: Some(1),
+            ..registration
+        };
+        assert_eq!(realtime.signal_name().as_deref(), Some("signal 34"));
+        assert_eq!(realtime.to_json()["handler address"], Value::Null);
+
+        // sigpipe_ignore of curl: SIG_IGN stored in the struct sigaction built on the stack.
+        let buffer = read_elf_file("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        let graph = CallGraph::build(&elf, &buffer, false).unwrap();
+        let registrations = signal_registrations(&elf, &buffer, &graph, &[]).unwrap();
+        let ignore = registrations
+            .iter()
+            .find(|registration| registration.caller == "sigpipe_ignore")
+            .unwrap();
+        assert_eq!(ignore.signal_name().as_deref(), Some("SIGPIPE"));
+        assert_eq!(ignore.disposition(), "ignore");
+        assert!(registrations
+            .windows(2)
+            .all(|pair| pair[0].site <= pair[1].site));
+    }
+}
@@ -0,0 +1,645 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::Write as _,
+    fs,
+    path::Path,
+};
+
+use goblin::elf::{program_header::PT_LOAD, Elf};
+use serde_json::{json, Value};
+use tracing::debug;
+
+use crate::{
+    api_detection::compile_patterns,
+    call_graph::function_nodes,
+    disassembler::{Disassembler, Instruction},
+    elf_utils::{code_bytes, executable_regions, sha256_hex, API},
+    error::{Error, Result},
+};
+
+/// The number of bytes at the start of a function matched by the pattern of a signature.
+pub const PATTERN_LENGTH: usize = 32;
+
+// Minimum size of the functions given a signature, as for FLIRT: the shorter ones are made of
+// the same few instructions in every library.
+const MIN_FUNCTION_SIZE: u64 = 35;
+
+// Maximum number of bytes after the pattern covered by the CRC16 of a signature.
+const MAX_CRC_LENGTH: usize = 255;
+
+// The polynomial of the CRC16 of FLIRT, reversed.
+const CRC16_POLY: u32 = 0x8408;
+
+/// The signature of a library function, as a line of a FLIRT `.pat` file: the first
+/// [`PATTERN_LENGTH`] bytes of its code, the bytes depending on where it is linked left out,
+/// and the CRC16 of the bytes following them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Signature {
+    /// The first bytes of the function, `None` for the variable ones.
+    pub pattern: Vec<Option<u8>>,
+    /// The number of bytes after the pattern covered by the CRC.
+    pub crc_length: usize,
+    /// The CRC16 of these bytes.
+    pub crc: u16,
+    /// The size of the function, in bytes.
+    pub size: u64,
+    /// The name of the function.
+    pub name: String,
+}
+
+impl Signature {
+    // Build the signature of the code of a function, `variable` marking its bytes that depend on
+    // where it is linked.
+    fn from_code(name: &str, code: &[u8], variable: &[bool]) -> Self {
+        let pattern = (0..PATTERN_LENGTH)
+            .map(|i| code.get(i).filter(|_| !variable[i]).copied())
+            .collect();
+        let crc_length = (PATTERN_LENGTH..code.len().min(PATTERN_LENGTH + MAX_CRC_LENGTH))
+            .take_while(|&i| !variable[i])
+            .count();
+        Self {
+            pattern,
+            crc_length,
+            crc: crc16(&code[PATTERN_LENGTH..PATTERN_LENGTH + crc_length]),
+            size: code.len() as u64,
+            name: name.to_string(),
+        }
+    }
+
+    // Tell whether the code at the start of a function is that of the signature.
+    fn matches(&self, code: &[u8]) -> bool {
+        (code.len() as u64) >= self.size
+            && code.len() >= PATTERN_LENGTH + self.crc_length
+            && self
+                .pattern
+                .iter()
+                .zip(code)
+                .all(|(expected, byte)| expected.is_none_or(|expected| expected == *byte))
+            && crc16(&code[PATTERN_LENGTH..PATTERN_LENGTH + self.crc_length]) == self.crc
+    }
+
+    // Write the signature as a line of a `.pat` file.
+    fn to_pat(&self) -> String {
+        let mut line = String::new();
+        for byte in &self.pattern {
+            match byte {
+                Some(byte) => write!(line, "{:02X}", byte).unwrap(),
+                None => line.push_str(".."),
+            }
+        }
+        write!(
+            line,
+            " {:02X} {:04X} {:04X} :0000 {}",
+            self.crc_length, self.crc, self.size, self.name
+        )
+        .unwrap();
+        line
+    }
+}
+
+/// The signatures of the functions of a library, read from a FLIRT `.pat` file as written by
+/// IDA's `sigmake` tools, rizin or [`SignatureFile::to_pat`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignatureFile {
+    /// The library, named after the file, e.g. `musl` for `musl.pat`.
+    pub library: String,
+    /// The signatures of its functions.
+    pub signatures: Vec<Signature>,
+}
+
+impl SignatureFile {
+    /// Load a `.pat` file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read, or [`Error::InvalidPatternFile`] if one of
+    /// its lines is malformed.
+    pub fn load(path: &str) -> Result<Self> {
+        let library = Path::new(path)
+            .file_stem()
+            .map_or(path.into(), |stem| stem.to_string_lossy());
+        Self::parse(&library, &fs::read_to_string(path)?)
+    }
+
+    /// Parse the content of a `.pat` file: one signature per line, up to a `---` line.
+    ///
+    /// Each line holds the pattern, 64 hexadecimal digits or `..` for a variable byte, the
+    /// length and value of the CRC16, the size of the function and its public names as
+    /// `:offset name`; the names it references and its tail bytes are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidPatternFile`] if a line is malformed.
+    pub fn parse(library: &str, text: &str) -> Result<Self> {
+        let mut signatures = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line == "---" {
+                break;
+            }
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |reason: &str| {
+                Error::InvalidPatternFile(format!("{}: line {}: {}", library, index + 1, reason))
+            };
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [pattern, crc_length, crc, size, names @ ..] = fields.as_slice() else {
+                return Err(invalid("expected a pattern, a CRC, a size and names"));
+            };
+            let pattern = parse_pattern(pattern).ok_or_else(|| invalid("invalid pattern"))?;
+            let crc_length =
+                usize::from_str_radix(crc_length, 16).map_err(|_| invalid("invalid CRC length"))?;
+            let crc = u16::from_str_radix(crc, 16).map_err(|_| invalid("invalid CRC"))?;
+            let size = u64::from_str_radix(size, 16).map_err(|_| invalid("invalid size"))?;
+            let name = public_name(names).ok_or_else(|| invalid("no public name"))?;
+            signatures.push(Signature {
+                pattern,
+                crc_length,
+                crc,
+                size,
+                name: name.to_string(),
+            });
+        }
+        Ok(Self {
+            library: library.to_string(),
+            signatures,
+        })
+    }
+
+    /// Write the signatures as a `.pat` file.
+    pub fn to_pat(&self) -> String {
+        let mut pat: String = self
+            .signatures
+            .iter()
+            .map(|signature| signature.to_pat() + "\n")
+            .collect();
+        pat.push_str("---\n");
+        pat
+    }
+}
+
+/// A function of a stripped binary, found as the target of a call and named by the signature
+/// recognizing it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecognizedFunction {
+    /// The name of the function: that of its signature, `main`, or `sub_<address>` when it is
+    /// not recognized.
+    pub name: String,
+    /// The starting address of the function.
+    pub start_addr: u64,
+    /// The ending address of the function.
+    pub end_addr: u64,
+    /// The library of the signature recognizing it.
+    pub library: Option<String>,
+}
+
+/// Generate the signatures of the functions of a binary with a symbol table, e.g. an
+/// unstripped build of a library, to recognize them in stripped binaries.
+///
+/// # Arguments
+///
+/// * `elf` - The parsed ELF structure.
+/// * `buffer` - The content of the binary.
+/// * `library` - The name of the library.
+///
+/// # Returns
+///
+/// Returns a `Result` containing the signatures of the functions of at least 35 bytes.
+///
+/// # Errors
+///
+/// Returns [`Error::Capstone`] if the disassembler cannot be initialized.
+pub fn generate_signatures(elf: &Elf, buffer: &[u8], library: &str) -> Result<SignatureFile> {
+    let disassembler = Disassembler::new()?;
+    let image = image_range(elf);
+    let mut signatures = Vec::new();
+    for func in function_nodes(elf).into_values() {
+        if func.end_addr - func.start_addr < MIN_FUNCTION_SIZE {
+            continue;
+        }
+        let Some(code) = code_bytes(elf, buffer, func.start_addr, func.end_addr) else {
+            continue;
+        };
+        let variable = variable_bytes(&disassembler, code, func.start_addr, image)?;
+        let signature = Signature::from_code(&func.name, code, &variable);
+        if !signatures.contains(&signature) {
+            signatures.push(signature);
+        }
+    }
+    Ok(SignatureFile {
+        library: library.to_string(),
+        signatures,
+    })
+}
+
+/// Find the functions of a stripped binary and name those recognized by the signatures.
+///
+/// The functions are the entry point, the targets of the direct calls of the executable code
+/// and the `main` given to `__libc_start_main` by the entry point; each one ends where the next
+/// one starts, or after the size of its signature. A function matched by the signatures of
+/// several functions is left unrecognized.
+///
+/// # Arguments
+///
+/// * `elf` - The parsed ELF structure.
+/// * `buffer` - The content of the binary.
+/// * `files` - The signatures of the libraries to recognize.
+///
+/// # Returns
+///
+/// Returns a `Result` containing the functions, by starting address.
+///
+/// # Errors
+///
+/// Returns [`Error::Capstone`] if the disassembler cannot be initialized.
+pub fn recognize_functions(
+    elf: &Elf,
+    buffer: &[u8],
+    files: &[SignatureFile],
+) -> Result<Vec<RecognizedFunction>> {
+    let disassembler = Disassembler::new()?;
+    let regions: Vec<(u64, &[u8])> = executable_regions(elf)
+        .into_iter()
+        .filter_map(|(addr, offset, size)| {
+            let start = usize::try_from(offset).ok()?;
+            Some((addr, buffer.get(start..start.checked_add(size as usize)?)?))
+        })
+        .collect();
+    let code_at = |addr: u64| {
+        regions.iter().find_map(|(start, code)| {
+            (*start..*start + code.len() as u64)
+                .contains(&addr)
+                .then(|| &code[(addr - start) as usize..])
+        })
+    };
+
+    let mut starts = BTreeSet::new();
+    for (addr, code) in &regions {
+        for insn in sweep(&disassembler, code, *addr) {
+            if insn.name() == "call" {
+                starts.extend(hex_value(&insn.op_str).filter(|&target| code_at(target).is_some()));
+            }
+        }
+    }
+    let entry = elf.header.e_entry;
+    let main = code_at(entry).and_then(|code| main_address(&disassembler, code, entry));
+    starts.extend([Some(entry), main].into_iter().flatten());
+    starts.retain(|&addr| code_at(addr).is_some());
+
+    // The signatures by first byte, those starting with a variable byte apart.
+    let mut index: HashMap<Option<u8>, Vec<(&Signature, &str)>> = HashMap::new();
+    for file in files {
+        for signature in &file.signatures {
+            let first = signature.pattern.first().copied().flatten();
+            index
+                .entry(first)
+                .or_default()
+                .push((signature, &file.library));
+        }
+    }
+
+    let starts: Vec<u64> = starts.into_iter().collect();
+    let mut functions = Vec::new();
+    for (i, &start) in starts.iter().enumerate() {
+        let code = code_at(start).unwrap_or_default();
+        let candidates = code
+            .first()
+            .and_then(|byte| index.get(&Some(*byte)))
+            .into_iter()
+            .chain(index.get(&None))
+            .flatten()
+            .filter(|(signature, _)| signature.matches(code));
+        let mut names = BTreeSet::new();
+        let mut recognized = None;
+        for (signature, library) in candidates {
+            names.insert(signature.name.as_str());
+            recognized.get_or_insert((signature, *library));
+        }
+        let end = starts
+            .get(i + 1)
+            .copied()
+            .unwrap_or(start + code.len() as u64)
+            .min(start + code.len() as u64);
+        functions.push(match recognized {
+            Some((signature, library)) if names.len() == 1 => RecognizedFunction {
+                name: signature.name.clone(),
+                start_addr: start,
+                end_addr: start + signature.size,
+                library: Some(library.to_string()),
+            },
+            _ => {
+                if names.len() > 1 {
+                    debug!(addr = start, ?names, "ambiguous signatures");
+                }
+                let name = match start {
+                    _ if Some(start) == main => "main".to_string(),
+                    _ if start == entry => "_start".to_string(),
+                    _ => format!("sub_{:x}", start),
+                };
+                RecognizedFunction {
+                    name,
+                    start_addr: start,
+                    end_addr: end,
+                    library: None,
+                }
+            }
+        });
+    }
+    Ok(functions)
+}
+
+/// Do an API lookup among the functions of a stripped binary.
+///
+/// # Returns
+///
+/// Returns a `Result` containing the APIs found, or an error for an invalid entry of the list.
+pub fn recognized_api_search(
+    functions: &[RecognizedFunction],
+    api_list: &[&str],
+) -> Result<Vec<API>> {
+    let patterns = compile_patterns(api_list)?;
+    Ok(functions
+        .iter()
+        .filter(|func| patterns.iter().any(|pattern| pattern.matches(&func.name)))
+        .map(|func| API::new(func.name.clone(), func.start_addr, func.end_addr))
+        .collect())
+}
+
+/// Return the functions of a stripped binary as the `recognized functions` section of the
+/// basic information manifest: how many were found and recognized, by library.
+pub fn recognized_json(functions: &[RecognizedFunction]) -> Value {
+    let mut libraries: BTreeMap<&str, usize> = BTreeMap::new();
+    for library in functions.iter().filter_map(|func| func.library.as_deref()) {
+        *libraries.entry(library).or_default() += 1;
+    }
+    json!({
+        "discovered": functions.len(),
+        "recognized": libraries.values().sum::<usize>(),
+        "libraries": libraries,
+    })
+}
+
+/// Return a digest of the names of the functions, telling apart the caches of the analyses of
+/// a stripped binary with different signatures.
+pub fn names_digest(functions: &[RecognizedFunction]) -> String {
+    let names: Vec<&str> = functions.iter().map(|func| func.name.as_str()).collect();
+    sha256_hex(names.join("\n").as_bytes())
+}
+
+// The CRC16 of FLIRT: CRC-16/X-25, its two bytes swapped.
+fn crc16(data: &[u8]) -> u16 {
+    if data.is_empty() {
+        return 0;
+    }
+    let mut crc: u32 = 0xffff;
+    for &byte in data {
+        let mut byte = u32::from(byte);
+        for _ in 0..8 {
+            crc = if (crc ^ byte) & 1 != 0 {
+                (crc >> 1) ^ CRC16_POLY
+            } else {
+                crc >> 1
+            };
+            byte >>= 1;
+        }
+    }
+    let crc = !crc & 0xffff;
+    ((crc << 8) | (crc >> 8)) as u16
+}
+
+// Parse the hexadecimal digits of a pattern, `..` standing for a variable byte.
+fn parse_pattern(pattern: &str) -> Option<Vec<Option<u8>>> {
+    if !pattern.len().is_multiple_of(2) || pattern.len() > 2 * PATTERN_LENGTH {
+        return None;
+    }
+    let mut bytes: Vec<Option<u8>> = (0..pattern.len())
+        .step_by(2)
+        .map(|i| match &pattern[i..i + 2] {
+            ".." => Some(None),
+            digits => u8::from_str_radix(digits, 16).ok().map(Some),
+        })
+        .collect::<Option<_>>()?;
+    bytes.resize(PATTERN_LENGTH, None);
+    Some(bytes)
+}
+
+// The name of a function among the fields following its size: the public name at offset 0, else
+// the first public one.
+fn public_name<'a>(fields: &[&'a str]) -> Option<&'a str> {
+    let mut publics = Vec::new();
+    for pair in fields.windows(2) {
+        if let Some(offset) = pair[0].strip_prefix(':') {
+            publics.push((offset.trim_end_matches('@'), pair[1]));
+        }
+    }
+    publics
+        .iter()
+        .find(|(offset, _)| offset.trim_start_matches('0').is_empty())
+        .or(publics.first())
+        .map(|(_, name)| *name)
+}
+
+// The range of addresses loaded from the binary.
+fn image_range(elf: &Elf) -> (u64, u64) {
+    let segments = elf.program_headers.iter().filter(|ph| ph.p_type == PT_LOAD);
+    let start = segments.clone().map(|ph| ph.p_vaddr).min().unwrap_or(0);
+    let end = segments
+        .map(|ph| ph.p_vaddr + ph.p_memsz)
+        .max()
+        .unwrap_or(0);
+    (start, end)
+}
+
+// Mark the bytes of the code of a function that depend on where it and its data are linked:
+// the targets of its calls and long jumps, its RIP-relative displacements and the addresses of
+// the image it holds.
+fn variable_bytes(
+    disassembler: &Disassembler,
+    code: &[u8],
+    addr: u64,
+    image: (u64, u64),
+) -> Result<Vec<bool>> {
+    let mut variable = vec![false; code.len()];
+    for insn in disassembler.disassemble(code, addr)? {
+        let start = (insn.address - addr) as usize;
+        let bytes = &code[start..start + insn.size];
+        let mut mask = |value: &[u8]| {
+            if let Some(at) = bytes
+                .windows(value.len())
+                .rposition(|window| window == value)
+            {
+                variable[start + at..start + at + value.len()].fill(true);
+            }
+        };
+        let branch = insn.name().starts_with('j') || insn.name() == "call";
+        if branch && insn.size >= 5 && hex_value(&insn.op_str).is_some() {
+            variable[start + insn.size - 4..start + insn.size].fill(true);
+            continue;
+        }
+        for (value, rip) in operand_values(&insn.op_str) {
+            if rip {
+                mask(&(value as i32).to_le_bytes());
+            } else if (image.0..image.1).contains(&(value as u64)) {
+                mask(&(value as u32).to_le_bytes());
+            }
+        }
+    }
+    Ok(variable)
+}
+
+// The numbers of the operands of an instruction, with whether each is a RIP-relative
+// displacement, e.g. `0x2edf(%rip)`.
+fn operand_values(op_str: &str) -> Vec<(i64, bool)> {
+    let mut values = Vec::new();
+    for operand in op_str.split(',') {
+        let operand = operand.trim().trim_start_matches(['$', '*']);
+        let number = operand.split('(').next().unwrap_or_default();
+        let (negative, digits) = match number.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, number),
+        };
+        let Some(value) = digits
+            .strip_prefix("0x")
+            .and_then(|hex| u64::from_str_radix(hex, 16).ok())
+        else {
+            continue;
+        };
+        let value = if negative {
+            -(value as i64)
+        } else {
+            value as i64
+        };
+        values.push((value, operand.ends_with("(%rip)")));
+    }
+    values
+}
+
+// The target of a direct call or jump, written as an address.
+fn hex_value(op_str: &str) -> Option<u64> {
+    u64::from_str_radix(op_str.strip_prefix("0x")?, 16).ok()
+}
+
+// Decode the executable code of a region, going past the bytes that are not instructions.
+fn sweep(disassembler: &Disassembler, code: &[u8], addr: u64) -> Vec<Instruction> {
+    let mut instructions = Vec::new();
+    let mut offset = 0;
+    while offset < code.len() {
+        let decoded = disassembler
+            .disassemble(&code[offset..], addr + offset as u64)
+            .unwrap_or_default();
+        offset += decoded.iter().map(|insn| insn.size).sum::<usize>() + 1;
+        instructions.extend(decoded);
+    }
+    instructions
+}
+
+// The address of `main`, given in `%rdi` to `__libc_start_main` by the entry point of glibc.
+fn main_address(disassembler: &Disassembler, code: &[u8], entry: u64) -> Option<u64> {
+    let instructions = disassembler
+        .disassemble(&code[..code.len().min(64)], entry)
+        .ok()?;
+    let call = instructions
+        .iter()
+        .position(|insn| insn.name().starts_with("call"))?;
+    let setup = instructions[..call]
+        .iter()
+        .rev()
+        .find(|insn| insn.op_str.ends_with(", %rdi"))?;
+    let (value, rip) = *operand_values(&setup.op_str).first()?;
+    match setup.name() {
+        "lea" if rip => Some(setup.next_addr().wrapping_add_signed(value)),
+        name if name.starts_with("mov") && !rip => Some(value as u64),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf_utils::read_elf_file;
+
+    #[test]
+    fn test_crc16() {
+        assert_eq!(crc16(b""), 0);
+        assert_eq!(crc16(b"123456789"), 0x6e90);
+    }
+
+    #[test]
+    fn test_parse_pat() {
+        let pat = "\
+5589E5........C745F8........8B45F8890424E8........C9C3.......... 1E 4A6B 0030 :0000 my_func ^0014 helper 8B45\n\
+31ED4989D15E4889E24883E4F050544C8D05........488D0D........488D3D 00 0000 002A :0010@ local :0000 _start\n\
+---\n\
+ignored after the end";
+        let file = SignatureFile::parse("libfoo", pat).unwrap();
+        assert_eq!(file.signatures.len(), 2);
+        let first = &file.signatures[0];
+        assert_eq!(first.name, "my_func");
+        assert_eq!(
+            (first.crc_length, first.crc, first.size),
+            (0x1e, 0x4a6b, 0x30)
+        );
+        assert_eq!(
+            &first.pattern[..4],
+            [Some(0x55), Some(0x89), Some(0xe5), None]
+        );
+        assert_eq!(first.pattern.len(), PATTERN_LENGTH);
+        assert_eq!(file.signatures[1].name, "_start");
+        assert_eq!(
+            SignatureFile::parse("libfoo", &file.to_pat()).unwrap(),
+            file
+        );
+
+        for line in [
+            "5589E5 1E 4A6B",
+            "5589ZZ 00 0000 0030 :0000 f",
+            "5589E5 00 0000 0030",
+        ] {
+            assert!(matches!(
+                SignatureFile::parse("libfoo", line),
+                Err(Error::InvalidPatternFile(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_recognize_functions() {
+        let buffer = read_elf_file("./tests/elf_file/minimal-fake-firmware-c-static").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        let file = generate_signatures(&elf, &buffer, "firmware").unwrap();
+        assert!(file.signatures.iter().any(|s| s.name == "writeOnDrive"));
+        assert!(file.signatures.iter().all(|s| s.size >= MIN_FUNCTION_SIZE));
+
+        let functions = recognize_functions(&elf, &buffer, &[file]).unwrap();
+        let symbols = function_nodes(&elf);
+        let write = functions
+            .iter()
+            .find(|func| func.name == "writeOnDrive")
+            .unwrap();
+        assert_eq!(write.library.as_deref(), Some("firmware"));
+        assert_eq!(
+            symbols[&write.start_addr].end_addr, write.end_addr,
+            "the function ends after the size of its signature"
+        );
+        let main = functions.iter().find(|func| func.name == "main").unwrap();
+        assert_eq!(symbols[&main.start_addr].name, "main");
+        // A function recognized is recognized at the address of the symbol of that name.
+        for func in functions.iter().filter(|func| func.library.is_some()) {
+            assert_eq!(
+                symbols.get(&func.start_addr).map(|sym| sym.name.as_str()),
+                Some(func.name.as_str())
+            );
+        }
+        let json = recognized_json(&functions);
+        assert!(json["recognized"].as_u64().unwrap() > 0);
+        assert_eq!(json["discovered"], functions.len());
+
+        let apis = recognized_api_search(&functions, &["writeOnDrive"]).unwrap();
+        assert_eq!(apis.len(), 1);
+        assert!(recognize_functions(&elf, &buffer, &[])
+            .unwrap()
+            .iter()
+            .all(|func| func.library.is_none()));
+    }
+}
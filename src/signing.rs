@@ -0,0 +1,220 @@
+use std::{collections::BTreeSet, fs, path::Path};
+
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde_json::Value;
+
+use crate::{
+    elf_utils::sha256_hex,
+    error::{Error, Result},
+    manifest_creation::read_manifest,
+};
+
+/// The name of the signature file written next to the signed manifests.
+pub const SIGNATURE_FILE: &str = "manifest.sig.json";
+
+/// Parse an ed25519 signing key written as the 64 hexadecimal digits of its 32-byte seed.
+pub fn parse_signing_key(hex: &str) -> Result<SigningKey> {
+    Ok(SigningKey::from_bytes(&key_bytes(hex)?))
+}
+
+/// Parse an ed25519 public key written as 64 hexadecimal digits.
+pub fn parse_verifying_key(hex: &str) -> Result<VerifyingKey> {
+    VerifyingKey::from_bytes(&key_bytes(hex)?).map_err(|error| Error::InvalidKey(error.to_string()))
+}
+
+/// Return the hexadecimal encoding of the public key of a signing key.
+pub fn public_key_hex(key: &SigningKey) -> String {
+    hex_string(key.verifying_key().as_bytes())
+}
+
+/// Sign the manifests of an output directory.
+///
+/// The signature covers the SHA-256 digest of each file of the directory, except the signature
+/// file itself, and the digest of the analyzed binary: the directory should hold the manifests
+/// of a single analysis. The signed statement, its signature and the public key are written to
+/// [`SIGNATURE_FILE`].
+///
+/// # Arguments
+///
+/// * `path` - The directory holding the manifests.
+/// * `binary` - The content of the analyzed binary.
+/// * `key` - The signing key.
+///
+/// # Returns
+///
+/// Returns a `Result` indicating success or failure.
+pub fn sign_manifests(path: &str, binary: &[u8], key: &SigningKey) -> Result<()> {
+    let statement = serde_json::json!({
+        "binary sha256": sha256_hex(binary),
+        "manifests": manifest_digests(path)?,
+    });
+    let signature = key.sign(&serde_json::to_vec(&statement)?);
+    let document = serde_json::json!({
+        "algorithm": "ed25519",
+        "public key": public_key_hex(key),
+        "statement": statement,
+        "signature": hex_string(&signature.to_bytes()),
+    });
+    fs::write(
+        Path::new(path).join(SIGNATURE_FILE),
+        serde_json::to_string_pretty(&document)?,
+    )?;
+    Ok(())
+}
+
+/// Verify the signature of the manifests of an output directory.
+///
+/// The signature must have been made by `key`, and the files of the directory must match the
+/// signed digests, with no file added or removed. When given, the analyzed binary must match
+/// the signed digest, and the digest written in the `sha256` field of `basic_info`.
+///
+/// # Arguments
+///
+/// * `path` - The directory holding the manifests and [`SIGNATURE_FILE`].
+/// * `key` - The trusted public key.
+/// * `binary` - The content of the analyzed binary, if it is to be checked.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidSignature`] describing the first check that failed.
+pub fn verify_manifests(path: &str, key: &VerifyingKey, binary: Option<&[u8]>) -> Result<()> {
+    let invalid = |reason: &str| Error::InvalidSignature(reason.to_string());
+    let document: Value =
+        serde_json::from_str(&fs::read_to_string(Path::new(path).join(SIGNATURE_FILE))?)?;
+    let statement = document
+        .get("statement")
+        .ok_or_else(|| invalid("missing statement"))?;
+    let signature = document
+        .get("signature")
+        .and_then(Value::as_str)
+        .ok_or_else(|| invalid("missing signature"))
+        .and_then(|hex| {
+            let bytes: [u8; 64] = hex_bytes(hex)
+                .and_then(|bytes| bytes.try_into().ok())
+                .ok_or_else(|| invalid("malformed signature"))?;
+            Ok(Signature::from_bytes(&bytes))
+        })?;
+    key.verify(&serde_json::to_vec(statement)?, &signature)
+        .map_err(|_| invalid("the signature does not match the public key"))?;
+
+    let signed = statement
+        .get("manifests")
+        .ok_or_else(|| invalid("missing manifest digests"))?;
+    let current = manifest_digests(path)?;
+    if *signed != current {
+        let signed = signed.as_object().cloned().unwrap_or_default();
+        let current = current.as_object().cloned().unwrap_or_default();
+        let changed: BTreeSet<&String> = signed
+            .keys()
+            .chain(current.keys())
+            .filter(|name| signed.get(*name) != current.get(*name))
+            .collect();
+        return Err(Error::InvalidSignature(format!(
+            "manifests modified, added or removed: {:?}",
+            changed
+        )));
+    }
+
+    if let Some(binary) = binary {
+        let digest = sha256_hex(binary);
+        if statement.get("binary sha256").and_then(Value::as_str) != Some(digest.as_str()) {
+            return Err(invalid("the binary does not match the signed digest"));
+        }
+        for (name, _) in current.as_object().into_iter().flatten() {
+            if !name.starts_with("basic_info.") {
+                continue;
+            }
+            let basic_info =
+                read_manifest(Path::new(path).join(name).to_str().unwrap_or_default())?;
+            if basic_info.get("sha256").and_then(Value::as_str) != Some(digest.as_str()) {
+                return Err(invalid(
+                    "the binary does not match the digest in basic_info",
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+// Digests of the files of a directory, except the signature, by file name.
+fn manifest_digests(path: &str) -> Result<Value> {
+    let mut digests = serde_json::Map::new();
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if entry.file_type()?.is_file() && name != SIGNATURE_FILE {
+            digests.insert(name, Value::String(sha256_hex(&fs::read(entry.path())?)));
+        }
+    }
+    Ok(Value::Object(digests))
+}
+
+// Decode a 32-byte key from hexadecimal.
+fn key_bytes(hex: &str) -> Result<[u8; 32]> {
+    hex_bytes(hex.trim())
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| Error::InvalidKey("expected 64 hexadecimal digits".to_string()))
+}
+
+// Decode hexadecimal digits, `None` if they are malformed.
+fn hex_bytes(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+// Encode bytes as lowercase hexadecimal digits.
+fn hex_string(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env::temp_dir;
+
+    const SEED: &str = "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60";
+
+    #[test]
+    fn test_parse_keys() {
+        let key = parse_signing_key(SEED).unwrap();
+        // Test vector 1 of RFC 8032.
+        assert_eq!(
+            public_key_hex(&key),
+            "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a"
+        );
+        assert!(parse_verifying_key(&public_key_hex(&key)).is_ok());
+        assert!(parse_signing_key("9d61").is_err());
+        assert!(parse_signing_key(&SEED.replace('9', "g")).is_err());
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let dir = temp_dir().join("manifest-signing");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.to_str().unwrap();
+        let binary = b"\x7fELF firmware";
+        fs::write(
+            dir.join("basic_info.json"),
+            serde_json::json!({ "sha256": sha256_hex(binary) }).to_string(),
+        )
+        .unwrap();
+        fs::write(dir.join("flow_call.json"), "{}").unwrap();
+
+        let key = parse_signing_key(SEED).unwrap();
+        sign_manifests(path, binary, &key).unwrap();
+        let public = key.verifying_key();
+        verify_manifests(path, &public, Some(binary)).unwrap();
+
+        assert!(verify_manifests(path, &public, Some(b"other firmware")).is_err());
+        let other = parse_signing_key(&SEED.replace('9', "8")).unwrap();
+        assert!(verify_manifests(path, &other.verifying_key(), None).is_err());
+        fs::write(dir.join("flow_call.json"), "{ }").unwrap();
+        assert!(verify_manifests(path, &public, None).is_err());
+    }
+}
@@ -0,0 +1,368 @@
+use std::collections::{BTreeMap, HashMap};
+
+use gimli::{
+    BaseAddresses, CfaRule, CieOrFde, DebugFrame, EhFrame, EndianSlice, Register, RunTimeEndian,
+    UnwindContext, UnwindSection,
+};
+use goblin::elf::{
+    header::{EM_ARM, EM_X86_64},
+    Elf,
+};
+use serde_json::{json, Value};
+
+use crate::{
+    call_graph::CallGraph, cleanup::demangle_api_name, cortex_m::thumb_frame_sizes,
+    disassembler::Instruction,
+};
+
+// The bytes of the start of an x86-64 function read for its prologue.
+const PROLOGUE_BYTES: usize = 64;
+
+/// The stack used by a call flow, in bytes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StackUsage {
+    /// The frame of the function rooting the flow.
+    pub frame: u64,
+    /// The worst-case stack depth of the flow: the largest sum of the frames along a call chain
+    /// from the root.
+    pub worst_case: u64,
+    /// The functions of the deepest call chain, from the root.
+    pub deepest_path: Vec<String>,
+    /// Whether the flow holds recursive calls, whose depth is unbounded: they are counted once.
+    pub recursive: bool,
+}
+
+impl StackUsage {
+    /// Return the stack usage as an entry of the `stack usage` of the flow call manifest.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "frame": self.frame,
+            "worst case": self.worst_case,
+            "deepest path": self.deepest_path,
+            "recursion": self.recursive,
+        })
+    }
+}
+
+/// The estimated stack frames of the functions of a binary.
+///
+/// The frame of a function is the larger of two estimates: the offset of the canonical frame
+/// address from the stack pointer in the CFI of `.eh_frame` and `.debug_frame`, and the space
+/// taken by its prologue. On x86-64 the prologue is the return address, the registers pushed
+/// and the space reserved by `sub $imm, %rsp` before the first branch; on Thumb, the
+/// registers saved by `push` and `vpush` and the space reserved from `sp`, see
+/// [`thumb_frame_sizes`]. The frames of the other architectures are unknown.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StackFrames {
+    frames: HashMap<u64, u64>,
+}
+
+// The worst-case depth of the stack below a function.
+#[derive(Clone, Copy)]
+struct Depth {
+    worst_case: u64,
+    next: Option<u64>,
+    recursive: bool,
+}
+
+impl StackFrames {
+    /// Estimate the stack frames of the functions of the call graph.
+    ///
+    /// The x86-64 prologues are those decoded while building the graph, see
+    /// [`CallGraph::prologue_frame`]; the functions it did not disassemble only have their CFI.
+    ///
+    /// # Arguments
+    ///
+    /// * `elf` - The ELF object representing the binary.
+    /// * `buffer` - The buffer containing the binary data of the ELF file.
+    /// * `graph` - The call graph of the whole binary.
+    ///
+    /// # Returns
+    ///
+    /// Returns the frames, none for an architecture other than x86-64 and ARM.
+    pub fn estimate(elf: &Elf, buffer: &[u8], graph: &CallGraph) -> Self {
+        let (mut frames, sp) = match elf.header.e_machine {
+            EM_X86_64 => {
+                let frames = graph
+                    .functions()
+                    .map(|func| {
+                        let frame = graph.prologue_frame(func.start_addr).unwrap_or_default();
+                        (func.start_addr, frame)
+                    })
+                    .collect();
+                (frames, gimli::X86_64::RSP)
+            }
+            EM_ARM => (thumb_frame_sizes(elf, buffer, graph), gimli::Arm::SP),
+            _ => return Self::default(),
+        };
+        for (start, offset) in cfa_offsets(elf, buffer, sp) {
+            if let Some(frame) = frames.get_mut(&start) {
+                *frame = (*frame).max(offset);
+            }
+        }
+        Self { frames }
+    }
+
+    /// Whether no frame is known.
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Return the estimated frame of the function starting at the given address, 0 when unknown.
+    pub fn frame(&self, addr: u64) -> u64 {
+        self.frames.get(&addr).copied().unwrap_or_default()
+    }
+
+    /// Return the stack used by the flows rooted at the functions starting at the given
+    /// addresses.
+    ///
+    /// The calls to the functions outside of the binary, e.g. imported from a shared library,
+    /// and the indirect calls left unresolved do not count.
+    ///
+    /// # Arguments
+    ///
+    /// * `graph` - The call graph of the whole binary.
+    /// * `roots` - The starting addresses of the roots of the flows.
+    ///
+    /// # Returns
+    ///
+    /// Returns the stack usage of each flow, by the name of its root; the addresses where no
+    /// function starts are left out.
+    pub fn flows(&self, graph: &CallGraph, roots: &[u64]) -> BTreeMap<String, StackUsage> {
+        let mut memo = HashMap::new();
+        let mut usages = BTreeMap::new();
+        for &root in roots {
+            let Some(func) = graph.function(root) else {
+                continue;
+            };
+            let depth = self.depth(graph, root, &mut Vec::new(), &mut memo);
+            let mut path = vec![root];
+            while let Some(next) = memo.get(path.last().unwrap_or(&root)).and_then(|d| d.next) {
+                if path.contains(&next) {
+                    break;
+                }
+                path.push(next);
+            }
+            let usage = StackUsage {
+                frame: self.frame(root),
+                worst_case: depth.worst_case,
+                deepest_path: path
+                    .iter()
+                    .filter_map(|&addr| graph.function(addr))
+                    .map(|func| demangle_api_name(&func.name))
+                    .collect(),
+                recursive: depth.recursive,
+            };
+            usages.insert(demangle_api_name(&func.name), usage);
+        }
+        usages
+    }
+
+    // The worst-case depth of the stack from the function at `addr`, of which `stack` holds the
+    // callers being visited; the calls back into them are recursion.
+    fn depth(
+        &self,
+        graph: &CallGraph,
+        addr: u64,
+        stack: &mut Vec<u64>,
+        memo: &mut HashMap<u64, Depth>,
+    ) -> Depth {
+        if let Some(depth) = memo.get(&addr) {
+            return *depth;
+        }
+        stack.push(addr);
+        let mut deepest = Depth {
+            worst_case: 0,
+            next: None,
+            recursive: false,
+        };
+        for edge in graph.callees(addr) {
+            let Some(target) = edge.target.filter(|&t| graph.function(t).is_some()) else {
+                continue;
+            };
+            if stack.contains(&target) {
+                deepest.recursive = true;
+                continue;
+            }
+            let callee = self.depth(graph, target, stack, memo);
+            deepest.recursive |= callee.recursive;
+            if deepest.next.is_none() || callee.worst_case > deepest.worst_case {
+                deepest.worst_case = callee.worst_case;
+                deepest.next = Some(target);
+            }
+        }
+        stack.pop();
+        let depth = Depth {
+            worst_case: self.frame(addr) + deepest.worst_case,
+            ..deepest
+        };
+        memo.insert(addr, depth);
+        depth
+    }
+}
+
+/// Return the stack usage of the flows as the `stack usage` section of the flow call manifest,
+/// with the flows whose worst case exceeds the threshold, when one is given.
+pub fn stack_json(usages: &BTreeMap<String, StackUsage>, threshold: Option<u64>) -> Value {
+    let flows: serde_json::Map<String, Value> = usages
+        .iter()
+        .map(|(name, usage)| (name.clone(), usage.to_json()))
+        .collect();
+    let exceeding: Vec<&str> = usages
+        .iter()
+        .filter(|(_, usage)| threshold.is_some_and(|limit| usage.worst_case > limit))
+        .map(|(name, _)| name.as_str())
+        .collect();
+    json!({
+        "threshold": threshold,
+        "exceeding": exceeding,
+        "flows": flows,
+    })
+}
+
+/// Return the frame of an x86-64 function from the instructions of its prologue, within the
+/// first bytes of its code: the return address, the registers pushed and the space reserved on
+/// the stack before the first branch.
+pub(crate) fn prologue_frame(instructions: &[Instruction], start: u64) -> u64 {
+    let mut frame = 8;
+    for insn in instructions
+        .iter()
+        .take_while(|insn| insn.next_addr() <= start + PROLOGUE_BYTES as u64)
+    {
+        let name = insn.name();
+        match name.strip_suffix('q').unwrap_or(name) {
+            "push" => frame += 8,
+            "sub" => {
+                let reserved = insn
+                    .op_str
+                    .strip_suffix(", %rsp")
+                    .and_then(|imm| imm.strip_prefix("$0x"))
+                    .and_then(|imm| u64::from_str_radix(imm, 16).ok());
+                frame += reserved.unwrap_or_default();
+            }
+            "call" | "ret" => break,
+            name if name.starts_with('j') => break,
+            _ => {}
+        }
+    }
+    frame
+}
+
+// The largest offset of the canonical frame address from the stack pointer `sp` in the CFI of
+// each function, by its starting address. Malformed CFI is left out, the prologues remain.
+fn cfa_offsets(elf: &Elf, buffer: &[u8], sp: Register) -> HashMap<u64, u64> {
+    let endian = match elf.little_endian {
+        true => RunTimeEndian::Little,
+        false => RunTimeEndian::Big,
+    };
+    let mut offsets = HashMap::new();
+    for sec in &elf.section_headers {
+        let (Some(name), start) = (elf.shdr_strtab.get_at(sec.sh_name), sec.sh_offset as usize)
+        else {
+            continue;
+        };
+        let Some(data) = buffer.get(start..start + sec.sh_size as usize) else {
+            continue;
+        };
+        let data = EndianSlice::new(data, endian);
+        let _ = match name {
+            ".eh_frame" => {
+                let bases = BaseAddresses::default().set_eh_frame(sec.sh_addr);
+                section_offsets(
+                    &EhFrame::new(data.slice(), endian),
+                    &bases,
+                    sp,
+                    &mut offsets,
+                )
+            }
+            ".debug_frame" => {
+                let mut section = DebugFrame::new(data.slice(), endian);
+                section.set_address_size(if elf.is_64 { 8 } else { 4 });
+                section_offsets(&section, &BaseAddresses::default(), sp, &mut offsets)
+            }
+            _ => continue,
+        };
+    }
+    offsets
+}
+
+// Read the largest CFA offsets of the frame description entries of a CFI section.
+fn section_offsets<'a, S: UnwindSection<EndianSlice<'a, RunTimeEndian>>>(
+    section: &S,
+    bases: &BaseAddresses,
+    sp: Register,
+    offsets: &mut HashMap<u64, u64>,
+) -> gimli::Result<()> {
+    let mut context = UnwindContext::new();
+    let mut entries = section.entries(bases);
+    while let Some(entry) = entries.next()? {
+        let CieOrFde::Fde(partial) = entry else {
+            continue;
+        };
+        let fde = partial.parse(|section, bases, offset| section.cie_from_offset(bases, offset))?;
+        let mut rows = fde.rows(section, bases, &mut context)?;
+        let mut largest = 0;
+        while let Some(row) = rows.next_row()? {
+            if let CfaRule::RegisterAndOffset { register, offset } = row.cfa() {
+                if *register == sp && *offset > 0 {
+                    largest = largest.max(*offset as u64);
+                }
+            }
+        }
+        let start = fde.initial_address();
+        let offset = offsets.entry(start).or_insert(0);
+        *offset = (*offset).max(largest);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cortex_m::thumb_call_graph, elf_utils::read_elf_file};
+
+    #[test]
+    fn test_stack_usage() {
+        let buffer = read_elf_file("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        let graph = CallGraph::build(&elf, &buffer, false).unwrap();
+        let frames = StackFrames::estimate(&elf, &buffer, &graph);
+        let addr = |name: &str| {
+            graph
+                .functions()
+                .find(|func| func.name == name)
+                .unwrap()
+                .start_addr
+        };
+        let usages = frames.flows(&graph, &[addr("writeOnDrive"), addr("main"), 0]);
+        assert_eq!(usages.len(), 2);
+        // push %rbp and sub $0x10, %rsp, above the return address.
+        let write = &usages["writeOnDrive"];
+        assert_eq!((write.frame, write.worst_case), (32, 32));
+        assert_eq!(write.deepest_path, ["writeOnDrive"]);
+        let main = &usages["main"];
+        assert!(main.worst_case > main.frame);
+        assert_eq!(main.deepest_path[..2], ["main", "accessNetwork"]);
+
+        let json = stack_json(&usages, Some(1024));
+        assert_eq!(json["exceeding"], json!(["main"]));
+        assert_eq!(json["flows"]["writeOnDrive"]["worst case"], 32);
+        assert_eq!(stack_json(&usages, None)["exceeding"], json!([]));
+
+        let buffer = read_elf_file("./tests/elf_file/minimal-fake-firmware-cortex-m").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        let graph = thumb_call_graph(&elf, &buffer);
+        let frames = StackFrames::estimate(&elf, &buffer, &graph);
+        let roots: Vec<u64> = graph.functions().map(|func| func.start_addr).collect();
+        let usages = frames.flows(&graph, &roots);
+        // push {r7, lr} in each function.
+        let reset = &usages["Reset_Handler"];
+        assert_eq!(reset.worst_case, 16);
+        assert_eq!(
+            reset.deepest_path,
+            ["Reset_Handler", "main", "uart_init", "write_reg"]
+        );
+        assert_eq!(usages["UART0_IRQHandler"].frame, 8);
+        assert!(!reset.recursive);
+    }
+}
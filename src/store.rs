@@ -0,0 +1,509 @@
+//! The SQLite store of the results of the analyses, for the queries across the binaries of a
+//! fleet of firmware, e.g. which binaries call `system` or read `/etc/shadow`.
+//!
+//! Each analysis written to the store replaces the previous analysis of the same binary, i.e.
+//! of the same path and SHA-256 digest. The tables are described by [`SCHEMA`].
+
+use std::{cmp::Ordering, collections::BTreeMap, fmt};
+
+use rusqlite::{params, Connection};
+use serde_json::Value;
+
+use crate::{analyzer::Analysis, elf_utils::API, error::Result, libraries::compare_versions};
+
+/// The version of [`SCHEMA`], stored in the `user_version` of the database.
+pub const SCHEMA_VERSION: i32 = 2;
+
+/// The tables of the store, created when the database is opened.
+pub const SCHEMA: &str = "
+-- One row per binary analyzed.
+CREATE TABLE IF NOT EXISTS binaries (
+    id INTEGER PRIMARY KEY,
+    path TEXT NOT NULL,             -- the path the binary was analyzed at
+    file_name TEXT NOT NULL,
+    sha256 TEXT NOT NULL,
+    build_id TEXT,                  -- the GNU build ID, if any
+    architecture TEXT,
+    language TEXT,
+    link TEXT,                      -- statically or dynamically linked
+    file_type TEXT,
+    tool_version TEXT,              -- the version of manifest-producer
+    analyzed_at TEXT,               -- the time of the analysis, unless left out
+    UNIQUE (path, sha256)
+);
+
+-- The functions of the call graph of each binary.
+CREATE TABLE IF NOT EXISTS functions (
+    binary_id INTEGER NOT NULL REFERENCES binaries (id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    address INTEGER NOT NULL,
+    size INTEGER NOT NULL,
+    PRIMARY KEY (binary_id, address)
+);
+
+-- The calls of the call graph of each binary, between functions named as in `functions`.
+CREATE TABLE IF NOT EXISTS calls (
+    binary_id INTEGER NOT NULL REFERENCES binaries (id) ON DELETE CASCADE,
+    caller TEXT NOT NULL,
+    callee TEXT NOT NULL,           -- the function or imported symbol called
+    site INTEGER NOT NULL           -- the address of the call instruction
+);
+
+-- The APIs found in each binary.
+CREATE TABLE IF NOT EXISTS apis (
+    binary_id INTEGER NOT NULL REFERENCES binaries (id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    address INTEGER NOT NULL,
+    size INTEGER NOT NULL,
+    PRIMARY KEY (binary_id, name, address)
+);
+
+-- The functions called by each flow: of an API, a thread, a signal handler, a constructor
+-- (startup), a destructor (shutdown), an interrupt handler or an RTOS task.
+CREATE TABLE IF NOT EXISTS flows (
+    binary_id INTEGER NOT NULL REFERENCES binaries (id) ON DELETE CASCADE,
+    kind TEXT NOT NULL,             -- api, thread, handler, startup, shutdown, interrupt, task
+    root TEXT NOT NULL,             -- the name of the function the flow starts at
+    callee TEXT NOT NULL,
+    direct INTEGER NOT NULL         -- 1 when called by the root itself
+);
+
+-- The capabilities of each API, e.g. network-client.
+CREATE TABLE IF NOT EXISTS capabilities (
+    binary_id INTEGER NOT NULL REFERENCES binaries (id) ON DELETE CASCADE,
+    api TEXT NOT NULL,
+    capability TEXT NOT NULL,
+    evidence TEXT NOT NULL          -- a JSON array of the calls and strings revealing it
+);
+
+-- The findings about each binary, one row per entry of a section of the feature manifest.
+CREATE TABLE IF NOT EXISTS findings (
+    binary_id INTEGER NOT NULL REFERENCES binaries (id) ON DELETE CASCADE,
+    kind TEXT NOT NULL,             -- the section, e.g. dangerous calls or file paths
+    detail TEXT NOT NULL            -- the entry, as a JSON object read with json_extract
+);
+
+-- The manifests of each binary, as JSON documents.
+CREATE TABLE IF NOT EXISTS manifests (
+    binary_id INTEGER NOT NULL REFERENCES binaries (id) ON DELETE CASCADE,
+    name TEXT NOT NULL,             -- basic_info, flow_call or feature_manifest
+    content TEXT NOT NULL,
+    PRIMARY KEY (binary_id, name)
+);
+
+CREATE INDEX IF NOT EXISTS calls_callee ON calls (callee);
+CREATE INDEX IF NOT EXISTS flows_callee ON flows (callee);
+CREATE INDEX IF NOT EXISTS findings_kind ON findings (kind);
+";
+
+// The sections of the feature manifest stored as findings, by their JSON pointer.
+const FINDING_SECTIONS: [&str; 12] = [
+    "/secrets",
+    "/network endpoints/all",
+    "/crypto/algorithms",
+    "/dangerous calls",
+    "/vulnerability patterns",
+    "/libraries",
+    "/vulnerabilities",
+    "/licenses",
+    "/copyrights",
+    "/environment variables",
+    "/file paths",
+    "/ioctls",
+];
+
+// How long a writer waits for another to release the database, in milliseconds.
+const BUSY_TIMEOUT: u64 = 5000;
+
+/// A SQLite database of the results of the analyses.
+pub struct ResultStore {
+    connection: Connection,
+}
+
+impl ResultStore {
+    /// Open the database at `path`, creating it and its tables if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database cannot be opened or its tables created.
+    pub fn open(path: &str) -> Result<Self> {
+        let connection = Connection::open(path)?;
+        connection.busy_timeout(std::time::Duration::from_millis(BUSY_TIMEOUT))?;
+        connection.pragma_update(None, "foreign_keys", true)?;
+        connection.execute_batch(SCHEMA)?;
+        connection.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        Ok(Self { connection })
+    }
+
+    /// Write the results of an analysis, replacing those of a previous analysis of the binary.
+    ///
+    /// # Arguments
+    ///
+    /// * `analysis` - The results of the analysis.
+    /// * `manifests` - The manifests of the analysis, as built by
+    ///   [`Analyzer::manifests`](crate::analyzer::Analyzer::manifests).
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the id of the binary in the `binaries` table.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the results cannot be written.
+    pub fn insert(
+        &mut self,
+        analysis: &Analysis,
+        manifests: &BTreeMap<String, Value>,
+    ) -> Result<i64> {
+        let basic_info = manifests.get("basic_info").cloned().unwrap_or_default();
+        let text = |key: &str| {
+            basic_info
+                .get(key)
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        };
+        let sha256 = text("sha256").unwrap_or_default();
+        let path = &analysis.binary.path;
+
+        let transaction = self.connection.transaction()?;
+        transaction.execute(
+            "DELETE FROM binaries WHERE path = ?1 AND sha256 = ?2",
+            params![path, sha256],
+        )?;
+        transaction.execute(
+            "INSERT INTO binaries (path, file_name, sha256, build_id, architecture, language, \
+             link, file_type, tool_version, analyzed_at) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                path,
+                text("file_name").unwrap_or_default(),
+                sha256,
+                text("build id"),
+                text("architecture"),
+                text("programming language"),
+                text("link"),
+                text("file_type"),
+                basic_info["generator"]["version"].as_str(),
+                basic_info["generator"]["timestamp"].as_str(),
+            ],
+        )?;
+        let id = transaction.last_insert_rowid();
+
+        let mut statement = transaction.prepare(
+            "INSERT OR IGNORE INTO functions (binary_id, name, address, size) \
+             VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        for func in analysis.graph.functions() {
+            let size = func.end_addr - func.start_addr;
+            statement.execute(params![id, func.name, func.start_addr, size])?;
+        }
+        drop(statement);
+
+        let mut statement = transaction.prepare(
+            "INSERT INTO calls (binary_id, caller, callee, site) VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        for func in analysis.graph.functions() {
+            for edge in analysis.graph.callees(func.start_addr) {
+                statement.execute(params![id, func.name, edge.name, edge.site])?;
+            }
+        }
+        drop(statement);
+
+        let mut apis = transaction.prepare(
+            "INSERT OR IGNORE INTO apis (binary_id, name, address, size) VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        let mut capabilities = transaction.prepare(
+            "INSERT INTO capabilities (binary_id, api, capability, evidence) \
+             VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        for api in &analysis.apis {
+            let size = api.end_addr - api.start_addr;
+            apis.execute(params![id, api.name, api.start_addr, size])?;
+            for capability in &api.capabilities {
+                let evidence = serde_json::to_string(&capability.evidence)?;
+                capabilities.execute(params![id, api.name, capability.capability, evidence])?;
+            }
+        }
+        drop((apis, capabilities));
+
+        let flows: [(&str, &[API]); 7] = [
+            ("api", &analysis.apis),
+            ("thread", &analysis.thread_flows),
+            ("handler", &analysis.handler_flows),
+            ("startup", &analysis.startup_flows),
+            ("shutdown", &analysis.shutdown_flows),
+            ("interrupt", &analysis.interrupt_flows),
+            ("task", &analysis.task_flows),
+        ];
+        let mut statement = transaction.prepare(
+            "INSERT INTO flows (binary_id, kind, root, callee, direct) VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        for (kind, roots) in flows {
+            for root in roots {
+                for callee in &root.syscalls {
+                    statement.execute(params![id, kind, root.name, callee, true])?;
+                }
+                for callee in root
+                    .transitive_calls
+                    .iter()
+                    .filter(|callee| !root.syscalls.contains(callee))
+                {
+                    statement.execute(params![id, kind, root.name, callee, false])?;
+                }
+            }
+        }
+        drop(statement);
+
+        let mut statement = transaction
+            .prepare("INSERT INTO findings (binary_id, kind, detail) VALUES (?1, ?2, ?3)")?;
+        if let Some(features) = manifests.get("feature_manifest") {
+            for pointer in FINDING_SECTIONS {
+                let kind = pointer[1..].split('/').next().unwrap_or_default();
+                let entries = features.pointer(pointer).and_then(Value::as_array);
+                for entry in entries.into_iter().flatten() {
+                    let detail = serde_json::to_string(entry)?;
+                    statement.execute(params![id, kind, detail])?;
+                }
+            }
+        }
+        drop(statement);
+
+        let mut statement = transaction
+            .prepare("INSERT INTO manifests (binary_id, name, content) VALUES (?1, ?2, ?3)")?;
+        for (name, manifest) in manifests {
+            statement.execute(params![id, name, serde_json::to_string(manifest)?])?;
+        }
+        drop(statement);
+
+        transaction.commit()?;
+        Ok(id)
+    }
+
+    /// Return the connection to the database, for queries.
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    /// Answer a question about the binaries of the store, without analyzing them again.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the binaries matching the query, sorted by path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database cannot be read.
+    pub fn query(&self, query: &Query) -> Result<Vec<QueryMatch>> {
+        let (sql, argument) = match query {
+            // The imported symbols may be versioned, e.g. `fopen@GLIBC_2.2.5`.
+            Query::Calls(function) => (
+                "SELECT b.path, b.sha256, c.caller FROM calls c JOIN binaries b ON b.id = c.binary_id \
+                 WHERE c.callee = ?1 OR c.callee LIKE ?1 || '@%' \
+                 GROUP BY b.id, c.caller ORDER BY b.path, c.caller",
+                function,
+            ),
+            Query::Library { name, .. } => (
+                "SELECT b.path, b.sha256, json_extract(f.detail, '$.version') \
+                 FROM findings f JOIN binaries b ON b.id = f.binary_id \
+                 WHERE f.kind = 'libraries' AND json_extract(f.detail, '$.name') = ?1 \
+                 ORDER BY b.path",
+                name,
+            ),
+            Query::Capability(capability) => (
+                "SELECT b.path, b.sha256, c.api FROM capabilities c \
+                 JOIN binaries b ON b.id = c.binary_id \
+                 WHERE c.capability = ?1 ORDER BY b.path, c.api",
+                capability,
+            ),
+        };
+        let mut statement = self.connection.prepare(sql)?;
+        let rows = statement.query_map(params![argument], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?,
+            ))
+        })?;
+
+        let mut matches: Vec<QueryMatch> = Vec::new();
+        for row in rows {
+            let (path, sha256, detail) = row?;
+            let detail = match query {
+                Query::Calls(_) => detail.map(|caller| format!("called by {}", caller)),
+                Query::Library { name, below } => match (detail, below) {
+                    (None, _) => Some(format!("{} of an unknown version", name)),
+                    (Some(version), Some(below))
+                        if compare_versions(&version, below) != Ordering::Less =>
+                    {
+                        continue
+                    }
+                    (Some(version), _) => Some(format!("{} {}", name, version)),
+                },
+                Query::Capability(_) => detail.map(|api| format!("in {}", api)),
+            };
+            match matches.last_mut() {
+                Some(last) if last.path == path && last.sha256 == sha256 => {
+                    last.details.extend(detail)
+                }
+                _ => matches.push(QueryMatch {
+                    path,
+                    sha256,
+                    details: detail.into_iter().collect(),
+                }),
+            }
+        }
+        for found in &mut matches {
+            found.details.dedup();
+        }
+        Ok(matches)
+    }
+}
+
+/// A question about the binaries of a [`ResultStore`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Query {
+    /// The binaries whose call graph calls a function, e.g. `accessWebcam`.
+    Calls(String),
+    /// The binaries linking a library, only those of a version older than `below` when given;
+    /// a library of an unknown version always matches.
+    Library {
+        /// The name of the library, as in the `libraries` of the feature manifest.
+        name: String,
+        /// The first version not matching, e.g. `1.1.1`.
+        below: Option<String>,
+    },
+    /// The binaries with an API of a capability, e.g. `network-client`.
+    Capability(String),
+}
+
+/// A binary matching a [`Query`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QueryMatch {
+    /// The path the binary was analyzed at.
+    pub path: String,
+    /// The SHA-256 digest of the binary.
+    pub sha256: String,
+    /// What matched, e.g. `called by main` or `openssl 1.0.2k`.
+    pub details: Vec<String>,
+}
+
+impl QueryMatch {
+    /// Return the match as JSON.
+    pub fn to_json(&self) -> Value {
+        serde_json::json!({
+            "path": self.path,
+            "sha256": self.sha256,
+            "details": self.details,
+        })
+    }
+}
+
+impl fmt::Display for QueryMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path)?;
+        if !self.details.is_empty() {
+            write!(f, ": {}", self.details.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env::temp_dir;
+
+    use super::*;
+    use crate::{analyzer::Analyzer, api_detection::ApiSpec, progress::Verbosity};
+
+    // The single integer returned by a query.
+    fn count(store: &ResultStore, query: &str) -> i64 {
+        store
+            .connection()
+            .query_row(query, [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_result_store() {
+        let analyzer = Analyzer::new("./tests/elf_file/fake-firmware-c-dynamic")
+            .with_api_list(vec![ApiSpec::new("writeOnDrive")])
+            .with_verbosity(Verbosity::Quiet);
+        let analysis = analyzer.analyze().unwrap();
+        let manifests = analyzer.manifests(&analysis).unwrap();
+
+        let path = temp_dir().join("manifest-store.db");
+        let _ = std::fs::remove_file(&path);
+        let mut store = ResultStore::open(path.to_str().unwrap()).unwrap();
+        let id = store.insert(&analysis, &manifests).unwrap();
+        assert_eq!(count(&store, "SELECT max(id) FROM binaries"), id);
+        assert_eq!(count(&store, "SELECT count(*) FROM binaries"), 1);
+        assert!(count(&store, "SELECT count(*) FROM functions") > 100);
+        assert_eq!(
+            count(
+                &store,
+                "SELECT count(*) FROM apis WHERE name = 'writeOnDrive'"
+            ),
+            1
+        );
+        assert_eq!(
+            count(
+                &store,
+                "SELECT count(*) FROM flows \
+                 WHERE kind = 'api' AND root = 'writeOnDrive' AND callee = 'fopen64' AND direct"
+            ),
+            1
+        );
+        assert_eq!(
+            count(
+                &store,
+                "SELECT count(*) FROM findings WHERE kind = 'file paths' \
+                 AND json_extract(detail, '$.path') = '/dev/urandom'"
+            ),
+            1
+        );
+        assert_eq!(count(&store, "SELECT count(*) FROM manifests"), 3);
+
+        // Analyzing the binary again replaces its results.
+        store.insert(&analysis, &manifests).unwrap();
+        assert_eq!(count(&store, "SELECT count(*) FROM binaries"), 1);
+        assert_eq!(count(&store, "SELECT count(*) FROM manifests"), 3);
+        assert_eq!(
+            count(&store, "SELECT user_version FROM pragma_user_version"),
+            i64::from(SCHEMA_VERSION)
+        );
+
+        let found = store
+            .query(&Query::Calls("accessWebcam".to_string()))
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert!(found[0].details.contains(&"called by main".to_string()));
+        // The imported functions are matched without their symbol version.
+        assert_eq!(
+            store
+                .query(&Query::Calls("fopen64".to_string()))
+                .unwrap()
+                .len(),
+            1
+        );
+        let library = |below: &str| {
+            let query = Query::Library {
+                name: "openssl".to_string(),
+                below: Some(below.to_string()),
+            };
+            store.query(&query).unwrap()
+        };
+        assert_eq!(library("3.1")[0].details, ["openssl 3.0.0"]);
+        assert!(library("1.1.1").is_empty());
+        let found = store
+            .query(&Query::Capability("filesystem-write".to_string()))
+            .unwrap();
+        assert_eq!(
+            found[0].to_string(),
+            format!("{}: in writeOnDrive", found[0].path)
+        );
+        assert!(store
+            .query(&Query::Calls("no_such_function".to_string()))
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,335 @@
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+use goblin::elf::{section_header::SHT_NOBITS, Elf};
+use regex::Regex;
+
+use crate::{call_graph::CallGraph, cleanup::demangle_api_name, xrefs::XrefDb};
+
+// Sections scanned for strings.
+const STRING_SECTIONS: [&str; 3] = [".rodata", ".data", ".data.rel.ro"];
+
+// Commands whose presence at the beginning of a string denotes a shell command line.
+const SHELL_COMMANDS: [&str; 16] = [
+    "sh ",
+    "/bin/sh",
+    "/bin/bash",
+    "bash ",
+    "rm ",
+    "chmod ",
+    "chown ",
+    "wget ",
+    "curl ",
+    "echo ",
+    "kill",
+    "iptables",
+    "mount ",
+    "reboot",
+    "ifconfig ",
+    "insmod ",
+];
+
+// Extensions denoting a file name even without directory.
+const FILE_EXTENSIONS: [&str; 14] = [
+    "txt", "conf", "cfg", "log", "json", "xml", "ini", "db", "sh", "so", "bin", "dat", "pem", "key",
+];
+
+/// The kind of content of a string.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum StringClass {
+    /// A URL, e.g. `https://example.com/api`.
+    Url,
+    /// An IPv4 address, possibly followed by a port.
+    IpAddress,
+    /// A command line run through a shell.
+    ShellCommand,
+    /// A path or a file name.
+    FilePath,
+    /// A `printf`-like format string.
+    FormatString,
+    /// Any other text.
+    Other,
+}
+
+impl StringClass {
+    /// Return the name of the class, as written in the manifests.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StringClass::Url => "url",
+            StringClass::IpAddress => "ip address",
+            StringClass::ShellCommand => "shell command",
+            StringClass::FilePath => "file path",
+            StringClass::FormatString => "format string",
+            StringClass::Other => "other",
+        }
+    }
+}
+
+/// A printable string found in the data of the binary.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BinaryString {
+    /// The address of the first character of the string.
+    pub addr: u64,
+    /// The name of the section containing the string.
+    pub section: String,
+    /// The content of the string.
+    pub value: String,
+    /// The kind of content of the string.
+    pub class: StringClass,
+    /// The starting addresses of the functions referencing the string.
+    pub referenced_by: Vec<u64>,
+}
+
+/// A string referenced by the code reachable from an API.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StringRef {
+    /// The content of the string.
+    pub value: String,
+    /// The kind of content of the string.
+    pub class: StringClass,
+    /// The names of the functions referencing the string.
+    pub functions: Vec<String>,
+}
+
+/// Extract the printable, NUL-terminated strings of the data sections of the binary.
+///
+/// # Arguments
+///
+/// * `elf` - The ELF object representing the binary.
+/// * `buffer` - The buffer containing the binary data of the ELF file.
+/// * `min_len` - The minimum number of characters of a string.
+///
+/// # Returns
+///
+/// Returns the classified strings, sorted by address.
+pub fn extract_strings(elf: &Elf, buffer: &[u8], min_len: usize) -> Vec<BinaryString> {
+    let mut strings = Vec::new();
+    for sec in elf.section_headers.iter() {
+        let Some(name) = elf.shdr_strtab.get_at(sec.sh_name) else {
+            continue;
+        };
+        if !STRING_SECTIONS.contains(&name) || sec.sh_type == SHT_NOBITS {
+            continue;
+        }
+        let start = sec.sh_offset as usize;
+        let Some(data) = buffer.get(start..start + sec.sh_size as usize) else {
+            continue;
+        };
+
+        let mut begin = 0;
+        for (i, &byte) in data.iter().enumerate() {
+            if byte.is_ascii_graphic() || matches!(byte, b' ' | b'\t' | b'\n' | b'\r') {
+                continue;
+            }
+            if byte == 0 && i - begin >= min_len {
+                let value = String::from_utf8_lossy(&data[begin..i]).into_owned();
+                strings.push(BinaryString {
+                    addr: sec.sh_addr + begin as u64,
+                    section: name.to_string(),
+                    class: classify(&value),
+                    value,
+                    referenced_by: Vec::new(),
+                });
+            }
+            begin = i + 1;
+        }
+    }
+    strings.sort_by_key(|string| string.addr);
+    strings
+}
+
+/// Link each string to the functions referencing it, from the cross-references of the binary.
+///
+/// A reference into the middle of a string designates its suffix, which is a string on its
+/// own: this happens when the linker merges string tails, or when the bytes before a string
+/// are printable by chance. Such suffixes are added to the strings.
+pub fn link_references(strings: &mut Vec<BinaryString>, xrefs: &XrefDb, graph: &CallGraph) {
+    let mut by_addr: BTreeMap<u64, usize> = strings
+        .iter()
+        .enumerate()
+        .map(|(i, string)| (string.addr, i))
+        .collect();
+    for func in graph.functions() {
+        for data in xrefs.data_refs(func.start_addr) {
+            let Some((_, &i)) = by_addr.range(..=data.addr).next_back() else {
+                continue;
+            };
+            let string = &strings[i];
+            let offset = (data.addr - string.addr) as usize;
+            if offset >= string.value.len() || !string.value.is_char_boundary(offset) {
+                continue;
+            }
+            let i = if offset == 0 {
+                i
+            } else {
+                let value = string.value[offset..].to_string();
+                let suffix = BinaryString {
+                    addr: data.addr,
+                    section: string.section.clone(),
+                    class: classify(&value),
+                    value,
+                    referenced_by: Vec::new(),
+                };
+                strings.push(suffix);
+                by_addr.insert(data.addr, strings.len() - 1);
+                strings.len() - 1
+            };
+            if !strings[i].referenced_by.contains(&func.start_addr) {
+                strings[i].referenced_by.push(func.start_addr);
+            }
+        }
+    }
+    strings.sort_by_key(|string| string.addr);
+}
+
+/// Collect the classified strings referenced by the functions reachable from an API.
+///
+/// # Arguments
+///
+/// * `strings` - The strings of the binary, linked to their references.
+/// * `graph` - The call graph of the binary.
+/// * `addr` - The starting address of the API.
+///
+/// # Returns
+///
+/// Returns the strings of any class but [`StringClass::Other`], with the referencing functions.
+pub fn api_strings(strings: &[BinaryString], graph: &CallGraph, addr: u64) -> Vec<StringRef> {
+    let reachable = graph.reachable(&[addr]);
+    strings
+        .iter()
+        .filter(|string| string.class != StringClass::Other)
+        .filter_map(|string| {
+            let functions: Vec<String> = string
+                .referenced_by
+                .iter()
+                .filter(|func| reachable.contains(func))
+                .filter_map(|&func| graph.function(func))
+                .map(|func| demangle_api_name(&func.name))
+                .collect();
+            (!functions.is_empty()).then(|| StringRef {
+                value: string.value.clone(),
+                class: string.class,
+                functions,
+            })
+        })
+        .collect()
+}
+
+// An IPv4 address, possibly followed by a port, not being part of a longer dotted sequence.
+pub(crate) const IPV4_PATTERN: &str =
+    r"(?:^|[^\d.])(?P<host>\d{1,3}\.\d{1,3}\.\d{1,3}\.\d{1,3})(?::(?P<port>\d{1,5}))?(?:$|[^\d.])";
+
+// Whether a dotted quad is a plausible host address. Octets never have leading zeros, and
+// quads starting with 0, 1 or 2 are mostly the object identifiers (1.3.6.1, 2.5.4.3) of X.509
+// and SNMP code.
+pub(crate) fn is_ipv4(quad: &str) -> bool {
+    let octets: Vec<&str> = quad.split('.').collect();
+    octets.len() == 4
+        && octets.iter().all(|octet| {
+            octet.parse::<u8>().is_ok() && (octet.len() == 1 || !octet.starts_with('0'))
+        })
+        && octets[0].parse::<u8>().is_ok_and(|first| first > 2)
+}
+
+/// Classify the content of a string.
+pub fn classify(value: &str) -> StringClass {
+    static PATTERNS: OnceLock<[Regex; 3]> = OnceLock::new();
+    let [url, ip, format] = PATTERNS.get_or_init(|| {
+        [
+            Regex::new(r"\b[a-zA-Z][a-zA-Z0-9+.-]*://[^\s]+").unwrap(),
+            Regex::new(IPV4_PATTERN).unwrap(),
+            Regex::new(r"%[-+ #0]*(\d+|\*)?(\.(\d+|\*))?(hh|h|ll|l|z|j|t|L)?[diouxXeEfFgGcsp]")
+                .unwrap(),
+        ]
+    });
+    let trimmed = value.trim();
+
+    if url.is_match(trimmed) {
+        return StringClass::Url;
+    }
+    let is_ip = ip.captures_iter(trimmed).any(|caps| is_ipv4(&caps["host"]));
+    if is_ip {
+        return StringClass::IpAddress;
+    }
+    let is_command = SHELL_COMMANDS
+        .iter()
+        .any(|command| trimmed.starts_with(command))
+        || [" && ", " | ", " || ", "2>/dev/null", "; "]
+            .iter()
+            .any(|separator| trimmed.contains(separator) && trimmed.contains('/'));
+    if is_command {
+        return StringClass::ShellCommand;
+    }
+    let is_path = !trimmed.contains(' ')
+        && (["/", "./", "../", "~/"]
+            .iter()
+            .any(|prefix| trimmed.starts_with(prefix) && trimmed.len() > prefix.len())
+            || trimmed.rsplit_once('.').is_some_and(|(stem, ext)| {
+                !stem.is_empty()
+                    && stem
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || "_-/.".contains(c))
+                    && FILE_EXTENSIONS.contains(&ext)
+            }));
+    if is_path {
+        return StringClass::FilePath;
+    }
+    if format.is_match(trimmed) {
+        return StringClass::FormatString;
+    }
+    StringClass::Other
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf_utils::read_elf_file;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify("https://example.com/api/v1"), StringClass::Url);
+        assert_eq!(classify("mqtt://broker.local:1883"), StringClass::Url);
+        assert_eq!(classify("192.168.1.1:8080"), StringClass::IpAddress);
+        assert_eq!(classify("version 1.2.3.4000"), StringClass::Other);
+        assert_eq!(classify("1.3.6.1"), StringClass::Other);
+        assert_eq!(classify("011.012.012.113"), StringClass::Other);
+        assert_eq!(classify("1.3.6.1.4.1.311"), StringClass::Other);
+        assert_eq!(classify("/bin/sh -c reboot"), StringClass::ShellCommand);
+        assert_eq!(classify("rm -rf /tmp/update"), StringClass::ShellCommand);
+        assert_eq!(classify("/dev/video0"), StringClass::FilePath);
+        assert_eq!(classify("foo.txt"), StringClass::FilePath);
+        assert_eq!(
+            classify("Read %d bytes from %s\n"),
+            StringClass::FormatString
+        );
+        assert_eq!(classify("100%"), StringClass::Other);
+        assert_eq!(classify("Hello, world!"), StringClass::Other);
+    }
+
+    #[test]
+    fn test_extract_strings() {
+        let buffer = read_elf_file("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        let graph = CallGraph::build(&elf, &buffer, false).unwrap();
+        let xrefs = XrefDb::build(&elf, &buffer, &graph);
+        let mut strings = extract_strings(&elf, &buffer, 4);
+        link_references(&mut strings, &xrefs, &graph);
+
+        let file = strings
+            .iter()
+            .find(|string| string.value == "foo.txt")
+            .unwrap();
+        assert_eq!(file.class, StringClass::FilePath);
+        assert_eq!(file.section, ".rodata");
+
+        let write_on_drive = graph
+            .functions()
+            .find(|func| func.name == "writeOnDrive")
+            .unwrap();
+        assert!(file.referenced_by.contains(&write_on_drive.start_addr));
+        let refs = api_strings(&strings, &graph, write_on_drive.start_addr);
+        assert!(refs
+            .iter()
+            .any(|string| string.value == "foo.txt" && string.functions == ["writeOnDrive"]));
+    }
+}
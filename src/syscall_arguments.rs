@@ -0,0 +1,392 @@
+use std::collections::{BTreeMap, HashMap};
+
+use goblin::elf::{section_header::SHT_NOBITS, Elf};
+use serde_json::{json, Value};
+
+use crate::{
+    arguments::{call_arguments, CallArguments},
+    call_graph::CallGraph,
+    error::Result,
+    xrefs::XrefDb,
+};
+
+// The `dirfd` of the `*at` functions designating the working directory.
+const AT_FDCWD: i32 = -100;
+
+// The address families of the sockets.
+const AF_INET: u64 = 2;
+const AF_INET6: u64 = 10;
+
+// How a recovered argument of a key function is decoded.
+#[derive(Clone, Copy)]
+enum Decoder {
+    // A path, or another string, passed by address.
+    Path,
+    // The `fopen` mode string.
+    Mode,
+    // The `O_*` flags of `open`.
+    OpenFlags,
+    // The directory file descriptor of `openat`.
+    Directory,
+    // The `AF_*` domain of `socket`.
+    Domain,
+    // The `SOCK_*` type and flags of `socket`.
+    SocketType,
+    // The `IPPROTO_*` protocol of `socket`.
+    Protocol,
+    // The `struct sockaddr` of `connect` and `bind`, passed by address.
+    SocketAddress,
+    // The `PROT_*` protections of `mmap` and `mprotect`.
+    Protection,
+    // The `MAP_*` flags of `mmap`.
+    MapFlags,
+}
+
+// The arguments of a key function: their index, name and decoder.
+type Arguments = &'static [(usize, &'static str, Decoder)];
+
+const OPEN: Arguments = &[(0, "path", Decoder::Path), (1, "flags", Decoder::OpenFlags)];
+const OPENAT: Arguments = &[
+    (0, "dirfd", Decoder::Directory),
+    (1, "path", Decoder::Path),
+    (2, "flags", Decoder::OpenFlags),
+];
+const CREAT: Arguments = &[(0, "path", Decoder::Path)];
+const FOPEN: Arguments = &[(0, "path", Decoder::Path), (1, "mode", Decoder::Mode)];
+const SOCKET: Arguments = &[
+    (0, "domain", Decoder::Domain),
+    (1, "type", Decoder::SocketType),
+    (2, "protocol", Decoder::Protocol),
+];
+const CONNECT: Arguments = &[(1, "address", Decoder::SocketAddress)];
+const MMAP: Arguments = &[
+    (2, "prot", Decoder::Protection),
+    (3, "flags", Decoder::MapFlags),
+];
+const MPROTECT: Arguments = &[(2, "prot", Decoder::Protection)];
+
+// The key functions whose arguments are recovered.
+const KEY_FUNCTIONS: [(&str, Arguments); 16] = [
+    ("open", OPEN),
+    ("open64", OPEN),
+    ("__open_2", OPEN),
+    ("__open64_2", OPEN),
+    ("openat", OPENAT),
+    ("openat64", OPENAT),
+    ("creat", CREAT),
+    ("creat64", CREAT),
+    ("fopen", FOPEN),
+    ("fopen64", FOPEN),
+    ("socket", SOCKET),
+    ("connect", CONNECT),
+    ("bind", CONNECT),
+    ("mmap", MMAP),
+    ("mmap64", MMAP),
+    ("mprotect", MPROTECT),
+];
+
+// The bits of the `open` flags, after the access mode.
+const OPEN_FLAGS: [(u64, &str); 11] = [
+    (0o100, "O_CREAT"),
+    (0o200, "O_EXCL"),
+    (0o400, "O_NOCTTY"),
+    (0o1000, "O_TRUNC"),
+    (0o2000, "O_APPEND"),
+    (0o4000, "O_NONBLOCK"),
+    (0o4010000, "O_SYNC"),
+    (0o10000, "O_DSYNC"),
+    (0o200000, "O_DIRECTORY"),
+    (0o400000, "O_NOFOLLOW"),
+    (0o2000000, "O_CLOEXEC"),
+];
+
+// The address families of the sockets.
+const DOMAINS: [(u64, &str); 8] = [
+    (1, "AF_UNIX"),
+    (AF_INET, "AF_INET"),
+    (AF_INET6, "AF_INET6"),
+    (16, "AF_NETLINK"),
+    (17, "AF_PACKET"),
+    (29, "AF_CAN"),
+    (31, "AF_BLUETOOTH"),
+    (40, "AF_VSOCK"),
+];
+
+// The types of the sockets, in the low bits of the type argument.
+const SOCKET_TYPES: [(u64, &str); 5] = [
+    (1, "SOCK_STREAM"),
+    (2, "SOCK_DGRAM"),
+    (3, "SOCK_RAW"),
+    (4, "SOCK_RDM"),
+    (5, "SOCK_SEQPACKET"),
+];
+
+// The flags of the type of the sockets.
+const SOCKET_FLAGS: [(u64, &str); 2] = [(0o4000, "SOCK_NONBLOCK"), (0o2000000, "SOCK_CLOEXEC")];
+
+// The protocols of the sockets, 0 being the default one of the type.
+const PROTOCOLS: [(u64, &str); 7] = [
+    (0, "default"),
+    (1, "IPPROTO_ICMP"),
+    (6, "IPPROTO_TCP"),
+    (17, "IPPROTO_UDP"),
+    (58, "IPPROTO_ICMPV6"),
+    (132, "IPPROTO_SCTP"),
+    (255, "IPPROTO_RAW"),
+];
+
+// The memory protections.
+const PROTECTIONS: [(u64, &str); 3] = [(1, "PROT_READ"), (2, "PROT_WRITE"), (4, "PROT_EXEC")];
+
+// The flags of the mappings.
+const MAP_FLAGS: [(u64, &str); 8] = [
+    (0x1, "MAP_SHARED"),
+    (0x2, "MAP_PRIVATE"),
+    (0x10, "MAP_FIXED"),
+    (0x20, "MAP_ANONYMOUS"),
+    (0x100, "MAP_GROWSDOWN"),
+    (0x2000, "MAP_LOCKED"),
+    (0x8000, "MAP_POPULATE"),
+    (0x40000, "MAP_HUGETLB"),
+];
+
+/// A call to a key function, with the arguments recovered at its call site.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyscallArguments {
+    /// The function called, e.g. `openat`.
+    pub function: &'static str,
+    /// The address of the call instruction.
+    pub site: u64,
+    /// The starting address of the function performing the call.
+    pub caller: u64,
+    /// The arguments recovered, decoded, by name, e.g. `flags`: `O_WRONLY|O_CREAT`.
+    pub arguments: BTreeMap<&'static str, Value>,
+}
+
+impl SyscallArguments {
+    /// Return the call as an entry of the `syscall arguments` of a flow of the flow call
+    /// manifest.
+    pub fn to_json(&self) -> Value {
+        let mut entry = json!({
+            "function": self.function,
+            "site": format!("{:#x}", self.site),
+        });
+        if let Some(entry) = entry.as_object_mut() {
+            for (name, value) in &self.arguments {
+                entry.insert(name.to_string(), value.clone());
+            }
+        }
+        entry
+    }
+}
+
+/// Recover the arguments of the calls to the key functions of the syscalls: the path and
+/// flags of `open`, `openat`, `creat` and `fopen`, the domain, type and protocol of `socket`,
+/// the address and port of `connect` and `bind`, and the protections and flags of `mmap` and
+/// `mprotect`.
+///
+/// The arguments are the constants of the registers set before each call, see
+/// [`call_arguments`]. The `struct sockaddr` of `connect` and `bind` is read from the binary
+/// when it is a global; otherwise its port is the constant passed to the last `htons` of the
+/// caller before the call.
+///
+/// # Arguments
+///
+/// * `elf` - The ELF object representing the binary.
+/// * `buffer` - The buffer containing the binary data of the ELF file.
+/// * `graph` - The call graph of the binary.
+/// * `xrefs` - The cross-references of the functions, holding the strings they reference.
+///
+/// # Returns
+///
+/// Returns a `Result` containing the calls with at least one argument recovered, by address of
+/// the calling function and sorted by call site.
+pub fn syscall_arguments(
+    elf: &Elf,
+    buffer: &[u8],
+    graph: &CallGraph,
+    xrefs: &XrefDb,
+) -> Result<HashMap<u64, Vec<SyscallArguments>>> {
+    let mut functions: Vec<&'static str> = KEY_FUNCTIONS.iter().map(|(name, _)| *name).collect();
+    functions.push("htons");
+    let calls = call_arguments(elf, buffer, graph, &functions)?;
+
+    let mut found: HashMap<u64, Vec<SyscallArguments>> = HashMap::new();
+    let mut ports: HashMap<u64, u64> = HashMap::new();
+    for call in &calls {
+        if call.function == "htons" {
+            match call.values[0] {
+                Some(port) => ports.insert(call.caller, port & 0xffff),
+                None => ports.remove(&call.caller),
+            };
+            continue;
+        }
+        let Some((_, decoders)) = KEY_FUNCTIONS
+            .iter()
+            .find(|(name, _)| *name == call.function)
+        else {
+            continue;
+        };
+        let mut arguments = BTreeMap::new();
+        for &(index, name, decoder) in decoders.iter() {
+            let value = match decoder {
+                Decoder::Path | Decoder::Mode => call.string(index, xrefs).map(Value::from),
+                Decoder::SocketAddress => socket_address(elf, buffer, call)
+                    .or_else(|| ports.get(&call.caller).map(|port| json!({ "port": port }))),
+                _ => call.values[index].map(|value| decode(decoder, value)),
+            };
+            if let Some(value) = value {
+                arguments.insert(name, value);
+            }
+        }
+        if !arguments.is_empty() {
+            found
+                .entry(call.caller)
+                .or_default()
+                .push(SyscallArguments {
+                    function: call.function,
+                    site: call.site,
+                    caller: call.caller,
+                    arguments,
+                });
+        }
+    }
+    Ok(found)
+}
+
+// Decode a constant argument.
+fn decode(decoder: Decoder, value: u64) -> Value {
+    let value = value & 0xffff_ffff;
+    match decoder {
+        Decoder::OpenFlags => {
+            let access = match value & 0o3 {
+                0 => "O_RDONLY",
+                1 => "O_WRONLY",
+                2 => "O_RDWR",
+                _ => "O_ACCMODE",
+            };
+            Value::from(flag_names(Some(access), value & !0o3, &OPEN_FLAGS))
+        }
+        Decoder::Directory if value as i32 == AT_FDCWD => Value::from("AT_FDCWD"),
+        Decoder::Domain => named(value, &DOMAINS),
+        Decoder::SocketType => {
+            let kind = SOCKET_TYPES
+                .iter()
+                .find(|(kind, _)| *kind == value & 0xf)
+                .map(|(_, name)| *name);
+            Value::from(flag_names(kind, value & !0xf, &SOCKET_FLAGS))
+        }
+        Decoder::Protocol => named(value, &PROTOCOLS),
+        Decoder::Protection if value == 0 => Value::from("PROT_NONE"),
+        Decoder::Protection => Value::from(flag_names(None, value, &PROTECTIONS)),
+        Decoder::MapFlags => Value::from(flag_names(None, value, &MAP_FLAGS)),
+        _ => Value::from(value),
+    }
+}
+
+// The name of a constant, or the constant itself when it has no name.
+fn named(value: u64, names: &[(u64, &str)]) -> Value {
+    names
+        .iter()
+        .find(|(known, _)| *known == value)
+        .map_or(Value::from(value), |(_, name)| Value::from(*name))
+}
+
+// The names of the flags set in `value`, after `first`, joined by `|`; the unknown bits are
+// written in hexadecimal.
+fn flag_names(first: Option<&str>, value: u64, flags: &[(u64, &str)]) -> String {
+    let mut names: Vec<String> = first.into_iter().map(str::to_string).collect();
+    let mut rest = value;
+    for &(bits, name) in flags {
+        if bits != 0 && value & bits == bits && rest & bits != 0 {
+            names.push(name.to_string());
+            rest &= !bits;
+        }
+    }
+    if rest != 0 || names.is_empty() {
+        names.push(format!("{:#x}", rest));
+    }
+    names.join("|")
+}
+
+// The family, port and address of the `struct sockaddr` a call passes as its second argument,
+// when it is stored in the binary.
+fn socket_address(elf: &Elf, buffer: &[u8], call: &CallArguments) -> Option<Value> {
+    let addr = call.values[1]?;
+    let bytes = data_at(elf, buffer, addr, 8)?;
+    let family = u64::from(u16::from_le_bytes([bytes[0], bytes[1]]));
+    let port = u16::from_be_bytes([bytes[2], bytes[3]]);
+    let mut address = json!({ "family": named(family, &DOMAINS) });
+    if family == AF_INET || family == AF_INET6 {
+        address["port"] = Value::from(port);
+    }
+    if family == AF_INET {
+        let ip = std::net::Ipv4Addr::new(bytes[4], bytes[5], bytes[6], bytes[7]);
+        address["address"] = Value::from(ip.to_string());
+    }
+    Some(address)
+}
+
+// The `len` bytes of the binary loaded at `addr`.
+fn data_at<'a>(elf: &Elf, buffer: &'a [u8], addr: u64, len: usize) -> Option<&'a [u8]> {
+    let section = elf.section_headers.iter().find(|sec| {
+        sec.sh_type != SHT_NOBITS
+            && sec.sh_addr != 0
+            && sec.sh_addr <= addr
+            && addr + len as u64 <= sec.sh_addr + sec.sh_size
+    })?;
+    let start = (section.sh_offset + addr - section.sh_addr) as usize;
+    buffer.get(start..start + len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf_utils::read_elf_file;
+
+    #[test]
+    fn test_syscall_arguments() {
+        let buffer = read_elf_file("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        let graph = CallGraph::build(&elf, &buffer, false).unwrap();
+        let xrefs = XrefDb::build(&elf, &buffer, &graph);
+        let found = syscall_arguments(&elf, &buffer, &graph, &xrefs).unwrap();
+        let calls = |name: &str| {
+            let func = graph.functions().find(|func| func.name == name).unwrap();
+            found[&func.start_addr].clone()
+        };
+
+        let write = calls("writeOnDrive");
+        assert_eq!(
+            write[0].to_json(),
+            json!({"function": "fopen64", "site": "0x1b216", "path": "foo.txt", "mode": "wb"})
+        );
+        let urandom = &calls("randit")[0].arguments;
+        assert_eq!(urandom["path"], "/dev/urandom");
+        assert_eq!(urandom["flags"], "O_RDONLY");
+        let socket = &calls("Curl_ipv6works")[0].arguments;
+        assert_eq!(socket["domain"], "AF_INET6");
+        assert_eq!(socket["type"], "SOCK_DGRAM");
+        assert!(calls("Curl_fopen")
+            .iter()
+            .any(|call| call.arguments.get("flags") == Some(&json!("O_WRONLY|O_CREAT|O_EXCL"))));
+    }
+
+    #[test]
+    fn test_decode() {
+        let flags = decode(Decoder::OpenFlags, 0o1101);
+        assert_eq!(flags, "O_WRONLY|O_CREAT|O_TRUNC");
+        assert_eq!(decode(Decoder::OpenFlags, 0o4010000), "O_RDONLY|O_SYNC");
+        assert_eq!(decode(Decoder::OpenFlags, 0o40000000), "O_RDONLY|0x800000");
+        assert_eq!(decode(Decoder::Directory, AT_FDCWD as u64), "AT_FDCWD");
+        assert_eq!(decode(Decoder::Directory, 3), 3);
+        let kind = decode(Decoder::SocketType, 0o2004001);
+        assert_eq!(kind, "SOCK_STREAM|SOCK_NONBLOCK|SOCK_CLOEXEC");
+        assert_eq!(decode(Decoder::Protocol, 6), "IPPROTO_TCP");
+        assert_eq!(decode(Decoder::Domain, 99), 99);
+        assert_eq!(decode(Decoder::Protection, 0), "PROT_NONE");
+        assert_eq!(decode(Decoder::Protection, 5), "PROT_READ|PROT_EXEC");
+        let map = decode(Decoder::MapFlags, 0x22);
+        assert_eq!(map, "MAP_PRIVATE|MAP_ANONYMOUS");
+    }
+}
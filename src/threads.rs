@@ -0,0 +1,151 @@
+use goblin::elf::Elf;
+use serde_json::{json, Value};
+
+use crate::{
+    arguments::{call_arguments, ARGUMENT_REGISTERS},
+    call_graph::CallGraph,
+    cleanup::demangle_api_name,
+    elf_utils::API,
+    error::Result,
+};
+
+/// The functions creating threads, with the register holding the start routine given to them.
+///
+/// `__pthread_create_2_1` is the name of `pthread_create` in statically linked glibc, and the
+/// glibc `clone` takes the function to run first, unlike the raw system call.
+pub const THREAD_FUNCTIONS: [(&str, &str); 5] = [
+    ("pthread_create", "%rdx"),
+    ("__pthread_create_2_1", "%rdx"),
+    ("thrd_create", "%rsi"),
+    ("clone", "%rdi"),
+    ("__clone", "%rdi"),
+];
+
+/// A call creating a thread.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ThreadCreation {
+    /// The function called to create the thread, e.g. `pthread_create`.
+    pub function: String,
+    /// The address of the call instruction.
+    pub site: u64,
+    /// The name of the function performing the call.
+    pub caller: String,
+    /// The address of the start routine of the thread, when it is a constant.
+    pub routine: Option<u64>,
+    /// The name of the start routine, when it is a function of the binary.
+    pub routine_name: Option<String>,
+    /// The names of the APIs whose flow creates the thread.
+    pub apis: Vec<String>,
+}
+
+impl ThreadCreation {
+    /// Return the creation as an entry of the `threads` of the flow call manifest.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "created by": self.function,
+            "site": format!("{:#x}", self.site),
+            "caller": self.caller,
+            "start routine": self.routine_name,
+            "start address": self.routine.map(|addr| format!("{:#x}", addr)),
+            "APIs": self.apis,
+        })
+    }
+}
+
+/// Find the calls creating threads and resolve their start routine.
+///
+/// The start routine is the constant held by the argument register before the call, as set by
+/// the `lea` of a function address; a routine read from a structure or passed by the caller is
+/// left unresolved.
+///
+/// # Arguments
+///
+/// * `elf` - The ELF object representing the binary.
+/// * `buffer` - The buffer containing the binary data of the ELF file.
+/// * `graph` - The call graph of the binary.
+/// * `api_list` - The APIs found, to tell which of them create each thread.
+///
+/// # Returns
+///
+/// Returns a `Result` containing the thread creations, sorted by call site.
+pub fn thread_creations(
+    elf: &Elf,
+    buffer: &[u8],
+    graph: &CallGraph,
+    api_list: &[API],
+) -> Result<Vec<ThreadCreation>> {
+    let flows: Vec<_> = api_list
+        .iter()
+        .map(|api| (api.name.as_str(), graph.reachable(&[api.start_addr])))
+        .collect();
+    let functions = THREAD_FUNCTIONS.map(|(function, _)| function);
+    let mut creations = Vec::new();
+    for call in call_arguments(elf, buffer, graph, &functions)? {
+        let (_, reg) = thread_function(call.function).unwrap();
+        let index = ARGUMENT_REGISTERS.iter().position(|r| *r == reg).unwrap();
+        let routine = call.values[index].filter(|&addr| addr != 0);
+        creations.push(ThreadCreation {
+            function: call.function.to_string(),
+            site: call.site,
+            caller: graph
+                .function(call.caller)
+                .map(|func| demangle_api_name(&func.name))
+                .unwrap_or_default(),
+            routine,
+            routine_name: routine
+                .and_then(|addr| graph.function(addr))
+                .map(|routine| demangle_api_name(&routine.name)),
+            apis: flows
+                .iter()
+                .filter(|(_, reachable)| reachable.contains(&call.caller))
+                .map(|(name, _)| name.to_string())
+                .collect(),
+        });
+    }
+    creations.sort_by_key(|creation| creation.site);
+    Ok(creations)
+}
+
+// Return the thread function a called symbol stands for, with its start routine register.
+fn thread_function(name: &str) -> Option<(&'static str, &'static str)> {
+    let name = name.split('@').next().unwrap_or(name);
+    THREAD_FUNCTIONS
+        .iter()
+        .find(|(function, _)| *function == name)
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf_utils::read_elf_file;
+
+    #[test]
+    fn test_thread_creations() {
+        assert_eq!(
+            thread_function("pthread_create@GLIBC_2.34"),
+            Some(("pthread_create", "%rdx"))
+        );
+        assert_eq!(thread_function("clone"), Some(("clone", "%rdi")));
+        assert_eq!(thread_function("pthread_join"), None);
+
+        let buffer = read_elf_file("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        let graph = CallGraph::build(&elf, &buffer, false).unwrap();
+        let creations = thread_creations(&elf, &buffer, &graph, &[]).unwrap();
+        let curl = creations
+            .iter()
+            .find(|creation| creation.routine_name.as_deref() == Some("curl_thread_create_thunk"))
+            .unwrap();
+        assert_eq!(curl.function, "pthread_create");
+        assert_eq!(curl.caller, "Curl_thread_create");
+        assert!(curl.apis.is_empty());
+        assert_eq!(curl.to_json()["created by"], "pthread_create");
+
+        let roots = graph.root_apis(creations.iter().filter_map(|creation| creation.routine));
+        assert!(roots
+            .iter()
+            .any(|root| root.name == "curl_thread_create_thunk"));
+        assert!(roots.len() <= creations.len());
+    }
+}
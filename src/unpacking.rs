@@ -0,0 +1,172 @@
+use std::{
+    env, fs,
+    process::{self, Command, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use serde_json::{json, Value};
+
+use crate::{
+    elf_utils::sha256_hex,
+    error::{Error, Result},
+};
+
+/// The program unpacking UPX binaries, looked for in the `PATH`.
+pub const DEFAULT_UPX: &str = "upx";
+
+// The magic of the headers UPX writes after the program headers and at the end of the file.
+const UPX_MAGIC: &[u8] = b"UPX!";
+
+// Number of bytes at the start and at the end of the file searched for the magic, so that the
+// strings of a binary that is not packed are not mistaken for a header.
+const HEADER_WINDOW: usize = 4096;
+
+// The prefix of the version notice UPX leaves in the stub, e.g. `$Id: UPX 4.22 Copyright ...`.
+const UPX_ID: &[u8] = b"$Id: UPX ";
+
+// Number of binaries unpacked by the process, to name their scratch directories.
+static UNPACKED: AtomicUsize = AtomicUsize::new(0);
+
+/// How a binary was packed, recorded in the basic information manifest once it is unpacked.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Packing {
+    /// The packer, e.g. `UPX`.
+    pub packer: String,
+    /// The version of the packer, when the stub names it.
+    pub version: Option<String>,
+    /// The SHA-256 digest of the packed binary.
+    pub sha256: String,
+}
+
+impl Packing {
+    /// Return the packing as the `packing` section of the basic information manifest.
+    pub fn to_json(&self) -> Value {
+        let mut json = json!({
+            "packer": self.packer,
+            "packed sha256": self.sha256,
+        });
+        if let Some(version) = &self.version {
+            json["version"] = json!(version);
+        }
+        json
+    }
+}
+
+/// Detect a binary packed with UPX, from the magic of its headers.
+///
+/// # Returns
+///
+/// Returns the packing of the binary, `None` if it is not packed with UPX.
+pub fn detect_upx(buffer: &[u8]) -> Option<Packing> {
+    let head = &buffer[..buffer.len().min(HEADER_WINDOW)];
+    let tail = &buffer[buffer.len().saturating_sub(HEADER_WINDOW)..];
+    find(head, UPX_MAGIC).or_else(|| find(tail, UPX_MAGIC))?;
+    let version = find(buffer, UPX_ID).and_then(|start| {
+        let rest = &buffer[start + UPX_ID.len()..];
+        let end = rest
+            .iter()
+            .position(|byte| !byte.is_ascii_digit() && *byte != b'.')?;
+        (end > 0).then(|| String::from_utf8_lossy(&rest[..end]).to_string())
+    });
+    Some(Packing {
+        packer: "UPX".to_string(),
+        version,
+        sha256: sha256_hex(buffer),
+    })
+}
+
+/// Unpack a UPX binary with the `upx` program, in a scratch directory.
+///
+/// # Arguments
+///
+/// * `buffer` - The content of the packed binary.
+/// * `program` - The UPX program, e.g. [`DEFAULT_UPX`].
+///
+/// # Returns
+///
+/// Returns a `Result` containing the content of the unpacked binary.
+///
+/// # Errors
+///
+/// Returns [`Error::Unpacking`] if the program cannot be run or fails to unpack the binary.
+pub fn unpack_upx(buffer: &[u8], program: &str) -> Result<Vec<u8>> {
+    let dir = env::temp_dir().join(format!(
+        "manifest-unpack-{}-{}",
+        process::id(),
+        UNPACKED.fetch_add(1, Ordering::Relaxed)
+    ));
+    fs::create_dir_all(&dir)?;
+    let unpacked = run_upx(&dir, buffer, program);
+    fs::remove_dir_all(&dir)?;
+    unpacked
+}
+
+// Run `program -d` on the binary copied to `dir`, and read what it unpacked.
+fn run_upx(dir: &std::path::Path, buffer: &[u8], program: &str) -> Result<Vec<u8>> {
+    let packed = dir.join("packed");
+    let unpacked = dir.join("unpacked");
+    fs::write(&packed, buffer)?;
+    let output = Command::new(program)
+        .args(["-d", "-q", "-o"])
+        .arg(&unpacked)
+        .arg(&packed)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|error| Error::Unpacking(format!("cannot run {}: {}", program, error)))?;
+    if !output.status.success() {
+        return Err(Error::Unpacking(format!(
+            "{} failed with {}: {}",
+            program,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    fs::read(&unpacked)
+        .map_err(|error| Error::Unpacking(format!("{} wrote no binary: {}", program, error)))
+}
+
+// Find the first occurrence of `needle` in `buffer`.
+fn find(buffer: &[u8], needle: &[u8]) -> Option<usize> {
+    buffer
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn test_unpack_upx() {
+        let binary = fs::read("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        assert_eq!(detect_upx(&binary), None);
+
+        let mut packed = binary.clone();
+        packed.extend_from_slice(b"\0$Id: UPX 4.22 Copyright (C) 1996-2024\0UPX!\x0d\x16");
+        let packing = detect_upx(&packed).unwrap();
+        assert_eq!(packing.version.as_deref(), Some("4.22"));
+        assert_eq!(packing.to_json()["packer"], "UPX");
+        assert_eq!(packing.to_json()["packed sha256"], sha256_hex(&packed));
+
+        // An upx writing the original binary to the path given with -o.
+        let upx = env::temp_dir().join(format!("fake-upx-{}", process::id()));
+        fs::write(
+            &upx,
+            "#!/bin/sh\ncp ./tests/elf_file/fake-firmware-c-dynamic \"$4\"\n",
+        )
+        .unwrap();
+        fs::set_permissions(&upx, fs::Permissions::from_mode(0o700)).unwrap();
+        assert_eq!(unpack_upx(&packed, upx.to_str().unwrap()).unwrap(), binary);
+        fs::remove_file(&upx).unwrap();
+
+        assert!(matches!(
+            unpack_upx(&packed, "/nonexistent/upx"),
+            Err(Error::Unpacking(_))
+        ));
+        assert!(matches!(
+            unpack_upx(&packed, "false"),
+            Err(Error::Unpacking(_))
+        ));
+    }
+}
@@ -0,0 +1,802 @@
+use std::collections::HashMap;
+
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{Block, List, ListItem, ListState, Paragraph, Wrap},
+    Frame,
+};
+use serde_json::Value;
+
+use crate::{error::Result, manifest_creation::find_manifest};
+
+// The sections of the flow call manifest listed by the viewer, with the kind of their roots.
+const FLOW_SECTIONS: [(&str, &str); 7] = [
+    ("Public APIs flow", "API"),
+    ("Thread flows", "thread"),
+    ("Task flows", "task"),
+    ("Signal handler flows", "signal handler"),
+    ("Startup flows", "constructor"),
+    ("Shutdown flows", "destructor"),
+    ("Interrupt flows", "interrupt"),
+];
+
+// The lines moved by the page keys.
+const PAGE: usize = 10;
+
+/// A flow of the flow call manifest, rooted at an API, a thread, a signal handler...
+#[derive(Clone, Debug, PartialEq)]
+pub struct FlowEntry {
+    /// The kind of the root of the flow, e.g. `API` or `thread`.
+    pub kind: String,
+    /// The name of the function the flow is rooted at.
+    pub name: String,
+    /// The flow, as written in the flow call manifest.
+    pub flow: Value,
+}
+
+// The pane of the viewer the keys act on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Pane {
+    Flows,
+    Tree,
+    Details,
+}
+
+// A node of the call tree.
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    // A function called, with its identifier in the call graph when it has callees there.
+    Call { name: String, id: Option<String> },
+    // A heading grouping other nodes.
+    Group { label: String, children: Vec<Node> },
+}
+
+// A visible line of the call tree.
+#[derive(Clone, Debug, PartialEq)]
+struct TreeLine {
+    depth: usize,
+    node: Node,
+    expanded: bool,
+    // Whether the function is one of its own callers in the tree.
+    recursive: bool,
+}
+
+// The call graph of `call_graph.json`: the callees of each node, and the nodes of the
+// functions of the binary by name.
+#[derive(Default)]
+struct Graph {
+    names: HashMap<String, String>,
+    callees: HashMap<String, Vec<String>>,
+    functions: HashMap<String, String>,
+}
+
+impl Graph {
+    fn from_json(graph: &Value) -> Self {
+        let mut names = HashMap::new();
+        let mut functions = HashMap::new();
+        for node in graph["nodes"].as_array().into_iter().flatten() {
+            let (Some(id), Some(name)) = (node["id"].as_str(), node["name"].as_str()) else {
+                continue;
+            };
+            names.insert(id.to_string(), name.to_string());
+            if node["external"] != true {
+                functions.insert(name.to_string(), id.to_string());
+            }
+        }
+        let mut callees: HashMap<String, Vec<String>> = HashMap::new();
+        for edge in graph["edges"].as_array().into_iter().flatten() {
+            if let (Some(source), Some(target)) = (edge["source"].as_str(), edge["target"].as_str())
+            {
+                callees
+                    .entry(source.to_string())
+                    .or_default()
+                    .push(target.to_string());
+            }
+        }
+        Self {
+            names,
+            callees,
+            functions,
+        }
+    }
+
+    // The node of a call, with the identifier of the function when it has callees.
+    fn call(&self, name: &str, id: Option<&str>) -> Node {
+        let id = id.or_else(|| self.functions.get(name).map(String::as_str));
+        Node::Call {
+            name: name.to_string(),
+            id: id
+                .filter(|id| self.callees.contains_key(*id))
+                .map(str::to_string),
+        }
+    }
+
+    // The callees of a node, in the order of the graph.
+    fn callees_of(&self, id: &str) -> Vec<Node> {
+        self.callees
+            .get(id)
+            .into_iter()
+            .flatten()
+            .map(|callee| {
+                let name = self
+                    .names
+                    .get(callee)
+                    .map_or(callee.as_str(), String::as_str);
+                self.call(name, Some(callee))
+            })
+            .collect()
+    }
+}
+
+/// A terminal browser of the manifests of an output directory: the list of the flows, the call
+/// tree of the selected one and its findings.
+pub struct Viewer {
+    flows: Vec<FlowEntry>,
+    feature: Value,
+    graph: Graph,
+    filter: String,
+    editing: bool,
+    // The indices of the flows matching the filter.
+    visible: Vec<usize>,
+    list: ListState,
+    tree: Vec<TreeLine>,
+    tree_state: ListState,
+    scroll: u16,
+    focus: Pane,
+    quit: bool,
+}
+
+impl Viewer {
+    /// Load the flow call and feature manifests of an output directory, in any format, and
+    /// its call graph when written with `--call-graph`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory holds no flow call or feature manifest.
+    pub fn load(dir: &str) -> Result<Self> {
+        let flow_call = find_manifest(dir, "flow_call")?;
+        let feature = find_manifest(dir, "feature_manifest")?;
+        let graph = find_manifest(dir, "call_graph").ok();
+        Ok(Self::new(&flow_call, &feature, graph.as_ref()))
+    }
+
+    /// Browse the flows of a flow call manifest with the findings of a feature manifest.
+    ///
+    /// # Arguments
+    ///
+    /// * `flow_call` - The flow call manifest.
+    /// * `feature` - The feature manifest.
+    /// * `call_graph` - The `call_graph.json` document, whose callees are expanded in the call
+    ///   trees; the trees are the direct calls of each flow when `None`.
+    pub fn new(flow_call: &Value, feature: &Value, call_graph: Option<&Value>) -> Self {
+        let mut flows = Vec::new();
+        for (section, kind) in FLOW_SECTIONS {
+            for flow in flow_call[section].as_array().into_iter().flatten() {
+                let Some(name) = flow["name"].as_str() else {
+                    continue;
+                };
+                flows.push(FlowEntry {
+                    kind: kind.to_string(),
+                    name: name.to_string(),
+                    flow: flow.clone(),
+                });
+            }
+        }
+        let mut viewer = Self {
+            visible: (0..flows.len()).collect(),
+            flows,
+            feature: feature.clone(),
+            graph: call_graph.map(Graph::from_json).unwrap_or_default(),
+            filter: String::new(),
+            editing: false,
+            list: ListState::default(),
+            tree: Vec::new(),
+            tree_state: ListState::default(),
+            scroll: 0,
+            focus: Pane::Flows,
+            quit: false,
+        };
+        viewer.select(0);
+        viewer
+    }
+
+    /// Return the selected flow, `None` when no flow matches the filter.
+    pub fn selected(&self) -> Option<&FlowEntry> {
+        let index = self.visible.get(self.list.selected()?)?;
+        self.flows.get(*index)
+    }
+
+    /// Return whether the viewer was asked to quit.
+    pub fn should_quit(&self) -> bool {
+        self.quit
+    }
+
+    /// Act on a key: `Tab` switches pane, the arrows (or `j` and `k`) move, `Enter` folds the
+    /// node of the tree, `/` filters the flows by name, and `q` quits.
+    pub fn handle_key(&mut self, key: KeyEvent) {
+        if key.kind != KeyEventKind::Press {
+            return;
+        }
+        if self.editing {
+            match key.code {
+                KeyCode::Char(c) => self.filter.push(c),
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Esc => {
+                    self.filter.clear();
+                    self.editing = false;
+                }
+                KeyCode::Enter => self.editing = false,
+                _ => return,
+            }
+            self.refilter();
+            return;
+        }
+        match key.code {
+            KeyCode::Char('q') => self.quit = true,
+            KeyCode::Esc if !self.filter.is_empty() => {
+                self.filter.clear();
+                self.refilter();
+            }
+            KeyCode::Esc => self.quit = true,
+            KeyCode::Char('/') => {
+                self.editing = true;
+                self.focus = Pane::Flows;
+            }
+            KeyCode::Tab => {
+                self.focus = match self.focus {
+                    Pane::Flows => Pane::Tree,
+                    Pane::Tree => Pane::Details,
+                    Pane::Details => Pane::Flows,
+                }
+            }
+            KeyCode::BackTab => {
+                self.focus = match self.focus {
+                    Pane::Flows => Pane::Details,
+                    Pane::Tree => Pane::Flows,
+                    Pane::Details => Pane::Tree,
+                }
+            }
+            KeyCode::Down | KeyCode::Char('j') => self.move_by(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_by(-1),
+            KeyCode::PageDown => self.move_by(PAGE as isize),
+            KeyCode::PageUp => self.move_by(-(PAGE as isize)),
+            KeyCode::Enter | KeyCode::Char(' ') if self.focus == Pane::Tree => self.toggle(),
+            KeyCode::Right
+                if self.focus == Pane::Tree
+                    && self.current_line().is_some_and(|line| !line.expanded) =>
+            {
+                self.toggle()
+            }
+            KeyCode::Left if self.focus == Pane::Tree => self.collapse_or_parent(),
+            _ => {}
+        }
+    }
+
+    /// Draw the panes of the viewer: the flows, the call tree and the findings of the selected
+    /// flow, over a line of help.
+    pub fn draw(&mut self, frame: &mut Frame) {
+        let [main, help] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        let [flows, tree, details] = Layout::horizontal([
+            Constraint::Percentage(30),
+            Constraint::Percentage(40),
+            Constraint::Percentage(30),
+        ])
+        .areas(main);
+        let highlight = Style::new().add_modifier(Modifier::REVERSED);
+        let bold = Style::new().add_modifier(Modifier::BOLD);
+        let block = |title: String, pane: Pane| {
+            let block = Block::bordered().title(title);
+            match self.focus == pane {
+                true => block.border_style(bold),
+                false => block,
+            }
+        };
+
+        let items: Vec<ListItem> = self
+            .visible
+            .iter()
+            .map(|&index| {
+                let entry = &self.flows[index];
+                ListItem::new(format!("{} ({})", entry.name, entry.kind))
+            })
+            .collect();
+        let title = match (self.editing, self.filter.is_empty()) {
+            (true, _) => format!("Flows /{}_", self.filter),
+            (false, true) => format!("Flows ({})", self.visible.len()),
+            (false, false) => format!("Flows /{} ({})", self.filter, self.visible.len()),
+        };
+        let list = List::new(items)
+            .block(block(title, Pane::Flows))
+            .highlight_style(highlight);
+        frame.render_stateful_widget(list, flows, &mut self.list);
+
+        let items: Vec<ListItem> = self.tree.iter().map(|line| tree_item(line)).collect();
+        let list = List::new(items)
+            .block(block("Call tree".to_string(), Pane::Tree))
+            .highlight_style(highlight);
+        frame.render_stateful_widget(list, tree, &mut self.tree_state);
+
+        let paragraph = Paragraph::new(self.details())
+            .block(block("Findings".to_string(), Pane::Details))
+            .wrap(Wrap { trim: false })
+            .scroll((self.scroll, 0));
+        frame.render_widget(paragraph, details);
+
+        frame.render_widget(
+            Paragraph::new(
+                "q quit  Tab pane  ↑↓ move  Enter fold  ← parent  / filter  Esc clear filter",
+            ),
+            help,
+        );
+    }
+
+    // Move the selection of the focused pane, or scroll the findings.
+    fn move_by(&mut self, offset: isize) {
+        let shift = |current: usize, len: usize| -> usize {
+            (current as isize + offset).clamp(0, len.saturating_sub(1) as isize) as usize
+        };
+        match self.focus {
+            Pane::Flows => {
+                let current = self.list.selected().unwrap_or(0);
+                self.select(shift(current, self.visible.len()));
+            }
+            Pane::Tree => {
+                let current = self.tree_state.selected().unwrap_or(0);
+                if !self.tree.is_empty() {
+                    self.tree_state
+                        .select(Some(shift(current, self.tree.len())));
+                }
+            }
+            Pane::Details => {
+                self.scroll = (self.scroll as isize + offset).clamp(0, u16::MAX as isize) as u16;
+            }
+        }
+    }
+
+    // Select the flow of the given index among those matching the filter, and build its tree.
+    fn select(&mut self, index: usize) {
+        if self.visible.is_empty() {
+            self.list.select(None);
+            self.tree.clear();
+            self.tree_state.select(None);
+            return;
+        }
+        let index = index.min(self.visible.len() - 1);
+        self.list.select(Some(index));
+        self.tree = self.root_lines(&self.flows[self.visible[index]]);
+        self.tree_state.select((!self.tree.is_empty()).then_some(0));
+        self.scroll = 0;
+    }
+
+    // Keep the flows whose name contains the filter, ignoring the case.
+    fn refilter(&mut self) {
+        let filter = self.filter.to_lowercase();
+        self.visible = (0..self.flows.len())
+            .filter(|&index| self.flows[index].name.to_lowercase().contains(&filter))
+            .collect();
+        self.select(0);
+    }
+
+    // The first lines of the tree of a flow: its calls, expanded through the call graph, then
+    // the functions it reaches, by library, folded.
+    fn root_lines(&self, entry: &FlowEntry) -> Vec<TreeLine> {
+        let calls = match self.graph.functions.get(&entry.name) {
+            Some(id) => self.graph.callees_of(id),
+            None => {
+                let mut names: Vec<&str> = Vec::new();
+                for name in entry.flow["syscalls"].as_array().into_iter().flatten() {
+                    if let Some(name) = name.as_str().filter(|name| !names.contains(name)) {
+                        names.push(name);
+                    }
+                }
+                names
+                    .into_iter()
+                    .map(|name| self.graph.call(name, None))
+                    .collect()
+            }
+        };
+        let mut nodes = calls;
+        if let Some(libraries) = entry.flow["calls by library"].as_object() {
+            let children: Vec<Node> = libraries
+                .iter()
+                .map(|(library, functions)| {
+                    let functions: Vec<Node> = functions
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .filter_map(Value::as_str)
+                        .map(|name| self.graph.call(name, None))
+                        .collect();
+                    Node::Group {
+                        label: format!("{} ({})", library, functions.len()),
+                        children: functions,
+                    }
+                })
+                .collect();
+            if !children.is_empty() {
+                nodes.push(Node::Group {
+                    label: "reachable functions, by library".to_string(),
+                    children,
+                });
+            }
+        }
+        let root = self.graph.functions.get(&entry.name).cloned();
+        nodes
+            .into_iter()
+            .map(|node| TreeLine {
+                depth: 0,
+                recursive: matches!(&node, Node::Call { id: Some(id), .. } if Some(id) == root.as_ref()),
+                node,
+                expanded: false,
+            })
+            .collect()
+    }
+
+    fn current_line(&self) -> Option<&TreeLine> {
+        self.tree.get(self.tree_state.selected()?)
+    }
+
+    // Fold or unfold the selected node of the tree.
+    fn toggle(&mut self) {
+        let Some(index) = self.tree_state.selected() else {
+            return;
+        };
+        let Some(line) = self.tree.get(index) else {
+            return;
+        };
+        let depth = line.depth;
+        if line.expanded {
+            let end = (index + 1..self.tree.len())
+                .find(|&i| self.tree[i].depth <= depth)
+                .unwrap_or(self.tree.len());
+            self.tree.drain(index + 1..end);
+            self.tree[index].expanded = false;
+            return;
+        }
+        if line.recursive {
+            return;
+        }
+        let children = match &line.node {
+            Node::Group { children, .. } => children.clone(),
+            Node::Call { id: Some(id), .. } => self.graph.callees_of(id),
+            Node::Call { id: None, .. } => Vec::new(),
+        };
+        if children.is_empty() {
+            return;
+        }
+        let ancestors = self.ancestors(index);
+        let lines: Vec<TreeLine> = children
+            .into_iter()
+            .map(|node| TreeLine {
+                depth: depth + 1,
+                recursive: matches!(&node, Node::Call { id: Some(id), .. } if ancestors.contains(id)),
+                node,
+                expanded: false,
+            })
+            .collect();
+        self.tree.splice(index + 1..index + 1, lines);
+        self.tree[index].expanded = true;
+    }
+
+    // The call graph identifiers of the root of the flow, of the line at `index` and of the
+    // lines it is nested in.
+    fn ancestors(&self, index: usize) -> Vec<String> {
+        let mut ancestors: Vec<String> = self
+            .selected()
+            .and_then(|entry| self.graph.functions.get(&entry.name).cloned())
+            .into_iter()
+            .collect();
+        let mut depth = self.tree[index].depth + 1;
+        for line in self.tree[..=index].iter().rev() {
+            if line.depth < depth {
+                depth = line.depth;
+                if let Node::Call { id: Some(id), .. } = &line.node {
+                    ancestors.push(id.clone());
+                }
+            }
+        }
+        ancestors
+    }
+
+    // Fold the selected node, or select its parent when it is folded.
+    fn collapse_or_parent(&mut self) {
+        let Some(index) = self.tree_state.selected() else {
+            return;
+        };
+        if self.tree[index].expanded {
+            self.toggle();
+            return;
+        }
+        let depth = self.tree[index].depth;
+        if let Some(parent) = (0..index).rev().find(|&i| self.tree[i].depth < depth) {
+            self.tree_state.select(Some(parent));
+        }
+    }
+
+    // The findings of the selected flow: its feature categories and capabilities, the strings,
+    // endpoints and device requests it uses, and the banned calls and vulnerability patterns
+    // it reaches.
+    fn details(&self) -> Vec<Line<'static>> {
+        let Some(entry) = self.selected() else {
+            return vec![Line::from("No flow matches the filter.")];
+        };
+        let name = entry.name.as_str();
+        let feature = &self.feature;
+        let bold = Style::new().add_modifier(Modifier::BOLD);
+        let mut lines = vec![
+            Line::from(format!("{} {}", entry.kind, name)).style(bold),
+            Line::from(format!(
+                "{} direct calls, {} reachable functions",
+                entry.flow["syscalls"].as_array().map_or(0, Vec::len),
+                entry.flow["transitive calls"]
+                    .as_array()
+                    .map_or(0, Vec::len),
+            )),
+        ];
+        let mut section = |title: &str, items: Vec<String>| {
+            if items.is_empty() {
+                return;
+            }
+            lines.push(Line::from(""));
+            lines.push(Line::from(title.to_string()).style(bold));
+            lines.extend(
+                items
+                    .into_iter()
+                    .map(|item| Line::from(format!("  {item}"))),
+            );
+        };
+        let reaches = |finding: &Value, users: &[&str]| {
+            users.iter().any(|field| {
+                finding[*field] == name
+                    || finding[*field]
+                        .as_array()
+                        .is_some_and(|functions| functions.iter().any(|f| f == name))
+            })
+        };
+        let all = |value: &Value| -> Vec<Value> { value.as_array().cloned().unwrap_or_default() };
+        let text = |value: &Value| value.as_str().unwrap_or_default().to_string();
+
+        section("Categories", all(&feature[name]).iter().map(text).collect());
+        let capabilities = feature["capabilities"][name]
+            .as_object()
+            .into_iter()
+            .flatten()
+            .map(|(capability, evidence)| {
+                let evidence: Vec<String> = all(evidence).iter().map(text).collect();
+                format!("{capability}: {}", evidence.join(", "))
+            })
+            .collect();
+        section("Capabilities", capabilities);
+        let strings = all(&feature["strings"][name])
+            .iter()
+            .map(|string| format!("{}: {}", text(&string["class"]), text(&string["value"])))
+            .collect();
+        section("Strings", strings);
+        let endpoints = all(&feature["network endpoints"]["per API"][name])
+            .iter()
+            .map(|endpoint| {
+                let host = endpoint["host"].as_str().unwrap_or("?");
+                match endpoint["port"].as_u64() {
+                    Some(port) => format!("{} {host}:{port}", text(&endpoint["protocol"])),
+                    None => format!("{} {host}", text(&endpoint["protocol"])),
+                }
+            })
+            .collect();
+        section("Network endpoints", endpoints);
+        let ioctls = all(&feature["ioctls"])
+            .iter()
+            .filter(|ioctl| reaches(ioctl, &["used by", "reached from"]))
+            .map(|ioctl| {
+                let request = ioctl["name"]
+                    .as_str()
+                    .map_or_else(|| text(&ioctl["request"]), str::to_string);
+                format!("{request} ({})", text(&ioctl["subsystem"]))
+            })
+            .collect();
+        section("Device requests", ioctls);
+        let calls = all(&feature["dangerous calls"])
+            .iter()
+            .filter(|call| reaches(call, &["caller", "reached from"]))
+            .map(|call| {
+                format!(
+                    "{} at {} in {}",
+                    text(&call["function"]),
+                    text(&call["call site"]),
+                    text(&call["caller"])
+                )
+            })
+            .collect();
+        section("Banned calls", calls);
+        let patterns = all(&feature["vulnerability patterns"])
+            .iter()
+            .filter(|pattern| reaches(pattern, &["caller", "reached from"]))
+            .map(|pattern| {
+                format!(
+                    "{}: {} at {} in {}",
+                    text(&pattern["pattern"]),
+                    text(&pattern["function"]),
+                    text(&pattern["call site"]),
+                    text(&pattern["caller"])
+                )
+            })
+            .collect();
+        section("Vulnerability patterns", patterns);
+        lines
+    }
+}
+
+// The line of the tree of a node, with its indentation and fold marker.
+fn tree_item(line: &TreeLine) -> ListItem<'static> {
+    let indent = "  ".repeat(line.depth);
+    let expandable = match &line.node {
+        Node::Group { children, .. } => !children.is_empty(),
+        Node::Call { id, .. } => id.is_some() && !line.recursive,
+    };
+    let marker = match (expandable, line.expanded) {
+        (true, true) => "▾ ",
+        (true, false) => "▸ ",
+        (false, _) => "  ",
+    };
+    let label = match &line.node {
+        Node::Group { label, .. } => label.clone(),
+        Node::Call { name, .. } if line.recursive => format!("{name} (recursive)"),
+        Node::Call { name, .. } => name.clone(),
+    };
+    ListItem::new(format!("{indent}{marker}{label}"))
+}
+
+/// Browse the manifests of an output directory in the terminal, until `q` is pressed.
+///
+/// # Arguments
+///
+/// * `dir` - The output directory holding the manifests, in any format.
+///
+/// # Errors
+///
+/// Returns an error if the manifests cannot be read, or the terminal cannot be drawn.
+pub fn run_viewer(dir: &str) -> Result<()> {
+    let mut viewer = Viewer::load(dir)?;
+    let mut terminal = ratatui::init();
+    let result = (|| -> Result<()> {
+        while !viewer.should_quit() {
+            terminal.draw(|frame| viewer.draw(frame))?;
+            if let Event::Key(key) = event::read()? {
+                viewer.handle_key(key);
+            }
+        }
+        Ok(())
+    })();
+    ratatui::restore();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::{backend::TestBackend, crossterm::event::KeyModifiers, Terminal};
+    use serde_json::json;
+
+    fn press(viewer: &mut Viewer, code: KeyCode) {
+        viewer.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+    }
+
+    fn labels(viewer: &Viewer) -> Vec<String> {
+        viewer
+            .tree
+            .iter()
+            .map(|line| match &line.node {
+                Node::Call { name, .. } => format!("{}{name}", "  ".repeat(line.depth)),
+                Node::Group { label, .. } => format!("{}{label}", "  ".repeat(line.depth)),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_viewer() {
+        let flow_call = json!({
+            "Public APIs flow": [
+                {
+                    "name": "writeOnDrive",
+                    "syscalls": ["save", "fclose"],
+                    "transitive calls": ["save", "fopen", "fclose"],
+                    "calls by library": { "libc": ["fopen", "fclose"] },
+                },
+                { "name": "turnLampOn", "syscalls": ["ioctl"] },
+            ],
+            "Thread flows": [{ "name": "worker", "syscalls": [] }],
+        });
+        let feature = json!({
+            "writeOnDrive": ["File Manipulation"],
+            "capabilities": { "writeOnDrive": { "filesystem-write": ["fopen"] } },
+            "dangerous calls": [
+                { "function": "strcpy", "call site": "0x10", "caller": "save", "reached from": ["writeOnDrive"] },
+            ],
+        });
+        let graph = json!({
+            "nodes": [
+                { "id": "0x1", "name": "writeOnDrive", "external": false },
+                { "id": "0x2", "name": "save", "external": false },
+                { "id": "fclose", "name": "fclose", "external": true },
+                { "id": "fopen", "name": "fopen", "external": true },
+            ],
+            "edges": [
+                { "source": "0x1", "target": "0x2" },
+                { "source": "0x1", "target": "fclose" },
+                { "source": "0x2", "target": "fopen" },
+                { "source": "0x2", "target": "0x1" },
+            ],
+        });
+        let mut viewer = Viewer::new(&flow_call, &feature, Some(&graph));
+        assert_eq!(viewer.selected().unwrap().name, "writeOnDrive");
+        assert_eq!(
+            labels(&viewer),
+            ["save", "fclose", "reachable functions, by library"]
+        );
+
+        press(&mut viewer, KeyCode::Tab);
+        press(&mut viewer, KeyCode::Enter);
+        assert_eq!(
+            labels(&viewer),
+            [
+                "save",
+                "  fopen",
+                "  writeOnDrive",
+                "fclose",
+                "reachable functions, by library"
+            ]
+        );
+        assert!(viewer.tree[2].recursive);
+        press(&mut viewer, KeyCode::Down);
+        press(&mut viewer, KeyCode::Left);
+        assert_eq!(viewer.tree_state.selected(), Some(0));
+        press(&mut viewer, KeyCode::Left);
+        assert_eq!(labels(&viewer).len(), 3);
+
+        let backend = TestBackend::new(120, 20);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal.draw(|frame| viewer.draw(frame)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(screen.contains("writeOnDrive (API)"));
+        assert!(screen.contains("worker (thread)"));
+        assert!(screen.contains("filesystem-write: fopen"));
+        assert!(screen.contains("strcpy at 0x10 in save"));
+
+        press(&mut viewer, KeyCode::Char('/'));
+        for c in "LAMP".chars() {
+            press(&mut viewer, KeyCode::Char(c));
+        }
+        press(&mut viewer, KeyCode::Enter);
+        assert_eq!(viewer.selected().unwrap().name, "turnLampOn");
+        assert_eq!(labels(&viewer), ["ioctl"]);
+        press(&mut viewer, KeyCode::Esc);
+        assert_eq!(viewer.visible.len(), 3);
+        press(&mut viewer, KeyCode::Char('q'));
+        assert!(viewer.should_quit());
+
+        // Without a call graph, the tree is made of the direct calls.
+        let viewer = Viewer::new(&flow_call, &feature, None);
+        assert_eq!(
+            labels(&viewer),
+            ["save", "fclose", "reachable functions, by library"]
+        );
+        assert_eq!(
+            viewer.tree[0].node,
+            Node::Call {
+                name: "save".to_string(),
+                id: None
+            }
+        );
+    }
+}
@@ -0,0 +1,282 @@
+use std::{cmp::Ordering, fs};
+
+use serde_json::Value;
+
+use crate::{
+    error,
+    libraries::{compare_versions, Library, VersionSource},
+};
+use error::{Error, Result};
+
+/// A security advisory affecting a library.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Advisory {
+    /// The identifier of the advisory, e.g. `CVE-2023-0286` or `OSV-2023-1`.
+    pub id: String,
+    /// The other identifiers of the advisory.
+    pub aliases: Vec<String>,
+    /// A short description of the vulnerability.
+    pub summary: Option<String>,
+    /// The first version fixing the vulnerability, if any.
+    pub fixed: Option<String>,
+}
+
+/// The advisories matching a library of the binary.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LibraryVulnerabilities {
+    /// The name of the library.
+    pub library: String,
+    /// The version of the library the advisories were matched against.
+    pub version: String,
+    /// The matching advisories.
+    pub advisories: Vec<Advisory>,
+}
+
+// A version interval: from the introducing version (included) to the fixing one (excluded),
+// or to the last affected one (included).
+struct Range {
+    introduced: Option<String>,
+    fixed: Option<String>,
+    last_affected: Option<String>,
+}
+
+struct Entry {
+    package: String,
+    ranges: Vec<Range>,
+    versions: Vec<String>,
+    advisory: Advisory,
+}
+
+/// An offline database of advisories, loaded from a local snapshot in the OSV format.
+pub struct VulnDb {
+    entries: Vec<Entry>,
+}
+
+impl VulnDb {
+    /// Load a snapshot written as a JSON array of OSV entries, or as an object with a `vulns`
+    /// array, like the responses of the OSV query API.
+    pub fn load(path: &str) -> Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let json: Value = serde_json::from_str(&contents)?;
+        Self::from_json(&json)
+    }
+
+    /// Read the OSV entries of a JSON document, see [`VulnDb::load`].
+    pub fn from_json(json: &Value) -> Result<Self> {
+        let vulns = json
+            .as_array()
+            .or_else(|| json.get("vulns").and_then(Value::as_array))
+            .ok_or_else(|| Error::InvalidVulnDb("expected an array of OSV entries".to_string()))?;
+
+        let mut entries = Vec::new();
+        for vuln in vulns {
+            let id = vuln
+                .get("id")
+                .and_then(Value::as_str)
+                .ok_or_else(|| Error::InvalidVulnDb(format!("missing id in {}", vuln)))?;
+            let aliases = string_array(vuln.get("aliases"));
+            let summary = vuln
+                .get("summary")
+                .or_else(|| vuln.get("details"))
+                .and_then(Value::as_str)
+                .map(str::to_string);
+
+            for affected in vuln
+                .get("affected")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+            {
+                let Some(package) = affected.pointer("/package/name").and_then(Value::as_str)
+                else {
+                    continue;
+                };
+                let ranges: Vec<Range> = affected
+                    .get("ranges")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                    // Ranges of commits cannot be compared with release versions.
+                    .filter(|range| range.get("type").and_then(Value::as_str) != Some("GIT"))
+                    .flat_map(|range| parse_events(range.get("events")))
+                    .collect();
+                let versions = string_array(affected.get("versions"));
+                let fixed = ranges.iter().find_map(|range| range.fixed.clone());
+                entries.push(Entry {
+                    package: package.to_lowercase(),
+                    ranges,
+                    versions,
+                    advisory: Advisory {
+                        id: id.to_string(),
+                        aliases: aliases.clone(),
+                        summary: summary.clone(),
+                        fixed,
+                    },
+                });
+            }
+        }
+        Ok(Self { entries })
+    }
+
+    /// Return the advisories affecting a library.
+    ///
+    /// Only libraries whose exact version is known, from a version string, are matched: the
+    /// versions deduced from the symbol versions are minimum requirements, not the versions
+    /// the binary will run with.
+    pub fn advisories(&self, library: &Library) -> Vec<Advisory> {
+        let Some(version) = library
+            .version
+            .as_deref()
+            .filter(|_| library.version_source == Some(VersionSource::VersionString))
+        else {
+            return Vec::new();
+        };
+        let name = library.name.to_lowercase();
+        let mut advisories: Vec<Advisory> = Vec::new();
+        for entry in &self.entries {
+            if entry.package != name || advisories.iter().any(|adv| adv.id == entry.advisory.id) {
+                continue;
+            }
+            let affected = entry.versions.iter().any(|v| v == version)
+                || entry.ranges.iter().any(|range| range.contains(version));
+            if affected {
+                advisories.push(entry.advisory.clone());
+            }
+        }
+        advisories
+    }
+
+    /// Match the libraries of the binary against the database.
+    ///
+    /// # Returns
+    ///
+    /// Returns the libraries affected by at least one advisory.
+    pub fn scan(&self, libraries: &[Library]) -> Vec<LibraryVulnerabilities> {
+        libraries
+            .iter()
+            .filter_map(|library| {
+                let advisories = self.advisories(library);
+                (!advisories.is_empty()).then(|| LibraryVulnerabilities {
+                    library: library.name.clone(),
+                    version: library.version.clone().unwrap_or_default(),
+                    advisories,
+                })
+            })
+            .collect()
+    }
+}
+
+impl Range {
+    fn contains(&self, version: &str) -> bool {
+        let after_start = self.introduced.as_deref().is_none_or(|introduced| {
+            introduced == "0" || compare_versions(version, introduced) != Ordering::Less
+        });
+        let before_end = match (&self.fixed, &self.last_affected) {
+            (Some(fixed), _) => compare_versions(version, fixed) == Ordering::Less,
+            (None, Some(last)) => compare_versions(version, last) != Ordering::Greater,
+            (None, None) => true,
+        };
+        after_start && before_end
+    }
+}
+
+// Read an array of strings, ignoring the other values.
+fn string_array(value: Option<&Value>) -> Vec<String> {
+    value
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|value| value.as_str().map(str::to_string))
+        .collect()
+}
+
+// Turn the events of an OSV range into intervals, each one opened by an `introduced` event.
+fn parse_events(events: Option<&Value>) -> Vec<Range> {
+    let mut ranges: Vec<Range> = Vec::new();
+    for event in events.and_then(Value::as_array).into_iter().flatten() {
+        let get = |key| event.get(key).and_then(Value::as_str).map(str::to_string);
+        if let Some(introduced) = get("introduced") {
+            ranges.push(Range {
+                introduced: Some(introduced),
+                fixed: None,
+                last_affected: None,
+            });
+        } else if let Some(range) = ranges.last_mut() {
+            range.fixed = range.fixed.take().or_else(|| get("fixed"));
+            range.last_affected = range.last_affected.take().or_else(|| get("last_affected"));
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn library(name: &str, version: &str, source: VersionSource) -> Library {
+        Library {
+            name: name.to_string(),
+            sonames: Vec::new(),
+            version: Some(version.to_string()),
+            version_source: Some(source),
+        }
+    }
+
+    #[test]
+    fn test_advisories() {
+        let db = VulnDb::from_json(&serde_json::json!({
+            "vulns": [
+                {
+                    "id": "CVE-2023-38545",
+                    "summary": "SOCKS5 heap buffer overflow",
+                    "affected": [{
+                        "package": {"name": "curl", "ecosystem": "OSS-Fuzz"},
+                        "ranges": [{"type": "SEMVER", "events": [
+                            {"introduced": "7.69.0"}, {"fixed": "8.4.0"}
+                        ]}]
+                    }]
+                },
+                {
+                    "id": "CVE-2024-2398",
+                    "affected": [{
+                        "package": {"name": "curl"},
+                        "ranges": [{"type": "SEMVER", "events": [
+                            {"introduced": "0"}, {"last_affected": "8.6.0"}
+                        ]}]
+                    }]
+                },
+                {
+                    "id": "CVE-2022-0778",
+                    "affected": [{"package": {"name": "openssl"}, "versions": ["3.0.1"]}]
+                }
+            ]
+        }))
+        .unwrap();
+
+        let old = db.advisories(&library("curl", "8.0.1", VersionSource::VersionString));
+        let ids: Vec<&str> = old.iter().map(|adv| adv.id.as_str()).collect();
+        assert_eq!(ids, ["CVE-2023-38545", "CVE-2024-2398"]);
+        assert_eq!(old[0].fixed.as_deref(), Some("8.4.0"));
+
+        let recent = db.advisories(&library("curl", "8.5.0", VersionSource::VersionString));
+        assert_eq!(recent.len(), 1);
+        assert!(db
+            .advisories(&library("curl", "8.7.1", VersionSource::VersionString))
+            .is_empty());
+        assert_eq!(
+            db.advisories(&library("openssl", "3.0.1", VersionSource::VersionString))
+                .len(),
+            1
+        );
+        // Minimum versions are not matched.
+        assert!(db
+            .advisories(&library("openssl", "3.0.1", VersionSource::SymbolVersions))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_invalid_db() {
+        assert!(VulnDb::from_json(&serde_json::json!({"id": "CVE-1"})).is_err());
+        assert!(VulnDb::from_json(&serde_json::json!([{"summary": "no id"}])).is_err());
+    }
+}
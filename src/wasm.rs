@@ -0,0 +1,94 @@
+//! JavaScript bindings of the in-memory analysis, built with the `wasm` feature.
+//!
+//! ```js
+//! import init, { analyze } from "./pkg/manifest_producer.js";
+//!
+//! await init();
+//! const bytes = new Uint8Array(await file.arrayBuffer());
+//! const manifests = JSON.parse(analyze(file.name, bytes, '{"apis": ["writeOnDrive"]}'));
+//! ```
+
+use std::path::Path;
+
+use serde_json::{json, Value};
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    analyzer::{Analyzer, Options},
+    config::Config,
+    error::{Error, Result},
+    progress::Verbosity,
+};
+
+/// Return the version of the library.
+#[wasm_bindgen]
+pub fn version() -> String {
+    env!("CARGO_PKG_VERSION").to_string()
+}
+
+/// Analyze an ELF binary held in memory and return its manifests.
+///
+/// `options` is a JSON document with the keys of the configuration file, `apis` at least, e.g.
+/// `{"apis": ["writeOnDrive"], "max_depth": 8}`. The result is a JSON document holding the
+/// `basic_info`, `flow_call` and `feature_manifest` manifests by name.
+///
+/// # Errors
+///
+/// Throws the description of the error as a JSON string,
+/// `{"error": ..., "message": ..., "causes": [...], "exit_code": ...}`.
+#[wasm_bindgen]
+pub fn analyze(name: &str, data: &[u8], options: &str) -> std::result::Result<String, JsError> {
+    analyze_bytes(name, data, options)
+        .map(|manifests| manifests.to_string())
+        .map_err(|error| JsError::new(&error.to_json().to_string()))
+}
+
+// Analyze a binary in memory with the options of a JSON configuration.
+fn analyze_bytes(name: &str, data: &[u8], options: &str) -> Result<Value> {
+    let config = Config::from_json(&serde_json::from_str(options)?, Path::new(""))?;
+    let Some(specs) = config.apis.clone() else {
+        return Err(Error::InvalidApiList(
+            "the options give no apis".to_string(),
+        ));
+    };
+    let analyzer = Analyzer::from_bytes(name, data.to_vec())
+        .with_api_list(specs)
+        .with_options(Options {
+            verbosity: Verbosity::Quiet,
+            ..Options::from_config(&config)?
+        });
+    Ok(json!(analyzer.manifests(&analyzer.analyze()?)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_analyze_bytes() {
+        let data = fs::read("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let manifests = analyze_bytes(
+            "fake-firmware-c-dynamic",
+            &data,
+            r#"{"apis": ["writeOnDrive"]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            manifests["basic_info"]["file_name"],
+            "fake-firmware-c-dynamic"
+        );
+        assert_eq!(
+            manifests["flow_call"]["Public APIs flow"][0]["name"],
+            "writeOnDrive"
+        );
+        assert!(matches!(
+            analyze_bytes("fake-firmware-c-dynamic", &data, "{}"),
+            Err(Error::InvalidApiList(_))
+        ));
+        assert!(matches!(
+            analyze_bytes("empty", &[], r#"{"apis": ["main"]}"#),
+            Err(Error::InvalidElf { .. })
+        ));
+    }
+}
@@ -0,0 +1,160 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    batch::discover_elf_files,
+    error::{Error, Result},
+};
+
+// The modification time and size of each binary watched, by path.
+type Snapshot = BTreeMap<PathBuf, (Option<SystemTime>, u64)>;
+
+/// Watches binaries, or the directories of a build output, for changes, by polling their
+/// modification time and size.
+#[derive(Clone, Debug)]
+pub struct Watcher {
+    paths: Vec<String>,
+    exclude: Vec<String>,
+    interval: Duration,
+    snapshot: Snapshot,
+}
+
+impl Watcher {
+    /// Start watching the given paths.
+    ///
+    /// # Arguments
+    ///
+    /// * `paths` - The binaries, directories and glob patterns watched, as given to
+    ///   [`discover_elf_files`]; the ELF binaries added to a directory are watched too.
+    /// * `exclude` - The glob patterns of the paths left out.
+    /// * `interval` - How often the paths are polled.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a glob pattern is malformed.
+    pub fn new(paths: &[String], exclude: &[String], interval: Duration) -> Result<Self> {
+        let mut watcher = Self {
+            paths: paths.to_vec(),
+            exclude: exclude.to_vec(),
+            interval,
+            snapshot: Snapshot::new(),
+        };
+        watcher.snapshot = watcher.snapshot()?;
+        Ok(watcher)
+    }
+
+    /// Return the binaries changed or added since the last call, waiting for one to change and
+    /// then for the writes to end, so that a binary being linked is not read half-written.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the paths of the binaries changed, sorted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a glob pattern is malformed.
+    pub fn wait(&mut self) -> Result<Vec<PathBuf>> {
+        loop {
+            let changed = self.poll()?;
+            if !changed.is_empty() {
+                return Ok(changed);
+            }
+            thread::sleep(self.interval);
+        }
+    }
+
+    /// Return the binaries changed or added since the last call, without waiting for one to
+    /// change: empty while none did or while one is still being written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a glob pattern is malformed.
+    pub fn poll(&mut self) -> Result<Vec<PathBuf>> {
+        let mut current = self.snapshot()?;
+        if current == self.snapshot {
+            return Ok(Vec::new());
+        }
+        // The binaries are read once their size and modification time settle.
+        loop {
+            thread::sleep(self.interval);
+            let again = self.snapshot()?;
+            if again == current {
+                break;
+            }
+            current = again;
+        }
+        let changed = current
+            .iter()
+            .filter(|(path, state)| self.snapshot.get(*path) != Some(state))
+            .map(|(path, _)| path.clone())
+            .collect();
+        self.snapshot = current;
+        Ok(changed)
+    }
+
+    // The state of the binaries watched, a directory or a binary being removed while it is
+    // rebuilt counting as empty.
+    fn snapshot(&self) -> Result<Snapshot> {
+        let mut snapshot = Snapshot::new();
+        for path in &self.paths {
+            let binaries = match path.contains(['*', '?', '[']) || Path::new(path).is_dir() {
+                true => match discover_elf_files(std::slice::from_ref(path), &self.exclude) {
+                    Ok(inputs) => inputs.into_iter().map(|input| input.path).collect(),
+                    Err(error @ Error::InvalidPattern(_)) => return Err(error),
+                    Err(_) => Vec::new(),
+                },
+                false => vec![PathBuf::from(path)],
+            };
+            for binary in binaries {
+                if let Ok(metadata) = fs::metadata(&binary) {
+                    snapshot.insert(binary, (metadata.modified().ok(), metadata.len()));
+                }
+            }
+        }
+        Ok(snapshot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env::temp_dir;
+
+    #[test]
+    fn test_watcher() {
+        let dir = temp_dir().join("manifest-watch");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let elf = fs::read("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        fs::write(dir.join("httpd"), &elf).unwrap();
+        fs::write(dir.join("notes.txt"), "rebuilt").unwrap();
+
+        let paths = [dir.to_string_lossy().to_string()];
+        let mut watcher = Watcher::new(&paths, &[], Duration::from_millis(10)).unwrap();
+        assert!(watcher.poll().unwrap().is_empty());
+
+        // Another size: the modification time may not tick between two writes.
+        fs::write(dir.join("httpd"), [&elf[..], b"\0"].concat()).unwrap();
+        fs::write(dir.join("ntpd"), &elf).unwrap();
+        fs::write(dir.join("notes.txt"), "rebuilt again").unwrap();
+        assert_eq!(
+            watcher.wait().unwrap(),
+            [dir.join("httpd"), dir.join("ntpd")]
+        );
+        assert!(watcher.poll().unwrap().is_empty());
+
+        // A binary removed is not a change to analyze.
+        fs::remove_file(dir.join("ntpd")).unwrap();
+        assert!(watcher.poll().unwrap().is_empty());
+
+        let file = [dir.join("httpd").to_string_lossy().to_string()];
+        let mut watcher = Watcher::new(&file, &[], Duration::from_millis(10)).unwrap();
+        fs::write(dir.join("httpd"), &elf).unwrap();
+        assert_eq!(watcher.poll().unwrap(), [dir.join("httpd")]);
+    }
+}
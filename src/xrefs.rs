@@ -0,0 +1,262 @@
+use std::collections::HashMap;
+
+use goblin::elf::{
+    section_header::{SHF_ALLOC, SHF_EXECINSTR, SHT_NOBITS},
+    Elf,
+};
+use serde_json::{json, Value};
+
+use crate::call_graph::CallGraph;
+
+// Strings longer than this are truncated in the references.
+const MAX_STRING_LEN: usize = 256;
+
+/// A call to a function, seen from the called function.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CallerRef {
+    /// The starting address of the calling function.
+    pub caller: u64,
+    /// The address of the instruction performing the call.
+    pub site: u64,
+}
+
+/// A reference from the code of a function to a data address.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DataRef {
+    /// The address of the instruction referencing the data.
+    pub site: u64,
+    /// The referenced address.
+    pub addr: u64,
+    /// The name of the section containing the referenced address.
+    pub section: String,
+    /// The string found at the referenced address, when it holds a printable C string.
+    pub string: Option<String>,
+}
+
+/// The cross-references of the functions of a binary.
+///
+/// For every function, the database records who calls it, from the edges of the call graph,
+/// and which data addresses (strings, globals) its code references.
+pub struct XrefDb {
+    callers: HashMap<String, Vec<CallerRef>>,
+    data: HashMap<u64, Vec<DataRef>>,
+}
+
+impl XrefDb {
+    /// Build the cross-references of the functions of a call graph.
+    ///
+    /// The data references are read from the addresses the code loads, which the call graph
+    /// recorded while disassembling it, see [`CallGraph::loads`].
+    ///
+    /// # Arguments
+    ///
+    /// * `elf` - The ELF object representing the binary.
+    /// * `buffer` - The buffer containing the binary data of the ELF file.
+    /// * `graph` - The call graph of the binary.
+    ///
+    /// # Returns
+    ///
+    /// Returns the cross-reference database.
+    pub fn build(elf: &Elf, buffer: &[u8], graph: &CallGraph) -> Self {
+        let mut callers: HashMap<String, Vec<CallerRef>> = HashMap::new();
+        for func in graph.functions() {
+            for edge in graph.callees(func.start_addr) {
+                callers
+                    .entry(edge.name.clone())
+                    .or_default()
+                    .push(CallerRef {
+                        caller: func.start_addr,
+                        site: edge.site,
+                    });
+            }
+        }
+
+        let sections = data_sections(elf, buffer);
+        let mut data = HashMap::new();
+        for func in graph.functions() {
+            let refs: Vec<DataRef> = graph
+                .loads(func.start_addr)
+                .iter()
+                .filter_map(|load| {
+                    let section = sections.iter().find(|sec| sec.contains(load.addr))?;
+                    Some(DataRef {
+                        site: load.site,
+                        addr: load.addr,
+                        section: section.name.to_string(),
+                        string: section.string_at(load.addr),
+                    })
+                })
+                .collect();
+            if !refs.is_empty() {
+                data.insert(func.start_addr, refs);
+            }
+        }
+
+        Self { callers, data }
+    }
+
+    /// Return the calls to the function with the given symbol name.
+    pub fn callers_of(&self, name: &str) -> &[CallerRef] {
+        self.callers.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    /// Return the data references of the function starting at the given address, in program order.
+    pub fn data_refs(&self, addr: u64) -> &[DataRef] {
+        self.data.get(&addr).map_or(&[], Vec::as_slice)
+    }
+
+    /// Serialize the database, to be read back with [`XrefDb::from_cache`].
+    pub fn to_cache(&self) -> Value {
+        let mut callers: Vec<(&String, &Vec<CallerRef>)> = self.callers.iter().collect();
+        callers.sort_by_key(|(name, _)| name.as_str());
+        let callers: Vec<Value> = callers
+            .into_iter()
+            .map(|(name, refs)| {
+                let refs: Vec<Value> = refs
+                    .iter()
+                    .map(|caller| json!([caller.caller, caller.site]))
+                    .collect();
+                json!([name, refs])
+            })
+            .collect();
+        let mut data: Vec<(&u64, &Vec<DataRef>)> = self.data.iter().collect();
+        data.sort_by_key(|(addr, _)| **addr);
+        let data: Vec<Value> = data
+            .into_iter()
+            .map(|(addr, refs)| {
+                let refs: Vec<Value> = refs
+                    .iter()
+                    .map(|data| json!([data.site, data.addr, data.section, data.string]))
+                    .collect();
+                json!([addr, refs])
+            })
+            .collect();
+        json!({ "callers": callers, "data": data })
+    }
+
+    /// Read back a database serialized by [`XrefDb::to_cache`], `None` if it is malformed.
+    pub fn from_cache(value: &Value) -> Option<Self> {
+        let mut callers = HashMap::new();
+        for entry in value["callers"].as_array()? {
+            let refs = entry[1]
+                .as_array()?
+                .iter()
+                .map(|caller| {
+                    Some(CallerRef {
+                        caller: caller[0].as_u64()?,
+                        site: caller[1].as_u64()?,
+                    })
+                })
+                .collect::<Option<_>>()?;
+            callers.insert(entry[0].as_str()?.to_string(), refs);
+        }
+        let mut data = HashMap::new();
+        for entry in value["data"].as_array()? {
+            let refs = entry[1]
+                .as_array()?
+                .iter()
+                .map(|data| {
+                    Some(DataRef {
+                        site: data[0].as_u64()?,
+                        addr: data[1].as_u64()?,
+                        section: data[2].as_str()?.to_string(),
+                        string: data[3].as_str().map(str::to_string),
+                    })
+                })
+                .collect::<Option<_>>()?;
+            data.insert(entry[0].as_u64()?, refs);
+        }
+        Some(Self { callers, data })
+    }
+}
+
+// An allocated, non-executable section of the binary.
+struct DataSection<'a> {
+    name: &'a str,
+    addr: u64,
+    size: u64,
+    // The content of the section, empty for .bss-like sections.
+    data: &'a [u8],
+}
+
+impl DataSection<'_> {
+    fn contains(&self, addr: u64) -> bool {
+        self.addr <= addr && addr < self.addr + self.size
+    }
+
+    // Read a printable, NUL-terminated string starting at the given address.
+    fn string_at(&self, addr: u64) -> Option<String> {
+        let bytes = self.data.get((addr - self.addr) as usize..)?;
+        let len = bytes.iter().position(|&b| b == 0)?;
+        let bytes = &bytes[..len];
+        if bytes.is_empty()
+            || !bytes
+                .iter()
+                .all(|&b| b.is_ascii_graphic() || b.is_ascii_whitespace())
+        {
+            return None;
+        }
+        let mut string = String::from_utf8_lossy(bytes).into_owned();
+        string.truncate(MAX_STRING_LEN);
+        Some(string)
+    }
+}
+
+// Collect the sections the code may reference as data.
+fn data_sections<'a>(elf: &'a Elf, buffer: &'a [u8]) -> Vec<DataSection<'a>> {
+    elf.section_headers
+        .iter()
+        .filter(|sec| {
+            let flags = sec.sh_flags as u32;
+            flags & SHF_ALLOC != 0 && flags & SHF_EXECINSTR == 0 && sec.sh_addr != 0
+        })
+        .filter_map(|sec| {
+            let name = elf.shdr_strtab.get_at(sec.sh_name)?;
+            let data = if sec.sh_type == SHT_NOBITS {
+                &[]
+            } else {
+                let start = sec.sh_offset as usize;
+                buffer.get(start..start + sec.sh_size as usize)?
+            };
+            Some(DataSection {
+                name,
+                addr: sec.sh_addr,
+                size: sec.sh_size,
+                data,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf_utils::read_elf_file;
+
+    #[test]
+    fn test_xrefs_dynamic() {
+        let buffer = read_elf_file("./tests/elf_file/fake-firmware-c-dynamic").unwrap();
+        let elf = Elf::parse(&buffer).unwrap();
+        let graph = CallGraph::build(&elf, &buffer, false).unwrap();
+        let xrefs = XrefDb::build(&elf, &buffer, &graph);
+
+        let write_on_drive = graph
+            .functions()
+            .find(|func| func.name == "writeOnDrive")
+            .unwrap();
+        let callers = xrefs.callers_of("writeOnDrive");
+        assert!(!callers.is_empty());
+        assert!(callers
+            .iter()
+            .all(|caller| graph.function(caller.caller).is_some()));
+
+        let refs = xrefs.data_refs(write_on_drive.start_addr);
+        assert!(refs
+            .iter()
+            .any(|data| data.section == ".rodata" && data.string.is_some()));
+        assert!(xrefs
+            .callers_of("fopen64")
+            .iter()
+            .any(|caller| caller.caller == write_on_drive.start_addr));
+    }
+}
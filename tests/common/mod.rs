@@ -1,44 +1,103 @@
-use std::fs;
 use std::path::Path;
 
-use manifest_producer::api_detection::api_search;
-use manifest_producer::cleanup::syscall_flow;
-use manifest_producer::code_section_handler::code_section;
-use manifest_producer::dwarf_analysis::dwarf_analysis;
-use manifest_producer::elf_utils::{is_static, read_elf_file};
-use manifest_producer::error::Result;
-use manifest_producer::manifest_creation::{
-    basic_info_manifest, feature_manifest, flow_call_manifest,
-};
-
-pub fn elf_analysis(file_path: &str, api_list: Vec<&str>, path: &str) -> Result<()> {
-    let elf_data = read_elf_file(file_path)?;
-    let elf = goblin::elf::Elf::parse(&elf_data)?;
+use serde_json::Value;
 
-    let lang = match dwarf_analysis(file_path)?.strip_prefix("DW_LANG_") {
-        Some(stripped_lang) => stripped_lang.to_owned(),
-        None => "NOT_FOUND".to_string(),
-    };
-
-    let link = is_static(&elf);
+use manifest_producer::analyzer::Analyzer;
+use manifest_producer::api_detection::ApiSpec;
+use manifest_producer::error::Result;
+use manifest_producer::manifest_creation::read_manifest;
+use manifest_producer::progress::Verbosity;
+use manifest_producer::schema::{validate, ManifestKind};
 
-    let mut api_found = api_search(&elf, &api_list)?;
+// How many entries of each list the snapshots keep in the large sections, which list every
+// function, string and syscall found in the binaries.
+const SNAPSHOT_ENTRIES: usize = 8;
 
-    for api in &mut api_found {
-        let sys = code_section(&elf, api, &elf_data, link, lang.contains("Rust"))?;
-        syscall_flow(api, sys, &lang)?;
-    }
+// How many keys a map of a large section can have before the snapshots keep only its first
+// entries, the records having fewer fields than that and the maps keyed by function name more.
+const SNAPSHOT_KEYS: usize = 64;
 
-    basic_info_manifest(&elf, file_path, &api_found, lang, path)?;
-    flow_call_manifest(&api_found, path)?;
-    feature_manifest(&api_found, path)?;
+// The large sections of the basic information, flow call and feature manifests, cut in the
+// snapshots. The rest of these manifests, among which the APIs found and their syscalls, is
+// kept whole; the firmware manifests, merging whole binaries, are cut throughout.
+const LARGE_SECTIONS: &[&str] = &[
+    // basic_info
+    "dependencies",
+    "entropy",
+    "errors",
+    "function origins",
+    "go build info",
+    "hardening",
+    "layout",
+    "notes",
+    "packing",
+    "recognized functions",
+    "rtos",
+    "rust crates",
+    "section hashes",
+    // flow_call
+    "calls by library",
+    "constructors",
+    "correlation",
+    "dynamic loading",
+    "Interrupt flows",
+    "Loaded library flows",
+    "observed",
+    "recursive functions",
+    "Shutdown flows",
+    "signal handlers",
+    "Signal handler flows",
+    "stack usage",
+    "Startup flows",
+    "syscall arguments",
+    "Task flows",
+    "tasks",
+    "Thread flows",
+    "threads",
+    "transitive calls",
+    "unreachable functions",
+    "unresolved indirect calls",
+    "vector table",
+    // feature_manifest
+    "capabilities",
+    "complexity",
+    "crypto",
+    "dangerous calls",
+    "device subsystems",
+    "environment variables",
+    "file paths",
+    "fuzzy matches",
+    "ioctls",
+    "libraries",
+    "licenses",
+    "network endpoints",
+    "secrets",
+    "strings",
+    "vulnerability patterns",
+    // all of them
+    "diagnostics",
+    "plugins",
+];
 
-    Ok(())
+pub fn elf_analysis(file_path: &str, api_list: Vec<&str>, path: &str) -> Result<()> {
+    let analyzer = Analyzer::new(file_path)
+        .with_api_list(api_list.into_iter().map(ApiSpec::new).collect())
+        .with_timestamp(false)
+        .with_verbosity(Verbosity::Quiet);
+    let analysis = analyzer.analyze()?;
+    analyzer.write_manifests(&analysis, path)
 }
 
 pub fn compare_manifest(snapshot_path: &Path, path: &Path) {
-    let content = fs::read_to_string(path).unwrap();
     let name = path.file_name().and_then(|v| v.to_str());
+
+    // The manifests must follow the schema of the version they declare.
+    let kind: ManifestKind = path.file_stem().unwrap().to_str().unwrap().parse().unwrap();
+    let mut manifest = read_manifest(path.to_str().unwrap()).unwrap();
+    assert_eq!(validate(kind, &manifest).unwrap(), Vec::<String>::new());
+
+    shorten(&mut manifest, matches!(kind, ManifestKind::Firmware));
+    let content = serde_json::to_string_pretty(&manifest).unwrap();
     insta::with_settings!({
         snapshot_path => snapshot_path,
         prepend_module_to_snapshot => false,
@@ -46,3 +105,40 @@ pub fn compare_manifest(snapshot_path: &Path, path: &Path) {
         insta::assert_snapshot!(name, content);
     })
 }
+
+// Cut the lists of the large sections of a manifest, or of the whole of it when `cut` is set,
+// to their first entries, counting the ones left out, so that the snapshots stay small enough
+// to review.
+fn shorten(value: &mut Value, cut: bool) {
+    match value {
+        Value::Array(items) => {
+            let left_out = if cut {
+                items.len().saturating_sub(SNAPSHOT_ENTRIES)
+            } else {
+                0
+            };
+            items.truncate(items.len() - left_out);
+            items.iter_mut().for_each(|item| shorten(item, cut));
+            if left_out > 0 {
+                items.push(Value::from(format!("... and {} more", left_out)));
+            }
+        }
+        Value::Object(map) => {
+            let left_out = map.len().saturating_sub(SNAPSHOT_ENTRIES);
+            if cut && map.len() > SNAPSHOT_KEYS {
+                *map = std::mem::take(map)
+                    .into_iter()
+                    .take(SNAPSHOT_ENTRIES)
+                    .collect();
+                map.insert(
+                    "...".to_string(),
+                    Value::from(format!("and {} more", left_out)),
+                );
+            }
+            map.iter_mut().for_each(|(key, item)| {
+                shorten(item, cut || LARGE_SECTIONS.contains(&key.as_str()))
+            });
+        }
+        _ => {}
+    }
+}
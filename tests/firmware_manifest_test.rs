@@ -0,0 +1,80 @@
+mod common;
+
+use std::{env::temp_dir, fs::create_dir_all, path::Path};
+
+use common::{compare_manifest, elf_analysis};
+use manifest_producer::manifest_creation::{
+    collect_manifests, merge_manifests, read_manifest, Format,
+};
+
+const SNAPSHOT_PATH: &str = "../snapshots/firmware/";
+
+#[test]
+fn test_firmware() {
+    let api_list = vec![
+        "writeOnDrive",
+        "accessNetwork",
+        "accessWebcam",
+        "turnLampOn",
+        "turnLampOff",
+    ];
+
+    let tmp_dir = temp_dir();
+    let mut dirs = Vec::new();
+    for (name, file_path) in [
+        (
+            "firmware-c-dynamic",
+            "./tests/elf_file/fake-firmware-c-dynamic",
+        ),
+        (
+            "firmware-c-static",
+            "./tests/elf_file/minimal-fake-firmware-c-static",
+        ),
+    ] {
+        let folder = tmp_dir.join(name);
+        let path = folder.to_str().unwrap().to_string();
+        create_dir_all(&path).unwrap();
+        elf_analysis(file_path, api_list.clone(), &path).unwrap();
+        dirs.push(path);
+    }
+
+    let folder = tmp_dir.join("firmware");
+    let path = folder.to_str().unwrap();
+    create_dir_all(path).unwrap();
+    let dirs: Vec<&str> = dirs.iter().map(String::as_str).collect();
+    merge_manifests(&dirs, Format::Json, path).unwrap();
+
+    let firmware_path = format!("{}/firmware_manifest.json", path);
+    compare_manifest(Path::new(SNAPSHOT_PATH), Path::new(&firmware_path));
+}
+
+#[test]
+fn test_collect_manifests() {
+    let api_list = vec!["writeOnDrive", "accessWebcam"];
+
+    let folder = temp_dir().join("collected-c-static");
+    let path = folder.to_str().unwrap();
+    create_dir_all(path).unwrap();
+    elf_analysis(
+        "./tests/elf_file/minimal-fake-firmware-c-static",
+        api_list,
+        path,
+    )
+    .unwrap();
+
+    let manifests = collect_manifests(path).unwrap();
+    let names: Vec<&str> = manifests.keys().map(String::as_str).collect();
+    assert_eq!(
+        names,
+        [
+            "api_coverage",
+            "basic_info",
+            "feature_manifest",
+            "flow_call"
+        ]
+    );
+    assert_eq!(
+        manifests["basic_info"],
+        read_manifest(&format!("{}/basic_info.json", path)).unwrap()
+    );
+}
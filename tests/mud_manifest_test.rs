@@ -0,0 +1,43 @@
+use std::{env::temp_dir, fs, fs::create_dir_all};
+
+use manifest_producer::manifest_creation::mud_manifest;
+use manifest_producer::network::Endpoint;
+
+#[test]
+fn test_mud() {
+    let endpoint = |host: Option<&str>, port: Option<u16>, protocol: &str| Endpoint {
+        host: host.map(str::to_string),
+        port,
+        protocol: Some(protocol.to_string()),
+        source: "string",
+    };
+    let endpoints = [
+        endpoint(None, None, "udp"),
+        endpoint(Some("api.example.com"), Some(443), "https"),
+        endpoint(Some("10.0.0.7"), Some(5683), "coap"),
+        endpoint(Some("127.0.0.1"), Some(8080), "http"),
+    ];
+
+    let folder = temp_dir().join("mud");
+    let path = folder.to_str().unwrap();
+    create_dir_all(path).unwrap();
+
+    // A fixed last update time, for the snapshot.
+    std::env::set_var("SOURCE_DATE_EPOCH", "1700000000");
+    assert!(mud_manifest("fake-firmware", &endpoints, "http://example.com", path).is_err());
+    mud_manifest(
+        "./tests/elf_file/fake-firmware-c-dynamic",
+        &endpoints,
+        "https://example.com/fake-firmware.json",
+        path,
+    )
+    .unwrap();
+
+    let content = fs::read_to_string(format!("{}/mud.json", path)).unwrap();
+    insta::with_settings!({
+        snapshot_path => "snapshots/mud/",
+        prepend_module_to_snapshot => false,
+    }, {
+        insta::assert_snapshot!("mud.json", content);
+    })
+}